@@ -0,0 +1,235 @@
+//! Generates `chars_table.rs` (included by `src/chars.rs`) and
+//! `lexicon_table.rs` (included by `src/lexicon.rs`)
+//!
+//! Every precomposed Vietnamese vowel, derived from where Unicode puts each
+//! (base, mark) pair instead of hand-typed letters: Latin-1 and Latin
+//! Extended-A hold the plain bases and a few marked vowels, and the
+//! Vietnamese block (U+1EA0..U+1EF9) holds the rest as capital/small
+//! pairs, base by base. The build fails if a pair is missing or a code
+//! point is used twice.
+//!
+//! Each word list gets an offset table so a lookup goes straight to a
+//! word instead of scanning lines. The build fails if a list isn't one
+//! lowercase word per line, or a sorted list is out of byte order.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+// Marks, in table order
+const SAC: usize = 0;
+const HUYEN: usize = 1;
+const HOI: usize = 2;
+const NGA: usize = 3;
+const NANG: usize = 4;
+const ALL_MARKS: [usize; 5] = [SAC, HUYEN, HOI, NGA, NANG];
+
+/// Base vowels in table order: a, ă, â, e, ê, i, o, ô, ơ, u, ư, y, by the
+/// code point of the capital
+const BASES: [u32; 12] = [
+    0x41, 0x102, 0xC2, 0x45, 0xCA, 0x49, 0x4F, 0xD4, 0x1A0, 0x55, 0x1AF, 0x59,
+];
+
+/// Marked capitals outside the Vietnamese block: (base index, mark, code)
+const OUTSIDE: [(usize, usize, u32); 15] = [
+    (0, HUYEN, 0xC0),
+    (0, SAC, 0xC1),
+    (0, NGA, 0xC3),
+    (3, HUYEN, 0xC8),
+    (3, SAC, 0xC9),
+    (5, HUYEN, 0xCC),
+    (5, SAC, 0xCD),
+    (5, NGA, 0x128),
+    (6, HUYEN, 0xD2),
+    (6, SAC, 0xD3),
+    (6, NGA, 0xD5),
+    (9, HUYEN, 0xD9),
+    (9, SAC, 0xDA),
+    (9, NGA, 0x168),
+    (11, SAC, 0xDD),
+];
+
+/// Start of the Vietnamese block
+const BLOCK: u32 = 0x1EA0;
+
+/// The block's layout: marks of each base, in code point order
+const BLOCK_RUNS: [(usize, &[usize]); 12] = [
+    (0, &[NANG, HOI]),
+    (2, &ALL_MARKS),
+    (1, &ALL_MARKS),
+    (3, &[NANG, HOI, NGA]),
+    (4, &ALL_MARKS),
+    (5, &[HOI, NANG]),
+    (6, &[NANG, HOI]),
+    (7, &ALL_MARKS),
+    (8, &ALL_MARKS),
+    (9, &[NANG, HOI]),
+    (10, &ALL_MARKS),
+    (11, &[HUYEN, NANG, HOI, NGA]),
+];
+
+/// Small letter of a capital: +0x20 in ASCII and Latin-1, the next code
+/// point in Latin Extended-A and the Vietnamese block
+fn small(capital: u32) -> u32 {
+    if capital < 0x100 {
+        capital + 0x20
+    } else {
+        capital + 1
+    }
+}
+
+fn letter(code: u32) -> char {
+    char::from_u32(code).expect("code point in range")
+}
+
+/// One `(base, [marked...])` line of the generated table
+fn row(base: u32, marks: impl Iterator<Item = u32>) -> String {
+    let marks: Vec<String> = marks.map(|c| format!("{:?}", letter(c))).collect();
+    format!("    ({:?}, [{}]),\n", letter(base), marks.join(", "))
+}
+
+/// Start of each word in a list, then the length of the text: word `i` is
+/// `text[starts[i]..starts[i + 1] - 1]`
+fn word_starts(name: &str, text: &str) -> Vec<u32> {
+    assert!(text.ends_with('\n'), "{}: missing final newline", name);
+    let mut starts = vec![0];
+    let mut start = 0;
+    for word in text.lines() {
+        assert!(
+            !word.is_empty() && word.chars().all(char::is_lowercase),
+            "{}: not a lowercase word: {:?}",
+            name,
+            word
+        );
+        start += word.len() + 1;
+        starts.push(u32::try_from(start).expect("list under 4 GiB"));
+    }
+    starts
+}
+
+/// `const NAME: [type; len] = [...];` with a doc line
+fn array(doc: &str, name: &str, ty: &str, values: &[String]) -> String {
+    format!(
+        "/// {}\nconst {}: [{}; {}] = [{}];\n\n",
+        doc,
+        name,
+        ty,
+        values.len(),
+        values.join(", ")
+    )
+}
+
+fn lexicon_table() -> String {
+    let mut out = String::from("// Generated by build.rs; do not edit\n\n");
+
+    let english = read_list("english.txt");
+    let words: Vec<&str> = english.lines().collect();
+    for pair in words.windows(2) {
+        assert!(
+            pair[0] < pair[1],
+            "english.txt: not sorted/unique: {:?}",
+            pair
+        );
+    }
+    assert!(english.is_ascii(), "english.txt: not ASCII");
+    let starts = word_starts("english.txt", &english);
+    out.push_str(&array(
+        "Word offsets of `lexicon/english.txt`",
+        "ENGLISH_STARTS",
+        "u32",
+        &starts.iter().map(u32::to_string).collect::<Vec<_>>(),
+    ));
+
+    let vietnamese = read_list("vietnamese.txt");
+    let starts = word_starts("vietnamese.txt", &vietnamese);
+    let words: Vec<&str> = vietnamese.lines().collect();
+    let mut by_word: Vec<usize> = (0..words.len()).collect();
+    by_word.sort_unstable_by_key(|&rank| words[rank]);
+    for pair in by_word.windows(2) {
+        assert!(
+            words[pair[0]] != words[pair[1]],
+            "vietnamese.txt: {:?} listed twice",
+            words[pair[0]]
+        );
+    }
+    assert!(
+        words.len() <= usize::from(u16::MAX),
+        "vietnamese.txt: too long"
+    );
+    out.push_str(&array(
+        "Word offsets of `lexicon/vietnamese.txt`",
+        "VIETNAMESE_STARTS",
+        "u32",
+        &starts.iter().map(u32::to_string).collect::<Vec<_>>(),
+    ));
+    out.push_str(&array(
+        "Ranks of `lexicon/vietnamese.txt` in word (byte) order",
+        "VIETNAMESE_BY_WORD",
+        "u16",
+        &by_word.iter().map(usize::to_string).collect::<Vec<_>>(),
+    ));
+    out
+}
+
+fn read_list(file: &str) -> String {
+    let path = format!("lexicon/{}", file);
+    println!("cargo:rerun-if-changed={}", path);
+    std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("{}: {}", path, e))
+}
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+
+    let mut marked: HashMap<(usize, usize), u32> = HashMap::new();
+    let mut add = |base: usize, mark: usize, code: u32| {
+        assert!(
+            marked.insert((base, mark), code).is_none(),
+            "base {} mark {} defined twice",
+            base,
+            mark
+        );
+    };
+    for (base, mark, code) in OUTSIDE {
+        add(base, mark, code);
+    }
+    let mut code = BLOCK;
+    for (base, marks) in BLOCK_RUNS {
+        for &mark in marks {
+            add(base, mark, code);
+            code += 2;
+        }
+    }
+    assert_eq!(code, 0x1EFA, "Vietnamese block ends at U+1EF9");
+
+    let mut lower = String::new();
+    let mut upper = String::new();
+    for (b, &capital) in BASES.iter().enumerate() {
+        let forms: Vec<u32> = ALL_MARKS
+            .iter()
+            .map(|&m| *marked.get(&(b, m)).expect("every base takes every mark"))
+            .collect();
+        lower.push_str(&row(small(capital), forms.iter().map(|&c| small(c))));
+        upper.push_str(&row(capital, forms.iter().copied()));
+    }
+
+    let mut codes: Vec<u32> = marked.values().copied().chain(BASES).collect();
+    codes.extend(codes.clone().into_iter().map(small));
+    codes.sort_unstable();
+    codes.dedup();
+    assert_eq!(
+        codes.len(),
+        12 * 6 * 2,
+        "every letter has its own code point"
+    );
+
+    let table = format!(
+        "// Generated by build.rs; do not edit\n\n\
+         /// Small base vowels with their [sắc, huyền, hỏi, ngã, nặng] forms\n\
+         const VOWEL_TABLE: [(char, [char; 5]); 12] = [\n{}];\n\n\
+         /// `VOWEL_TABLE` in capitals\n\
+         const VOWEL_TABLE_UPPER: [(char, [char; 5]); 12] = [\n{}];\n",
+        lower, upper
+    );
+    let out_dir = PathBuf::from(std::env::var("OUT_DIR").unwrap());
+    std::fs::write(out_dir.join("chars_table.rs"), table).unwrap();
+    std::fs::write(out_dir.join("lexicon_table.rs"), lexicon_table()).unwrap();
+}
@@ -0,0 +1,77 @@
+//! Letter Bigram Model
+//!
+//! How much more English than Vietnamese (Telex keystrokes) each pair of
+//! letters is: `log2(P_english(b | a) / P_vietnamese(b | a))` in tenths of a
+//! bit, with `a`/`b` being `a`..`z` or the word boundary. 729 bytes.
+//!
+//! Generated by `scripts/gen-bigram-model.py` from 942 English words and
+//! 1027 Vietnamese ones; do not edit by hand.
+
+/// Row and column of the word boundary
+pub const EDGE: usize = 26;
+
+/// `SCORES[a][b]`, letters `a`..`z` as 0..=25
+#[rustfmt::skip]
+pub const SCORES: [[i8; 27]; 27] = [
+    [-69, 43, 18, 40, 4, -33, 55, 4, -3, -34, 39, 58, 7, -2, -51, 23, 4, 18, -2, 25, -16, 45, -28, -29, -14, 20, -19],
+    [-17, -4, -4, -4, 19, -4, -4, -4, -13, 12, -4, 42, -4, -4, 1, -4, -4, 31, -4, -19, 10, -4, -4, -4, 12, -4, 12],
+    [-6, -3, 34, -3, 48, -3, -3, -15, 48, -48, 22, 36, -3, -3, 6, -3, -3, 34, -39, 55, -10, -3, -3, -3, 13, -3, -20],
+    [-20, 2, -14, -42, 12, 2, 30, 2, 7, 2, 2, 2, 18, 2, -16, 2, 2, 41, 2, 2, -10, 2, 2, 2, 39, 2, 73],
+    [40, 9, 24, 33, -35, -14, 25, 9, 23, -62, 9, 30, -9, -3, -28, -9, 2, 16, 0, 2, -62, 41, 21, -5, 21, -14, 21],
+    [53, 8, 8, 8, 60, 56, 8, 8, 49, 8, 8, 36, -23, -48, 60, 8, 8, 40, 8, 36, 45, 8, 8, 8, 24, 8, -34],
+    [-4, 6, 6, 6, 64, -42, 22, 11, -13, -31, 6, 35, 6, 43, -3, 6, 6, 14, -47, 6, -9, 6, -22, -25, 35, 6, -4],
+    [-8, 8, 8, 8, 11, -36, 8, 8, 1, -26, 8, 8, -8, 24, -5, 8, 8, 43, -33, 52, -19, 8, -8, 8, 31, 8, 11],
+    [-8, 24, 34, 30, -21, -27, 47, -4, -4, -62, 19, 28, 12, 12, 19, 24, -4, 0, -5, 35, -36, 41, -27, -32, -4, 31, -66],
+    [29, 29, -12, 29, 52, 29, 29, 29, 29, 29, 29, 29, 13, -14, 60, -3, 29, 29, 29, -19, 44, 29, 29, 29, 29, 29, -48],
+    [-2, -2, -2, -2, 13, -2, -2, -56, -2, -2, -2, 14, -2, 22, -2, -2, -2, -2, -17, -2, -2, -2, -2, -2, -30, -2, 27],
+    [-20, -20, -20, 27, 17, 17, -20, -20, 5, -20, 3, 39, 3, -20, -11, -4, -20, -4, 3, 17, -31, 3, -4, -20, 9, -20, 35],
+    [0, 26, -2, -2, 21, -30, -2, -2, -1, -25, -2, -2, 14, -2, 2, 44, -2, -18, -13, -2, -17, -2, -2, -2, 21, -2, -14],
+    [-2, 2, 54, 64, 34, -24, -20, -72, 28, -26, 18, 26, 26, 18, 4, 2, 2, -35, 4, 70, -11, 30, 2, -14, 37, 2, 6],
+    [-17, 33, 1, 34, -7, -11, 35, 1, -24, -27, -8, 54, 24, 9, -24, 34, 1, 28, -12, 30, 49, 48, -20, -15, 29, 1, -17],
+    [40, -16, -16, 0, 42, -16, -16, -47, 21, -53, -16, 33, -23, -16, 17, 21, -16, 42, -23, 8, 25, -16, -16, -16, 0, -16, -12],
+    [9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, -10, 9, 9, 9, 9, 9, 9],
+    [-11, -14, 23, 32, 24, 14, 20, 2, 8, -14, 20, 17, 11, 0, 0, 2, -14, 20, 27, 26, -20, 17, 2, -14, 38, -14, -12],
+    [-14, 15, -5, -1, 32, 15, -1, 49, 28, -1, 34, -16, -12, -57, 23, 25, -1, -1, 32, 17, 14, -1, 15, -1, 23, -1, -26],
+    [-3, -10, 18, -10, 36, -10, -10, -9, 7, -53, -10, 21, 13, 5, -5, -10, -10, -15, -38, 32, 4, -10, -3, -10, 13, -10, 9],
+    [-15, 33, 21, 44, 9, -20, 50, 5, -1, -18, 5, 56, 37, 14, -46, 24, 5, 20, 4, 42, -18, 5, -70, -32, -4, 5, -35],
+    [-26, -4, -4, -4, 34, -4, -4, -4, -6, -4, -4, -4, -4, -4, -11, -4, -4, -4, -4, -4, -36, -4, -4, -4, 12, -4, -4],
+    [7, 13, -32, 13, 58, -37, 13, 61, -5, -32, 13, 29, -33, -16, -3, -10, 13, -4, -19, 1, -10, 13, 13, -32, 29, 13, 29],
+    [-5, 18, 18, 18, 3, 18, 18, 18, 6, 18, 18, 18, 18, -10, -5, 53, 18, 18, 18, 34, -10, 18, 18, 18, 18, 18, -21],
+    [9, 17, -7, -7, -7, -54, -7, -7, -7, -30, -7, 9, -7, -7, 17, 9, -7, -35, -16, 17, -7, -7, -7, -38, -7, -7, 12],
+    [11, -5, -5, -5, 26, -5, -5, -5, 11, -5, -5, -5, -5, -5, -5, -5, -5, -5, -5, -5, -5, -5, -5, -5, -5, -5, -5],
+    [16, 0, -7, -12, 43, 53, -8, -5, 60, 29, -18, -2, -2, -22, 15, 17, -21, 12, 17, -9, 9, -23, 68, -54, 5, 1, 1],
+];
+
+/// Average score per letter pair of a lowercase ASCII word, boundaries
+/// included (tenths of a bit; > 0 leans English). `None` for other input.
+pub fn score(word: &[u8]) -> Option<i32> {
+    let mut prev = EDGE;
+    let mut sum = 0i32;
+    for &c in word {
+        if !c.is_ascii_lowercase() {
+            return None;
+        }
+        let cur = (c - b'a') as usize;
+        sum += SCORES[prev][cur] as i32;
+        prev = cur;
+    }
+    sum += SCORES[prev][EDGE] as i32;
+    Some(sum / (word.len() as i32 + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_score() {
+        for word in ["through", "window", "question", "strength"] {
+            assert!(score(word.as_bytes()).unwrap() > 0, "{}", word);
+        }
+        for word in ["nguwowif", "truwowngf", "vieetj", "nhuwngx"] {
+            assert!(score(word.as_bytes()).unwrap() < 0, "{}", word);
+        }
+        assert_eq!(score(b"Ok"), None);
+        assert_eq!(score(b""), Some(SCORES[EDGE][EDGE] as i32));
+    }
+}
@@ -0,0 +1,442 @@
+//! Vietnamese Unicode Character System
+//!
+//! Provides character conversion between base vowels + modifiers + marks
+//! and composed Vietnamese Unicode characters.
+//!
+//! ## Design Principles
+//! - One lookup table per case for all vowel combinations (12 bases × 6
+//!   marks = 72), generated at build time (`build.rs`) from where Unicode
+//!   puts each letter rather than typed in by hand
+//! - No hardcoded case-by-case matching
+//!
+//! ## Character Components
+//! - Base vowel: a, ă, â, e, ê, i, o, ô, ơ, u, ư, y
+//! - Mark (dấu thanh): none, sắc, huyền, hỏi, ngã, nặng
+//! - Case: lowercase, uppercase
+
+use super::keys;
+use alloc::borrow::Cow;
+use alloc::string::String;
+
+/// Tone modifiers (dấu phụ) - changes base vowel form
+pub mod tone {
+    pub const NONE: u8 = 0;
+    pub const CIRCUMFLEX: u8 = 1; // ^ (mũ): a→â, e→ê, o→ô
+    pub const HORN: u8 = 2; // ơ, ư or breve ă
+}
+
+/// Marks (dấu thanh) - Vietnamese tone marks
+pub mod mark {
+    pub const NONE: u8 = 0;
+    pub const SAC: u8 = 1; // sắc (á)
+    pub const HUYEN: u8 = 2; // huyền (à)
+    pub const HOI: u8 = 3; // hỏi (ả)
+    pub const NGA: u8 = 4; // ngã (ã)
+    pub const NANG: u8 = 5; // nặng (ạ)
+}
+
+// `VOWEL_TABLE` and `VOWEL_TABLE_UPPER`, generated from Unicode's layout
+// by build.rs
+include!(concat!(env!("OUT_DIR"), "/chars_table.rs"));
+
+/// Get base character from key + tone modifier
+///
+/// # Arguments
+/// * `key` - Virtual keycode (a, e, i, o, u, y)
+/// * `tone` - Tone modifier: 0=none, 1=circumflex(^), 2=horn/breve
+///
+/// # Returns
+/// Base vowel character: a, ă, â, e, ê, i, o, ô, ơ, u, ư, y
+fn get_base_char(key: u16, t: u8) -> Option<char> {
+    match key {
+        keys::A => Some(match t {
+            tone::CIRCUMFLEX => 'â',
+            tone::HORN => 'ă', // breve for 'a'
+            _ => 'a',
+        }),
+        keys::E => Some(match t {
+            tone::CIRCUMFLEX => 'ê',
+            _ => 'e',
+        }),
+        keys::I => Some('i'),
+        keys::O => Some(match t {
+            tone::CIRCUMFLEX => 'ô',
+            tone::HORN => 'ơ',
+            _ => 'o',
+        }),
+        keys::U => Some(match t {
+            tone::HORN => 'ư',
+            _ => 'u',
+        }),
+        keys::Y => Some('y'),
+        _ => None,
+    }
+}
+
+/// Apply mark to base vowel character
+///
+/// Uses lookup table to find the marked variant.
+///
+/// # Arguments
+/// * `base` - Base vowel character (a, ă, â, e, ê, i, o, ô, ơ, u, ư, y)
+/// * `mark` - Mark: 0=none, 1=sắc, 2=huyền, 3=hỏi, 4=ngã, 5=nặng
+/// * `caps` - Capital letter wanted
+fn apply_mark(base: char, m: u8, caps: bool) -> char {
+    let table = if caps {
+        &VOWEL_TABLE_UPPER
+    } else {
+        &VOWEL_TABLE
+    };
+    let Some(i) = VOWEL_TABLE.iter().position(|(b, _)| *b == base) else {
+        return base;
+    };
+    let (plain, marks) = table[i];
+    match m {
+        mark::SAC..=mark::NANG => marks[(m - 1) as usize],
+        _ => plain,
+    }
+}
+
+/// Convert key + modifiers to Vietnamese character
+///
+/// # Arguments
+/// * `key` - Virtual keycode
+/// * `caps` - Uppercase flag
+/// * `tone` - Tone modifier: 0=none, 1=circumflex(^), 2=horn/breve
+/// * `mark` - Mark: 0=none, 1=sắc, 2=huyền, 3=hỏi, 4=ngã, 5=nặng
+pub fn to_char(key: u16, caps: bool, tone: u8, mark: u8) -> Option<char> {
+    // Handle D specially (not a vowel but needs conversion)
+    if key == keys::D {
+        return Some(if caps { 'D' } else { 'd' });
+    }
+
+    let base = get_base_char(key, tone)?;
+    Some(apply_mark(base, mark, caps))
+}
+
+/// Get đ/Đ character
+pub fn get_d(caps: bool) -> char {
+    if caps {
+        'Đ'
+    } else {
+        'đ'
+    }
+}
+
+/// Letter decomposed into key + modifiers (inverse of `to_char`)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Parsed {
+    pub key: u16,
+    pub caps: bool,
+    pub tone: u8,
+    pub mark: u8,
+    pub stroke: bool,
+}
+
+/// Get key + tone from base vowel character (inverse of `get_base_char`)
+fn parse_base_char(base: char) -> Option<(u16, u8)> {
+    Some(match base {
+        'a' => (keys::A, tone::NONE),
+        'ă' => (keys::A, tone::HORN),
+        'â' => (keys::A, tone::CIRCUMFLEX),
+        'e' => (keys::E, tone::NONE),
+        'ê' => (keys::E, tone::CIRCUMFLEX),
+        'i' => (keys::I, tone::NONE),
+        'o' => (keys::O, tone::NONE),
+        'ô' => (keys::O, tone::CIRCUMFLEX),
+        'ơ' => (keys::O, tone::HORN),
+        'u' => (keys::U, tone::NONE),
+        'ư' => (keys::U, tone::HORN),
+        'y' => (keys::Y, tone::NONE),
+        _ => return None,
+    })
+}
+
+/// Decompose a (precomposed) Vietnamese or ASCII letter
+///
+/// Used for text input from the OS layout (dead keys, another IME),
+/// where the engine receives characters instead of keystrokes.
+///
+/// # Returns
+/// `None` for anything that is not a letter of the Vietnamese alphabet
+/// (plus f, j, w, z).
+pub fn parse_char(ch: char) -> Option<Parsed> {
+    let caps = ch.is_uppercase();
+    let lower = ch.to_lowercase().next().unwrap_or(ch);
+
+    if lower == 'đ' {
+        return Some(Parsed {
+            key: keys::D,
+            caps,
+            tone: tone::NONE,
+            mark: mark::NONE,
+            stroke: true,
+        });
+    }
+
+    // Base vowel or marked vowel from the lookup table
+    let (base, m) = VOWEL_TABLE
+        .iter()
+        .find_map(|(b, marks)| {
+            if *b == lower {
+                Some((*b, mark::NONE))
+            } else {
+                marks
+                    .iter()
+                    .position(|&c| c == lower)
+                    .map(|i| (*b, i as u8 + 1))
+            }
+        })
+        .unwrap_or((lower, mark::NONE));
+
+    if let Some((key, t)) = parse_base_char(base) {
+        return Some(Parsed {
+            key,
+            caps,
+            tone: t,
+            mark: m,
+            stroke: false,
+        });
+    }
+
+    if !lower.is_ascii_lowercase() {
+        return None;
+    }
+    keys::from_char(lower).map(|key| Parsed {
+        key,
+        caps,
+        tone: tone::NONE,
+        mark: mark::NONE,
+        stroke: false,
+    })
+}
+
+// =============================================================================
+// Combining marks
+// =============================================================================
+
+const HORN: char = '\u{031B}';
+const BREVE: char = '\u{0306}';
+const CIRCUMFLEX: char = '\u{0302}';
+const DOT_BELOW: char = '\u{0323}';
+/// Combining marks by `mark` value - 1
+const MARKS: [char; 5] = ['\u{0301}', '\u{0300}', '\u{0309}', '\u{0303}', DOT_BELOW];
+
+/// Combining diacritical mark (U+0300..U+036F)
+pub fn is_combining(c: char) -> bool {
+    ('\u{0300}'..='\u{036F}').contains(&c)
+}
+
+/// Fold combining marks into the letter before them (NFC for Vietnamese)
+///
+/// Text from pastes, macOS file names or another IME may be decomposed
+/// (e + U+0302 + U+0323); everything that reads words composes it first.
+/// Borrows `text` when it has no combining marks.
+pub fn compose(text: &str) -> Cow<'_, str> {
+    if !text.chars().any(is_combining) {
+        return Cow::Borrowed(text);
+    }
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        let composed = out.chars().next_back().and_then(|prev| {
+            let p = parse_char(prev).filter(|p| !p.stroke)?;
+            let (t, m) = match c {
+                CIRCUMFLEX => (tone::CIRCUMFLEX, p.mark),
+                HORN | BREVE => (tone::HORN, p.mark),
+                _ => (p.tone, MARKS.iter().position(|&m| m == c)? as u8 + 1),
+            };
+            // Breve only on a, horn only on o and u
+            let fits = match c {
+                BREVE => p.key == keys::A,
+                HORN => p.key != keys::A,
+                _ => true,
+            };
+            let new = to_char(p.key, p.caps, t, m).filter(|_| fits)?;
+            (new != prev).then_some((prev, new))
+        });
+        match composed {
+            Some((prev, new)) => {
+                out.truncate(out.len() - prev.len_utf8());
+                out.push(new);
+            }
+            None => out.push(c),
+        }
+    }
+    Cow::Owned(out)
+}
+
+/// Letter, then combining marks in canonical order (NFD for Vietnamese)
+pub fn decompose(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() * 2);
+    for c in text.chars() {
+        let p = match parse_char(c) {
+            Some(p) if !c.is_ascii() && !p.stroke => p,
+            _ => {
+                out.push(c);
+                continue;
+            }
+        };
+        let base = to_char(p.key, p.caps, tone::NONE, mark::NONE).unwrap_or(c);
+        out.push(base);
+        // Horn (class 216), dot below (220), then the marks above (230)
+        let above = match p.tone {
+            tone::CIRCUMFLEX => Some(CIRCUMFLEX),
+            tone::HORN if p.key == keys::A => Some(BREVE),
+            tone::HORN => {
+                out.push(HORN);
+                None
+            }
+            _ => None,
+        };
+        if p.mark == mark::NANG {
+            out.push(DOT_BELOW);
+        }
+        out.extend(above);
+        if p.mark != mark::NONE && p.mark != mark::NANG {
+            out.push(MARKS[p.mark as usize - 1]);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_basic_vowels() {
+        // Basic vowels without modifiers
+        assert_eq!(to_char(keys::A, false, 0, 0), Some('a'));
+        assert_eq!(to_char(keys::E, false, 0, 0), Some('e'));
+        assert_eq!(to_char(keys::I, false, 0, 0), Some('i'));
+        assert_eq!(to_char(keys::O, false, 0, 0), Some('o'));
+        assert_eq!(to_char(keys::U, false, 0, 0), Some('u'));
+        assert_eq!(to_char(keys::Y, false, 0, 0), Some('y'));
+    }
+
+    #[test]
+    fn test_tone_modifiers() {
+        // Circumflex (^)
+        assert_eq!(to_char(keys::A, false, 1, 0), Some('â'));
+        assert_eq!(to_char(keys::E, false, 1, 0), Some('ê'));
+        assert_eq!(to_char(keys::O, false, 1, 0), Some('ô'));
+
+        // Horn/Breve
+        assert_eq!(to_char(keys::A, false, 2, 0), Some('ă'));
+        assert_eq!(to_char(keys::O, false, 2, 0), Some('ơ'));
+        assert_eq!(to_char(keys::U, false, 2, 0), Some('ư'));
+    }
+
+    #[test]
+    fn test_marks() {
+        // All 5 marks on 'a'
+        assert_eq!(to_char(keys::A, false, 0, 1), Some('á')); // sắc
+        assert_eq!(to_char(keys::A, false, 0, 2), Some('à')); // huyền
+        assert_eq!(to_char(keys::A, false, 0, 3), Some('ả')); // hỏi
+        assert_eq!(to_char(keys::A, false, 0, 4), Some('ã')); // ngã
+        assert_eq!(to_char(keys::A, false, 0, 5), Some('ạ')); // nặng
+    }
+
+    #[test]
+    fn test_combined_tone_and_mark() {
+        // â + sắc = ấ
+        assert_eq!(to_char(keys::A, false, 1, 1), Some('ấ'));
+        // ơ + huyền = ờ
+        assert_eq!(to_char(keys::O, false, 2, 2), Some('ờ'));
+        // ư + nặng = ự
+        assert_eq!(to_char(keys::U, false, 2, 5), Some('ự'));
+    }
+
+    #[test]
+    fn test_uppercase() {
+        assert_eq!(to_char(keys::A, true, 0, 0), Some('A'));
+        assert_eq!(to_char(keys::A, true, 0, 1), Some('Á'));
+        assert_eq!(to_char(keys::A, true, 1, 1), Some('Ấ'));
+        assert_eq!(to_char(keys::O, true, 2, 2), Some('Ờ'));
+        assert_eq!(to_char(keys::U, true, 2, 5), Some('Ự'));
+    }
+
+    #[test]
+    fn test_d() {
+        assert_eq!(get_d(false), 'đ');
+        assert_eq!(get_d(true), 'Đ');
+    }
+
+    #[test]
+    fn test_parse_char_roundtrip() {
+        for key in [keys::A, keys::E, keys::I, keys::O, keys::U, keys::Y] {
+            for t in 0..=2 {
+                for m in 0..=5 {
+                    for caps in [false, true] {
+                        let c = to_char(key, caps, t, m).unwrap();
+                        let p = parse_char(c).unwrap();
+                        assert_eq!((p.key, p.caps), (key, caps), "{}", c);
+                        // Tones that don't exist for the vowel (e.g. horn on i) collapse
+                        assert_eq!(to_char(p.key, p.caps, p.tone, p.mark), Some(c));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_table_bijective() {
+        // Every (base, tone, mark, case) has its own letter, and parses back
+        let mut letters = std::collections::HashSet::new();
+        for key in [keys::A, keys::E, keys::I, keys::O, keys::U, keys::Y] {
+            for t in 0..=2 {
+                if get_base_char(key, t) == get_base_char(key, tone::NONE) && t != tone::NONE {
+                    continue;
+                }
+                for m in 0..=5 {
+                    for caps in [false, true] {
+                        let c = to_char(key, caps, t, m).unwrap();
+                        assert!(letters.insert(c), "{} twice", c);
+                        let p = parse_char(c).unwrap();
+                        assert_eq!((p.key, p.caps, p.tone, p.mark), (key, caps, t, m), "{}", c);
+                        assert_eq!(c.is_uppercase(), caps, "{}", c);
+                    }
+                }
+            }
+        }
+        letters.insert(get_d(false));
+        letters.insert(get_d(true));
+        // 12 vowels × 6 marks + đ, in both cases; all but the 12 plain
+        // vowels are outside ASCII
+        assert_eq!(letters.len(), 146);
+        assert_eq!(letters.iter().filter(|c| !c.is_ascii()).count(), 134);
+    }
+
+    #[test]
+    fn test_compose() {
+        assert!(matches!(compose("Việt Nam"), Cow::Borrowed("Việt Nam")));
+        for word in ["Việt", "người", "Đặng", "khuỷu", "ĂN", "quyển"] {
+            let nfd = decompose(word);
+            assert_ne!(nfd, word);
+            assert_eq!(compose(&nfd), word);
+        }
+        // Marks in either order; a stray mark stays
+        assert_eq!(compose("e\u{0302}\u{0323}"), "ệ");
+        assert_eq!(compose("\u{0301}a"), "\u{0301}a");
+        assert_eq!(compose("i\u{031B}"), "i\u{031B}");
+    }
+
+    #[test]
+    fn test_parse_char_special() {
+        let p = parse_char('Đ').unwrap();
+        assert!(p.stroke && p.caps && p.key == keys::D);
+
+        let p = parse_char('ệ').unwrap();
+        assert_eq!(
+            (p.key, p.tone, p.mark),
+            (keys::E, tone::CIRCUMFLEX, mark::NANG)
+        );
+
+        let p = parse_char('w').unwrap();
+        assert_eq!((p.key, p.tone, p.mark), (keys::W, tone::NONE, mark::NONE));
+
+        assert_eq!(parse_char('1'), None);
+        assert_eq!(parse_char('ñ'), None);
+        assert_eq!(parse_char('@'), None);
+    }
+}
@@ -0,0 +1,118 @@
+//! Confusable Characters
+//!
+//! Characters that look like ASCII but aren't, as pasted text sometimes
+//! has them: a Cyrillic "а" in "nàm" keeps a correction from matching and
+//! gives another search key.
+//!
+//! - `fullwidth`: the fullwidth forms of East Asian text (U+FF01..U+FF5E,
+//!   U+3000) are ASCII by arithmetic
+//! - `lookalike`: Cyrillic and Greek letters drawn like a Latin one, from a
+//!   short table (those in the Unicode confusables list that fonts render
+//!   the same)
+//!
+//! Lookalikes are only errors among Latin letters: "сом" is a Russian word.
+//! Deciding that is up to the caller (`gonhanh_core::tokenizer`).
+
+/// (lookalike, Latin letter), sorted by lookalike for binary search
+const LOOKALIKES: [(char, char); 45] = [
+    // Greek capitals
+    ('Α', 'A'),
+    ('Β', 'B'),
+    ('Ε', 'E'),
+    ('Ζ', 'Z'),
+    ('Η', 'H'),
+    ('Ι', 'I'),
+    ('Κ', 'K'),
+    ('Μ', 'M'),
+    ('Ν', 'N'),
+    ('Ο', 'O'),
+    ('Ρ', 'P'),
+    ('Τ', 'T'),
+    ('Υ', 'Y'),
+    ('Χ', 'X'),
+    // Greek small
+    ('ο', 'o'),
+    // Cyrillic
+    ('Ѕ', 'S'),
+    ('І', 'I'),
+    ('Ј', 'J'),
+    ('А', 'A'),
+    ('В', 'B'),
+    ('Е', 'E'),
+    ('К', 'K'),
+    ('М', 'M'),
+    ('Н', 'H'),
+    ('О', 'O'),
+    ('Р', 'P'),
+    ('С', 'C'),
+    ('Т', 'T'),
+    ('Х', 'X'),
+    ('а', 'a'),
+    ('е', 'e'),
+    ('о', 'o'),
+    ('р', 'p'),
+    ('с', 'c'),
+    ('у', 'y'),
+    ('х', 'x'),
+    ('ѕ', 's'),
+    ('і', 'i'),
+    ('ј', 'j'),
+    ('Ү', 'Y'),
+    ('һ', 'h'),
+    ('ԁ', 'd'),
+    ('ԛ', 'q'),
+    ('Ԝ', 'W'),
+    ('ԝ', 'w'),
+];
+
+/// ASCII of a fullwidth form ("ｖ" → 'v', "，" → ',', U+3000 → ' ')
+pub fn fullwidth(c: char) -> Option<char> {
+    match c {
+        '\u{3000}' => Some(' '),
+        '\u{FF01}'..='\u{FF5E}' => char::from_u32(c as u32 - 0xFEE0),
+        _ => None,
+    }
+}
+
+/// Latin letter drawn like `c` ("а" (Cyrillic) → 'a')
+pub fn lookalike(c: char) -> Option<char> {
+    LOOKALIKES
+        .binary_search_by(|(l, _)| l.cmp(&c))
+        .ok()
+        .map(|i| LOOKALIKES[i].1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sorted() {
+        for pair in LOOKALIKES.windows(2) {
+            assert!(pair[0].0 < pair[1].0, "{:?}", pair);
+        }
+        for (l, latin) in LOOKALIKES {
+            assert!(!l.is_ascii() && latin.is_ascii_alphabetic(), "{}", l);
+        }
+    }
+
+    #[test]
+    fn test_fullwidth() {
+        assert_eq!(fullwidth('Ｖ'), Some('V'));
+        assert_eq!(fullwidth('ｚ'), Some('z'));
+        assert_eq!(fullwidth('０'), Some('0'));
+        assert_eq!(fullwidth('，'), Some(','));
+        assert_eq!(fullwidth('\u{3000}'), Some(' '));
+        assert_eq!(fullwidth('a'), None);
+        assert_eq!(fullwidth('。'), None);
+    }
+
+    #[test]
+    fn test_lookalike() {
+        assert_eq!(lookalike('а'), Some('a'));
+        assert_eq!(lookalike('Р'), Some('P'));
+        assert_eq!(lookalike('ο'), Some('o'));
+        assert_eq!(lookalike('a'), None);
+        assert_eq!(lookalike('м'), None);
+    }
+}
@@ -2,7 +2,7 @@
 //!
 //! Centralized constants for valid initials, finals, and spelling rules.
 
-use crate::data::keys;
+use crate::keys;
 
 /// Valid single initial consonants
 pub const VALID_INITIALS_1: &[u16] = &[
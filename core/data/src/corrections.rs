@@ -0,0 +1,92 @@
+//! Dialect Correction Packs
+//!
+//! Misspellings that come from regional pronunciation: the word is typed
+//! the way it is said. Each pack only lists forms that are not words
+//! themselves, so correcting them never changes a real word.
+//!
+//! Entries are `(typed, correct)`, sorted by `typed` (byte order) for
+//! binary search.
+
+/// Corrections for one dialect
+pub struct Pack {
+    pub name: &'static str,
+    pub entries: &'static [(&'static str, &'static str)],
+}
+
+impl Pack {
+    /// Correct form of lowercase `word`, if the pack has one
+    pub fn lookup(&self, word: &str) -> Option<&'static str> {
+        self.entries
+            .binary_search_by(|(typed, _)| (*typed).cmp(word))
+            .ok()
+            .map(|i| self.entries[i].1)
+    }
+}
+
+/// Northern: r/d/gi and s/x sound alike
+pub const NORTHERN: Pack = Pack {
+    name: "northern",
+    entries: &[
+        ("dượu", "rượu"),
+        ("giòng", "dòng"),
+        ("giễ", "dễ"),
+        ("xắp", "sắp"),
+        ("xẽ", "sẽ"),
+        ("xửa", "sửa"),
+    ],
+};
+
+/// Central: hỏi and ngã sound alike
+pub const CENTRAL: Pack = Pack {
+    name: "central",
+    entries: &[
+        ("cãm", "cảm"),
+        ("giử", "giữ"),
+        ("hõi", "hỏi"),
+        ("mổi", "mỗi"),
+        ("nghỉa", "nghĩa"),
+        ("nhửng", "những"),
+    ],
+};
+
+/// Southern: n/l swaps, final t/c, and spoken forms (nhìu, bịnh)
+pub const SOUTHERN: Pack = Pack {
+    name: "southern",
+    entries: &[
+        ("bịnh", "bệnh"),
+        ("lói", "nói"),
+        ("lước", "nước"),
+        ("mún", "muốn"),
+        ("nhìu", "nhiều"),
+        ("nuôn", "luôn"),
+        ("nàm", "làm"),
+        ("nạnh", "lạnh"),
+        ("nớn", "lớn"),
+        ("đượt", "được"),
+    ],
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL: [Pack; 3] = [NORTHERN, CENTRAL, SOUTHERN];
+
+    #[test]
+    fn test_sorted() {
+        for pack in ALL {
+            for pair in pack.entries.windows(2) {
+                assert!(pair[0].0 < pair[1].0, "{}: {:?}", pack.name, pair);
+            }
+        }
+    }
+
+    #[test]
+    fn test_lookup() {
+        assert_eq!(SOUTHERN.lookup("nàm"), Some("làm"));
+        assert_eq!(SOUTHERN.lookup("làm"), None);
+        assert_eq!(NORTHERN.lookup("dượu"), Some("rượu"));
+        assert_eq!(CENTRAL.lookup("nhửng"), Some("những"));
+        assert_eq!(CENTRAL.lookup(""), None);
+    }
+}
@@ -0,0 +1,561 @@
+//! macOS virtual keycodes
+//!
+//! The engine speaks macOS keycodes on every platform. Hosts on other
+//! platforms translate first (see `from_windows_vk` and the Linux keysym map).
+
+// Letters
+pub const A: u16 = 0;
+pub const S: u16 = 1;
+pub const D: u16 = 2;
+pub const F: u16 = 3;
+pub const H: u16 = 4;
+pub const G: u16 = 5;
+pub const Z: u16 = 6;
+pub const X: u16 = 7;
+pub const C: u16 = 8;
+pub const V: u16 = 9;
+pub const B: u16 = 11;
+pub const Q: u16 = 12;
+pub const W: u16 = 13;
+pub const E: u16 = 14;
+pub const R: u16 = 15;
+pub const Y: u16 = 16;
+pub const T: u16 = 17;
+pub const O: u16 = 31;
+pub const U: u16 = 32;
+pub const I: u16 = 34;
+pub const P: u16 = 35;
+pub const L: u16 = 37;
+pub const J: u16 = 38;
+pub const K: u16 = 40;
+pub const N: u16 = 45;
+pub const M: u16 = 46;
+
+// Numbers
+pub const N1: u16 = 18;
+pub const N2: u16 = 19;
+pub const N3: u16 = 20;
+pub const N4: u16 = 21;
+pub const N5: u16 = 23;
+pub const N6: u16 = 22;
+pub const N7: u16 = 26;
+pub const N8: u16 = 28;
+pub const N9: u16 = 25;
+pub const N0: u16 = 29;
+
+// Special
+pub const SPACE: u16 = 49;
+pub const DELETE: u16 = 51;
+pub const TAB: u16 = 48;
+pub const RETURN: u16 = 36;
+pub const ENTER: u16 = 76;
+pub const ESC: u16 = 53;
+pub const LEFT: u16 = 123;
+pub const RIGHT: u16 = 124;
+pub const DOWN: u16 = 125;
+pub const UP: u16 = 126;
+pub const HOME: u16 = 115;
+pub const END: u16 = 119;
+pub const PAGE_UP: u16 = 116;
+pub const PAGE_DOWN: u16 = 121;
+pub const FORWARD_DELETE: u16 = 117;
+
+// Numpad (never VNI modifiers, always end the word)
+pub const KP_0: u16 = 82;
+pub const KP_1: u16 = 83;
+pub const KP_2: u16 = 84;
+pub const KP_3: u16 = 85;
+pub const KP_4: u16 = 86;
+pub const KP_5: u16 = 87;
+pub const KP_6: u16 = 88;
+pub const KP_7: u16 = 89;
+pub const KP_8: u16 = 91;
+pub const KP_9: u16 = 92;
+pub const KP_DECIMAL: u16 = 65;
+pub const KP_MULTIPLY: u16 = 67;
+pub const KP_PLUS: u16 = 69;
+pub const KP_CLEAR: u16 = 71;
+pub const KP_DIVIDE: u16 = 75;
+pub const KP_MINUS: u16 = 78;
+pub const KP_EQUALS: u16 = 81;
+
+// International (ISO / JIS)
+pub const SECTION: u16 = 10; // ISO key left of 1 (§ on Mac, <> on PC)
+pub const JIS_YEN: u16 = 93;
+pub const JIS_UNDERSCORE: u16 = 94;
+pub const JIS_KP_COMMA: u16 = 95;
+pub const JIS_EISU: u16 = 102;
+pub const JIS_KANA: u16 = 104;
+
+// Modifiers (pressed alone they never touch the buffer)
+pub const RIGHT_COMMAND: u16 = 54;
+pub const COMMAND: u16 = 55;
+pub const SHIFT: u16 = 56;
+pub const CAPS_LOCK: u16 = 57;
+pub const OPTION: u16 = 58;
+pub const CONTROL: u16 = 59;
+pub const RIGHT_SHIFT: u16 = 60;
+pub const RIGHT_OPTION: u16 = 61; // AltGr on PC keyboards
+pub const RIGHT_CONTROL: u16 = 62;
+pub const FUNCTION: u16 = 63;
+
+// Function keys
+pub const F1: u16 = 122;
+pub const F2: u16 = 120;
+pub const F3: u16 = 99;
+pub const F4: u16 = 118;
+pub const F5: u16 = 96;
+pub const F6: u16 = 97;
+pub const F7: u16 = 98;
+pub const F8: u16 = 100;
+pub const F9: u16 = 101;
+pub const F10: u16 = 109;
+pub const F11: u16 = 103;
+pub const F12: u16 = 111;
+
+/// Pseudo keycode for a dead key (no macOS keycode exists for it).
+///
+/// Hosts send this when the OS layout reports a pending dead key
+/// (US-International `'`, `"`, `~`...). The composed character that
+/// follows is not a Telex/VNI letter, so the word is ended here.
+pub const DEAD: u16 = 0xFE;
+
+// Punctuation
+pub const DOT: u16 = 47;
+pub const COMMA: u16 = 43;
+pub const SLASH: u16 = 44;
+pub const SEMICOLON: u16 = 41;
+pub const QUOTE: u16 = 39;
+pub const LBRACKET: u16 = 33;
+pub const RBRACKET: u16 = 30;
+pub const BACKSLASH: u16 = 42;
+pub const MINUS: u16 = 27;
+pub const EQUAL: u16 = 24;
+pub const BACKQUOTE: u16 = 50;
+
+/// Check if key breaks word (space, punctuation, arrows, numpad, etc.)
+pub fn is_break(key: u16) -> bool {
+    matches!(
+        key,
+        SPACE
+            | TAB
+            | RETURN
+            | ENTER
+            | ESC
+            | LEFT
+            | RIGHT
+            | UP
+            | DOWN
+            | HOME
+            | END
+            | PAGE_UP
+            | PAGE_DOWN
+            | FORWARD_DELETE
+            | DOT
+            | COMMA
+            | SLASH
+            | SEMICOLON
+            | QUOTE
+            | LBRACKET
+            | RBRACKET
+            | BACKSLASH
+            | MINUS
+            | EQUAL
+            | BACKQUOTE
+            | SECTION
+            | JIS_YEN
+            | JIS_UNDERSCORE
+            | JIS_KP_COMMA
+            | DEAD
+    ) || is_numpad(key)
+}
+
+/// Check if key types punctuation (the break keys of the main block
+/// besides space)
+pub fn is_punctuation(key: u16) -> bool {
+    matches!(
+        key,
+        DOT | COMMA
+            | SLASH
+            | SEMICOLON
+            | QUOTE
+            | LBRACKET
+            | RBRACKET
+            | BACKSLASH
+            | MINUS
+            | EQUAL
+            | BACKQUOTE
+            | SECTION
+            | JIS_YEN
+            | JIS_UNDERSCORE
+            | JIS_KP_COMMA
+    )
+}
+
+/// Check if key is on the numeric keypad
+pub fn is_numpad(key: u16) -> bool {
+    matches!(
+        key,
+        KP_0 | KP_1
+            | KP_2
+            | KP_3
+            | KP_4
+            | KP_5
+            | KP_6
+            | KP_7
+            | KP_8
+            | KP_9
+            | KP_DECIMAL
+            | KP_MULTIPLY
+            | KP_PLUS
+            | KP_CLEAR
+            | KP_DIVIDE
+            | KP_MINUS
+            | KP_EQUALS
+    )
+}
+
+/// Check if key is a modifier key (Shift, Control, Option/AltGr, Command, Fn, CapsLock)
+pub fn is_modifier(key: u16) -> bool {
+    matches!(
+        key,
+        COMMAND
+            | RIGHT_COMMAND
+            | SHIFT
+            | RIGHT_SHIFT
+            | CAPS_LOCK
+            | OPTION
+            | RIGHT_OPTION
+            | CONTROL
+            | RIGHT_CONTROL
+            | FUNCTION
+    )
+}
+
+/// Check if key should pass through without touching the buffer
+/// (modifiers, function keys, input-source toggles)
+pub fn is_passthrough(key: u16) -> bool {
+    is_modifier(key)
+        || matches!(
+            key,
+            F1 | F2 | F3 | F4 | F5 | F6 | F7 | F8 | F9 | F10 | F11 | F12 | JIS_EISU | JIS_KANA
+        )
+}
+
+/// Check if key is a vowel (a, e, i, o, u, y)
+pub fn is_vowel(key: u16) -> bool {
+    matches!(key, A | E | I | O | U | Y)
+}
+
+/// Check if key is a letter
+pub fn is_letter(key: u16) -> bool {
+    matches!(
+        key,
+        A | B
+            | C
+            | D
+            | E
+            | F
+            | G
+            | H
+            | I
+            | J
+            | K
+            | L
+            | M
+            | N
+            | O
+            | P
+            | Q
+            | R
+            | S
+            | T
+            | U
+            | V
+            | W
+            | X
+            | Y
+            | Z
+    )
+}
+
+/// Check if key is a consonant
+pub fn is_consonant(key: u16) -> bool {
+    is_letter(key) && !is_vowel(key)
+}
+
+/// Check if key is a number (0-9)
+pub fn is_number(key: u16) -> bool {
+    matches!(key, N0 | N1 | N2 | N3 | N4 | N5 | N6 | N7 | N8 | N9)
+}
+
+/// Keycode for an ASCII letter, digit or space typed as text
+///
+/// Case is dropped; callers track it separately.
+pub fn from_char(c: char) -> Option<u16> {
+    let key = match c.to_ascii_lowercase() {
+        'a' => A,
+        'b' => B,
+        'c' => C,
+        'd' => D,
+        'e' => E,
+        'f' => F,
+        'g' => G,
+        'h' => H,
+        'i' => I,
+        'j' => J,
+        'k' => K,
+        'l' => L,
+        'm' => M,
+        'n' => N,
+        'o' => O,
+        'p' => P,
+        'q' => Q,
+        'r' => R,
+        's' => S,
+        't' => T,
+        'u' => U,
+        'v' => V,
+        'w' => W,
+        'x' => X,
+        'y' => Y,
+        'z' => Z,
+        '0' => N0,
+        '1' => N1,
+        '2' => N2,
+        '3' => N3,
+        '4' => N4,
+        '5' => N5,
+        '6' => N6,
+        '7' => N7,
+        '8' => N8,
+        '9' => N9,
+        ' ' => SPACE,
+        _ => return None,
+    };
+    Some(key)
+}
+
+/// Translate a Windows virtual-key code to the engine keycode.
+///
+/// Returns `None` for keys the engine has no use for; hosts should
+/// pass those through untouched. AltGr chords arrive on Windows as
+/// Ctrl+Alt and must be reported with `ctrl = true` (word break).
+///
+/// Reference: https://learn.microsoft.com/windows/win32/inputdev/virtual-key-codes
+pub fn from_windows_vk(vk: u16) -> Option<u16> {
+    let key = match vk {
+        // Letters A-Z (0x41 - 0x5A)
+        0x41 => A,
+        0x42 => B,
+        0x43 => C,
+        0x44 => D,
+        0x45 => E,
+        0x46 => F,
+        0x47 => G,
+        0x48 => H,
+        0x49 => I,
+        0x4A => J,
+        0x4B => K,
+        0x4C => L,
+        0x4D => M,
+        0x4E => N,
+        0x4F => O,
+        0x50 => P,
+        0x51 => Q,
+        0x52 => R,
+        0x53 => S,
+        0x54 => T,
+        0x55 => U,
+        0x56 => V,
+        0x57 => W,
+        0x58 => X,
+        0x59 => Y,
+        0x5A => Z,
+        // Numbers 0-9 (0x30 - 0x39)
+        0x30 => N0,
+        0x31 => N1,
+        0x32 => N2,
+        0x33 => N3,
+        0x34 => N4,
+        0x35 => N5,
+        0x36 => N6,
+        0x37 => N7,
+        0x38 => N8,
+        0x39 => N9,
+        // Special
+        0x08 => DELETE,
+        0x09 => TAB,
+        0x0D => RETURN,
+        0x1B => ESC,
+        0x20 => SPACE,
+        0x21 => PAGE_UP,
+        0x22 => PAGE_DOWN,
+        0x23 => END,
+        0x24 => HOME,
+        0x25 => LEFT,
+        0x26 => UP,
+        0x27 => RIGHT,
+        0x28 => DOWN,
+        0x2E => FORWARD_DELETE,
+        // Numpad (0x60 - 0x6F)
+        0x60 => KP_0,
+        0x61 => KP_1,
+        0x62 => KP_2,
+        0x63 => KP_3,
+        0x64 => KP_4,
+        0x65 => KP_5,
+        0x66 => KP_6,
+        0x67 => KP_7,
+        0x68 => KP_8,
+        0x69 => KP_9,
+        0x6A => KP_MULTIPLY,
+        0x6B => KP_PLUS,
+        0x6D => KP_MINUS,
+        0x6E => KP_DECIMAL,
+        0x6F => KP_DIVIDE,
+        0x0C => KP_CLEAR,
+        // Function keys F1-F12 (0x70 - 0x7B)
+        0x70 => F1,
+        0x71 => F2,
+        0x72 => F3,
+        0x73 => F4,
+        0x74 => F5,
+        0x75 => F6,
+        0x76 => F7,
+        0x77 => F8,
+        0x78 => F9,
+        0x79 => F10,
+        0x7A => F11,
+        0x7B => F12,
+        // Modifiers
+        0x10 | 0xA0 => SHIFT,
+        0xA1 => RIGHT_SHIFT,
+        0x11 | 0xA2 => CONTROL,
+        0xA3 => RIGHT_CONTROL,
+        0x12 | 0xA4 => OPTION,
+        0xA5 => RIGHT_OPTION, // AltGr
+        0x5B => COMMAND,      // Left Windows key
+        0x5C => RIGHT_COMMAND,
+        0x14 => CAPS_LOCK,
+        // Punctuation (US layout)
+        0xBA => SEMICOLON,
+        0xBB => EQUAL,
+        0xBC => COMMA,
+        0xBD => MINUS,
+        0xBE => DOT,
+        0xBF => SLASH,
+        0xC0 => BACKQUOTE,
+        0xDB => LBRACKET,
+        0xDC => BACKSLASH,
+        0xDD => RBRACKET,
+        0xDE => QUOTE,
+        // International
+        0xE2 => SECTION, // VK_OEM_102: <> key on ISO keyboards
+        0xDF => SECTION, // VK_OEM_8: layout-specific extra key
+        0xE5 => DEAD,    // VK_PROCESSKEY: another IME owns this keystroke
+        _ => return None,
+    };
+    Some(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_macos_numpad_is_break() {
+        for key in [KP_0, KP_5, KP_9, KP_DECIMAL, KP_PLUS, KP_EQUALS] {
+            assert!(is_numpad(key), "{} should be numpad", key);
+            assert!(is_break(key), "{} should break word", key);
+            assert!(!is_number(key), "{} is not a main-row number", key);
+            assert!(!is_letter(key));
+        }
+        // ENTER (76) is the numpad Enter key and already a break
+        assert!(is_break(ENTER));
+    }
+
+    #[test]
+    fn test_punctuation_breaks() {
+        for key in [DOT, COMMA, QUOTE, RBRACKET, JIS_KP_COMMA] {
+            assert!(is_punctuation(key) && is_break(key), "{}", key);
+        }
+        for key in [SPACE, RETURN, LEFT, ESC, A, N0] {
+            assert!(!is_punctuation(key), "{}", key);
+        }
+    }
+
+    #[test]
+    fn test_macos_international_and_dead_keys_break() {
+        assert!(is_break(SECTION));
+        assert!(is_break(JIS_YEN));
+        assert!(is_break(JIS_UNDERSCORE));
+        assert!(is_break(DEAD));
+        assert!(!is_letter(DEAD));
+    }
+
+    #[test]
+    fn test_macos_modifiers_pass_through() {
+        for key in [
+            SHIFT,
+            RIGHT_SHIFT,
+            CONTROL,
+            OPTION,
+            RIGHT_OPTION,
+            COMMAND,
+            FUNCTION,
+        ] {
+            assert!(is_modifier(key));
+            assert!(is_passthrough(key));
+            assert!(!is_break(key));
+            assert!(!is_letter(key));
+        }
+        assert!(is_passthrough(F1));
+        assert!(is_passthrough(JIS_KANA));
+        assert!(!is_passthrough(A));
+        assert!(!is_passthrough(SPACE));
+    }
+
+    #[test]
+    fn test_from_char() {
+        assert_eq!(from_char('a'), Some(A));
+        assert_eq!(from_char('Z'), Some(Z));
+        assert_eq!(from_char('7'), Some(N7));
+        assert_eq!(from_char(' '), Some(SPACE));
+        assert_eq!(from_char('é'), None);
+        assert_eq!(from_char('@'), None);
+    }
+
+    #[test]
+    fn test_windows_letters_and_numbers() {
+        assert_eq!(from_windows_vk(0x41), Some(A));
+        assert_eq!(from_windows_vk(0x53), Some(S));
+        assert_eq!(from_windows_vk(0x5A), Some(Z));
+        assert_eq!(from_windows_vk(0x30), Some(N0));
+        assert_eq!(from_windows_vk(0x39), Some(N9));
+        assert_eq!(from_windows_vk(0x08), Some(DELETE));
+    }
+
+    #[test]
+    fn test_windows_numpad_maps_to_numpad() {
+        for vk in 0x60..=0x69 {
+            let key = from_windows_vk(vk).unwrap();
+            assert!(is_numpad(key), "VK {:#x} should map to numpad", vk);
+            assert!(is_break(key));
+        }
+        assert_eq!(from_windows_vk(0x6E), Some(KP_DECIMAL));
+    }
+
+    #[test]
+    fn test_windows_altgr_and_dead_keys() {
+        // AltGr (right Alt) is a modifier, never a letter
+        let altgr = from_windows_vk(0xA5).unwrap();
+        assert!(is_modifier(altgr));
+        // VK_PROCESSKEY: keystroke owned by another IME → break
+        assert_eq!(from_windows_vk(0xE5), Some(DEAD));
+        // ISO <> key
+        assert!(is_break(from_windows_vk(0xE2).unwrap()));
+        // Unknown VK → None
+        assert_eq!(from_windows_vk(0xFF), None);
+    }
+}
@@ -0,0 +1,200 @@
+//! Embedded Word Lists
+//!
+//! Each list is newline-separated UTF-8 text compiled in with
+//! `include_str!`, indexed by offset tables that `build.rs` generates from
+//! the same files: a lookup goes straight to a word instead of scanning
+//! lines. Nothing is built at startup, nothing is allocated, and only the
+//! pages a search touches are ever read. Sorted lists (`Lexicon`) are
+//! binary-searched; ranked lists (`Ranked`) are in frequency order, with
+//! an index in word order to look up a word's rank.
+//!
+//! The lists are small and hand-curated (a few hundred words), which this
+//! layout suits. It is not the large-lexicon format: a full dictionary
+//! would want a compressed archive (fst or rkyv) mapped from its own
+//! file, and nothing here provides that yet.
+
+use core::cmp::Ordering;
+
+include!(concat!(env!("OUT_DIR"), "/lexicon_table.rs"));
+
+/// Newline-terminated words and the offset of each (see `word_starts` in
+/// `build.rs`)
+#[derive(Clone, Copy)]
+struct Words {
+    text: &'static str,
+    starts: &'static [u32],
+}
+
+impl Words {
+    fn len(&self) -> usize {
+        self.starts.len().saturating_sub(1)
+    }
+
+    fn get(&self, i: usize) -> &'static str {
+        let start = self.starts[i] as usize;
+        let end = self.starts[i + 1] as usize - 1;
+        self.text.get(start..end).unwrap_or("")
+    }
+
+    fn iter(self) -> impl Iterator<Item = &'static str> {
+        (0..self.len()).map(move |i| self.get(i))
+    }
+}
+
+/// Binary search over `0..len` by `cmp(i)` (the ordering of entry `i`
+/// against the target)
+fn search(len: usize, cmp: impl Fn(usize) -> Ordering) -> Option<usize> {
+    let (mut lo, mut hi) = (0, len);
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        match cmp(mid) {
+            Ordering::Equal => return Some(mid),
+            Ordering::Less => lo = mid + 1,
+            Ordering::Greater => hi = mid,
+        }
+    }
+    None
+}
+
+/// Sorted word list (one lowercase word per line, byte order)
+pub struct Lexicon {
+    words: Words,
+}
+
+impl Lexicon {
+    /// `starts`: offset of each word, then `text.len()`
+    pub const fn new(text: &'static str, starts: &'static [u32]) -> Self {
+        Self {
+            words: Words { text, starts },
+        }
+    }
+
+    pub fn contains(&self, word: &str) -> bool {
+        search(self.words.len(), |i| self.words.get(i).cmp(word)).is_some()
+    }
+
+    pub fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn words(&self) -> impl Iterator<Item = &'static str> {
+        self.words.iter()
+    }
+}
+
+/// Word list in frequency order (one lowercase word per line, most
+/// frequent first)
+pub struct Ranked {
+    words: Words,
+    /// Ranks in word order
+    by_word: &'static [u16],
+}
+
+impl Ranked {
+    /// `starts` as for `Lexicon::new`; `by_word`: every rank, sorted by
+    /// its word
+    pub const fn new(text: &'static str, starts: &'static [u32], by_word: &'static [u16]) -> Self {
+        Self {
+            words: Words { text, starts },
+            by_word,
+        }
+    }
+
+    /// Words, most frequent first
+    pub fn words(&self) -> impl Iterator<Item = &'static str> {
+        self.words.iter()
+    }
+
+    /// Number of words
+    pub fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Position of `word` (0 = most frequent)
+    pub fn rank(&self, word: &str) -> Option<usize> {
+        let rank = |i: usize| self.by_word[i] as usize;
+        search(self.by_word.len(), |i| self.words.get(rank(i)).cmp(word)).map(rank)
+    }
+}
+
+/// Common Vietnamese words, most frequent first, used by word suggestions
+pub static VIETNAMESE: Ranked = Ranked::new(
+    include_str!("../lexicon/vietnamese.txt"),
+    &VIETNAMESE_STARTS,
+    &VIETNAMESE_BY_WORD,
+);
+
+/// English words whose Telex keystrokes compose a valid-looking Vietnamese
+/// syllable ("mix" → "mĩ", "boss" → "bos"), used by `AutoCorrectMode::All`.
+///
+/// Not a general dictionary: it only needs the words the engine's restore
+/// checks let through, so it holds the common words that survive them,
+/// about a hundred and twenty. Words whose keystrokes are the usual way to
+/// type a real Vietnamese word ("see" → "sê", "moon" → "môn") are
+/// deliberately left out: `All` would take that word away from Vietnamese
+/// typists.
+pub static ENGLISH: Lexicon = Lexicon::new(include_str!("../lexicon/english.txt"), &ENGLISH_STARTS);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sorted_lowercase_unique() {
+        let words: Vec<&str> = ENGLISH.words().collect();
+        assert!(!words.is_empty());
+        for pair in words.windows(2) {
+            assert!(pair[0] < pair[1], "not sorted/unique: {:?}", pair);
+        }
+        for w in &words {
+            assert!(w.bytes().all(|b| b.is_ascii_lowercase()), "{:?}", w);
+        }
+    }
+
+    #[test]
+    fn test_every_word_found() {
+        assert_eq!(ENGLISH.len(), ENGLISH.words().count());
+        for w in ENGLISH.words() {
+            assert!(ENGLISH.contains(w), "{}", w);
+        }
+    }
+
+    #[test]
+    fn test_vietnamese_unique_lowercase() {
+        let mut words: Vec<&str> = VIETNAMESE.words().collect();
+        assert_eq!(VIETNAMESE.rank("của"), Some(0));
+        assert_eq!(VIETNAMESE.len(), words.len());
+        for (rank, w) in words.iter().enumerate() {
+            assert_eq!(VIETNAMESE.rank(w), Some(rank), "{}", w);
+        }
+        assert_eq!(VIETNAMESE.rank("cua"), None);
+        for w in &words {
+            assert!(w.chars().all(char::is_lowercase), "{:?}", w);
+        }
+        words.sort_unstable();
+        words.dedup();
+        assert_eq!(words.len(), VIETNAMESE.len(), "duplicate words");
+    }
+
+    #[test]
+    fn test_contains() {
+        let lex = Lexicon::new("ant\nbee\ncat\ndog\nelk\n", &[0, 4, 8, 12, 16, 20]);
+        assert_eq!(lex.len(), 5);
+        for w in ["ant", "bee", "cat", "dog", "elk"] {
+            assert!(lex.contains(w), "{}", w);
+        }
+        for w in ["", "a", "an", "ants", "cow", "zebra", "bee\ncat"] {
+            assert!(!lex.contains(w), "{:?}", w);
+        }
+        assert!(!Lexicon::new("", &[0]).contains("a"));
+        assert!(Lexicon::new("", &[0]).is_empty());
+    }
+}
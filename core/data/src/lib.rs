@@ -0,0 +1,39 @@
+//! Vietnamese Language Data Modules
+//!
+//! This crate contains all linguistic data for Vietnamese input:
+//! - `keys`: Virtual keycode definitions (platform-specific)
+//! - `chars`: Unicode character conversion (includes tone/mark constants)
+//! - `vowel`: Vietnamese vowel phonology system
+//! - `tone_rules`: Which vowel carries the tone mark
+//! - `corrections`: Dialect spelling correction packs
+//! - `toneless`: Most likely spelling of common words typed without diacritics
+//! - `lexicon`: Embedded word lists (feature `lexicon`)
+//! - `long_press`: Accent popups for on-screen keyboards
+//! - `bigram`: Letter-pair model telling English from Telex keystrokes
+//! - `confusables`: Fullwidth and Cyrillic/Greek lookalikes of ASCII
+//!
+//! Pure tables and lookups: no state, no I/O, `no_std` (needs `alloc`).
+//! The IME engine (`gonhanh-core`, published to Rust users as
+//! `gonhanh-engine`) re-exports it as `gonhanh_core::data`.
+
+#![cfg_attr(not(test), no_std)]
+
+extern crate alloc;
+
+pub mod bigram;
+pub mod chars;
+pub mod confusables;
+pub mod constants;
+pub mod corrections;
+pub mod keys;
+#[cfg(feature = "lexicon")]
+pub mod lexicon;
+pub mod long_press;
+pub mod tone_rules;
+pub mod toneless;
+pub mod vowel;
+
+pub use chars::{get_d, mark, to_char, tone};
+pub use constants::*;
+pub use keys::{is_break, is_letter, is_vowel};
+pub use vowel::{Modifier, Phonology, Role, Vowel};
@@ -0,0 +1,84 @@
+//! Long-press Accent Candidates
+//!
+//! What an on-screen keyboard pops up when a key is held: every letter of
+//! the Vietnamese alphabet typed from that key, derived from `chars` so
+//! mobile hosts don't keep their own tables.
+//!
+//! ```text
+//! a → a á à ả ã ạ ă ắ ằ ẳ ẵ ặ â ấ ầ ẩ ẫ ậ
+//! d → d đ
+//! ```
+//!
+//! Plain form first, then marks in the usual order (sắc, huyền, hỏi, ngã,
+//! nặng), one modified form after another (ă before â, ô before ơ).
+
+use alloc::vec::Vec;
+
+use super::chars::{self, mark, tone};
+use super::keys;
+
+/// Modifiers each vowel key can take, in popup order
+fn modifiers(key: u16) -> &'static [u8] {
+    match key {
+        keys::A => &[tone::NONE, tone::HORN, tone::CIRCUMFLEX],
+        keys::E => &[tone::NONE, tone::CIRCUMFLEX],
+        keys::O => &[tone::NONE, tone::CIRCUMFLEX, tone::HORN],
+        keys::U => &[tone::NONE, tone::HORN],
+        _ => &[tone::NONE],
+    }
+}
+
+/// Candidates for the key labeled `label` (a letter, either case)
+///
+/// Empty for anything that isn't an ASCII letter. Consonants other than d
+/// only have themselves.
+pub fn candidates(label: char) -> Vec<char> {
+    let Some(key) = label
+        .is_ascii_alphabetic()
+        .then(|| keys::from_char(label.to_ascii_lowercase()))
+        .flatten()
+    else {
+        return Vec::new();
+    };
+    let caps = label.is_ascii_uppercase();
+    if key == keys::D {
+        return alloc::vec![label, chars::get_d(caps)];
+    }
+    if !keys::is_vowel(key) {
+        return alloc::vec![label];
+    }
+    let mut out = Vec::with_capacity(18);
+    for &t in modifiers(key) {
+        for m in mark::NONE..=mark::NANG {
+            out.extend(chars::to_char(key, caps, t, m));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::String;
+
+    fn popup(label: char) -> String {
+        candidates(label).into_iter().collect()
+    }
+
+    #[test]
+    fn test_vowels() {
+        assert_eq!(popup('a'), "aáàảãạăắằẳẵặâấầẩẫậ");
+        assert_eq!(popup('O'), "OÓÒỎÕỌÔỐỒỔỖỘƠỚỜỞỠỢ");
+        assert_eq!(popup('u'), "uúùủũụưứừửữự");
+        assert_eq!(popup('y'), "yýỳỷỹỵ");
+    }
+
+    #[test]
+    fn test_consonants() {
+        assert_eq!(popup('d'), "dđ");
+        assert_eq!(popup('D'), "DĐ");
+        assert_eq!(popup('k'), "k");
+        assert!(candidates('1').is_empty());
+        assert!(candidates('é').is_empty());
+    }
+}
@@ -0,0 +1,275 @@
+//! Tone Placement Rules
+//!
+//! Which vowel of the nucleus carries the tone mark, as an ordered table:
+//! the first rule whose nucleus and conditions match decides. Spelling
+//! styles, the qu/gi initials and future dialect options are rows here
+//! rather than branches in the engine.
+//!
+//! Based on docs/vietnamese-language-system.md section 7.3.
+//!
+//! ```text
+//! nucleus   when                 modern   classic
+//! any 2     final consonant      2nd      2nd      hoàn, tiến
+//! any 2     2nd has diacritic    2nd      2nd      thuở
+//! any 2     1st has diacritic    1st      1st      mừa
+//! ia        gi initial           2nd      2nd      già
+//! ua        qu initial           2nd      2nd      quá
+//! oa oe uy  -                    2nd      1st      hoà / hòa
+//! ```
+
+use super::keys;
+use super::vowel::{TonePosition, Vowel};
+use TonePosition::{First, Last, Second};
+
+/// Initial consonant that changes where the tone goes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Initial {
+    /// qu: the u belongs to the initial (quá)
+    Qu,
+    /// gi: the i belongs to the initial (già)
+    Gi,
+}
+
+/// Which rules apply to a nucleus
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Nucleus {
+    /// These vowel keys, in order
+    Keys(&'static [u16]),
+    /// Any nucleus of this many vowels
+    Any(usize),
+}
+
+/// Syllable context a rule depends on (`None` = either way)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct When {
+    /// Ends in a consonant
+    pub final_consonant: Option<bool>,
+    /// Starts with this initial
+    pub initial: Option<Initial>,
+    /// The vowel at this position has a diacritic (â, ơ, ư...)
+    pub diacritic: Option<TonePosition>,
+}
+
+impl When {
+    /// No conditions
+    pub const ALWAYS: When = When {
+        final_consonant: None,
+        initial: None,
+        diacritic: None,
+    };
+}
+
+/// One row of the table
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ToneRule {
+    pub nucleus: Nucleus,
+    pub when: When,
+    /// Placement in the modern style (hoà, thuý)
+    pub modern: TonePosition,
+    /// Placement in the classic style (hòa, thúy)
+    pub classic: TonePosition,
+}
+
+/// Syllable facts a rule is matched against
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Context {
+    pub final_consonant: bool,
+    pub qu_initial: bool,
+    pub gi_initial: bool,
+}
+
+impl ToneRule {
+    /// Same placement in both styles
+    const fn both(nucleus: Nucleus, when: When, position: TonePosition) -> Self {
+        Self {
+            nucleus,
+            when,
+            modern: position,
+            classic: position,
+        }
+    }
+
+    fn matches(&self, vowels: &[Vowel], ctx: &Context) -> bool {
+        let nucleus = match self.nucleus {
+            Nucleus::Any(len) => vowels.len() == len,
+            Nucleus::Keys(keys) => {
+                keys.len() == vowels.len() && keys.iter().zip(vowels).all(|(k, v)| *k == v.key)
+            }
+        };
+        let when = &self.when;
+        nucleus
+            && when
+                .final_consonant
+                .is_none_or(|f| f == ctx.final_consonant)
+            && when.initial.is_none_or(|i| match i {
+                Initial::Qu => ctx.qu_initial,
+                Initial::Gi => ctx.gi_initial,
+            })
+            && when
+                .diacritic
+                .is_none_or(|p| index(p, vowels.len()).is_some_and(|i| vowels[i].has_diacritic()))
+    }
+}
+
+/// Vowel index of `position` in a nucleus of `len` vowels
+fn index(position: TonePosition, len: usize) -> Option<usize> {
+    match position {
+        First if len > 0 => Some(0),
+        Second if len > 1 => Some(1),
+        Last if len > 0 => Some(len - 1),
+        _ => None,
+    }
+}
+
+const fn triple(keys: &'static [u16; 3], position: TonePosition) -> ToneRule {
+    ToneRule::both(Nucleus::Keys(keys), When::ALWAYS, position)
+}
+
+const FINAL: When = When {
+    final_consonant: Some(true),
+    ..When::ALWAYS
+};
+
+const fn diacritic_at(position: TonePosition) -> When {
+    When {
+        diacritic: Some(position),
+        ..When::ALWAYS
+    }
+}
+
+const fn after(initial: Initial) -> When {
+    When {
+        initial: Some(initial),
+        ..When::ALWAYS
+    }
+}
+
+/// Main vowel + glide: tone on the first (Section 7.3.1)
+///
+/// ai, ao, au, ay, eo, ia, iu, oi, ui, ua, ưu
+const fn first(nucleus: Nucleus) -> ToneRule {
+    ToneRule::both(nucleus, When::ALWAYS, First)
+}
+
+/// Medial + main vowel: second in the modern style, first in the classic
+///
+/// oa, oe, uy, and the compounds uê, iê, uô/ươ (those carry a diacritic on
+/// the second vowel and never get this far)
+const fn medial(nucleus: Nucleus) -> ToneRule {
+    ToneRule {
+        nucleus,
+        when: When::ALWAYS,
+        modern: Second,
+        classic: First,
+    }
+}
+
+/// Standard Vietnamese placement, first match wins
+pub const TONE_RULES: &[ToneRule] = &[
+    // Single vowel
+    ToneRule::both(Nucleus::Any(1), When::ALWAYS, First),
+    // Diphthongs: a final consonant, then diacritics decide (7.3.2, 7.2.5)
+    ToneRule::both(Nucleus::Any(2), FINAL, Second),
+    ToneRule::both(Nucleus::Any(2), diacritic_at(Second), Second),
+    ToneRule::both(Nucleus::Any(2), diacritic_at(First), First),
+    // gi / qu: the i / u belongs to the initial
+    ToneRule::both(
+        Nucleus::Keys(&[keys::I, keys::A]),
+        after(Initial::Gi),
+        Second,
+    ),
+    ToneRule::both(
+        Nucleus::Keys(&[keys::U, keys::A]),
+        after(Initial::Qu),
+        Second,
+    ),
+    medial(Nucleus::Keys(&[keys::O, keys::A])),
+    medial(Nucleus::Keys(&[keys::O, keys::E])),
+    medial(Nucleus::Keys(&[keys::U, keys::E])),
+    medial(Nucleus::Keys(&[keys::U, keys::Y])),
+    medial(Nucleus::Keys(&[keys::I, keys::E])),
+    medial(Nucleus::Keys(&[keys::U, keys::O])),
+    first(Nucleus::Keys(&[keys::A, keys::I])),
+    first(Nucleus::Keys(&[keys::A, keys::O])),
+    first(Nucleus::Keys(&[keys::A, keys::U])),
+    first(Nucleus::Keys(&[keys::A, keys::Y])),
+    first(Nucleus::Keys(&[keys::E, keys::O])),
+    first(Nucleus::Keys(&[keys::I, keys::A])),
+    first(Nucleus::Keys(&[keys::I, keys::U])),
+    first(Nucleus::Keys(&[keys::O, keys::I])),
+    first(Nucleus::Keys(&[keys::U, keys::I])),
+    first(Nucleus::Keys(&[keys::U, keys::A])),
+    first(Nucleus::Keys(&[keys::U, keys::U])),
+    ToneRule::both(Nucleus::Any(2), When::ALWAYS, Second),
+    // Triphthongs: the middle, except uyê and giươ (7.3.3)
+    triple(&[keys::I, keys::E, keys::U], Second), // iêu: tiếu
+    triple(&[keys::Y, keys::E, keys::U], Second), // yêu: yếu
+    triple(&[keys::O, keys::A, keys::I], Second), // oai: ngoài
+    triple(&[keys::O, keys::A, keys::Y], Second), // oay: xoáy
+    triple(&[keys::O, keys::E, keys::O], Second), // oeo: khoèo
+    triple(&[keys::U, keys::A, keys::Y], Second), // uây: khuấy
+    triple(&[keys::U, keys::O, keys::I], Second), // uôi / ươi: cuối, mười
+    triple(&[keys::U, keys::O, keys::U], Second), // ươu: rượu
+    triple(&[keys::I, keys::U, keys::O], Last),   // iươ: giường
+    triple(&[keys::U, keys::Y, keys::E], Last),   // uyê: khuyến, quyền
+    ToneRule::both(Nucleus::Any(3), diacritic_at(Second), Second),
+    ToneRule::both(Nucleus::Any(3), diacritic_at(Last), Last),
+    ToneRule::both(Nucleus::Any(3), When::ALWAYS, Second),
+];
+
+/// Buffer position of the vowel that takes the tone, by the first rule of
+/// `rules` that matches; `None` if none does
+pub fn place(rules: &[ToneRule], vowels: &[Vowel], ctx: &Context, modern: bool) -> Option<usize> {
+    let rule = rules.iter().find(|r| r.matches(vowels, ctx))?;
+    let position = if modern { rule.modern } else { rule.classic };
+    index(position, vowels.len()).map(|i| vowels[i].pos)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vowel::Modifier;
+
+    fn nucleus(keys: &[(u16, Modifier)]) -> alloc::vec::Vec<Vowel> {
+        keys.iter()
+            .enumerate()
+            .map(|(i, &(k, m))| Vowel::new(k, m, i + 1))
+            .collect()
+    }
+
+    #[test]
+    fn test_styles_differ_on_medial_pairs() {
+        let oa = nucleus(&[(keys::O, Modifier::None), (keys::A, Modifier::None)]);
+        let ctx = Context::default();
+        assert_eq!(place(TONE_RULES, &oa, &ctx, true), Some(2));
+        assert_eq!(place(TONE_RULES, &oa, &ctx, false), Some(1));
+
+        // A final consonant settles it for both
+        let ctx = Context {
+            final_consonant: true,
+            ..Context::default()
+        };
+        assert_eq!(place(TONE_RULES, &oa, &ctx, false), Some(2));
+    }
+
+    #[test]
+    fn test_initials() {
+        let ua = nucleus(&[(keys::U, Modifier::None), (keys::A, Modifier::None)]);
+        let qu = Context {
+            qu_initial: true,
+            ..Context::default()
+        };
+        assert_eq!(place(TONE_RULES, &ua, &Context::default(), true), Some(1));
+        assert_eq!(place(TONE_RULES, &ua, &qu, true), Some(2));
+        assert_eq!(place(TONE_RULES, &ua, &qu, false), Some(2));
+    }
+
+    #[test]
+    fn test_custom_table() {
+        // A dialect option is a different table
+        let rules = [ToneRule::both(Nucleus::Any(2), When::ALWAYS, Last)];
+        let ai = nucleus(&[(keys::A, Modifier::None), (keys::I, Modifier::None)]);
+        assert_eq!(place(&rules, &ai, &Context::default(), true), Some(2));
+        assert_eq!(place(&rules, &ai[..1], &Context::default(), true), None);
+    }
+}
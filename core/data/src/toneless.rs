@@ -0,0 +1,145 @@
+//! Toneless Word Model
+//!
+//! Common words as they are most often written, keyed by their toneless
+//! spelling ("duoc" → "được"). A unigram baseline for restoring text typed
+//! without diacritics: it picks the most frequent reading and ignores
+//! context, so "ban" is always "bạn", never "bàn". Words that need no
+//! diacritics are listed too ("xin" → "xin"): a hit means "known word".
+//!
+//! Sorted by the toneless spelling (byte order) for binary search.
+
+/// `(toneless, most frequent spelling)`
+pub const TONELESS: &[(&str, &str)] = &[
+    ("anh", "anh"),
+    ("ban", "bạn"),
+    ("bao", "bao"),
+    ("biet", "biết"),
+    ("bo", "bố"),
+    ("cac", "các"),
+    ("cam", "cảm"),
+    ("can", "cần"),
+    ("chao", "chào"),
+    ("chi", "chỉ"),
+    ("cho", "cho"),
+    ("chua", "chưa"),
+    ("chuc", "chúc"),
+    ("chung", "chúng"),
+    ("co", "có"),
+    ("con", "con"),
+    ("cua", "của"),
+    ("cung", "cũng"),
+    ("cuoc", "cuộc"),
+    ("da", "đã"),
+    ("dang", "đang"),
+    ("day", "đây"),
+    ("de", "để"),
+    ("den", "đến"),
+    ("dep", "đẹp"),
+    ("di", "đi"),
+    ("dieu", "điều"),
+    ("do", "đó"),
+    ("dung", "đúng"),
+    ("duoc", "được"),
+    ("em", "em"),
+    ("gap", "gặp"),
+    ("gi", "gì"),
+    ("giup", "giúp"),
+    ("hay", "hay"),
+    ("hieu", "hiểu"),
+    ("hoc", "học"),
+    ("hoi", "hỏi"),
+    ("hom", "hôm"),
+    ("khi", "khi"),
+    ("khoe", "khỏe"),
+    ("khong", "không"),
+    ("la", "là"),
+    ("lai", "lại"),
+    ("lam", "làm"),
+    ("len", "lên"),
+    ("loi", "lời"),
+    ("long", "lòng"),
+    ("luc", "lúc"),
+    ("me", "mẹ"),
+    ("minh", "mình"),
+    ("moi", "mới"),
+    ("mot", "một"),
+    ("mung", "mừng"),
+    ("muon", "muốn"),
+    ("nao", "nào"),
+    ("nay", "này"),
+    ("nen", "nên"),
+    ("neu", "nếu"),
+    ("ngay", "ngày"),
+    ("ngon", "ngon"),
+    ("nguoi", "người"),
+    ("nha", "nhà"),
+    ("nhat", "nhất"),
+    ("nhe", "nhé"),
+    ("nhieu", "nhiều"),
+    ("nhu", "như"),
+    ("nhung", "những"),
+    ("noi", "nói"),
+    ("nuoc", "nước"),
+    ("oi", "ơi"),
+    ("on", "ơn"),
+    ("ong", "ông"),
+    ("qua", "qua"),
+    ("ra", "ra"),
+    ("rang", "rằng"),
+    ("rat", "rất"),
+    ("roi", "rồi"),
+    ("sao", "sao"),
+    ("sau", "sau"),
+    ("se", "sẽ"),
+    ("song", "sống"),
+    ("tai", "tại"),
+    ("ten", "tên"),
+    ("thay", "thấy"),
+    ("the", "thế"),
+    ("thi", "thì"),
+    ("thoi", "thôi"),
+    ("tieng", "tiếng"),
+    ("toi", "tôi"),
+    ("tren", "trên"),
+    ("trong", "trong"),
+    ("tu", "từ"),
+    ("tuoi", "tuổi"),
+    ("uong", "uống"),
+    ("va", "và"),
+    ("van", "vẫn"),
+    ("vao", "vào"),
+    ("ve", "về"),
+    ("viec", "việc"),
+    ("viet", "việt"),
+    ("voi", "với"),
+    ("vui", "vui"),
+    ("xin", "xin"),
+    ("yeu", "yêu"),
+];
+
+/// Most frequent spelling of lowercase, toneless `word`
+pub fn likely_spelling(word: &str) -> Option<&'static str> {
+    TONELESS
+        .binary_search_by(|(toneless, _)| (*toneless).cmp(word))
+        .ok()
+        .map(|i| TONELESS[i].1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sorted() {
+        for pair in TONELESS.windows(2) {
+            assert!(pair[0].0 < pair[1].0, "{:?}", pair);
+        }
+    }
+
+    #[test]
+    fn test_lookup() {
+        assert_eq!(likely_spelling("duoc"), Some("được"));
+        assert_eq!(likely_spelling("xin"), Some("xin"));
+        assert_eq!(likely_spelling("computer"), None);
+    }
+}
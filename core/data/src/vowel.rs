@@ -18,8 +18,37 @@
 //! - **Medial (âm đệm)**: o, u when followed by main vowel (oa, oe, uy, ua, uê)
 //! - **Main (âm chính)**: The primary vowel carrying tone
 //! - **Glide (bán nguyên âm)**: i/y, u/o at syllable end (ai, ao, iu, oi)
+//!
+//! ## Repeated Modifier Keys
+//!
+//! How the engine treats a modifier key (Telex a/e/o/w, VNI 6/7/8) typed
+//! again on the same nucleus. `K` is the key, `K'` another modifier key
+//! for the same vowel:
+//!
+//! ```text
+//!            K                K (next key)          K
+//!   plain ──────▶ modified ──────────────▶ undone ──────▶ undone + K ...
+//!                   │  ▲
+//!                K' │  │ K
+//!                   ▼  │
+//!                 switched
+//! ```
+//!
+//! - **plain → modified**: `aa` → â, `ow` → ơ, `uow` → ươ (both vowels).
+//! - **modified → undone**: the same key right away takes the diacritic
+//!   off the whole nucleus and types itself: `aaa` → aa, `oww` → ow,
+//!   `uoww` → uow, `o77` → o7.
+//! - **undone**: the key is spent; more presses stay literal until the
+//!   word ends: `aaaa` → aaa, `owww` → oww.
+//! - **switched**: another modifier for the vowel replaces the diacritic,
+//!   last key wins: `oow` → ơ, `owo` → ô, `a86` → â.
+//! - Anything typed in between ends the "right away": `caaca` → câca
+//!   (the new a is plain), `coomo` → cômo (ô is already modified, so o is
+//!   a letter).
 
 use super::keys;
+use super::tone_rules;
+use alloc::vec::Vec;
 
 /// Vowel modifier type (dấu phụ)
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -64,7 +93,7 @@ impl Vowel {
 /// Position for tone mark placement
 ///
 /// Based on docs/vietnamese-language-system.md section 7.3
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum TonePosition {
     /// Position 1 - First vowel (âm chính + glide: ai, ao, ia, ưu...)
     First,
@@ -134,136 +163,15 @@ pub const HORN_PATTERNS: &[VowelPairPattern] = &[
     // Default: second gets breve, but with consonant prefix: first gets horn
 ];
 
-// =============================================================================
-// TONE POSITION PATTERNS - Based on docs/vietnamese-language-system.md 7.3
-// =============================================================================
-
-/// Triphthong pattern for tone placement
-pub struct TriphthongTonePattern {
-    pub v1: u16,
-    pub v2: u16,
-    pub v3: u16,
-    pub position: TonePosition,
-}
-
-/// Diphthongs with tone on FIRST vowel (âm chính + glide)
-///
-/// Section 7.3.1: ai, ao, au, ay, âu, ây, eo, êu, ia, iu, oi, ôi, ơi, ui, ưi, ưu, ua*, ưa
-/// *ua is First only when NOT preceded by 'q'
-pub const TONE_FIRST_PATTERNS: &[[u16; 2]] = &[
-    [keys::A, keys::I], // ai: mái, hài
-    [keys::A, keys::O], // ao: cáo, sào
-    [keys::A, keys::U], // au: sáu, màu
-    [keys::A, keys::Y], // ay: máy, tày
-    [keys::E, keys::O], // eo: kéo, trèo
-    [keys::I, keys::A], // ia: kìa, mía (not after gi)
-    [keys::I, keys::U], // iu: dịu, kíu
-    [keys::O, keys::I], // oi: đói, còi
-    [keys::U, keys::I], // ui: túi, mùi
-    [keys::U, keys::A], // ua: mùa, cúa (not after q)
-    [keys::U, keys::U], // ưu: lưu, hưu (when first has horn)
-];
-
-/// Diphthongs with tone on SECOND vowel (âm đệm + chính, compound)
-///
-/// Section 7.3.1: oa, oă, oe, uê, uy, ua (after q), iê, uô, ươ
-pub const TONE_SECOND_PATTERNS: &[[u16; 2]] = &[
-    [keys::O, keys::A], // oa: hoà, toá
-    [keys::O, keys::E], // oe: khoẻ, xoè
-    [keys::U, keys::E], // uê: huế, tuệ
-    [keys::U, keys::Y], // uy: quý, thuỳ
-    [keys::I, keys::E], // iê: tiên (compound)
-    [keys::U, keys::O], // uô/ươ: (compound - when both have horn)
-];
-
-/// Triphthongs - all use middle (position 2) except uyê
-///
-/// Section 7.3.3
-pub const TRIPHTHONG_PATTERNS: &[TriphthongTonePattern] = &[
-    TriphthongTonePattern {
-        v1: keys::I,
-        v2: keys::E,
-        v3: keys::U,
-        position: TonePosition::Second,
-    }, // iêu: tiếu
-    TriphthongTonePattern {
-        v1: keys::Y,
-        v2: keys::E,
-        v3: keys::U,
-        position: TonePosition::Second,
-    }, // yêu: yếu
-    TriphthongTonePattern {
-        v1: keys::O,
-        v2: keys::A,
-        v3: keys::I,
-        position: TonePosition::Second,
-    }, // oai: ngoài
-    TriphthongTonePattern {
-        v1: keys::O,
-        v2: keys::A,
-        v3: keys::Y,
-        position: TonePosition::Second,
-    }, // oay: xoáy
-    TriphthongTonePattern {
-        v1: keys::O,
-        v2: keys::E,
-        v3: keys::O,
-        position: TonePosition::Second,
-    }, // oeo: khoèo
-    TriphthongTonePattern {
-        v1: keys::U,
-        v2: keys::A,
-        v3: keys::Y,
-        position: TonePosition::Second,
-    }, // uây: khuấy (â in middle)
-    TriphthongTonePattern {
-        v1: keys::U,
-        v2: keys::O,
-        v3: keys::I,
-        position: TonePosition::Second,
-    }, // uôi: cuối
-    TriphthongTonePattern {
-        v1: keys::U,
-        v2: keys::O,
-        v3: keys::I,
-        position: TonePosition::Second,
-    }, // ươi: mười (ư+ơ+i, both have horn)
-    TriphthongTonePattern {
-        v1: keys::U,
-        v2: keys::O,
-        v3: keys::U,
-        position: TonePosition::Second,
-    }, // ươu: rượu
-    TriphthongTonePattern {
-        v1: keys::I,
-        v2: keys::U,
-        v3: keys::O,
-        position: TonePosition::Last,
-    }, // iươ: giường (gi + ươ, tone on ơ)
-    // Special: uyê uses Last position
-    TriphthongTonePattern {
-        v1: keys::U,
-        v2: keys::Y,
-        v3: keys::E,
-        position: TonePosition::Last,
-    }, // uyê: khuyến, quyền
-];
-
 /// Vietnamese vowel phonology analyzer
 pub struct Phonology;
 
 impl Phonology {
     /// Find the position where tone mark should be placed
     ///
-    /// Uses data-driven pattern lookup from TONE_*_PATTERNS and TRIPHTHONG_PATTERNS.
-    /// See docs/vietnamese-language-system.md section 7.3 for the complete matrix.
-    ///
-    /// ## Rules Summary
-    /// 1. Single vowel: mark on it
-    /// 2. Diacritic priority: vowel with diacritic (ă,â,ê,ô,ơ,ư) gets the mark
-    /// 3. With final consonant: mark on 2nd vowel
-    /// 4. Open syllable: use pattern tables
-    /// 5. Triphthong: use TRIPHTHONG_PATTERNS (middle, except uyê → last)
+    /// Looks the nucleus up in `tone_rules::TONE_RULES`; see
+    /// docs/vietnamese-language-system.md section 7.3 for the matrix.
+    /// `modern` picks the style (hoà vs hòa).
     pub fn find_tone_position(
         vowels: &[Vowel],
         has_final_consonant: bool,
@@ -271,113 +179,19 @@ impl Phonology {
         has_qu_initial: bool,
         has_gi_initial: bool,
     ) -> usize {
+        let ctx = tone_rules::Context {
+            final_consonant: has_final_consonant,
+            qu_initial: has_qu_initial,
+            gi_initial: has_gi_initial,
+        };
         match vowels.len() {
             0 => 0,
-            1 => vowels[0].pos,
-            2 => Self::find_diphthong_position(
-                vowels,
-                has_final_consonant,
-                modern,
-                has_qu_initial,
-                has_gi_initial,
-            ),
-            3 => Self::find_triphthong_position(vowels),
+            1..=3 => tone_rules::place(tone_rules::TONE_RULES, vowels, &ctx, modern)
+                .unwrap_or(vowels[0].pos),
             _ => Self::find_default_position(vowels),
         }
     }
 
-    /// Find tone position for diphthongs (2 vowels)
-    fn find_diphthong_position(
-        vowels: &[Vowel],
-        has_final_consonant: bool,
-        modern: bool,
-        has_qu_initial: bool,
-        has_gi_initial: bool,
-    ) -> usize {
-        let (v1, v2) = (&vowels[0], &vowels[1]);
-
-        // Rule 1: With final consonant → always 2nd (Section 7.3.2)
-        if has_final_consonant {
-            return v2.pos;
-        }
-
-        // Rule 2: Diacritic priority (Section 7.2.5)
-        // - If 1st has diacritic and 2nd doesn't → 1st (ưa → ư)
-        // - If 2nd has diacritic → 2nd (iê → ê)
-        if v1.has_diacritic() && !v2.has_diacritic() {
-            return v1.pos;
-        }
-        if v2.has_diacritic() {
-            return v2.pos;
-        }
-
-        // Rule 3: Context-dependent patterns
-        // ia: 1st unless gi-initial (gia → a, kìa → i)
-        if v1.key == keys::I && v2.key == keys::A {
-            return if has_gi_initial { v2.pos } else { v1.pos };
-        }
-
-        // ua: 1st unless qu-initial (mùa → u, quá → a)
-        if v1.key == keys::U && v2.key == keys::A {
-            if has_qu_initial {
-                return if modern { v2.pos } else { v1.pos };
-            }
-            return v1.pos;
-        }
-
-        // Rule 4: Pattern table lookup
-        let pair = [v1.key, v2.key];
-
-        // Check TONE_SECOND_PATTERNS (medial + main, compound)
-        if TONE_SECOND_PATTERNS
-            .iter()
-            .any(|p| p[0] == pair[0] && p[1] == pair[1])
-        {
-            return if modern { v2.pos } else { v1.pos };
-        }
-
-        // Check TONE_FIRST_PATTERNS (main + glide)
-        if TONE_FIRST_PATTERNS
-            .iter()
-            .any(|p| p[0] == pair[0] && p[1] == pair[1])
-        {
-            return v1.pos;
-        }
-
-        // Default: 2nd vowel
-        v2.pos
-    }
-
-    /// Find tone position for triphthongs (3 vowels)
-    fn find_triphthong_position(vowels: &[Vowel]) -> usize {
-        let (k0, k1, k2) = (vowels[0].key, vowels[1].key, vowels[2].key);
-
-        // Rule 1: Pattern table lookup (takes priority)
-        // Patterns define exact tone positions for known triphthongs
-        for pattern in TRIPHTHONG_PATTERNS {
-            if k0 == pattern.v1 && k1 == pattern.v2 && k2 == pattern.v3 {
-                return match pattern.position {
-                    TonePosition::First => vowels[0].pos,
-                    TonePosition::Second => vowels[1].pos,
-                    TonePosition::Last => vowels[2].pos,
-                };
-            }
-        }
-
-        // Rule 2: Diacritic priority (for unmatched patterns)
-        // Middle with diacritic → middle (ươi: ơ has diacritic)
-        if vowels[1].has_diacritic() {
-            return vowels[1].pos;
-        }
-        // Last with diacritic → last (uyê: ê has diacritic)
-        if vowels[2].has_diacritic() {
-            return vowels[2].pos;
-        }
-
-        // Default: middle vowel
-        vowels[1].pos
-    }
-
     /// Find tone position for 4+ vowels (rare cases)
     fn find_default_position(vowels: &[Vowel]) -> usize {
         let mid = vowels.len() / 2;
@@ -443,7 +257,14 @@ impl Phonology {
                 // Match against pattern table
                 for pattern in HORN_PATTERNS {
                     if k1 == pattern.v1 && k2 == pattern.v2 {
+                        // "quo": the u belongs to the initial
+                        let qu = k1 == keys::U
+                            && pos1 > 0
+                            && buffer_keys.get(pos1 - 1) == Some(&keys::Q);
                         match pattern.placement {
+                            HornPlacement::Both if qu => {
+                                result.push(pos2);
+                            }
                             HornPlacement::Both => {
                                 result.push(pos1);
                                 result.push(pos2);
@@ -0,0 +1,25 @@
+//! Gõ Nhanh Vietnamese IME Engine
+//!
+//! The stateful half of the split: `Engine`, its settings and the `ime_*`
+//! FFI. Everything is `gonhanh-core` re-exported under the published
+//! name; the platform builds keep linking `gonhanh_core` directly.
+//!
+//! Users who only need the linguistic tables (keys, characters, vowel
+//! phonology) depend on `gonhanh-data` instead, which has no engine and
+//! is `no_std`.
+
+pub use gonhanh_core::*;
+
+#[cfg(test)]
+mod tests {
+    use super::data::keys;
+    use super::engine::Engine;
+    use super::utils::type_word;
+
+    #[test]
+    fn test_engine_reexported() {
+        let mut e = Engine::new();
+        assert_eq!(type_word(&mut e, "vieetj "), "việt ");
+        assert_eq!(keys::from_char('a'), Some(keys::A));
+    }
+}
@@ -0,0 +1,20 @@
+//! Arbitrary key streams: first byte picks the settings, each following
+//! byte is one key press (see `Press::from_byte`)
+
+#![no_main]
+
+use gonhanh_core::engine::Engine;
+use gonhanh_core_fuzz::{configure, Host, Press};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Some((&settings, presses)) = data.split_first() else {
+        return;
+    };
+    let mut engine = Engine::new();
+    configure(&mut engine, settings);
+    let mut host = Host::new(engine);
+    for &b in presses {
+        host.press(Press::from_byte(b));
+    }
+});
@@ -0,0 +1,42 @@
+//! Arbitrary UTF-8 seeded as the word before the caret, then typed on
+//!
+//! Layout: settings byte, key count `n`, `n` key bytes, then the text.
+
+#![no_main]
+
+use gonhanh_core::engine::Engine;
+use gonhanh_core_fuzz::{configure, Host, Press};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let [settings, n, rest @ ..] = data else {
+        return;
+    };
+    let (presses, text) = rest.split_at((*n as usize).min(rest.len()));
+    let Ok(text) = std::str::from_utf8(text) else {
+        return;
+    };
+
+    let mut engine = Engine::new();
+    configure(&mut engine, *settings);
+    let mut host = Host::new(engine);
+    host.insert(text);
+
+    // Only a word of Vietnamese letters is taken, and it reads back as is
+    if host.engine.seed_word(text) {
+        let info = host.engine.word_info().expect("seeded word has no info");
+        assert_eq!(info.text, text, "seeded word reads back differently");
+        assert!(!info.committed);
+    } else {
+        assert_eq!(host.engine.word_info(), None);
+    }
+
+    // The whole text as context: the seeded part must be on screen
+    host.engine.caret_moved(Some(text.encode_utf16().count()));
+    let seeded = host.engine.text_context(text);
+    assert!(seeded <= text.chars().count());
+
+    for &b in presses {
+        host.press(Press::from_byte(b));
+    }
+});
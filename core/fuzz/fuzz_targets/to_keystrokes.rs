@@ -0,0 +1,22 @@
+//! Arbitrary UTF-8 through reverse transliteration
+//!
+//! Keystrokes are always plain ASCII, and no longer than two keys per
+//! letter plus the mark key.
+
+#![no_main]
+
+use gonhanh_core::config::Method;
+use gonhanh_core::input::to_keystrokes;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(word) = std::str::from_utf8(data) else {
+        return;
+    };
+    for method in [Method::Telex, Method::Vni] {
+        if let Some(keys) = to_keystrokes(word, method) {
+            assert!(keys.is_ascii(), "{:?} -> {:?}", word, keys);
+            assert!(keys.len() <= 3 * word.chars().count() + 1);
+        }
+    }
+});
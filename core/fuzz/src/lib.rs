@@ -0,0 +1,250 @@
+//! Shared harness for the fuzz targets
+//!
+//! `Host` plays the platform layer over a plain text buffer, like
+//! `tests/common/field.rs`: it sends a key, applies the result at the caret
+//! and types pass-through keys itself. Each key is checked against what a
+//! host relies on; a broken invariant panics and the fuzzer reports it.
+
+use gonhanh_core::data::keys;
+use gonhanh_core::engine::buffer::MAX;
+use gonhanh_core::engine::output::{Delivery, OutputProfile};
+use gonhanh_core::engine::{Action, Engine};
+use gonhanh_core::utils::key_to_char;
+
+/// Keys the fuzzer picks from, besides letters and digits, with what the
+/// app inserts when one passes through
+const OTHER_KEYS: &[(u16, Option<char>)] = &[
+    (keys::SPACE, Some(' ')),
+    (keys::DELETE, None),
+    (keys::ESC, None),
+    (keys::DOT, Some('.')),
+    (keys::COMMA, Some(',')),
+    (keys::SLASH, Some('/')),
+    (keys::SEMICOLON, Some(';')),
+    (keys::QUOTE, Some('\'')),
+    (keys::LBRACKET, Some('[')),
+    (keys::RBRACKET, Some(']')),
+    (keys::MINUS, Some('-')),
+    (keys::RETURN, Some('\n')),
+    (keys::TAB, Some('\t')),
+    (keys::LEFT, None),
+    (keys::RIGHT, None),
+    (keys::FORWARD_DELETE, None),
+];
+
+const LETTERS_AND_DIGITS: &[u16] = &[
+    keys::A,
+    keys::B,
+    keys::C,
+    keys::D,
+    keys::E,
+    keys::F,
+    keys::G,
+    keys::H,
+    keys::I,
+    keys::J,
+    keys::K,
+    keys::L,
+    keys::M,
+    keys::N,
+    keys::O,
+    keys::P,
+    keys::Q,
+    keys::R,
+    keys::S,
+    keys::T,
+    keys::U,
+    keys::V,
+    keys::W,
+    keys::X,
+    keys::Y,
+    keys::Z,
+    keys::N0,
+    keys::N1,
+    keys::N2,
+    keys::N3,
+    keys::N4,
+    keys::N5,
+    keys::N6,
+    keys::N7,
+    keys::N8,
+    keys::N9,
+];
+
+/// One key press decoded from a fuzz byte
+#[derive(Clone, Copy, Debug)]
+pub struct Press {
+    pub key: u16,
+    pub typed: Option<char>,
+    pub caps: bool,
+    pub ctrl: bool,
+    pub shift: bool,
+}
+
+impl Press {
+    /// Low 6 bits pick the key (the slots past the table are Ctrl chords),
+    /// bit 6 = Shift, bit 7 = Caps
+    pub fn from_byte(b: u8) -> Self {
+        let count = LETTERS_AND_DIGITS.len() + OTHER_KEYS.len();
+        let slot = (b & 0x3f) as usize;
+        let ctrl = slot >= count;
+        let slot = slot % count;
+        let caps = b & 0x80 != 0;
+        let (key, typed) = match LETTERS_AND_DIGITS.get(slot) {
+            Some(&key) => (key, key_to_char(key, caps)),
+            None => OTHER_KEYS[slot - LETTERS_AND_DIGITS.len()],
+        };
+        Self {
+            key,
+            typed,
+            caps,
+            ctrl,
+            shift: b & 0x40 != 0,
+        }
+    }
+}
+
+/// Engine settings from the first fuzz byte
+pub fn configure(e: &mut Engine, b: u8) {
+    e.set_method(b & 1);
+    e.set_modern(b & 2 != 0);
+    e.set_auto_restore(b & 4 != 0);
+    e.set_bracket_shortcuts(b & 8 != 0);
+    let mut profile = match (b >> 4) & 3 {
+        1 => OutputProfile::SLOW,
+        2 => OutputProfile::BOUNDARY,
+        _ => OutputProfile::default(),
+    };
+    if b & 0x40 != 0 {
+        profile.delivery = Delivery::Select;
+    }
+    e.set_output_profile(profile);
+}
+
+/// Text field driven by the engine
+pub struct Host {
+    pub engine: Engine,
+    pub text: Vec<char>,
+    /// Caret as a char index into `text`
+    pub caret: usize,
+}
+
+impl Host {
+    pub fn new(engine: Engine) -> Self {
+        let mut host = Self {
+            engine,
+            text: Vec::new(),
+            caret: 0,
+        };
+        host.engine.set_caret(Some(0));
+        host
+    }
+
+    /// Put `s` in the field at the caret, the way a paste would
+    pub fn insert(&mut self, s: &str) {
+        for c in s.chars() {
+            self.text.insert(self.caret, c);
+            self.caret += 1;
+        }
+        let offset = self.utf16(self.caret);
+        self.engine.caret_moved(Some(offset));
+    }
+
+    pub fn press(&mut self, p: Press) {
+        let select = self.engine.output_profile().delivery == Delivery::Select;
+        let r = self.engine.on_key_ext(p.key, p.caps, p.ctrl, p.shift);
+
+        assert!(
+            r.action <= Action::Select as u8,
+            "unknown action {}",
+            r.action
+        );
+        assert!(r.count as usize <= MAX, "count {} over MAX", r.count);
+        let out: Vec<char> = r.chars[..r.count as usize]
+            .iter()
+            .map(|&c| char::from_u32(c).expect("output is not a char"))
+            .collect();
+
+        if r.action == Action::Select as u8 {
+            assert!(select, "Select without Delivery::Select");
+            assert!(r.backspace > 0, "Select with nothing to replace");
+            let start = self.caret - self.erasable(r.backspace);
+            assert_eq!(
+                self.engine.edit_range(),
+                Some(self.utf16(start)..self.utf16(self.caret)),
+                "edit range doesn't match the backspaces"
+            );
+            self.replace(start, &out);
+        } else if r.action == Action::Send as u8 {
+            let start = self.caret - self.erasable(r.backspace);
+            self.replace(start, &out);
+        } else {
+            assert_eq!(r.backspace, 0, "backspaces without Send");
+            assert_eq!(r.count, 0, "output without Send");
+        }
+
+        let consumed =
+            r.action != Action::None as u8 && (p.key == keys::SPACE || !keys::is_break(p.key));
+        if !consumed && !p.ctrl {
+            self.pass_through(p);
+        }
+
+        // Keys that lose the engine's caret get it re-reported
+        match self.engine.caret() {
+            Some(caret) => assert_eq!(
+                caret,
+                self.utf16(self.caret),
+                "engine caret drifted from the field"
+            ),
+            None => {
+                let offset = self.utf16(self.caret);
+                self.engine.caret_moved(Some(offset));
+            }
+        }
+    }
+
+    /// Backspaces must stay within the text before the caret
+    fn erasable(&self, backspace: u8) -> usize {
+        let n = backspace as usize;
+        assert!(
+            n <= self.caret,
+            "{} backspaces with only {} chars before the caret",
+            n,
+            self.caret
+        );
+        n
+    }
+
+    fn replace(&mut self, start: usize, out: &[char]) {
+        self.text.splice(start..self.caret, out.iter().copied());
+        self.caret = start + out.len();
+    }
+
+    fn pass_through(&mut self, p: Press) {
+        match p.key {
+            keys::DELETE => {
+                if self.caret > 0 {
+                    self.caret -= 1;
+                    self.text.remove(self.caret);
+                }
+            }
+            keys::FORWARD_DELETE => {
+                if self.caret < self.text.len() {
+                    self.text.remove(self.caret);
+                }
+            }
+            keys::LEFT => self.caret = self.caret.saturating_sub(1),
+            keys::RIGHT => self.caret = (self.caret + 1).min(self.text.len()),
+            _ => {
+                if let Some(c) = p.typed {
+                    self.text.insert(self.caret, c);
+                    self.caret += 1;
+                }
+            }
+        }
+    }
+
+    fn utf16(&self, pos: usize) -> usize {
+        self.text[..pos].iter().map(|c| c.len_utf16()).sum()
+    }
+}
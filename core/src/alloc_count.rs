@@ -0,0 +1,78 @@
+//! Allocation Counting
+//!
+//! A system allocator that counts heap allocations, for tests and benches
+//! that pin the hot path to zero (or near-zero) allocations per key. The
+//! library never installs it; a test or bench binary opts in:
+//!
+//! ```ignore
+//! use gonhanh_core::alloc_count::{allocs_during, CountingAllocator};
+//!
+//! #[global_allocator]
+//! static GLOBAL: CountingAllocator = CountingAllocator;
+//!
+//! let n = allocs_during(|| { engine.process_key(key, false, false, false); });
+//! assert_eq!(n, 0);
+//! ```
+//!
+//! Reallocations count as allocations, frees don't. Counts are kept per
+//! thread (parallel tests don't disturb each other) and process-wide.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+thread_local! {
+    // `const` init: no lazy setup that could itself allocate
+    static THREAD: Cell<usize> = const { Cell::new(0) };
+}
+
+static TOTAL: AtomicU64 = AtomicU64::new(0);
+
+/// `System` plus counters
+pub struct CountingAllocator;
+
+impl CountingAllocator {
+    fn count(&self) {
+        // Fails only while the thread is being torn down
+        let _ = THREAD.try_with(|n| n.set(n.get() + 1));
+        TOTAL.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        self.count();
+        System.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        self.count();
+        System.alloc_zeroed(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        self.count();
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+/// Allocations made by this thread so far
+pub fn thread_allocs() -> usize {
+    THREAD.with(Cell::get)
+}
+
+/// Allocations made by every thread so far
+pub fn total_allocs() -> u64 {
+    TOTAL.load(Ordering::Relaxed)
+}
+
+/// Allocations `f` made on this thread
+pub fn allocs_during(f: impl FnOnce()) -> usize {
+    let before = thread_allocs();
+    f();
+    thread_allocs() - before
+}
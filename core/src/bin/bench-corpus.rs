@@ -0,0 +1,356 @@
+//! Benchmark corpus replayer
+//!
+//! ```text
+//! bench-corpus [--repeat <n>] [<session.txt>...]
+//! bench-corpus --trace <session.txt>...
+//! ```
+//!
+//! Replays recorded typing sessions (default: every `bench/corpus/*.txt`)
+//! through the engine once per feature set and reports throughput, heap
+//! allocations per key, and the overhead of each feature over the baseline.
+//! Run it in release mode:
+//! `cargo run --release --features count-allocs --bin bench-corpus`.
+//!
+//! `--trace` replays each session once, untimed, and prints the engine
+//! state after every key (`Engine::debug_state`), to see where a session
+//! goes wrong.
+//!
+//! Input is deterministic, so the checksum over every engine result only
+//! changes when behavior does: a performance change that moves it is also a
+//! behavior change.
+//!
+//! Session format: UTF-8 text, typed one character per key. Each line ends
+//! with Return, `⌫` is Backspace, lines starting with `#` are comments and
+//! `# method: vni` switches the session to VNI (default Telex). Characters
+//! with no key on a US layout (emoji, precomposed Vietnamese) are skipped.
+
+use gonhanh_core::alloc_count::{self, CountingAllocator};
+use gonhanh_core::config::AutoCorrectMode;
+use gonhanh_core::data::keys;
+#[cfg(feature = "macros")]
+use gonhanh_core::engine::shortcut::Shortcut;
+use gonhanh_core::engine::Engine;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::Instant;
+
+const USAGE: &str = "usage: bench-corpus [--repeat <n>] [<session.txt>...]
+       bench-corpus --trace <session.txt>...";
+const DEFAULT_REPEAT: usize = 100;
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// One key press
+#[derive(Clone, Copy)]
+struct Key {
+    /// Character it was read from
+    typed: char,
+    key: u16,
+    caps: bool,
+    shift: bool,
+}
+
+struct Session {
+    name: String,
+    method: u8,
+    keys: Vec<Key>,
+    /// Characters with no key
+    skipped: usize,
+}
+
+/// Feature set to measure
+struct Pass {
+    name: &'static str,
+    setup: fn(&mut Engine),
+}
+
+const PASSES: &[Pass] = &[
+    Pass {
+        name: "base",
+        setup: |e| e.set_auto_correct(AutoCorrectMode::Off),
+    },
+    #[cfg(feature = "autocorrect")]
+    Pass {
+        name: "autocorrect",
+        setup: |e| e.set_auto_correct(AutoCorrectMode::Restore),
+    },
+    #[cfg(feature = "autocorrect")]
+    Pass {
+        name: "autocorrect-all",
+        setup: |e| e.set_auto_correct(AutoCorrectMode::All),
+    },
+    #[cfg(feature = "macros")]
+    Pass {
+        name: "shortcuts",
+        setup: |e| {
+            e.set_auto_correct(AutoCorrectMode::Off);
+            add_shortcuts(e);
+        },
+    },
+];
+
+/// A realistic table: a few common abbreviations plus bulk that never fires
+#[cfg(feature = "macros")]
+fn add_shortcuts(e: &mut Engine) {
+    let table = e.shortcuts_mut();
+    for (trigger, replacement) in [
+        ("vn", "Việt Nam"),
+        ("ko", "không"),
+        ("dc", "được"),
+        ("btw", "by the way"),
+    ] {
+        table.add(Shortcut::new(trigger, replacement));
+    }
+    for i in 0..500 {
+        table.add(Shortcut::new(&format!("zq{}", i), &format!("macro {}", i)));
+    }
+}
+
+fn main() -> ExitCode {
+    match run(std::env::args().skip(1).collect()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(msg) => {
+            eprintln!("bench-corpus: {}", msg);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: Vec<String>) -> Result<(), String> {
+    let mut repeat = DEFAULT_REPEAT;
+    let mut trace = false;
+    let mut files = Vec::new();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--repeat" => {
+                repeat = args
+                    .next()
+                    .and_then(|n| n.parse().ok())
+                    .filter(|&n| n > 0)
+                    .ok_or(USAGE)?;
+            }
+            "--trace" => trace = true,
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return Ok(());
+            }
+            _ if arg.starts_with('-') => return Err(USAGE.to_string()),
+            _ => files.push(PathBuf::from(arg)),
+        }
+    }
+    if trace && files.is_empty() {
+        return Err(USAGE.to_string());
+    }
+    if files.is_empty() {
+        files = default_corpus()?;
+    }
+
+    let sessions = files
+        .iter()
+        .map(|f| load(f))
+        .collect::<Result<Vec<_>, _>>()?;
+    if trace {
+        sessions.iter().for_each(print_trace);
+        return Ok(());
+    }
+
+    println!(
+        "{:<10} {:>9} {:<16} {:>9} {:>8} {:>10} {:>9} {:>9}",
+        "session", "keys", "features", "Mkeys/s", "ns/key", "allocs/key", "overhead", "checksum"
+    );
+    for session in &sessions {
+        let total = session.keys.len() * repeat;
+        let mut base_ns = None;
+        for pass in PASSES {
+            let r = replay(session, pass, repeat);
+            let ns = r.nanos as f64 / total as f64;
+            let overhead = match base_ns {
+                None => {
+                    base_ns = Some(ns);
+                    "-".to_string()
+                }
+                Some(base) => format!("{:+.1}%", (ns / base - 1.0) * 100.0),
+            };
+            println!(
+                "{:<10} {:>9} {:<16} {:>9.2} {:>8.1} {:>10.3} {:>9} {:>9}",
+                session.name,
+                total,
+                pass.name,
+                1e3 / ns,
+                ns,
+                r.allocs as f64 / total as f64,
+                overhead,
+                format!("{:08x}", r.checksum as u32),
+            );
+        }
+        if session.skipped > 0 {
+            println!(
+                "{:<10} ({} character(s) with no key skipped)",
+                "", session.skipped
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Replay once with the default settings, printing the state after each key
+fn print_trace(session: &Session) {
+    let mut engine = Engine::new();
+    engine
+        .set_method(session.method)
+        .expect("default settings fit either method");
+    for k in &session.keys {
+        engine.on_key_ext(k.key, k.caps, false, k.shift);
+        println!("# {} {:?}", session.name, k.typed);
+        print!("{}", engine.debug_state().to_json().to_pretty());
+    }
+}
+
+fn default_corpus() -> Result<Vec<PathBuf>, String> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("bench/corpus");
+    let entries = std::fs::read_dir(&dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    let mut files: Vec<_> = entries
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.extension().is_some_and(|x| x == "txt"))
+        .collect();
+    files.sort();
+    if files.is_empty() {
+        return Err(format!("no sessions in {}", dir.display()));
+    }
+    Ok(files)
+}
+
+fn load(path: &Path) -> Result<Session, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let mut session = Session {
+        name: path
+            .file_stem()
+            .map_or_else(String::new, |s| s.to_string_lossy().into_owned()),
+        method: 0,
+        keys: Vec::new(),
+        skipped: 0,
+    };
+    for line in text.lines() {
+        if let Some(comment) = line.strip_prefix('#') {
+            if let Some(method) = comment.trim().strip_prefix("method:") {
+                session.method = match method.trim() {
+                    "telex" => 0,
+                    "vni" => 1,
+                    other => return Err(format!("{}: unknown method `{}`", path.display(), other)),
+                };
+            }
+            continue;
+        }
+        for c in line.chars().chain(['\n']) {
+            match key_for(c) {
+                Some(k) => session.keys.push(k),
+                None => session.skipped += 1,
+            }
+        }
+    }
+    Ok(session)
+}
+
+/// Key for a character on a US layout
+fn key_for(c: char) -> Option<Key> {
+    if let Some(key) = keys::from_char(c) {
+        let upper = c.is_ascii_uppercase();
+        return Some(Key {
+            typed: c,
+            key,
+            caps: upper,
+            shift: upper,
+        });
+    }
+    let (key, shift) = match c {
+        '\n' => (keys::RETURN, false),
+        '\t' => (keys::TAB, false),
+        '⌫' => (keys::DELETE, false),
+        '.' => (keys::DOT, false),
+        ',' => (keys::COMMA, false),
+        ';' => (keys::SEMICOLON, false),
+        '\'' => (keys::QUOTE, false),
+        '-' => (keys::MINUS, false),
+        '=' => (keys::EQUAL, false),
+        '[' => (keys::LBRACKET, false),
+        ']' => (keys::RBRACKET, false),
+        '\\' => (keys::BACKSLASH, false),
+        '/' => (keys::SLASH, false),
+        '`' => (keys::BACKQUOTE, false),
+        '!' => (keys::N1, true),
+        '@' => (keys::N2, true),
+        '#' => (keys::N3, true),
+        '$' => (keys::N4, true),
+        '%' => (keys::N5, true),
+        '^' => (keys::N6, true),
+        '&' => (keys::N7, true),
+        '*' => (keys::N8, true),
+        '(' => (keys::N9, true),
+        ')' => (keys::N0, true),
+        '_' => (keys::MINUS, true),
+        '+' => (keys::EQUAL, true),
+        '{' => (keys::LBRACKET, true),
+        '}' => (keys::RBRACKET, true),
+        '|' => (keys::BACKSLASH, true),
+        ':' => (keys::SEMICOLON, true),
+        '"' => (keys::QUOTE, true),
+        '<' => (keys::COMMA, true),
+        '>' => (keys::DOT, true),
+        '?' => (keys::SLASH, true),
+        '~' => (keys::BACKQUOTE, true),
+        _ => return None,
+    };
+    Some(Key {
+        typed: c,
+        key,
+        caps: false,
+        shift,
+    })
+}
+
+struct Measured {
+    nanos: u128,
+    allocs: u64,
+    checksum: u64,
+}
+
+fn replay(session: &Session, pass: &Pass, repeat: usize) -> Measured {
+    let mut engine = Engine::new();
+    engine
+        .set_method(session.method)
+        .expect("default settings fit either method");
+    (pass.setup)(&mut engine);
+
+    // One untimed round so tables and caches are warm
+    for k in &session.keys {
+        engine.on_key_ext(k.key, k.caps, false, k.shift);
+    }
+    engine.clear();
+
+    // FNV-1a over every result
+    let mut checksum: u64 = 0xcbf2_9ce4_8422_2325;
+    let mut mix = |n: u32| {
+        for b in n.to_le_bytes() {
+            checksum = (checksum ^ b as u64).wrapping_mul(0x0100_0000_01b3);
+        }
+    };
+    let allocs = alloc_count::total_allocs();
+    let start = Instant::now();
+    for _ in 0..repeat {
+        for k in &session.keys {
+            let r = engine.on_key_ext(k.key, k.caps, false, k.shift);
+            mix(u32::from_le_bytes([r.action, r.backspace, r.count, 0]));
+            for &c in &r.chars[..r.count as usize] {
+                mix(c);
+            }
+        }
+    }
+    let nanos = start.elapsed().as_nanos();
+    Measured {
+        nanos,
+        allocs: alloc_count::total_allocs() - allocs,
+        checksum,
+    }
+}
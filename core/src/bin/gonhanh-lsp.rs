@@ -0,0 +1,390 @@
+//! Language server for Vietnamese spelling
+//!
+//! ```text
+//! gonhanh-lsp [--dir <dir>]
+//! ```
+//!
+//! Speaks the Language Server Protocol over stdin/stdout so editors (VS
+//! Code, Neovim, Helix...) underline what `lint` finds: misspelled words,
+//! correction pack fixes and i/y spelled unlike the rest of the document.
+//! Each issue with a suggestion gets a quick fix.
+//!
+//! Only a small subset is spoken: `initialize`, `shutdown`, `exit`, full
+//! document sync (`didOpen`, `didChange`, `didClose`), published
+//! diagnostics and `textDocument/codeAction`. Settings (correction packs)
+//! come from the user's saved config, as the IME has them (default:
+//! platform config directory).
+//!
+//! Build with `cargo build --release --features lsp --bin gonhanh-lsp`.
+
+use gonhanh_core::engine::Engine;
+use gonhanh_core::json::{self, Value};
+use gonhanh_core::lint::{LintIssue, LintKind};
+use gonhanh_core::storage::FileStorage;
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+const USAGE: &str = "usage: gonhanh-lsp [--dir <dir>]";
+
+// JSON-RPC error codes
+const METHOD_NOT_FOUND: f64 = -32601.0;
+const INVALID_REQUEST: f64 = -32600.0;
+
+// LSP `DiagnosticSeverity`
+const WARNING: f64 = 2.0;
+const INFORMATION: f64 = 3.0;
+
+// LSP `TextDocumentSyncKind.Full`
+const SYNC_FULL: f64 = 1.0;
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let dir = match args.as_slice() {
+        [] => FileStorage::default_dir(),
+        [flag, dir] if flag == "--dir" => Some(PathBuf::from(dir)),
+        [flag] if flag == "-h" || flag == "--help" => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        _ => {
+            eprintln!("gonhanh-lsp: {}", USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut engine = Engine::new();
+    if let Some(dir) = dir {
+        engine.set_storage(FileStorage::new(&dir));
+        // Defaults are fine for linting; say why settings were skipped
+        if let Err(e) = engine.load() {
+            eprintln!("gonhanh-lsp: settings not loaded: {}", e);
+        }
+    }
+
+    let mut server = Server {
+        engine,
+        docs: HashMap::new(),
+        shutdown: false,
+    };
+    let stdin = io::stdin();
+    let mut input = stdin.lock();
+    let stdout = io::stdout();
+    let mut output = stdout.lock();
+    loop {
+        let message = match read_message(&mut input) {
+            Ok(Some(m)) => m,
+            // Client went away without `exit`
+            Ok(None) => return ExitCode::FAILURE,
+            Err(e) => {
+                eprintln!("gonhanh-lsp: {}", e);
+                return ExitCode::FAILURE;
+            }
+        };
+        if message.get("method").and_then(Value::as_str) == Some("exit") {
+            return if server.shutdown {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::FAILURE
+            };
+        }
+        for reply in server.handle(&message) {
+            if let Err(e) = write_message(&mut output, &reply) {
+                eprintln!("gonhanh-lsp: {}", e);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+}
+
+struct Server {
+    engine: Engine,
+    /// Open documents by URI
+    docs: HashMap<String, String>,
+    shutdown: bool,
+}
+
+impl Server {
+    /// Messages to send back for `message`: a response to a request, and
+    /// diagnostics for documents that changed
+    fn handle(&mut self, message: &Value) -> Vec<Value> {
+        let id = message.get("id").cloned();
+        let params = message.get("params").unwrap_or(&Value::Null);
+        let Some(method) = message.get("method").and_then(Value::as_str) else {
+            // A response to us (we send no requests) or garbage
+            return match id {
+                Some(id) => vec![error(id, INVALID_REQUEST, "no method")],
+                None => Vec::new(),
+            };
+        };
+        let uri = params
+            .get("textDocument")
+            .and_then(|d| d.get("uri"))
+            .and_then(Value::as_str)
+            .map(str::to_string);
+
+        match (method, id) {
+            ("initialize", Some(id)) => vec![response(id, capabilities())],
+            ("shutdown", Some(id)) => {
+                self.shutdown = true;
+                vec![response(id, Value::Null)]
+            }
+            ("textDocument/codeAction", Some(id)) => {
+                let actions = uri.map_or_else(Vec::new, |uri| self.code_actions(&uri, params));
+                vec![response(id, Value::Arr(actions))]
+            }
+            ("textDocument/didOpen", None) => {
+                let text = params
+                    .get("textDocument")
+                    .and_then(|d| d.get("text"))
+                    .and_then(Value::as_str);
+                match (uri, text) {
+                    (Some(uri), Some(text)) => self.update(uri, text.to_string()),
+                    _ => Vec::new(),
+                }
+            }
+            ("textDocument/didChange", None) => {
+                // Full sync: the last change holds the whole text
+                let text = params
+                    .get("contentChanges")
+                    .and_then(Value::as_array)
+                    .and_then(|changes| changes.last())
+                    .and_then(|c| c.get("text"))
+                    .and_then(Value::as_str);
+                match (uri, text) {
+                    (Some(uri), Some(text)) => self.update(uri, text.to_string()),
+                    _ => Vec::new(),
+                }
+            }
+            ("textDocument/didClose", None) => match uri {
+                Some(uri) => {
+                    self.docs.remove(&uri);
+                    vec![publish(&uri, Vec::new())]
+                }
+                None => Vec::new(),
+            },
+            (_, Some(id)) => vec![error(id, METHOD_NOT_FOUND, method)],
+            // Notifications we don't handle (`initialized`, `$/...`)
+            (_, None) => Vec::new(),
+        }
+    }
+
+    /// Store `text` for `uri` and lint it
+    fn update(&mut self, uri: String, text: String) -> Vec<Value> {
+        let diagnostics = self
+            .engine
+            .lint(&text)
+            .iter()
+            .map(|issue| diagnostic(&text, issue))
+            .collect();
+        let reply = publish(&uri, diagnostics);
+        self.docs.insert(uri, text);
+        vec![reply]
+    }
+
+    /// Quick fixes for the issues in the requested range
+    fn code_actions(&self, uri: &str, params: &Value) -> Vec<Value> {
+        let Some(text) = self.docs.get(uri) else {
+            return Vec::new();
+        };
+        let range = params.get("range");
+        let start = range
+            .and_then(|r| r.get("start"))
+            .and_then(|p| offset(text, p))
+            .unwrap_or(0);
+        let end = range
+            .and_then(|r| r.get("end"))
+            .and_then(|p| offset(text, p))
+            .unwrap_or(text.len());
+        let mut actions = Vec::new();
+        for issue in self.engine.lint(text) {
+            if issue.span.end < start || issue.span.start > end {
+                continue;
+            }
+            let range = lsp_range(text, &issue);
+            for fix in &issue.suggestions {
+                let edit = obj(vec![
+                    ("range", range.clone()),
+                    ("newText", Value::Str(fix.clone())),
+                ]);
+                actions.push(obj(vec![
+                    ("title", Value::Str(format!("Đổi thành “{}”", fix))),
+                    ("kind", Value::Str("quickfix".into())),
+                    ("diagnostics", Value::Arr(vec![diagnostic(text, &issue)])),
+                    (
+                        "edit",
+                        obj(vec![(
+                            "changes",
+                            Value::Obj(vec![(uri.to_string(), Value::Arr(vec![edit]))]),
+                        )]),
+                    ),
+                ]));
+            }
+        }
+        actions
+    }
+}
+
+fn capabilities() -> Value {
+    obj(vec![
+        (
+            "capabilities",
+            obj(vec![
+                ("textDocumentSync", Value::Num(SYNC_FULL)),
+                ("codeActionProvider", Value::Bool(true)),
+            ]),
+        ),
+        (
+            "serverInfo",
+            obj(vec![
+                ("name", Value::Str("gonhanh-lsp".into())),
+                ("version", Value::Str(env!("CARGO_PKG_VERSION").into())),
+            ]),
+        ),
+    ])
+}
+
+fn diagnostic(text: &str, issue: &LintIssue) -> Value {
+    let severity = match issue.kind {
+        LintKind::IySpelling => INFORMATION,
+        LintKind::Misspelled(_) | LintKind::Correction => WARNING,
+    };
+    let mut message = issue.kind.message().to_string();
+    if !issue.suggestions.is_empty() {
+        message.push_str(&format!(" (→ {})", issue.suggestions.join(", ")));
+    }
+    obj(vec![
+        ("range", lsp_range(text, issue)),
+        ("severity", Value::Num(severity)),
+        ("code", Value::Str(issue.kind.as_str().into())),
+        ("source", Value::Str("gonhanh".into())),
+        ("message", Value::Str(message)),
+    ])
+}
+
+fn publish(uri: &str, diagnostics: Vec<Value>) -> Value {
+    obj(vec![
+        ("jsonrpc", Value::Str("2.0".into())),
+        (
+            "method",
+            Value::Str("textDocument/publishDiagnostics".into()),
+        ),
+        (
+            "params",
+            obj(vec![
+                ("uri", Value::Str(uri.into())),
+                ("diagnostics", Value::Arr(diagnostics)),
+            ]),
+        ),
+    ])
+}
+
+fn response(id: Value, result: Value) -> Value {
+    obj(vec![
+        ("jsonrpc", Value::Str("2.0".into())),
+        ("id", id),
+        ("result", result),
+    ])
+}
+
+fn error(id: Value, code: f64, message: &str) -> Value {
+    obj(vec![
+        ("jsonrpc", Value::Str("2.0".into())),
+        ("id", id),
+        (
+            "error",
+            obj(vec![
+                ("code", Value::Num(code)),
+                ("message", Value::Str(message.into())),
+            ]),
+        ),
+    ])
+}
+
+fn obj(fields: Vec<(&str, Value)>) -> Value {
+    Value::Obj(
+        fields
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v))
+            .collect(),
+    )
+}
+
+/// LSP range of an issue: lines, and columns in UTF-16 units
+fn lsp_range(text: &str, issue: &LintIssue) -> Value {
+    obj(vec![
+        ("start", position(text, issue.span.start)),
+        ("end", position(text, issue.span.end)),
+    ])
+}
+
+fn position(text: &str, byte: usize) -> Value {
+    let before = &text[..byte];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    obj(vec![
+        ("line", Value::Num(before.matches('\n').count() as f64)),
+        (
+            "character",
+            Value::Num(before[line_start..].encode_utf16().count() as f64),
+        ),
+    ])
+}
+
+/// Byte offset of an LSP position, clamped to the text
+fn offset(text: &str, position: &Value) -> Option<usize> {
+    let line = position.get("line")?.as_u64()? as usize;
+    let character = position.get("character")?.as_u64()? as usize;
+    let line_start = match line.checked_sub(1) {
+        None => 0,
+        Some(n) => match text.match_indices('\n').nth(n) {
+            Some((i, _)) => i + 1,
+            None => return Some(text.len()),
+        },
+    };
+    let mut units = 0;
+    for (i, c) in text[line_start..].char_indices() {
+        if units >= character || c == '\n' {
+            return Some(line_start + i);
+        }
+        units += c.len_utf16();
+    }
+    Some(text.len())
+}
+
+/// Next message, or `None` at end of input
+fn read_message(input: &mut impl BufRead) -> io::Result<Option<Value>> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+    let length = length.ok_or_else(|| invalid("missing Content-Length"))?;
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    let body = String::from_utf8(body).map_err(|_| invalid("body is not UTF-8"))?;
+    json::parse(&body)
+        .map(Some)
+        .map_err(|e| invalid(&e.to_string()))
+}
+
+fn write_message(output: &mut impl Write, message: &Value) -> io::Result<()> {
+    let body = message.to_pretty();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
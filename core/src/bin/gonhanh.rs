@@ -0,0 +1,159 @@
+//! Gõ Nhanh command-line tool
+//!
+//! ```text
+//! gonhanh import <unikey|csv|evkey|openkey> <file> [--dir <dir>]
+//! gonhanh export <unikey|csv> [<file>] [--dir <dir>]
+//! gonhanh retone <modern|classic> [<file>]
+//! ```
+//!
+//! `import` reads the user's saved settings (default: platform config
+//! directory), merges the imported file and saves the result.
+//!
+//! `export` writes the saved shortcuts as a macro file (default: stdout).
+//!
+//! `retone` prints the file (default: stdin) with tone marks moved to the
+//! modern (hoà, thuý) or classic (hòa, thúy) place.
+
+use gonhanh_core::config::ToneStyle;
+use gonhanh_core::engine::Engine;
+use gonhanh_core::importer::ImportFormat;
+use gonhanh_core::selection::retone;
+use gonhanh_core::storage::FileStorage;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+const USAGE: &str = "usage: gonhanh import <unikey|csv|evkey|openkey> <file> [--dir <dir>]
+       gonhanh export <unikey|csv> [<file>] [--dir <dir>]
+       gonhanh retone <modern|classic> [<file>]";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = match args.first().map(String::as_str) {
+        Some("import") => import(&args[1..]),
+        Some("export") => export(&args[1..]),
+        Some("retone") => retone_file(&args[1..]),
+        Some("-h" | "--help") => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        _ => Err(USAGE.to_string()),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(msg) => {
+            eprintln!("gonhanh: {}", msg);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn import(args: &[String]) -> Result<(), String> {
+    let (format, file, dir) = match args {
+        [format, file] => (format, file, None),
+        [format, file, flag, dir] if flag == "--dir" => (format, file, Some(PathBuf::from(dir))),
+        _ => return Err(USAGE.to_string()),
+    };
+    let format =
+        ImportFormat::parse(format).ok_or_else(|| format!("unknown format `{}`", format))?;
+    let dir = dir
+        .or_else(FileStorage::default_dir)
+        .ok_or("no config directory; pass --dir")?;
+    let bytes = std::fs::read(file).map_err(|e| format!("{}: {}", file, e))?;
+
+    let mut engine = saved_engine(&dir)?;
+    let report = engine
+        .import_from(format, &bytes)
+        .map_err(|e| format!("{}: {}", file, e))?;
+    engine.save().map_err(|e| e.to_string())?;
+
+    println!(
+        "imported {} shortcut(s) from {} into {}",
+        report.shortcuts,
+        format.as_str(),
+        dir.display()
+    );
+    if !report.skipped.is_empty() {
+        println!(
+            "skipped unsupported settings: {}",
+            report.skipped.join(", ")
+        );
+    }
+    Ok(())
+}
+
+fn export(args: &[String]) -> Result<(), String> {
+    let (format, rest) = args.split_first().ok_or(USAGE)?;
+    let (file, dir) = match rest {
+        [] => (None, None),
+        [file] => (Some(file), None),
+        [flag, dir] if flag == "--dir" => (None, Some(PathBuf::from(dir))),
+        [file, flag, dir] if flag == "--dir" => (Some(file), Some(PathBuf::from(dir))),
+        _ => return Err(USAGE.to_string()),
+    };
+    let format =
+        ImportFormat::parse(format).ok_or_else(|| format!("unknown format `{}`", format))?;
+    let dir = dir
+        .or_else(FileStorage::default_dir)
+        .ok_or("no config directory; pass --dir")?;
+
+    let engine = saved_engine(&dir)?;
+    let exported = engine
+        .export_macros(format)
+        .ok_or_else(|| format!("{} holds settings, not shortcuts", format.as_str()))?;
+    match file {
+        Some(file) => {
+            std::fs::write(file, &exported.text).map_err(|e| format!("{}: {}", file, e))?
+        }
+        None => io::stdout()
+            .write_all(exported.text.as_bytes())
+            .map_err(|e| format!("stdout: {}", e))?,
+    }
+    if !exported.skipped.is_empty() {
+        eprintln!(
+            "gonhanh: {} can't hold these shortcuts, skipped: {}",
+            format.as_str(),
+            exported.skipped.join(", ")
+        );
+    }
+    Ok(())
+}
+
+/// Engine with the settings and shortcuts saved in `dir`
+fn saved_engine(dir: &Path) -> Result<Engine, String> {
+    let mut engine = Engine::new();
+    engine.set_storage(FileStorage::new(dir));
+    engine.load().map_err(|e| e.to_string())?;
+    for name in &engine.state_health().reset {
+        eprintln!(
+            "gonhanh: warning: {} was unreadable and reset to defaults",
+            name
+        );
+    }
+    if let Some(err) = &engine.state_health().config_error {
+        eprintln!("gonhanh: warning: {}; reset to the default", err);
+    }
+    Ok(engine)
+}
+
+fn retone_file(args: &[String]) -> Result<(), String> {
+    let (style, file) = match args {
+        [style] => (style, None),
+        [style, file] => (style, Some(file)),
+        _ => return Err(USAGE.to_string()),
+    };
+    let style = ToneStyle::parse(style).ok_or_else(|| format!("unknown style `{}`", style))?;
+    let text = match file {
+        Some(file) => std::fs::read_to_string(file).map_err(|e| format!("{}: {}", file, e))?,
+        None => {
+            let mut text = String::new();
+            io::stdin()
+                .read_to_string(&mut text)
+                .map_err(|e| format!("stdin: {}", e))?;
+            text
+        }
+    };
+    io::stdout()
+        .write_all(retone(&text, style).as_bytes())
+        .map_err(|e| format!("stdout: {}", e))
+}
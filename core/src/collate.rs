@@ -0,0 +1,181 @@
+//! Vietnamese Collation
+//!
+//! Dictionary order for candidate lists and user dictionaries. Letters
+//! sort by the Vietnamese alphabet, where modified letters are letters of
+//! their own:
+//!
+//! ```text
+//! a ă â b c d đ e ê f g h i j k l m n o ô ơ p q r s t u ư v w x y z
+//! ```
+//!
+//! Marks only break ties, in dictionary order (ngang, huyền, hỏi, ngã,
+//! sắc, nặng: ba < bà < bả < bã < bá < bạ < bác), then case (lowercase
+//! first). Digits, spaces and punctuation sort before letters, other
+//! scripts after them.
+
+use std::cmp::Ordering;
+
+use crate::data::chars::{self, mark, tone};
+use crate::data::keys;
+
+/// Letters in alphabet order: key, modifier, stroke
+const ALPHABET: [(u16, u8, bool); 33] = [
+    (keys::A, tone::NONE, false),
+    (keys::A, tone::HORN, false),
+    (keys::A, tone::CIRCUMFLEX, false),
+    (keys::B, tone::NONE, false),
+    (keys::C, tone::NONE, false),
+    (keys::D, tone::NONE, false),
+    (keys::D, tone::NONE, true),
+    (keys::E, tone::NONE, false),
+    (keys::E, tone::CIRCUMFLEX, false),
+    (keys::F, tone::NONE, false),
+    (keys::G, tone::NONE, false),
+    (keys::H, tone::NONE, false),
+    (keys::I, tone::NONE, false),
+    (keys::J, tone::NONE, false),
+    (keys::K, tone::NONE, false),
+    (keys::L, tone::NONE, false),
+    (keys::M, tone::NONE, false),
+    (keys::N, tone::NONE, false),
+    (keys::O, tone::NONE, false),
+    (keys::O, tone::CIRCUMFLEX, false),
+    (keys::O, tone::HORN, false),
+    (keys::P, tone::NONE, false),
+    (keys::Q, tone::NONE, false),
+    (keys::R, tone::NONE, false),
+    (keys::S, tone::NONE, false),
+    (keys::T, tone::NONE, false),
+    (keys::U, tone::NONE, false),
+    (keys::U, tone::HORN, false),
+    (keys::V, tone::NONE, false),
+    (keys::W, tone::NONE, false),
+    (keys::X, tone::NONE, false),
+    (keys::Y, tone::NONE, false),
+    (keys::Z, tone::NONE, false),
+];
+
+/// Mark weights in dictionary order, indexed by `mark` value
+const MARK_ORDER: [u32; 6] = [
+    1, // ngang
+    5, // sắc
+    2, // huyền
+    3, // hỏi
+    4, // ngã
+    6, // nặng
+];
+
+/// First primary weight of letters: everything below is ASCII that isn't
+/// a letter
+const LETTERS: u32 = 0x80;
+/// First primary weight of characters outside the Vietnamese alphabet
+const OTHER: u32 = 0x100;
+
+/// Comparable key: compare keys instead of strings when sorting many times
+/// (a database index, a cached candidate list)
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SortKey(Vec<u32>);
+
+impl SortKey {
+    /// Weights: letters, 0, marks, 0, case
+    pub fn weights(&self) -> &[u32] {
+        &self.0
+    }
+}
+
+/// Weights of one character: (primary, mark, case)
+fn weights(c: char) -> (u32, u32, u32) {
+    let case = if c.is_uppercase() { 2 } else { 1 };
+    let letter = chars::parse_char(c).and_then(|p| {
+        let i = ALPHABET
+            .iter()
+            .position(|&(k, t, s)| k == p.key && t == p.tone && s == p.stroke)?;
+        Some((LETTERS + i as u32, MARK_ORDER[p.mark as usize]))
+    });
+    match letter {
+        Some((primary, mark)) => (primary, mark, case),
+        None if c.is_ascii() => (c as u32 + 1, MARK_ORDER[mark::NONE as usize], case),
+        None => (OTHER + c as u32, MARK_ORDER[mark::NONE as usize], case),
+    }
+}
+
+/// Sort key of `s`
+pub fn sort_key(s: &str) -> SortKey {
+    let w: Vec<_> = s.chars().map(weights).collect();
+    let mut key = Vec::with_capacity(w.len() * 3 + 2);
+    key.extend(w.iter().map(|w| w.0));
+    key.push(0);
+    key.extend(w.iter().map(|w| w.1));
+    key.push(0);
+    key.extend(w.iter().map(|w| w.2));
+    SortKey(key)
+}
+
+/// Compare two strings in Vietnamese dictionary order
+pub fn compare(a: &str, b: &str) -> Ordering {
+    let (wa, wb): (Vec<_>, Vec<_>) = (
+        a.chars().map(weights).collect(),
+        b.chars().map(weights).collect(),
+    );
+    let level = |f: fn(&(u32, u32, u32)) -> u32| wa.iter().map(f).cmp(wb.iter().map(f));
+    level(|w| w.0)
+        .then_with(|| level(|w| w.1))
+        .then_with(|| level(|w| w.2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(words: &[&str]) -> Vec<String> {
+        let mut v: Vec<String> = words.iter().map(|s| s.to_string()).collect();
+        v.sort_by(|a, b| compare(a, b));
+        v
+    }
+
+    #[test]
+    fn test_alphabet() {
+        assert_eq!(
+            sorted(&[
+                "đi", "ăn", "bé", "anh", "ông", "ơi", "em", "dạ", "ê", "ước", "uống"
+            ]),
+            ["anh", "ăn", "bé", "dạ", "đi", "em", "ê", "ông", "ơi", "uống", "ước"]
+        );
+    }
+
+    #[test]
+    fn test_marks_break_ties() {
+        assert_eq!(
+            sorted(&["bạ", "bác", "bá", "bã", "bả", "bà", "ba"]),
+            ["ba", "bà", "bả", "bã", "bá", "bạ", "bác"]
+        );
+        // Letters first: "bà" < "bac" even though à sorts after a
+        assert_eq!(sorted(&["bac", "bà"]), ["bà", "bac"]);
+    }
+
+    #[test]
+    fn test_case_last() {
+        assert_eq!(sorted(&["Việt", "việt", "viết"]), ["viết", "việt", "Việt"]);
+        assert_eq!(compare("Đ", "đ"), Ordering::Greater);
+    }
+
+    #[test]
+    fn test_others() {
+        assert_eq!(
+            sorted(&["a", "1", " b", "日本", "z"]),
+            [" b", "1", "a", "z", "日本"]
+        );
+    }
+
+    #[test]
+    fn test_sort_key_agrees() {
+        let words = [
+            "ba", "bà", "Bà", "bác", "bắc", "bấc", "đá", "da", "a b", "ab",
+        ];
+        for a in words {
+            for b in words {
+                assert_eq!(sort_key(a).cmp(&sort_key(b)), compare(a, b), "{} {}", a, b);
+            }
+        }
+    }
+}
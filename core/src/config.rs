@@ -0,0 +1,1699 @@
+//! Engine configuration
+//!
+//! One canonical settings struct shared by all hosts (macOS, Windows, Linux)
+//! and the CLI. Serialized as a flat TOML subset (`key = value`, `# comments`)
+//! with a hand-written reader/writer, so the default build stays dependency-free.
+//! Enable the `serde` feature for `Serialize`/`Deserialize` derives.
+//!
+//! ```toml
+//! method = "telex"
+//! enabled = true
+//! tone_style = "modern"
+//! auto_correct = "restore"
+//! bracket_shortcuts = false
+//! money_shorthand = false
+//! tab_shortcuts = false
+//! max_word_len = 32
+//! overflow = "pass-through"
+//! remove_key = "default"
+//! uo_compound = true
+//! correction_packs = ""
+//! iy_style = "off"
+//! correction_style = "replace"
+//! accept_key = "tab"
+//! nbsp = "off"
+//! nbsp_chord = "shift-space"
+//! idle_commit_secs = 0
+//! mouse_click = "commit"
+//! mouse_scroll = "keep"
+//! repeat_key = "raw"
+//! gestures = ""
+//! feedback = false
+//! feedback_gap_ms = 80
+//! edit_limit = 40
+//! restore_order = "invalid,modifier-consonant,w-final,modifier-between-vowels,mark-final,w-initial,modifier-vowel-pair,english-suffix,bigram,dictionary"
+//! restore_disabled = ""
+//! bigram_threshold = 15
+//! min_confidence = 0
+//! ```
+//!
+//! Loading never falls back to defaults silently: unknown keys, bad values
+//! and conflicting options are reported as `ConfigError`.
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Largest `max_word_len`: the typing buffer keeps one more slot for the
+/// character that overflows it
+pub const MAX_WORD_LEN: u8 = crate::engine::buffer::MAX as u8 - 1;
+
+/// Default `max_word_len`: longer than any Vietnamese or common English word
+pub const DEFAULT_MAX_WORD_LEN: u8 = 32;
+
+/// Default `feedback_gap_ms`: a few words a second still get a sound each
+pub const DEFAULT_FEEDBACK_GAP_MS: u8 = 80;
+
+/// Default `edit_limit`: twice the keys per second of the fastest typists
+pub const DEFAULT_EDIT_LIMIT: u8 = 40;
+
+/// Default `bigram_threshold`: 1.5 bits per letter pair, well above every
+/// Telex word in `data/lexicon/vietnamese.txt` and the bench corpus
+pub const DEFAULT_BIGRAM_THRESHOLD: u8 = 15;
+
+/// Input method
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Method {
+    #[default]
+    Telex,
+    Vni,
+}
+
+impl Method {
+    /// Engine method id (0=Telex, 1=VNI)
+    pub fn id(self) -> u8 {
+        match self {
+            Method::Telex => 0,
+            Method::Vni => 1,
+        }
+    }
+
+    pub fn from_id(id: u8) -> Self {
+        match id {
+            1 => Method::Vni,
+            _ => Method::Telex,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Method::Telex => "telex",
+            Method::Vni => "vni",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "telex" => Some(Method::Telex),
+            "vni" => Some(Method::Vni),
+            _ => None,
+        }
+    }
+}
+
+/// Tone mark placement
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum ToneStyle {
+    /// hoà, thuý (mark on the second vowel)
+    #[default]
+    Modern,
+    /// hòa, thúy (mark on the first vowel)
+    Classic,
+}
+
+impl ToneStyle {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ToneStyle::Modern => "modern",
+            ToneStyle::Classic => "classic",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "modern" => Some(ToneStyle::Modern),
+            "classic" => Some(ToneStyle::Classic),
+            _ => None,
+        }
+    }
+}
+
+/// What the engine does with words that turn out not to be Vietnamese
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum AutoCorrectMode {
+    /// Keep whatever was composed ("text" stays "tẽt")
+    Off,
+    /// Restore raw keystrokes at word boundary ("tẽt" → "text")
+    #[default]
+    Restore,
+    /// `Restore`, plus dictionary English words that happen to compose
+    /// valid Vietnamese ("mĩ" → "mix", "bos" → "boss"). The dictionary is
+    /// a curated list of common words, not a full one (see
+    /// `data::lexicon::ENGLISH`)
+    All,
+}
+
+impl AutoCorrectMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AutoCorrectMode::Off => "off",
+            AutoCorrectMode::Restore => "restore",
+            AutoCorrectMode::All => "all",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "off" => Some(AutoCorrectMode::Off),
+            "restore" => Some(AutoCorrectMode::Restore),
+            "all" => Some(AutoCorrectMode::All),
+            _ => None,
+        }
+    }
+}
+
+/// What happens once a word grows past `max_word_len`
+///
+/// Long tokens (pasted base64, URLs typed by hand) aren't words; analyzing
+/// them only costs time and risks stray transforms.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum OverflowPolicy {
+    /// Forget the word; the rest of it goes through untouched
+    #[default]
+    PassThrough,
+    /// Keep analyzing the last `max_word_len` characters
+    KeepTail,
+}
+
+impl OverflowPolicy {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            OverflowPolicy::PassThrough => "pass-through",
+            OverflowPolicy::KeepTail => "keep-tail",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "pass-through" => Some(OverflowPolicy::PassThrough),
+            "keep-tail" => Some(OverflowPolicy::KeepTail),
+            _ => None,
+        }
+    }
+}
+
+/// Key that strips diacritics from the word, one layer per press
+///
+/// Written as `"default"` (z in Telex, 0 in VNI), `"off"`, or a single
+/// lowercase letter or digit used in either method.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum RemoveKey {
+    /// The method's own key: z (Telex) / 0 (VNI)
+    #[default]
+    Default,
+    /// No removal key: z / 0 type themselves
+    Off,
+    Key(char),
+}
+
+impl RemoveKey {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "default" => Some(RemoveKey::Default),
+            "off" => Some(RemoveKey::Off),
+            _ => {
+                let mut chars = s.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) if c.is_ascii_lowercase() || c.is_ascii_digit() => {
+                        Some(RemoveKey::Key(c))
+                    }
+                    _ => None,
+                }
+            }
+        }
+    }
+
+    /// Whether `c` already means something else in `method`
+    pub(crate) fn conflicts_with(c: char, method: Method) -> bool {
+        match method {
+            Method::Telex => "sfrxjaeowd".contains(c),
+            Method::Vni => ('1'..='9').contains(&c),
+        }
+    }
+}
+
+impl std::fmt::Display for RemoveKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RemoveKey::Default => f.write_str("default"),
+            RemoveKey::Off => f.write_str("off"),
+            RemoveKey::Key(c) => write!(f, "{}", c),
+        }
+    }
+}
+
+/// What the `nbsp_chord` types
+///
+/// French-Vietnamese documents need a non-breaking space before `:` `;`
+/// `!` `?` and inside « ». The chord types it without finishing the word:
+/// no shortcut, restore or correction runs, and the word stays in the
+/// buffer until the next key (Backspace takes the space back).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Nbsp {
+    /// The chord is a plain space
+    #[default]
+    Off,
+    /// U+00A0 NO-BREAK SPACE
+    Regular,
+    /// U+202F NARROW NO-BREAK SPACE
+    Narrow,
+}
+
+impl Nbsp {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Nbsp::Off => "off",
+            Nbsp::Regular => "regular",
+            Nbsp::Narrow => "narrow",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "off" => Some(Nbsp::Off),
+            "regular" => Some(Nbsp::Regular),
+            "narrow" => Some(Nbsp::Narrow),
+            _ => None,
+        }
+    }
+
+    /// Character typed by the chord
+    pub fn char(self) -> Option<char> {
+        match self {
+            Nbsp::Off => None,
+            Nbsp::Regular => Some('\u{00A0}'),
+            Nbsp::Narrow => Some('\u{202F}'),
+        }
+    }
+}
+
+/// Space with modifiers that types the `nbsp` character
+///
+/// `Engine::on_key_ext` folds Alt into `ctrl`, so chords with Alt are only
+/// recognized from `Engine::on_event`, which has every modifier.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum NbspChord {
+    #[default]
+    ShiftSpace,
+    /// Option+Space on macOS
+    AltSpace,
+    ShiftAltSpace,
+}
+
+impl NbspChord {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            NbspChord::ShiftSpace => "shift-space",
+            NbspChord::AltSpace => "alt-space",
+            NbspChord::ShiftAltSpace => "shift-alt-space",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "shift-space" => Some(NbspChord::ShiftSpace),
+            "alt-space" => Some(NbspChord::AltSpace),
+            "shift-alt-space" => Some(NbspChord::ShiftAltSpace),
+            _ => None,
+        }
+    }
+
+    /// Modifiers held with Space (`engine::event::modifier`)
+    pub fn modifiers(self) -> u8 {
+        use crate::engine::event::modifier::{ALT, SHIFT};
+        match self {
+            NbspChord::ShiftSpace => SHIFT,
+            NbspChord::AltSpace => ALT,
+            NbspChord::ShiftAltSpace => SHIFT | ALT,
+        }
+    }
+}
+
+/// What a mouse event does to the word being typed
+///
+/// A click usually puts the caret somewhere else, so the engine must stop
+/// editing the word it was composing. Scrolling doesn't move the caret.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum MouseAction {
+    /// Keep composing (the event can't have moved the caret)
+    #[default]
+    Keep,
+    /// End the word as shown, like a boundary key without the edit
+    Commit,
+    /// Forget the word (nothing is recorded in history)
+    Clear,
+}
+
+impl MouseAction {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            MouseAction::Keep => "keep",
+            MouseAction::Commit => "commit",
+            MouseAction::Clear => "clear",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "keep" => Some(MouseAction::Keep),
+            "commit" => Some(MouseAction::Commit),
+            "clear" => Some(MouseAction::Clear),
+            _ => None,
+        }
+    }
+}
+
+/// What a held letter or number key's auto-repeat does
+///
+/// Held keys repeat the same key-down, which would compose like the key
+/// typed again: a held "o" turns "oo" into "ô" and back. A key-down is a
+/// repeat when the host says so (`KeyEvent::repeat`), when the key wasn't
+/// released since (`Engine::on_event`), or when it comes within
+/// `REPEAT_MS` of the same key (timestamped keys); the first repeat after
+/// the OS delay is too slow to tell apart from the key typed twice.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum RepeatKey {
+    /// Swallowed: holding the key types it once
+    Single,
+    /// Passed through untouched, ending the word
+    #[default]
+    Raw,
+    /// Handled like the key typed again
+    Normal,
+}
+
+/// Gap under which a timestamped key-down of the same key is auto-repeat,
+/// in milliseconds (faster than anyone types a letter twice)
+pub const REPEAT_MS: u64 = 50;
+
+impl RepeatKey {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RepeatKey::Single => "single",
+            RepeatKey::Raw => "raw",
+            RepeatKey::Normal => "normal",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "single" => Some(RepeatKey::Single),
+            "raw" => Some(RepeatKey::Raw),
+            "normal" => Some(RepeatKey::Normal),
+            _ => None,
+        }
+    }
+}
+
+/// Modifier key a gesture is made with (left or right)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum GestureKey {
+    Shift,
+    Ctrl,
+    /// Option on macOS
+    Alt,
+    /// Command on macOS, Windows key on PC
+    Cmd,
+}
+
+impl GestureKey {
+    pub const ALL: [GestureKey; 4] = [
+        GestureKey::Shift,
+        GestureKey::Ctrl,
+        GestureKey::Alt,
+        GestureKey::Cmd,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            GestureKey::Shift => "shift",
+            GestureKey::Ctrl => "ctrl",
+            GestureKey::Alt => "alt",
+            GestureKey::Cmd => "cmd",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "shift" => Some(GestureKey::Shift),
+            "ctrl" => Some(GestureKey::Ctrl),
+            "alt" => Some(GestureKey::Alt),
+            "cmd" => Some(GestureKey::Cmd),
+            _ => None,
+        }
+    }
+
+    /// Gesture key of a modifier keycode
+    pub fn from_key(key: u16) -> Option<Self> {
+        use crate::data::keys;
+        match key {
+            keys::SHIFT | keys::RIGHT_SHIFT => Some(GestureKey::Shift),
+            keys::CONTROL | keys::RIGHT_CONTROL => Some(GestureKey::Ctrl),
+            keys::OPTION | keys::RIGHT_OPTION => Some(GestureKey::Alt),
+            keys::COMMAND | keys::RIGHT_COMMAND => Some(GestureKey::Cmd),
+            _ => None,
+        }
+    }
+}
+
+/// What a gesture does
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum GestureAction {
+    /// Vietnamese on or off
+    ToggleEnabled,
+    /// Telex ↔ VNI
+    ToggleMethod,
+    /// Left to the host: show suggestions for the word being typed
+    Suggestions,
+}
+
+impl GestureAction {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            GestureAction::ToggleEnabled => "toggle-enabled",
+            GestureAction::ToggleMethod => "toggle-method",
+            GestureAction::Suggestions => "suggestions",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "toggle-enabled" => Some(GestureAction::ToggleEnabled),
+            "toggle-method" => Some(GestureAction::ToggleMethod),
+            "suggestions" => Some(GestureAction::Suggestions),
+            _ => None,
+        }
+    }
+}
+
+/// What double-tapping each modifier does (see `engine::gesture`)
+///
+/// Written as a comma-separated list of `key:action`
+/// (`"shift:toggle-enabled,ctrl:suggestions"`), `""` for none. All are off
+/// by default: a host may already use a gesture for something else.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(into = "String", try_from = "String"))]
+pub struct Gestures([Option<GestureAction>; 4]);
+
+impl Gestures {
+    pub const NONE: Gestures = Gestures([None; 4]);
+
+    pub fn get(self, key: GestureKey) -> Option<GestureAction> {
+        self.0[key as usize]
+    }
+
+    /// Same mappings with `key` doing `action` (None = nothing)
+    pub fn with(mut self, key: GestureKey, action: Option<GestureAction>) -> Self {
+        self.0[key as usize] = action;
+        self
+    }
+
+    /// Each key at most once
+    pub fn parse(s: &str) -> Option<Self> {
+        let mut gestures = Gestures::NONE;
+        for item in s.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+            let (key, action) = item.split_once(':')?;
+            let key = GestureKey::parse(key.trim())?;
+            if gestures.get(key).is_some() {
+                return None;
+            }
+            gestures = gestures.with(key, Some(GestureAction::parse(action.trim())?));
+        }
+        Some(gestures)
+    }
+}
+
+impl std::fmt::Display for Gestures {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut sep = "";
+        for key in GestureKey::ALL {
+            if let Some(action) = self.get(key) {
+                write!(f, "{}{}:{}", sep, key.as_str(), action.as_str())?;
+                sep = ",";
+            }
+        }
+        Ok(())
+    }
+}
+
+impl From<Gestures> for String {
+    fn from(gestures: Gestures) -> String {
+        gestures.to_string()
+    }
+}
+
+impl TryFrom<String> for Gestures {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, String> {
+        Gestures::parse(&s).ok_or(s)
+    }
+}
+
+/// Which spelling wins where both i and y are in use: h, k, l, m, qu, s, t
+/// plus a toned i/y ("lí"/"lý", "kĩ"/"kỹ", "quí"/"quý")
+///
+/// Applied as a correction rule at the word boundary, after the providers
+/// and packs. Toneless words are left alone ("hi", "my" are English too).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum IyStyle {
+    /// Keep whichever was typed
+    #[default]
+    Off,
+    /// "lí" → "lý"
+    PreferY,
+    /// "lý" → "lí"
+    PreferI,
+}
+
+impl IyStyle {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            IyStyle::Off => "off",
+            IyStyle::PreferY => "prefer-y",
+            IyStyle::PreferI => "prefer-i",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "off" => Some(IyStyle::Off),
+            "prefer-y" => Some(IyStyle::PreferY),
+            "prefer-i" => Some(IyStyle::PreferI),
+            _ => None,
+        }
+    }
+}
+
+/// What the engine does with a correction it found for a finished word
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum CorrectionStyle {
+    /// Replace the word right away ("nàm " → "làm ")
+    #[default]
+    Replace,
+    /// Leave the word and offer the fix (`Engine::suggestion`); `accept_key`
+    /// applies it, Down/Up browse the other candidates, any other key drops
+    /// it
+    Suggest,
+}
+
+impl CorrectionStyle {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CorrectionStyle::Replace => "replace",
+            CorrectionStyle::Suggest => "suggest",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "replace" => Some(CorrectionStyle::Replace),
+            "suggest" => Some(CorrectionStyle::Suggest),
+            _ => None,
+        }
+    }
+}
+
+/// Key that applies an offered correction (`CorrectionStyle::Suggest`)
+///
+/// The key is swallowed when it accepts; with nothing on offer it does
+/// what it always does.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum AcceptKey {
+    #[default]
+    Tab,
+    /// Right arrow
+    Right,
+    /// `` ` ``
+    Backquote,
+}
+
+impl AcceptKey {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AcceptKey::Tab => "tab",
+            AcceptKey::Right => "right",
+            AcceptKey::Backquote => "backquote",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "tab" => Some(AcceptKey::Tab),
+            "right" => Some(AcceptKey::Right),
+            "backquote" => Some(AcceptKey::Backquote),
+            _ => None,
+        }
+    }
+
+    /// Keycode
+    pub fn key(self) -> u16 {
+        match self {
+            AcceptKey::Tab => crate::data::keys::TAB,
+            AcceptKey::Right => crate::data::keys::RIGHT,
+            AcceptKey::Backquote => crate::data::keys::BACKQUOTE,
+        }
+    }
+}
+
+/// Region whose spelling slips a correction pack fixes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Dialect {
+    Northern,
+    Central,
+    Southern,
+}
+
+impl Dialect {
+    pub const ALL: [Dialect; 3] = [Dialect::Northern, Dialect::Central, Dialect::Southern];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Dialect::Northern => "northern",
+            Dialect::Central => "central",
+            Dialect::Southern => "southern",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "northern" => Some(Dialect::Northern),
+            "central" => Some(Dialect::Central),
+            "southern" => Some(Dialect::Southern),
+            _ => None,
+        }
+    }
+}
+
+/// Dialect correction packs that are on
+///
+/// Written as a comma-separated list (`"northern,southern"`), `""` for
+/// none. Each pack only fixes slips typical of its region, so a pack that
+/// helps one user can be noise for another: all are off by default.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(into = "String", try_from = "String"))]
+pub struct CorrectionPacks(u8);
+
+impl CorrectionPacks {
+    pub const NONE: CorrectionPacks = CorrectionPacks(0);
+
+    pub fn contains(self, dialect: Dialect) -> bool {
+        self.0 & (1 << dialect as u8) != 0
+    }
+
+    /// Same set with `dialect` turned on or off
+    pub fn with(self, dialect: Dialect, on: bool) -> Self {
+        let bit = 1 << dialect as u8;
+        CorrectionPacks(if on { self.0 | bit } else { self.0 & !bit })
+    }
+
+    /// Bit per `Dialect`, in declaration order (for FFI)
+    pub fn bits(self) -> u8 {
+        self.0
+    }
+
+    pub fn from_bits(bits: u8) -> Self {
+        CorrectionPacks(bits & 0b111)
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        let mut packs = CorrectionPacks::NONE;
+        for name in s.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+            packs = packs.with(Dialect::parse(name)?, true);
+        }
+        Some(packs)
+    }
+}
+
+impl std::fmt::Display for CorrectionPacks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut sep = "";
+        for dialect in Dialect::ALL.into_iter().filter(|&d| self.contains(d)) {
+            write!(f, "{}{}", sep, dialect.as_str())?;
+            sep = ",";
+        }
+        Ok(())
+    }
+}
+
+impl From<CorrectionPacks> for String {
+    fn from(packs: CorrectionPacks) -> String {
+        packs.to_string()
+    }
+}
+
+impl TryFrom<String> for CorrectionPacks {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, String> {
+        CorrectionPacks::parse(&s).ok_or(s)
+    }
+}
+
+/// One of the checks that decide whether auto-restore turns a word back
+/// into its raw keystrokes (see `engine::restore`)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum RestoreCheck {
+    /// Raw keystrokes spell an English dictionary word (`auto_correct = "all"`)
+    Dictionary,
+    /// Result is not a Vietnamese syllable ("tẽt")
+    Invalid,
+    /// A mark the final consonant can't take ("hòp")
+    MarkFinal,
+    /// W initial with a consonant or another W after it ("window", "wow")
+    WInitial,
+    /// Tone key followed by a consonant ("text", "expect")
+    ModifierConsonant,
+    /// Tone key after an English vowel pair at the end ("their", "pair")
+    ModifierVowelPair,
+    /// Tone key between two vowels ("use", "core")
+    ModifierBetweenVowels,
+    /// W after a vowel at the end ("raw", "law")
+    WFinal,
+    /// English suffix after a stem that lost its doubled letter and isn't
+    /// a syllable ("mising" from "missing", "sesion")
+    EnglishSuffix,
+    /// Letter pairs far more common in English than in Telex keystrokes
+    /// (`bigram_threshold`, see `data::bigram`)
+    Bigram,
+}
+
+impl RestoreCheck {
+    pub const ALL: [RestoreCheck; 10] = [
+        RestoreCheck::Dictionary,
+        RestoreCheck::Invalid,
+        RestoreCheck::MarkFinal,
+        RestoreCheck::WInitial,
+        RestoreCheck::ModifierConsonant,
+        RestoreCheck::ModifierVowelPair,
+        RestoreCheck::ModifierBetweenVowels,
+        RestoreCheck::WFinal,
+        RestoreCheck::EnglishSuffix,
+        RestoreCheck::Bigram,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RestoreCheck::Dictionary => "dictionary",
+            RestoreCheck::Invalid => "invalid",
+            RestoreCheck::MarkFinal => "mark-final",
+            RestoreCheck::WInitial => "w-initial",
+            RestoreCheck::ModifierConsonant => "modifier-consonant",
+            RestoreCheck::ModifierVowelPair => "modifier-vowel-pair",
+            RestoreCheck::ModifierBetweenVowels => "modifier-between-vowels",
+            RestoreCheck::WFinal => "w-final",
+            RestoreCheck::EnglishSuffix => "english-suffix",
+            RestoreCheck::Bigram => "bigram",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        RestoreCheck::ALL.into_iter().find(|c| c.as_str() == s)
+    }
+}
+
+/// Order the auto-restore checks run in
+///
+/// Every check always runs until one fires, so the order changes speed,
+/// never the outcome. Written as a comma-separated list; checks left out
+/// follow in default order, so `""` is the default and a list from an
+/// older version stays complete.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(into = "String", try_from = "String"))]
+pub struct RestoreOrder([RestoreCheck; RestoreCheck::ALL.len()]);
+
+impl RestoreOrder {
+    /// Most often decisive checks first, as measured over the word lists
+    /// in `tests/` (`RestoreStats::suggested_order`); the dictionary
+    /// lookup, the costliest, last
+    pub const DEFAULT: RestoreOrder = RestoreOrder([
+        RestoreCheck::Invalid,
+        RestoreCheck::ModifierConsonant,
+        RestoreCheck::WFinal,
+        RestoreCheck::ModifierBetweenVowels,
+        RestoreCheck::MarkFinal,
+        RestoreCheck::WInitial,
+        RestoreCheck::ModifierVowelPair,
+        RestoreCheck::EnglishSuffix,
+        RestoreCheck::Bigram,
+        RestoreCheck::Dictionary,
+    ]);
+
+    pub fn checks(&self) -> &[RestoreCheck; RestoreCheck::ALL.len()] {
+        &self.0
+    }
+
+    /// `first` in that order, then the remaining checks in default order;
+    /// `None` if a check repeats
+    pub fn new(first: &[RestoreCheck]) -> Option<Self> {
+        let mut order = Vec::with_capacity(RestoreCheck::ALL.len());
+        for &c in first {
+            if order.contains(&c) {
+                return None;
+            }
+            order.push(c);
+        }
+        for c in RestoreOrder::DEFAULT.0 {
+            if !order.contains(&c) {
+                order.push(c);
+            }
+        }
+        order.try_into().ok().map(RestoreOrder)
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        let checks = s
+            .split(',')
+            .map(str::trim)
+            .filter(|n| !n.is_empty())
+            .map(RestoreCheck::parse)
+            .collect::<Option<Vec<_>>>()?;
+        RestoreOrder::new(&checks)
+    }
+}
+
+impl Default for RestoreOrder {
+    fn default() -> Self {
+        RestoreOrder::DEFAULT
+    }
+}
+
+impl std::fmt::Display for RestoreOrder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut sep = "";
+        for check in self.0 {
+            write!(f, "{}{}", sep, check.as_str())?;
+            sep = ",";
+        }
+        Ok(())
+    }
+}
+
+impl From<RestoreOrder> for String {
+    fn from(order: RestoreOrder) -> String {
+        order.to_string()
+    }
+}
+
+impl TryFrom<String> for RestoreOrder {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, String> {
+        RestoreOrder::parse(&s).ok_or(s)
+    }
+}
+
+/// Restore checks that are turned off
+///
+/// Written as a comma-separated list like `CorrectionPacks`, `""` for
+/// none. A check that misfires on someone's spelling ("cỏe" from
+/// `modifier-between-vowels`) can go without losing the rest.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(into = "String", try_from = "String"))]
+pub struct RestoreChecks(u16);
+
+impl RestoreChecks {
+    pub const NONE: RestoreChecks = RestoreChecks(0);
+
+    pub fn contains(self, check: RestoreCheck) -> bool {
+        self.0 & (1 << check as u16) != 0
+    }
+
+    /// Same set with `check` added or removed
+    pub fn with(self, check: RestoreCheck, on: bool) -> Self {
+        let bit = 1 << check as u16;
+        RestoreChecks(if on { self.0 | bit } else { self.0 & !bit })
+    }
+
+    /// Bit per `RestoreCheck`, in declaration order (for FFI)
+    pub fn bits(self) -> u16 {
+        self.0
+    }
+
+    pub fn from_bits(bits: u16) -> Self {
+        RestoreChecks(bits & ((1 << RestoreCheck::ALL.len()) - 1))
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        let mut checks = RestoreChecks::NONE;
+        for name in s.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+            checks = checks.with(RestoreCheck::parse(name)?, true);
+        }
+        Some(checks)
+    }
+}
+
+impl std::fmt::Display for RestoreChecks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut sep = "";
+        for check in RestoreCheck::ALL.into_iter().filter(|&c| self.contains(c)) {
+            write!(f, "{}{}", sep, check.as_str())?;
+            sep = ",";
+        }
+        Ok(())
+    }
+}
+
+impl From<RestoreChecks> for String {
+    fn from(checks: RestoreChecks) -> String {
+        checks.to_string()
+    }
+}
+
+impl TryFrom<String> for RestoreChecks {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, String> {
+        RestoreChecks::parse(&s).ok_or(s)
+    }
+}
+
+/// Config loading error
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConfigError {
+    /// Line is not `key = value`
+    Syntax { line: usize },
+    /// Key not recognized (typo or setting from a newer version)
+    UnknownKey { line: usize, key: String },
+    /// Value has the wrong type (e.g. string for a bool)
+    InvalidType {
+        line: usize,
+        key: String,
+        expected: &'static str,
+    },
+    /// Value has the right type but is out of range / not a known option
+    InvalidValue {
+        line: usize,
+        key: String,
+        value: String,
+    },
+    /// Options that cannot be used together
+    Conflict(&'static str),
+}
+
+impl ConfigError {
+    /// Stable numeric code for FFI (0 is reserved for success)
+    pub fn code(&self) -> i32 {
+        match self {
+            ConfigError::Syntax { .. } => 1,
+            ConfigError::UnknownKey { .. } => 2,
+            ConfigError::InvalidType { .. } => 3,
+            ConfigError::InvalidValue { .. } => 4,
+            ConfigError::Conflict(_) => 5,
+        }
+    }
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::Syntax { line } => write!(f, "line {}: expected `key = value`", line),
+            ConfigError::UnknownKey { line, key } => {
+                write!(f, "line {}: unknown key `{}`", line, key)
+            }
+            ConfigError::InvalidType {
+                line,
+                key,
+                expected,
+            } => write!(f, "line {}: `{}` must be a {}", line, key, expected),
+            ConfigError::InvalidValue { line, key, value } => {
+                write!(f, "line {}: invalid value {} for `{}`", line, value, key)
+            }
+            ConfigError::Conflict(msg) => write!(f, "conflicting options: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// All engine settings
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct EngineConfig {
+    pub method: Method,
+    pub enabled: bool,
+    pub tone_style: ToneStyle,
+    pub auto_correct: AutoCorrectMode,
+    /// Telex quick vowels: `[` → ơ, `]` → ư
+    pub bracket_shortcuts: bool,
+    /// Expand money shorthand typed as a word: "25k" → "25.000₫",
+    /// "1m2" → "1,2 triệu"
+    pub money_shorthand: bool,
+    /// Tab right after a shortcut's trigger expands it, with no space
+    /// after the replacement (the Tab itself isn't typed)
+    pub tab_shortcuts: bool,
+    /// Longest word analyzed, in characters (1..=`MAX_WORD_LEN`)
+    pub max_word_len: u8,
+    pub overflow: OverflowPolicy,
+    pub remove_key: RemoveKey,
+    /// One horn key on "uo" gives "ươ" (`uow`, `wo`, VNI `uo7`)
+    pub uo_compound: bool,
+    pub correction_packs: CorrectionPacks,
+    pub iy_style: IyStyle,
+    pub correction_style: CorrectionStyle,
+    pub accept_key: AcceptKey,
+    /// Character the `nbsp_chord` types instead of a space
+    pub nbsp: Nbsp,
+    pub nbsp_chord: NbspChord,
+    /// Commit a word left mid-composition this long (0 = never); needs
+    /// timestamped keys (`Engine::on_key_at`)
+    pub idle_commit_secs: u8,
+    pub mouse_click: MouseAction,
+    pub mouse_scroll: MouseAction,
+    pub repeat_key: RepeatKey,
+    pub gestures: Gestures,
+    /// Tell observers when a word is committed, corrected or restored, for
+    /// sounds or haptics (`Notification::Feedback`)
+    pub feedback: bool,
+    /// Least time between two feedback events, in milliseconds; a sooner
+    /// one is dropped unless it outranks the last (a restore after a commit)
+    pub feedback_gap_ms: u8,
+    /// Most edits a second before the engine takes them for a feedback
+    /// loop and stops (see `engine::runaway`; 0 = no limit)
+    pub edit_limit: u8,
+    pub restore_order: RestoreOrder,
+    /// Restore checks that never fire
+    pub restore_disabled: RestoreChecks,
+    /// Average English lean of a word's letter pairs, in tenths of a bit,
+    /// at which the bigram check restores it (0 = never)
+    pub bigram_threshold: u8,
+    /// Least confidence, in percent (0..=100), for anything to change a
+    /// finished word (`engine::decision`)
+    pub min_confidence: u8,
+}
+
+impl Default for EngineConfig {
+    fn default() -> Self {
+        Self {
+            method: Method::Telex,
+            enabled: true,
+            tone_style: ToneStyle::Modern,
+            auto_correct: AutoCorrectMode::Restore,
+            bracket_shortcuts: false,
+            money_shorthand: false,
+            tab_shortcuts: false,
+            max_word_len: DEFAULT_MAX_WORD_LEN,
+            overflow: OverflowPolicy::PassThrough,
+            remove_key: RemoveKey::Default,
+            uo_compound: true,
+            correction_packs: CorrectionPacks::NONE,
+            iy_style: IyStyle::Off,
+            correction_style: CorrectionStyle::Replace,
+            accept_key: AcceptKey::Tab,
+            nbsp: Nbsp::Off,
+            nbsp_chord: NbspChord::ShiftSpace,
+            idle_commit_secs: 0,
+            mouse_click: MouseAction::Commit,
+            mouse_scroll: MouseAction::Keep,
+            repeat_key: RepeatKey::Raw,
+            gestures: Gestures::NONE,
+            feedback: false,
+            feedback_gap_ms: DEFAULT_FEEDBACK_GAP_MS,
+            edit_limit: DEFAULT_EDIT_LIMIT,
+            restore_order: RestoreOrder::DEFAULT,
+            restore_disabled: RestoreChecks::NONE,
+            bigram_threshold: DEFAULT_BIGRAM_THRESHOLD,
+            min_confidence: 0,
+        }
+    }
+}
+
+impl EngineConfig {
+    /// Serialize to TOML
+    pub fn to_toml(&self) -> String {
+        let mut out = String::from("# Gõ Nhanh configuration\n");
+        out.push_str(&format!("method = \"{}\"\n", self.method.as_str()));
+        out.push_str(&format!("enabled = {}\n", self.enabled));
+        out.push_str(&format!("tone_style = \"{}\"\n", self.tone_style.as_str()));
+        out.push_str(&format!(
+            "auto_correct = \"{}\"\n",
+            self.auto_correct.as_str()
+        ));
+        out.push_str(&format!("bracket_shortcuts = {}\n", self.bracket_shortcuts));
+        out.push_str(&format!("money_shorthand = {}\n", self.money_shorthand));
+        out.push_str(&format!("tab_shortcuts = {}\n", self.tab_shortcuts));
+        out.push_str(&format!("max_word_len = {}\n", self.max_word_len));
+        out.push_str(&format!("overflow = \"{}\"\n", self.overflow.as_str()));
+        out.push_str(&format!("remove_key = \"{}\"\n", self.remove_key));
+        out.push_str(&format!("uo_compound = {}\n", self.uo_compound));
+        out.push_str(&format!(
+            "correction_packs = \"{}\"\n",
+            self.correction_packs
+        ));
+        out.push_str(&format!("iy_style = \"{}\"\n", self.iy_style.as_str()));
+        out.push_str(&format!(
+            "correction_style = \"{}\"\n",
+            self.correction_style.as_str()
+        ));
+        out.push_str(&format!("accept_key = \"{}\"\n", self.accept_key.as_str()));
+        out.push_str(&format!("nbsp = \"{}\"\n", self.nbsp.as_str()));
+        out.push_str(&format!("nbsp_chord = \"{}\"\n", self.nbsp_chord.as_str()));
+        out.push_str(&format!("idle_commit_secs = {}\n", self.idle_commit_secs));
+        out.push_str(&format!(
+            "mouse_click = \"{}\"\n",
+            self.mouse_click.as_str()
+        ));
+        out.push_str(&format!(
+            "mouse_scroll = \"{}\"\n",
+            self.mouse_scroll.as_str()
+        ));
+        out.push_str(&format!("repeat_key = \"{}\"\n", self.repeat_key.as_str()));
+        out.push_str(&format!("gestures = \"{}\"\n", self.gestures));
+        out.push_str(&format!("feedback = {}\n", self.feedback));
+        out.push_str(&format!("feedback_gap_ms = {}\n", self.feedback_gap_ms));
+        out.push_str(&format!("edit_limit = {}\n", self.edit_limit));
+        out.push_str(&format!("restore_order = \"{}\"\n", self.restore_order));
+        out.push_str(&format!(
+            "restore_disabled = \"{}\"\n",
+            self.restore_disabled
+        ));
+        out.push_str(&format!("bigram_threshold = {}\n", self.bigram_threshold));
+        out.push_str(&format!("min_confidence = {}\n", self.min_confidence));
+        out
+    }
+
+    /// Check option combinations
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.bracket_shortcuts && self.method == Method::Vni {
+            return Err(ConfigError::Conflict(
+                "bracket_shortcuts is Telex-only; VNI has no bracket vowels",
+            ));
+        }
+        if let RemoveKey::Key(c) = self.remove_key {
+            if RemoveKey::conflicts_with(c, self.method) {
+                return Err(ConfigError::Conflict(
+                    "remove_key is already a tone or mark key of the method",
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Reset options that conflict with the rest (see `validate`) to their
+    /// default; the conflict found, if any
+    pub fn drop_conflicts(&mut self) -> Option<ConfigError> {
+        let err = self.validate().err()?;
+        if self.bracket_shortcuts && self.method == Method::Vni {
+            self.bracket_shortcuts = false;
+        }
+        if let RemoveKey::Key(c) = self.remove_key {
+            if RemoveKey::conflicts_with(c, self.method) {
+                self.remove_key = RemoveKey::Default;
+            }
+        }
+        debug_assert_eq!(self.validate(), Ok(()));
+        Some(err)
+    }
+
+    /// Parse from TOML
+    ///
+    /// Missing keys keep their default. `method` also accepts the engine
+    /// id (0=Telex, 1=VNI) as stored by older host settings.
+    pub fn from_toml(s: &str) -> Result<EngineConfig, ConfigError> {
+        let config = Self::parse_toml(s)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// `from_toml` without `validate`
+    pub(crate) fn parse_toml(s: &str) -> Result<EngineConfig, ConfigError> {
+        let mut config = EngineConfig::default();
+
+        for (i, line) in s.lines().enumerate() {
+            let line_no = i + 1;
+            let line = strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let (key, raw) = line
+                .split_once('=')
+                .ok_or(ConfigError::Syntax { line: line_no })?;
+            let key = key.trim();
+            let raw = raw.trim();
+            let value = Value::parse(raw).ok_or(ConfigError::Syntax { line: line_no })?;
+
+            let invalid_type = |expected| ConfigError::InvalidType {
+                line: line_no,
+                key: key.to_string(),
+                expected,
+            };
+            let invalid_value = || ConfigError::InvalidValue {
+                line: line_no,
+                key: key.to_string(),
+                value: raw.to_string(),
+            };
+
+            match key {
+                "method" => {
+                    config.method = match value {
+                        Value::Str(s) => Method::parse(s).ok_or_else(invalid_value)?,
+                        Value::Int(0) => Method::Telex,
+                        Value::Int(1) => Method::Vni,
+                        Value::Int(_) => return Err(invalid_value()),
+                        Value::Bool(_) => return Err(invalid_type("string")),
+                    }
+                }
+                "enabled" => {
+                    config.enabled = value.as_bool().ok_or_else(|| invalid_type("bool"))?
+                }
+                "tone_style" => {
+                    let s = value.as_str().ok_or_else(|| invalid_type("string"))?;
+                    config.tone_style = ToneStyle::parse(s).ok_or_else(invalid_value)?;
+                }
+                "auto_correct" => {
+                    let s = value.as_str().ok_or_else(|| invalid_type("string"))?;
+                    config.auto_correct = AutoCorrectMode::parse(s).ok_or_else(invalid_value)?;
+                }
+                "bracket_shortcuts" => {
+                    config.bracket_shortcuts =
+                        value.as_bool().ok_or_else(|| invalid_type("bool"))?
+                }
+                "money_shorthand" => {
+                    config.money_shorthand = value.as_bool().ok_or_else(|| invalid_type("bool"))?
+                }
+                "tab_shortcuts" => {
+                    config.tab_shortcuts = value.as_bool().ok_or_else(|| invalid_type("bool"))?
+                }
+                "max_word_len" => {
+                    let Value::Int(n) = value else {
+                        return Err(invalid_type("integer"));
+                    };
+                    config.max_word_len = u8::try_from(n)
+                        .ok()
+                        .filter(|n| (1..=MAX_WORD_LEN).contains(n))
+                        .ok_or_else(invalid_value)?;
+                }
+                "idle_commit_secs" => {
+                    let Value::Int(n) = value else {
+                        return Err(invalid_type("integer"));
+                    };
+                    config.idle_commit_secs = u8::try_from(n).map_err(|_| invalid_value())?;
+                }
+                "mouse_click" => {
+                    let s = value.as_str().ok_or_else(|| invalid_type("string"))?;
+                    config.mouse_click = MouseAction::parse(s).ok_or_else(invalid_value)?;
+                }
+                "mouse_scroll" => {
+                    let s = value.as_str().ok_or_else(|| invalid_type("string"))?;
+                    config.mouse_scroll = MouseAction::parse(s).ok_or_else(invalid_value)?;
+                }
+                "repeat_key" => {
+                    let s = value.as_str().ok_or_else(|| invalid_type("string"))?;
+                    config.repeat_key = RepeatKey::parse(s).ok_or_else(invalid_value)?;
+                }
+                "gestures" => {
+                    let s = value.as_str().ok_or_else(|| invalid_type("string"))?;
+                    config.gestures = Gestures::parse(s).ok_or_else(invalid_value)?;
+                }
+                "feedback" => {
+                    config.feedback = value.as_bool().ok_or_else(|| invalid_type("bool"))?
+                }
+                "feedback_gap_ms" => {
+                    let Value::Int(n) = value else {
+                        return Err(invalid_type("integer"));
+                    };
+                    config.feedback_gap_ms = u8::try_from(n).map_err(|_| invalid_value())?;
+                }
+                "edit_limit" => {
+                    let Value::Int(n) = value else {
+                        return Err(invalid_type("integer"));
+                    };
+                    config.edit_limit = u8::try_from(n).map_err(|_| invalid_value())?;
+                }
+                "overflow" => {
+                    let s = value.as_str().ok_or_else(|| invalid_type("string"))?;
+                    config.overflow = OverflowPolicy::parse(s).ok_or_else(invalid_value)?;
+                }
+                "remove_key" => {
+                    let s = value.as_str().ok_or_else(|| invalid_type("string"))?;
+                    config.remove_key = RemoveKey::parse(s).ok_or_else(invalid_value)?;
+                }
+                "uo_compound" => {
+                    config.uo_compound = value.as_bool().ok_or_else(|| invalid_type("bool"))?
+                }
+                "correction_packs" => {
+                    let s = value.as_str().ok_or_else(|| invalid_type("string"))?;
+                    config.correction_packs =
+                        CorrectionPacks::parse(s).ok_or_else(invalid_value)?;
+                }
+                "iy_style" => {
+                    let s = value.as_str().ok_or_else(|| invalid_type("string"))?;
+                    config.iy_style = IyStyle::parse(s).ok_or_else(invalid_value)?;
+                }
+                "correction_style" => {
+                    let s = value.as_str().ok_or_else(|| invalid_type("string"))?;
+                    config.correction_style =
+                        CorrectionStyle::parse(s).ok_or_else(invalid_value)?;
+                }
+                "accept_key" => {
+                    let s = value.as_str().ok_or_else(|| invalid_type("string"))?;
+                    config.accept_key = AcceptKey::parse(s).ok_or_else(invalid_value)?;
+                }
+                "nbsp" => {
+                    let s = value.as_str().ok_or_else(|| invalid_type("string"))?;
+                    config.nbsp = Nbsp::parse(s).ok_or_else(invalid_value)?;
+                }
+                "nbsp_chord" => {
+                    let s = value.as_str().ok_or_else(|| invalid_type("string"))?;
+                    config.nbsp_chord = NbspChord::parse(s).ok_or_else(invalid_value)?;
+                }
+                "restore_order" => {
+                    let s = value.as_str().ok_or_else(|| invalid_type("string"))?;
+                    config.restore_order = RestoreOrder::parse(s).ok_or_else(invalid_value)?;
+                }
+                "restore_disabled" => {
+                    let s = value.as_str().ok_or_else(|| invalid_type("string"))?;
+                    config.restore_disabled = RestoreChecks::parse(s).ok_or_else(invalid_value)?;
+                }
+                "bigram_threshold" => {
+                    let Value::Int(n) = value else {
+                        return Err(invalid_type("integer"));
+                    };
+                    // Pair scores are `i8`s, so no average reaches past 127
+                    config.bigram_threshold = u8::try_from(n)
+                        .ok()
+                        .filter(|n| *n <= i8::MAX as u8)
+                        .ok_or_else(invalid_value)?;
+                }
+                "min_confidence" => {
+                    let Value::Int(n) = value else {
+                        return Err(invalid_type("integer"));
+                    };
+                    config.min_confidence = u8::try_from(n)
+                        .ok()
+                        .filter(|n| *n <= 100)
+                        .ok_or_else(invalid_value)?;
+                }
+                _ => {
+                    return Err(ConfigError::UnknownKey {
+                        line: line_no,
+                        key: key.to_string(),
+                    })
+                }
+            }
+        }
+
+        Ok(config)
+    }
+}
+
+/// Drop a trailing `# comment` (outside of quotes)
+fn strip_comment(line: &str) -> &str {
+    let mut in_str = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => in_str = !in_str,
+            '#' if !in_str => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+/// TOML scalar value
+#[derive(Debug, PartialEq)]
+enum Value<'a> {
+    Str(&'a str),
+    Bool(bool),
+    Int(i64),
+}
+
+impl<'a> Value<'a> {
+    fn parse(s: &'a str) -> Option<Self> {
+        if let Some(inner) = s.strip_prefix('"').and_then(|s| s.strip_suffix('"')) {
+            return Some(Value::Str(inner));
+        }
+        match s {
+            "true" => Some(Value::Bool(true)),
+            "false" => Some(Value::Bool(false)),
+            _ => s.parse().ok().map(Value::Int),
+        }
+    }
+
+    fn as_str(&self) -> Option<&'a str> {
+        match self {
+            Value::Str(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() {
+        let config = EngineConfig {
+            method: Method::Vni,
+            enabled: false,
+            tone_style: ToneStyle::Classic,
+            auto_correct: AutoCorrectMode::Off,
+            bracket_shortcuts: false,
+            money_shorthand: true,
+            tab_shortcuts: true,
+            max_word_len: 20,
+            overflow: OverflowPolicy::KeepTail,
+            remove_key: RemoveKey::Key('q'),
+            uo_compound: false,
+            correction_packs: CorrectionPacks::NONE.with(Dialect::Southern, true),
+            iy_style: IyStyle::PreferI,
+            correction_style: CorrectionStyle::Suggest,
+            accept_key: AcceptKey::Backquote,
+            nbsp: Nbsp::Narrow,
+            nbsp_chord: NbspChord::AltSpace,
+            idle_commit_secs: 5,
+            mouse_click: MouseAction::Clear,
+            mouse_scroll: MouseAction::Commit,
+            repeat_key: RepeatKey::Single,
+            gestures: Gestures::NONE
+                .with(GestureKey::Shift, Some(GestureAction::ToggleEnabled))
+                .with(GestureKey::Ctrl, Some(GestureAction::Suggestions)),
+            feedback: true,
+            feedback_gap_ms: 0,
+            edit_limit: 0,
+            restore_order: RestoreOrder::new(&[RestoreCheck::WFinal]).unwrap(),
+            restore_disabled: RestoreChecks::NONE.with(RestoreCheck::ModifierBetweenVowels, true),
+            bigram_threshold: 0,
+            min_confidence: 80,
+        };
+        assert_eq!(EngineConfig::from_toml(&config.to_toml()), Ok(config));
+    }
+
+    #[test]
+    fn test_defaults_for_missing_keys() {
+        let config = EngineConfig::from_toml("method = \"vni\"").unwrap();
+        assert_eq!(config.method, Method::Vni);
+        assert!(config.enabled);
+        assert_eq!(config.tone_style, ToneStyle::Modern);
+    }
+
+    #[test]
+    fn test_comments_and_blank_lines() {
+        let toml = "# header\n\nenabled = false # off for now\n";
+        assert!(!EngineConfig::from_toml(toml).unwrap().enabled);
+    }
+
+    #[test]
+    fn test_syntax_errors() {
+        assert_eq!(
+            EngineConfig::from_toml("method"),
+            Err(ConfigError::Syntax { line: 1 })
+        );
+        assert_eq!(
+            EngineConfig::from_toml("# ok\nmethod = telex"),
+            Err(ConfigError::Syntax { line: 2 })
+        );
+    }
+
+    #[test]
+    fn test_unknown_key() {
+        let err = EngineConfig::from_toml("metod = \"vni\"").unwrap_err();
+        assert_eq!(
+            err,
+            ConfigError::UnknownKey {
+                line: 1,
+                key: "metod".into()
+            }
+        );
+        assert_eq!(err.code(), 2);
+        assert_eq!(err.to_string(), "line 1: unknown key `metod`");
+    }
+
+    #[test]
+    fn test_invalid_type_and_value() {
+        let err = EngineConfig::from_toml("enabled = \"yes\"").unwrap_err();
+        assert!(matches!(
+            err,
+            ConfigError::InvalidType {
+                expected: "bool",
+                ..
+            }
+        ));
+
+        let err = EngineConfig::from_toml("tone_style = \"new\"").unwrap_err();
+        assert!(matches!(err, ConfigError::InvalidValue { .. }));
+
+        // Out-of-range method id
+        let err = EngineConfig::from_toml("method = 2").unwrap_err();
+        assert_eq!(err.code(), 4);
+        assert_eq!(
+            EngineConfig::from_toml("method = 1").unwrap().method,
+            Method::Vni
+        );
+
+        for toml in [
+            "max_word_len = 0",
+            "max_word_len = 64",
+            "overflow = \"drop\"",
+            "remove_key = \"\"",
+            "remove_key = \"Z\"",
+            "remove_key = \"zz\"",
+            "remove_key = \"[\"",
+            "correction_packs = \"western\"",
+            "iy_style = \"y\"",
+            "correction_style = \"ask\"",
+            "accept_key = \"enter\"",
+            "nbsp = \"thin\"",
+            "nbsp_chord = \"ctrl-space\"",
+            "idle_commit_secs = 300",
+            "idle_commit_secs = -1",
+            "mouse_click = \"ignore\"",
+            "repeat_key = \"twice\"",
+            "gestures = \"shift\"",
+            "feedback_gap_ms = 1000",
+            "edit_limit = -1",
+            "gestures = \"fn:toggle-enabled\"",
+            "gestures = \"shift:toggle-enabled,shift:suggestions\"",
+            "restore_order = \"invalid,invalid\"",
+            "restore_order = \"spelling\"",
+            "restore_disabled = \"pattern-4\"",
+            "bigram_threshold = 128",
+            "min_confidence = 101",
+        ] {
+            let err = EngineConfig::from_toml(toml).unwrap_err();
+            assert_eq!(err.code(), 4, "{}", toml);
+        }
+        let err = EngineConfig::from_toml("max_word_len = \"32\"").unwrap_err();
+        assert_eq!(err.code(), 3);
+        assert!(EngineConfig::from_toml("max_word_len = 63").is_ok());
+    }
+
+    #[test]
+    fn test_conflict() {
+        let err =
+            EngineConfig::from_toml("method = \"vni\"\nbracket_shortcuts = true").unwrap_err();
+        assert!(matches!(err, ConfigError::Conflict(_)));
+        assert_eq!(err.code(), 5);
+        assert!(EngineConfig::from_toml("bracket_shortcuts = true").is_ok());
+
+        // A removal key can't take over a tone or mark key
+        let err = EngineConfig::from_toml("remove_key = \"s\"").unwrap_err();
+        assert!(matches!(err, ConfigError::Conflict(_)));
+        assert_eq!(err.code(), 5);
+        let err = EngineConfig::from_toml("method = \"vni\"\nremove_key = \"6\"").unwrap_err();
+        assert!(matches!(err, ConfigError::Conflict(_)));
+        assert!(EngineConfig::from_toml("remove_key = \"6\"").is_ok());
+        assert!(EngineConfig::from_toml("method = \"vni\"\nremove_key = \"s\"").is_ok());
+    }
+
+    #[test]
+    fn test_correction_packs() {
+        let packs = CorrectionPacks::parse(" southern, northern").unwrap();
+        assert!(packs.contains(Dialect::Northern) && !packs.contains(Dialect::Central));
+        assert_eq!(packs.to_string(), "northern,southern");
+        assert_eq!(CorrectionPacks::parse(""), Some(CorrectionPacks::NONE));
+        assert_eq!(CorrectionPacks::from_bits(packs.bits()), packs);
+        assert_eq!(packs.with(Dialect::Northern, false).to_string(), "southern");
+    }
+
+    #[test]
+    fn test_gestures() {
+        let gestures = Gestures::parse(" ctrl:suggestions, shift : toggle-enabled").unwrap();
+        assert_eq!(
+            gestures.get(GestureKey::Shift),
+            Some(GestureAction::ToggleEnabled)
+        );
+        assert_eq!(gestures.get(GestureKey::Alt), None);
+        assert_eq!(
+            gestures.to_string(),
+            "shift:toggle-enabled,ctrl:suggestions"
+        );
+        assert_eq!(Gestures::parse(""), Some(Gestures::NONE));
+        assert_eq!(
+            GestureKey::from_key(crate::data::keys::RIGHT_SHIFT),
+            Some(GestureKey::Shift)
+        );
+        assert_eq!(GestureKey::from_key(crate::data::keys::CAPS_LOCK), None);
+    }
+
+    #[test]
+    fn test_restore_checks() {
+        let off = RestoreChecks::parse("w-final, invalid").unwrap();
+        assert!(off.contains(RestoreCheck::WFinal) && !off.contains(RestoreCheck::Dictionary));
+        assert_eq!(off.to_string(), "invalid,w-final");
+        assert_eq!(RestoreChecks::from_bits(off.bits()), off);
+        assert_eq!(RestoreChecks::parse(""), Some(RestoreChecks::NONE));
+    }
+
+    #[test]
+    fn test_restore_order() {
+        let order = RestoreOrder::parse("w-final, invalid").unwrap();
+        assert_eq!(
+            order.checks()[..2],
+            [RestoreCheck::WFinal, RestoreCheck::Invalid]
+        );
+        // The rest follow in default order
+        assert_eq!(order.checks()[2], RestoreCheck::ModifierConsonant);
+        assert_eq!(RestoreOrder::parse(""), Some(RestoreOrder::DEFAULT));
+        assert_eq!(RestoreOrder::parse(&order.to_string()), Some(order));
+    }
+
+    #[test]
+    fn test_method_id() {
+        assert_eq!(Method::from_id(Method::Vni.id()), Method::Vni);
+        assert_eq!(Method::from_id(99), Method::Telex);
+    }
+}
@@ -0,0 +1,375 @@
+//! Community correction packs
+//!
+//! Word-fix lists anyone can write and share (medical terms, legal
+//! wording, gaming slang). A loaded pack is a `CorrectionProvider`: add it
+//! with `Engine::add_correction_provider`.
+//!
+//! ```json
+//! {
+//!   "format": "gonhanh-correction-pack",
+//!   "format_version": 1,
+//!   "name": "medical",
+//!   "version": "1.2.0",
+//!   "language": "vi",
+//!   "entries": [
+//!     { "typed": "bịnh", "correct": "bệnh" },
+//!     { "typed": "thuốt", "correct": "thuốc", "after": ["uống", "viên"] }
+//!   ]
+//! }
+//! ```
+//!
+//! - `typed`: the misspelling, one lowercase word
+//! - `correct`: what replaces it (case follows the typed word)
+//! - `after` (optional): only correct right after one of these words.
+//!   The first matching entry wins, so list context entries before a
+//!   general one for the same word. Context comes from the word history,
+//!   so with `Privacy` retention off these entries never apply.
+//!
+//! The pack is shipped with a detached signature, checked by a
+//! `PackVerifier` before anything is parsed. `Checksum` only catches
+//! corruption; hosts with a trust store plug in real public-key
+//! verification. Unknown keys are ignored; a higher `format_version` is
+//! rejected.
+
+use crate::config::MAX_WORD_LEN;
+use crate::engine::correction::CorrectionProvider;
+use crate::engine::shortcut::MAX_REPLACEMENT_LEN;
+use crate::json::{self, Value};
+use crate::storage::crc32;
+use std::collections::HashMap;
+
+/// `format` marker
+pub const PACK_FORMAT: &str = "gonhanh-correction-pack";
+/// Current format version
+pub const PACK_FORMAT_VERSION: u64 = 1;
+/// Largest pack file accepted
+pub const MAX_PACK_BYTES: usize = 512 * 1024;
+/// Most entries in one pack
+pub const MAX_PACK_ENTRIES: usize = 10_000;
+
+/// Error while loading a pack (nothing is loaded)
+#[derive(Debug, PartialEq, Eq)]
+pub enum PackError {
+    /// File larger than `MAX_PACK_BYTES`
+    TooLarge(usize),
+    /// Signature doesn't match the file
+    BadSignature,
+    /// Not UTF-8
+    Encoding,
+    /// Not valid JSON
+    Json(json::ParseError),
+    /// Valid JSON, but not a correction pack
+    NotAPack,
+    /// Written for a newer format
+    UnsupportedVersion(u64),
+    /// More than `MAX_PACK_ENTRIES` entries
+    TooManyEntries(usize),
+    /// Field missing or of the wrong type/value
+    Invalid(String),
+}
+
+impl PackError {
+    /// Stable numeric code for FFI (after ProfileError's 20-23)
+    pub fn code(&self) -> i32 {
+        match self {
+            PackError::TooLarge(_) => 30,
+            PackError::BadSignature => 31,
+            PackError::Encoding => 32,
+            PackError::Json(_) => 33,
+            PackError::NotAPack => 34,
+            PackError::UnsupportedVersion(_) => 35,
+            PackError::TooManyEntries(_) => 36,
+            PackError::Invalid(_) => 37,
+        }
+    }
+}
+
+impl std::fmt::Display for PackError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PackError::TooLarge(n) => {
+                write!(f, "pack is {} bytes (limit {})", n, MAX_PACK_BYTES)
+            }
+            PackError::BadSignature => write!(f, "signature does not match"),
+            PackError::Encoding => write!(f, "pack is not UTF-8"),
+            PackError::Json(e) => write!(f, "{}", e),
+            PackError::NotAPack => write!(f, "not a {} document", PACK_FORMAT),
+            PackError::UnsupportedVersion(v) => write!(
+                f,
+                "pack format {} is newer than supported ({})",
+                v, PACK_FORMAT_VERSION
+            ),
+            PackError::TooManyEntries(n) => {
+                write!(f, "pack has {} entries (limit {})", n, MAX_PACK_ENTRIES)
+            }
+            PackError::Invalid(field) => write!(f, "invalid `{}`", field),
+        }
+    }
+}
+
+impl std::error::Error for PackError {}
+
+/// Checks a pack's detached signature
+pub trait PackVerifier {
+    fn verify(&self, body: &[u8], signature: &str) -> bool;
+}
+
+/// `crc32:xxxxxxxx` signatures: integrity only, anyone can make one
+pub struct Checksum;
+
+impl Checksum {
+    /// Signature for `body`
+    pub fn sign(body: &[u8]) -> String {
+        format!("crc32:{:08x}", crc32(body))
+    }
+}
+
+impl PackVerifier for Checksum {
+    fn verify(&self, body: &[u8], signature: &str) -> bool {
+        signature.trim() == Self::sign(body)
+    }
+}
+
+/// One correction
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PackEntry {
+    pub typed: String,
+    pub correct: String,
+    /// Previous words this entry applies after (empty = anywhere)
+    pub after: Vec<String>,
+}
+
+/// A loaded correction pack
+#[derive(Clone, Debug)]
+pub struct CorrectionPack {
+    pub name: String,
+    pub version: String,
+    pub language: String,
+    entries: Vec<PackEntry>,
+    /// `typed` → entry indices, in file order
+    index: HashMap<String, Vec<usize>>,
+}
+
+impl CorrectionPack {
+    /// Check size and signature, then parse
+    pub fn load(
+        body: &[u8],
+        signature: &str,
+        verifier: &dyn PackVerifier,
+    ) -> Result<CorrectionPack, PackError> {
+        if body.len() > MAX_PACK_BYTES {
+            return Err(PackError::TooLarge(body.len()));
+        }
+        if !verifier.verify(body, signature) {
+            return Err(PackError::BadSignature);
+        }
+        let text = std::str::from_utf8(body).map_err(|_| PackError::Encoding)?;
+        Self::from_value(&json::parse(text).map_err(PackError::Json)?)
+    }
+
+    pub fn entries(&self) -> &[PackEntry] {
+        &self.entries
+    }
+
+    fn from_value(doc: &Value) -> Result<CorrectionPack, PackError> {
+        if doc.get("format").and_then(Value::as_str) != Some(PACK_FORMAT) {
+            return Err(PackError::NotAPack);
+        }
+        let version = doc
+            .get("format_version")
+            .and_then(Value::as_u64)
+            .ok_or_else(|| invalid("format_version"))?;
+        if version > PACK_FORMAT_VERSION {
+            return Err(PackError::UnsupportedVersion(version));
+        }
+        let text = |key: &str| {
+            doc.get(key)
+                .and_then(Value::as_str)
+                .filter(|s| !s.trim().is_empty())
+                .map(str::to_string)
+                .ok_or_else(|| invalid(key))
+        };
+        let (name, version, language) = (text("name")?, text("version")?, text("language")?);
+
+        let list = doc
+            .get("entries")
+            .and_then(Value::as_array)
+            .ok_or_else(|| invalid("entries"))?;
+        if list.len() > MAX_PACK_ENTRIES {
+            return Err(PackError::TooManyEntries(list.len()));
+        }
+        let entries: Vec<PackEntry> = list.iter().map(entry_from_json).collect::<Result<_, _>>()?;
+
+        let mut index: HashMap<String, Vec<usize>> = HashMap::new();
+        for (i, e) in entries.iter().enumerate() {
+            index.entry(e.typed.clone()).or_default().push(i);
+        }
+        Ok(CorrectionPack {
+            name,
+            version,
+            language,
+            entries,
+            index,
+        })
+    }
+}
+
+impl CorrectionProvider for CorrectionPack {
+    fn correct(&self, word: &str, prev: Option<&str>) -> Option<String> {
+        self.index
+            .get(word)?
+            .iter()
+            .map(|&i| &self.entries[i])
+            .find(|e| e.after.is_empty() || prev.is_some_and(|p| e.after.iter().any(|a| a == p)))
+            .map(|e| e.correct.clone())
+    }
+}
+
+fn invalid(field: &str) -> PackError {
+    PackError::Invalid(field.into())
+}
+
+/// One lowercase word that fits the typing buffer
+fn is_word(s: &str) -> bool {
+    let len = s.chars().count();
+    (1..=MAX_WORD_LEN as usize).contains(&len)
+        && !s.chars().any(char::is_whitespace)
+        && s.to_lowercase() == s
+}
+
+fn entry_from_json(v: &Value) -> Result<PackEntry, PackError> {
+    let text = |key: &str| {
+        v.get(key)
+            .and_then(Value::as_str)
+            .ok_or_else(|| invalid(&format!("entries.{}", key)))
+    };
+    let typed = text("typed")?;
+    if !is_word(typed) {
+        return Err(invalid("entries.typed"));
+    }
+    let correct = text("correct")?;
+    let len = correct.chars().count();
+    if len == 0 || len > MAX_REPLACEMENT_LEN {
+        return Err(invalid("entries.correct"));
+    }
+    let after = match v.get("after") {
+        None => Vec::new(),
+        Some(list) => list
+            .as_array()
+            .ok_or_else(|| invalid("entries.after"))?
+            .iter()
+            .map(|w| w.as_str().filter(|w| is_word(w)).map(str::to_string))
+            .collect::<Option<_>>()
+            .ok_or_else(|| invalid("entries.after"))?,
+    };
+    Ok(PackEntry {
+        typed: typed.to_string(),
+        correct: correct.to_string(),
+        after,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MEDICAL: &str = r#"{
+  "format": "gonhanh-correction-pack",
+  "format_version": 1,
+  "name": "medical",
+  "version": "1.2.0",
+  "language": "vi",
+  "entries": [
+    { "typed": "bịnh", "correct": "bệnh" },
+    { "typed": "thuốt", "correct": "thuốc", "after": ["uống", "viên"] },
+    { "typed": "thuốt", "correct": "thuốt (?)" }
+  ],
+  "homepage": "ignored"
+}"#;
+
+    fn load(body: &str) -> Result<CorrectionPack, PackError> {
+        CorrectionPack::load(body.as_bytes(), &Checksum::sign(body.as_bytes()), &Checksum)
+    }
+
+    #[test]
+    fn test_load() {
+        let pack = load(MEDICAL).unwrap();
+        assert_eq!(
+            (
+                pack.name.as_str(),
+                pack.version.as_str(),
+                pack.language.as_str()
+            ),
+            ("medical", "1.2.0", "vi")
+        );
+        assert_eq!(pack.entries().len(), 3);
+        assert_eq!(pack.correct("bịnh", None).as_deref(), Some("bệnh"));
+        assert_eq!(pack.correct("bệnh", None), None);
+    }
+
+    #[test]
+    fn test_context_rules() {
+        let pack = load(MEDICAL).unwrap();
+        assert_eq!(
+            pack.correct("thuốt", Some("uống")).as_deref(),
+            Some("thuốc")
+        );
+        assert_eq!(
+            pack.correct("thuốt", Some("ăn")).as_deref(),
+            Some("thuốt (?)")
+        );
+        assert_eq!(pack.correct("thuốt", None).as_deref(), Some("thuốt (?)"));
+    }
+
+    #[test]
+    fn test_signature() {
+        let body = MEDICAL.as_bytes();
+        let err = CorrectionPack::load(body, "crc32:00000000", &Checksum).unwrap_err();
+        assert_eq!(err, PackError::BadSignature);
+
+        let mut tampered = body.to_vec();
+        tampered[body.len() - 3] = b' ';
+        let err = CorrectionPack::load(&tampered, &Checksum::sign(body), &Checksum).unwrap_err();
+        assert_eq!(err.code(), 31);
+    }
+
+    #[test]
+    fn test_size_limits() {
+        let big = vec![b' '; MAX_PACK_BYTES + 1];
+        let err = CorrectionPack::load(&big, &Checksum::sign(&big), &Checksum).unwrap_err();
+        assert_eq!(err, PackError::TooLarge(MAX_PACK_BYTES + 1));
+
+        let entries = vec![r#"{"typed":"a","correct":"b"}"#; MAX_PACK_ENTRIES + 1].join(",");
+        let body = MEDICAL.replacen(
+            r#"{ "typed": "bịnh", "correct": "bệnh" },"#,
+            &format!("{},", entries),
+            1,
+        );
+        assert_eq!(
+            load(&body).unwrap_err(),
+            PackError::TooManyEntries(MAX_PACK_ENTRIES + 3)
+        );
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert_eq!(load("{}").unwrap_err(), PackError::NotAPack);
+        assert_eq!(
+            load(&MEDICAL.replace("\"format_version\": 1", "\"format_version\": 9")).unwrap_err(),
+            PackError::UnsupportedVersion(9)
+        );
+        for (from, to, field) in [
+            ("\"bịnh\"", "\"Bịnh\"", "entries.typed"),
+            ("\"bịnh\"", "\"bịnh bịnh\"", "entries.typed"),
+            ("\"bệnh\"", "\"\"", "entries.correct"),
+            ("[\"uống\", \"viên\"]", "\"uống\"", "entries.after"),
+            ("\"medical\"", "\" \"", "name"),
+        ] {
+            let err = load(&MEDICAL.replacen(from, to, 1)).unwrap_err();
+            assert_eq!(err, PackError::Invalid(field.into()), "{}", to);
+        }
+        assert_eq!(load("{").unwrap_err().code(), 33);
+        let err = CorrectionPack::load(&[0xff], &Checksum::sign(&[0xff]), &Checksum);
+        assert_eq!(err.unwrap_err(), PackError::Encoding);
+    }
+}
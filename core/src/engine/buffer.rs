@@ -1,9 +1,46 @@
 //! Typing buffer
+//!
+//! One `Char` per displayed grapheme (always a single precomposed code point
+//! for Vietnamese), each carrying the keystrokes that produced it. Backspace
+//! pops a whole grapheme together with its keystrokes, so restore never
+//! drifts out of sync with what is on screen.
 
 pub const MAX: usize = 64;
 
+/// Max keystrokes kept per character (a + a + s → ấ uses 3)
+pub const MAX_KEYS: usize = 6;
+
+use crate::data::{chars, keys};
 use crate::utils;
 
+/// Original input event that contributed to a character
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Keystroke {
+    pub key: u16,
+    pub caps: bool,
+    /// Position in the word's input sequence (restore sorts by this)
+    pub seq: u16,
+    /// Character composed by the OS layout (text input), if any
+    pub text: Option<char>,
+}
+
+impl Keystroke {
+    pub fn new(key: u16, caps: bool, seq: u16) -> Self {
+        Self {
+            key,
+            caps,
+            seq,
+            text: None,
+        }
+    }
+
+    /// Character this keystroke types without Vietnamese transforms
+    pub fn to_char(&self) -> Option<char> {
+        self.text
+            .or_else(|| utils::key_to_char(self.key, self.caps))
+    }
+}
+
 /// Single character in buffer
 ///
 /// Modifiers:
@@ -17,6 +54,8 @@ pub struct Char {
     pub tone: u8,     // 0=none, 1=circumflex(^), 2=horn/breve
     pub mark: u8,     // 0=none, 1=sắc, 2=huyền, 3=hỏi, 4=ngã, 5=nặng
     pub stroke: bool, // true if 'd' → 'đ' (stroke through)
+    origin: [Keystroke; MAX_KEYS],
+    origin_len: u8,
 }
 
 impl Char {
@@ -27,9 +66,43 @@ impl Char {
             tone: 0,
             mark: 0,
             stroke: false,
+            origin: [Keystroke::default(); MAX_KEYS],
+            origin_len: 0,
+        }
+    }
+
+    /// Displayed character (đ, ấ, ư... or the plain letter)
+    pub fn to_char(&self) -> Option<char> {
+        if self.key == keys::D && self.stroke {
+            Some(chars::get_d(self.caps))
+        } else {
+            chars::to_char(self.key, self.caps, self.tone, self.mark)
+                .or_else(|| utils::key_to_char(self.key, self.caps))
+        }
+    }
+
+    /// Keystrokes that produced this character
+    pub fn origin(&self) -> &[Keystroke] {
+        &self.origin[..self.origin_len as usize]
+    }
+
+    /// Record a keystroke; extra keystrokes beyond `MAX_KEYS` are dropped
+    pub fn push_origin(&mut self, k: Keystroke) {
+        if (self.origin_len as usize) < MAX_KEYS {
+            self.origin[self.origin_len as usize] = k;
+            self.origin_len += 1;
         }
     }
 
+    /// Packed visible state (key, case, diacritics), used to detect changes
+    fn state(&self) -> u32 {
+        (self.key as u32) << 16
+            | (self.tone as u32) << 8
+            | (self.mark as u32) << 4
+            | (self.stroke as u32) << 1
+            | self.caps as u32
+    }
+
     pub fn has_tone(&self) -> bool {
         self.tone > 0
     }
@@ -39,6 +112,40 @@ impl Char {
     }
 }
 
+/// Visible buffer state before a keystroke (see `Buffer::record`)
+pub struct Snapshot {
+    state: [u32; MAX],
+    len: usize,
+}
+
+/// Keycodes of the buffer, copied on the stack (see `Buffer::keys`)
+pub struct Keys {
+    keys: [u16; MAX],
+    len: usize,
+}
+
+impl std::ops::Deref for Keys {
+    type Target = [u16];
+
+    fn deref(&self) -> &[u16] {
+        &self.keys[..self.len]
+    }
+}
+
+/// Displayed text of the buffer, on the stack (see `Buffer::shown`)
+pub struct Shown {
+    chars: [char; MAX],
+    len: usize,
+}
+
+impl std::ops::Deref for Shown {
+    type Target = [char];
+
+    fn deref(&self) -> &[char] {
+        &self.chars[..self.len]
+    }
+}
+
 /// Typing buffer
 pub struct Buffer {
     data: [Char; MAX],
@@ -79,6 +186,13 @@ impl Buffer {
         self.len = 0;
     }
 
+    /// Drop the first `n` characters, keeping the rest in order
+    pub fn drain_front(&mut self, n: usize) {
+        let n = n.min(self.len);
+        self.data.copy_within(n..self.len, 0);
+        self.len -= n;
+    }
+
     pub fn len(&self) -> usize {
         self.len
     }
@@ -111,9 +225,80 @@ impl Buffer {
         }
     }
 
+    /// Displayed characters, without allocating
+    ///
+    /// Empty if a character has no display form (nothing to compare).
+    pub fn shown(&self) -> Shown {
+        let mut chars = ['\0'; MAX];
+        for (slot, c) in chars.iter_mut().zip(self.iter()) {
+            match c.to_char() {
+                Some(ch) => *slot = ch,
+                None => return Shown { chars, len: 0 },
+            }
+        }
+        Shown {
+            chars,
+            len: self.len,
+        }
+    }
+
+    /// Keycodes in order, without allocating
+    pub fn keys(&self) -> Keys {
+        let mut keys = [0; MAX];
+        for (k, c) in keys.iter_mut().zip(self.iter()) {
+            *k = c.key;
+        }
+        Keys {
+            keys,
+            len: self.len,
+        }
+    }
+
+    /// Capture visible state before processing a keystroke
+    pub fn snapshot(&self) -> Snapshot {
+        let mut state = [0; MAX];
+        for (s, c) in state.iter_mut().zip(self.iter()) {
+            *s = c.state();
+        }
+        Snapshot {
+            state,
+            len: self.len,
+        }
+    }
+
+    /// Attach a keystroke to the character it produced or modified
+    ///
+    /// - New character appended: the keystroke belongs to it
+    /// - Existing character changed (tone, mark, stroke): the last changed one
+    /// - Nothing visible changed: the last character
+    pub fn record(&mut self, before: &Snapshot, k: Keystroke) {
+        let target = if self.len > before.len {
+            self.len.checked_sub(1)
+        } else {
+            (0..self.len)
+                .rev()
+                .find(|&i| self.data[i].state() != before.state[i])
+                .or(self.len.checked_sub(1))
+        };
+        if let Some(i) = target {
+            self.data[i].push_origin(k);
+        }
+    }
+
+    /// All keystrokes of the word in typing order
+    pub fn keystrokes(&self) -> Vec<Keystroke> {
+        let mut all: Vec<Keystroke> = self.iter().flat_map(|c| c.origin().to_vec()).collect();
+        all.sort_by_key(|k| k.seq);
+        all
+    }
+
+    /// Displayed text
+    pub fn to_display_string(&self) -> String {
+        self.iter().filter_map(|c| c.to_char()).collect()
+    }
+
     /// Find indices of vowels in buffer
     pub fn find_vowels(&self) -> Vec<usize> {
-        use crate::data::keys;
         (0..self.len)
             .filter(|&i| keys::is_vowel(self.data[i].key))
             .collect()
@@ -121,7 +306,6 @@ impl Buffer {
 
     /// Find vowel position by key (from end)
     pub fn find_vowel_by_key(&self, key: u16) -> Option<usize> {
-        use crate::data::keys;
         (0..self.len)
             .rev()
             .find(|&i| self.data[i].key == key && keys::is_vowel(key))
@@ -142,42 +326,43 @@ impl Buffer {
 
     /// Convert buffer to string preserving case (for shortcut case matching)
     pub fn to_string_preserve_case(&self) -> String {
-        use crate::data::keys;
-        self.data[..self.len]
-            .iter()
-            .filter_map(|c| {
-                let ch = match c.key {
-                    keys::A => 'a',
-                    keys::B => 'b',
-                    keys::C => 'c',
-                    keys::D => 'd',
-                    keys::E => 'e',
-                    keys::F => 'f',
-                    keys::G => 'g',
-                    keys::H => 'h',
-                    keys::I => 'i',
-                    keys::J => 'j',
-                    keys::K => 'k',
-                    keys::L => 'l',
-                    keys::M => 'm',
-                    keys::N => 'n',
-                    keys::O => 'o',
-                    keys::P => 'p',
-                    keys::Q => 'q',
-                    keys::R => 'r',
-                    keys::S => 's',
-                    keys::T => 't',
-                    keys::U => 'u',
-                    keys::V => 'v',
-                    keys::W => 'w',
-                    keys::X => 'x',
-                    keys::Y => 'y',
-                    keys::Z => 'z',
-                    _ => return None,
-                };
-                Some(if c.caps { ch.to_ascii_uppercase() } else { ch })
-            })
-            .collect()
+        self.letters().collect()
+    }
+
+    /// The letters `to_string_preserve_case` spells, one at a time
+    pub fn letters(&self) -> impl Iterator<Item = char> + Clone + '_ {
+        self.data[..self.len].iter().filter_map(|c| {
+            let ch = match c.key {
+                keys::A => 'a',
+                keys::B => 'b',
+                keys::C => 'c',
+                keys::D => 'd',
+                keys::E => 'e',
+                keys::F => 'f',
+                keys::G => 'g',
+                keys::H => 'h',
+                keys::I => 'i',
+                keys::J => 'j',
+                keys::K => 'k',
+                keys::L => 'l',
+                keys::M => 'm',
+                keys::N => 'n',
+                keys::O => 'o',
+                keys::P => 'p',
+                keys::Q => 'q',
+                keys::R => 'r',
+                keys::S => 's',
+                keys::T => 't',
+                keys::U => 'u',
+                keys::V => 'v',
+                keys::W => 'w',
+                keys::X => 'x',
+                keys::Y => 'y',
+                keys::Z => 'z',
+                _ => return None,
+            };
+            Some(if c.caps { ch.to_ascii_uppercase() } else { ch })
+        })
     }
 }
 
@@ -201,4 +386,56 @@ mod tests {
         buf.clear();
         assert!(buf.is_empty());
     }
+
+    #[test]
+    fn test_record_new_char() {
+        let mut buf = Buffer::new();
+        let before = buf.snapshot();
+        buf.push(Char::new(keys::A, false));
+        buf.record(&before, Keystroke::new(keys::A, false, 0));
+        assert_eq!(buf.get(0).unwrap().origin().len(), 1);
+    }
+
+    #[test]
+    fn test_record_modified_char() {
+        let mut buf = Buffer::new();
+        for (seq, key) in [keys::T, keys::O, keys::A, keys::N].into_iter().enumerate() {
+            let before = buf.snapshot();
+            buf.push(Char::new(key, false));
+            buf.record(&before, Keystroke::new(key, false, seq as u16));
+        }
+
+        // "s" marks the 'a' (position 2), not the last char
+        let before = buf.snapshot();
+        buf.get_mut(2).unwrap().mark = chars::mark::SAC;
+        buf.record(&before, Keystroke::new(keys::S, false, 4));
+        assert_eq!(buf.get(2).unwrap().origin().len(), 2);
+        assert_eq!(buf.to_display_string(), "toán");
+
+        // Keystrokes come back in typing order
+        let raw: String = buf
+            .keystrokes()
+            .iter()
+            .filter_map(|k| k.to_char())
+            .collect();
+        assert_eq!(raw, "toans");
+
+        // Backspace drops 'n' with its keystroke only
+        buf.pop();
+        let raw: String = buf
+            .keystrokes()
+            .iter()
+            .filter_map(|k| k.to_char())
+            .collect();
+        assert_eq!(raw, "toas");
+    }
+
+    #[test]
+    fn test_origin_overflow_is_dropped() {
+        let mut c = Char::new(keys::A, false);
+        for seq in 0..(MAX_KEYS as u16 + 2) {
+            c.push_origin(Keystroke::new(keys::A, false, seq));
+        }
+        assert_eq!(c.origin().len(), MAX_KEYS);
+    }
 }
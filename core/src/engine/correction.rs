@@ -0,0 +1,433 @@
+//! Corrections - fixing misspelled words at the word boundary
+//!
+//! When a word is finished with space, the engine asks a chain of
+//! `CorrectionProvider`s for a replacement; the first answer wins. Hosts
+//! add their own providers (such as community packs, see
+//! `crate::correction_pack`) in front of the built-in dialect packs
+//! (`gonhanh_data::corrections`), which are toggled by `CorrectionPacks`.
+//!
+//! Providers only see bare words: punctuation around the word ("(nàm,",
+//! "“nàm”") is stripped before the lookup and put back around the answer.
+//! Hyphens and apostrophes inside a token ("nàm-nàm", "ng'ười") split it
+//! into halves that are looked up on their own, each after the one before,
+//! and joined back with the same separators.
+//!
+//! Last in the chain is the i/y rule (`IyStyle`): not a list of pairs but
+//! a respelling of any syllable where both are in use ("lí" ↔ "lý").
+//!
+//! The packs' misspellings are also kept by their letters (`typos`), so
+//! the engine knows while a word is typed whether it is one of them: a
+//! host can underline it before it is fixed (`Engine::typo`).
+
+use super::trie::{LetterTrie, Node};
+use crate::config::{CorrectionPacks, Dialect, IyStyle};
+use crate::data::chars;
+use crate::data::corrections::{self, Pack};
+use crate::data::keys;
+use crate::search::search_key;
+use crate::tokenizer::is_punctuation;
+use std::sync::OnceLock;
+
+/// Source of corrections
+pub trait CorrectionProvider: Send {
+    /// Correct form of `word`, or `None` to pass
+    ///
+    /// Both `word` and `prev`, the word typed before it (if history is
+    /// kept), are composed (NFC), lowercase and stripped of surrounding
+    /// punctuation.
+    fn correct(&self, word: &str, prev: Option<&str>) -> Option<String>;
+}
+
+/// Built-in dialect packs
+fn pack(dialect: Dialect) -> &'static Pack {
+    match dialect {
+        Dialect::Northern => &corrections::NORTHERN,
+        Dialect::Central => &corrections::CENTRAL,
+        Dialect::Southern => &corrections::SOUTHERN,
+    }
+}
+
+/// A misspelling in a built-in pack
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Typo {
+    pub dialect: Dialect,
+    pub typed: &'static str,
+    pub fixed: &'static str,
+}
+
+/// Misspellings of every built-in pack, under their letters without
+/// diacritics ("mún" under "mun"), in pack order
+pub fn typos() -> &'static LetterTrie<Typo> {
+    static TYPOS: OnceLock<LetterTrie<Typo>> = OnceLock::new();
+    TYPOS.get_or_init(|| {
+        let mut trie = LetterTrie::new();
+        for dialect in Dialect::ALL {
+            for &(typed, fixed) in pack(dialect).entries {
+                let typo = Typo {
+                    dialect,
+                    typed,
+                    fixed,
+                };
+                trie.insert(&search_key(typed).key, typo);
+            }
+        }
+        trie
+    })
+}
+
+impl CorrectionProvider for CorrectionPacks {
+    fn correct(&self, word: &str, _prev: Option<&str>) -> Option<String> {
+        Dialect::ALL
+            .into_iter()
+            .filter(|&d| self.contains(d))
+            .find_map(|d| pack(d).lookup(word))
+            .map(str::to_string)
+    }
+}
+
+impl CorrectionProvider for IyStyle {
+    fn correct(&self, word: &str, _prev: Option<&str>) -> Option<String> {
+        let want_y = match self {
+            IyStyle::Off => return None,
+            IyStyle::PreferY => true,
+            IyStyle::PreferI => false,
+        };
+        if iy_variant(word)? == want_y {
+            return None;
+        }
+        swap_iy(word)
+    }
+}
+
+/// Whether `word` is a syllable spelled either way that uses y ("lý": true,
+/// "lí": false); `None` for any other word, toneless ones included
+pub(crate) fn iy_variant(word: &str) -> Option<bool> {
+    if word.is_ascii() {
+        return None;
+    }
+    let key = search_key(word).key;
+    let (head, last) = key.split_at(key.len().checked_sub(1)?);
+    // Not v: "vì" is never "vỳ"
+    if !matches!(head, "h" | "k" | "l" | "m" | "qu" | "s" | "t") {
+        return None;
+    }
+    match last {
+        "i" => Some(false),
+        "y" => Some(true),
+        _ => None,
+    }
+}
+
+/// `word` with its final i and y swapped, case kept and the tone mark on
+/// the new final ("qúi" → "quý")
+pub(crate) fn swap_iy(word: &str) -> Option<String> {
+    let mut chars: Vec<char> = word.chars().collect();
+    let (last, head) = chars.split_last_mut()?;
+    let mut mark = 0;
+    for c in head {
+        if let Some(p) = chars::parse_char(*c).filter(|p| p.mark != 0) {
+            mark = p.mark;
+            *c = chars::to_char(p.key, p.caps, p.tone, 0)?;
+        }
+    }
+    let p = chars::parse_char(*last)?;
+    let key = if p.key == keys::I { keys::Y } else { keys::I };
+    *last = chars::to_char(key, p.caps, p.tone, p.mark.max(mark))?;
+    Some(chars.into_iter().collect())
+}
+
+/// Most candidates on offer: one per number key
+pub const MAX_CANDIDATES: usize = 9;
+
+/// Correction on offer (`CorrectionStyle::Suggest`)
+///
+/// While it's on offer, keys work the list the same way on every host:
+///
+/// | key                 | list                   | screen, buffer                  |
+/// |---------------------|------------------------|---------------------------------|
+/// | accept key (Tab)    | closed                 | word → selected candidate       |
+/// | Down / Up           | selection moves, wraps | unchanged; key swallowed        |
+/// | 1-9, after Down/Up  | closed                 | word → that candidate           |
+/// | Esc, after Down/Up  | closed                 | unchanged; key swallowed        |
+/// | anything else       | closed                 | the key does its usual job      |
+///
+/// Down and Up only browse when there is more than one candidate; until
+/// then, digits and Esc keep their usual job too. Accepting retypes the
+/// word and the character after it, and the next word starts afresh; no
+/// other path touches the screen. The buffer is empty throughout (the word
+/// was already finished).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Suggestion {
+    /// Word as it stands on screen
+    pub word: String,
+    /// Corrections, best first (at least one, at most `MAX_CANDIDATES`)
+    pub candidates: Vec<String>,
+    /// Index of the one accepting applies
+    pub selected: usize,
+    /// Down or Up was pressed: number keys pick, Esc closes
+    pub browsing: bool,
+    /// Character typed after the word, retyped on accepting
+    pub after: char,
+}
+
+impl Suggestion {
+    /// What accepting turns the word into
+    pub fn fixed(&self) -> &str {
+        &self.candidates[self.selected]
+    }
+
+    /// Move the selection by `step`, wrapping around
+    pub(crate) fn browse(&mut self, step: isize) {
+        let n = self.candidates.len() as isize;
+        self.selected = (self.selected as isize + step).rem_euclid(n) as usize;
+        self.browsing = true;
+    }
+}
+
+/// Provider chain: host providers in the order added, then the packs,
+/// then the i/y rule
+#[derive(Default)]
+pub struct Corrections {
+    pub packs: CorrectionPacks,
+    pub iy: IyStyle,
+    providers: Vec<Box<dyn CorrectionProvider>>,
+}
+
+impl Corrections {
+    pub fn push(&mut self, provider: impl CorrectionProvider + 'static) {
+        self.providers.push(Box::new(provider));
+    }
+
+    /// Replacement for `word` after `prev`, in the word's case and with
+    /// its punctuation ("Nàm," → "Làm,", "nàm-nàm" → "làm-làm"); the
+    /// answer is composed even if `word` was not
+    pub fn correct(&self, word: &str, prev: Option<&str>) -> Option<String> {
+        if self.providers.is_empty()
+            && self.packs == CorrectionPacks::NONE
+            && self.iy == IyStyle::Off
+        {
+            return None;
+        }
+        let word = chars::compose(word);
+        let (lead, core, trail) = split_punctuation(&word);
+        let mut prev = prev.map(|p| chars::compose(split_punctuation(p).1).to_lowercase());
+        let mut out = String::from(lead);
+        let mut fixed_any = false;
+        for part in core.split_inclusive(is_joiner) {
+            let half = part.trim_end_matches(is_joiner);
+            let lower = half.to_lowercase();
+            let fixed = match half {
+                "" => None,
+                _ => self.lookup(&lower, prev.as_deref()),
+            };
+            match fixed {
+                Some(fixed) => {
+                    out.push_str(&match_case(half, &fixed));
+                    fixed_any = true;
+                }
+                _ => out.push_str(half),
+            }
+            out.push_str(&part[half.len()..]);
+            prev = Some(lower);
+        }
+        out.push_str(trail);
+        fixed_any.then_some(out)
+    }
+
+    /// `(typed, correct)` entries of the enabled packs whose typed word
+    /// is spelled `letters` without diacritics (`search_key`)
+    pub fn spelled(
+        &self,
+        letters: &str,
+    ) -> impl Iterator<Item = (&'static str, &'static str)> + '_ {
+        let trie = typos();
+        let found = trie
+            .walk(letters.chars())
+            .map_or(&[][..], |n| trie.values(n));
+        found
+            .iter()
+            .filter(|t| self.packs.contains(t.dialect))
+            .map(|t| (t.typed, t.fixed))
+    }
+
+    /// Fix an enabled pack has for `word` (composed, any case), from the
+    /// misspellings at `node` of `typos()`
+    pub fn typo_at(&self, node: Node, word: &[char]) -> Option<&'static str> {
+        typos()
+            .values(node)
+            .iter()
+            .filter(|t| self.packs.contains(t.dialect))
+            .find(|t| {
+                word.iter()
+                    .flat_map(|c| c.to_lowercase())
+                    .eq(t.typed.chars())
+            })
+            .map(|t| t.fixed)
+    }
+
+    /// Only the built-in packs can fix a word: no host provider and no
+    /// i/y rule
+    pub fn packs_only(&self) -> bool {
+        self.providers.is_empty() && self.iy == IyStyle::Off
+    }
+
+    fn lookup(&self, word: &str, prev: Option<&str>) -> Option<String> {
+        self.providers
+            .iter()
+            .find_map(|p| p.correct(word, prev))
+            .or_else(|| self.packs.correct(word, prev))
+            .or_else(|| self.iy.correct(word, prev))
+    }
+}
+
+/// Separators that join two halves of one token: "on-line", "d'accord"
+fn is_joiner(c: char) -> bool {
+    matches!(c, '-' | '\'' | '’')
+}
+
+/// `word` as (leading punctuation, the word itself, trailing punctuation)
+fn split_punctuation(word: &str) -> (&str, &str, &str) {
+    let rest = word.trim_start_matches(is_punctuation);
+    let core = rest.trim_end_matches(is_punctuation);
+    let lead = &word[..word.len() - rest.len()];
+    (lead, core, &rest[core.len()..])
+}
+
+/// `fixed` in the case of `word`: all caps or first letter capitalized
+pub(crate) fn match_case(word: &str, fixed: &str) -> String {
+    let mut letters = word.chars().filter(|c| c.is_alphabetic());
+    match letters.next() {
+        Some(first) if first.is_uppercase() => {
+            if word.chars().count() > 1 && letters.all(char::is_uppercase) {
+                return fixed.to_uppercase();
+            }
+            let mut chars = fixed.chars();
+            chars
+                .next()
+                .map(|c| c.to_uppercase().chain(chars).collect())
+                .unwrap_or_default()
+        }
+        _ => fixed.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Slang;
+
+    impl CorrectionProvider for Slang {
+        fn correct(&self, word: &str, _prev: Option<&str>) -> Option<String> {
+            (word == "nàm").then(|| "nàm (slang)".to_string())
+        }
+    }
+
+    fn southern() -> Corrections {
+        Corrections {
+            packs: CorrectionPacks::NONE.with(Dialect::Southern, true),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_packs_off_by_default() {
+        let c = Corrections::default();
+        assert_eq!(c.correct("nàm", None), None);
+    }
+
+    #[test]
+    fn test_pack_toggle() {
+        let mut c = southern();
+        assert_eq!(c.correct("nàm", None).as_deref(), Some("làm"));
+        assert_eq!(c.correct("dượu", None), None);
+        c.packs = c.packs.with(Dialect::Northern, true);
+        assert_eq!(c.correct("dượu", None).as_deref(), Some("rượu"));
+    }
+
+    #[test]
+    fn test_typos() {
+        let c = southern();
+        let node = typos().walk("mun".chars()).unwrap();
+        let word = |s: &str| s.chars().collect::<Vec<_>>();
+        assert_eq!(c.typo_at(node, &word("Mún")), Some("muốn"));
+        assert_eq!(c.typo_at(node, &word("mun")), None);
+        let node = typos().walk("duou".chars()).unwrap();
+        assert_eq!(c.typo_at(node, &word("dượu")), None);
+        assert_eq!(c.spelled("luoc").collect::<Vec<_>>(), [("lước", "nước")]);
+    }
+
+    #[test]
+    fn test_decomposed() {
+        let c = southern();
+        assert_eq!(c.correct("Na\u{0300}m,", None).as_deref(), Some("Làm,"));
+        assert_eq!(c.correct("nu\u{031B}o\u{031B}\u{0301}c", None), None);
+    }
+
+    #[test]
+    fn test_case() {
+        let c = southern();
+        assert_eq!(c.correct("Nàm", None).as_deref(), Some("Làm"));
+        assert_eq!(c.correct("NÀM", None).as_deref(), Some("LÀM"));
+    }
+
+    #[test]
+    fn test_punctuation() {
+        let c = southern();
+        for (word, fixed) in [
+            ("nàm,", "làm,"),
+            ("(nàm)", "(làm)"),
+            ("“Nàm”", "“Làm”"),
+            ("«nàm»…", "«làm»…"),
+            ("nàm?!", "làm?!"),
+        ] {
+            assert_eq!(c.correct(word, None).as_deref(), Some(fixed), "{}", word);
+        }
+        assert_eq!(c.correct("...", None), None);
+        assert_eq!(split_punctuation("“a”"), ("“", "a", "”"));
+    }
+
+    #[test]
+    fn test_joined_halves() {
+        let c = southern();
+        for (word, fixed) in [
+            ("nàm-nàm", "làm-làm"),
+            ("Nàm-lói,", "Làm-nói,"),
+            ("nàm'on", "làm'on"),
+            ("on-nàm", "on-làm"),
+        ] {
+            assert_eq!(c.correct(word, None).as_deref(), Some(fixed), "{}", word);
+        }
+        assert_eq!(c.correct("on-line", None), None);
+        assert_eq!(c.correct("d'accord", None), None);
+    }
+
+    #[test]
+    fn test_host_provider_first() {
+        let mut c = southern();
+        c.push(Slang);
+        assert_eq!(c.correct("nàm", None).as_deref(), Some("nàm (slang)"));
+        assert_eq!(c.correct("lói", None).as_deref(), Some("nói"));
+    }
+
+    #[test]
+    fn test_iy_rule() {
+        let mut c = Corrections {
+            iy: IyStyle::PreferY,
+            ..Default::default()
+        };
+        assert_eq!(c.correct("Lí,", None).as_deref(), Some("Lý,"));
+        assert_eq!(c.correct("kĩ", None).as_deref(), Some("kỹ"));
+        assert_eq!(c.correct("QUÍ", None).as_deref(), Some("QUÝ"));
+        assert_eq!(c.correct("qúi", None).as_deref(), Some("quý"));
+        for word in ["lý", "vì", "thì", "li", "hi", "tìm"] {
+            assert_eq!(c.correct(word, None), None, "{}", word);
+        }
+        c.iy = IyStyle::PreferI;
+        assert_eq!(c.correct("mỹ", None).as_deref(), Some("mĩ"));
+        assert_eq!(c.correct("lí", None), None);
+        // Alongside the packs
+        c.packs = CorrectionPacks::NONE.with(Dialect::Southern, true);
+        assert_eq!(c.correct("nàm-kỹ", None).as_deref(), Some("làm-kĩ"));
+    }
+}
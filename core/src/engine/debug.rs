@@ -0,0 +1,209 @@
+//! Debug State - the engine's word state, for tests and tools
+//!
+//! `Engine::debug_state()` copies out everything the engine keeps about
+//! the word being typed: each buffered character with its diacritics and
+//! the keystrokes that made it, the keystrokes in the order typed, the
+//! last transform (what a repeated key would undo), and the modes the next
+//! key depends on. Tests assert on it, the corpus replayer prints it after
+//! every key (`bench-corpus --trace`), and a host's dev build can show it
+//! as an overlay (`ime_debug_state`).
+//!
+//! It is a copy, built on request: nothing here is kept up to date while
+//! typing, and the settings are in `Engine::config` instead.
+
+use super::buffer::Keystroke;
+use super::spell::SpellStatus;
+use super::Engine;
+use crate::json::Value;
+
+/// One character of the word (see `buffer::Char`)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DebugChar {
+    /// As shown ("ấ")
+    pub shown: char,
+    /// Base key (`keys::A`)
+    pub key: u16,
+    pub caps: bool,
+    /// 0 = none, 1 = circumflex, 2 = horn or breve
+    pub tone: u8,
+    /// 0 = none, 1 = sắc, 2 = huyền, 3 = hỏi, 4 = ngã, 5 = nặng
+    pub mark: u8,
+    /// d → đ
+    pub stroke: bool,
+    /// Keystrokes that made it ("a", "a", "s" for "ấ")
+    pub origin: Vec<Keystroke>,
+}
+
+/// Snapshot of `Engine::debug_state`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DebugState {
+    /// 0 = Telex, 1 = VNI
+    pub method: u8,
+    pub enabled: bool,
+    /// Vietnamese transforms are off until the next break (after "@",
+    /// in "snake_case")
+    pub raw_mode: bool,
+    /// The word outgrew `max_word_len` and is ignored until a break
+    pub overflowed: bool,
+    /// Word being typed
+    pub chars: Vec<DebugChar>,
+    /// Its keystrokes in the order typed: what a restore would give back
+    pub raw: String,
+    /// Screen text while the word's edits are held back
+    pub held_back: Option<String>,
+    /// Last transform, which the same key again would undo
+    pub last_transform: Option<String>,
+    pub spell: SpellStatus,
+    /// Fix of the known misspelling the word is (`Engine::typo`)
+    pub typo: Option<&'static str>,
+    /// Shortcut trigger the word spells, if any
+    pub trigger: Option<String>,
+    /// Correction on offer (`Engine::suggestion`)
+    pub suggestion: Option<String>,
+}
+
+impl DebugState {
+    /// Word as shown
+    pub fn shown(&self) -> String {
+        self.chars.iter().map(|c| c.shown).collect()
+    }
+
+    /// `{"method", "enabled", "raw_mode", "overflowed", "shown", "raw",
+    /// "chars": [{"shown", "key", "caps", "tone", "mark", "stroke",
+    /// "origin": [{"key", "seq"}]}], "held_back", "last_transform",
+    /// "spell", "typo", "trigger", "suggestion"}` (`key` as typed)
+    pub fn to_json(&self) -> Value {
+        let opt = |s: Option<&str>| s.map_or(Value::Null, |s| Value::Str(s.into()));
+        let keystroke = |k: &Keystroke| {
+            Value::Obj(vec![
+                ("key".into(), opt(k.to_char().map(String::from).as_deref())),
+                ("seq".into(), Value::Num(k.seq as f64)),
+            ])
+        };
+        let char = |c: &DebugChar| {
+            Value::Obj(vec![
+                ("shown".into(), Value::Str(c.shown.into())),
+                ("key".into(), Value::Num(c.key as f64)),
+                ("caps".into(), Value::Bool(c.caps)),
+                ("tone".into(), Value::Num(c.tone as f64)),
+                ("mark".into(), Value::Num(c.mark as f64)),
+                ("stroke".into(), Value::Bool(c.stroke)),
+                (
+                    "origin".into(),
+                    Value::Arr(c.origin.iter().map(keystroke).collect()),
+                ),
+            ])
+        };
+        Value::Obj(vec![
+            ("method".into(), Value::Num(self.method as f64)),
+            ("enabled".into(), Value::Bool(self.enabled)),
+            ("raw_mode".into(), Value::Bool(self.raw_mode)),
+            ("overflowed".into(), Value::Bool(self.overflowed)),
+            ("shown".into(), Value::Str(self.shown())),
+            ("raw".into(), Value::Str(self.raw.clone())),
+            (
+                "chars".into(),
+                Value::Arr(self.chars.iter().map(char).collect()),
+            ),
+            ("held_back".into(), opt(self.held_back.as_deref())),
+            ("last_transform".into(), opt(self.last_transform.as_deref())),
+            ("spell".into(), Value::Str(self.spell.as_str().into())),
+            ("typo".into(), opt(self.typo)),
+            ("trigger".into(), opt(self.trigger.as_deref())),
+            ("suggestion".into(), opt(self.suggestion.as_deref())),
+        ])
+    }
+}
+
+pub(super) fn capture(e: &Engine) -> DebugState {
+    let chars = e
+        .buf
+        .iter()
+        .map(|c| DebugChar {
+            shown: c.to_char().unwrap_or('\u{fffd}'),
+            key: c.key,
+            caps: c.caps,
+            tone: c.tone,
+            mark: c.mark,
+            stroke: c.stroke,
+            origin: c.origin().to_vec(),
+        })
+        .collect();
+    #[cfg(feature = "macros")]
+    let trigger = e
+        .trigger_cursor
+        .node()
+        .filter(|_| !e.buf.is_empty() && e.trigger_cursor.follows(e.shortcuts.trie()))
+        .and_then(|n| e.shortcuts.trie().values(n).first().cloned());
+    #[cfg(not(feature = "macros"))]
+    let trigger = None;
+    DebugState {
+        method: e.method,
+        enabled: e.enabled,
+        raw_mode: e.raw_mode,
+        overflowed: e.overflowed,
+        chars,
+        raw: e
+            .buf
+            .keystrokes()
+            .iter()
+            .filter_map(|k| k.to_char())
+            .collect(),
+        held_back: e.pending.map(|p| p.as_slice().iter().collect()),
+        last_transform: e.last_transform.map(|t| format!("{:?}", t)),
+        spell: e.spell.status(),
+        typo: e.typo(),
+        trigger,
+        suggestion: e
+            .suggestion
+            .as_ref()
+            .map(|s| s.candidates[s.selected].clone()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::keys;
+    use crate::utils::type_word;
+
+    #[test]
+    fn test_capture() {
+        let mut e = Engine::new();
+        type_word(&mut e, "tiees");
+        assert!(e.debug_state().last_transform.is_some());
+        type_word(&mut e, "ng");
+        let state = e.debug_state();
+        assert_eq!(state.shown(), "tiếng");
+        assert_eq!(state.raw, "tieesng");
+        let ee = &state.chars[2];
+        assert_eq!((ee.key, ee.tone, ee.mark), (keys::E, 1, 1));
+        let typed: Vec<_> = ee.origin.iter().map(|k| (k.key, k.seq)).collect();
+        assert_eq!(typed, [(keys::E, 2), (keys::E, 3), (keys::S, 4)]);
+        assert_eq!(state.spell, SpellStatus::Vietnamese);
+
+        type_word(&mut e, " ");
+        let state = e.debug_state();
+        assert!(state.chars.is_empty() && state.raw.is_empty());
+        assert_eq!(state.spell, SpellStatus::Empty);
+    }
+
+    #[test]
+    fn test_to_json() {
+        let mut e = Engine::new();
+        type_word(&mut e, "as");
+        let json = e.debug_state().to_json();
+        assert_eq!(json.get("shown").and_then(Value::as_str), Some("á"));
+        assert_eq!(json.get("raw").and_then(Value::as_str), Some("as"));
+        let origin = json.get("chars").and_then(Value::as_array).unwrap()[0]
+            .get("origin")
+            .and_then(Value::as_array)
+            .unwrap();
+        let typed: Vec<_> = origin
+            .iter()
+            .filter_map(|k| k.get("key").and_then(Value::as_str))
+            .collect();
+        assert_eq!(typed, ["a", "s"]);
+        assert_eq!(json.get("typo"), Some(&Value::Null));
+    }
+}
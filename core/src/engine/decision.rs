@@ -0,0 +1,174 @@
+//! Decisions - one currency for everything that acts on a finished word
+//!
+//! Word rules, restore hints, the restore checks and corrections each have
+//! an opinion on the word that just ended. Rather than running them in a
+//! fixed order where the first to speak wins, each gives a `Decision` with
+//! a confidence in [0, 1], and the engine takes the most confident one at
+//! or above `min_confidence` (`decide`); ties go to the source listed
+//! first in `Source`. With the threshold at 0 (the default) this settles
+//! words the way the fixed order did: rules are sure (1.0), every restore
+//! check is surer than any correction.
+//!
+//! | source        | confidence                                      |
+//! |---------------|-------------------------------------------------|
+//! | `Rule`        | 1.0                                             |
+//! | `Hint`        | the hint's own (`RestoreHint`, `crate::ml`)     |
+//! | `Check`       | per check (`check_confidence`)                  |
+//! | `Correction`  | `CORRECTION_CONFIDENCE`                         |
+//! | `Similar`     | the "did you mean" score (`crate::suggest`)     |
+//!
+//! Raising `min_confidence` makes the IME more conservative: below it,
+//! the word is left as shown.
+
+use crate::config::RestoreCheck;
+
+/// What a decision does with the word
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DecisionAction {
+    /// Leave the word as shown
+    Keep,
+    /// Back to the keystrokes as typed
+    Restore,
+    /// Type this instead
+    Replace(String),
+}
+
+/// "keep", "restore" or `replace "text"`
+impl std::fmt::Display for DecisionAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DecisionAction::Keep => write!(f, "keep"),
+            DecisionAction::Restore => write!(f, "restore"),
+            DecisionAction::Replace(text) => write!(f, "replace {:?}", text),
+        }
+    }
+}
+
+/// Who decided, in tie-breaking order
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Source {
+    /// A word rule (`engine::rules`)
+    Rule,
+    /// A restore hint: a plugin or the neural model
+    Hint,
+    /// One of the auto-restore checks
+    Check(RestoreCheck),
+    /// The correction chain (`engine::correction`)
+    Correction,
+    /// Edit-distance suggestions
+    Similar,
+}
+
+/// One source's verdict on a word
+#[derive(Clone, Debug, PartialEq)]
+pub struct Decision {
+    pub action: DecisionAction,
+    /// In [0, 1]
+    pub confidence: f32,
+    pub source: Source,
+}
+
+impl Decision {
+    /// `confidence` is clamped to [0, 1]; NaN counts as 0
+    pub fn new(action: DecisionAction, confidence: f32, source: Source) -> Self {
+        Decision {
+            action,
+            confidence: if confidence.is_nan() {
+                0.0
+            } else {
+                confidence.clamp(0.0, 1.0)
+            },
+            source,
+        }
+    }
+
+    /// A restore hint's probability that the word should be restored, as
+    /// a decision either way
+    pub fn from_hint(restore: f32) -> Self {
+        if restore >= 0.5 {
+            Decision::new(DecisionAction::Restore, restore, Source::Hint)
+        } else {
+            Decision::new(DecisionAction::Keep, 1.0 - restore, Source::Hint)
+        }
+    }
+}
+
+/// Confidence of a rule
+pub const RULE_CONFIDENCE: f32 = 1.0;
+
+/// Confidence of a correction: below every restore check, so a word that
+/// is restored isn't also corrected
+pub const CORRECTION_CONFIDENCE: f32 = 0.5;
+
+/// How sure a restore check is when it fires: checks on what can't be
+/// Vietnamese above those on what merely looks English
+pub fn check_confidence(check: RestoreCheck) -> f32 {
+    match check {
+        RestoreCheck::Dictionary => 0.95,
+        RestoreCheck::Invalid | RestoreCheck::MarkFinal => 0.9,
+        RestoreCheck::WInitial | RestoreCheck::WFinal => 0.85,
+        RestoreCheck::ModifierConsonant | RestoreCheck::ModifierBetweenVowels => 0.8,
+        RestoreCheck::ModifierVowelPair | RestoreCheck::EnglishSuffix => 0.75,
+        RestoreCheck::Bigram => 0.6,
+    }
+}
+
+/// Most confident of `decisions` at or above `threshold`; the first given
+/// wins a tie
+pub fn decide(decisions: impl IntoIterator<Item = Decision>, threshold: f32) -> Option<Decision> {
+    decisions
+        .into_iter()
+        .filter(|d| d.confidence >= threshold)
+        .fold(None, |best: Option<Decision>, d| match best {
+            Some(b) if b.confidence >= d.confidence => Some(b),
+            _ => Some(d),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::RestoreOrder;
+
+    #[test]
+    fn test_decide() {
+        let restore = Decision::new(
+            DecisionAction::Restore,
+            check_confidence(RestoreCheck::Bigram),
+            Source::Check(RestoreCheck::Bigram),
+        );
+        let fix = Decision::new(
+            DecisionAction::Replace("làm".into()),
+            CORRECTION_CONFIDENCE,
+            Source::Correction,
+        );
+        let both = [restore.clone(), fix.clone()];
+        assert_eq!(decide(both.clone(), 0.0), Some(restore.clone()));
+        assert_eq!(decide(both.clone(), 0.7), None);
+        assert_eq!(decide([fix.clone()], 0.5), Some(fix));
+        assert_eq!(decide([], 0.0), None);
+
+        // Ties go to the first
+        let keep = Decision::new(DecisionAction::Keep, 0.6, Source::Hint);
+        assert_eq!(decide([keep.clone(), restore], 0.0), Some(keep));
+    }
+
+    #[test]
+    fn test_confidence() {
+        assert_eq!(Decision::from_hint(0.9).action, DecisionAction::Restore);
+        let keep = Decision::from_hint(0.2);
+        assert_eq!(keep.action, DecisionAction::Keep);
+        assert!((keep.confidence - 0.8).abs() < 1e-6);
+        assert_eq!(
+            Decision::new(DecisionAction::Keep, 3.0, Source::Rule).confidence,
+            1.0
+        );
+        assert_eq!(
+            Decision::new(DecisionAction::Keep, f32::NAN, Source::Rule).confidence,
+            0.0
+        );
+        for check in RestoreOrder::DEFAULT.checks() {
+            assert!(check_confidence(*check) > CORRECTION_CONFIDENCE);
+        }
+    }
+}
@@ -0,0 +1,130 @@
+//! Key Event Model
+//!
+//! Full keyboard event as delivered by the host: keycode, modifier flags,
+//! key-down/key-up and the OS auto-repeat flag.
+//!
+//! `Engine::on_key_ext` only sees key-downs. Hosts that can observe key-ups
+//! should use `Engine::on_event` instead, so the engine can tell a held key
+//! (auto-repeat) apart from the same key typed twice.
+
+/// Modifier flags (bitmask)
+pub mod modifier {
+    pub const SHIFT: u8 = 1 << 0;
+    pub const CTRL: u8 = 1 << 1;
+    /// Option on macOS, Alt on PC (AltGr = CTRL | ALT)
+    pub const ALT: u8 = 1 << 2;
+    /// Command on macOS, Windows key on PC
+    pub const CMD: u8 = 1 << 3;
+    pub const CAPS_LOCK: u8 = 1 << 4;
+
+    /// Modifiers that turn a keystroke into a shortcut (bypass IME)
+    pub const COMMAND_MASK: u8 = CTRL | ALT | CMD;
+}
+
+/// Keyboard event (C layout: passed in arrays to `ime_key_events`)
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyEvent {
+    /// macOS virtual keycode
+    pub key: u16,
+    /// Bitmask of `modifier::*`
+    pub modifiers: u8,
+    /// true = key-down, false = key-up
+    pub down: bool,
+    /// OS auto-repeat flag (key held down)
+    pub repeat: bool,
+    /// Host monotonic clock in milliseconds, 0 if not known (see
+    /// `Engine::on_key_at`)
+    pub time_ms: u64,
+}
+
+impl KeyEvent {
+    /// Key-down event
+    pub fn down(key: u16, modifiers: u8) -> Self {
+        Self {
+            key,
+            modifiers,
+            down: true,
+            repeat: false,
+            time_ms: 0,
+        }
+    }
+
+    /// Key-up event
+    pub fn up(key: u16, modifiers: u8) -> Self {
+        Self {
+            key,
+            modifiers,
+            down: false,
+            repeat: false,
+            time_ms: 0,
+        }
+    }
+
+    /// Mark as OS auto-repeat
+    pub fn repeated(mut self) -> Self {
+        self.repeat = true;
+        self
+    }
+
+    /// Stamp with the host's monotonic clock
+    pub fn at(mut self, time_ms: u64) -> Self {
+        self.time_ms = time_ms;
+        self
+    }
+
+    pub fn shift(&self) -> bool {
+        self.modifiers & modifier::SHIFT != 0
+    }
+
+    /// Uppercase letter: Shift or CapsLock (macOS semantics)
+    pub fn caps(&self) -> bool {
+        self.modifiers & (modifier::SHIFT | modifier::CAPS_LOCK) != 0
+    }
+
+    /// Ctrl/Alt/Cmd held: keystroke is a shortcut, not text
+    pub fn is_command(&self) -> bool {
+        self.modifiers & modifier::COMMAND_MASK != 0
+    }
+}
+
+/// Mouse event the host reports (see `Engine::on_mouse`)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MouseEvent {
+    /// Button press in the focused field or elsewhere
+    Click,
+    /// Wheel or trackpad scroll
+    Scroll,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::keys;
+
+    #[test]
+    fn test_modifier_flags() {
+        let e = KeyEvent::down(keys::A, modifier::SHIFT);
+        assert!(e.shift() && e.caps() && !e.is_command());
+
+        let e = KeyEvent::down(keys::A, modifier::CAPS_LOCK);
+        assert!(!e.shift() && e.caps());
+
+        let e = KeyEvent::down(keys::A, modifier::CMD);
+        assert!(e.is_command());
+
+        // AltGr
+        let e = KeyEvent::down(keys::Q, modifier::CTRL | modifier::ALT);
+        assert!(e.is_command());
+    }
+
+    #[test]
+    fn test_up_and_repeat() {
+        let e = KeyEvent::up(keys::A, 0);
+        assert!(!e.down && !e.repeat);
+
+        let e = KeyEvent::down(keys::A, 0).repeated().at(1500);
+        assert!(e.down && e.repeat);
+        assert_eq!(e.time_ms, 1500);
+    }
+}
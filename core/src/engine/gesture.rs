@@ -0,0 +1,180 @@
+//! Modifier Gestures - double-tapping Shift, Ctrl, Alt or Cmd
+//!
+//! `Recognizer` watches the key events of `Engine::on_event` and reports a
+//! modifier tapped twice: pressed and released alone, twice in a row. What
+//! that does is the user's `Gestures` mapping (toggle Vietnamese, switch
+//! method, ask the host for suggestions), so every host gets the same
+//! gesture from the same rules instead of one of its own.
+//!
+//! A tap is a press no longer than `TAP_MS` with no other key in between
+//! (Shift held for a capital letter isn't one), and the second tap must
+//! start within `DOUBLE_TAP_MS` of the first one's release. Untimed events
+//! (`time_ms` 0) skip the timing checks: only the order of keys counts.
+
+use super::event::KeyEvent;
+use crate::config::GestureKey;
+
+/// Longest press that counts as a tap, in milliseconds
+pub const TAP_MS: u64 = 300;
+
+/// Longest gap between the two taps, in milliseconds
+pub const DOUBLE_TAP_MS: u64 = 400;
+
+/// Tap under way
+#[derive(Clone, Copy, Debug)]
+struct Tap {
+    key: GestureKey,
+    /// Taps finished before this press (0 or 1)
+    taps: u8,
+    /// Time of the press, or of the release once `released`
+    time_ms: u64,
+    released: bool,
+}
+
+/// Double-tap state, fed every key event
+#[derive(Debug, Default)]
+pub(crate) struct Recognizer {
+    tap: Option<Tap>,
+}
+
+impl Recognizer {
+    /// Follow `ev`; the modifier it finished double-tapping, if any
+    pub fn on_event(&mut self, ev: &KeyEvent) -> Option<GestureKey> {
+        let Some(key) = GestureKey::from_key(ev.key) else {
+            // Another key: no gesture under way anymore
+            if ev.down {
+                self.tap = None;
+            }
+            return None;
+        };
+        let within = |since: u64, limit: u64| {
+            ev.time_ms == 0 || since == 0 || ev.time_ms.saturating_sub(since) <= limit
+        };
+
+        if ev.down {
+            self.tap = match self.tap {
+                _ if ev.repeat => None,
+                // Held, or another modifier pressed with it
+                Some(tap) if !tap.released => None,
+                Some(tap)
+                    if tap.key == key && tap.taps == 0 && within(tap.time_ms, DOUBLE_TAP_MS) =>
+                {
+                    Some(Tap {
+                        taps: 1,
+                        time_ms: ev.time_ms,
+                        released: false,
+                        ..tap
+                    })
+                }
+                _ => Some(Tap {
+                    key,
+                    taps: 0,
+                    time_ms: ev.time_ms,
+                    released: false,
+                }),
+            };
+            return None;
+        }
+
+        let tap = self.tap.filter(|t| t.key == key && !t.released)?;
+        if !within(tap.time_ms, TAP_MS) {
+            self.tap = None;
+            return None;
+        }
+        if tap.taps == 1 {
+            self.tap = None;
+            return Some(key);
+        }
+        self.tap = Some(Tap {
+            time_ms: ev.time_ms,
+            released: true,
+            ..tap
+        });
+        None
+    }
+
+    /// Forget the taps so far (focus moved)
+    pub fn reset(&mut self) {
+        self.tap = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::keys;
+
+    fn feed(r: &mut Recognizer, events: &[(bool, u16, u64)]) -> Vec<GestureKey> {
+        events
+            .iter()
+            .filter_map(|&(down, key, time_ms)| {
+                let ev = if down {
+                    KeyEvent::down(key, 0)
+                } else {
+                    KeyEvent::up(key, 0)
+                };
+                r.on_event(&ev.at(time_ms))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_double_tap() {
+        let mut r = Recognizer::default();
+        let shift_twice = [
+            (true, keys::SHIFT, 1_000),
+            (false, keys::SHIFT, 1_080),
+            (true, keys::RIGHT_SHIFT, 1_200),
+            (false, keys::RIGHT_SHIFT, 1_280),
+        ];
+        assert_eq!(feed(&mut r, &shift_twice), [GestureKey::Shift]);
+        // A third tap starts over
+        assert!(feed(
+            &mut r,
+            &[(true, keys::SHIFT, 1_400), (false, keys::SHIFT, 1_450)]
+        )
+        .is_empty());
+
+        // Untimed: only the order counts
+        let untimed = shift_twice.map(|(down, key, _)| (down, key, 0));
+        assert_eq!(feed(&mut r, &untimed), [GestureKey::Shift]);
+    }
+
+    #[test]
+    fn test_not_a_double_tap() {
+        let mut r = Recognizer::default();
+        // Too slow between the taps
+        let slow = [
+            (true, keys::CONTROL, 1_000),
+            (false, keys::CONTROL, 1_050),
+            (true, keys::CONTROL, 2_000),
+            (false, keys::CONTROL, 2_050),
+        ];
+        assert!(feed(&mut r, &slow).is_empty());
+        // Second press held
+        let held = [
+            (true, keys::CONTROL, 3_000),
+            (false, keys::CONTROL, 3_050),
+            (true, keys::CONTROL, 3_100),
+            (false, keys::CONTROL, 3_900),
+        ];
+        assert!(feed(&mut r, &held).is_empty());
+        // Shift for a capital, then Shift again
+        let capital = [
+            (true, keys::SHIFT, 4_000),
+            (true, keys::A, 4_020),
+            (false, keys::SHIFT, 4_060),
+            (true, keys::SHIFT, 4_100),
+            (false, keys::SHIFT, 4_150),
+        ];
+        assert!(feed(&mut r, &capital).is_empty());
+        // Two different modifiers
+        let mixed = [
+            (true, keys::SHIFT, 5_000),
+            (false, keys::SHIFT, 5_050),
+            (true, keys::COMMAND, 5_100),
+            (false, keys::COMMAND, 5_150),
+        ];
+        assert!(feed(&mut r, &mixed).is_empty());
+    }
+}
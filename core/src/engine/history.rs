@@ -0,0 +1,327 @@
+//! Word history and privacy
+//!
+//! Words the user finished, newest last, for features that look back at
+//! what was typed. Everything the engine keeps past the current word goes
+//! through here, so the user's `Privacy` settings apply in one place: with
+//! retention off nothing is recorded, entries expire after a set time, and
+//! the ring never holds more than `HISTORY_CAP` words. Retention is off
+//! until the host turns it on.
+//!
+//! Word counts (for pack and macro authors to see what they type most) are
+//! opt-in and follow the same rules: a word's count is forgotten once the
+//! word hasn't been typed for `expire_after`.
+
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+/// Most words kept; older ones drop off
+pub const HISTORY_CAP: usize = 200;
+
+/// Most distinct words counted; the least typed one makes room
+pub const COUNT_CAP: usize = 5000;
+
+/// What the engine may keep about what the user typed (nothing by default)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Privacy {
+    /// Keep anything past the word being typed
+    pub retain: bool,
+    /// Forget words older than this (None = only the cap applies)
+    pub expire_after: Option<Duration>,
+}
+
+impl Default for Privacy {
+    fn default() -> Self {
+        Self::OFF
+    }
+}
+
+impl Privacy {
+    /// Keep nothing
+    pub const OFF: Privacy = Privacy {
+        retain: false,
+        expire_after: None,
+    };
+
+    /// Keep words until the cap pushes them out
+    pub const KEEP: Privacy = Privacy {
+        retain: true,
+        expire_after: None,
+    };
+}
+
+#[derive(Debug, Default)]
+pub struct WordHistory {
+    privacy: Privacy,
+    words: VecDeque<(String, Instant)>,
+    /// Lowercase word → (times typed, last typed); `None` unless opted in
+    counts: Option<HashMap<String, (u32, Instant)>>,
+}
+
+impl WordHistory {
+    pub fn privacy(&self) -> Privacy {
+        self.privacy
+    }
+
+    /// Apply new settings to what's already kept
+    pub fn set_privacy(&mut self, privacy: Privacy, now: Instant) {
+        self.privacy = privacy;
+        if privacy.retain {
+            self.expire(now);
+        } else {
+            self.wipe();
+        }
+    }
+
+    /// Count finished words (off by default); turning it off forgets the counts
+    pub fn set_counting(&mut self, on: bool) {
+        if on != self.counts.is_some() {
+            self.counts = on.then(HashMap::new);
+        }
+    }
+
+    pub fn is_counting(&self) -> bool {
+        self.counts.is_some()
+    }
+
+    /// Add a finished word (no-op with retention off)
+    pub fn record(&mut self, word: &[char], now: Instant) {
+        if !self.privacy.retain {
+            return;
+        }
+        self.expire(now);
+        if let Some(counts) = &mut self.counts {
+            let key: String = word.iter().flat_map(|c| c.to_lowercase()).collect();
+            if counts.len() >= COUNT_CAP && !counts.contains_key(&key) {
+                let least = counts
+                    .iter()
+                    .min_by_key(|(_, &(n, at))| (n, at))
+                    .map(|(w, _)| w.clone());
+                if let Some(least) = least {
+                    counts.remove(&least);
+                }
+            }
+            let entry = counts.entry(key).or_insert((0, now));
+            *entry = (entry.0.saturating_add(1), now);
+        }
+        // Reuse the oldest entry's allocation once the ring is full
+        let mut text = if self.words.len() >= HISTORY_CAP {
+            self.words.pop_front().map(|(s, _)| s).unwrap_or_default()
+        } else {
+            String::new()
+        };
+        text.clear();
+        text.extend(word);
+        self.words.push_back((text, now));
+    }
+
+    /// Drop words older than `expire_after`
+    pub fn expire(&mut self, now: Instant) {
+        let Some(max_age) = self.privacy.expire_after else {
+            return;
+        };
+        while let Some((_, at)) = self.words.front() {
+            if now.saturating_duration_since(*at) <= max_age {
+                break;
+            }
+            self.words.pop_front();
+        }
+        if let Some(counts) = &mut self.counts {
+            counts.retain(|_, (_, at)| now.saturating_duration_since(*at) <= max_age);
+        }
+    }
+
+    /// Words still within `expire_after`, newest first
+    pub fn recent(&self, now: Instant) -> impl Iterator<Item = &str> {
+        let max_age = self.privacy.expire_after;
+        self.words
+            .iter()
+            .rev()
+            .take_while(move |(_, at)| {
+                max_age.is_none_or(|max| now.saturating_duration_since(*at) <= max)
+            })
+            .map(|(s, _)| s.as_str())
+    }
+
+    /// Counted words, most typed first (ties alphabetical)
+    pub fn word_counts(&self, now: Instant) -> Vec<(&str, u32)> {
+        let max_age = self.privacy.expire_after;
+        let mut list: Vec<(&str, u32)> = self
+            .counts
+            .iter()
+            .flatten()
+            .filter(|(_, (_, at))| {
+                max_age.is_none_or(|max| now.saturating_duration_since(*at) <= max)
+            })
+            .map(|(w, &(n, _))| (w.as_str(), n))
+            .collect();
+        list.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        list
+    }
+
+    /// `word_counts` as CSV with a `word,count` header
+    pub fn word_counts_csv(&self, now: Instant) -> String {
+        let mut out = String::from("word,count\n");
+        for (word, n) in self.word_counts(now) {
+            if word.contains([',', '"', '\n', '\r']) {
+                out.push('"');
+                out.push_str(&word.replace('"', "\"\""));
+                out.push('"');
+            } else {
+                out.push_str(word);
+            }
+            out.push_str(&format!(",{}\n", n));
+        }
+        out
+    }
+
+    /// Forget everything, freeing the memory (counting stays on if it was)
+    pub fn wipe(&mut self) {
+        self.words = VecDeque::new();
+        if let Some(counts) = &mut self.counts {
+            *counts = HashMap::new();
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chars(s: &str) -> Vec<char> {
+        s.chars().collect()
+    }
+
+    /// History with retention turned on, as a host would
+    fn kept() -> WordHistory {
+        let mut h = WordHistory::default();
+        h.set_privacy(Privacy::KEEP, Instant::now());
+        h
+    }
+
+    #[test]
+    fn test_keeps_nothing_by_default() {
+        let mut h = WordHistory::default();
+        h.set_counting(true);
+        let now = Instant::now();
+        h.record(&chars("việt"), now);
+        assert!(h.is_empty());
+        assert!(h.word_counts(now).is_empty());
+    }
+
+    #[test]
+    fn test_ring_is_capped() {
+        let mut h = kept();
+        let now = Instant::now();
+        for i in 0..HISTORY_CAP + 10 {
+            h.record(&chars(&i.to_string()), now);
+        }
+        assert_eq!(h.len(), HISTORY_CAP);
+        let newest = (HISTORY_CAP + 9).to_string();
+        assert_eq!(h.recent(now).next(), Some(newest.as_str()));
+        assert_eq!(h.recent(now).last(), Some("10"));
+    }
+
+    #[test]
+    fn test_retention_off() {
+        let mut h = kept();
+        let now = Instant::now();
+        h.record(&chars("việt"), now);
+        h.set_privacy(Privacy::OFF, now);
+        assert!(h.is_empty());
+        h.record(&chars("nam"), now);
+        assert!(h.is_empty());
+    }
+
+    #[test]
+    fn test_counts() {
+        let mut h = kept();
+        let now = Instant::now();
+        h.record(&chars("việt"), now);
+        assert!(h.word_counts(now).is_empty(), "off by default");
+
+        h.set_counting(true);
+        for w in ["Việt", "nam", "việt", "a,\"b"] {
+            h.record(&chars(w), now);
+        }
+        assert_eq!(h.word_counts(now), [("việt", 2), ("a,\"b", 1), ("nam", 1)]);
+        assert_eq!(
+            h.word_counts_csv(now),
+            "word,count\nviệt,2\n\"a,\"\"b\",1\nnam,1\n"
+        );
+
+        h.set_privacy(Privacy::OFF, now);
+        assert!(h.word_counts(now).is_empty());
+        h.record(&chars("nam"), now);
+        assert!(h.word_counts(now).is_empty());
+
+        h.set_privacy(Privacy::KEEP, now);
+        h.record(&chars("nam"), now);
+        h.set_counting(false);
+        assert!(h.word_counts(now).is_empty());
+    }
+
+    #[test]
+    fn test_counts_expire() {
+        let mut h = WordHistory::default();
+        h.set_counting(true);
+        let start = Instant::now();
+        let minute = Duration::from_secs(60);
+        h.set_privacy(
+            Privacy {
+                retain: true,
+                expire_after: Some(5 * minute),
+            },
+            start,
+        );
+        h.record(&chars("cũ"), start);
+        h.record(&chars("mới"), start + 4 * minute);
+        h.record(&chars("mới"), start + 4 * minute);
+        assert_eq!(h.word_counts(start + 6 * minute), [("mới", 2)]);
+    }
+
+    #[test]
+    fn test_counts_capped() {
+        let mut h = kept();
+        h.set_counting(true);
+        let now = Instant::now();
+        h.record(&chars("hay"), now);
+        h.record(&chars("hay"), now);
+        for i in 0..COUNT_CAP + 10 {
+            h.record(&chars(&i.to_string()), now);
+        }
+        let counts = h.word_counts(now);
+        assert_eq!(counts.len(), COUNT_CAP);
+        assert_eq!(counts[0], ("hay", 2));
+    }
+
+    #[test]
+    fn test_expiry() {
+        let mut h = kept();
+        let start = Instant::now();
+        let minute = Duration::from_secs(60);
+        h.record(&chars("cũ"), start);
+        h.record(&chars("mới"), start + 4 * minute);
+
+        h.set_privacy(
+            Privacy {
+                retain: true,
+                expire_after: Some(5 * minute),
+            },
+            start + 5 * minute,
+        );
+        assert_eq!(h.len(), 2);
+        // Read-side filtering before anything prunes
+        let later = start + 6 * minute;
+        assert_eq!(h.recent(later).collect::<Vec<_>>(), ["mới"]);
+        h.expire(later);
+        assert_eq!(h.len(), 1);
+    }
+}
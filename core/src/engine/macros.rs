@@ -0,0 +1,463 @@
+//! Dynamic Macros - shortcuts whose replacement is computed
+//!
+//! A trigger is typed after "=" at the start of a word and expanded on
+//! space, like a shortcut: "=1234d" → "một nghìn hai trăm ba mươi tư đồng",
+//! "=date" → "ngày 16 tháng 10 năm 2026". The keys after "=" are typed as
+//! they are, so spreadsheet formulas ("=sum") aren't turned into Vietnamese.
+//!
+//! The engine has no clock: "=date" stays as typed until the host sets
+//! today's date (`Engine::set_date`) or gives it a `Clock` to read
+//! (`Engine::set_clock`). Hosts add their own `DynamicMacro`s, asked
+//! before the built-ins.
+//!
+//! Shortcut replacements take placeholders, filled in as they expand
+//! (`fill_placeholders`): `%d` the date ("16/10/2026"), `%t` the time
+//! ("09:05"), `%n` the shortcut's own counter and `%%` a percent sign.
+//! Without a clock `%t` stays as typed, and so does `%d` without a date.
+//!
+//! Money shorthand ("25k" → "25.000₫", "1m2" → "1,2 triệu") also expands
+//! without "=" once `money_shorthand` is on: only as a whole word starting
+//! right after a space or a new line, so "x*25k" or "a1m2" is left alone.
+
+use std::ffi::c_void;
+use std::fmt::Write;
+
+/// Source of dynamic macro expansions
+pub trait DynamicMacro: Send {
+    /// Replacement for `trigger`, the letters and digits typed after "=",
+    /// or `None` to pass; a `{|}` in it is where the caret ends up
+    fn expand(&self, trigger: &str, today: Option<Date>) -> Option<String>;
+}
+
+/// Calendar date, as set by the host
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Date {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+}
+
+impl Date {
+    /// `None` unless the day exists (Gregorian calendar)
+    pub fn new(year: u16, month: u8, day: u8) -> Option<Date> {
+        let leap =
+            year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400));
+        let days = match month {
+            2 if leap => 29,
+            2 => 28,
+            4 | 6 | 9 | 11 => 30,
+            1..=12 => 31,
+            _ => return None,
+        };
+        (1..=days)
+            .contains(&day)
+            .then_some(Date { year, month, day })
+    }
+
+    /// Date `days` days after 1970-01-01
+    fn from_days(days: i64) -> Option<Date> {
+        // Howard Hinnant's civil_from_days
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z.rem_euclid(146_097);
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + i64::from(month <= 2);
+        Date::new(u16::try_from(year).ok()?, month as u8, day as u8)
+    }
+}
+
+/// Time of day, as read from the host's clock
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Time {
+    pub hour: u8,
+    pub minute: u8,
+}
+
+impl Time {
+    /// `None` unless `hour` < 24 and `minute` < 60
+    pub fn new(hour: u8, minute: u8) -> Option<Time> {
+        (hour < 24 && minute < 60).then_some(Time { hour, minute })
+    }
+}
+
+/// Local date and time, asked for when a placeholder or "=date" expands
+pub trait Clock: Send {
+    /// `None` if unknown
+    fn now(&self) -> Option<(Date, Time)>;
+}
+
+impl<F> Clock for F
+where
+    F: Fn() -> Option<(Date, Time)> + Send,
+{
+    fn now(&self) -> Option<(Date, Time)> {
+        self()
+    }
+}
+
+/// Host clock callback (`ime_set_clock`): local time as seconds since
+/// 1970-01-01 00:00 (Unix time plus the UTC offset); negative = unknown
+pub type ClockCallback = extern "C" fn(user_data: *mut c_void) -> i64;
+
+/// `Clock` backed by a host callback
+pub struct CallbackClock {
+    callback: ClockCallback,
+    user_data: *mut c_void,
+}
+
+// SAFETY: user_data is opaque to us; the host owns its thread-safety
+unsafe impl Send for CallbackClock {}
+
+impl CallbackClock {
+    pub fn new(callback: ClockCallback, user_data: *mut c_void) -> Self {
+        CallbackClock {
+            callback,
+            user_data,
+        }
+    }
+}
+
+impl Clock for CallbackClock {
+    fn now(&self) -> Option<(Date, Time)> {
+        let secs = (self.callback)(self.user_data);
+        if secs < 0 {
+            return None;
+        }
+        let (days, secs) = (secs / 86_400, secs % 86_400);
+        let time = Time::new((secs / 3600) as u8, (secs / 60 % 60) as u8)?;
+        Some((Date::from_days(days)?, time))
+    }
+}
+
+/// `text` with its placeholders filled in: `%d` → "16/10/2026", `%t` →
+/// "09:05", `%n` → `counter`, `%%` → "%"; any other `%` is kept
+///
+/// Returns whether `%n` was used, so the caller knows to count it.
+pub fn fill_placeholders(
+    text: &str,
+    date: Option<Date>,
+    time: Option<Time>,
+    counter: u32,
+) -> (String, bool) {
+    let mut out = String::with_capacity(text.len() + 16);
+    let mut counted = false;
+    let mut rest = text;
+    while let Some(at) = rest.find('%') {
+        out.push_str(&rest[..at]);
+        let next = rest[at + 1..].chars().next();
+        match (next, date, time) {
+            (Some('d'), Some(d), _) => {
+                let _ = write!(out, "{:02}/{:02}/{}", d.day, d.month, d.year);
+            }
+            (Some('t'), _, Some(t)) => {
+                let _ = write!(out, "{:02}:{:02}", t.hour, t.minute);
+            }
+            (Some('n'), _, _) => {
+                counted = true;
+                let _ = write!(out, "{}", counter);
+            }
+            (Some('%'), _, _) => out.push('%'),
+            _ => {
+                out.push('%');
+                rest = &rest[at + 1..];
+                continue;
+            }
+        }
+        rest = &rest[at + 2..];
+    }
+    out.push_str(rest);
+    (out, counted)
+}
+
+/// "=1234" → "một nghìn hai trăm ba mươi tư", "=1234d" → "... đồng"
+pub struct NumberWords;
+
+impl DynamicMacro for NumberWords {
+    fn expand(&self, trigger: &str, _today: Option<Date>) -> Option<String> {
+        let (digits, unit) = match trigger.strip_suffix(['d', 'D']) {
+            Some(digits) => (digits, " đồng"),
+            None => (trigger, ""),
+        };
+        if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        Some(number_words(digits.parse().ok()?) + unit)
+    }
+}
+
+/// "=25k" → "25.000₫", "=1m2" → "1,2 triệu" (see `money`)
+pub struct Money;
+
+impl DynamicMacro for Money {
+    fn expand(&self, trigger: &str, _today: Option<Date>) -> Option<String> {
+        money(trigger)
+    }
+}
+
+/// "=date" → "ngày 16 tháng 10 năm 2026"
+pub struct Today;
+
+impl DynamicMacro for Today {
+    fn expand(&self, trigger: &str, today: Option<Date>) -> Option<String> {
+        if !trigger.eq_ignore_ascii_case("date") {
+            return None;
+        }
+        let d = today?;
+        Some(format!("ngày {} tháng {} năm {}", d.day, d.month, d.year))
+    }
+}
+
+/// Macro chain: host macros in the order added, then the built-ins
+#[derive(Default)]
+pub struct DynamicMacros {
+    /// Set by the host; the clock's date wins when there is one
+    pub today: Option<Date>,
+    pub clock: Option<Box<dyn Clock>>,
+    macros: Vec<Box<dyn DynamicMacro>>,
+}
+
+impl DynamicMacros {
+    pub fn push(&mut self, m: impl DynamicMacro + 'static) {
+        self.macros.push(Box::new(m));
+    }
+
+    /// Date and time as far as they are known
+    pub fn now(&self) -> (Option<Date>, Option<Time>) {
+        match self.clock.as_ref().and_then(|c| c.now()) {
+            Some((date, time)) => (Some(date), Some(time)),
+            None => (self.today, None),
+        }
+    }
+
+    pub fn expand(&self, trigger: &str) -> Option<String> {
+        let (today, _) = self.now();
+        self.macros
+            .iter()
+            .find_map(|m| m.expand(trigger, today))
+            .or_else(|| NumberWords.expand(trigger, today))
+            .or_else(|| Money.expand(trigger, today))
+            .or_else(|| Today.expand(trigger, today))
+    }
+}
+
+/// Money shorthand of chats and shop listings in full: thousands of đồng
+/// ("25k" → "25.000₫") or millions ("1m2", "1tr2" → "1,2 triệu")
+///
+/// Anything else is `None`: a leading zero ("05k"), more than six digits
+/// before k or three around m, other letters ("25kg", "2mm").
+pub fn money(word: &str) -> Option<String> {
+    let int_len = word.bytes().take_while(u8::is_ascii_digit).count();
+    let (int, unit) = word.split_at(int_len);
+    if int.is_empty() || int.starts_with('0') {
+        return None;
+    }
+    if unit == "k" {
+        return (int.len() <= 6).then(|| format!("{}.000₫", group_thousands(int)));
+    }
+    let dec = unit.strip_prefix("tr").or_else(|| unit.strip_prefix('m'))?;
+    if int.len() > 3 || dec.len() > 3 || !dec.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    Some(match dec.trim_end_matches('0') {
+        "" => format!("{} triệu", int),
+        dec => format!("{},{} triệu", int, dec),
+    })
+}
+
+/// Digits with a dot between thousands ("1500" → "1.500")
+fn group_thousands(digits: &str) -> String {
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push('.');
+        }
+        out.push(c);
+    }
+    out
+}
+
+const DIGITS: [&str; 10] = [
+    "không", "một", "hai", "ba", "bốn", "năm", "sáu", "bảy", "tám", "chín",
+];
+
+/// `n` read out in Vietnamese ("hai mươi mốt", "một trăm linh năm")
+pub fn number_words(n: u64) -> String {
+    if n == 0 {
+        return DIGITS[0].to_string();
+    }
+    let mut words = Vec::new();
+    read(n, false, &mut words);
+    words.join(" ")
+}
+
+/// Billions are counted in thousands, millions and so on: "hai nghìn tỷ"
+fn read(n: u64, full: bool, words: &mut Vec<&'static str>) {
+    const BILLION: u64 = 1_000_000_000;
+    if n < BILLION {
+        read_groups(n, full, words);
+        return;
+    }
+    read(n / BILLION, full, words);
+    words.push("tỷ");
+    if !n.is_multiple_of(BILLION) {
+        read_groups(n % BILLION, true, words);
+    }
+}
+
+/// `n` below a billion; `full` reads leading zeros ("không trăm linh một")
+/// as a group does after a larger one
+fn read_groups(n: u64, mut full: bool, words: &mut Vec<&'static str>) {
+    for (size, name) in [
+        (1_000_000, Some("triệu")),
+        (1_000, Some("nghìn")),
+        (1, None),
+    ] {
+        let group = (n / size % 1000) as usize;
+        if group == 0 {
+            continue;
+        }
+        read_hundreds(group, full, words);
+        words.extend(name);
+        full = true;
+    }
+}
+
+/// One group of three digits, not zero
+fn read_hundreds(n: usize, full: bool, words: &mut Vec<&'static str>) {
+    let (h, t, u) = (n / 100, n / 10 % 10, n % 10);
+    let lead = h > 0 || full;
+    if lead {
+        words.extend([DIGITS[h], "trăm"]);
+    }
+    match t {
+        0 if u > 0 && lead => words.push("linh"),
+        0 => {}
+        1 => words.push("mười"),
+        _ => words.extend([DIGITS[t], "mươi"]),
+    }
+    words.push(match u {
+        0 => return,
+        1 if t > 1 => "mốt",
+        4 if t > 1 => "tư",
+        5 if t > 0 => "lăm",
+        _ => DIGITS[u],
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_number_words() {
+        for (n, words) in [
+            (0, "không"),
+            (5, "năm"),
+            (10, "mười"),
+            (11, "mười một"),
+            (15, "mười lăm"),
+            (21, "hai mươi mốt"),
+            (24, "hai mươi tư"),
+            (105, "một trăm linh năm"),
+            (110, "một trăm mười"),
+            (1234, "một nghìn hai trăm ba mươi tư"),
+            (1005, "một nghìn không trăm linh năm"),
+            (1_000_000, "một triệu"),
+            (1_000_001, "một triệu không trăm linh một"),
+            (2_003_000_000_000, "hai nghìn không trăm linh ba tỷ"),
+        ] {
+            assert_eq!(number_words(n), words, "{}", n);
+        }
+    }
+
+    #[test]
+    fn test_money() {
+        for (word, full) in [
+            ("25k", "25.000₫"),
+            ("1500k", "1.500.000₫"),
+            ("1m2", "1,2 triệu"),
+            ("1tr5", "1,5 triệu"),
+            ("12m250", "12,25 triệu"),
+            ("3m", "3 triệu"),
+            ("3m0", "3 triệu"),
+        ] {
+            assert_eq!(money(word).as_deref(), Some(full), "{}", word);
+        }
+        for word in [
+            "05k", "k", "25kg", "25K", "2mm", "1m2k", "1234m", "1234567k",
+        ] {
+            assert_eq!(money(word), None, "{}", word);
+        }
+    }
+
+    #[test]
+    fn test_date() {
+        assert!(Date::new(2024, 2, 29).is_some());
+        assert!(Date::new(2026, 2, 29).is_none());
+        assert!(Date::new(2026, 13, 1).is_none());
+        assert!(Date::new(2026, 4, 31).is_none());
+    }
+
+    #[test]
+    fn test_clock() {
+        let clock = |secs: i64| {
+            extern "C" fn read(user_data: *mut c_void) -> i64 {
+                unsafe { *(user_data as *const i64) }
+            }
+            let mut secs = secs;
+            CallbackClock::new(read, &mut secs as *mut i64 as *mut c_void).now()
+        };
+        assert_eq!(
+            clock(0),
+            Some((Date::new(1970, 1, 1).unwrap(), Time::new(0, 0).unwrap()))
+        );
+        // 2026-10-16 09:05:30
+        assert_eq!(
+            clock(1_792_141_530),
+            Some((Date::new(2026, 10, 16).unwrap(), Time::new(9, 5).unwrap()))
+        );
+        assert_eq!(clock(951_782_400).map(|(d, _)| d), Date::new(2000, 2, 29));
+        assert_eq!(clock(-1), None);
+    }
+
+    #[test]
+    fn test_fill_placeholders() {
+        let (date, time) = (Date::new(2026, 10, 6), Time::new(9, 5));
+        assert_eq!(
+            fill_placeholders("[%d %t] #%n: 100%% %x", date, time, 7),
+            ("[06/10/2026 09:05] #7: 100% %x".to_string(), true)
+        );
+        assert_eq!(
+            fill_placeholders("%d %t%", date, None, 1),
+            ("06/10/2026 %t%".to_string(), false)
+        );
+        assert_eq!(
+            fill_placeholders("%d", None, None, 1),
+            ("%d".to_string(), false)
+        );
+    }
+
+    #[test]
+    fn test_expand() {
+        let mut macros = DynamicMacros::default();
+        assert_eq!(
+            macros.expand("1234d").as_deref(),
+            Some("một nghìn hai trăm ba mươi tư đồng")
+        );
+        assert_eq!(macros.expand("50").as_deref(), Some("năm mươi"));
+        assert_eq!(macros.expand("date"), None);
+        assert_eq!(macros.expand("d"), None);
+        assert_eq!(macros.expand("sum"), None);
+        assert_eq!(macros.expand("99999999999999999999"), None);
+
+        macros.today = Date::new(2026, 10, 16);
+        assert_eq!(
+            macros.expand("date").as_deref(),
+            Some("ngày 16 tháng 10 năm 2026")
+        );
+    }
+}
@@ -11,21 +11,77 @@
 //! 4. **Longest-Match-First**: For diacritic placement
 
 pub mod buffer;
+pub mod correction;
+pub mod debug;
+pub mod decision;
+pub mod event;
+pub mod gesture;
+pub mod history;
+#[cfg(feature = "macros")]
+pub mod macros;
+pub mod observer;
+pub mod output;
+pub mod prewarm;
+pub mod restore;
+#[cfg(feature = "rules")]
+pub mod rules;
+pub mod runaway;
+pub mod selftest;
+pub mod shadow;
 pub mod shortcut;
+pub mod spell;
 pub mod syllable;
 pub mod transform;
+pub mod trie;
 pub mod validation;
 
+use crate::config::{
+    AcceptKey, AutoCorrectMode, ConfigError, CorrectionPacks, CorrectionStyle, EngineConfig,
+    GestureAction, Gestures, IyStyle, Method, MouseAction, Nbsp, NbspChord, OverflowPolicy,
+    RemoveKey, RepeatKey, RestoreCheck, RestoreChecks, RestoreOrder, ToneStyle, REPEAT_MS,
+};
 use crate::data::{
     chars::{self, mark, tone},
     keys,
     vowel::{Phonology, Vowel},
 };
+#[cfg(feature = "macros")]
+use crate::importer::{self, Exported, ImportError, ImportFormat, ImportReport};
 use crate::input::{self, ToneType};
+use crate::profile::{Profile, ProfileError};
+use crate::storage::{
+    self, StateHealth, Storage, StorageError, CONFIG_BLOB, SHORTCUTS_BLOB, SYNC_BLOB,
+};
+use crate::sync::{SyncDoc, SyncError, SyncReport, SyncTransport};
+use crate::tokenizer::{self, TokenKind};
 use crate::utils;
-use buffer::{Buffer, Char, MAX};
-use shortcut::{InputMethod, ShortcutTable};
-use validation::{is_foreign_word_pattern, is_valid};
+use buffer::{Buffer, Char, Keystroke, MAX};
+use correction::{CorrectionProvider, Corrections, Suggestion};
+use decision::{check_confidence, Decision, DecisionAction, Source, CORRECTION_CONFIDENCE};
+use event::{modifier, KeyEvent, MouseEvent};
+use history::{Privacy, WordHistory};
+#[cfg(feature = "macros")]
+use macros::{Clock, Date, DynamicMacro, DynamicMacros};
+use observer::{Feedback, Notification, ObserverId, Observers, Setting};
+use output::{
+    BoundaryKey, Delivery, FieldCheck, GhostEdit, OutputProfile, Pending, Strategy, WordInfo,
+    MAX_GHOST_EDITS,
+};
+#[cfg(feature = "autocorrect")]
+use restore::RestoreHint;
+use restore::RestoreReason;
+#[cfg(feature = "rules")]
+use rules::{RuleAction, Rules, WordContext};
+use shadow::{ShadowHeuristic, ShadowLog, ShadowWord};
+use shortcut::ShortcutTable;
+#[cfg(feature = "macros")]
+use shortcut::{Conflict, InputMethod, Shortcut, ShortcutConflict};
+use spell::{SpellState, SpellStatus};
+use std::time::Instant;
+use trie::TrieCursor;
+use validation::{allows_mark, is_foreign_word_pattern, is_valid, ValidationResult};
+#[cfg(feature = "autocorrect")]
+use validation::{validate, Problem};
 
 /// Engine action result
 #[repr(u8)]
@@ -34,6 +90,9 @@ pub enum Action {
     None = 0,
     Send = 1,
     Restore = 2,
+    /// Replace `Engine::edit_range` with the chars (`Delivery::Select`);
+    /// `backspace` still holds its length for hosts that fall back
+    Select = 3,
 }
 
 /// Result for FFI
@@ -43,7 +102,9 @@ pub struct Result {
     pub action: u8,
     pub backspace: u8,
     pub count: u8,
-    pub _pad: u8,
+    /// Characters to move the caret left once `chars` are in (a `{|}` in
+    /// a shortcut or macro expansion); 0 = leave it after them
+    pub caret_back: u8,
 }
 
 impl Result {
@@ -53,7 +114,7 @@ impl Result {
             action: Action::None as u8,
             backspace: 0,
             count: 0,
-            _pad: 0,
+            caret_back: 0,
         }
     }
 
@@ -63,7 +124,7 @@ impl Result {
             action: Action::Send as u8,
             backspace,
             count: chars.len().min(MAX) as u8,
-            _pad: 0,
+            caret_back: 0,
         };
         for (i, &c) in chars.iter().take(MAX).enumerate() {
             result.chars[i] = c as u32;
@@ -72,11 +133,140 @@ impl Result {
     }
 }
 
+/// Key result for Rust hosts, borrowing the engine's output buffer
+///
+/// Unlike `Result` (fixed array for FFI), text is a `&str` into a buffer
+/// the engine reuses, so no key does heap work once the engine exists.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Output<'a> {
+    /// Let the key through unchanged
+    PassThrough,
+    /// Insert one character, nothing to delete
+    Append(char),
+    /// Delete `backspace` characters, then insert `text`
+    Replace { backspace: u8, text: &'a str },
+}
+
+impl From<Output<'_>> for Result {
+    fn from(output: Output<'_>) -> Self {
+        match output {
+            Output::PassThrough => Result::none(),
+            Output::Append(c) => Result::send(0, &[c]),
+            Output::Replace { backspace, text } => {
+                let mut r = Result::send(backspace, &[]);
+                for (slot, c) in r.chars.iter_mut().zip(text.chars()) {
+                    *slot = c as u32;
+                    r.count += 1;
+                }
+                r
+            }
+        }
+    }
+}
+
+/// Word left on screen once a boundary key is handled, and the caret
+/// distance past it (None if the key moves the caret elsewhere)
+fn finished_word(mut screen: Pending, r: &Result, key: u16) -> Option<(Pending, usize)> {
+    let mut tail = output::caret_after_key(0, key, false)?;
+    if r.action != Action::None as u8 {
+        screen.truncate_back(r.backspace as usize);
+        for c in r.chars[..r.count as usize]
+            .iter()
+            .filter_map(|&c| char::from_u32(c))
+        {
+            screen.push(c);
+        }
+        // Space (or NBSP) comes with the output instead of passing through,
+        // and so may Enter
+        if key == keys::SPACE || enter_in_output(key, r) {
+            tail = 0;
+            if screen.as_slice().last().is_some_and(|c| c.is_whitespace()) {
+                screen.truncate_back(1);
+                tail = 1;
+            }
+        }
+    }
+    (!screen.as_slice().is_empty()).then_some((screen, tail))
+}
+
+/// Enter that came at the end of the edit (`BoundaryKey::AfterEdit`)
+/// instead of going through
+fn enter_in_output(key: u16, r: &Result) -> bool {
+    matches!(key, keys::RETURN | keys::ENTER)
+        && r.action != Action::None as u8
+        && r.count > 0
+        && r.chars[r.count as usize - 1] == '\n' as u32
+}
+
+/// Key that finishes the word: a break key, or Shift+digit, which types
+/// a symbol ("!", ")") rather than a digit or a VNI mark
+fn ends_word(key: u16, shift: bool) -> bool {
+    keys::is_break(key) || (shift && keys::is_number(key))
+}
+
+/// Character a key types when passed through (letters, digits, `[` `]`)
+fn raw_char(key: u16, caps: bool) -> Option<char> {
+    match key {
+        keys::LBRACKET => Some('['),
+        keys::RBRACKET => Some(']'),
+        _ => utils::key_to_char(key, caps),
+    }
+}
+
+/// Replace a word typed raw (`screen`) with what immediate output would
+/// have left: `before` (the composed word) with the boundary result `r`
+/// applied. Space is part of the edit, other boundary keys follow it.
+fn fix_up_word(screen: &[char], before: &[char], r: &Result, space: bool) -> Result {
+    let mut text = before.to_vec();
+    if r.action == Action::Send as u8 {
+        text.truncate(text.len().saturating_sub(r.backspace as usize));
+        text.extend(
+            r.chars[..r.count as usize]
+                .iter()
+                .filter_map(|&c| char::from_u32(c)),
+        );
+    } else if space {
+        text.push(' ');
+    }
+    let fix = skip_unchanged(Result::send(screen.len() as u8, &text), screen);
+    let only_space = space && fix.count == 1 && fix.chars[0] == ' ' as u32;
+    if fix.backspace == 0 && (fix.count == 0 || only_space) {
+        return Result::none();
+    }
+    fix
+}
+
+/// Drop the part of an edit that rewrites what's already on screen
+///
+/// `before` is the word as displayed before the key. Backspaces delete from
+/// the end, so only a common prefix of the deleted text and the new text can
+/// stay: restoring "tẽt" to "text " deletes "ẽt" instead of the whole word.
+fn skip_unchanged(mut r: Result, before: &[char]) -> Result {
+    let bs = r.backspace as usize;
+    if r.action != Action::Send as u8 || bs == 0 || bs > before.len() {
+        return r;
+    }
+    let count = r.count as usize;
+    let same = before[before.len() - bs..]
+        .iter()
+        .zip(&r.chars[..count])
+        .take_while(|(&old, &new)| old as u32 == new)
+        .count();
+    if same > 0 {
+        r.chars.copy_within(same..count, 0);
+        r.count -= same as u8;
+        r.backspace -= same as u8;
+    }
+    r
+}
+
 /// Transform type for revert tracking
 #[derive(Clone, Copy, Debug, PartialEq)]
 enum Transform {
     Mark(u16, u8),
     Tone(u16, u8),
+    /// Tone key undid its own diacritic: pressing it again stays literal
+    ToneReverted(u16),
     Stroke(u16),
     /// W as vowel ư (for revert: ww → w)
     WAsVowel,
@@ -90,12 +280,172 @@ pub struct Engine {
     method: u8,
     enabled: bool,
     modern: bool,
+    /// Restore raw keystrokes at word boundary when the word isn't Vietnamese
+    auto_correct: AutoCorrectMode,
+    restore_order: RestoreOrder,
+    restore_disabled: RestoreChecks,
+    /// Asked before the restore checks (`add_restore_hint`)
+    #[cfg(feature = "autocorrect")]
+    restore_hints: Vec<Box<dyn RestoreHint>>,
+    /// See `EngineConfig::bigram_threshold`
+    bigram_threshold: u8,
+    /// Least confidence, in percent, to change a finished word
+    min_confidence: u8,
+    restore_stats: restore::RestoreStats,
+    /// Why the word the last boundary key finished was restored
+    last_restore: Option<RestoreReason>,
+    /// Telex quick vowels: `[` → ơ, `]` → ư
+    bracket_shortcuts: bool,
+    /// Expand "25k", "1m2" typed as words (`macros::money`)
+    money_shorthand: bool,
+    /// Tab right after a trigger expands the shortcut
+    tab_shortcuts: bool,
+    /// Longest word analyzed (see `OverflowPolicy`)
+    max_word_len: u8,
+    overflow: OverflowPolicy,
+    /// Word outgrew `max_word_len` under `PassThrough`: ignore it until a break
+    overflowed: bool,
+    remove_key: RemoveKey,
+    /// One horn key on "uo" horns both vowels ("uow" → ươ)
+    uo_compound: bool,
+    /// What the `nbsp_chord` types
+    nbsp: Nbsp,
+    nbsp_chord: NbspChord,
+    /// The chord typed its space after the word, which is still in the
+    /// buffer (see `nbsp_key`)
+    nbsp_after: bool,
+    /// Commit a word idle this long (0 = never)
+    idle_commit_secs: u8,
+    /// Host clock at the last timestamped key-down (ms)
+    last_key_ms: Option<u64>,
+    /// Host clock of the key being handled (ms); None for a key without
+    /// one and between keys
+    key_ms: Option<u64>,
+    /// Host clock at the first timestamped key-down and when it came, to
+    /// put later host times on `Instant`'s clock (see `now`)
+    host_epoch: Option<(u64, Instant)>,
+    mouse_click: MouseAction,
+    mouse_scroll: MouseAction,
+    repeat_key: RepeatKey,
+    /// Key of the last timestamped key-down (see `REPEAT_MS`)
+    last_timed_key: Option<u16>,
+    /// What double-tapping each modifier does
+    gestures: Gestures,
+    recognizer: gesture::Recognizer,
+    /// Gesture the last key event completed
+    gesture: Option<GestureAction>,
+    /// Observers hear of committed, corrected and restored words
+    feedback: bool,
+    feedback_gap_ms: u8,
+    /// Last feedback event sent, for throttling
+    last_feedback: Option<(Instant, Feedback)>,
+    /// Most edits a second (see `runaway`)
+    edit_limit: u8,
+    runaway: runaway::Guard,
+    /// Times the runaway guard tripped
+    runaways: u32,
     last_transform: Option<Transform>,
     shortcuts: ShortcutTable,
-    /// Raw keystroke history for ESC restore (key, caps)
-    raw_input: Vec<(u16, bool)>,
-    /// Raw mode: skip Vietnamese transforms after prefix chars (@ # $ ^ : > ?)
+    /// The word so far in the shortcuts' trigger trie
+    #[cfg(feature = "macros")]
+    trigger_cursor: TrieCursor,
+    /// The word so far in the correction packs' typo trie
+    typo_cursor: TrieCursor,
+    /// How the word so far reads (see `spell`)
+    spell: SpellState,
+    /// Observers hear of spell status changes
+    watch_spell: bool,
+    /// Computed shortcuts ("=1234d", "=date")
+    #[cfg(feature = "macros")]
+    macros: DynamicMacros,
+    /// Keys typed after "=" while a dynamic macro trigger may be under way
+    #[cfg(feature = "macros")]
+    macro_trigger: Option<String>,
+    /// Word typed so far while it may be money shorthand
+    #[cfg(feature = "macros")]
+    amount: Option<String>,
+    /// The last key was a space or a new line (or nothing yet)
+    #[cfg(feature = "macros")]
+    word_start: bool,
+    /// User word rules, asked first at word boundary
+    #[cfg(feature = "rules")]
+    rules: Rules,
+    /// Focused app, for rules (`set_app`)
+    #[cfg(feature = "rules")]
+    app: Option<String>,
+    /// Misspelling fixes at word boundary (dialect packs + host providers)
+    corrections: Corrections,
+    /// Neural diacritic restorer (`set_diacritic_model`)
+    #[cfg(feature = "ml")]
+    diacritic_model: Option<crate::ml::DiacriticModel>,
+    /// Heuristics on trial (`add_shadow`) and what they found
+    shadows: Vec<Box<dyn ShadowHeuristic>>,
+    shadow_log: ShadowLog,
+    /// Loaded plugins, for their suggestions (`add_plugin`)
+    #[cfg(feature = "plugins")]
+    plugins: Vec<crate::plugin::Plugin>,
+    correction_style: CorrectionStyle,
+    accept_key: AcceptKey,
+    /// Correction on offer until the next key (`CorrectionStyle::Suggest`)
+    suggestion: Option<Suggestion>,
+    /// Keystroke counter within the current word (provenance ordering)
+    seq: u16,
+    /// Raw mode: skip Vietnamese transforms and autocorrect after prefix
+    /// chars (@ # $ ^ : > ?) and in identifiers ("myVar", "snake_case")
     raw_mode: bool,
+    /// Settings-change subscribers
+    observers: Observers,
+    /// Last key-down not yet released (for auto-repeat detection in `on_event`)
+    held_key: Option<u16>,
+    /// Backend for `save`/`load` (none until the host sets one)
+    storage: Option<Box<dyn Storage>>,
+    /// Per-entry change history for `sync`
+    sync_doc: SyncDoc,
+    /// Blobs the last `load` had to reset
+    health: StateHealth,
+    /// Text of the last `Output::Replace` (capacity for `MAX` chars)
+    out: String,
+    /// Last `current_preview` (capacity for `MAX` chars)
+    preview: String,
+    /// `Result::caret_back` of the last key
+    caret_back: u8,
+    /// Output tuning for the focused app
+    output: OutputProfile,
+    /// Edits the app lost, oldest first (see `check_field`)
+    ghost_edits: Vec<GhostEdit>,
+    /// Screen text of the current word while its edits are held back
+    pending: Option<Pending>,
+    /// Caret offset in the focused field (UTF-16 units), if known
+    caret: Option<usize>,
+    /// Range replaced by the last key's edit
+    edit: Option<std::ops::Range<usize>>,
+    /// Word the last key finished, and how far the caret is past it
+    last_word: Option<(Pending, usize)>,
+    /// Finished words, kept as `Privacy` allows
+    history: WordHistory,
+}
+
+/// Read and parse a sealed blob
+///
+/// `Ok(None)` if it doesn't exist. If it can't be verified or parsed, it's
+/// recorded in `health` and kept as `<name>.corrupt`.
+fn read_blob<T>(
+    storage: &mut dyn Storage,
+    name: &'static str,
+    health: &mut StateHealth,
+    parse: impl FnOnce(&str) -> Option<T>,
+) -> std::io::Result<Option<T>> {
+    let Some(data) = storage.read(name)? else {
+        return Ok(None);
+    };
+    let parsed = storage::unseal(&data)
+        .and_then(|body| std::str::from_utf8(body).ok())
+        .and_then(parse);
+    if parsed.is_none() {
+        health.reset.push(name);
+        let _ = storage.write(&format!("{}.corrupt", name), &data);
+    }
+    Ok(parsed)
 }
 
 impl Default for Engine {
@@ -111,82 +461,1972 @@ impl Engine {
             method: 0,
             enabled: true,
             modern: true,
+            auto_correct: AutoCorrectMode::Restore,
+            restore_order: RestoreOrder::DEFAULT,
+            restore_disabled: RestoreChecks::NONE,
+            #[cfg(feature = "autocorrect")]
+            restore_hints: Vec::new(),
+            bigram_threshold: crate::config::DEFAULT_BIGRAM_THRESHOLD,
+            min_confidence: 0,
+            restore_stats: restore::RestoreStats::default(),
+            last_restore: None,
+            bracket_shortcuts: false,
+            money_shorthand: false,
+            tab_shortcuts: false,
+            max_word_len: crate::config::DEFAULT_MAX_WORD_LEN,
+            overflow: OverflowPolicy::PassThrough,
+            overflowed: false,
+            remove_key: RemoveKey::Default,
+            uo_compound: true,
+            nbsp: Nbsp::Off,
+            nbsp_chord: NbspChord::ShiftSpace,
+            nbsp_after: false,
+            idle_commit_secs: 0,
+            last_key_ms: None,
+            key_ms: None,
+            host_epoch: None,
+            mouse_click: MouseAction::Commit,
+            mouse_scroll: MouseAction::Keep,
+            repeat_key: RepeatKey::Raw,
+            last_timed_key: None,
+            gestures: Gestures::NONE,
+            recognizer: gesture::Recognizer::default(),
+            gesture: None,
+            feedback: false,
+            feedback_gap_ms: crate::config::DEFAULT_FEEDBACK_GAP_MS,
+            last_feedback: None,
+            edit_limit: crate::config::DEFAULT_EDIT_LIMIT,
+            runaway: runaway::Guard::default(),
+            runaways: 0,
             last_transform: None,
             shortcuts: ShortcutTable::with_defaults(),
-            raw_input: Vec::with_capacity(64),
+            #[cfg(feature = "macros")]
+            trigger_cursor: TrieCursor::new(),
+            typo_cursor: TrieCursor::new(),
+            spell: SpellState::default(),
+            watch_spell: false,
+            #[cfg(feature = "macros")]
+            macros: DynamicMacros::default(),
+            #[cfg(feature = "macros")]
+            macro_trigger: None,
+            #[cfg(feature = "macros")]
+            amount: None,
+            #[cfg(feature = "macros")]
+            word_start: true,
+            #[cfg(feature = "rules")]
+            rules: Rules::default(),
+            #[cfg(feature = "rules")]
+            app: None,
+            corrections: Corrections::default(),
+            shadows: Vec::new(),
+            shadow_log: ShadowLog::default(),
+            #[cfg(feature = "ml")]
+            diacritic_model: None,
+            #[cfg(feature = "plugins")]
+            plugins: Vec::new(),
+            correction_style: CorrectionStyle::Replace,
+            accept_key: AcceptKey::Tab,
+            suggestion: None,
+            seq: 0,
             raw_mode: false,
+            observers: Observers::new(),
+            held_key: None,
+            storage: None,
+            sync_doc: SyncDoc::new(),
+            health: StateHealth::default(),
+            out: String::with_capacity(MAX * 4),
+            preview: String::with_capacity(MAX * 4),
+            caret_back: 0,
+            output: OutputProfile::default(),
+            ghost_edits: Vec::new(),
+            pending: None,
+            caret: None,
+            edit: None,
+            last_word: None,
+            history: WordHistory::default(),
         }
     }
 
-    pub fn set_method(&mut self, method: u8) {
+    /// Switch input method (0 = Telex, 1 = VNI)
+    ///
+    /// Refused, with nothing changed, if settings made for the current
+    /// method conflict with the new one (see `EngineConfig::validate`):
+    /// bracket shortcuts, or a custom `remove_key` that is one of its tone
+    /// or mark keys.
+    pub fn set_method(&mut self, method: u8) -> std::result::Result<(), ConfigError> {
+        self.check_config(|c| c.method = Method::from_id(method))?;
+        self.apply_method(method);
+        Ok(())
+    }
+
+    fn apply_method(&mut self, method: u8) {
+        let changed = Method::from_id(method) != Method::from_id(self.method);
         self.method = method;
+        if changed {
+            self.notify_setting(Setting::Method(Method::from_id(method)));
+        }
+    }
+
+    /// The current settings with `change` made to them pass
+    /// `EngineConfig::validate`
+    fn check_config(
+        &self,
+        change: impl FnOnce(&mut EngineConfig),
+    ) -> std::result::Result<(), ConfigError> {
+        let mut config = self.config();
+        change(&mut config);
+        config.validate()
     }
 
     pub fn set_enabled(&mut self, enabled: bool) {
+        let changed = enabled != self.enabled;
         self.enabled = enabled;
         if !enabled {
             self.buf.clear();
         }
+        if changed {
+            self.notify_setting(Setting::Enabled(enabled));
+        }
     }
 
     pub fn set_modern(&mut self, modern: bool) {
-        self.modern = modern;
+        if modern != self.modern {
+            self.modern = modern;
+            let style = if modern {
+                ToneStyle::Modern
+            } else {
+                ToneStyle::Classic
+            };
+            self.notify_setting(Setting::ToneStyle(style));
+        }
     }
 
-    pub fn shortcuts(&self) -> &ShortcutTable {
-        &self.shortcuts
+    pub fn set_auto_restore(&mut self, auto_restore: bool) {
+        self.set_auto_correct(if auto_restore {
+            AutoCorrectMode::Restore
+        } else {
+            AutoCorrectMode::Off
+        });
     }
 
-    pub fn shortcuts_mut(&mut self) -> &mut ShortcutTable {
-        &mut self.shortcuts
+    pub fn set_auto_correct(&mut self, mode: AutoCorrectMode) {
+        if mode != self.auto_correct {
+            self.auto_correct = mode;
+            self.notify_setting(Setting::AutoCorrect(mode));
+        }
     }
 
-    /// Get current input method as InputMethod enum
-    fn current_input_method(&self) -> InputMethod {
-        match self.method {
-            0 => InputMethod::Telex,
-            1 => InputMethod::Vni,
-            _ => InputMethod::All,
+    /// Order the auto-restore checks run in (see `restore_stats`)
+    pub fn set_restore_order(&mut self, order: RestoreOrder) {
+        if order != self.restore_order {
+            self.restore_order = order;
+            self.notify_setting(Setting::RestoreOrder(order));
         }
     }
 
-    /// Handle key event - main entry point
+    /// Turn individual auto-restore checks off (all on by default)
+    pub fn set_restore_disabled(&mut self, checks: RestoreChecks) {
+        if checks != self.restore_disabled {
+            self.restore_disabled = checks;
+            self.notify_setting(Setting::RestoreDisabled(checks));
+        }
+    }
+
+    /// Add an outside opinion on restoring words, asked before the checks
+    /// in the order added
+    #[cfg(feature = "autocorrect")]
+    pub fn add_restore_hint(&mut self, hint: impl RestoreHint + 'static) {
+        self.restore_hints.push(Box::new(hint));
+    }
+
+    /// Turn one auto-restore check on or off
+    pub fn set_restore_check(&mut self, check: RestoreCheck, enabled: bool) {
+        self.set_restore_disabled(self.restore_disabled.with(check, !enabled));
+    }
+
+    /// How English a word's letter pairs must lean for the bigram check
+    /// to restore it, in tenths of a bit (0 = never)
+    pub fn set_bigram_threshold(&mut self, threshold: u8) {
+        if threshold != self.bigram_threshold {
+            self.bigram_threshold = threshold;
+            self.notify_setting(Setting::BigramThreshold(threshold));
+        }
+    }
+
+    /// Least confidence, in percent, for a rule, restore or correction to
+    /// change a finished word (clamped to 100; 0 lets every one through)
+    pub fn set_min_confidence(&mut self, percent: u8) {
+        let percent = percent.min(100);
+        if percent != self.min_confidence {
+            self.min_confidence = percent;
+            self.notify_setting(Setting::MinConfidence(percent));
+        }
+    }
+
+    /// How often each auto-restore check ran and fired since the engine
+    /// started (or `reset`)
+    pub fn restore_stats(&self) -> &restore::RestoreStats {
+        &self.restore_stats
+    }
+
+    /// Why auto-restore turned the word the last space or punctuation
+    /// finished back into its keystrokes; None if it didn't
     ///
-    /// # Arguments
-    /// * `key` - macOS virtual keycode
-    /// * `caps` - true if Caps Lock is active (for uppercase letters)
-    /// * `ctrl` - true if Cmd/Ctrl/Alt is pressed (bypasses IME)
-    pub fn on_key(&mut self, key: u16, caps: bool, ctrl: bool) -> Result {
-        self.on_key_ext(key, caps, ctrl, false)
+    /// For a "restored because…" tooltip and for bug reports: names the
+    /// check that fired and the characters it fired on.
+    pub fn last_restore_reason(&self) -> Option<RestoreReason> {
+        self.last_restore.clone()
     }
 
-    /// Check if key+shift combo is a raw mode prefix character
-    /// Raw prefixes: @ # : /
-    fn is_raw_prefix(key: u16, shift: bool) -> bool {
-        // / doesn't need shift
-        if key == keys::SLASH && !shift {
-            return true;
+    /// Telex quick vowels (`[` → ơ, `]` → ư); refused under VNI
+    pub fn set_bracket_shortcuts(&mut self, enabled: bool) -> std::result::Result<(), ConfigError> {
+        self.check_config(|c| c.bracket_shortcuts = enabled)?;
+        self.apply_bracket_shortcuts(enabled);
+        Ok(())
+    }
+
+    fn apply_bracket_shortcuts(&mut self, enabled: bool) {
+        if enabled != self.bracket_shortcuts {
+            self.bracket_shortcuts = enabled;
+            self.notify_setting(Setting::BracketShortcuts(enabled));
         }
-        // @ # : need shift
-        if !shift {
-            return false;
+    }
+
+    /// Expand money shorthand typed as a word ("25k" → "25.000₫"); off by
+    /// default
+    pub fn set_money_shorthand(&mut self, enabled: bool) {
+        if enabled != self.money_shorthand {
+            self.money_shorthand = enabled;
+            self.notify_setting(Setting::MoneyShorthand(enabled));
+        }
+    }
+
+    /// Let Tab expand a shortcut right after its trigger ("vn" Tab →
+    /// "Việt Nam", no space after it); off by default, when Tab is just a
+    /// word boundary
+    pub fn set_tab_shortcuts(&mut self, enabled: bool) {
+        if enabled != self.tab_shortcuts {
+            self.tab_shortcuts = enabled;
+            self.notify_setting(Setting::TabShortcuts(enabled));
+        }
+    }
+
+    /// Longest word analyzed, clamped to 1..=`MAX_WORD_LEN`
+    pub fn set_max_word_len(&mut self, len: u8) {
+        let len = len.clamp(1, crate::config::MAX_WORD_LEN);
+        if len != self.max_word_len {
+            self.max_word_len = len;
+            self.notify_setting(Setting::MaxWordLen(len));
+        }
+    }
+
+    pub fn set_overflow_policy(&mut self, policy: OverflowPolicy) {
+        if policy != self.overflow {
+            self.overflow = policy;
+            self.notify_setting(Setting::Overflow(policy));
+        }
+    }
+
+    /// Let one horn key turn "uo" into "ươ" (`uow`, `wo`, VNI `uo7`)
+    ///
+    /// Off, the horn goes on one vowel ("uow" → uơ) and ươ is typed with
+    /// a horn on each ("uwow").
+    pub fn set_uo_compound(&mut self, enabled: bool) {
+        if enabled != self.uo_compound {
+            self.uo_compound = enabled;
+            self.notify_setting(Setting::UoCompound(enabled));
+        }
+    }
+
+    /// Key that strips diacritics (z / 0 by default)
+    ///
+    /// Tone, mark and stroke keys keep their meaning: a custom key that is
+    /// one of the method's is refused, with nothing changed (see
+    /// `EngineConfig::validate`).
+    pub fn set_remove_key(&mut self, key: RemoveKey) -> std::result::Result<(), ConfigError> {
+        self.check_config(|c| c.remove_key = key)?;
+        self.apply_remove_key(key);
+        Ok(())
+    }
+
+    fn apply_remove_key(&mut self, key: RemoveKey) {
+        if key != self.remove_key {
+            self.remove_key = key;
+            self.notify_setting(Setting::RemoveKey(key));
+        }
+    }
+
+    /// Dialect correction packs to apply at word boundary (all off by default)
+    pub fn set_correction_packs(&mut self, packs: CorrectionPacks) {
+        if packs != self.corrections.packs {
+            self.corrections.packs = packs;
+            self.follow_word();
+            self.notify_setting(Setting::CorrectionPacks(packs));
+        }
+    }
+
+    /// Spelling to normalize i/y to at word boundary (off by default)
+    pub fn set_iy_style(&mut self, style: IyStyle) {
+        if style != self.corrections.iy {
+            self.corrections.iy = style;
+            self.notify_setting(Setting::IyStyle(style));
+        }
+    }
+
+    /// Apply corrections right away, or offer them for `accept_key`
+    pub fn set_correction_style(&mut self, style: CorrectionStyle) {
+        if style != self.correction_style {
+            self.correction_style = style;
+            self.dismiss_suggestion();
+            self.notify_setting(Setting::CorrectionStyle(style));
+        }
+    }
+
+    /// Key that applies an offered correction (Tab by default)
+    pub fn set_accept_key(&mut self, key: AcceptKey) {
+        if key != self.accept_key {
+            self.accept_key = key;
+            self.notify_setting(Setting::AcceptKey(key));
+        }
+    }
+
+    /// Correction on offer for the word just finished, if any
+    ///
+    /// Hosts show it when `Notification::SuggestionOffered` arrives and
+    /// hide it on `SuggestionCleared`.
+    pub fn suggestion(&self) -> Option<&Suggestion> {
+        self.suggestion.as_ref()
+    }
+
+    /// Words in `text` the IME would flag or fix, with this engine's
+    /// correction packs and providers (see `lint`)
+    #[cfg(feature = "suggestions")]
+    pub fn lint(&self, text: &str) -> Vec<crate::lint::LintIssue> {
+        crate::lint::lint_with(text, &self.corrections)
+    }
+
+    /// What the `nbsp_chord` types (a plain space by default)
+    pub fn set_nbsp(&mut self, nbsp: Nbsp) {
+        if nbsp != self.nbsp {
+            self.nbsp = nbsp;
+            self.notify_setting(Setting::Nbsp(nbsp));
+        }
+    }
+
+    /// Space with which modifiers types the `nbsp` character (Shift+Space
+    /// by default)
+    pub fn set_nbsp_chord(&mut self, chord: NbspChord) {
+        if chord != self.nbsp_chord {
+            self.nbsp_chord = chord;
+            self.notify_setting(Setting::NbspChord(chord));
+        }
+    }
+
+    /// Commit a word left mid-composition for `secs` seconds (0 = never)
+    ///
+    /// Keys only carry time through `on_key_at` and `KeyEvent::time_ms`;
+    /// untimed keys never commit.
+    pub fn set_idle_commit_secs(&mut self, secs: u8) {
+        if secs != self.idle_commit_secs {
+            self.idle_commit_secs = secs;
+            self.notify_setting(Setting::IdleCommitSecs(secs));
+        }
+    }
+
+    /// What clicks and scrolls do to the word being typed (`on_mouse`)
+    pub fn set_mouse_actions(&mut self, click: MouseAction, scroll: MouseAction) {
+        if click != self.mouse_click {
+            self.mouse_click = click;
+            self.notify_setting(Setting::MouseClick(click));
+        }
+        if scroll != self.mouse_scroll {
+            self.mouse_scroll = scroll;
+            self.notify_setting(Setting::MouseScroll(scroll));
+        }
+    }
+
+    /// What a held letter or number key's auto-repeat does (see `RepeatKey`)
+    pub fn set_repeat_key(&mut self, repeat_key: RepeatKey) {
+        if repeat_key != self.repeat_key {
+            self.repeat_key = repeat_key;
+            self.notify_setting(Setting::RepeatKey(repeat_key));
+        }
+    }
+
+    /// What double-tapping each modifier does (see `gesture`)
+    pub fn set_gestures(&mut self, gestures: Gestures) {
+        if gestures != self.gestures {
+            self.gestures = gestures;
+            self.recognizer.reset();
+            self.notify_setting(Setting::Gestures(gestures));
+        }
+    }
+
+    /// Gesture the last key event (or `process_keys` run) completed, after
+    /// the engine did its part
+    /// (toggling Vietnamese or the method); `Suggestions` is the host's to
+    /// show. Observers hear of each one (`Notification::Gesture`).
+    pub fn gesture(&self) -> Option<GestureAction> {
+        self.gesture
+    }
+
+    /// Tell observers when a word is committed, corrected or restored
+    /// (`Notification::Feedback`), for hosts playing sounds or haptics
+    pub fn set_feedback(&mut self, on: bool) {
+        if on != self.feedback {
+            self.feedback = on;
+            self.notify_setting(Setting::Feedback(on));
+        }
+    }
+
+    /// Least time between two feedback events, in milliseconds (0 = no
+    /// throttling); a sooner event only goes out if it outranks the last.
+    /// Timed by the host clock of the keys when they carry one
+    /// (`on_key_at`)
+    pub fn set_feedback_gap_ms(&mut self, ms: u8) {
+        if ms != self.feedback_gap_ms {
+            self.feedback_gap_ms = ms;
+            self.notify_setting(Setting::FeedbackGapMs(ms));
+        }
+    }
+
+    /// Most edits a second before the engine stops editing, taking them
+    /// for a feedback loop (see `runaway`; 0 = no limit)
+    pub fn set_edit_limit(&mut self, limit: u8) {
+        if limit != self.edit_limit {
+            self.edit_limit = limit;
+            self.notify_setting(Setting::EditLimit(limit));
+        }
+    }
+
+    /// Times edits came faster than `edit_limit` and the engine stopped
+    /// (`Notification::Runaway`)
+    pub fn runaways(&self) -> u32 {
+        self.runaways
+    }
+
+    /// Let `result` through unless it's one edit too many (see `runaway`)
+    fn guard_edit(&mut self, result: Result) -> Result {
+        if result.action == Action::None as u8 || result.backspace == 0 {
+            return result;
+        }
+        // Keys without a host time can't be timed
+        let Some(now_ms) = self.key_ms else {
+            return result;
+        };
+        match self.runaway.on_edit(self.edit_limit, now_ms) {
+            runaway::Verdict::Allow => return result,
+            runaway::Verdict::Trip => {
+                self.runaways = self.runaways.saturating_add(1);
+                self.observers.notify(Notification::Runaway);
+            }
+            runaway::Verdict::Blocked => {}
+        }
+        self.clear();
+        Result::none()
+    }
+
+    /// Send a feedback event, unless off or throttled
+    fn feedback(&mut self, kind: Feedback) {
+        if !self.feedback {
+            return;
+        }
+        let now = self.now();
+        let gap = std::time::Duration::from_millis(self.feedback_gap_ms as u64);
+        if let Some((at, last)) = self.last_feedback {
+            if kind <= last && now.duration_since(at) < gap {
+                return;
+            }
+        }
+        self.last_feedback = Some((now, kind));
+        self.observers.notify(Notification::Feedback(kind));
+    }
+
+    /// Add a correction source, asked before the dialect packs
+    pub fn add_correction_provider(&mut self, provider: impl CorrectionProvider + 'static) {
+        self.corrections.push(provider);
+    }
+
+    /// Use a plugin for corrections and restore hints; its suggestions
+    /// join `plugin_suggestions`
+    #[cfg(feature = "plugins")]
+    pub fn add_plugin(&mut self, plugin: crate::plugin::Plugin) {
+        self.add_correction_provider(plugin.clone());
+        #[cfg(feature = "autocorrect")]
+        self.add_restore_hint(plugin.clone());
+        self.plugins.push(plugin);
+    }
+
+    /// Let the neural model (`crate::ml`) weigh in on restoring words;
+    /// it is also what `diacritic_model` hands out for toneless text
+    #[cfg(feature = "ml")]
+    pub fn set_diacritic_model(&mut self, model: crate::ml::DiacriticModel) {
+        #[cfg(feature = "autocorrect")]
+        self.add_restore_hint(model.clone());
+        self.diacritic_model = Some(model);
+    }
+
+    #[cfg(feature = "ml")]
+    pub fn diacritic_model(&self) -> Option<&crate::ml::DiacriticModel> {
+        self.diacritic_model.as_ref()
+    }
+
+    /// Suggestions for `word` from every plugin, in the order added
+    #[cfg(feature = "plugins")]
+    pub fn plugin_suggestions(&self, word: &str) -> Vec<String> {
+        let mut words: Vec<String> = Vec::new();
+        for w in self.plugins.iter().flat_map(|p| p.suggest(word)) {
+            if !words.contains(&w) {
+                words.push(w);
+            }
+        }
+        words
+    }
+
+    /// Get notified whenever a setting changes
+    pub fn subscribe(&mut self, f: impl FnMut(&Notification) + Send + 'static) -> ObserverId {
+        self.observers.subscribe(f)
+    }
+
+    /// Get setting changes over a channel
+    pub fn subscribe_channel(&mut self) -> (ObserverId, std::sync::mpsc::Receiver<Notification>) {
+        self.observers.subscribe_channel()
+    }
+
+    /// Remove a subscription; returns false if the id is unknown
+    pub fn unsubscribe(&mut self, id: ObserverId) -> bool {
+        self.observers.unsubscribe(id)
+    }
+
+    fn notify_setting(&mut self, setting: Setting) {
+        self.observers.notify(Notification::SettingChanged(setting));
+    }
+
+    /// Current settings
+    pub fn config(&self) -> EngineConfig {
+        EngineConfig {
+            method: Method::from_id(self.method),
+            enabled: self.enabled,
+            tone_style: if self.modern {
+                ToneStyle::Modern
+            } else {
+                ToneStyle::Classic
+            },
+            auto_correct: self.auto_correct,
+            bracket_shortcuts: self.bracket_shortcuts,
+            money_shorthand: self.money_shorthand,
+            tab_shortcuts: self.tab_shortcuts,
+            max_word_len: self.max_word_len,
+            overflow: self.overflow,
+            remove_key: self.remove_key,
+            uo_compound: self.uo_compound,
+            correction_packs: self.corrections.packs,
+            iy_style: self.corrections.iy,
+            correction_style: self.correction_style,
+            accept_key: self.accept_key,
+            nbsp: self.nbsp,
+            nbsp_chord: self.nbsp_chord,
+            idle_commit_secs: self.idle_commit_secs,
+            mouse_click: self.mouse_click,
+            mouse_scroll: self.mouse_scroll,
+            repeat_key: self.repeat_key,
+            gestures: self.gestures,
+            feedback: self.feedback,
+            feedback_gap_ms: self.feedback_gap_ms,
+            edit_limit: self.edit_limit,
+            restore_order: self.restore_order,
+            restore_disabled: self.restore_disabled,
+            bigram_threshold: self.bigram_threshold,
+            min_confidence: self.min_confidence,
+        }
+    }
+
+    /// Apply all settings at once; nothing is applied if they conflict
+    /// (see `EngineConfig::validate`)
+    pub fn apply_config(&mut self, config: &EngineConfig) -> std::result::Result<(), ConfigError> {
+        config.validate()?;
+        self.apply_method(config.method.id());
+        self.set_enabled(config.enabled);
+        self.set_modern(config.tone_style == ToneStyle::Modern);
+        self.set_auto_correct(config.auto_correct);
+        self.set_restore_order(config.restore_order);
+        self.set_restore_disabled(config.restore_disabled);
+        self.set_bigram_threshold(config.bigram_threshold);
+        self.set_min_confidence(config.min_confidence);
+        self.apply_bracket_shortcuts(config.bracket_shortcuts);
+        self.set_money_shorthand(config.money_shorthand);
+        self.set_tab_shortcuts(config.tab_shortcuts);
+        self.set_max_word_len(config.max_word_len);
+        self.set_overflow_policy(config.overflow);
+        self.apply_remove_key(config.remove_key);
+        self.set_uo_compound(config.uo_compound);
+        self.set_correction_packs(config.correction_packs);
+        self.set_iy_style(config.iy_style);
+        self.set_correction_style(config.correction_style);
+        self.set_accept_key(config.accept_key);
+        self.set_nbsp(config.nbsp);
+        self.set_nbsp_chord(config.nbsp_chord);
+        self.set_idle_commit_secs(config.idle_commit_secs);
+        self.set_mouse_actions(config.mouse_click, config.mouse_scroll);
+        self.set_repeat_key(config.repeat_key);
+        self.set_gestures(config.gestures);
+        self.set_feedback(config.feedback);
+        self.set_feedback_gap_ms(config.feedback_gap_ms);
+        self.set_edit_limit(config.edit_limit);
+        Ok(())
+    }
+
+    /// Set the backend used by `save` and `load`
+    pub fn set_storage(&mut self, storage: impl Storage + 'static) {
+        self.storage = Some(Box::new(storage));
+    }
+
+    /// Persist settings and shortcuts
+    ///
+    /// Each blob is sealed with a checksum so `load` can detect corruption.
+    pub fn save(&mut self) -> std::result::Result<(), StorageError> {
+        let config = self.config().to_toml();
+        let shortcuts = self.shortcuts.to_tsv();
+        let storage = self.storage.as_mut().ok_or(StorageError::NoStorage)?;
+        storage.write(CONFIG_BLOB, &storage::seal(config.as_bytes()))?;
+        storage.write(SHORTCUTS_BLOB, &storage::seal(shortcuts.as_bytes()))?;
+        if !self.sync_doc.is_empty() {
+            storage.write(
+                SYNC_BLOB,
+                &storage::seal(self.sync_doc.to_json().as_bytes()),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Restore settings and shortcuts saved by `save`
+    ///
+    /// Missing blobs keep current values. Corrupt or unreadable blobs are
+    /// reset to built-in defaults and reported by `state_health`; only
+    /// backend failures are errors (and then nothing is applied). Settings
+    /// that read fine but conflict lose only the conflicting option
+    /// (`StateHealth::config_error`).
+    pub fn load(&mut self) -> std::result::Result<(), StorageError> {
+        let storage = self.storage.as_deref_mut().ok_or(StorageError::NoStorage)?;
+        let mut health = StateHealth::default();
+        let mut config = read_blob(storage, CONFIG_BLOB, &mut health, |s| {
+            EngineConfig::parse_toml(s).ok()
+        })?;
+        if let Some(config) = &mut config {
+            health.config_error = config.drop_conflicts();
+        }
+        let shortcuts = read_blob(storage, SHORTCUTS_BLOB, &mut health, |s| {
+            ShortcutTable::from_tsv(s).ok()
+        })?;
+        let sync_doc = read_blob(storage, SYNC_BLOB, &mut health, |s| {
+            SyncDoc::from_json(s).ok()
+        })?;
+
+        let reset = |name| health.reset.contains(&name);
+        if let Some(config) = config.or_else(|| reset(CONFIG_BLOB).then(EngineConfig::default)) {
+            // Conflicts are dropped above
+            if let Err(err) = self.apply_config(&config) {
+                health.config_error = Some(err);
+            }
+        }
+        if let Some(shortcuts) =
+            shortcuts.or_else(|| reset(SHORTCUTS_BLOB).then(ShortcutTable::with_defaults))
+        {
+            self.shortcuts = shortcuts;
+        }
+        if let Some(sync_doc) = sync_doc.or_else(|| reset(SYNC_BLOB).then(SyncDoc::new)) {
+            self.sync_doc = sync_doc;
+        }
+        self.health = health;
+        Ok(())
+    }
+
+    /// Warm up the data tables on a background thread
+    ///
+    /// Call once at app launch. Later calls are no-ops and return `None`;
+    /// the handle is only needed to wait for completion.
+    pub fn prewarm() -> Option<std::thread::JoinHandle<()>> {
+        prewarm::start()
+    }
+
+    pub fn prewarm_status() -> prewarm::PrewarmStatus {
+        prewarm::status()
+    }
+
+    /// Table that failed validation, when status is `Failed`
+    pub fn prewarm_failure() -> Option<&'static str> {
+        prewarm::failure()
+    }
+
+    /// Type a fixed battery of cases on fresh engines (see `selftest`)
+    pub fn self_test() -> selftest::SelfTestReport {
+        selftest::run()
+    }
+
+    /// Copy of the word state, for tests and tools (see `debug`)
+    pub fn debug_state(&self) -> debug::DebugState {
+        debug::capture(self)
+    }
+
+    /// Shape edits for the focused app (ends the current word)
+    pub fn set_output_profile(&mut self, profile: OutputProfile) {
+        self.clear();
+        self.output = profile;
+    }
+
+    /// The focused app changed (`None`: unknown): its built-in output
+    /// profile, and the app word rules see
+    pub fn set_app(&mut self, app_id: Option<&str>) {
+        self.set_output_profile(app_id.map_or_else(OutputProfile::default, OutputProfile::for_app));
+        #[cfg(feature = "rules")]
+        {
+            self.app = app_id.map(str::to_string);
+        }
+    }
+
+    /// Replace the word rules (see `rules`)
+    #[cfg(feature = "rules")]
+    pub fn set_rules(&mut self, rules: Rules) {
+        self.rules = rules;
+    }
+
+    #[cfg(feature = "rules")]
+    pub fn rules(&self) -> &Rules {
+        &self.rules
+    }
+
+    pub fn output_profile(&self) -> OutputProfile {
+        self.output
+    }
+
+    /// Compare the field with what the engine sent (see `output`)
+    ///
+    /// `prefix` is the field's text before the caret, read by the host
+    /// after delivering the last edit. On a mismatch the word is dropped,
+    /// a `GhostEdit` is recorded and edits fall back to
+    /// `OutputProfile::BOUNDARY` until `set_output_profile`.
+    pub fn check_field(&mut self, prefix: &str) -> FieldCheck {
+        let (expected, prefix) = if !self.buf.is_empty() {
+            let screen = self
+                .pending
+                .unwrap_or_else(|| Pending::new(&self.buf.shown()));
+            // Skip the space the `nbsp_chord` typed after the word
+            let end = match prefix.chars().next_back() {
+                Some(c) if self.nbsp_after => prefix.len() - c.len_utf8(),
+                _ => prefix.len(),
+            };
+            (screen, &prefix[..end])
+        } else if let Some((word, tail)) = self.last_word {
+            // Skip what the boundary key typed after the word
+            let end = prefix
+                .char_indices()
+                .rev()
+                .take(tail)
+                .last()
+                .map_or(prefix.len(), |(i, _)| i);
+            (word, &prefix[..end])
+        } else {
+            return FieldCheck::Unknown;
+        };
+        self.compare_field(expected.as_slice(), prefix)
+    }
+
+    fn compare_field(&mut self, expected: &[char], prefix: &str) -> FieldCheck {
+        if expected.is_empty() {
+            return FieldCheck::Unknown;
+        }
+        let matched = output::trailing_match(expected, prefix);
+        if matched == expected.len() {
+            return FieldCheck::Match;
+        }
+        if self.ghost_edits.len() == MAX_GHOST_EDITS {
+            self.ghost_edits.remove(0);
+        }
+        self.ghost_edits.push(GhostEdit {
+            expected: expected.len(),
+            matched,
+            profile: self.output,
+        });
+        self.clear();
+        self.output = OutputProfile::BOUNDARY;
+        FieldCheck::Mismatch
+    }
+
+    /// Lost edits found by `check_field`, oldest first (the last 16)
+    pub fn ghost_edits(&self) -> &[GhostEdit] {
+        &self.ghost_edits
+    }
+
+    /// Tell the engine where the caret is (UTF-16 offset in the field)
+    ///
+    /// Needed for `Delivery::Select`; None = unknown. The engine follows the
+    /// caret from here through the keys it handles.
+    pub fn set_caret(&mut self, offset: Option<usize>) {
+        self.caret = offset;
+        self.edit = None;
+    }
+
+    /// Caret offset as far as the engine can follow it
+    pub fn caret(&self) -> Option<usize> {
+        self.caret
+    }
+
+    /// Text the last key's edit replaces, as absolute offsets
+    ///
+    /// Covers the `backspace` characters of the result, ending at the caret
+    /// before the key. None when the key made no edit or the caret is
+    /// unknown. `process_keys` merges edits, so only the last range is kept.
+    pub fn edit_range(&self) -> Option<std::ops::Range<usize>> {
+        self.edit.clone()
+    }
+
+    /// Word being composed, or the one the last key finished
+    ///
+    /// For screen readers: query after each key and announce the word once
+    /// `committed` is set. None between words or when some character of
+    /// the word has no display form.
+    pub fn word_info(&self) -> Option<WordInfo> {
+        let utf16 = |chars: &[char]| chars.iter().map(|c| c.len_utf16()).sum::<usize>() as isize;
+        if !self.buf.is_empty() {
+            let shown = self.buf.shown();
+            if shown.is_empty() {
+                return None;
+            }
+            // Deferred edits: the composed word isn't on screen yet
+            let on_screen = match self.pending {
+                Some(ref p) => utf16(p.as_slice()),
+                None => utf16(&shown),
+            };
+            // The `nbsp_chord` may have typed a space after it
+            let end = -(self.nbsp_after as isize);
+            return Some(WordInfo {
+                text: shown.iter().collect(),
+                start: end - on_screen,
+                end,
+                committed: false,
+            });
+        }
+        let (word, tail) = self.last_word.as_ref()?;
+        let end = -(*tail as isize);
+        Some(WordInfo {
+            text: word.as_slice().iter().collect(),
+            start: end - utf16(word.as_slice()),
+            end,
+            committed: true,
+        })
+    }
+
+    /// Characters the host should move the caret left after applying the
+    /// last key's output (`Result::caret_back`; a `{|}` in an expansion)
+    ///
+    /// For hosts on `process_key`, whose `Output` doesn't carry it.
+    pub fn caret_back(&self) -> u8 {
+        self.caret_back
+    }
+
+    /// Word being composed, as it stands after the last key ("đươc" while
+    /// "dduwowc" is typed); empty between words
+    ///
+    /// For hosts drawing an inline preview or a hint window. It's the
+    /// composed word even while edits are held back (`Strategy::AtBoundary`)
+    /// and the screen still shows the keystrokes. Doesn't allocate: fine to
+    /// call after every key.
+    pub fn current_preview(&mut self) -> &str {
+        self.preview.clear();
+        self.preview.extend(self.buf.shown().iter());
+        &self.preview
+    }
+
+    /// How the word being typed reads so far: Vietnamese, not, or
+    /// English (see `spell`)
+    pub fn spell_status(&self) -> SpellStatus {
+        self.spell.status()
+    }
+
+    /// Tell observers each time `spell_status` changes
+    /// (`Notification::SpellStatus`); off by default, since it changes
+    /// several times a word
+    pub fn watch_spell_status(&mut self, on: bool) {
+        self.watch_spell = on;
+    }
+
+    fn notify_spell(&mut self, status: SpellStatus) {
+        if self.watch_spell {
+            self.observers.notify(Notification::SpellStatus(status));
+        }
+    }
+
+    /// Fix an enabled correction pack has for the word being typed, if
+    /// it's one of their misspellings ("mún" → "muốn" with the southern
+    /// pack)
+    ///
+    /// For hosts underlining known typos as they're typed, before the
+    /// boundary fixes them. Doesn't allocate: fine to call after every key.
+    pub fn typo(&self) -> Option<&'static str> {
+        if self.corrections.packs == CorrectionPacks::NONE || self.buf.is_empty() {
+            return None;
+        }
+        let node = self.typo_cursor.node()?;
+        self.corrections.typo_at(node, &self.buf.shown())
+    }
+
+    /// What the engine may keep about typed words (nothing until set; see
+    /// `history`)
+    pub fn set_privacy(&mut self, privacy: Privacy) {
+        self.history.set_privacy(privacy, self.now());
+        if !privacy.retain {
+            self.shadow_log.forget_words();
+        }
+    }
+
+    /// Run a heuristic alongside the active ones without using it (see
+    /// `shadow`)
+    pub fn add_shadow(&mut self, heuristic: impl ShadowHeuristic + 'static) {
+        self.shadows.push(Box::new(heuristic));
+    }
+
+    /// What the shadow heuristics would have done differently
+    pub fn shadow_log(&self) -> &ShadowLog {
+        &self.shadow_log
+    }
+
+    pub fn clear_shadow_log(&mut self) {
+        self.shadow_log.clear();
+    }
+
+    pub fn privacy(&self) -> Privacy {
+        self.history.privacy()
+    }
+
+    /// Recently finished words, newest first, as `Privacy` allows
+    pub fn recent_words(&self) -> impl Iterator<Item = &str> {
+        self.history.recent(self.now())
+    }
+
+    /// Count finished words for `word_counts` (off by default; turning it
+    /// off forgets the counts). Nothing is counted with retention off.
+    pub fn set_word_counting(&mut self, on: bool) {
+        self.history.set_counting(on);
+    }
+
+    /// How often each word was typed, most typed first
+    pub fn word_counts(&self) -> Vec<(&str, u32)> {
+        self.history.word_counts(self.now())
+    }
+
+    /// `word_counts` as CSV (`word,count` header), for pack authors
+    pub fn word_counts_csv(&self) -> String {
+        self.history.word_counts_csv(self.now())
+    }
+
+    /// Forget everything typed: the current word and all history
+    ///
+    /// For a "Clear typing data" button. Settings and shortcuts stay.
+    pub fn wipe_all(&mut self) {
+        self.clear();
+        self.history.wipe();
+    }
+
+    /// What the last `load` had to reset
+    pub fn state_health(&self) -> &StateHealth {
+        &self.health
+    }
+
+    /// Merge settings and shortcuts with the copy shared through `transport`
+    ///
+    /// Local changes since the last sync are stamped with `now` (ms since
+    /// the Unix epoch) and `device`, merged last-writer-wins with the shared
+    /// document, applied, and pushed back. `enabled` stays per machine.
+    pub fn sync(
+        &mut self,
+        transport: &mut dyn SyncTransport,
+        device: &str,
+        now: u64,
+    ) -> std::result::Result<SyncReport, SyncError> {
+        let local = self.profile();
+        let mut doc = self.sync_doc.clone();
+        doc.update(&local, now, device);
+
+        let mut report = SyncReport::default();
+        if let Some(bytes) = transport.pull()? {
+            report.pulled = doc.merge(&SyncDoc::from_bytes(&bytes)?);
+        }
+        let mut merged = doc.to_profile(&local.config).map_err(SyncError::Profile)?;
+        merged.config.enabled = local.config.enabled;
+        // Re-stamp anything the merge had to override (conflicting settings)
+        doc.update(&merged, now, device);
+        transport.push(doc.to_json().as_bytes())?;
+
+        self.sync_doc = doc;
+        self.apply_profile(merged).map_err(SyncError::Profile)?;
+        Ok(report)
+    }
+    /// Import settings and shortcuts exported by another IME
+    ///
+    /// Imported shortcuts replace existing ones with the same trigger.
+    /// Nothing is applied if the file is invalid.
+    #[cfg(feature = "macros")]
+    pub fn import_from(
+        &mut self,
+        format: ImportFormat,
+        bytes: &[u8],
+    ) -> std::result::Result<ImportReport, ImportError> {
+        let imported = importer::parse(format, bytes, &self.config())?;
+        self.apply_config(&imported.config)
+            .map_err(ImportError::Config)?;
+        let shortcuts = imported.shortcuts.len();
+        for sc in imported.shortcuts {
+            self.shortcuts.add(sc);
+        }
+        Ok(ImportReport {
+            shortcuts,
+            skipped: imported.skipped,
+        })
+    }
+
+    /// Shortcuts as a macro file to share (`ImportFormat::UniKey` or
+    /// `Csv`, read back by `import_from`); `None` for settings formats
+    #[cfg(feature = "macros")]
+    pub fn export_macros(&self, format: ImportFormat) -> Option<Exported> {
+        importer::export_macros(format, self.shortcuts.iter())
+    }
+
+    /// Settings and shortcuts as a portable JSON profile
+    pub fn export_profile(&self) -> String {
+        self.profile().to_json()
+    }
+
+    fn profile(&self) -> Profile {
+        Profile {
+            config: self.config(),
+            shortcuts: self.shortcuts.iter().cloned().collect(),
+        }
+    }
+
+    fn apply_profile(&mut self, profile: Profile) -> std::result::Result<(), ProfileError> {
+        self.apply_config(&profile.config)
+            .map_err(ProfileError::Config)?;
+        self.shortcuts.clear();
+        for sc in profile.shortcuts {
+            self.shortcuts.add(sc);
+        }
+        Ok(())
+    }
+
+    /// Replace settings and shortcuts with a profile from `export_profile`
+    ///
+    /// Nothing is applied if the profile is invalid.
+    pub fn import_profile(&mut self, json: &str) -> std::result::Result<(), ProfileError> {
+        self.apply_profile(Profile::from_json(json)?)
+    }
+
+    pub fn shortcuts(&self) -> &ShortcutTable {
+        &self.shortcuts
+    }
+
+    pub fn shortcuts_mut(&mut self) -> &mut ShortcutTable {
+        &mut self.shortcuts
+    }
+
+    /// How `sc` would get in the way of typing, next to the shortcuts
+    /// already in the table (one with the same trigger is the one `sc`
+    /// replaces); for a settings UI before it saves
+    #[cfg(feature = "macros")]
+    pub fn check_shortcut(&self, sc: &Shortcut) -> Vec<Conflict> {
+        let mut out = Vec::new();
+        // Triggers match the letters typed, whatever the marks on them
+        let letters = sc.trigger.to_lowercase();
+        if validation::explain(&letters).is_valid() {
+            out.push(Conflict::Word(letters.clone()));
+        }
+        out.extend(
+            self.corrections
+                .spelled(&letters)
+                .map(|(word, fixed)| Conflict::Correction {
+                    word: word.to_string(),
+                    fixed: fixed.to_string(),
+                }),
+        );
+        for other in self.shortcuts.iter() {
+            let (a, b) = (&sc.trigger, &other.trigger);
+            if other.enabled
+                && a != b
+                && (a.starts_with(b.as_str()) || b.starts_with(a.as_str()))
+                && sc.shares_method(other)
+            {
+                out.push(Conflict::Prefix(b.clone()));
+            }
+        }
+        out
+    }
+
+    /// Conflicts of every enabled shortcut in the table (after an import)
+    #[cfg(feature = "macros")]
+    pub fn shortcut_conflicts(&self) -> Vec<ShortcutConflict> {
+        let mut out = Vec::new();
+        for sc in self.shortcuts.iter().filter(|sc| sc.enabled) {
+            out.extend(
+                self.check_shortcut(sc)
+                    .into_iter()
+                    .map(|conflict| ShortcutConflict {
+                        trigger: sc.trigger.clone(),
+                        conflict,
+                    }),
+            );
+        }
+        out
+    }
+
+    /// Add a dynamic macro, asked before the built-in ones
+    #[cfg(feature = "macros")]
+    pub fn add_dynamic_macro(&mut self, m: impl DynamicMacro + 'static) {
+        self.macros.push(m);
+    }
+
+    /// Today's date for "=date" and `%d`; the host sets it at startup and
+    /// when the day changes (`None` leaves them as typed)
+    #[cfg(feature = "macros")]
+    pub fn set_date(&mut self, date: Option<Date>) {
+        self.macros.today = date;
+    }
+
+    /// Clock read when "=date" or a shortcut's `%d` / `%t` expands; its
+    /// date wins over `set_date`'s
+    #[cfg(feature = "macros")]
+    pub fn set_clock(&mut self, clock: impl Clock + 'static) {
+        self.macros.clock = Some(Box::new(clock));
+    }
+
+    /// Back to the date from `set_date` alone
+    #[cfg(feature = "macros")]
+    pub fn clear_clock(&mut self) {
+        self.macros.clock = None;
+    }
+
+    /// Get current input method as InputMethod enum
+    #[cfg(feature = "macros")]
+    fn current_input_method(&self) -> InputMethod {
+        match self.method {
+            0 => InputMethod::Telex,
+            1 => InputMethod::Vni,
+            _ => InputMethod::All,
+        }
+    }
+
+    /// Handle key event - main entry point
+    ///
+    /// # Arguments
+    /// * `key` - macOS virtual keycode
+    /// * `caps` - true if Caps Lock is active (for uppercase letters)
+    /// * `ctrl` - true if Cmd/Ctrl/Alt is pressed (bypasses IME)
+    pub fn on_key(&mut self, key: u16, caps: bool, ctrl: bool) -> Result {
+        self.on_key_ext(key, caps, ctrl, false)
+    }
+
+    /// Handle a key, returning text borrowed from the engine
+    ///
+    /// Same arguments and behavior as `on_key_ext`; the result stays valid
+    /// until the next call. Nothing is allocated, so Rust hosts can call it
+    /// on every keystroke without touching the heap.
+    pub fn process_key(&mut self, key: u16, caps: bool, ctrl: bool, shift: bool) -> Output<'_> {
+        let r = self.on_key_ext(key, caps, ctrl, shift);
+        if r.action == Action::None as u8 {
+            return Output::PassThrough;
+        }
+        let mut chars = r.chars[..r.count as usize]
+            .iter()
+            .filter_map(|&c| char::from_u32(c));
+        if r.backspace == 0 && r.count == 1 {
+            if let Some(c) = chars.next() {
+                return Output::Append(c);
+            }
+        }
+        self.out.clear();
+        self.out.extend(chars);
+        Output::Replace {
+            backspace: r.backspace,
+            text: &self.out,
+        }
+    }
+
+    /// Handle a full key event (key-down/key-up with modifier flags)
+    ///
+    /// - Key-up: releases the held key, never touches the buffer
+    /// - Ctrl/Alt/Cmd chords (Cmd+A, AltGr+Q...): clear buffer, pass through
+    /// - Auto-repeat of a letter/number: per `repeat_key`, by default typed
+    ///   literally, no mark/tone toggling (holding `s` must not flip sắc on
+    ///   and off)
+    ///
+    /// A key-down for the key still held counts as repeat even if the host
+    /// doesn't set `repeat`, so hosts using this API must deliver key-ups.
+    pub fn on_event(&mut self, ev: KeyEvent) -> Result {
+        let result = self.handle_event(ev);
+        self.key_ms = None;
+        result
+    }
+
+    fn handle_event(&mut self, ev: KeyEvent) -> Result {
+        self.gesture = None;
+        if self.gestures != Gestures::NONE {
+            if let Some(key) = self.recognizer.on_event(&ev) {
+                if let Some(action) = self.gestures.get(key) {
+                    self.run_gesture(action);
+                }
+            }
+        }
+        let mut repeat = false;
+        if ev.down && ev.time_ms != 0 {
+            repeat = self.timed_key(ev.time_ms, ev.key);
+        }
+        if !ev.down {
+            if self.held_key == Some(ev.key) {
+                self.held_key = None;
+            }
+            return Result::none();
+        }
+
+        repeat |= ev.repeat || self.held_key == Some(ev.key);
+        self.held_key = Some(ev.key);
+
+        if repeat && !ev.is_command() {
+            if let Some(result) = self.on_repeat(ev.key) {
+                return result;
+            }
+        }
+
+        self.on_key_mods(ev.key, ev.caps(), ev.modifiers)
+    }
+
+    /// A modifier was double-tapped: do what `action` says. A method
+    /// switch the settings conflict with (`set_method`) is no gesture.
+    fn run_gesture(&mut self, action: GestureAction) {
+        match action {
+            GestureAction::ToggleEnabled => self.set_enabled(!self.enabled),
+            GestureAction::ToggleMethod => {
+                if self.set_method(1 - self.method.min(1)).is_err() {
+                    return;
+                }
+            }
+            GestureAction::Suggestions => {}
+        }
+        self.gesture = Some(action);
+        self.observers.notify(Notification::Gesture(action));
+    }
+
+    /// Auto-repeat of `key`, per `repeat_key`; None to handle it as the key
+    /// typed again (also for keys other than letters and numbers)
+    fn on_repeat(&mut self, key: u16) -> Option<Result> {
+        if !(keys::is_letter(key) || keys::is_number(key)) || !self.enabled {
+            return None;
+        }
+        match self.repeat_key {
+            RepeatKey::Normal => None,
+            RepeatKey::Single => Some(Result::send(0, &[])),
+            RepeatKey::Raw => {
+                self.clear();
+                let mut result = Result::none();
+                self.track_caret(key, false, false, &mut result);
+                Some(result)
+            }
+        }
+    }
+
+    /// Handle buffered events in one call (fast typing, replay)
+    ///
+    /// Returns one output per event, in order. Consecutive edits are merged
+    /// into a single backspace+insert on the last of them, so the host
+    /// redraws once; the earlier keys get `Replace { backspace: 0, text: "" }`
+    /// (swallow the key, nothing to draw). A pass-through key-down ends the
+    /// run, since the host inserts that key itself. Merged text stays within
+    /// `MAX` characters, like `Result`.
+    pub fn process_keys(&mut self, events: &[KeyEvent]) -> Vec<Output<'_>> {
+        // Per event: None = pass through, Some((backspace, byte range in `out`))
+        let mut edits: Vec<Option<(u8, std::ops::Range<usize>)>> = Vec::with_capacity(events.len());
+        // Edit the next one may merge into
+        let mut open: Option<usize> = None;
+        // Last gesture of the run, for `gesture`
+        let mut gesture = None;
+        self.out.clear();
+
+        for &ev in events {
+            let r = self.on_event(ev);
+            gesture = self.gesture.or(gesture);
+            if r.action == Action::None as u8 {
+                edits.push(None);
+                // Key-ups insert nothing, so they don't split a run
+                if ev.down {
+                    open = None;
+                }
+                continue;
+            }
+            let start = self.out.len();
+            self.out.extend(
+                r.chars[..r.count as usize]
+                    .iter()
+                    .filter_map(|&c| char::from_u32(c)),
+            );
+            let mut edit = (r.backspace, start..self.out.len());
+
+            if let Some(i) = open {
+                if let Some((prev_bs, prev)) = edits[i].clone() {
+                    let prev_chars = self.out[prev.clone()].chars().count();
+                    let new_chars = self.out[start..].chars().count();
+                    let bs = r.backspace as usize;
+                    // Backspaces eat into the previous insert first
+                    let kept = prev_chars.saturating_sub(bs);
+                    let merged_bs = prev_bs as usize + bs.saturating_sub(prev_chars);
+                    if kept + new_chars <= MAX && merged_bs <= u8::MAX as usize {
+                        let cut = self.out[prev.clone()]
+                            .char_indices()
+                            .nth(kept)
+                            .map_or(start, |(b, _)| prev.start + b);
+                        self.out.replace_range(cut..start, "");
+                        edit = (merged_bs as u8, prev.start..self.out.len());
+                        edits[i] = Some((0, 0..0));
+                    }
+                }
+            }
+            // Keys after a caret move are typed elsewhere: no merging into it
+            open = (r.caret_back == 0).then_some(edits.len());
+            edits.push(Some(edit));
+        }
+        self.gesture = gesture;
+
+        let out = &self.out;
+        edits
+            .into_iter()
+            .map(|edit| match edit {
+                None => Output::PassThrough,
+                Some((backspace, range)) => {
+                    let text = &out[range];
+                    let mut chars = text.chars();
+                    match (backspace, chars.next(), chars.next()) {
+                        (0, Some(c), None) => Output::Append(c),
+                        _ => Output::Replace { backspace, text },
+                    }
+                }
+            })
+            .collect()
+    }
+
+    /// Handle a character already composed by the OS layout
+    ///
+    /// For US-International dead keys (`'` + `e` → `é`) or another IME
+    /// layered below, the host receives text instead of keystrokes.
+    /// The host inserts the character itself (action is always None for
+    /// composed letters); the engine only records it so that following
+    /// keystrokes compose on top: `ê` (from the OS) + `s` → `ế`.
+    ///
+    /// ASCII letters, digits and space go through the normal key path.
+    /// Anything else (symbols, emoji, foreign letters) ends the word;
+    /// punctuation ("," or a closing "”") may correct it first, like a
+    /// punctuation key.
+    pub fn on_text(&mut self, ch: char) -> Result {
+        self.dismiss_suggestion();
+        if ch.is_ascii() {
+            return match keys::from_char(ch) {
+                Some(key) => self.on_key_ext(key, ch.is_ascii_uppercase(), false, false),
+                None => {
+                    let result = self.end_word_at_text(ch);
+                    self.edit = None;
+                    self.caret = self.caret.map(|c| c + 1);
+                    result
+                }
+            };
+        }
+
+        // The host inserts it itself
+        self.edit = None;
+        self.caret = self.caret.map(|c| c + ch.len_utf16());
+
+        let parsed = match chars::parse_char(ch) {
+            Some(p) if self.enabled => p,
+            _ => return self.end_word_at_text(ch),
+        };
+
+        let c = self.composed_char(ch, &parsed);
+        if self.pending.is_some() || self.output.strategy == Strategy::AtBoundary {
+            let mut screen = self
+                .pending
+                .take()
+                .unwrap_or_else(|| Pending::new(&self.buf.shown()));
+            if !screen.push(ch) {
+                self.clear();
+                return Result::none();
+            }
+            self.pending = Some(screen);
+        }
+        self.buf.push(c);
+        self.last_transform = None;
+        self.follow_word();
+        Result::none()
+    }
+
+    /// A character that isn't a letter arrived as text: restore or correct
+    /// the word before it if it's punctuation, then end the word
+    fn end_word_at_text(&mut self, ch: char) -> Result {
+        let mut result = Result::none();
+        // Held-back edits (`pending`) leave the screen out of step with the
+        // word: give it up as is
+        if self.enabled && self.pending.is_none() && tokenizer::is_punctuation(ch) {
+            result = self.finish_word(None, Some(ch), true);
+            result = self.guard_edit(result);
+        }
+        self.clear();
+        result
+    }
+
+    /// Buffer char for a character that arrived as text
+    fn composed_char(&mut self, ch: char, parsed: &chars::Parsed) -> Char {
+        let mut c = Char::new(parsed.key, parsed.caps);
+        c.tone = parsed.tone;
+        c.mark = parsed.mark;
+        c.stroke = parsed.stroke;
+        c.push_origin(Keystroke {
+            text: Some(ch),
+            ..Keystroke::new(parsed.key, parsed.caps, self.next_seq())
+        });
+        c
+    }
+
+    /// The caret moved without typing (click, arrow keys, Home/End...)
+    ///
+    /// Ends the word so nothing composes across the jump. `offset` is the
+    /// new caret position if the host knows it (UTF-16 units).
+    pub fn caret_moved(&mut self, offset: Option<usize>) {
+        self.dismiss_suggestion();
+        self.clear();
+        self.set_caret(offset);
+    }
+
+    /// Another field, window or app has focus
+    ///
+    /// Ends the word and forgets the caret and any held key (its key-up
+    /// goes to the old target).
+    pub fn focus_changed(&mut self) {
+        self.dismiss_suggestion();
+        self.clear();
+        self.set_caret(None);
+        self.held_key = None;
+        self.recognizer.reset();
+    }
+
+    /// Start over after a panic, keeping settings and shortcuts
+    ///
+    /// Drops everything a half-finished key may have left inconsistent:
+    /// the word, held key, caret and output scratch.
+    pub fn recover(&mut self) {
+        self.focus_changed();
+        self.buf = Buffer::new();
+        self.out.clear();
+    }
+
+    /// Re-seed the word from the text before the caret
+    ///
+    /// After a jump the host reads the field and passes the text up to the
+    /// caret; its last word becomes the word being typed (see `seed_word`).
+    /// Returns how many characters were taken (0 if the text ends in a
+    /// space/punctuation, a link or identifier, or the word isn't
+    /// Vietnamese letters).
+    pub fn text_context(&mut self, prefix: &str) -> usize {
+        // Tokens never span whitespace, so the last chunk is enough
+        let chunk = prefix.rsplit(char::is_whitespace).next().unwrap_or("");
+        let word = tokenizer::tokens(chunk)
+            .last()
+            .filter(|t| t.kind == TokenKind::Word)
+            .map_or("", |t| &chunk[t.span.start..]);
+        if self.seed_word(word) {
+            word.chars().count()
+        } else {
+            0
+        }
+    }
+
+    /// Continue a word that's already on screen
+    ///
+    /// Parses text back into buffer state (base letters, tones, marks, đ;
+    /// decomposed text is composed first), as if the user had just typed
+    /// it: clicking after "viêt" (or into "việtnam" after "việt") and
+    /// typing `j` / `s` moves or replaces the mark like it would
+    /// mid-typing. Ends the current word first. Returns false, with nothing
+    /// seeded, unless `word` is one word of Vietnamese-alphabet letters
+    /// (plus f, j, w, z) of at most `MAX` characters.
+    pub fn seed_word(&mut self, word: &str) -> bool {
+        self.clear();
+        let word = chars::compose(word);
+        if !self.enabled || word.is_empty() || word.chars().count() > MAX {
+            return false;
+        }
+        let Some(parsed) = word
+            .chars()
+            .map(|ch| chars::parse_char(ch).map(|p| (ch, p)))
+            .collect::<Option<Vec<_>>>()
+        else {
+            return false;
+        };
+        for (ch, p) in &parsed {
+            let c = self.composed_char(*ch, p);
+            self.buf.push(c);
+        }
+        self.follow_word();
+        true
+    }
+
+    /// Check if key+shift combo is a raw mode prefix character
+    /// Raw prefixes: @ # : /
+    fn is_raw_prefix(key: u16, shift: bool) -> bool {
+        // / doesn't need shift
+        if key == keys::SLASH && !shift {
+            return true;
+        }
+        // @ # : need shift
+        if !shift {
+            return false;
+        }
+        matches!(
+            key,
+            keys::N2              // @ = Shift+2
+                | keys::N3        // # = Shift+3
+                | keys::SEMICOLON // : = Shift+;
+        )
+    }
+
+    /// Handle key event with extended parameters
+    ///
+    /// # Arguments
+    /// * `key` - macOS virtual keycode
+    /// * `caps` - true if Caps Lock is active (for uppercase letters)
+    /// * `ctrl` - true if Cmd/Ctrl/Alt is pressed (bypasses IME)
+    /// * `shift` - true if Shift key is pressed (for symbols like @, #, $)
+    pub fn on_key_ext(&mut self, key: u16, caps: bool, ctrl: bool, shift: bool) -> Result {
+        let mut mods = 0;
+        if ctrl {
+            mods |= modifier::CTRL;
+        }
+        if shift {
+            mods |= modifier::SHIFT;
+        }
+        self.on_key_mods(key, caps, mods)
+    }
+
+    /// `on_key_ext` with each modifier known (`modifier::*`), as from
+    /// `on_event`
+    fn on_key_mods(&mut self, key: u16, caps: bool, mods: u8) -> Result {
+        let ctrl = mods & modifier::COMMAND_MASK != 0;
+        let shift = mods & modifier::SHIFT != 0;
+        if let Some(suggestion) = self.suggestion.take() {
+            if let Some(result) = self.candidate_key(suggestion, key, ctrl, shift) {
+                return result;
+            }
+        }
+        if let Some(mut result) = self.nbsp_key(key, mods) {
+            result = self.guard_edit(result);
+            self.track_caret(key, false, false, &mut result);
+            self.caret_back = 0;
+            self.last_word = None;
+            return result;
+        }
+        let screen = if self.buf.is_empty() {
+            None
+        } else {
+            Some(
+                self.pending
+                    .unwrap_or_else(|| Pending::new(&self.buf.shown())),
+            )
+        };
+        let mut result = self.key_result(key, caps, ctrl, shift);
+        result = self.guard_edit(result);
+        self.enter_after_edit(key, ctrl, shift, &mut result);
+        self.track_caret(key, ctrl, shift, &mut result);
+        self.caret_back = result.caret_back;
+        self.last_word = None;
+        if let Some(screen) = screen {
+            // After a caret move the word isn't just before the caret
+            if self.enabled && !ctrl && ends_word(key, shift) && result.caret_back == 0 {
+                self.last_word = finished_word(screen, &result, key);
+                if let Some((ref word, _)) = self.last_word {
+                    let now = self.now();
+                    self.history.record(word.as_slice(), now);
+                }
+            }
+        }
+        result
+    }
+
+    /// The `nbsp_chord` types the `nbsp` character after the word without
+    /// finishing it: no shortcut, restore or correction, nothing recorded.
+    /// The word stays in the buffer for the next key: Backspace takes the
+    /// space back and typing goes on in the word; any other key commits
+    /// the word as shown and is handled as usual.
+    fn nbsp_key(&mut self, key: u16, mods: u8) -> Option<Result> {
+        if self.nbsp_after && !keys::is_passthrough(key) {
+            self.nbsp_after = false;
+            if key == keys::DELETE && mods & modifier::COMMAND_MASK == 0 {
+                return Some(Result::none());
+            }
+            self.commit_word();
+        }
+        let c = self.nbsp.char()?;
+        let chord = mods & !modifier::CAPS_LOCK == self.nbsp_chord.modifiers();
+        if !self.enabled || key != keys::SPACE || !chord {
+            return None;
+        }
+        let result = match self.pending.take() {
+            // Held-back edits: the composed word goes on screen first
+            Some(screen) => {
+                let mut text = self.buf.shown().to_vec();
+                text.push(c);
+                skip_unchanged(
+                    Result::send(screen.as_slice().len() as u8, &text),
+                    screen.as_slice(),
+                )
+            }
+            None => Result::send(0, &[c]),
+        };
+        self.nbsp_after = !self.buf.is_empty();
+        Some(result)
+    }
+
+    /// With `BoundaryKey::AfterEdit`, put Enter at the end of the edit it
+    /// comes with, so the app can't act on it (send a chat message) before
+    /// the word is corrected
+    fn enter_after_edit(&self, key: u16, ctrl: bool, shift: bool, result: &mut Result) {
+        if self.output.boundary_key != BoundaryKey::AfterEdit
+            || !matches!(key, keys::RETURN | keys::ENTER)
+            || ctrl
+            || shift
+            || result.action != Action::Send as u8
+            || result.count as usize >= MAX
+        {
+            return;
+        }
+        result.chars[result.count as usize] = '\n' as u32;
+        result.count += 1;
+    }
+
+    /// A key while a correction is on offer (the list protocol is on
+    /// `Suggestion`); None once the offer is dropped and the key should do
+    /// its usual job
+    fn candidate_key(
+        &mut self,
+        mut suggestion: Suggestion,
+        key: u16,
+        ctrl: bool,
+        shift: bool,
+    ) -> Option<Result> {
+        let plain = !ctrl && !shift;
+        let step = match key {
+            keys::DOWN => 1,
+            keys::UP => -1,
+            _ => 0,
+        };
+        if plain && step != 0 && suggestion.candidates.len() > 1 {
+            suggestion.browse(step);
+            let selected = suggestion.selected;
+            self.suggestion = Some(suggestion);
+            self.observers
+                .notify(Notification::SuggestionSelected(selected));
+            return Some(self.swallow(key));
+        }
+        self.observers.notify(Notification::SuggestionCleared);
+        if !plain {
+            return None;
+        }
+        if key == self.accept_key.key() {
+            return Some(self.accept_suggestion(suggestion));
+        }
+        if !suggestion.browsing {
+            return None;
+        }
+        if key == keys::ESC {
+            return Some(self.swallow(key));
+        }
+        let n = raw_char(key, false)
+            .and_then(|c| c.to_digit(10))
+            .filter(|&n| n >= 1 && n as usize <= suggestion.candidates.len())?;
+        suggestion.selected = n as usize - 1;
+        Some(self.accept_suggestion(suggestion))
+    }
+
+    /// Take the key without touching the screen (nothing to delete or
+    /// insert)
+    fn swallow(&mut self, key: u16) -> Result {
+        let mut result = Result::send(0, &[]);
+        self.track_caret(key, false, false, &mut result);
+        result
+    }
+
+    /// Retype the word and the character after it as corrected; the
+    /// accept key is swallowed
+    fn accept_suggestion(&mut self, suggestion: Suggestion) -> Result {
+        let backspace = suggestion.word.chars().count() + 1;
+        let chars: Vec<char> = suggestion
+            .fixed()
+            .chars()
+            .chain([suggestion.after])
+            .collect();
+        let mut result = Result::send(backspace as u8, &chars);
+        self.clear();
+        self.last_word = None;
+        self.feedback(Feedback::Corrected);
+        // Like Space, the key brings no text of its own beyond the edit
+        self.track_caret(keys::SPACE, false, false, &mut result);
+        result
+    }
+
+    /// Drop the correction on offer, telling observers
+    fn dismiss_suggestion(&mut self) {
+        if self.suggestion.take().is_some() {
+            self.observers.notify(Notification::SuggestionCleared);
+        }
+    }
+
+    /// `on_key_ext` with the host's monotonic clock, in milliseconds
+    ///
+    /// A word that sat unfinished for `idle_commit_secs` is committed as
+    /// shown before the key is handled, so a key typed much later starts a
+    /// new word instead of editing the stale one. The same key again within
+    /// `REPEAT_MS` is auto-repeat (see `RepeatKey`).
+    pub fn on_key_at(
+        &mut self,
+        time_ms: u64,
+        key: u16,
+        caps: bool,
+        ctrl: bool,
+        shift: bool,
+    ) -> Result {
+        let mut repeated = None;
+        if self.timed_key(time_ms, key) && !ctrl {
+            repeated = self.on_repeat(key);
+        }
+        let result = repeated.unwrap_or_else(|| self.on_key_ext(key, caps, ctrl, shift));
+        self.key_ms = None;
+        result
+    }
+
+    /// Remember a key-down at `time_ms`, first committing the word if the
+    /// last key was `idle_commit_secs` before; whether it's the same key
+    /// again within `REPEAT_MS` (auto-repeat). Keys stamped with the same
+    /// time came in one batch, not from a held key.
+    fn timed_key(&mut self, time_ms: u64, key: u16) -> bool {
+        self.host_epoch
+            .get_or_insert_with(|| (time_ms, Instant::now()));
+        self.key_ms = Some(time_ms);
+        let last = self.last_key_ms.replace(time_ms);
+        let gap = last.map(|last| time_ms.saturating_sub(last));
+        let repeat = self.last_timed_key.replace(key) == Some(key)
+            && gap.is_some_and(|gap| (1..REPEAT_MS).contains(&gap));
+        let idle = gap.unwrap_or(0);
+        if self.idle_commit_secs != 0
+            && !self.buf.is_empty()
+            && idle >= self.idle_commit_secs as u64 * 1000
+        {
+            self.commit_word();
+        }
+        repeat
+    }
+
+    /// The engine's clock: the host time of the key being handled
+    /// (`on_key_at`, `KeyEvent::time_ms`) on `Instant`'s clock, so a
+    /// replayed session spaces words as the original did; `Instant::now()`
+    /// for a key without one and between keys
+    fn now(&self) -> Instant {
+        match (self.host_epoch, self.key_ms) {
+            (Some((epoch_ms, epoch)), Some(ms)) => {
+                epoch + std::time::Duration::from_millis(ms.saturating_sub(epoch_ms))
+            }
+            _ => Instant::now(),
+        }
+    }
+
+    /// End the word as shown: into history, no restore or correction
+    /// (there is no key to carry an edit)
+    fn commit_word(&mut self) {
+        if !self.buf.is_empty() {
+            let screen = self
+                .pending
+                .unwrap_or_else(|| Pending::new(&self.buf.shown()));
+            self.history.record(screen.as_slice(), self.now());
+            self.feedback(Feedback::WordCommitted);
+        }
+        self.clear();
+    }
+
+    /// A mouse event from the host, handled per `set_mouse_actions`
+    ///
+    /// Anything but `Keep` also forgets the caret, which a click may have
+    /// moved.
+    pub fn on_mouse(&mut self, event: MouseEvent) {
+        let action = match event {
+            MouseEvent::Click => self.mouse_click,
+            MouseEvent::Scroll => self.mouse_scroll,
+        };
+        match action {
+            MouseAction::Keep => return,
+            MouseAction::Commit => self.commit_word(),
+            MouseAction::Clear => self.clear(),
+        }
+        self.dismiss_suggestion();
+        self.set_caret(None);
+    }
+
+    /// Move the known caret past this key and its edit
+    fn track_caret(&mut self, key: u16, ctrl: bool, shift: bool, result: &mut Result) {
+        self.edit = None;
+        let Some(mut caret) = self.caret else {
+            return;
+        };
+        if result.action == Action::Send as u8 {
+            let Some(start) = caret.checked_sub(result.backspace as usize) else {
+                self.caret = None;
+                return;
+            };
+            if result.backspace > 0 {
+                self.edit = Some(start..caret);
+                if self.output.delivery == Delivery::Select {
+                    result.action = Action::Select as u8;
+                }
+            }
+            // Up to the `caret_back` last characters
+            let count = result.count as usize;
+            caret = start
+                + result.chars[..count - (result.caret_back as usize).min(count)]
+                    .iter()
+                    .filter_map(|&c| char::from_u32(c))
+                    .map(char::len_utf16)
+                    .sum::<usize>();
+            // Space is part of the output (and Enter may be); other break
+            // keys still go through
+            if key == keys::SPACE || enter_in_output(key, result) || !ends_word(key, shift) {
+                self.caret = Some(caret);
+                return;
+            }
+        }
+        self.caret = output::caret_after_key(caret, key, ctrl);
+    }
+
+    /// `on_key_ext` before caret tracking
+    fn key_result(&mut self, key: u16, caps: bool, ctrl: bool, shift: bool) -> Result {
+        if self.overflowed {
+            if !keys::is_break(key) && !ctrl {
+                return Result::none();
+            }
+            self.overflowed = false;
+        }
+        let result = self.compose_key(key, caps, ctrl, shift);
+        if self.buf.len() > self.max_word_len as usize {
+            self.overflow_word();
+        }
+        self.follow_word();
+        result
+    }
+
+    /// Follow the word one more key through the trigger and typo tries
+    /// (see `trie`), the typo trie only while a pack is on, and tell
+    /// observers if its spell status changed
+    fn follow_word(&mut self) {
+        let letters = || self.buf.letters().map(|c| c.to_ascii_lowercase());
+        let changed = self.spell.follow(letters(), self.bigram_threshold);
+        if self.corrections.packs != CorrectionPacks::NONE {
+            self.typo_cursor.follow(correction::typos(), letters());
+        }
+        #[cfg(feature = "macros")]
+        if !self.shortcuts.is_empty() {
+            self.trigger_cursor
+                .follow(self.shortcuts.trie(), self.buf.letters());
+        }
+        if let Some(status) = changed {
+            self.notify_spell(status);
+        }
+    }
+
+    /// Word got longer than `max_word_len`: apply the overflow policy
+    fn overflow_word(&mut self) {
+        match self.overflow {
+            OverflowPolicy::PassThrough => {
+                self.clear();
+                self.overflowed = true;
+            }
+            // Held-back edits can't be trimmed to match: give the word up
+            OverflowPolicy::KeepTail if self.pending.is_some() => self.clear(),
+            OverflowPolicy::KeepTail => {
+                let extra = self.buf.len() - self.max_word_len as usize;
+                self.buf.drain_front(extra);
+            }
+        }
+    }
+
+    /// `key_result` before the word length limit
+    fn compose_key(&mut self, key: u16, caps: bool, ctrl: bool, shift: bool) -> Result {
+        #[cfg(feature = "macros")]
+        if let Some(result) = self.money_key(key, caps, ctrl, shift) {
+            return result;
+        }
+        #[cfg(feature = "macros")]
+        if let Some(result) = self.macro_key(key, caps, ctrl, shift) {
+            return result;
+        }
+        if self.pending.is_some() || self.output.strategy == Strategy::AtBoundary {
+            return self.on_key_deferred(key, caps, ctrl, shift);
+        }
+        if self.buf.is_empty() {
+            return self.handle_key(key, caps, ctrl, shift);
+        }
+        let before = self.buf.shown();
+        let result = skip_unchanged(self.handle_key(key, caps, ctrl, shift), &before);
+
+        // Too much to delete for this app: type the rest of the word raw and
+        // fix it up at the boundary
+        let max = self.output.max_backspace;
+        if max > 0 && result.backspace > max && !self.buf.is_empty() {
+            let mut screen = Pending::new(&before);
+            if raw_char(key, caps).is_some_and(|c| screen.push(c)) {
+                self.pending = Some(screen);
+                return Result::none();
+            }
+        }
+        result
+    }
+
+    /// `on_key_ext` while the word's edits are held back (see `output`)
+    ///
+    /// Mid-word keys go through raw and are only recorded; the boundary key
+    /// replaces what's on screen with the word as it would have been typed.
+    fn on_key_deferred(&mut self, key: u16, caps: bool, ctrl: bool, shift: bool) -> Result {
+        if keys::is_passthrough(key) {
+            return Result::none();
+        }
+        let mut screen = self
+            .pending
+            .take()
+            .unwrap_or_else(|| Pending::new(&self.buf.shown()));
+        let boundary = self.enabled && !ctrl && ends_word(key, shift);
+        let before = self.buf.shown();
+        let r = self.handle_key(key, caps, ctrl, shift);
+
+        if boundary {
+            return fix_up_word(screen.as_slice(), &before, &r, key == keys::SPACE);
+        }
+        // Backspace removes a raw character on screen but a composed one in
+        // the buffer: they no longer match, give the word up
+        if key == keys::DELETE || self.buf.is_empty() {
+            self.clear();
+            return Result::none();
         }
-        matches!(
-            key,
-            keys::N2              // @ = Shift+2
-                | keys::N3        // # = Shift+3
-                | keys::SEMICOLON // : = Shift+;
-        )
+        match raw_char(key, caps) {
+            Some(c) if screen.push(c) => self.pending = Some(screen),
+            _ => self.clear(),
+        }
+        Result::none()
     }
 
-    /// Handle key event with extended parameters
-    ///
-    /// # Arguments
-    /// * `key` - macOS virtual keycode
-    /// * `caps` - true if Caps Lock is active (for uppercase letters)
-    /// * `ctrl` - true if Cmd/Ctrl/Alt is pressed (bypasses IME)
-    /// * `shift` - true if Shift key is pressed (for symbols like @, #, $)
-    pub fn on_key_ext(&mut self, key: u16, caps: bool, ctrl: bool, shift: bool) -> Result {
+    fn handle_key(&mut self, key: u16, caps: bool, ctrl: bool, shift: bool) -> Result {
+        // Modifiers / function keys alone: leave the word being typed intact
+        if keys::is_passthrough(key) {
+            return Result::none();
+        }
+
+        // AltGr chords arrive as Ctrl+Alt and break the word here
         if !self.enabled || ctrl {
             self.clear();
             return Result::none();
@@ -199,53 +2439,196 @@ impl Engine {
             return Result::none();
         }
 
+        // Telex quick vowels: [ → ơ, ] → ư (Shift+[ is '{', not a vowel)
+        if self.bracket_shortcuts
+            && self.method == 0
+            && !shift
+            && (key == keys::LBRACKET || key == keys::RBRACKET)
+        {
+            return self.handle_bracket_vowel(key, caps);
+        }
+
         // Check for word boundary shortcuts ONLY on SPACE
         // Also auto-restore invalid Vietnamese to raw English
         if key == keys::SPACE {
             // First check for shortcut
-            let shortcut_result = self.try_word_boundary_shortcut();
+            #[cfg(feature = "macros")]
+            {
+                let shortcut_result = self.try_word_boundary_shortcut(Some(' '));
+                if shortcut_result.action != 0 {
+                    self.clear();
+                    return shortcut_result;
+                }
+            }
+
+            // Restore, correct or keep the word (`finish_word`)
+            let result = self.finish_word(Some(' '), Some(' '), true);
+            self.clear();
+            return result;
+        }
+
+        // Tab expands a shortcut in place, when turned on; otherwise it ends
+        // the word like other break keys
+        #[cfg(feature = "macros")]
+        if key == keys::TAB && !shift && self.tab_shortcuts {
+            let shortcut_result = self.try_word_boundary_shortcut(None);
             if shortcut_result.action != 0 {
                 self.clear();
                 return shortcut_result;
             }
-
-            // Auto-restore: if buffer has transforms but is invalid Vietnamese,
-            // restore to raw English (like ESC but triggered by space)
-            let restore_result = self.try_auto_restore_on_space();
-            self.clear();
-            return restore_result;
         }
 
         // ESC key: restore to raw ASCII (undo all Vietnamese transforms)
         if key == keys::ESC {
             let result = self.restore_to_raw();
+            if result.action != 0 {
+                self.feedback(Feedback::Restored);
+            }
             self.clear();
             return result;
         }
 
+        // Underscore makes the token an identifier ("snake_case"): the part
+        // before it goes back to its keystrokes, the rest is typed raw
+        if key == keys::MINUS && shift {
+            let result = self.restore_identifier(None);
+            self.clear();
+            self.raw_mode = true;
+            return result;
+        }
+
         // Other break keys (punctuation, arrows, etc.)
-        // Also trigger auto-restore for invalid Vietnamese before clearing
-        if keys::is_break(key) {
-            let restore_result = self.try_auto_restore_on_break();
+        // Also trigger auto-restore for invalid Vietnamese before clearing;
+        // a word ending in punctuation ("nàm,", "nàm)") is corrected too
+        if ends_word(key, shift) {
+            let correct = keys::is_punctuation(key) || keys::is_number(key);
+            let result = self.finish_word(None, None, correct);
             self.clear();
-            return restore_result;
+            return result;
         }
 
+        // Backspace removes the last grapheme together with its keystrokes
         if key == keys::DELETE {
             self.buf.pop();
-            self.raw_input.pop();
             self.last_transform = None;
             return Result::none();
         }
 
-        // Record raw keystroke for ESC restore (letters and numbers only)
+        // Record keystroke on the character it produced/modified (for restore)
         if keys::is_letter(key) || keys::is_number(key) {
-            self.raw_input.push((key, caps));
+            let before = self.buf.snapshot();
+            let len = self.buf.len();
+            let result = self.process(key, caps, shift);
+            let k = Keystroke::new(key, caps, self.next_seq());
+            self.buf.record(&before, k);
+            // A capital starting a new part makes the token an identifier
+            // ("myVariableName")
+            if caps && result.action == Action::None as u8 && self.is_camel_hump(len) {
+                return self.start_identifier();
+            }
+            return result;
         }
 
         self.process(key, caps, shift)
     }
 
+    /// The letter just typed, a capital, starts a new part of a camelCase
+    /// name: it was added as is after a lowercase letter (`len` before it)
+    /// and can't continue the syllable ("isV", "forE", but not the "viE"
+    /// of a mixed-case "viEets")
+    fn is_camel_hump(&self, len: usize) -> bool {
+        if self.raw_mode || self.buf.len() != len + 1 {
+            return false;
+        }
+        let plain = self
+            .buf
+            .last()
+            .is_some_and(|c| c.caps && c.tone == 0 && c.mark == 0);
+        let after_lowercase = self
+            .buf
+            .iter()
+            .take(len)
+            .any(|c| !c.caps && keys::is_letter(c.key));
+        plain
+            && after_lowercase
+            && !matches!(
+                validation::validate(&self.buf.keys()),
+                ValidationResult::Valid | ValidationResult::NoVowel
+            )
+    }
+
+    /// A capital keystroke after a lowercase letter, unless it typed a
+    /// Vietnamese capital ("viẾt" from "viEets"): "isEmpty" once the word
+    /// ends, or "maxSize", where S only changed the tone of "mã"
+    fn is_camel_case(&self) -> bool {
+        let mut lowercase = false;
+        for c in self.buf.iter() {
+            let shifted = c.origin().iter().any(|k| k.caps);
+            let transformed = c.tone > 0 || c.mark > 0 || c.stroke;
+            if lowercase && shifted && (!c.caps || !transformed) {
+                return true;
+            }
+            lowercase |= !c.caps && keys::is_letter(c.key);
+        }
+        false
+    }
+
+    /// The word as typed, then `tail`, when an identifier ends
+    fn restore_identifier(&mut self, tail: Option<char>) -> Result {
+        self.last_restore = None;
+        match self.build_raw_chars() {
+            Some(mut raw) => {
+                raw.extend(tail);
+                Result::send(self.buf.len() as u8, &raw)
+            }
+            None => Result::none(),
+        }
+    }
+
+    /// Turn the word back into its keystrokes and type the rest of the
+    /// token raw; the last letter isn't on screen yet
+    fn start_identifier(&mut self) -> Result {
+        let restored = self.build_raw_chars();
+        let backspace = self.buf.len() as u8 - 1;
+        let strokes = self.buf.keystrokes();
+        self.buf.clear();
+        self.last_transform = None;
+        self.raw_mode = true;
+        for k in strokes {
+            let before = self.buf.snapshot();
+            self.buf.push(Char::new(k.key, k.caps));
+            self.buf.record(&before, k);
+        }
+        restored.map_or(Result::none(), |raw| Result::send(backspace, &raw))
+    }
+
+    /// Insert ơ / ư for a bracket key (Telex quick vowels)
+    fn handle_bracket_vowel(&mut self, key: u16, caps: bool) -> Result {
+        let (base, text) = if key == keys::LBRACKET {
+            (keys::O, '[')
+        } else {
+            (keys::U, ']')
+        };
+        let mut c = Char::new(base, caps);
+        c.tone = tone::HORN;
+        c.push_origin(Keystroke {
+            text: Some(text),
+            ..Keystroke::new(key, caps, self.next_seq())
+        });
+        self.buf.push(c);
+        self.last_transform = None;
+
+        let vowel_char = chars::to_char(base, caps, tone::HORN, 0).unwrap();
+        Result::send(0, &[vowel_char])
+    }
+
+    /// Next keystroke sequence number in the current word
+    fn next_seq(&mut self) -> u16 {
+        let seq = self.seq;
+        self.seq = self.seq.wrapping_add(1);
+        seq
+    }
+
     /// Main processing pipeline - pattern-based
     fn process(&mut self, key: u16, caps: bool, shift: bool) -> Result {
         // Raw mode: skip all Vietnamese transforms, just pass through letters
@@ -288,10 +2671,13 @@ impl Engine {
             }
         }
 
-        // 4. Remove modifier
-        if !skip_vni_modifiers && m.remove(key) {
+        // 4. Remove modifier (nothing to remove: the key types itself)
+        if !skip_vni_modifiers && self.is_remove_key(m, key) {
             self.last_transform = None;
-            return self.handle_remove();
+            let result = self.handle_remove();
+            if result.action != Action::None as u8 {
+                return result;
+            }
         }
 
         // 5. In Telex: "w" as vowel "ư" when valid Vietnamese context
@@ -306,22 +2692,126 @@ impl Engine {
         self.handle_normal_letter(key, caps)
     }
 
-    /// Try word boundary shortcuts (triggered by space, punctuation, etc.)
-    fn try_word_boundary_shortcut(&mut self) -> Result {
-        if self.buf.is_empty() {
-            return Result::none();
+    /// A key of a dynamic macro trigger: "=" starts one in place of a
+    /// word, letters and digits go through as typed, and space expands it.
+    /// Any other key, or space when nothing expands, drops the trigger and
+    /// is handled as usual.
+    #[cfg(feature = "macros")]
+    fn macro_key(&mut self, key: u16, caps: bool, ctrl: bool, shift: bool) -> Option<Result> {
+        if !self.enabled || ctrl || keys::is_passthrough(key) {
+            return None;
+        }
+        let Some(trigger) = self.macro_trigger.as_mut() else {
+            let start = key == keys::EQUAL && !shift && self.buf.is_empty();
+            if start && self.pending.is_none() {
+                self.macro_trigger = Some(String::new());
+                return Some(Result::none());
+            }
+            return None;
+        };
+        if key == keys::DELETE {
+            if trigger.pop().is_none() {
+                self.macro_trigger = None;
+            }
+            return Some(Result::none());
+        }
+        let typed = keys::is_letter(key) || (keys::is_number(key) && !shift);
+        if let Some(c) = raw_char(key, caps).filter(|_| typed && trigger.len() < MAX) {
+            trigger.push(c);
+            return Some(Result::none());
+        }
+        let trigger = self.macro_trigger.take()?;
+        if key != keys::SPACE || shift {
+            return None;
+        }
+        let mut text = self.macros.expand(&trigger)?;
+        text.push(' ');
+        let caret_back = shortcut::take_caret_marker(&mut text);
+        let output: Vec<char> = text.chars().collect();
+        if output.len() > shortcut::MAX_REPLACEMENT_LEN + 1 {
+            return None;
+        }
+        self.clear();
+        let backspace = trigger.chars().count() + 1;
+        let mut result = Result::send(backspace as u8, &output);
+        result.caret_back = caret_back as u8;
+        Some(result)
+    }
+
+    /// Follow a word that may be money shorthand ("25k", "1m2"): it starts
+    /// with a digit right after a space or a new line and expands on
+    /// space. The keys themselves are handled as usual.
+    #[cfg(feature = "macros")]
+    fn money_key(&mut self, key: u16, caps: bool, ctrl: bool, shift: bool) -> Option<Result> {
+        if !self.money_shorthand || !self.enabled || ctrl || keys::is_passthrough(key) {
+            return None;
+        }
+        let word_start = std::mem::replace(
+            &mut self.word_start,
+            matches!(key, keys::SPACE | keys::RETURN | keys::ENTER | keys::TAB),
+        );
+        if key == keys::SPACE && !shift {
+            let amount = self.amount.take()?;
+            let mut output: Vec<char> = macros::money(&amount)?.chars().collect();
+            output.push(' ');
+            self.clear();
+            return Some(Result::send(amount.len() as u8, &output));
+        }
+        let c = raw_char(key, caps).filter(|c| c.is_ascii_alphanumeric() && !shift);
+        match (self.amount.as_mut(), c) {
+            (Some(amount), Some(c)) if amount.len() < 8 => amount.push(c),
+            (None, Some(c)) if word_start && c.is_ascii_digit() => {
+                self.amount = Some(c.to_string())
+            }
+            _ => self.amount = None,
+        }
+        None
+    }
+
+    /// Try word boundary shortcuts (triggered by space, or by Tab with
+    /// `tab_shortcuts`); `key_char` follows the replacement
+    #[cfg(feature = "macros")]
+    fn try_word_boundary_shortcut(&mut self, key_char: Option<char>) -> Result {
+        // The word was followed key by key: where it ended says whether
+        // it's a trigger (unless the table changed since the last key)
+        let trie = self.shortcuts.trie();
+        if !self.trigger_cursor.follows(trie) {
+            self.trigger_cursor.follow(trie, self.buf.letters());
         }
+        let Some(trigger) = self
+            .trigger_cursor
+            .node()
+            .and_then(|n| trie.values(n).first())
+        else {
+            return Result::none();
+        };
 
-        let buffer_str = self.buf.to_string_preserve_case();
+        let buffer_str = trigger.clone();
         let input_method = self.current_input_method();
 
-        // Check for word boundary shortcut match
-        if let Some(m) =
+        // Check for word boundary shortcut match, placeholders filled in
+        let (date, time) = self.macros.now();
+        let mut counted = false;
+        let found =
             self.shortcuts
-                .try_match_for_method(&buffer_str, Some(' '), true, input_method)
-        {
+                .try_match_with(&buffer_str, key_char, true, input_method, |sc| {
+                    let (text, uses_counter) = macros::fill_placeholders(
+                        &sc.replacement,
+                        date,
+                        time,
+                        sc.counter.saturating_add(1),
+                    );
+                    counted = uses_counter;
+                    text
+                });
+        if let Some(m) = found {
+            if counted {
+                self.shortcuts.next_count(&buffer_str);
+            }
             let output: Vec<char> = m.output.chars().collect();
-            return Result::send(m.backspace_count as u8, &output);
+            let mut result = Result::send(m.backspace_count as u8, &output);
+            result.caret_back = m.caret_back.min(output.len()) as u8;
+            return result;
         }
 
         Result::none()
@@ -362,7 +2852,7 @@ impl Engine {
         }
 
         // Validate: is this valid Vietnamese?
-        let buffer_keys: Vec<u16> = self.buf.iter().map(|c| c.key).collect();
+        let buffer_keys = self.buf.keys();
         if is_valid(&buffer_keys) {
             self.last_transform = Some(Transform::WAsVowel);
 
@@ -398,7 +2888,7 @@ impl Engine {
             // Validate buffer before applying stroke
             // Only validate if buffer has vowels (complete syllable)
             // Allow stroke on initial consonant before vowel is typed (e.g., "dd" → "đ" then "đi")
-            let buffer_keys: Vec<u16> = self.buf.iter().map(|c| c.key).collect();
+            let buffer_keys = self.buf.keys();
             let has_vowel = buffer_keys.iter().any(|&k| keys::is_vowel(k));
             if has_vowel && !is_valid(&buffer_keys) {
                 return None;
@@ -428,15 +2918,23 @@ impl Engine {
             return None;
         }
 
-        // Check revert first (same key pressed twice)
-        if let Some(Transform::Tone(last_key, _)) = self.last_transform {
-            if last_key == key {
+        // Same key again: undo, then stay literal (see `data::vowel`)
+        match self.last_transform {
+            Some(Transform::Tone(last_key, _)) if last_key == key => {
                 return Some(self.revert_tone(key, caps));
             }
+            Some(Transform::ToneReverted(last_key)) if last_key == key => {
+                if !keys::is_letter(key) {
+                    return None;
+                }
+                self.buf.push(Char::new(key, caps));
+                return Some(Result::none());
+            }
+            _ => {}
         }
 
         // Validate buffer
-        let buffer_keys: Vec<u16> = self.buf.iter().map(|c| c.key).collect();
+        let buffer_keys = self.buf.keys();
         if !is_valid(&buffer_keys) {
             return None;
         }
@@ -614,7 +3112,7 @@ impl Engine {
         let has_horn_transforms = self.buf.iter().any(|c| c.tone == tone::HORN);
 
         // Validate buffer (skip if has horn transforms - already intentional Vietnamese)
-        let buffer_keys: Vec<u16> = self.buf.iter().map(|c| c.key).collect();
+        let buffer_keys = self.buf.keys();
         if !has_horn_transforms && !is_valid(&buffer_keys) {
             return None;
         }
@@ -634,6 +3132,11 @@ impl Engine {
             return None;
         }
 
+        // Stop finals take only sắc or nặng: "hopf" stays literal
+        if !allows_mark(&buffer_keys, mark_val) {
+            return None;
+        }
+
         let vowels = self.collect_vowels();
         if vowels.is_empty() {
             return None;
@@ -644,7 +3147,7 @@ impl Engine {
         let has_final = self.has_final_consonant(last_vowel_pos);
         let has_qu = self.has_qu_initial();
         let has_gi = self.has_gi_initial();
-        let pos = Phonology::find_tone_position(&vowels, has_final, true, has_qu, has_gi);
+        let pos = Phonology::find_tone_position(&vowels, has_final, self.modern, has_qu, has_gi);
 
         if let Some(c) = self.buf.get_mut(pos) {
             c.mark = mark_val;
@@ -655,20 +3158,27 @@ impl Engine {
         None
     }
 
-    /// Check for uo compound in buffer
+    /// Check for uo compound in buffer (the u of "qu" belongs to the initial)
     fn has_uo_compound(&self) -> bool {
+        if !self.uo_compound {
+            return false;
+        }
         let mut prev_key: Option<u16> = None;
+        let mut before_prev: Option<u16> = None;
         for c in self.buf.iter() {
             if keys::is_vowel(c.key) {
                 if let Some(pk) = prev_key {
-                    if (pk == keys::U && c.key == keys::O) || (pk == keys::O && c.key == keys::U) {
+                    let qu = pk == keys::U && before_prev == Some(keys::Q);
+                    if !qu
+                        && ((pk == keys::U && c.key == keys::O)
+                            || (pk == keys::O && c.key == keys::U))
+                    {
                         return true;
                     }
                 }
-                prev_key = Some(c.key);
-            } else {
-                prev_key = None;
             }
+            before_prev = prev_key;
+            prev_key = Some(c.key);
         }
         false
     }
@@ -693,10 +3203,19 @@ impl Engine {
             return vec![];
         }
 
-        let buffer_keys: Vec<u16> = self.buf.iter().map(|c| c.key).collect();
+        let buffer_keys = self.buf.keys();
 
         // Use centralized phonology rules (context inferred from buffer)
-        Phonology::find_horn_positions(&buffer_keys, &vowels)
+        let mut positions = Phonology::find_horn_positions(&buffer_keys, &vowels);
+        if !self.uo_compound {
+            // Only the o of a "uo" pair
+            if let [u, o] = positions[..] {
+                if buffer_keys[u] == keys::U && buffer_keys[o] == keys::O {
+                    positions.remove(0);
+                }
+            }
+        }
+        positions
             .into_iter()
             .filter(|&pos| {
                 self.buf
@@ -728,7 +3247,8 @@ impl Engine {
             let has_final = self.has_final_consonant(last_vowel_pos);
             let has_qu = self.has_qu_initial();
             let has_gi = self.has_gi_initial();
-            let new_pos = Phonology::find_tone_position(&vowels, has_final, true, has_qu, has_gi);
+            let new_pos =
+                Phonology::find_tone_position(&vowels, has_final, self.modern, has_qu, has_gi);
 
             if new_pos != old_pos {
                 if let Some(c) = self.buf.get_mut(old_pos) {
@@ -751,27 +3271,42 @@ impl Engine {
         // Add the reverted key to buffer so validation sees the full sequence
         self.buf.push(Char::new(key, caps));
 
-        // Build output from position (includes new key)
+        // Build output from position (includes new key); other diacritics stay
         let output: Vec<char> = (pos..self.buf.len())
             .filter_map(|i| self.buf.get(i))
-            .filter_map(|c| utils::key_to_char(c.key, c.caps))
+            .filter_map(|c| c.to_char())
             .collect();
 
         Result::send(backspace, &output)
     }
 
     /// Revert tone transformation
+    ///
+    /// Takes the diacritic off the whole nucleus: "ươ" loses both horns.
     fn revert_tone(&mut self, key: u16, caps: bool) -> Result {
-        self.last_transform = None;
+        self.last_transform = Some(Transform::ToneReverted(key));
 
         for pos in self.buf.find_vowels().into_iter().rev() {
-            if let Some(c) = self.buf.get_mut(pos) {
-                if c.tone > tone::NONE {
-                    c.tone = tone::NONE;
-                    return self.revert_and_rebuild(pos, key, caps);
+            let Some(c) = self.buf.get_mut(pos) else {
+                continue;
+            };
+            if c.tone == tone::NONE {
+                continue;
+            }
+            let horn_o = c.key == keys::O && c.tone == tone::HORN;
+            c.tone = tone::NONE;
+            let mut from = pos;
+            if horn_o && pos > 0 {
+                if let Some(u) = self.buf.get_mut(pos - 1) {
+                    if u.key == keys::U && u.tone == tone::HORN {
+                        u.tone = tone::NONE;
+                        from = pos - 1;
+                    }
                 }
             }
+            return self.revert_and_rebuild(from, key, caps);
         }
+        self.last_transform = None;
         Result::none()
     }
 
@@ -805,6 +3340,14 @@ impl Engine {
         Result::none()
     }
 
+    fn is_remove_key(&self, m: &dyn input::Method, key: u16) -> bool {
+        match self.remove_key {
+            RemoveKey::Default => m.remove(key),
+            RemoveKey::Off => false,
+            RemoveKey::Key(c) => keys::from_char(c) == Some(key),
+        }
+    }
+
     /// Handle remove modifier
     fn handle_remove(&mut self) -> Result {
         for pos in self.buf.find_vowels().into_iter().rev() {
@@ -826,7 +3369,10 @@ impl Engine {
     fn handle_normal_letter(&mut self, key: u16, caps: bool) -> Result {
         // Special case: "o" after "w→ư" should form "ươ" compound
         // This allows typing "ddwocj" → "được" instead of "đưọc"
-        if key == keys::O && matches!(self.last_transform, Some(Transform::WAsVowel)) {
+        if key == keys::O
+            && self.uo_compound
+            && matches!(self.last_transform, Some(Transform::WAsVowel))
+        {
             // Add O with horn to form ươ compound
             let mut c = Char::new(key, caps);
             c.tone = tone::HORN;
@@ -850,11 +3396,21 @@ impl Engine {
             // w-as-vowel: first horn is U at position 0 (was standalone 'w')
             // w-as-tone: horns are on vowels after initial consonant
             if self.has_w_as_vowel_transform() {
-                let buffer_keys: Vec<u16> = self.buf.iter().map(|c| c.key).collect();
+                let buffer_keys = self.buf.keys();
                 if is_foreign_word_pattern(&buffer_keys, key) {
                     return self.revert_w_as_vowel_transforms();
                 }
             }
+
+            // Classic style: a final consonant moves the mark (hòa → hoàn)
+            if !self.modern && !keys::is_vowel(key) && is_valid(&self.buf.keys()) {
+                if let Some(from) = self.reposition_mark_if_needed() {
+                    let mut result = self.rebuild_from(from);
+                    // The new letter isn't on screen yet
+                    result.backspace -= 1;
+                    return result;
+                }
+            }
         } else {
             self.buf.clear();
         }
@@ -877,7 +3433,8 @@ impl Engine {
     }
 
     /// Revert w-as-vowel transforms and rebuild output
-    /// Used when foreign word pattern is detected after w→ư transformation
+    /// Used when foreign word pattern is detected after w→ư transformation.
+    /// The letter that triggered it is already in the buffer but not on screen.
     fn revert_w_as_vowel_transforms(&mut self) -> Result {
         // Only revert if first char is U with horn (w-as-vowel pattern)
         if !self.has_w_as_vowel_transform() {
@@ -910,7 +3467,9 @@ impl Engine {
             }
         }
 
-        self.rebuild_from(first_pos)
+        let mut result = self.rebuild_from(first_pos);
+        result.backspace -= 1;
+        result
     }
 
     /// Collect vowels from buffer
@@ -941,12 +3500,7 @@ impl Engine {
         for i in from..self.buf.len() {
             if let Some(c) = self.buf.get(i) {
                 backspace += 1;
-
-                if c.key == keys::D && c.stroke {
-                    output.push(chars::get_d(c.caps));
-                } else if let Some(ch) = chars::to_char(c.key, c.caps, c.tone, c.mark) {
-                    output.push(ch);
-                } else if let Some(ch) = utils::key_to_char(c.key, c.caps) {
+                if let Some(ch) = c.to_char() {
                     output.push(ch);
                 }
             }
@@ -959,262 +3513,415 @@ impl Engine {
         }
     }
 
-    /// Clear buffer and raw input history
+    /// Clear buffer and keystroke history
     pub fn clear(&mut self) {
         self.buf.clear();
-        self.raw_input.clear();
+        if let Some(status) = self.spell.reset() {
+            self.notify_spell(status);
+        }
+        self.typo_cursor.reset();
+        #[cfg(feature = "macros")]
+        self.trigger_cursor.reset();
+        self.pending = None;
+        self.nbsp_after = false;
+        self.last_word = None;
+        self.seq = 0;
         self.last_transform = None;
         self.raw_mode = false;
+        self.overflowed = false;
+        #[cfg(feature = "macros")]
+        {
+            self.macro_trigger = None;
+        }
+    }
+
+    /// Auto-restore is compiled out of slim builds
+    #[cfg(not(feature = "autocorrect"))]
+    fn should_auto_restore(&self) -> Option<(Vec<char>, RestoreReason)> {
+        None
     }
 
-    /// Check if buffer has transforms and is invalid Vietnamese
-    /// Returns the raw chars if restore is needed, None otherwise
-    fn should_auto_restore(&self) -> Option<Vec<char>> {
-        if self.raw_input.is_empty() || self.buf.is_empty() {
+    /// Run the restore checks in `restore_order` until one fires
+    /// Returns the raw chars and why if restore is needed, None otherwise
+    #[cfg(feature = "autocorrect")]
+    fn should_auto_restore(&self) -> Option<(Vec<char>, RestoreReason)> {
+        if self.auto_correct == AutoCorrectMode::Off || self.buf.is_empty() || self.raw_mode {
             return None;
         }
 
-        // Check if any transforms were applied
+        // Only dictionary English restores a word nothing was applied to
+        // ("boss" → "bos" leaves no transform behind)
         let has_transforms = self
             .buf
             .iter()
             .any(|c| c.tone > 0 || c.mark > 0 || c.stroke);
-        if !has_transforms {
-            return None;
-        }
-
-        // Check 1: If buffer_keys is structurally invalid Vietnamese → RESTORE
-        let buffer_keys: Vec<u16> = self.buf.iter().map(|c| c.key).collect();
-        if !is_valid(&buffer_keys) {
-            return self.build_raw_chars();
-        }
+        let buffer_keys = self.buf.keys();
+        let strokes = self.buf.keystrokes();
+        let raw: Vec<u16> = strokes.iter().map(|k| k.key).collect();
+        let composed = self.buf.to_display_string();
+        let typed = |range: std::ops::Range<usize>| -> String {
+            strokes[range].iter().filter_map(|k| k.to_char()).collect()
+        };
+        let shown = |range: std::ops::Range<usize>| -> String {
+            composed
+                .chars()
+                .skip(range.start)
+                .take(range.len())
+                .collect()
+        };
 
-        // Check 2: English patterns in raw_input
-        // Even if buffer is valid, certain patterns suggest English
-        if self.has_english_modifier_pattern() {
-            return self.build_raw_chars();
+        for &check in self.restore_order.checks() {
+            if self.restore_disabled.contains(check) {
+                continue;
+            }
+            // (problem, characters at fault) when the check fires
+            let hit = match check {
+                RestoreCheck::Dictionary => {
+                    if self.auto_correct != AutoCorrectMode::All {
+                        continue;
+                    }
+                    self.is_dictionary_english()
+                        .then(|| (None, typed(0..strokes.len())))
+                }
+                // Runs without transforms too: "mising" from "missing"
+                RestoreCheck::EnglishSuffix => restore::english_suffix(&raw)
+                    .filter(|_| !is_valid(&buffer_keys))
+                    .map(|r| (None, typed(r))),
+                // Tie-breaker for what is left: no syllable, and nothing
+                // else explains it ("pased" from "passed"). A valid one
+                // ("mể") stays Vietnamese.
+                RestoreCheck::Bigram => restore::bigram(&raw, self.bigram_threshold)
+                    .filter(|_| !is_valid(&buffer_keys))
+                    .map(|r| (None, typed(r))),
+                _ if !has_transforms => continue,
+                // Not a Vietnamese syllable ("tẽt")
+                RestoreCheck::Invalid => {
+                    let result = validate(&buffer_keys);
+                    (!result.is_valid()).then(|| {
+                        let span = restore::problem_span(&buffer_keys, &result);
+                        (Some(result.into()), shown(span))
+                    })
+                }
+                // A mark the final can't take ("hofp" → "hòp")
+                RestoreCheck::MarkFinal => self
+                    .buf
+                    .iter()
+                    .any(|c| !allows_mark(&buffer_keys, c.mark))
+                    .then(|| {
+                        let span = syllable::parse(&buffer_keys).final_c.iter();
+                        (Some(Problem::StopFinalTone), shown(span))
+                    }),
+                // English patterns in raw keystrokes, even on a valid syllable
+                RestoreCheck::WInitial => restore::w_initial(&raw).map(|r| (None, typed(r))),
+                RestoreCheck::ModifierConsonant => {
+                    restore::modifier_consonant(&raw).map(|r| (None, typed(r)))
+                }
+                RestoreCheck::ModifierVowelPair => {
+                    restore::modifier_vowel_pair(&raw).map(|r| (None, typed(r)))
+                }
+                RestoreCheck::ModifierBetweenVowels => {
+                    restore::modifier_between_vowels(&raw).map(|r| (None, typed(r)))
+                }
+                RestoreCheck::WFinal => restore::w_final(&raw).map(|r| (None, typed(r))),
+            };
+            self.restore_stats.record(check, hit.is_some());
+            if let Some((problem, matched)) = hit {
+                let raw_chars = self.build_raw_chars()?;
+                let reason = RestoreReason {
+                    check,
+                    problem,
+                    matched,
+                    composed,
+                    restored: raw_chars.iter().collect(),
+                };
+                return Some((raw_chars, reason));
+            }
         }
 
-        // Buffer is valid Vietnamese AND no English patterns → KEEP
+        // Valid Vietnamese and no English pattern → KEEP
         None
     }
 
-    /// Build raw chars from raw_input for restore
+    /// Build raw chars from keystroke provenance for restore
+    ///
+    /// Returns None when restoring would not change the screen
+    /// (e.g. text composed by the OS layout).
     fn build_raw_chars(&self) -> Option<Vec<char>> {
         let raw_chars: Vec<char> = self
-            .raw_input
+            .buf
+            .keystrokes()
             .iter()
-            .filter_map(|&(key, caps)| utils::key_to_char(key, caps))
+            .filter_map(|k| k.to_char())
             .collect();
 
-        if raw_chars.is_empty() {
+        if raw_chars.is_empty()
+            || raw_chars
+                .iter()
+                .copied()
+                .eq(self.buf.to_display_string().chars())
+        {
             None
         } else {
             Some(raw_chars)
         }
     }
 
-    /// Check for English patterns in raw_input that suggest non-Vietnamese
+    /// Raw keystrokes spell a word from the embedded English lexicon
+    #[cfg(feature = "autocorrect")]
+    fn is_dictionary_english(&self) -> bool {
+        let raw: String = self
+            .buf
+            .keystrokes()
+            .iter()
+            .filter_map(|k| k.to_char())
+            .map(|c| c.to_ascii_lowercase())
+            .collect();
+        crate::data::lexicon::ENGLISH.contains(&raw)
+    }
+
+    /// Settle the word at a boundary, then `tail`
     ///
-    /// Patterns detected:
-    /// 1. Modifier (s/f/r/x/j in Telex) followed by consonant: "text" (x before t)
-    /// 2. Modifier at end of long word (>2 chars): "their" (r at end)
-    /// 3. Modifier after first vowel then another vowel: "use" (s between u and e)
-    fn has_english_modifier_pattern(&self) -> bool {
-        // Check for W at start - W is not a valid Vietnamese initial consonant
-        // Words like "wow", "window", "water" start with W
-        // Exception: standalone "w" → "ư" is valid Vietnamese
-        if self.raw_input.len() >= 2 {
-            let (first, _) = self.raw_input[0];
-            if first == keys::W {
-                // Check if there's another W later (non-adjacent) → English pattern like "wow"
-                let has_later_w = self.raw_input[2..].iter().any(|(k, _)| *k == keys::W);
-                if has_later_w {
-                    return true;
-                }
-                // Check for consonant after W → English like "window"
-                let has_consonant = self.raw_input[1..]
-                    .iter()
-                    .any(|(k, _)| keys::is_consonant(*k) && *k != keys::W);
-                if has_consonant {
-                    return true;
-                }
-            }
+    /// Rules, restore hints, the restore checks and corrections each give
+    /// a `Decision`; the most confident at or above `min_confidence` wins
+    /// (see `decision`). `after` is the character the host puts after the
+    /// word, for offering a correction instead (`CorrectionStyle::Suggest`;
+    /// nothing is offered when it isn't known: a punctuation key's
+    /// character depends on the layout). `correct` is false where
+    /// corrections don't run.
+    ///
+    /// Examples: "tẽt" + space → "text " (restored), "ễpct" + comma →
+    /// "expect" (the app adds the comma), "nàm" + space → "làm " with the
+    /// Southern pack on. Observers hear what became of the word
+    /// (`Feedback`).
+    fn finish_word(&mut self, tail: Option<char>, after: Option<char>, correct: bool) -> Result {
+        self.last_restore = None;
+        if self.buf.is_empty() {
+            return Result::none();
         }
+        let (result, feedback) = self.settle_word(tail, after, correct);
+        // Nothing to restore after all ("snake_case" with no transforms)
+        self.feedback(if result.action == 0 {
+            Feedback::WordCommitted
+        } else {
+            feedback
+        });
+        result
+    }
 
-        // Telex modifiers that add tone marks
-        let tone_modifiers = [keys::S, keys::F, keys::R, keys::X, keys::J];
-
-        // Find positions of modifiers in raw_input
-        for i in 0..self.raw_input.len() {
-            let (key, _) = self.raw_input[i];
-
-            if !tone_modifiers.contains(&key) {
-                continue;
-            }
-
-            // Found a modifier at position i
+    /// `finish_word` for a word under way; the edit and what it did
+    fn settle_word(
+        &mut self,
+        tail: Option<char>,
+        after: Option<char>,
+        correct: bool,
+    ) -> (Result, Feedback) {
+        let kept = (Result::none(), Feedback::WordCommitted);
+        let threshold = self.min_confidence as f32 / 100.0;
+        let mut decisions = Vec::new();
+        #[cfg(feature = "rules")]
+        decisions.extend(self.rule_decision());
+        if decisions.is_empty() && self.is_camel_case() {
+            return (self.restore_identifier(tail), Feedback::Restored);
+        }
+        #[cfg(feature = "autocorrect")]
+        decisions.extend(self.hint_decision());
+        let restore = self.should_auto_restore();
+        if let Some((_, reason)) = &restore {
+            decisions.push(Decision::new(
+                DecisionAction::Restore,
+                check_confidence(reason.check),
+                Source::Check(reason.check),
+            ));
+        }
+        // Corrections are asked last, and only if they could still win
+        let beaten = CORRECTION_CONFIDENCE < threshold
+            || decisions
+                .iter()
+                .any(|d| d.confidence >= CORRECTION_CONFIDENCE);
+        if correct && !beaten {
+            decisions.extend(self.correction_decision());
+        }
+        let decided = decision::decide(decisions, threshold);
+        if !self.shadows.is_empty() {
+            self.run_shadows(decided.as_ref().map(|d| &d.action), threshold);
+        }
+        let Some(decision) = decided else {
+            return kept;
+        };
 
-            // Pattern 1: Modifier followed by consonant → English
-            // Example: "text" has X followed by T, "expect" has X followed by P
-            // Counter-example: "muwowjt" has J followed by T (Vietnamese - multiple vowels)
-            if i + 1 < self.raw_input.len() {
-                let (next_key, _) = self.raw_input[i + 1];
-                if keys::is_consonant(next_key) {
-                    // Case 1a: More letters after the consonant → definitely English
-                    // Example: "expect" = E+X+P+E+C+T (X followed by P, then more)
-                    if i + 2 < self.raw_input.len() {
-                        return true;
-                    }
-                    // Case 1b: Final consonant but only 1 vowel before modifier → likely English
-                    // Example: "text" = T+E+X+T (only 1 vowel E before X)
-                    // Counter: "muwowjt" = M+U+W+O+W+J+T (2 vowels with W modifiers)
-                    let vowels_before: usize = (0..i)
-                        .filter(|&j| keys::is_vowel(self.raw_input[j].0))
-                        .count();
-                    if vowels_before == 1 {
-                        return true;
-                    }
+        let word = self.buf.to_display_string();
+        let feedback = match decision.action {
+            DecisionAction::Restore => Feedback::Restored,
+            _ => Feedback::Corrected,
+        };
+        let mut chars: Vec<char> = match decision.action {
+            DecisionAction::Keep => return kept,
+            DecisionAction::Restore => match restore {
+                Some((raw_chars, reason)) if decision.source == Source::Check(reason.check) => {
+                    self.last_restore = Some(reason);
+                    raw_chars
                 }
-            }
-
-            // Pattern 2: Modifier at end AND suspicious vowel pair before → English
-            // Example: "their" → t-h-e-i-r, "ei" before r → suspicious English pattern
-            // Example: "pair" → p-a-i-r, "ai" before r (only 2 vowels) → suspicious English pattern
-            // Counter-example: "booj" → b-o-o-j, "oo" (same vowel) → Telex doubling, Vietnamese
-            // Counter-example: "chiuj" → c-h-i-u-j, "iu" → valid Vietnamese diphthong
-            // Counter-example: "hoaij" → h-o-a-i-j, "oai" (3 vowels) → valid Vietnamese
-            if i + 1 == self.raw_input.len() && i >= 2 {
-                let (v1, _) = self.raw_input[i - 2];
-                let (v2, _) = self.raw_input[i - 1];
-                // Check for suspicious English vowel patterns before modifier
-                // Same vowel doubling (oo, aa, ee) is Telex pattern, not suspicious
-                if keys::is_vowel(v1) && keys::is_vowel(v2) && v1 != v2 {
-                    // Count total vowels before modifier
-                    let total_vowels: usize = (0..i)
-                        .filter(|&j| keys::is_vowel(self.raw_input[j].0))
-                        .count();
-
-                    // EI before modifier is very English (their, weird, vein)
-                    if v1 == keys::E && v2 == keys::I {
-                        return true;
+                _ => {
+                    let Some(raw_chars) = self.build_raw_chars() else {
+                        return kept;
+                    };
+                    // Hints restore English words, as far as anyone asks
+                    if decision.source == Source::Hint {
+                        self.last_restore = Some(RestoreReason {
+                            check: RestoreCheck::Dictionary,
+                            problem: None,
+                            matched: self.typed_keys(),
+                            composed: word,
+                            restored: raw_chars.iter().collect(),
+                        });
                     }
-                    // AI before modifier is English ONLY if:
-                    // 1. Exactly 2 vowels (not "oai" in "hoại")
-                    // 2. AND initial is P alone (not PH) - P is rare in native Vietnamese
-                    // This catches "pair" but not "mái", "cái", "xài" (common Vietnamese)
-                    if v1 == keys::A && v2 == keys::I && total_vowels == 2 {
-                        // Check if initial is just P (rare in native Vietnamese)
-                        if !self.raw_input.is_empty() && self.raw_input[0].0 == keys::P {
-                            // Make sure it's not PH (PH is common Vietnamese)
-                            let is_ph = self.raw_input.len() >= 2 && self.raw_input[1].0 == keys::H;
-                            if !is_ph {
-                                return true;
-                            }
-                        }
+                    raw_chars
+                }
+            },
+            DecisionAction::Replace(text) => {
+                if text == word {
+                    return kept;
+                }
+                if decision.source == Source::Correction
+                    && self.correction_style == CorrectionStyle::Suggest
+                {
+                    if let Some(after) = after {
+                        let candidates = self.candidates(&word, text);
+                        self.suggestion = Some(Suggestion {
+                            word,
+                            candidates,
+                            selected: 0,
+                            browsing: false,
+                            after,
+                        });
+                        self.observers.notify(Notification::SuggestionOffered);
                     }
+                    return kept;
                 }
+                text.chars().collect()
             }
+        };
+        chars.extend(tail);
+        (Result::send(self.buf.len() as u8, &chars), feedback)
+    }
 
-            // Pattern 3: Modifier immediately after single vowel, then another vowel
-            // AND no initial consonant before the vowel
-            // Example: "use" → U (vowel) + S (modifier) + E (vowel) = starts with vowel → English
-            // Counter-example: "cura" → C + U + R + A = starts with consonant → Vietnamese "của"
-            let vowels_before: usize = (0..i)
-                .filter(|&j| keys::is_vowel(self.raw_input[j].0))
-                .count();
-
-            // If only 1 vowel before modifier AND vowel after AND no initial consonant → English
-            if vowels_before == 1 && i + 1 < self.raw_input.len() {
-                let (next_key, _) = self.raw_input[i + 1];
-                if keys::is_vowel(next_key) {
-                    // Find first vowel position
-                    let first_vowel_pos = (0..i)
-                        .find(|&j| keys::is_vowel(self.raw_input[j].0))
-                        .unwrap_or(0);
-                    // Check if there's a consonant before the first vowel
-                    let has_initial_consonant = first_vowel_pos > 0
-                        && keys::is_consonant(self.raw_input[first_vowel_pos - 1].0);
-                    // Only restore if NO initial consonant (pure vowel-start like "use")
-                    if !has_initial_consonant {
-                        return true;
-                    }
-
-                    // Pattern 4: vowel + modifier + DIFFERENT vowel → English
-                    // EXCEPT for Vietnamese patterns:
-                    // - Same vowel: e+s+e in "these" → "thế" (Vietnamese)
-                    // - u+a (ưa), u+o (ươ) patterns
-                    // Example: "core" = c + o + r + e → o+r+e is NOT Vietnamese pattern
-                    // Example: "cura" = c + u + r + a → u+r+a IS Vietnamese (cửa)
-                    // Example: "these" = th + e + s + e → e+s+e same vowel, Vietnamese "thế"
-                    if has_initial_consonant {
-                        let (prev_vowel, _) = self.raw_input[i - 1];
-                        // Vietnamese exception 1: Same vowel before and after modifier
-                        // "these" → e+s+e, "thaar" → a+a+r, etc.
-                        if prev_vowel == next_key {
-                            continue; // Not English, check next modifier
-                        }
-                        // Vietnamese exception 2: U + modifier + A/O → valid (ưa, ươ patterns)
-                        let is_vietnamese_ua =
-                            prev_vowel == keys::U && (next_key == keys::A || next_key == keys::O);
-                        if !is_vietnamese_ua {
-                            return true;
-                        }
-                    }
-                }
+    /// What to offer for `word`: the correction `fixed`, then similar
+    /// words and plugin suggestions, each once
+    fn candidates(&self, word: &str, fixed: String) -> Vec<String> {
+        let more = std::iter::empty::<String>();
+        #[cfg(feature = "suggestions")]
+        let more = more.chain(
+            crate::suggest::suggest_similar(word, 1)
+                .into_iter()
+                .map(|(w, _)| correction::match_case(word, w)),
+        );
+        #[cfg(feature = "plugins")]
+        let more = more.chain(self.plugin_suggestions(word));
+        let mut candidates = vec![fixed];
+        for w in more {
+            if candidates.len() == correction::MAX_CANDIDATES {
+                break;
+            }
+            if w != word && !candidates.contains(&w) {
+                candidates.push(w);
             }
         }
+        candidates
+    }
 
-        // Pattern 5: W at end after vowel → English (like "raw", "law", "saw")
-        // W as final is not valid Vietnamese, it's an English pattern
-        // Exception: "uw" ending is Vietnamese (tuw → tư)
-        if self.raw_input.len() >= 2 {
-            let (last, _) = self.raw_input[self.raw_input.len() - 1];
-            if last == keys::W {
-                let (second_last, _) = self.raw_input[self.raw_input.len() - 2];
-                // W after vowel (not U) at end is English: raw, law, saw
-                // W after U is Vietnamese: tuw → tư
-                if keys::is_vowel(second_last) && second_last != keys::U {
-                    return true;
-                }
-            }
+    /// Ask every shadow heuristic about the word and log where it differs
+    /// from `active`, what the engine did (`shadow`)
+    fn run_shadows(&mut self, active: Option<&DecisionAction>, threshold: f32) {
+        let word = self.buf.to_display_string();
+        let keys = self.typed_keys();
+        let prev = self.history.recent(self.now()).next();
+        let w = ShadowWord {
+            word: &word,
+            keys: &keys,
+            prev,
+        };
+        let active = active.unwrap_or(&DecisionAction::Keep);
+        let retain = self.history.privacy().retain;
+        for shadow in &self.shadows {
+            let verdict = shadow
+                .decide(&w)
+                .filter(|d| d.confidence >= threshold)
+                .map_or(DecisionAction::Keep, |d| d.action);
+            self.shadow_log
+                .record(shadow.name(), &w, active, &verdict, retain);
         }
+    }
 
-        false
+    /// Keys of the word as typed
+    fn typed_keys(&self) -> String {
+        self.buf
+            .keystrokes()
+            .iter()
+            .filter_map(|k| k.to_char())
+            .collect()
     }
 
-    /// Auto-restore invalid Vietnamese to raw English on space
-    ///
-    /// Called when SPACE is pressed. If buffer has transforms but result is not
-    /// valid Vietnamese, restore to original English + space.
-    /// Example: "tẽt" (from typing "text") → "text " (restored + space)
-    /// Example: "ễpct" (from typing "expect") → "expect " (restored + space)
-    fn try_auto_restore_on_space(&self) -> Result {
-        if let Some(mut raw_chars) = self.should_auto_restore() {
-            // Add space at the end
-            raw_chars.push(' ');
-            // Backspace count = current buffer length (displayed chars)
-            let backspace = self.buf.len() as u8;
-            Result::send(backspace, &raw_chars)
-        } else {
-            Result::none()
+    /// The word rules' verdict on the word, if a rule holds
+    #[cfg(feature = "rules")]
+    fn rule_decision(&self) -> Option<Decision> {
+        if self.rules.is_empty() {
+            return None;
         }
+        let word = self.buf.to_display_string();
+        let keys = self.typed_keys();
+        let w = WordContext {
+            word: &word,
+            keys: &keys,
+            app: self.app.as_deref(),
+            method: Method::from_id(self.method),
+        };
+        let action = match self.rules.evaluate(&w)? {
+            RuleAction::Keep => DecisionAction::Keep,
+            RuleAction::Restore => DecisionAction::Restore,
+            RuleAction::Replace(text) => DecisionAction::Replace(text.clone()),
+        };
+        Some(Decision::new(
+            action,
+            decision::RULE_CONFIDENCE,
+            Source::Rule,
+        ))
     }
 
-    /// Auto-restore invalid Vietnamese to raw English on break key
-    ///
-    /// Called when punctuation/break key is pressed. If buffer has transforms
-    /// but result is not valid Vietnamese, restore to original English.
-    /// Does NOT include the break key (it's passed through by the app).
-    /// Example: "ễpct" + comma → "expect" (comma added by app)
-    fn try_auto_restore_on_break(&self) -> Result {
-        if let Some(raw_chars) = self.should_auto_restore() {
-            // Backspace count = current buffer length (displayed chars)
-            let backspace = self.buf.len() as u8;
-            Result::send(backspace, &raw_chars)
-        } else {
-            Result::none()
+    /// The first restore hint with an opinion
+    #[cfg(feature = "autocorrect")]
+    fn hint_decision(&self) -> Option<Decision> {
+        if self.restore_hints.is_empty()
+            || self.auto_correct == AutoCorrectMode::Off
+            || self.raw_mode
+        {
+            return None;
+        }
+        let keys = self.typed_keys();
+        let word = self.buf.to_display_string();
+        self.restore_hints
+            .iter()
+            .find_map(|h| h.restore(&keys, &word))
+            .map(Decision::from_hint)
+    }
+
+    /// A fix for a misspelled word from the correction chain
+    fn correction_decision(&self) -> Option<Decision> {
+        if self.raw_mode || self.is_camel_case() {
+            return None;
+        }
+        // With only the packs to ask, the word already told whether it's
+        // one of their typos while it was typed
+        if self.corrections.packs_only() && self.typo().is_none() {
+            return None;
         }
+        let word = self.buf.to_display_string();
+        let prev = self.history.recent(self.now()).next();
+        let fixed = self.corrections.correct(&word, prev)?;
+        Some(Decision::new(
+            DecisionAction::Replace(fixed),
+            CORRECTION_CONFIDENCE,
+            Source::Correction,
+        ))
     }
 
     /// Restore buffer to raw ASCII (undo all Vietnamese transforms)
@@ -1222,7 +3929,7 @@ impl Engine {
     /// Called when ESC is pressed. Replaces transformed output with original keystrokes.
     /// Example: "tẽt" (from typing "text" in Telex) → "text"
     fn restore_to_raw(&self) -> Result {
-        if self.raw_input.is_empty() || self.buf.is_empty() {
+        if self.buf.is_empty() {
             return Result::none();
         }
 
@@ -1235,16 +3942,10 @@ impl Engine {
             return Result::none();
         }
 
-        // Build raw ASCII output from raw_input history
-        let raw_chars: Vec<char> = self
-            .raw_input
-            .iter()
-            .filter_map(|&(key, caps)| utils::key_to_char(key, caps))
-            .collect();
-
-        if raw_chars.is_empty() {
+        // Build raw ASCII output from keystroke provenance
+        let Some(raw_chars) = self.build_raw_chars() else {
             return Result::none();
-        }
+        };
 
         // Backspace count = current buffer length (displayed chars)
         let backspace = self.buf.len() as u8;
@@ -1299,6 +4000,10 @@ mod tests {
         ("dd\x1b", "dd"),         // đ → dd (stroke restore)
         ("vieejt\x1b", "vieejt"), // việt → vieejt (all typed keys)
         ("Vieejt\x1b", "Vieejt"), // Việt → Vieejt (preserve case)
+        // Backspace drops a whole grapheme with all its keystrokes
+        ("aas<text\x1b", "text"), // ấ deleted → its a, a, s are gone too
+        ("toans<\x1b", "toas"),   // n deleted, s stays with á
+        ("vieejt<<\x1b", "vi"),   // ệ and t deleted → nothing to restore
     ];
 
     const VNI_ESC_RESTORE: &[(&str, &str)] = &[
@@ -1324,6 +4029,31 @@ mod tests {
         ("vieejt", "việt"), // Normal Vietnamese typing
     ];
 
+    // Identifiers: an interior capital or an underscore keeps the whole
+    // token as typed
+    const IDENTIFIERS: &[(&str, &str)] = &[
+        ("isValid", "isValid"),         // í → is once V arrives
+        ("forEach", "forEach"),         // fỏ → for
+        ("textContent", "textContent"), // tẽt → text
+        ("hasOwnProperty", "hasOwnProperty"),
+        ("setTimeout", "setTimeout"),
+        ("addEventListener", "addEventListener"),
+        // Decided when the token ends
+        ("isEmpty ", "isEmpty "),   // "ie" goes on as a syllable
+        ("userId,", "userId,"),     // úẻ + I
+        ("toString ", "toString "), // S only changes the tone of "tó"
+        ("useState.", "useState."),
+        ("user_id", "user_id"),       // úẻ → user at the underscore
+        ("parse_args", "parse_args"), // args stays raw too
+        ("__dirname", "__dirname"),
+        ("max_retries ", "max_retries "), // no autocorrect on space
+        ("isValid vieejt", "isValid việt"), // the next word is Vietnamese
+    ];
+
+    // Capitals that don't follow a lowercase letter aren't identifiers
+    const NOT_IDENTIFIERS: &[(&str, &str)] =
+        &[("Vieejt", "Việt"), ("VIEEJT", "VIỆT"), ("DDaij", "Đại")];
+
     #[test]
     fn test_telex_basic() {
         telex(TELEX_BASIC);
@@ -1354,6 +4084,12 @@ mod tests {
         raw_mode(RAW_MODE_PREFIX);
     }
 
+    #[test]
+    fn test_identifiers() {
+        raw_mode(IDENTIFIERS);
+        telex(NOT_IDENTIFIERS);
+    }
+
     #[test]
     fn test_raw_mode_normal() {
         // Without prefix, Vietnamese transforms should still apply
@@ -0,0 +1,262 @@
+//! Engine Observers
+//!
+//! Hosts often have several components reading engine settings (menu bar,
+//! preferences window, per-app overrides). Instead of polling, they
+//! subscribe once and get a `Notification` whenever something changes.
+//! The same channel tells them when a correction is on offer, so they can
+//! show or hide it (`CorrectionStyle::Suggest`), when a modifier gesture
+//! asks for something (`Gesture`), and, if asked, how the word being typed
+//! reads, to tint a preview (`SpellStatus`), and when a word is committed,
+//! corrected or restored, to play a sound or a haptic (`Feedback`).
+//!
+//! Observers run synchronously inside the call that changed the setting.
+//! FFI subscribers are dispatched after the engine lock is released
+//! (see `ime_subscribe` in lib.rs), so they may call back into `ime_*`.
+
+use super::spell::SpellStatus;
+use crate::config::{
+    AcceptKey, AutoCorrectMode, CorrectionPacks, CorrectionStyle, GestureAction, Gestures, IyStyle,
+    Method, MouseAction, Nbsp, NbspChord, OverflowPolicy, RemoveKey, RepeatKey, RestoreChecks,
+    RestoreOrder, ToneStyle,
+};
+use std::sync::mpsc;
+
+/// Setting that changed, with its new value
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Setting {
+    Method(Method),
+    Enabled(bool),
+    ToneStyle(ToneStyle),
+    AutoCorrect(AutoCorrectMode),
+    BracketShortcuts(bool),
+    MaxWordLen(u8),
+    Overflow(OverflowPolicy),
+    RemoveKey(RemoveKey),
+    UoCompound(bool),
+    CorrectionPacks(CorrectionPacks),
+    Nbsp(Nbsp),
+    IdleCommitSecs(u8),
+    MouseClick(MouseAction),
+    MouseScroll(MouseAction),
+    RestoreOrder(RestoreOrder),
+    RestoreDisabled(RestoreChecks),
+    BigramThreshold(u8),
+    CorrectionStyle(CorrectionStyle),
+    AcceptKey(AcceptKey),
+    IyStyle(IyStyle),
+    MoneyShorthand(bool),
+    MinConfidence(u8),
+    TabShortcuts(bool),
+    RepeatKey(RepeatKey),
+    Gestures(Gestures),
+    Feedback(bool),
+    FeedbackGapMs(u8),
+    EditLimit(u8),
+    NbspChord(NbspChord),
+}
+
+impl Setting {
+    /// Numeric (kind, value) pair for FFI
+    ///
+    /// kind: 0=method, 1=enabled, 2=tone_style, 3=auto_correct, 4=bracket_shortcuts,
+    /// 5=max_word_len, 6=overflow, 7=remove_key, 8=uo_compound, 9=correction_packs,
+    /// 10=nbsp, 11=idle_commit_secs, 12=mouse_click, 13=mouse_scroll,
+    /// 14=restore_order, 15=restore_disabled, 16=bigram_threshold,
+    /// 17=correction_style, 18=accept_key, 19=iy_style, 20=money_shorthand,
+    /// 21=min_confidence (percent), 22=tab_shortcuts, 23=repeat_key,
+    /// 24=gestures, 25=feedback, 26=feedback_gap_ms, 27=edit_limit,
+    /// 28=nbsp_chord
+    ///
+    /// auto_correct value: 0=off, 1=restore, 2=all; overflow value:
+    /// 0=pass-through, 1=keep-tail; remove_key value: 0=default, 1=off,
+    /// otherwise the key's ASCII character; correction_packs value: bit 0
+    /// northern, bit 1 central, bit 2 southern; nbsp value: 0=off, 1=regular,
+    /// 2=narrow; mouse_click / mouse_scroll value: 0=keep, 1=commit, 2=clear;
+    /// restore_order / restore_disabled / gestures value: always 0 (read
+    /// them from the config); correction_style value: 0=replace, 1=suggest;
+    /// accept_key value: 0=tab, 1=right, 2=backquote; iy_style value: 0=off,
+    /// 1=prefer-y, 2=prefer-i; repeat_key value: 0=single, 1=raw, 2=normal;
+    /// nbsp_chord value: 0=shift-space, 1=alt-space, 2=shift-alt-space
+    pub fn to_ffi(self) -> (u8, u8) {
+        match self {
+            Setting::Method(m) => (0, m.id()),
+            Setting::Enabled(b) => (1, b as u8),
+            Setting::ToneStyle(t) => (2, (t == ToneStyle::Classic) as u8),
+            Setting::AutoCorrect(a) => (3, a as u8),
+            Setting::BracketShortcuts(b) => (4, b as u8),
+            Setting::MaxWordLen(n) => (5, n),
+            Setting::Overflow(o) => (6, o as u8),
+            Setting::RemoveKey(k) => (
+                7,
+                match k {
+                    RemoveKey::Default => 0,
+                    RemoveKey::Off => 1,
+                    RemoveKey::Key(c) => c as u8,
+                },
+            ),
+            Setting::UoCompound(b) => (8, b as u8),
+            Setting::CorrectionPacks(p) => (9, p.bits()),
+            Setting::Nbsp(n) => (10, n as u8),
+            Setting::IdleCommitSecs(n) => (11, n),
+            Setting::MouseClick(a) => (12, a as u8),
+            Setting::MouseScroll(a) => (13, a as u8),
+            Setting::RestoreOrder(_) => (14, 0),
+            Setting::RestoreDisabled(_) => (15, 0),
+            Setting::BigramThreshold(n) => (16, n),
+            Setting::CorrectionStyle(c) => (17, c as u8),
+            Setting::AcceptKey(k) => (18, k as u8),
+            Setting::IyStyle(s) => (19, s as u8),
+            Setting::MoneyShorthand(b) => (20, b as u8),
+            Setting::MinConfidence(n) => (21, n),
+            Setting::TabShortcuts(b) => (22, b as u8),
+            Setting::RepeatKey(r) => (23, r as u8),
+            Setting::Gestures(_) => (24, 0),
+            Setting::Feedback(b) => (25, b as u8),
+            Setting::FeedbackGapMs(n) => (26, n),
+            Setting::EditLimit(n) => (27, n),
+            Setting::NbspChord(c) => (28, c as u8),
+        }
+    }
+}
+
+/// Event delivered to observers
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Notification {
+    /// A setting changed value (not fired when set to the same value)
+    SettingChanged(Setting),
+    /// A correction is on offer (`Engine::suggestion`)
+    SuggestionOffered,
+    /// Down or Up moved the selection to this candidate
+    SuggestionSelected(usize),
+    /// The offer was accepted or dropped
+    SuggestionCleared,
+    /// The word being typed now reads this way (`Engine::spell_status`;
+    /// only sent after `Engine::watch_spell_status`)
+    SpellStatus(SpellStatus),
+    /// A modifier was double-tapped (`Engine::gesture`)
+    Gesture(GestureAction),
+    /// Something a sound or haptic could mark (only with `feedback` on)
+    Feedback(Feedback),
+    /// Edits came faster than `edit_limit`: the engine dropped the word and
+    /// stopped editing for a while (see `runaway`)
+    Runaway,
+}
+
+/// What a feedback event marks, least notable first
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Feedback {
+    /// A word ended as typed
+    WordCommitted = 0,
+    /// A word was corrected, or a suggestion accepted
+    Corrected = 1,
+    /// A word went back to its keystrokes
+    Restored = 2,
+}
+
+/// Subscription handle (pass to `unsubscribe`)
+pub type ObserverId = u32;
+
+type Callback = Box<dyn FnMut(&Notification) + Send>;
+
+/// Registered observers
+#[derive(Default)]
+pub struct Observers {
+    list: Vec<(ObserverId, Callback)>,
+    next_id: ObserverId,
+}
+
+impl Observers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn subscribe(&mut self, f: impl FnMut(&Notification) + Send + 'static) -> ObserverId {
+        self.next_id = self.next_id.wrapping_add(1);
+        self.list.push((self.next_id, Box::new(f)));
+        self.next_id
+    }
+
+    /// Subscribe via channel; the observer is dropped once the receiver is
+    pub fn subscribe_channel(&mut self) -> (ObserverId, mpsc::Receiver<Notification>) {
+        let (tx, rx) = mpsc::channel();
+        let id = self.subscribe(move |n| {
+            let _ = tx.send(*n);
+        });
+        (id, rx)
+    }
+
+    /// Returns false if no observer had this id
+    pub fn unsubscribe(&mut self, id: ObserverId) -> bool {
+        let before = self.list.len();
+        self.list.retain(|(i, _)| *i != id);
+        self.list.len() != before
+    }
+
+    pub fn notify(&mut self, n: Notification) {
+        for (_, f) in self.list.iter_mut() {
+            f(&n);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.list.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_subscribe_and_unsubscribe() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let mut obs = Observers::new();
+
+        let sink = seen.clone();
+        let id = obs.subscribe(move |n| sink.lock().unwrap().push(*n));
+        let n = Notification::SettingChanged(Setting::Enabled(false));
+        obs.notify(n);
+        assert_eq!(*seen.lock().unwrap(), vec![n]);
+
+        assert!(obs.unsubscribe(id));
+        assert!(!obs.unsubscribe(id));
+        obs.notify(n);
+        assert_eq!(seen.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_channel() {
+        let mut obs = Observers::new();
+        let (_, rx) = obs.subscribe_channel();
+        obs.notify(Notification::SettingChanged(Setting::Method(Method::Vni)));
+        assert_eq!(
+            rx.try_recv(),
+            Ok(Notification::SettingChanged(Setting::Method(Method::Vni)))
+        );
+    }
+
+    #[test]
+    fn test_ffi_encoding() {
+        assert_eq!(Setting::Method(Method::Vni).to_ffi(), (0, 1));
+        assert_eq!(Setting::ToneStyle(ToneStyle::Classic).to_ffi(), (2, 1));
+        assert_eq!(Setting::BracketShortcuts(true).to_ffi(), (4, 1));
+        assert_eq!(Setting::AutoCorrect(AutoCorrectMode::All).to_ffi(), (3, 2));
+        assert_eq!(
+            Setting::CorrectionStyle(CorrectionStyle::Suggest).to_ffi(),
+            (17, 1)
+        );
+        assert_eq!(Setting::IyStyle(IyStyle::PreferI).to_ffi(), (19, 2));
+        assert_eq!(Setting::MoneyShorthand(true).to_ffi(), (20, 1));
+        assert_eq!(Setting::MinConfidence(80).to_ffi(), (21, 80));
+        assert_eq!(Setting::TabShortcuts(true).to_ffi(), (22, 1));
+        assert_eq!(Setting::RepeatKey(RepeatKey::Normal).to_ffi(), (23, 2));
+        assert_eq!(Setting::Gestures(Gestures::NONE).to_ffi(), (24, 0));
+        assert_eq!(Setting::FeedbackGapMs(80).to_ffi(), (26, 80));
+        assert_eq!(Setting::EditLimit(40).to_ffi(), (27, 40));
+    }
+}
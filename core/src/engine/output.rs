@@ -0,0 +1,308 @@
+//! Output Tuning per Application
+//!
+//! Some targets drop or reorder rapid synthetic backspaces (Excel cells,
+//! remote desktop clients, Java/Swing apps). The host picks a profile for
+//! the focused app (`OutputProfile::for_app`, or its own per-app settings)
+//! and the engine shapes its edits accordingly:
+//!
+//! - `max_backspace`: an edit that would delete more than this stops
+//!   mid-word editing; the rest of the word is typed raw and fixed up once
+//!   at the word boundary.
+//! - `edit_delay_us`: pause the host should leave between synthetic
+//!   keystrokes (a hint, the engine doesn't sleep).
+//! - `Strategy::AtBoundary`: never edit mid-word, one replacement per word.
+//! - `Delivery::Select`: instead of backspaces, the host selects the edit's
+//!   range and types over it in one step (accessibility API, TSF).
+//! - `BoundaryKey::AfterEdit`: Enter that ends a corrected word comes with
+//!   the edit, after the word, instead of going through on its own. In
+//!   chat apps Enter sends the message; passed through, it can beat the
+//!   synthetic backspaces, which then hit an empty field.
+//!
+//! Apps that drop edits without telling leave the engine editing text that
+//! isn't there. Hosts that can read the field report the text before the
+//! caret (`Engine::check_field`); on a mismatch the engine records a
+//! `GhostEdit` and falls back to `OutputProfile::BOUNDARY` until the host
+//! picks a profile again (on the next app switch).
+//!
+//! Selection needs absolute offsets: the host tells the engine where the
+//! caret is (`Engine::set_caret`) and the engine follows it through every
+//! key it sees. Offsets are UTF-16 code units, like `NSRange` and TSF.
+//! Anything the engine can't follow (arrows, Cmd+V, a click) makes the
+//! caret unknown until the host sets it again; edits then fall back to
+//! backspaces.
+
+use super::buffer::MAX;
+use crate::data::keys;
+
+/// When edits are sent
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Strategy {
+    /// Edit on every key (ệ appears as soon as `j` is typed)
+    #[default]
+    Immediate,
+    /// Keys go through raw; the word is replaced once at space/punctuation
+    AtBoundary,
+}
+
+/// How an edit reaches the app
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Delivery {
+    /// Send `backspace` deletes, then the text
+    #[default]
+    Backspace,
+    /// Replace the range from `Engine::edit_range` with the text
+    Select,
+}
+
+/// Where Enter goes when the word it ends gets an edit
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BoundaryKey {
+    /// Through to the app, alongside the edit
+    #[default]
+    PassThrough,
+    /// At the end of the edit's text as `'\n'`: the host swallows the key,
+    /// delivers the edit, then presses Return for the `'\n'`. Shift+Enter
+    /// (a new line in chat apps) still goes through.
+    AfterEdit,
+}
+
+/// Output tuning for the focused application
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct OutputProfile {
+    /// Most backspaces in one mid-word edit (0 = no limit)
+    pub max_backspace: u8,
+    /// Suggested delay between synthetic keystrokes, in microseconds
+    pub edit_delay_us: u32,
+    pub strategy: Strategy,
+    pub delivery: Delivery,
+    pub boundary_key: BoundaryKey,
+}
+
+impl OutputProfile {
+    /// Apps that lose fast synthetic input
+    pub const SLOW: OutputProfile = OutputProfile {
+        max_backspace: 2,
+        edit_delay_us: 8000,
+        strategy: Strategy::Immediate,
+        delivery: Delivery::Backspace,
+        boundary_key: BoundaryKey::PassThrough,
+    };
+
+    /// Apps that mangle any mid-word edit
+    pub const BOUNDARY: OutputProfile = OutputProfile {
+        max_backspace: 0,
+        edit_delay_us: 8000,
+        strategy: Strategy::AtBoundary,
+        delivery: Delivery::Backspace,
+        boundary_key: BoundaryKey::PassThrough,
+    };
+
+    /// Chat apps, where Enter sends the message
+    pub const CHAT: OutputProfile = OutputProfile {
+        max_backspace: 0,
+        edit_delay_us: 0,
+        strategy: Strategy::Immediate,
+        delivery: Delivery::Backspace,
+        boundary_key: BoundaryKey::AfterEdit,
+    };
+
+    /// Built-in profile for an app (macOS bundle id or Windows exe name)
+    pub fn for_app(app_id: &str) -> OutputProfile {
+        let id = app_id.to_ascii_lowercase();
+        match id.as_str() {
+            "com.microsoft.excel" | "excel.exe" => Self::BOUNDARY,
+            "com.microsoft.rdc.macos"
+            | "com.microsoft.rdc.mac"
+            | "mstsc.exe"
+            | "msrdc.exe"
+            | "com.teamviewer.teamviewer"
+            | "teamviewer.exe" => Self::BOUNDARY,
+            "java.exe" | "javaw.exe" | "net.java.openjdk.java" => Self::SLOW,
+            "com.tinyspeck.slackmacgap"
+            | "slack.exe"
+            | "com.hnc.discord"
+            | "discord.exe"
+            | "com.facebook.archon"
+            | "messenger.exe"
+            | "ru.keepcoder.telegram"
+            | "com.tdesktop.telegram"
+            | "telegram.exe"
+            | "com.vng.zalo"
+            | "zalo.exe"
+            | "net.whatsapp.whatsapp"
+            | "whatsapp.exe"
+            | "com.microsoft.teams2"
+            | "ms-teams.exe" => Self::CHAT,
+            _ => OutputProfile::default(),
+        }
+    }
+}
+
+/// Outcome of `Engine::check_field`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FieldCheck {
+    /// No word on screen to compare
+    Unknown,
+    /// The field shows what the engine sent
+    Match,
+    /// It doesn't: an edit was lost (now recorded in `ghost_edits`)
+    Mismatch,
+}
+
+/// Diagnostic for one lost edit
+///
+/// Lengths only: the text itself isn't kept.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GhostEdit {
+    /// Characters the engine expected before the caret
+    pub expected: usize,
+    /// How many of them, counted back from the caret, the field has
+    pub matched: usize,
+    /// Profile in use when it happened
+    pub profile: OutputProfile,
+}
+
+/// Most `GhostEdit`s kept (oldest dropped first)
+pub(super) const MAX_GHOST_EDITS: usize = 16;
+
+/// Characters at the end of `expected` that `actual` also ends with
+pub(super) fn trailing_match(expected: &[char], actual: &str) -> usize {
+    expected
+        .iter()
+        .rev()
+        .zip(actual.chars().rev())
+        .take_while(|(&e, a)| e == *a)
+        .count()
+}
+
+/// Word being composed or just finished, for screen readers
+///
+/// Lets the host announce "việt" once it's composed instead of echoing
+/// v-i-e-e-t-j. Offsets locate the word on screen relative to the caret.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WordInfo {
+    /// Composed text (what the word becomes, even while edits are deferred)
+    pub text: String,
+    /// Start offset from the caret in UTF-16 units (negative = before it)
+    pub start: isize,
+    /// End offset from the caret (0 while composing, before the boundary
+    /// character once committed)
+    pub end: isize,
+    /// A boundary key ended the word
+    pub committed: bool,
+}
+
+/// Caret after a key the app handled itself (None = can't tell)
+pub(super) fn caret_after_key(caret: usize, key: u16, ctrl: bool) -> Option<usize> {
+    if keys::is_passthrough(key) {
+        return Some(caret);
+    }
+    // Cmd+V, Cmd+Z, Option+arrow...
+    if ctrl {
+        return None;
+    }
+    match key {
+        keys::DELETE => caret.checked_sub(1),
+        keys::ESC | keys::FORWARD_DELETE | keys::DEAD => Some(caret),
+        keys::LEFT
+        | keys::RIGHT
+        | keys::UP
+        | keys::DOWN
+        | keys::HOME
+        | keys::END
+        | keys::PAGE_UP
+        | keys::PAGE_DOWN
+        | keys::KP_CLEAR => None,
+        _ if keys::is_letter(key) || keys::is_number(key) || keys::is_break(key) => Some(caret + 1),
+        _ => None,
+    }
+}
+
+/// What's on screen for a word whose edits are held back
+#[derive(Clone, Copy)]
+pub(super) struct Pending {
+    chars: [char; MAX],
+    len: usize,
+}
+
+impl Pending {
+    pub(super) fn new(shown: &[char]) -> Self {
+        let mut p = Pending {
+            chars: ['\0'; MAX],
+            len: 0,
+        };
+        for &c in shown {
+            p.push(c);
+        }
+        p
+    }
+
+    /// false once full (the word is then given up)
+    pub(super) fn push(&mut self, c: char) -> bool {
+        if self.len == MAX {
+            return false;
+        }
+        self.chars[self.len] = c;
+        self.len += 1;
+        true
+    }
+
+    pub(super) fn as_slice(&self) -> &[char] {
+        &self.chars[..self.len]
+    }
+
+    /// Drop `n` characters from the end
+    pub(super) fn truncate_back(&mut self, n: usize) {
+        self.len = self.len.saturating_sub(n);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_for_app() {
+        assert_eq!(
+            OutputProfile::for_app("com.microsoft.Excel").strategy,
+            Strategy::AtBoundary
+        );
+        assert_eq!(OutputProfile::for_app("EXCEL.EXE"), OutputProfile::BOUNDARY);
+        assert_eq!(OutputProfile::for_app("javaw.exe"), OutputProfile::SLOW);
+        assert_eq!(
+            OutputProfile::for_app("com.tinyspeck.slackmacgap").boundary_key,
+            BoundaryKey::AfterEdit
+        );
+        assert_eq!(
+            OutputProfile::for_app("com.apple.TextEdit"),
+            OutputProfile::default()
+        );
+    }
+
+    #[test]
+    fn test_caret_after_key() {
+        assert_eq!(caret_after_key(5, keys::A, false), Some(6));
+        assert_eq!(caret_after_key(5, keys::COMMA, false), Some(6));
+        assert_eq!(caret_after_key(5, keys::SHIFT, true), Some(5));
+        assert_eq!(caret_after_key(5, keys::DELETE, false), Some(4));
+        assert_eq!(caret_after_key(0, keys::DELETE, false), None);
+        assert_eq!(caret_after_key(5, keys::LEFT, false), None);
+        assert_eq!(caret_after_key(5, keys::V, true), None);
+    }
+
+    #[test]
+    fn test_trailing_match() {
+        let word: Vec<char> = "việt".chars().collect();
+        assert_eq!(trailing_match(&word, "tiếng việt"), 4);
+        assert_eq!(trailing_match(&word, "tiếng viêt"), 1);
+        assert_eq!(trailing_match(&word, "ệt"), 2);
+        assert_eq!(trailing_match(&word, ""), 0);
+    }
+
+    #[test]
+    fn test_pending_full() {
+        let mut p = Pending::new(&['a'; MAX]);
+        assert!(!p.push('b'));
+        assert_eq!(p.as_slice().len(), MAX);
+    }
+}
@@ -0,0 +1,171 @@
+//! Data table warm-up
+//!
+//! Tables are embedded in the binary, so the first lookup pays for paging
+//! them in (and the lexicon is several pages of text). `Engine::prewarm()`
+//! walks every enabled table once on a background thread at app launch and
+//! checks it is well-formed, so the first keystroke after enabling
+//! autocorrect doesn't stall. It also checks the syllable trie the spell
+//! status follows (`spell::syllables`).
+//!
+//! The status is process-wide: tables are shared by every engine.
+
+use crate::data::{chars, keys};
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::Mutex;
+use std::thread::JoinHandle;
+
+/// Warm-up progress
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PrewarmStatus {
+    NotStarted = 0,
+    Running = 1,
+    Ready = 2,
+    /// A table failed validation (see `prewarm_failure`)
+    Failed = 3,
+}
+
+impl PrewarmStatus {
+    fn from_u8(n: u8) -> Self {
+        match n {
+            1 => PrewarmStatus::Running,
+            2 => PrewarmStatus::Ready,
+            3 => PrewarmStatus::Failed,
+            _ => PrewarmStatus::NotStarted,
+        }
+    }
+}
+
+static STATUS: AtomicU8 = AtomicU8::new(PrewarmStatus::NotStarted as u8);
+static FAILURE: Mutex<Option<&'static str>> = Mutex::new(None);
+
+/// Start the warm-up thread unless it already ran or is running
+pub(super) fn start() -> Option<JoinHandle<()>> {
+    STATUS
+        .compare_exchange(
+            PrewarmStatus::NotStarted as u8,
+            PrewarmStatus::Running as u8,
+            Ordering::AcqRel,
+            Ordering::Acquire,
+        )
+        .ok()?;
+    let spawned = std::thread::Builder::new()
+        .name("gonhanh-prewarm".into())
+        .spawn(run);
+    match spawned {
+        Ok(handle) => Some(handle),
+        Err(_) => {
+            // No thread available: warm up on the caller instead
+            run();
+            None
+        }
+    }
+}
+
+pub(super) fn status() -> PrewarmStatus {
+    PrewarmStatus::from_u8(STATUS.load(Ordering::Acquire))
+}
+
+pub(super) fn failure() -> Option<&'static str> {
+    *FAILURE.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn run() {
+    let result = validate();
+    if let Err(table) = result {
+        *FAILURE.lock().unwrap_or_else(|e| e.into_inner()) = Some(table);
+    }
+    let status = match result {
+        Ok(()) => PrewarmStatus::Ready,
+        Err(_) => PrewarmStatus::Failed,
+    };
+    STATUS.store(status as u8, Ordering::Release);
+}
+
+/// Walk every enabled table; `Err(name)` for the first malformed one
+fn validate() -> Result<(), &'static str> {
+    check_vowels().then_some(()).ok_or("vowels")?;
+    check_syllables().then_some(()).ok_or("syllables")?;
+    #[cfg(feature = "autocorrect")]
+    check_lexicon(&crate::data::lexicon::ENGLISH)
+        .then_some(())
+        .ok_or("lexicon")?;
+    Ok(())
+}
+
+/// Every vowel × modifier × mark composes and parses back to itself
+fn check_vowels() -> bool {
+    [keys::A, keys::E, keys::I, keys::O, keys::U, keys::Y]
+        .into_iter()
+        .all(|key| {
+            (0..=2).all(|tone| {
+                (0..=5).all(|mark| {
+                    [false, true].into_iter().all(|caps| {
+                        let Some(c) = chars::to_char(key, caps, tone, mark) else {
+                            return false;
+                        };
+                        chars::parse_char(c).is_some_and(|p| {
+                            p.key == key
+                                && p.caps == caps
+                                && chars::to_char(p.key, p.caps, p.tone, p.mark) == Some(c)
+                        })
+                    })
+                })
+            })
+        })
+}
+
+/// The syllable trie holds the longest spellings
+fn check_syllables() -> bool {
+    let trie = super::spell::syllables();
+    ["nghieng", "khuyu"].into_iter().all(|w| {
+        trie.walk(w.chars())
+            .is_some_and(|n| !trie.values(n).is_empty())
+    })
+}
+
+/// Sorted, lowercase, and every word found by the binary search
+#[cfg(feature = "autocorrect")]
+fn check_lexicon(lexicon: &crate::data::lexicon::Lexicon) -> bool {
+    let mut prev = "";
+    lexicon.words().all(|w| {
+        let ok = w > prev && w.bytes().all(|b| b.is_ascii_lowercase()) && lexicon.contains(w);
+        prev = w;
+        ok
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tables_valid() {
+        assert_eq!(validate(), Ok(()));
+    }
+
+    #[test]
+    #[cfg(feature = "autocorrect")]
+    fn test_lexicon_unsorted_fails() {
+        use crate::data::lexicon::Lexicon;
+        assert!(check_lexicon(&Lexicon::new("apple\nbanana\n", &[0, 6, 13])));
+        assert!(!check_lexicon(&Lexicon::new(
+            "banana\napple\n",
+            &[0, 7, 13]
+        )));
+        assert!(!check_lexicon(&Lexicon::new("Apple\n", &[0, 6])));
+    }
+
+    #[test]
+    fn test_start_once() {
+        if let Some(handle) = start() {
+            handle.join().unwrap();
+        }
+        assert!(start().is_none());
+        // Another test may have started it first; wait for that thread
+        while status() == PrewarmStatus::Running {
+            std::thread::yield_now();
+        }
+        assert_eq!(status(), PrewarmStatus::Ready);
+        assert_eq!(failure(), None);
+    }
+}
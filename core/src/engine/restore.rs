@@ -0,0 +1,382 @@
+//! Auto-restore Checks
+//!
+//! On a word boundary, auto-restore runs the checks of `RestoreOrder` one
+//! after another and turns the word back into its raw keystrokes as soon
+//! as one fires. The keystroke patterns live here as plain functions of
+//! the raw keys; checks needing engine state (dictionary, validation) are
+//! evaluated by the engine.
+//!
+//! `RestoreStats` counts how often each check ran and fired, so the order
+//! can be tuned from real typing (`RestoreStats::suggested_order`), and
+//! `RestoreReason` says which check restored the last word. A
+//! `RestoreHint` competes with the checks on confidence (`decision`); a
+//! restore it wins is reported as `Dictionary`.
+
+use super::buffer::MAX;
+#[cfg(feature = "autocorrect")]
+use super::syllable::parse;
+use super::validation::Problem;
+#[cfg(feature = "autocorrect")]
+use super::validation::ValidationResult;
+use crate::config::{RestoreCheck, RestoreOrder};
+use crate::data::keys;
+use crate::json::Value;
+use crate::utils::key_to_char;
+use std::cell::Cell;
+use std::ops::Range;
+
+/// Telex keys that add tone marks
+const TONE_MODIFIERS: [u16; 5] = [keys::S, keys::F, keys::R, keys::X, keys::J];
+
+fn vowels_in(raw: &[u16]) -> usize {
+    raw.iter().filter(|&&k| keys::is_vowel(k)).count()
+}
+
+/// Positions of tone keys
+fn modifiers(raw: &[u16]) -> impl Iterator<Item = usize> + '_ {
+    (0..raw.len()).filter(|&i| TONE_MODIFIERS.contains(&raw[i]))
+}
+
+// Each pattern returns the keystrokes it matched, as a range into `raw`.
+
+/// W is not a Vietnamese initial: "wow" (another W later), "window" (a
+/// consonant after it). A lone "w" is "ư".
+pub fn w_initial(raw: &[u16]) -> Option<Range<usize>> {
+    if raw.len() < 2 || raw[0] != keys::W {
+        return None;
+    }
+    let english = raw[2..].contains(&keys::W)
+        || raw[1..]
+            .iter()
+            .any(|&k| keys::is_consonant(k) && k != keys::W);
+    english.then_some(0..1)
+}
+
+/// Tone key followed by a consonant: "expect" (more letters after it),
+/// "text" (one vowel before it). "muwowjt" has two vowels, so it stays.
+pub fn modifier_consonant(raw: &[u16]) -> Option<Range<usize>> {
+    modifiers(raw)
+        .find(|&i| {
+            i + 1 < raw.len()
+                && keys::is_consonant(raw[i + 1])
+                && (i + 2 < raw.len() || vowels_in(&raw[..i]) == 1)
+        })
+        .map(|i| i..i + 2)
+}
+
+/// Tone key last, after a pair of different vowels that is English:
+/// "ei" ("their"), or "ai" as the only vowels after a lone P ("pair").
+/// "booj" (doubling), "chiuj" and "hoaij" are Vietnamese.
+pub fn modifier_vowel_pair(raw: &[u16]) -> Option<Range<usize>> {
+    let i = raw.len().checked_sub(1)?;
+    if i < 2 || !TONE_MODIFIERS.contains(&raw[i]) {
+        return None;
+    }
+    let (v1, v2) = (raw[i - 2], raw[i - 1]);
+    if !keys::is_vowel(v1) || !keys::is_vowel(v2) || v1 == v2 {
+        return None;
+    }
+    // P is rare as a native initial, PH is common
+    let english = (v1 == keys::E && v2 == keys::I)
+        || (v1 == keys::A
+            && v2 == keys::I
+            && vowels_in(&raw[..i]) == 2
+            && raw[0] == keys::P
+            && raw[1] != keys::H);
+    english.then_some(i - 2..i + 1)
+}
+
+/// Tone key between the only vowel before it and another vowel: English
+/// without an initial consonant ("use"), or when the vowels differ
+/// ("core"). Same vowel ("these" → "thế") and u + a/o ("cura" → "cửa")
+/// are Vietnamese.
+pub fn modifier_between_vowels(raw: &[u16]) -> Option<Range<usize>> {
+    modifiers(raw)
+        .find(|&i| {
+            if i + 1 >= raw.len() || !keys::is_vowel(raw[i + 1]) || vowels_in(&raw[..i]) != 1 {
+                return false;
+            }
+            let first_vowel = raw[..i]
+                .iter()
+                .position(|&k| keys::is_vowel(k))
+                .unwrap_or(0);
+            let has_initial = first_vowel > 0 && keys::is_consonant(raw[first_vowel - 1]);
+            if !has_initial {
+                return true;
+            }
+            let (prev, next) = (raw[i - 1], raw[i + 1]);
+            prev != next && !(prev == keys::U && (next == keys::A || next == keys::O))
+        })
+        .map(|i| i - 1..i + 2)
+}
+
+/// W after a vowel other than U at the end: "raw", "law" ("tuw" is "tư")
+pub fn w_final(raw: &[u16]) -> Option<Range<usize>> {
+    match raw {
+        [.., prev, keys::W] if keys::is_vowel(*prev) && *prev != keys::U => {
+            Some(raw.len() - 2..raw.len())
+        }
+        _ => None,
+    }
+}
+
+/// English suffixes, longest first
+const SUFFIXES: [&[u16]; 9] = [
+    &[keys::T, keys::I, keys::O, keys::N],
+    &[keys::S, keys::I, keys::O, keys::N],
+    &[keys::M, keys::E, keys::N, keys::T],
+    &[keys::N, keys::E, keys::S, keys::S],
+    &[keys::A, keys::B, keys::L, keys::E],
+    &[keys::I, keys::B, keys::L, keys::E],
+    &[keys::I, keys::N, keys::G],
+    &[keys::I, keys::V, keys::E],
+    &[keys::E, keys::S],
+];
+
+/// English suffix after a stem of two or more letters with a vowel
+/// ("missing", "session"); returns the suffix
+///
+/// The engine only asks about words that aren't a syllable. Those can
+/// slip past the other checks: a doubled tone key undoes its tone
+/// ("missing" shows "mising"), leaving no transform behind.
+pub fn english_suffix(raw: &[u16]) -> Option<Range<usize>> {
+    SUFFIXES.iter().find_map(|suffix| {
+        let start = raw.len().checked_sub(suffix.len())?;
+        let stem = &raw[..start];
+        (raw.ends_with(suffix) && stem.len() >= 2 && vowels_in(stem) > 0)
+            .then_some(start..raw.len())
+    })
+}
+
+/// Shortest word the bigram check judges: two- and three-letter Telex
+/// ("dd", "did" for "đi") are too short for their pairs to mean much
+pub(crate) const BIGRAM_MIN_LEN: usize = 4;
+
+/// Letter pairs leaning English by `threshold` tenths of a bit or more on
+/// average (`data::bigram`); returns the whole word
+///
+/// A tie-breaker for words none of the patterns above explain: "passed"
+/// shows "pased", which is no syllable but has no transform left either.
+/// A `threshold` of 0 turns it off.
+pub fn bigram(raw: &[u16], threshold: u8) -> Option<Range<usize>> {
+    if threshold == 0 || !(BIGRAM_MIN_LEN..=MAX).contains(&raw.len()) {
+        return None;
+    }
+    // On the stack: this runs on every space
+    let mut word = [0u8; MAX];
+    for (b, &k) in word.iter_mut().zip(raw) {
+        *b = key_to_char(k, false).filter(char::is_ascii_lowercase)? as u8;
+    }
+    let score = crate::data::bigram::score(&word[..raw.len()])?;
+    (score >= threshold as i32).then_some(0..raw.len())
+}
+
+/// Letters of the buffer a validation failure is about: the initial for
+/// an invalid initial, and so on (the whole word when there is no part)
+#[cfg(feature = "autocorrect")]
+pub(crate) fn problem_span(buffer_keys: &[u16], result: &ValidationResult) -> Range<usize> {
+    let syllable = parse(buffer_keys);
+    let span = match result {
+        ValidationResult::InvalidInitial => syllable.initial.iter(),
+        ValidationResult::InvalidSpelling => syllable.initial.start..syllable.vowel.end,
+        ValidationResult::InvalidFinal => syllable.final_c.iter(),
+        ValidationResult::InvalidVowelPattern => syllable.vowel.iter(),
+        ValidationResult::NoVowel | ValidationResult::Valid => 0..0,
+    };
+    if span.is_empty() {
+        0..buffer_keys.len()
+    } else {
+        span
+    }
+}
+
+/// Outside opinion on a word, weighed with the checks (a plugin, see
+/// `crate::plugin`, or `crate::ml`)
+pub trait RestoreHint: Send {
+    /// Probability in [0, 1] that `raw`, the keys as typed, is meant
+    /// rather than `word`, as shown; `None` to leave it to the checks
+    fn restore(&self, raw: &str, word: &str) -> Option<f32>;
+}
+
+/// Why auto-restore turned a word back into its keystrokes
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RestoreReason {
+    /// The check that fired
+    pub check: RestoreCheck,
+    /// What is wrong with the syllable, for `Invalid` and `MarkFinal`
+    pub problem: Option<Problem>,
+    /// The characters at fault: keystrokes for the keystroke patterns
+    /// ("xt" in "text"), letters of the composed word for `Invalid` and
+    /// `MarkFinal` ("f" in "fé"), the whole word for `Dictionary`
+    pub matched: String,
+    /// Word as composed ("tẽt")
+    pub composed: String,
+    /// Word as restored ("text")
+    pub restored: String,
+}
+
+impl RestoreReason {
+    /// Short English explanation, for a tooltip or a bug report
+    pub fn message(&self) -> String {
+        let why = match (self.check, self.problem) {
+            (RestoreCheck::Dictionary, _) => "an English word",
+            (_, Some(problem)) => problem.message(),
+            (RestoreCheck::WInitial, _) => "W does not start Vietnamese words",
+            (RestoreCheck::ModifierConsonant, _) => "tone key before a consonant",
+            (RestoreCheck::ModifierVowelPair, _) => "tone key after an English vowel pair",
+            (RestoreCheck::ModifierBetweenVowels, _) => "tone key between two vowels",
+            (RestoreCheck::WFinal, _) => "W does not end Vietnamese words",
+            (RestoreCheck::EnglishSuffix, _) => "English suffix",
+            (RestoreCheck::Bigram, _) => "letters read as English",
+            (RestoreCheck::Invalid | RestoreCheck::MarkFinal, None) => "not Vietnamese",
+        };
+        format!("\"{}\": {}", self.matched, why)
+    }
+
+    /// `{"check", "problem", "matched", "composed", "restored", "message"}`
+    /// (`problem` is null for the keystroke patterns)
+    pub fn to_json(&self) -> Value {
+        let problem = match self.problem {
+            Some(p) => Value::Str(p.message().into()),
+            None => Value::Null,
+        };
+        Value::Obj(vec![
+            ("check".into(), Value::Str(self.check.as_str().into())),
+            ("problem".into(), problem),
+            ("matched".into(), Value::Str(self.matched.clone())),
+            ("composed".into(), Value::Str(self.composed.clone())),
+            ("restored".into(), Value::Str(self.restored.clone())),
+            ("message".into(), Value::Str(self.message())),
+        ])
+    }
+}
+
+fn index(check: RestoreCheck) -> usize {
+    RestoreCheck::ALL
+        .iter()
+        .position(|&c| c == check)
+        .unwrap_or(0)
+}
+
+/// How often each check ran and fired
+///
+/// A check only runs when every check before it stayed quiet, so `hits`
+/// counts the words it decided.
+#[derive(Debug, Default)]
+pub struct RestoreStats {
+    evaluated: [Cell<u64>; RestoreCheck::ALL.len()],
+    hits: [Cell<u64>; RestoreCheck::ALL.len()],
+}
+
+impl RestoreStats {
+    #[cfg(feature = "autocorrect")]
+    pub(crate) fn record(&self, check: RestoreCheck, hit: bool) {
+        let i = index(check);
+        self.evaluated[i].set(self.evaluated[i].get() + 1);
+        if hit {
+            self.hits[i].set(self.hits[i].get() + 1);
+        }
+    }
+
+    pub fn evaluated(&self, check: RestoreCheck) -> u64 {
+        self.evaluated[index(check)].get()
+    }
+
+    pub fn hits(&self, check: RestoreCheck) -> u64 {
+        self.hits[index(check)].get()
+    }
+
+    pub fn reset(&self) {
+        for c in self.evaluated.iter().chain(&self.hits) {
+            c.set(0);
+        }
+    }
+
+    /// `current` with the checks that decided the most words first (ties
+    /// keep their place)
+    pub fn suggested_order(&self, current: RestoreOrder) -> RestoreOrder {
+        let mut checks = *current.checks();
+        checks.sort_by_key(|&c| std::cmp::Reverse(self.hits(c)));
+        RestoreOrder::new(&checks).unwrap_or(current)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn raw(s: &str) -> Vec<u16> {
+        s.chars().filter_map(keys::from_char).collect()
+    }
+
+    type Pattern = fn(&[u16]) -> Option<Range<usize>>;
+
+    /// English words with the keystrokes matched, then Vietnamese ones
+    fn assert_pattern(check: Pattern, english: &[(&str, &str)], vietnamese: &[&str]) {
+        for (word, matched) in english {
+            let range = check(&raw(word)).unwrap_or_else(|| panic!("{}", word));
+            assert_eq!(&word[range], *matched, "{}", word);
+        }
+        for word in vietnamese {
+            assert_eq!(check(&raw(word)), None, "{}", word);
+        }
+    }
+
+    #[test]
+    fn test_patterns() {
+        assert_pattern(w_initial, &[("wow", "w"), ("window", "w")], &["w", "wa"]);
+        assert_pattern(
+            modifier_consonant,
+            &[("text", "xt"), ("expect", "xp")],
+            &["muwowjt", "as"],
+        );
+        assert_pattern(
+            modifier_vowel_pair,
+            &[("their", "eir"), ("pair", "air")],
+            &["booj", "chiuj", "hoaij", "phair"],
+        );
+        assert_pattern(
+            modifier_between_vowels,
+            &[("use", "use"), ("core", "ore")],
+            &["these", "cura", "cuso"],
+        );
+        assert_pattern(w_final, &[("raw", "aw"), ("law", "aw")], &["tuw", "w"]);
+        assert_pattern(
+            english_suffix,
+            &[("missing", "ing"), ("session", "sion"), ("boxes", "es")],
+            &["ing", "ses", "tieng"],
+        );
+    }
+
+    #[test]
+    fn test_bigram() {
+        for word in ["passed", "issue", "correct", "different"] {
+            assert_eq!(bigram(&raw(word), 15), Some(0..word.len()), "{}", word);
+        }
+        for word in ["nguwowif", "thuowng", "tieengs", "dd", "did"] {
+            assert_eq!(bigram(&raw(word), 15), None, "{}", word);
+        }
+        assert_eq!(bigram(&raw("well"), 0), None);
+    }
+
+    #[test]
+    #[cfg(feature = "autocorrect")]
+    fn test_suggested_order() {
+        let stats = RestoreStats::default();
+        for _ in 0..3 {
+            stats.record(RestoreCheck::WFinal, true);
+        }
+        stats.record(RestoreCheck::Invalid, true);
+        stats.record(RestoreCheck::Dictionary, false);
+        assert_eq!(stats.evaluated(RestoreCheck::Dictionary), 1);
+        assert_eq!(stats.hits(RestoreCheck::Dictionary), 0);
+        let order = stats.suggested_order(RestoreOrder::DEFAULT);
+        assert_eq!(
+            order.checks()[..2],
+            [RestoreCheck::WFinal, RestoreCheck::Invalid]
+        );
+        assert_eq!(order.checks()[9], RestoreCheck::Dictionary);
+        stats.reset();
+        assert_eq!(stats.hits(RestoreCheck::WFinal), 0);
+    }
+}
@@ -0,0 +1,343 @@
+//! Word Rules - custom word-boundary behaviour in a few lines of text
+//!
+//! For niche workflows that don't deserve a setting:
+//!
+//! ```text
+//! # "wr" is a keyword in this editor, not "ử"
+//! when keys ends "wr" and app is "com.microsoft.VSCode" then restore
+//! when word is "ko" then replace "không"
+//! when not app is "com.apple.Terminal" and keys starts "git" then keep
+//! ```
+//!
+//! One rule per line, `#` starts a comment. A rule is `when`, conditions
+//! joined by `and`, `then` and an action. Rules run when a word ends (space
+//! or punctuation), before auto-restore and corrections; the first rule
+//! whose conditions all hold decides.
+//!
+//! A condition is `[not] <subject> <test> "<text>"`:
+//! - subjects: `word` (as shown), `keys` (as typed), `app` (the focused
+//!   app, `Engine::set_app`; compared ignoring case), `method` ("telex" or
+//!   "vni")
+//! - tests: `is`, `starts`, `ends`, `contains`
+//!
+//! Actions: `restore` (the keystrokes as typed), `keep` (the word as shown,
+//! no auto-restore or correction), `replace "<text>"`. Strings take `\"`
+//! and `\\` escapes.
+
+use super::shortcut::MAX_REPLACEMENT_LEN;
+use crate::config::Method;
+
+/// What a condition looks at
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Subject {
+    Word,
+    Keys,
+    App,
+    Method,
+}
+
+/// How a condition compares
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Test {
+    Is,
+    Starts,
+    Ends,
+    Contains,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Condition {
+    pub negate: bool,
+    pub subject: Subject,
+    pub test: Test,
+    pub text: String,
+}
+
+/// What a matching rule does with the word
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RuleAction {
+    Restore,
+    Keep,
+    Replace(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Rule {
+    pub conditions: Vec<Condition>,
+    pub action: RuleAction,
+}
+
+/// The word that just ended, as rules see it
+#[derive(Clone, Copy, Debug)]
+pub struct WordContext<'a> {
+    pub word: &'a str,
+    pub keys: &'a str,
+    pub app: Option<&'a str>,
+    pub method: Method,
+}
+
+/// Rule file error
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RuleError {
+    pub line: usize,
+    pub message: &'static str,
+}
+
+impl std::fmt::Display for RuleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for RuleError {}
+
+/// Parsed rule file
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Rules {
+    rules: Vec<Rule>,
+}
+
+impl Rules {
+    /// Parse a rule file; nothing is kept if any line is wrong
+    pub fn parse(src: &str) -> Result<Rules, RuleError> {
+        let mut rules = Vec::new();
+        for (i, line) in src.lines().enumerate() {
+            let error = |message| RuleError {
+                line: i + 1,
+                message,
+            };
+            let tokens = lex(line).map_err(error)?;
+            if !tokens.is_empty() {
+                rules.push(parse_rule(&tokens).map_err(error)?);
+            }
+        }
+        Ok(Rules { rules })
+    }
+
+    /// Action of the first rule that holds for `w`
+    pub fn evaluate(&self, w: &WordContext) -> Option<&RuleAction> {
+        self.rules
+            .iter()
+            .find(|r| r.conditions.iter().all(|c| c.holds(w)))
+            .map(|r| &r.action)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Rule> {
+        self.rules.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+}
+
+impl Condition {
+    fn holds(&self, w: &WordContext) -> bool {
+        let value = match self.subject {
+            Subject::Word => Some(w.word.to_string()),
+            Subject::Keys => Some(w.keys.to_string()),
+            Subject::App => w.app.map(str::to_lowercase),
+            Subject::Method => Some(w.method.as_str().to_string()),
+        };
+        let hit = value.is_some_and(|value| {
+            let text = match self.subject {
+                Subject::App => self.text.to_lowercase(),
+                _ => self.text.clone(),
+            };
+            match self.test {
+                Test::Is => value == text,
+                Test::Starts => value.starts_with(&text),
+                Test::Ends => value.ends_with(&text),
+                Test::Contains => value.contains(&text),
+            }
+        });
+        hit != self.negate
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Token<'a> {
+    Word(&'a str),
+    Str(String),
+}
+
+/// Words and strings of one line, up to a comment
+fn lex(line: &str) -> Result<Vec<Token<'_>>, &'static str> {
+    let mut tokens = Vec::new();
+    let mut rest = line.trim_start();
+    while let Some(c) = rest.chars().next() {
+        if c == '#' {
+            break;
+        }
+        if c == '"' {
+            let mut text = String::new();
+            let mut chars = rest[1..].char_indices();
+            let end = loop {
+                match chars.next() {
+                    Some((i, '"')) => break i + 2,
+                    Some((_, '\\')) => match chars.next() {
+                        Some((_, c @ ('"' | '\\'))) => text.push(c),
+                        _ => return Err("unknown escape in string"),
+                    },
+                    Some((_, c)) => text.push(c),
+                    None => return Err("unterminated string"),
+                }
+            };
+            tokens.push(Token::Str(text));
+            rest = &rest[end..];
+        } else {
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            tokens.push(Token::Word(&rest[..end]));
+            rest = &rest[end..];
+        }
+        rest = rest.trim_start();
+    }
+    Ok(tokens)
+}
+
+fn parse_rule(tokens: &[Token]) -> Result<Rule, &'static str> {
+    let mut tokens = tokens.iter();
+    if tokens.next() != Some(&Token::Word("when")) {
+        return Err("a rule starts with `when`");
+    }
+    let mut conditions = Vec::new();
+    loop {
+        let mut word = expect_word(tokens.next(), "expected a condition")?;
+        let negate = word == "not";
+        if negate {
+            word = expect_word(tokens.next(), "expected a condition after `not`")?;
+        }
+        let subject = match word {
+            "word" => Subject::Word,
+            "keys" => Subject::Keys,
+            "app" => Subject::App,
+            "method" => Subject::Method,
+            _ => return Err("unknown subject (word, keys, app, method)"),
+        };
+        let test = match expect_word(tokens.next(), "expected a test")? {
+            "is" => Test::Is,
+            "starts" => Test::Starts,
+            "ends" => Test::Ends,
+            "contains" => Test::Contains,
+            _ => return Err("unknown test (is, starts, ends, contains)"),
+        };
+        let text = expect_str(tokens.next())?;
+        if subject == Subject::Method && (test != Test::Is || Method::parse(&text).is_none()) {
+            return Err("method takes `is \"telex\"` or `is \"vni\"`");
+        }
+        conditions.push(Condition {
+            negate,
+            subject,
+            test,
+            text,
+        });
+        match expect_word(tokens.next(), "expected `and` or `then`")? {
+            "and" => continue,
+            "then" => break,
+            _ => return Err("expected `and` or `then`"),
+        }
+    }
+    let action = match expect_word(tokens.next(), "expected an action")? {
+        "restore" => RuleAction::Restore,
+        "keep" => RuleAction::Keep,
+        "replace" => {
+            let text = expect_str(tokens.next())?;
+            if text.chars().count() > MAX_REPLACEMENT_LEN {
+                return Err("replacement is longer than 63 characters");
+            }
+            RuleAction::Replace(text)
+        }
+        _ => return Err("unknown action (restore, keep, replace)"),
+    };
+    if tokens.next().is_some() {
+        return Err("unexpected text after the action");
+    }
+    Ok(Rule { conditions, action })
+}
+
+fn expect_word<'a>(t: Option<&Token<'a>>, err: &'static str) -> Result<&'a str, &'static str> {
+    match t {
+        Some(Token::Word(w)) => Ok(w),
+        _ => Err(err),
+    }
+}
+
+fn expect_str(t: Option<&Token>) -> Result<String, &'static str> {
+    match t {
+        Some(Token::Str(s)) => Ok(s.clone()),
+        _ => Err("expected a quoted string"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ctx<'a>(word: &'a str, keys: &'a str, app: Option<&'a str>) -> WordContext<'a> {
+        WordContext {
+            word,
+            keys,
+            app,
+            method: Method::Telex,
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        let rules = Rules::parse(
+            "# comment\n\
+             \n\
+             when keys ends \"wr\" and app is \"code\" then restore # trailing\n\
+             when not method is \"vni\" then replace \"say \\\"hi\\\"\"\n",
+        )
+        .unwrap();
+        assert_eq!(rules.len(), 2);
+        let second = rules.iter().nth(1).unwrap();
+        assert!(second.conditions[0].negate);
+        assert_eq!(second.action, RuleAction::Replace("say \"hi\"".into()));
+    }
+
+    #[test]
+    fn test_errors() {
+        for (src, line) in [
+            ("if word is \"a\" then keep", 1),
+            ("\nwhen word is a then keep", 2),
+            ("when word equals \"a\" then keep", 1),
+            ("when word is \"a\" then drop", 1),
+            ("when word is \"a\" then keep now", 1),
+            ("when word is \"a", 1),
+            ("when method is \"qwerty\" then keep", 1),
+            ("when word is \"a\"", 1),
+        ] {
+            assert_eq!(Rules::parse(src).unwrap_err().line, line, "{}", src);
+        }
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let rules = Rules::parse(
+            "when keys ends \"wr\" and app is \"Code\" then restore\n\
+             when word is \"ko\" then replace \"không\"\n\
+             when not app is \"term\" and keys starts \"git\" then keep",
+        )
+        .unwrap();
+        assert_eq!(
+            rules.evaluate(&ctx("ử", "wr", Some("code"))),
+            Some(&RuleAction::Restore)
+        );
+        assert_eq!(rules.evaluate(&ctx("ử", "wr", None)), None);
+        assert_eq!(
+            rules.evaluate(&ctx("ko", "ko", None)),
+            Some(&RuleAction::Replace("không".into()))
+        );
+        assert_eq!(
+            rules.evaluate(&ctx("gít", "gits", None)),
+            Some(&RuleAction::Keep)
+        );
+        assert_eq!(rules.evaluate(&ctx("gít", "gits", Some("term"))), None);
+    }
+}
@@ -0,0 +1,91 @@
+//! Runaway Guard - stop a feedback loop before it floods the document
+//!
+//! A host whose event tap picks up the keys it injects feeds the engine its
+//! own output: each edit comes back as keys that make another edit, and
+//! backspaces pile into the user's document faster than anyone can stop
+//! it. No one types that fast, so `Guard` counts the edits that delete
+//! (`backspace` > 0) per `WINDOW_MS`; past the `edit_limit` setting it
+//! trips. The engine then drops the word, tells observers
+//! (`Notification::Runaway`) and lets keys through untouched until a whole
+//! window passes without an edit.
+//!
+//! Time is the host's clock of the key that made the edit
+//! (`Engine::on_key_at`, `KeyEvent::time_ms`), so a replayed session trips
+//! where the original did. Keys without a time can't be timed and are
+//! never counted.
+
+/// Window the edits are counted in
+pub const WINDOW_MS: u64 = 1000;
+
+/// What to do with an edit
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Verdict {
+    Allow,
+    /// This edit went past the limit: stop now
+    Trip,
+    /// Still stopped
+    Blocked,
+}
+
+/// Edit counter (see the module doc)
+#[derive(Debug, Default)]
+pub(crate) struct Guard {
+    /// Start of the current window (host ms) and the edits in it
+    window: Option<(u64, u16)>,
+    tripped: bool,
+}
+
+impl Guard {
+    /// Count an edit made at `now` (host ms), with at most `limit` per
+    /// window (0 = no limit)
+    pub fn on_edit(&mut self, limit: u8, now: u64) -> Verdict {
+        if limit == 0 {
+            return Verdict::Allow;
+        }
+        let (start, edits) = match self.window {
+            Some((start, edits)) if now.saturating_sub(start) < WINDOW_MS => {
+                (start, edits.saturating_add(1))
+            }
+            _ => {
+                self.tripped = false;
+                (now, 1)
+            }
+        };
+        if self.tripped {
+            // Quiet for a whole window before editing again
+            self.window = Some((now, edits));
+            return Verdict::Blocked;
+        }
+        self.window = Some((start, edits));
+        if edits > limit as u16 {
+            self.tripped = true;
+            self.window = Some((now, edits));
+            return Verdict::Trip;
+        }
+        Verdict::Allow
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_guard() {
+        let mut guard = Guard::default();
+        let at = |ms: u64| 5_000 + ms;
+        for ms in 0..3 {
+            assert_eq!(guard.on_edit(3, at(ms)), Verdict::Allow);
+        }
+        assert_eq!(guard.on_edit(3, at(3)), Verdict::Trip);
+        // Each edit while stopped starts the wait over
+        assert_eq!(guard.on_edit(3, at(900)), Verdict::Blocked);
+        assert_eq!(guard.on_edit(3, at(1_500)), Verdict::Blocked);
+        assert_eq!(guard.on_edit(3, at(2_500)), Verdict::Allow);
+
+        // A new window counts from zero
+        assert_eq!(guard.on_edit(3, at(2_600)), Verdict::Allow);
+        assert_eq!(guard.on_edit(3, at(3_600)), Verdict::Allow);
+        assert_eq!(guard.on_edit(0, at(3_600)), Verdict::Allow);
+    }
+}
@@ -0,0 +1,173 @@
+//! In-process self-test
+//!
+//! `Engine::self_test()` types a fixed battery of words on fresh engines
+//! and reports each result. A host's "Report a problem" screen attaches the
+//! report: if every case passes here but the user sees wrong text, the
+//! fault is in the platform layer (event tap, injection), not the core.
+
+use super::prewarm::{self, PrewarmStatus};
+use super::Engine;
+use crate::json::Value;
+use crate::utils::{self, type_word};
+
+/// (group, method id, input, expected screen)
+const CASES: &[(&str, u8, &str, &str)] = &[
+    ("telex", 0, "vieetj ", "việt "),
+    ("telex", 0, "dduowcj ", "được "),
+    ("telex", 0, "nguoiwf ", "người "),
+    ("telex", 0, "khuyeens ", "khuyến "),
+    ("telex", 0, "hoaf ", "hoà "),
+    ("telex", 0, "giuwowngf ", "giường "),
+    ("telex", 0, "ww", "w"),
+    ("vni", 1, "vie65t ", "việt "),
+    ("vni", 1, "d9u7o7c5 ", "được "),
+    ("vni", 1, "a8n ", "ăn "),
+    ("edit", 0, "vieetj<<s ", "ví "),
+    ("edit", 0, "as<af ", "à "),
+    ("restore", 0, "vieetj\x1b", "vieetj"),
+    ("restore", 1, "vie65t\x1b", "vie65t"),
+    #[cfg(feature = "autocorrect")]
+    ("autocorrect", 0, "text ", "text "),
+    #[cfg(feature = "autocorrect")]
+    ("autocorrect", 0, "expect ", "expect "),
+    #[cfg(feature = "autocorrect")]
+    ("autocorrect", 0, "Google ", "Google "),
+];
+
+/// One typed case
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SelfTestCase {
+    pub group: &'static str,
+    /// Keys typed (`<` = Backspace, `\x1b` = Esc)
+    pub input: &'static str,
+    pub expected: &'static str,
+    /// Screen text, or `panic: <message>`
+    pub actual: String,
+}
+
+impl SelfTestCase {
+    pub fn passed(&self) -> bool {
+        self.actual == self.expected
+    }
+}
+
+/// Outcome of `Engine::self_test`
+#[derive(Clone, Debug)]
+pub struct SelfTestReport {
+    pub version: &'static str,
+    /// Compiled-in features
+    pub features: Vec<&'static str>,
+    pub prewarm: PrewarmStatus,
+    pub cases: Vec<SelfTestCase>,
+}
+
+impl SelfTestReport {
+    pub fn passed(&self) -> bool {
+        self.cases.iter().all(SelfTestCase::passed)
+    }
+
+    pub fn failures(&self) -> impl Iterator<Item = &SelfTestCase> {
+        self.cases.iter().filter(|c| !c.passed())
+    }
+
+    pub fn to_json(&self) -> Value {
+        let case = |c: &SelfTestCase| {
+            Value::Obj(vec![
+                ("group".into(), Value::Str(c.group.into())),
+                ("input".into(), Value::Str(c.input.into())),
+                ("expected".into(), Value::Str(c.expected.into())),
+                ("actual".into(), Value::Str(c.actual.clone())),
+                ("passed".into(), Value::Bool(c.passed())),
+            ])
+        };
+        Value::Obj(vec![
+            ("version".into(), Value::Str(self.version.into())),
+            (
+                "features".into(),
+                Value::Arr(
+                    self.features
+                        .iter()
+                        .map(|f| Value::Str(f.to_string()))
+                        .collect(),
+                ),
+            ),
+            (
+                "prewarm".into(),
+                Value::Str(format!("{:?}", self.prewarm).to_lowercase()),
+            ),
+            ("passed".into(), Value::Bool(self.passed())),
+            (
+                "cases".into(),
+                Value::Arr(self.cases.iter().map(case).collect()),
+            ),
+        ])
+    }
+}
+
+pub(super) fn run() -> SelfTestReport {
+    let cases = CASES
+        .iter()
+        .map(|&(group, method, input, expected)| {
+            let typed = std::panic::catch_unwind(|| {
+                let mut e = Engine::new();
+                e.set_method(method)
+                    .expect("default settings fit either method");
+                type_word(&mut e, input)
+            });
+            let actual =
+                typed.unwrap_or_else(|panic| format!("panic: {}", utils::panic_message(&*panic)));
+            SelfTestCase {
+                group,
+                input,
+                expected,
+                actual,
+            }
+        })
+        .collect();
+    SelfTestReport {
+        version: env!("CARGO_PKG_VERSION"),
+        features: features(),
+        prewarm: prewarm::status(),
+        cases,
+    }
+}
+
+fn features() -> Vec<&'static str> {
+    let mut features = Vec::new();
+    if cfg!(feature = "autocorrect") {
+        features.push("autocorrect");
+    }
+    if cfg!(feature = "macros") {
+        features.push("macros");
+    }
+    if cfg!(feature = "conversion") {
+        features.push("conversion");
+    }
+    if cfg!(feature = "suggestions") {
+        features.push("suggestions");
+    }
+    features
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_all_cases_pass() {
+        let report = run();
+        let failed: Vec<_> = report.failures().collect();
+        assert!(failed.is_empty(), "{:?}", failed);
+        assert_eq!(report.cases.len(), CASES.len());
+    }
+
+    #[test]
+    fn test_report_json() {
+        let json = run().to_json();
+        assert_eq!(json.get("passed").and_then(Value::as_bool), Some(true));
+        assert_eq!(
+            json.get("cases").and_then(Value::as_array).map(<[_]>::len),
+            Some(CASES.len())
+        );
+    }
+}
@@ -0,0 +1,222 @@
+//! Shadow Evaluation - new heuristics on real typing, without using them
+//!
+//! A heuristic under test (a reworked restore check, a new correction
+//! source) is added as a `ShadowHeuristic`. At every word boundary it
+//! gives its `Decision` next to the one the engine acted on; the output
+//! never changes. Where the two differ, the `ShadowLog` keeps the word, so
+//! a maintainer can read what flipping the default would have done to
+//! their own typing (`Engine::shadow_log`, `ime_shadow_report`).
+//!
+//! Shadow decisions face the same `min_confidence` as the real ones, and
+//! an unsure one counts as keeping the word. Words are only kept while
+//! `Privacy::retain` allows, and never more than `SHADOW_CAP` of them; the
+//! counts are always kept. Nothing leaves the process unless the host
+//! saves the report.
+//!
+//! Heuristics run on the keyboard thread, once per word: keep them cheap.
+
+use super::decision::{Decision, DecisionAction};
+use crate::json::Value;
+use std::collections::VecDeque;
+
+/// Most disagreements kept; older ones drop off
+pub const SHADOW_CAP: usize = 100;
+
+/// The word that just ended, as a shadow heuristic sees it
+#[derive(Clone, Copy, Debug)]
+pub struct ShadowWord<'a> {
+    /// As shown
+    pub word: &'a str,
+    /// As typed
+    pub keys: &'a str,
+    /// The word before it, if history is kept
+    pub prev: Option<&'a str>,
+}
+
+/// A heuristic run alongside the active ones
+pub trait ShadowHeuristic: Send {
+    /// Name in the report
+    fn name(&self) -> &str;
+
+    /// Verdict on `word`, or `None` to keep it
+    fn decide(&self, word: &ShadowWord) -> Option<Decision>;
+}
+
+/// `(name, closure)` pairs, for quick experiments
+impl<F> ShadowHeuristic for (&'static str, F)
+where
+    F: Fn(&ShadowWord) -> Option<Decision> + Send,
+{
+    fn name(&self) -> &str {
+        self.0
+    }
+
+    fn decide(&self, word: &ShadowWord) -> Option<Decision> {
+        (self.1)(word)
+    }
+}
+
+/// A word the shadow would have settled differently
+#[derive(Clone, Debug, PartialEq)]
+pub struct Disagreement {
+    pub heuristic: String,
+    pub word: String,
+    pub keys: String,
+    /// What the engine did
+    pub active: DecisionAction,
+    /// What the shadow would have done
+    pub shadow: DecisionAction,
+}
+
+/// Words seen and disagreements, per heuristic
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ShadowStats {
+    pub words: u64,
+    pub disagreements: u64,
+}
+
+/// What the shadows found so far
+#[derive(Debug, Default)]
+pub struct ShadowLog {
+    stats: Vec<(String, ShadowStats)>,
+    recent: VecDeque<Disagreement>,
+}
+
+impl ShadowLog {
+    /// Count one word for `heuristic` and keep it if the two disagree
+    /// (and `retain` allows)
+    pub(crate) fn record(
+        &mut self,
+        heuristic: &str,
+        word: &ShadowWord,
+        active: &DecisionAction,
+        shadow: &DecisionAction,
+        retain: bool,
+    ) {
+        let i = match self.stats.iter().position(|(n, _)| n == heuristic) {
+            Some(i) => i,
+            None => {
+                self.stats
+                    .push((heuristic.to_string(), ShadowStats::default()));
+                self.stats.len() - 1
+            }
+        };
+        let stats = &mut self.stats[i].1;
+        stats.words += 1;
+        if active == shadow {
+            return;
+        }
+        stats.disagreements += 1;
+        if !retain {
+            return;
+        }
+        if self.recent.len() == SHADOW_CAP {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(Disagreement {
+            heuristic: heuristic.to_string(),
+            word: word.word.to_string(),
+            keys: word.keys.to_string(),
+            active: active.clone(),
+            shadow: shadow.clone(),
+        });
+    }
+
+    pub fn stats(&self, heuristic: &str) -> Option<&ShadowStats> {
+        self.stats
+            .iter()
+            .find(|(n, _)| n == heuristic)
+            .map(|(_, s)| s)
+    }
+
+    /// Kept disagreements, oldest first
+    pub fn disagreements(&self) -> impl Iterator<Item = &Disagreement> {
+        self.recent.iter()
+    }
+
+    /// Forget the words, keep the counts (privacy turned off)
+    pub(crate) fn forget_words(&mut self) {
+        self.recent.clear();
+    }
+
+    pub fn clear(&mut self) {
+        self.stats.clear();
+        self.recent.clear();
+    }
+
+    /// `{"heuristics": [{"name", "words", "disagreements"}],
+    /// "disagreements": [{"heuristic", "word", "keys", "active", "shadow"}]}`
+    pub fn to_json(&self) -> Value {
+        let heuristics = self
+            .stats
+            .iter()
+            .map(|(name, s)| {
+                Value::Obj(vec![
+                    ("name".into(), Value::Str(name.clone())),
+                    ("words".into(), Value::Num(s.words as f64)),
+                    ("disagreements".into(), Value::Num(s.disagreements as f64)),
+                ])
+            })
+            .collect();
+        let recent = self
+            .recent
+            .iter()
+            .map(|d| {
+                Value::Obj(vec![
+                    ("heuristic".into(), Value::Str(d.heuristic.clone())),
+                    ("word".into(), Value::Str(d.word.clone())),
+                    ("keys".into(), Value::Str(d.keys.clone())),
+                    ("active".into(), Value::Str(d.active.to_string())),
+                    ("shadow".into(), Value::Str(d.shadow.to_string())),
+                ])
+            })
+            .collect();
+        Value::Obj(vec![
+            ("heuristics".into(), Value::Arr(heuristics)),
+            ("disagreements".into(), Value::Arr(recent)),
+        ])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WORD: ShadowWord = ShadowWord {
+        word: "tẽt",
+        keys: "text",
+        prev: None,
+    };
+
+    #[test]
+    fn test_record() {
+        let mut log = ShadowLog::default();
+        let keep = DecisionAction::Keep;
+        let restore = DecisionAction::Restore;
+        log.record("new", &WORD, &keep, &keep, true);
+        log.record("new", &WORD, &keep, &restore, true);
+        log.record("new", &WORD, &keep, &restore, false);
+        assert_eq!(
+            log.stats("new"),
+            Some(&ShadowStats {
+                words: 3,
+                disagreements: 2
+            })
+        );
+        assert_eq!(log.disagreements().count(), 1);
+        let d = log.disagreements().next().unwrap();
+        assert_eq!((d.word.as_str(), d.keys.as_str()), ("tẽt", "text"));
+        assert!(log
+            .to_json()
+            .to_pretty()
+            .contains("\"shadow\": \"restore\""));
+
+        for _ in 0..SHADOW_CAP + 5 {
+            log.record("new", &WORD, &keep, &restore, true);
+        }
+        assert_eq!(log.disagreements().count(), SHADOW_CAP);
+        log.forget_words();
+        assert_eq!(log.disagreements().count(), 0);
+        assert_eq!(log.stats("new").unwrap().words, SHADOW_CAP as u64 + 8);
+    }
+}
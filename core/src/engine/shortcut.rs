@@ -2,8 +2,29 @@
 //!
 //! Allows users to define shortcuts like "vn" → "Việt Nam"
 //! Shortcuts can be specific to input methods (Telex/VNI) or apply to all.
+//!
+//! A replacement may hold a caret marker, `{|}`: "ks" → "Kính gửi {|},"
+//! types "Kính gửi , " and leaves the caret before the comma
+//! (`ShortcutMatch::caret_back`), so the user fills the template in.
+//!
+//! Shortcuts expand on space; with `tab_shortcuts` on, Tab right after a
+//! trigger expands it too, without a space after the replacement. The
+//! engine follows the word through the triggers' trie as it is typed
+//! (see `trie`), so the boundary key only confirms where it ended.
+//!
+//! With the `macros` feature, `%d`, `%t` and `%n` in a replacement are
+//! filled in when it expands: the date and time from the host's clock,
+//! and a counter kept per shortcut ("bug" → "BUG-%n" types "BUG-1", then
+//! "BUG-2"). See `macros::fill_placeholders`.
+//!
+//! A trigger can get in the way of typing: `Engine::shortcut_conflicts`
+//! lists the ones spelled like a Vietnamese word or a misspelling a
+//! correction pack fixes, and those that start another trigger
+//! (`Conflict`), for the settings UI to warn about before saving.
 
 use super::buffer::MAX;
+use super::trie::LetterTrie;
+use crate::json::Value;
 use std::collections::HashMap;
 
 /// Maximum replacement length in UTF-32 codepoints (matches Result.chars array size)
@@ -11,6 +32,19 @@ use std::collections::HashMap;
 /// Note: Vietnamese characters with diacritics (ồ, ế, ẫ) count as 1 codepoint each.
 pub const MAX_REPLACEMENT_LEN: usize = MAX - 1; // -1 to leave room for trailing space
 
+/// Where the caret goes in a replacement (first one; any others are dropped)
+pub const CARET_MARKER: &str = "{|}";
+
+/// Take the caret markers out of `text`; returns how many characters
+/// follow the first one (0 if there is none)
+pub fn take_caret_marker(text: &mut String) -> usize {
+    let Some(at) = text.find(CARET_MARKER) else {
+        return 0;
+    };
+    *text = text.replace(CARET_MARKER, "");
+    text[at..].chars().count()
+}
+
 /// Input method that shortcut applies to
 #[derive(Debug, Clone, Copy, PartialEq, Default)]
 pub enum InputMethod {
@@ -56,6 +90,8 @@ pub struct Shortcut {
     pub enabled: bool,
     /// Which input method this shortcut applies to
     pub input_method: InputMethod,
+    /// Last number typed for `%n` (0 = not used yet)
+    pub counter: u32,
 }
 
 impl Shortcut {
@@ -82,6 +118,7 @@ impl Shortcut {
             case_mode: CaseMode::Exact, // Exact match, no case transformation
             enabled: true,
             input_method: InputMethod::All,
+            counter: 0,
         }
     }
 
@@ -95,6 +132,7 @@ impl Shortcut {
             case_mode: CaseMode::Exact,
             enabled: true,
             input_method: InputMethod::All,
+            counter: 0,
         }
     }
 
@@ -108,6 +146,7 @@ impl Shortcut {
             case_mode: CaseMode::Exact,
             enabled: true,
             input_method: InputMethod::Telex,
+            counter: 0,
         }
     }
 
@@ -121,6 +160,7 @@ impl Shortcut {
             case_mode: CaseMode::Exact,
             enabled: true,
             input_method: InputMethod::Vni,
+            counter: 0,
         }
     }
 
@@ -130,6 +170,11 @@ impl Shortcut {
         self
     }
 
+    /// Whether both shortcuts can be typed with the same input method
+    pub fn shares_method(&self, other: &Shortcut) -> bool {
+        self.applies_to(other.input_method) || other.applies_to(self.input_method)
+    }
+
     /// Check if shortcut applies to given input method
     ///
     /// - If shortcut is for `All`: matches any method
@@ -150,6 +195,52 @@ impl Shortcut {
     }
 }
 
+/// How a trigger gets in the way of typing
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Conflict {
+    /// The trigger is spelled like a Vietnamese word. Triggers match the
+    /// letters whatever their marks, so "an" takes ăn, ấn... too: typed
+    /// with a space, they expand instead
+    Word(String),
+    /// A misspelling a correction pack fixes ("nàm" → "làm") has the
+    /// trigger's letters: the shortcut wins
+    Correction { word: String, fixed: String },
+    /// One trigger starts the other ("vn", "vnd"); an immediate one
+    /// expands before the longer one can be typed
+    Prefix(String),
+}
+
+/// A shortcut's conflict
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ShortcutConflict {
+    pub trigger: String,
+    pub conflict: Conflict,
+}
+
+impl ShortcutConflict {
+    /// `{"trigger", "kind"}` and, by kind: "word" `{"word"}`, "correction"
+    /// `{"word", "fixed"}`, "prefix" `{"other"}`
+    pub fn to_json(&self) -> Value {
+        let mut fields = vec![("trigger".into(), Value::Str(self.trigger.clone()))];
+        match &self.conflict {
+            Conflict::Word(word) => {
+                fields.push(("kind".into(), Value::Str("word".into())));
+                fields.push(("word".into(), Value::Str(word.clone())));
+            }
+            Conflict::Correction { word, fixed } => {
+                fields.push(("kind".into(), Value::Str("correction".into())));
+                fields.push(("word".into(), Value::Str(word.clone())));
+                fields.push(("fixed".into(), Value::Str(fixed.clone())));
+            }
+            Conflict::Prefix(other) => {
+                fields.push(("kind".into(), Value::Str("prefix".into())));
+                fields.push(("other".into(), Value::Str(other.clone())));
+            }
+        }
+        Value::Obj(fields)
+    }
+}
+
 /// Shortcut match result
 #[derive(Debug)]
 pub struct ShortcutMatch {
@@ -159,6 +250,9 @@ pub struct ShortcutMatch {
     pub output: String,
     /// Whether to include the trigger key in output
     pub include_trigger_key: bool,
+    /// Characters to move the caret back once `output` is typed (the
+    /// replacement's `{|}`; 0 = stay at the end)
+    pub caret_back: usize,
 }
 
 /// Shortcut table manager
@@ -166,15 +260,15 @@ pub struct ShortcutMatch {
 pub struct ShortcutTable {
     /// Shortcuts indexed by trigger (lowercase)
     shortcuts: HashMap<String, Shortcut>,
-    /// Sorted triggers by length (longest first) for matching
-    sorted_triggers: Vec<String>,
+    /// Triggers by their letters, for following a word as it is typed
+    trie: LetterTrie<String>,
 }
 
 impl ShortcutTable {
     pub fn new() -> Self {
         Self {
             shortcuts: HashMap::new(),
-            sorted_triggers: vec![],
+            trie: LetterTrie::new(),
         }
     }
 
@@ -228,18 +322,23 @@ impl ShortcutTable {
     pub fn add(&mut self, shortcut: Shortcut) {
         let trigger = shortcut.trigger.clone();
         self.shortcuts.insert(trigger.clone(), shortcut);
-        self.rebuild_sorted_triggers();
+        self.rebuild_trie();
     }
 
     /// Remove a shortcut (exact match, case-sensitive)
     pub fn remove(&mut self, trigger: &str) -> Option<Shortcut> {
         let result = self.shortcuts.remove(trigger);
         if result.is_some() {
-            self.rebuild_sorted_triggers();
+            self.rebuild_trie();
         }
         result
     }
 
+    /// Triggers as a trie, each stored under its own letters
+    pub fn trie(&self) -> &LetterTrie<String> {
+        &self.trie
+    }
+
     /// Check if buffer matches any shortcut (for any input method)
     ///
     /// Returns (trigger, shortcut) if match found
@@ -255,17 +354,11 @@ impl ShortcutTable {
         buffer: &str,
         method: InputMethod,
     ) -> Option<(&str, &Shortcut)> {
-        // Longest-match-first, exact case-sensitive match
-        for trigger in &self.sorted_triggers {
-            if buffer == *trigger {
-                if let Some(shortcut) = self.shortcuts.get(trigger) {
-                    if shortcut.enabled && shortcut.applies_to(method) {
-                        return Some((trigger, shortcut));
-                    }
-                }
-            }
-        }
-        None
+        // Exact case-sensitive match
+        self.shortcuts
+            .get_key_value(buffer)
+            .filter(|(_, shortcut)| shortcut.enabled && shortcut.applies_to(method))
+            .map(|(trigger, shortcut)| (trigger.as_str(), shortcut))
     }
 
     /// Try to match buffer with trigger key (for any input method)
@@ -302,38 +395,66 @@ impl ShortcutTable {
         key_char: Option<char>,
         is_word_boundary: bool,
         method: InputMethod,
+    ) -> Option<ShortcutMatch> {
+        self.try_match_with(buffer, key_char, is_word_boundary, method, |sc| {
+            sc.replacement.clone()
+        })
+    }
+
+    /// `try_match_for_method`, with the replacement as `fill` gives it
+    /// (placeholders filled in); cut to `MAX_REPLACEMENT_LEN` if it grew
+    pub fn try_match_with(
+        &self,
+        buffer: &str,
+        key_char: Option<char>,
+        is_word_boundary: bool,
+        method: InputMethod,
+        fill: impl FnOnce(&Shortcut) -> String,
     ) -> Option<ShortcutMatch> {
         let (trigger, shortcut) = self.lookup_for_method(buffer, method)?;
+        if shortcut.condition == TriggerCondition::OnWordBoundary && !is_word_boundary {
+            return None;
+        }
+        let mut replacement = fill(shortcut);
+        if let Some((cut, _)) = replacement.char_indices().nth(MAX_REPLACEMENT_LEN) {
+            replacement.truncate(cut);
+        }
 
         match shortcut.condition {
             TriggerCondition::Immediate => {
-                let output = self.apply_case(buffer, &shortcut.replacement, shortcut.case_mode);
+                let mut output = self.apply_case(buffer, &replacement, shortcut.case_mode);
+                let caret_back = take_caret_marker(&mut output);
                 Some(ShortcutMatch {
                     backspace_count: trigger.len(),
                     output,
                     include_trigger_key: false,
+                    caret_back,
                 })
             }
             TriggerCondition::OnWordBoundary => {
-                if is_word_boundary {
-                    let mut output =
-                        self.apply_case(buffer, &shortcut.replacement, shortcut.case_mode);
-                    // Append the trigger key (space, etc.)
-                    if let Some(ch) = key_char {
-                        output.push(ch);
-                    }
-                    Some(ShortcutMatch {
-                        backspace_count: trigger.len(),
-                        output,
-                        include_trigger_key: true,
-                    })
-                } else {
-                    None
+                let mut output = self.apply_case(buffer, &replacement, shortcut.case_mode);
+                // Append the trigger key (space, etc.)
+                if let Some(ch) = key_char {
+                    output.push(ch);
                 }
+                let caret_back = take_caret_marker(&mut output);
+                Some(ShortcutMatch {
+                    backspace_count: trigger.len(),
+                    output,
+                    include_trigger_key: true,
+                    caret_back,
+                })
             }
         }
     }
 
+    /// Count one more use of `trigger`'s `%n`; returns the new number
+    pub fn next_count(&mut self, trigger: &str) -> Option<u32> {
+        let sc = self.shortcuts.get_mut(trigger)?;
+        sc.counter = sc.counter.saturating_add(1);
+        Some(sc.counter)
+    }
+
     /// Apply case transformation based on mode
     fn apply_case(&self, trigger: &str, replacement: &str, mode: CaseMode) -> String {
         match mode {
@@ -362,11 +483,12 @@ impl ShortcutTable {
         }
     }
 
-    /// Rebuild sorted triggers list (longest first)
-    fn rebuild_sorted_triggers(&mut self) {
-        self.sorted_triggers = self.shortcuts.keys().cloned().collect();
-        self.sorted_triggers
-            .sort_by_key(|s| std::cmp::Reverse(s.len()));
+    /// Rebuild the trie from the triggers
+    fn rebuild_trie(&mut self) {
+        self.trie = LetterTrie::new();
+        for trigger in self.shortcuts.keys() {
+            self.trie.insert(trigger, trigger.clone());
+        }
     }
 
     /// Check if shortcut table is empty
@@ -382,8 +504,127 @@ impl ShortcutTable {
     /// Clear all shortcuts
     pub fn clear(&mut self) {
         self.shortcuts.clear();
-        self.sorted_triggers.clear();
+        self.trie = LetterTrie::new();
+    }
+
+    /// Iterate shortcuts ordered by trigger
+    pub fn iter(&self) -> impl Iterator<Item = &Shortcut> {
+        let mut list: Vec<&Shortcut> = self.shortcuts.values().collect();
+        list.sort_by(|a, b| a.trigger.cmp(&b.trigger));
+        list.into_iter()
     }
+
+    /// Serialize as TSV: `trigger<TAB>replacement<TAB>flags`, one per line
+    ///
+    /// Flags (comma-separated, omitted when default):
+    /// `immediate`, `match_case`, `disabled`, `telex`, `vni`, and `n=12` for
+    /// the `%n` counter.
+    /// Tabs, newlines and backslashes in fields are escaped (`\t`, `\n`, `\\`).
+    pub fn to_tsv(&self) -> String {
+        let mut out = String::new();
+        for sc in self.iter() {
+            let mut flags = Vec::new();
+            if sc.condition == TriggerCondition::Immediate {
+                flags.push("immediate");
+            }
+            if sc.case_mode == CaseMode::MatchCase {
+                flags.push("match_case");
+            }
+            if !sc.enabled {
+                flags.push("disabled");
+            }
+            match sc.input_method {
+                InputMethod::All => {}
+                InputMethod::Telex => flags.push("telex"),
+                InputMethod::Vni => flags.push("vni"),
+            }
+            let counter = format!("n={}", sc.counter);
+            if sc.counter > 0 {
+                flags.push(&counter);
+            }
+            out.push_str(&escape_field(&sc.trigger));
+            out.push('\t');
+            out.push_str(&escape_field(&sc.replacement));
+            out.push('\t');
+            out.push_str(&flags.join(","));
+            out.push('\n');
+        }
+        out
+    }
+
+    /// Parse TSV written by `to_tsv`
+    ///
+    /// Returns the 1-based number of the first invalid line on error.
+    pub fn from_tsv(s: &str) -> Result<Self, usize> {
+        let mut table = Self::new();
+        for (i, line) in s.lines().enumerate() {
+            if line.is_empty() {
+                continue;
+            }
+            let mut fields = line.split('\t');
+            let (Some(trigger), Some(replacement)) = (fields.next(), fields.next()) else {
+                return Err(i + 1);
+            };
+            let trigger = unescape_field(trigger);
+            if trigger.is_empty() {
+                return Err(i + 1);
+            }
+            let mut sc = Shortcut::new(&trigger, &unescape_field(replacement));
+            for flag in fields
+                .next()
+                .unwrap_or("")
+                .split(',')
+                .filter(|f| !f.is_empty())
+            {
+                match flag {
+                    "immediate" => sc.condition = TriggerCondition::Immediate,
+                    "match_case" => sc.case_mode = CaseMode::MatchCase,
+                    "disabled" => sc.enabled = false,
+                    "telex" => sc.input_method = InputMethod::Telex,
+                    "vni" => sc.input_method = InputMethod::Vni,
+                    _ => {
+                        sc.counter = flag
+                            .strip_prefix("n=")
+                            .and_then(|n| n.parse().ok())
+                            .ok_or(i + 1)?
+                    }
+                }
+            }
+            table.add(sc);
+        }
+        Ok(table)
+    }
+}
+
+fn escape_field(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\t' => out.push_str("\\t"),
+            '\n' => out.push_str("\\n"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+fn unescape_field(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => out.push('\t'),
+            Some('n') => out.push('\n'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    out
 }
 
 #[cfg(test)]
@@ -503,6 +744,39 @@ mod tests {
         assert!(!m.include_trigger_key);
     }
 
+    #[test]
+    fn test_caret_marker() {
+        let table = table_with_shortcut("ks", "Kính gửi {|},");
+        let m = table.try_match("ks", Some(' '), true).unwrap();
+        assert_eq!((m.output.as_str(), m.caret_back), ("Kính gửi , ", 2));
+
+        // No marker: the caret stays at the end; only the first one counts
+        let m = table_with_shortcut("vn", "Việt Nam")
+            .try_match("vn", Some(' '), true)
+            .unwrap();
+        assert_eq!(m.caret_back, 0);
+        let mut text = "{|}a{|}b".to_string();
+        assert_eq!(take_caret_marker(&mut text), 2);
+        assert_eq!(text, "ab");
+    }
+
+    #[test]
+    fn test_trigger_trie() {
+        let mut table = ShortcutTable::new();
+        table.add(Shortcut::new("vn", "Việt Nam"));
+        table.add(Shortcut::new("vnd", "đồng"));
+        let trie = table.trie();
+        let vn = trie.walk("vn".chars()).unwrap();
+        assert_eq!(trie.values(vn), ["vn"]);
+        assert!(trie.walk("hcm".chars()).is_none());
+
+        table.remove("vn");
+        let trie = table.trie();
+        assert!(trie.values(trie.walk("vn".chars()).unwrap()).is_empty());
+        table.clear();
+        assert_eq!(table.trie().walk("vnd".chars()), None);
+    }
+
     #[test]
     fn test_word_boundary_required() {
         let table = table_with_shortcut("vn", "Việt Nam");
@@ -685,4 +959,40 @@ mod tests {
         assert_eq!(shortcut.replacement.chars().count(), 22);
         assert_eq!(shortcut.replacement, vietnamese);
     }
+
+    #[test]
+    fn test_tsv_roundtrip() {
+        let mut table = ShortcutTable::new();
+        table.add(Shortcut::new("vn", "Việt Nam"));
+        table.add(Shortcut::telex("ko", "không"));
+        let mut tricky = Shortcut::immediate("sig", "Chào\tbạn\nthân\\ái");
+        tricky.case_mode = CaseMode::MatchCase;
+        tricky.enabled = false;
+        table.add(tricky);
+        table.add(Shortcut::new("bug", "BUG-%n"));
+        assert_eq!(table.next_count("bug"), Some(1));
+        assert_eq!(table.next_count("bug"), Some(2));
+
+        let tsv = table.to_tsv();
+        assert_eq!(tsv.lines().count(), 4);
+        assert!(tsv.contains("bug\tBUG-%n\tn=2\n"));
+        let back = ShortcutTable::from_tsv(&tsv).unwrap();
+        assert_eq!(back.to_tsv(), tsv);
+
+        let sig = back.iter().find(|s| s.trigger == "sig").unwrap();
+        assert_eq!(sig.replacement, "Chào\tbạn\nthân\\ái");
+        assert_eq!(sig.condition, TriggerCondition::Immediate);
+        assert!(!sig.enabled);
+        let ko = back.iter().find(|s| s.trigger == "ko").unwrap();
+        assert_eq!(ko.input_method, InputMethod::Telex);
+        assert_eq!(back.iter().find(|s| s.trigger == "bug").unwrap().counter, 2);
+    }
+
+    #[test]
+    fn test_tsv_invalid() {
+        assert_eq!(ShortcutTable::from_tsv("vn\tViệt Nam\nbad").err(), Some(2));
+        assert_eq!(ShortcutTable::from_tsv("vn\tx\tbogus").err(), Some(1));
+        assert_eq!(ShortcutTable::from_tsv("\tx").err(), Some(1));
+        assert_eq!(ShortcutTable::from_tsv("vn\tx\tn=-1").err(), Some(1));
+    }
 }
@@ -0,0 +1,223 @@
+//! Spell Status - how the word being typed reads so far
+//!
+//! `SpellStatus` says whether the letters typed so far can still become a
+//! Vietnamese syllable, can't, or can't and lean English. Asked to
+//! (`Engine::watch_spell_status`), the engine tells observers each time it
+//! changes (`Notification::SpellStatus`), so a host can tint the preview or
+//! the menu-bar icon while the user types; FFI hosts poll it.
+//!
+//! Nothing is parsed again on each key. Every spelling the validator
+//! accepts is kept as a `LetterTrie` (`syllables`), built once: the word
+//! steps one node per letter and is Vietnamese so far while it stays in
+//! the trie ("ngh" is, "str" isn't). Once it falls out, its English lean is
+//! the average bigram score of its letter pairs (`data::bigram`), summed
+//! as the letters come, against the same `bigram_threshold` the bigram
+//! restore check uses.
+
+use super::buffer::MAX;
+use super::restore::BIGRAM_MIN_LEN;
+use super::trie::{LetterTrie, TrieCursor};
+use super::validation::{validate, ValidationResult};
+use crate::data::bigram::{EDGE, SCORES};
+use crate::data::constants::{VALID_INITIALS_1, VALID_INITIALS_2};
+use crate::data::keys;
+use std::sync::OnceLock;
+
+/// How the word being typed reads so far
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SpellStatus {
+    /// No word under way
+    #[default]
+    Empty = 0,
+    /// A Vietnamese syllable, or the start of one
+    Vietnamese = 1,
+    /// No Vietnamese syllable starts this way
+    Invalid = 2,
+    /// Not Vietnamese, and its letters lean English
+    English = 3,
+}
+
+impl SpellStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SpellStatus::Empty => "empty",
+            SpellStatus::Vietnamese => "vietnamese",
+            SpellStatus::Invalid => "invalid",
+            SpellStatus::English => "english",
+        }
+    }
+}
+
+const VOWELS: [char; 6] = ['a', 'e', 'i', 'o', 'u', 'y'];
+const FINALS: [&str; 9] = ["", "c", "m", "n", "p", "t", "ch", "ng", "nh"];
+
+/// Letters of every syllable the validator accepts, diacritics aside
+/// ("nghieng", "uoi"); built with the first engine, so no key pays for it
+pub fn syllables() -> &'static LetterTrie<()> {
+    static SYLLABLES: OnceLock<LetterTrie<()>> = OnceLock::new();
+    SYLLABLES.get_or_init(|| {
+        let letter = |k: u16| crate::utils::key_to_char(k, false).unwrap_or('?');
+        let mut initials = vec![String::new(), "ngh".to_string()];
+        initials.extend(VALID_INITIALS_1.iter().map(|&k| letter(k).to_string()));
+        initials.extend(
+            VALID_INITIALS_2
+                .iter()
+                .map(|p| p.map(letter).iter().collect()),
+        );
+        // Up to three vowels; semivowel finals (i, y, o, u) are among them
+        let mut nuclei: Vec<String> = VOWELS.iter().map(|v| v.to_string()).collect();
+        for len in 2..=3 {
+            let longer: Vec<String> = nuclei
+                .iter()
+                .filter(|n| n.len() == len - 1)
+                .flat_map(|n| VOWELS.iter().map(move |v| format!("{}{}", n, v)))
+                .collect();
+            nuclei.extend(longer);
+        }
+
+        let mut trie = LetterTrie::new();
+        let mut keys = Vec::with_capacity(MAX);
+        for initial in &initials {
+            for nucleus in &nuclei {
+                for last in FINALS {
+                    let word = format!("{}{}{}", initial, nucleus, last);
+                    keys.clear();
+                    keys.extend(word.chars().filter_map(keys::from_char));
+                    if validate(&keys) == ValidationResult::Valid {
+                        trie.insert(&word, ());
+                    }
+                }
+            }
+        }
+        trie
+    })
+}
+
+/// The word's standing, followed key by key (see the module doc)
+#[derive(Debug)]
+pub(crate) struct SpellState {
+    cursor: TrieCursor,
+    /// Letters so far, each with the bigram score summed up to it
+    lean: Vec<(char, i32)>,
+    status: SpellStatus,
+}
+
+impl Default for SpellState {
+    fn default() -> Self {
+        syllables();
+        Self {
+            cursor: TrieCursor::new(),
+            lean: Vec::with_capacity(MAX),
+            status: SpellStatus::Empty,
+        }
+    }
+}
+
+impl SpellState {
+    pub fn status(&self) -> SpellStatus {
+        self.status
+    }
+
+    /// Catch up with the word, now `letters` (lowercase `a`..`z`); the new
+    /// status if it changed
+    ///
+    /// `threshold` is the English lean, in tenths of a bit, from which a
+    /// word that isn't Vietnamese reads as English (0 = never).
+    pub fn follow<I>(&mut self, letters: I, threshold: u8) -> Option<SpellStatus>
+    where
+        I: Iterator<Item = char> + Clone,
+    {
+        let vietnamese = self.cursor.follow(syllables(), letters.clone()).is_some();
+        let (mut depth, mut sum, mut prev) = (0, 0, EDGE);
+        for c in letters {
+            let cur = (c as usize).wrapping_sub('a' as usize).min(EDGE);
+            match self.lean.get(depth) {
+                Some(&(followed, summed)) if followed == c => sum = summed,
+                _ => {
+                    self.lean.truncate(depth);
+                    sum += SCORES[prev][cur] as i32;
+                    if self.lean.len() < MAX {
+                        self.lean.push((c, sum));
+                    }
+                }
+            }
+            prev = cur;
+            depth += 1;
+        }
+        self.lean.truncate(depth);
+
+        let status = if depth == 0 {
+            SpellStatus::Empty
+        } else if vietnamese {
+            SpellStatus::Vietnamese
+        } else if threshold > 0 && depth >= BIGRAM_MIN_LEN && sum / depth as i32 >= threshold as i32
+        {
+            SpellStatus::English
+        } else {
+            SpellStatus::Invalid
+        };
+        self.set(status)
+    }
+
+    /// Between words; the new status if it changed
+    pub fn reset(&mut self) -> Option<SpellStatus> {
+        self.cursor.reset();
+        self.lean.clear();
+        self.set(SpellStatus::Empty)
+    }
+
+    fn set(&mut self, status: SpellStatus) -> Option<SpellStatus> {
+        (status != self.status).then(|| {
+            self.status = status;
+            status
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(word: &str) -> SpellStatus {
+        let mut state = SpellState::default();
+        state.follow(word.chars(), 15);
+        state.status()
+    }
+
+    #[test]
+    fn test_syllables() {
+        let trie = syllables();
+        let is_syllable = |w: &str| {
+            trie.walk(w.chars())
+                .is_some_and(|n| !trie.values(n).is_empty())
+        };
+        for word in ["a", "nghieng", "truong", "khuyu", "gia", "quy", "uoi"] {
+            assert!(is_syllable(word), "{}", word);
+        }
+        for word in ["ngh", "ka", "str", "bl"] {
+            assert!(!is_syllable(word), "{}", word);
+        }
+        assert!(trie.walk("ngh".chars()).is_some());
+        assert!(trie.walk("cy".chars()).is_none());
+    }
+
+    #[test]
+    fn test_status() {
+        assert_eq!(status(""), SpellStatus::Empty);
+        assert_eq!(status("ngh"), SpellStatus::Vietnamese);
+        assert_eq!(status("truong"), SpellStatus::Vietnamese);
+        assert_eq!(status("bl"), SpellStatus::Invalid);
+        assert_eq!(status("through"), SpellStatus::English);
+
+        // Followed letter by letter, and back
+        let mut state = SpellState::default();
+        let mut seen = vec![];
+        for word in ["w", "wi", "win", "wind", "windo", "window", "windo", ""] {
+            seen.extend(state.follow(word.chars(), 15));
+        }
+        assert_eq!(seen.first(), Some(&SpellStatus::Invalid));
+        assert!(seen.contains(&SpellStatus::English));
+        assert_eq!(seen.last(), Some(&SpellStatus::Empty));
+        assert_eq!(state.reset(), None);
+    }
+}
@@ -9,17 +9,59 @@
 use crate::data::constants;
 use crate::data::keys;
 
+/// Run of consecutive buffer indices (`start..end`)
+///
+/// Syllable parts are always contiguous, so parsing needs no allocation.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// Extend by the next index (starts the span if empty)
+    pub fn push(&mut self, i: usize) {
+        if self.is_empty() {
+            *self = Span::new(i, i + 1);
+        } else {
+            debug_assert_eq!(i, self.end, "syllable parts are contiguous");
+            self.end = i + 1;
+        }
+    }
+
+    pub fn iter(&self) -> std::ops::Range<usize> {
+        self.start..self.end
+    }
+
+    /// The keys this span covers
+    pub fn keys<'a>(&self, keys: &'a [u16]) -> &'a [u16] {
+        &keys[self.start..self.end]
+    }
+}
+
 /// Parsed syllable structure
 #[derive(Debug, Clone, Default)]
 pub struct Syllable {
     /// Initial consonant indices in buffer
-    pub initial: Vec<usize>,
+    pub initial: Span,
     /// Glide/medial index (o in "hoa", u in "qua")
     pub glide: Option<usize>,
     /// Vowel nucleus indices
-    pub vowel: Vec<usize>,
+    pub vowel: Span,
     /// Final consonant indices
-    pub final_c: Vec<usize>,
+    pub final_c: Span,
 }
 
 impl Syllable {
@@ -167,7 +209,7 @@ fn match_final(keys: &[u16], start: usize, syllable: &mut Syllable) {
     if remaining >= 2 {
         for pattern in FINALS_2 {
             if keys[start] == pattern[0] && keys[start + 1] == pattern[1] {
-                syllable.final_c = vec![start, start + 1];
+                syllable.final_c = Span::new(start, start + 2);
                 return;
             }
         }
@@ -175,7 +217,7 @@ fn match_final(keys: &[u16], start: usize, syllable: &mut Syllable) {
 
     // Try 1-char finals
     if remaining >= 1 && constants::VALID_FINALS_1.contains(&keys[start]) {
-        syllable.final_c = vec![start];
+        syllable.final_c = Span::new(start, start + 1);
     }
 }
 
@@ -3,9 +3,9 @@
 //! Pattern-based transformation for Vietnamese diacritics.
 //! Scans entire buffer instead of case-by-case processing.
 
-use super::buffer::Buffer;
+use super::buffer::{Buffer, Char, MAX};
 use crate::data::{
-    chars::{mark, tone},
+    chars::{self, mark, tone},
     keys,
     vowel::Phonology,
 };
@@ -178,6 +178,30 @@ pub fn apply_mark(buf: &mut Buffer, mark_value: u8, modern: bool) -> TransformRe
     TransformResult::none()
 }
 
+/// `word` with its tone mark where the modern or classic style puts it
+/// ("hòa" ↔ "hoà", "thúy" ↔ "thuý"), by the same rules as typing
+///
+/// `None` unless `word` is letters with exactly one mark.
+pub fn place_mark(word: &str, modern: bool) -> Option<String> {
+    let mut buf = Buffer::new();
+    let mut mark_value = mark::NONE;
+    for (i, ch) in word.chars().enumerate() {
+        let p = chars::parse_char(ch)?;
+        if i == MAX || (p.mark != mark::NONE && mark_value != mark::NONE) {
+            return None;
+        }
+        mark_value = mark_value.max(p.mark);
+        let mut c = Char::new(p.key, p.caps);
+        c.tone = p.tone;
+        c.stroke = p.stroke;
+        buf.push(c);
+    }
+    if mark_value == mark::NONE || !apply_mark(&mut buf, mark_value, modern).applied {
+        return None;
+    }
+    Some(buf.to_display_string())
+}
+
 /// Apply stroke transformation (d → đ)
 ///
 /// Scans buffer for 'd' at any position
@@ -303,7 +327,6 @@ fn reposition_mark_if_needed(buf: &mut Buffer) {
 
 #[cfg(test)]
 mod tests {
-    use super::super::buffer::Char;
     use super::*;
 
     fn setup_buffer(s: &str) -> Buffer {
@@ -362,4 +385,19 @@ mod tests {
         assert_eq!(buf.get(1).unwrap().tone, tone::HORN); // u
         assert_eq!(buf.get(2).unwrap().tone, tone::HORN); // o
     }
+
+    #[test]
+    fn test_place_mark() {
+        for (modern, classic) in [("hoà", "hòa"), ("Thuý", "Thúy"), ("KHOẺ", "KHỎE")] {
+            assert_eq!(place_mark(classic, true).as_deref(), Some(modern));
+            assert_eq!(place_mark(modern, false).as_deref(), Some(classic));
+        }
+        // Same in both styles
+        assert_eq!(place_mark("hoàn", false).as_deref(), Some("hoàn"));
+        assert_eq!(place_mark("quá", false).as_deref(), Some("quá"));
+        // Unmarked, two marks, not letters
+        assert_eq!(place_mark("hoa", true), None);
+        assert_eq!(place_mark("hòá", true), None);
+        assert_eq!(place_mark("hòa!", true), None);
+    }
 }
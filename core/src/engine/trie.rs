@@ -0,0 +1,235 @@
+//! Letter Trie - matching a word while it is typed
+//!
+//! Shortcut triggers (`ShortcutTable::trie`) and the misspellings of the
+//! built-in correction packs (`correction::typos`) are both kept as a
+//! `LetterTrie`, keyed by the letters the keys typed, diacritics aside
+//! (`Buffer::letters`: "mún" is under "mun").
+//!
+//! A `TrieCursor` follows the word being typed one keystroke at a time:
+//! a new letter is one `step`, and a tone or mark key, which changes no
+//! letter, is none. When the word ends, the node it reached is all there
+//! is to check, with no string built to look the word up; mid-word, the
+//! same node tells the host the word is a known typo (`Engine::typo`).
+
+use super::buffer::MAX;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// A node of a `LetterTrie`
+pub type Node = u32;
+
+/// Where every word starts
+pub const ROOT: Node = 0;
+
+/// Tells tries apart, so a cursor notices when the one it followed was
+/// rebuilt
+static NEXT_ID: AtomicU32 = AtomicU32::new(0);
+
+/// Words letter by letter, each with the values stored under it
+#[derive(Debug)]
+pub struct LetterTrie<T> {
+    id: u32,
+    nodes: Vec<TrieNode<T>>,
+}
+
+#[derive(Debug)]
+struct TrieNode<T> {
+    /// Sorted by letter
+    children: Vec<(char, Node)>,
+    /// Of the word ending here
+    values: Vec<T>,
+}
+
+impl<T> TrieNode<T> {
+    fn new() -> Self {
+        Self {
+            children: vec![],
+            values: vec![],
+        }
+    }
+}
+
+impl<T> Default for LetterTrie<T> {
+    fn default() -> Self {
+        Self {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            nodes: vec![TrieNode::new()],
+        }
+    }
+}
+
+impl<T> LetterTrie<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Store `value` under `letters`; values under the same letters keep
+    /// the order they were inserted in. Nothing is stored at the root.
+    pub fn insert(&mut self, letters: &str, value: T) {
+        if letters.is_empty() {
+            return;
+        }
+        let mut node = ROOT;
+        for c in letters.chars() {
+            let next = self.nodes.len() as Node;
+            let children = &mut self.nodes[node as usize].children;
+            node = match children.binary_search_by_key(&c, |&(k, _)| k) {
+                Ok(i) => children[i].1,
+                Err(i) => {
+                    children.insert(i, (c, next));
+                    self.nodes.push(TrieNode::new());
+                    next
+                }
+            };
+        }
+        self.nodes[node as usize].values.push(value);
+    }
+
+    /// The node after `c`, if some word goes on that way
+    pub fn step(&self, node: Node, c: char) -> Option<Node> {
+        let children = &self.nodes.get(node as usize)?.children;
+        let i = children.binary_search_by_key(&c, |&(k, _)| k).ok()?;
+        Some(children[i].1)
+    }
+
+    /// The node `letters` lead to from the root
+    pub fn walk(&self, letters: impl IntoIterator<Item = char>) -> Option<Node> {
+        letters
+            .into_iter()
+            .try_fold(ROOT, |node, c| self.step(node, c))
+    }
+
+    /// Values of the word ending at `node` (empty if none does)
+    pub fn values(&self, node: Node) -> &[T] {
+        self.nodes.get(node as usize).map_or(&[], |n| &n.values)
+    }
+}
+
+/// Where a word being typed stands in a `LetterTrie`
+///
+/// Keeps the letters followed so far with their nodes, so each key only
+/// steps past what changed. Never allocates once created.
+#[derive(Debug)]
+pub struct TrieCursor {
+    /// The trie followed (its id), `u32::MAX` for none yet
+    trie: u32,
+    path: Vec<(char, Node)>,
+    /// Node of the whole word, None once it left the trie
+    node: Option<Node>,
+}
+
+impl Default for TrieCursor {
+    fn default() -> Self {
+        Self {
+            trie: u32::MAX,
+            path: Vec::with_capacity(MAX),
+            node: None,
+        }
+    }
+}
+
+impl TrieCursor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Catch up with the word, now `letters`: the part followed before is
+    /// kept as far as it still matches, the rest is stepped through
+    pub fn follow<T>(
+        &mut self,
+        trie: &LetterTrie<T>,
+        letters: impl IntoIterator<Item = char>,
+    ) -> Option<Node> {
+        if self.trie != trie.id {
+            self.trie = trie.id;
+            self.path.clear();
+        }
+        let mut node = ROOT;
+        let mut depth = 0;
+        for c in letters {
+            match self.path.get(depth) {
+                Some(&(followed, next)) if followed == c => node = next,
+                _ => {
+                    self.path.truncate(depth);
+                    let Some(next) = trie.step(node, c).filter(|_| depth < MAX) else {
+                        self.node = None;
+                        return None;
+                    };
+                    self.path.push((c, next));
+                    node = next;
+                }
+            }
+            depth += 1;
+        }
+        self.path.truncate(depth);
+        self.node = Some(node);
+        self.node
+    }
+
+    /// Whether the cursor follows `trie` (not another, or an older build
+    /// of it)
+    pub fn follows<T>(&self, trie: &LetterTrie<T>) -> bool {
+        self.trie == trie.id
+    }
+
+    /// Node the word reached at the last `follow`
+    pub fn node(&self) -> Option<Node> {
+        self.node
+    }
+
+    /// Back to the root, for a new word
+    pub fn reset(&mut self) {
+        self.path.clear();
+        self.node = Some(ROOT);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trie() -> LetterTrie<&'static str> {
+        let mut trie = LetterTrie::new();
+        trie.insert("vn", "Việt Nam");
+        trie.insert("vnd", "đồng");
+        trie.insert("mun", "muốn");
+        trie.insert("mun", "mún");
+        trie
+    }
+
+    #[test]
+    fn test_walk() {
+        let trie = trie();
+        let v = trie.step(ROOT, 'v').unwrap();
+        assert!(trie.values(v).is_empty());
+        assert_eq!(trie.values(trie.step(v, 'n').unwrap()), ["Việt Nam"]);
+        assert_eq!(
+            trie.values(trie.walk("mun".chars()).unwrap()),
+            ["muốn", "mún"]
+        );
+        assert_eq!(trie.walk("vnx".chars()), None);
+        assert_eq!(trie.walk("VN".chars()), None);
+        assert!(trie.values(ROOT).is_empty());
+    }
+
+    #[test]
+    fn test_cursor() {
+        let trie = trie();
+        let mut cursor = TrieCursor::new();
+        assert!(!cursor.follows(&trie));
+
+        // Letter by letter, and back after a backspace
+        let vn = cursor.follow(&trie, "vn".chars()).unwrap();
+        assert_eq!(trie.values(vn), ["Việt Nam"]);
+        assert!(cursor.follow(&trie, "vnd".chars()).is_some());
+        assert_eq!(cursor.follow(&trie, "vn".chars()), Some(vn));
+        assert_eq!(cursor.follow(&trie, "vnx".chars()), None);
+        assert_eq!(cursor.node(), None);
+        // A letter changed earlier in the word
+        assert!(cursor.follow(&trie, "mun".chars()).is_some());
+
+        // Another trie: the path followed means nothing there
+        let other = LetterTrie::<()>::new();
+        assert_eq!(cursor.follow(&other, "m".chars()), None);
+        assert!(cursor.follows(&other) && !cursor.follows(&trie));
+    }
+}
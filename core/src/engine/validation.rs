@@ -4,6 +4,7 @@
 //! Each rule is a simple function that returns Some(error) if invalid, None if OK.
 
 use super::syllable::{parse, Syllable};
+use crate::data::chars::{self, mark, tone};
 use crate::data::constants;
 use crate::data::keys;
 
@@ -59,7 +60,7 @@ fn rule_valid_initial(keys: &[u16], syllable: &Syllable) -> Option<ValidationRes
         return None; // No initial = starts with vowel, OK
     }
 
-    let initial: Vec<u16> = syllable.initial.iter().map(|&i| keys[i]).collect();
+    let initial = syllable.initial.keys(keys);
 
     let is_valid = match initial.len() {
         1 => constants::VALID_INITIALS_1.contains(&initial[0]),
@@ -95,8 +96,8 @@ fn rule_spelling(keys: &[u16], syllable: &Syllable) -> Option<ValidationResult>
         return None;
     }
 
-    let initial: Vec<u16> = syllable.initial.iter().map(|&i| keys[i]).collect();
-    let first_vowel = keys[syllable.glide.unwrap_or(syllable.vowel[0])];
+    let initial = syllable.initial.keys(keys);
+    let first_vowel = keys[syllable.glide.unwrap_or(syllable.vowel.start)];
 
     // Check all spelling rules
     for &(consonant, vowels, _msg) in constants::SPELLING_RULES {
@@ -114,7 +115,7 @@ fn rule_valid_final(keys: &[u16], syllable: &Syllable) -> Option<ValidationResul
         return None;
     }
 
-    let final_c: Vec<u16> = syllable.final_c.iter().map(|&i| keys[i]).collect();
+    let final_c = syllable.final_c.keys(keys);
 
     let is_valid = match final_c.len() {
         1 => constants::VALID_FINALS_1.contains(&final_c[0]),
@@ -149,7 +150,7 @@ fn rule_valid_vowel_pattern(keys: &[u16], syllable: &Syllable) -> Option<Validat
     }
 
     // Get consecutive vowel keys
-    let vowels: Vec<u16> = syllable.vowel.iter().map(|&i| keys[i]).collect();
+    let vowels = syllable.vowel.keys(keys);
 
     // Check each consecutive vowel pair against valid combinations
     for i in 0..vowels.len() - 1 {
@@ -200,6 +201,23 @@ pub fn is_valid(buffer_keys: &[u16]) -> bool {
     validate(buffer_keys).is_valid()
 }
 
+/// Final c, ch, p or t: the syllable takes only sắc or nặng (học, hát)
+fn is_stop_final(final_c: &[u16]) -> bool {
+    matches!(
+        final_c,
+        [keys::C] | [keys::C, keys::H] | [keys::P] | [keys::T]
+    )
+}
+
+/// Whether tone mark `mark_val` can go on the syllable in the buffer
+///
+/// Huyền, hỏi and ngã never go on a stop final: "hòp" is not a word.
+/// No mark is fine too, since the word may still be in progress.
+pub fn allows_mark(buffer_keys: &[u16], mark_val: u8) -> bool {
+    !matches!(mark_val, mark::HUYEN | mark::HOI | mark::NGA)
+        || !is_stop_final(parse(buffer_keys).final_c.keys(buffer_keys))
+}
+
 /// Check if the buffer shows patterns that suggest foreign word input.
 ///
 /// This is a heuristic to detect when the user is likely typing a foreign word
@@ -213,7 +231,7 @@ pub fn is_foreign_word_pattern(buffer_keys: &[u16], modifier_key: u16) -> bool {
 
     // Check 1: Invalid vowel patterns in current buffer (using inclusion approach)
     if syllable.vowel.len() >= 2 {
-        let vowels: Vec<u16> = syllable.vowel.iter().map(|&i| buffer_keys[i]).collect();
+        let vowels = syllable.vowel.keys(buffer_keys);
         for i in 0..vowels.len() - 1 {
             let pair = [vowels[i], vowels[i + 1]];
             // Check if pair is NOT in valid pairs list
@@ -231,7 +249,7 @@ pub fn is_foreign_word_pattern(buffer_keys: &[u16], modifier_key: u16) -> bool {
     // - Buffer has a final consonant (T, P, or C)
     // - Modifier key is R (forms T+R, P+R, C+R clusters common in English)
     if modifier_key == keys::R && syllable.final_c.len() == 1 && !syllable.initial.is_empty() {
-        let final_key = buffer_keys[syllable.final_c[0]];
+        let final_key = buffer_keys[syllable.final_c.start];
         if matches!(final_key, keys::T | keys::P | keys::C) {
             return true;
         }
@@ -245,8 +263,8 @@ pub fn is_foreign_word_pattern(buffer_keys: &[u16], modifier_key: u16) -> bool {
         && syllable.vowel.len() == 1
         && syllable.final_c.is_empty()
     {
-        let initial = buffer_keys[syllable.initial[0]];
-        let vowel = buffer_keys[syllable.vowel[0]];
+        let initial = buffer_keys[syllable.initial.start];
+        let vowel = buffer_keys[syllable.vowel.start];
 
         // Common English prefixes: de-, re-, pre-
         // "de" + 's' → describe, destroy, design, desk
@@ -260,6 +278,204 @@ pub fn is_foreign_word_pattern(buffer_keys: &[u16], modifier_key: u16) -> bool {
     false
 }
 
+// =============================================================================
+// EXPLAIN - validation of a finished word, with the reason
+// =============================================================================
+
+/// Why a word is not a valid Vietnamese syllable
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Problem {
+    Empty,
+    /// A character that is not a letter of the alphabet
+    NotALetter(char),
+    NoVowel,
+    InvalidInitial,
+    /// c/k, g/gh or ng/ngh used before the wrong vowel
+    InvalidSpelling,
+    InvalidFinal,
+    InvalidVowelPattern,
+    /// Vowels and final that never go together (tiê, ăi, inh vs ing)
+    ImpossibleRhyme,
+    /// More than one letter carries a tone mark
+    MultipleMarks,
+    /// Final c, ch, p, t takes only sắc or nặng
+    StopFinalTone,
+}
+
+impl Problem {
+    /// Short English explanation, for "why was my word changed?"
+    pub fn message(&self) -> &'static str {
+        match self {
+            Problem::Empty => "empty word",
+            Problem::NotALetter(_) => "contains a character that is not a letter",
+            Problem::NoVowel => "has no vowel",
+            Problem::InvalidInitial => "not a Vietnamese initial consonant",
+            Problem::InvalidSpelling => "c/k, g/gh or ng/ngh before the wrong vowel",
+            Problem::InvalidFinal => "not a Vietnamese final consonant",
+            Problem::InvalidVowelPattern => "vowels that never go together",
+            Problem::ImpossibleRhyme => "vowels and final that never go together",
+            Problem::MultipleMarks => "more than one tone mark",
+            Problem::StopFinalTone => "a word ending in c, ch, p or t takes only sắc or nặng",
+        }
+    }
+}
+
+impl From<ValidationResult> for Problem {
+    fn from(result: ValidationResult) -> Self {
+        match result {
+            ValidationResult::InvalidInitial => Problem::InvalidInitial,
+            ValidationResult::InvalidFinal => Problem::InvalidFinal,
+            ValidationResult::InvalidSpelling => Problem::InvalidSpelling,
+            ValidationResult::InvalidVowelPattern => Problem::InvalidVowelPattern,
+            ValidationResult::NoVowel | ValidationResult::Valid => Problem::NoVowel,
+        }
+    }
+}
+
+/// What `explain` found out about a word
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyllableReport {
+    /// Initial consonant as written ("ngh", "qu", "" for none)
+    pub initial: String,
+    /// Everything after the initial, as written
+    pub rhyme: String,
+    /// Tone mark (`mark::*`)
+    pub mark: u8,
+    /// `None` for a valid syllable
+    pub problem: Option<Problem>,
+}
+
+impl SyllableReport {
+    pub fn is_valid(&self) -> bool {
+        self.problem.is_none()
+    }
+}
+
+/// Check whether `word` is a valid Vietnamese syllable, and say why not
+///
+/// Unlike `validate`, which sees raw keys mid-typing, this takes the
+/// finished word with its diacritics and tone mark, so it also checks the
+/// rhyme (tiê, ăi) and that stop finals carry sắc or nặng. Decomposed
+/// input is composed first.
+pub fn explain(word: &str) -> SyllableReport {
+    let mut report = SyllableReport {
+        initial: String::new(),
+        rhyme: String::new(),
+        mark: mark::NONE,
+        problem: None,
+    };
+    let chars: Vec<char> = chars::compose(word).chars().collect();
+    report.problem = check_word(&chars, &mut report);
+    report
+}
+
+fn check_word(chars: &[char], report: &mut SyllableReport) -> Option<Problem> {
+    if chars.is_empty() {
+        return Some(Problem::Empty);
+    }
+    let mut parsed = Vec::with_capacity(chars.len());
+    for &c in chars {
+        match chars::parse_char(c) {
+            Some(p) => parsed.push(p),
+            None => return Some(Problem::NotALetter(c)),
+        }
+    }
+    let buffer_keys: Vec<u16> = parsed.iter().map(|p| p.key).collect();
+    let syllable = parse(&buffer_keys);
+    let split = syllable.initial.end;
+    report.initial = chars[..split].iter().collect();
+    report.rhyme = chars[split..].iter().collect();
+
+    let mut marks = parsed.iter().filter(|p| p.mark != mark::NONE);
+    report.mark = marks.next().map_or(mark::NONE, |p| p.mark);
+    if marks.next().is_some() {
+        return Some(Problem::MultipleMarks);
+    }
+
+    let result = validate(&buffer_keys);
+    if !result.is_valid() {
+        return Some(result.into());
+    }
+    if !is_possible_rhyme(&parsed, &syllable) {
+        return Some(Problem::ImpossibleRhyme);
+    }
+
+    if is_stop_final(syllable.final_c.keys(&buffer_keys))
+        && !matches!(report.mark, mark::SAC | mark::NANG)
+    {
+        return Some(Problem::StopFinalTone);
+    }
+    None
+}
+
+/// Rhyme rules the raw keys can't show: they depend on diacritics
+fn is_possible_rhyme(parsed: &[chars::Parsed], syllable: &Syllable) -> bool {
+    let start = syllable.glide.unwrap_or(syllable.vowel.start);
+    let nucleus: Vec<(u16, u8)> = parsed[start..syllable.vowel.end]
+        .iter()
+        .map(|p| (p.key, p.tone))
+        .collect();
+    let final_c: Vec<u16> = syllable.final_c.iter().map(|i| parsed[i].key).collect();
+    let has_final = !final_c.is_empty();
+
+    for (i, &(key, t)) in nucleus.iter().enumerate() {
+        let next = nucleus.get(i + 1).copied();
+        match (key, t) {
+            // ă closes with a consonant: ăn, ắt (never ă, ăi)
+            (keys::A, tone::HORN) if next.is_some() || !has_final => return false,
+            // â closes with a consonant, u or y: ân, âu, ây
+            (keys::A, tone::CIRCUMFLEX) => match next {
+                Some((keys::U | keys::Y, tone::NONE)) => {}
+                Some(_) => return false,
+                None if !has_final => return false,
+                None => {}
+            },
+            // ie, ye, uo are only written iê, yê, uô/ươ/uơ
+            (keys::I | keys::Y, _) if next == Some((keys::E, tone::NONE)) => return false,
+            (keys::U, tone::NONE) if next == Some((keys::O, tone::NONE)) => return false,
+            (keys::U, tone::HORN) if matches!(next, Some((keys::O, t)) if t != tone::HORN) => {
+                return false
+            }
+            _ => {}
+        }
+    }
+
+    // iê, yê, uô, ươ need something after them (tiên, yêu, cuối, mười);
+    // ia, ua, ưa take nothing after them (mía, múa, mưa)
+    let last = nucleus.len() - 1;
+    if last > 0 {
+        let open_only = matches!(
+            (nucleus[last - 1], nucleus[last]),
+            (
+                (keys::I | keys::U, tone::NONE) | (keys::U, tone::HORN),
+                (keys::A, tone::NONE)
+            )
+        );
+        let closed_only = matches!(
+            (nucleus[last - 1], nucleus[last]),
+            ((keys::I | keys::Y, _), (keys::E, tone::CIRCUMFLEX))
+                | ((keys::U, tone::NONE), (keys::O, tone::CIRCUMFLEX))
+                | ((keys::U, tone::HORN), (keys::O, tone::HORN))
+        );
+        if (open_only && has_final) || (closed_only && !has_final) {
+            return false;
+        }
+    }
+
+    // ch, nh follow a, ê, i, y; c, ng never follow i, y (ích, inh)
+    let (key, t) = nucleus[last];
+    match final_c.as_slice() {
+        [keys::C | keys::N, keys::H] => {
+            matches!(
+                (key, t),
+                (keys::A, tone::NONE) | (keys::E, tone::CIRCUMFLEX) | (keys::I | keys::Y, _)
+            )
+        }
+        [keys::C] | [keys::N, keys::G] => !matches!(key, keys::I | keys::Y),
+        _ => true,
+    }
+}
+
 // =============================================================================
 // TESTS
 // =============================================================================
@@ -323,4 +539,85 @@ mod tests {
     fn test_invalid_foreign() {
         assert_all_invalid(INVALID_FOREIGN);
     }
+
+    #[test]
+    fn test_allows_mark() {
+        for w in ["hop", "hoc", "sach", "mat"] {
+            let k = keys_from_str(w);
+            assert!(allows_mark(&k, mark::SAC) && allows_mark(&k, mark::NANG));
+            assert!(!allows_mark(&k, mark::HUYEN), "'{}'", w);
+            assert!(!allows_mark(&k, mark::HOI) && !allows_mark(&k, mark::NGA));
+        }
+        assert!(allows_mark(&keys_from_str("hong"), mark::HOI));
+        assert!(allows_mark(&keys_from_str("ho"), mark::HUYEN));
+    }
+
+    #[test]
+    fn test_explain_valid() {
+        for w in [
+            "ba",
+            "Việt",
+            "nghiêng",
+            "quyển",
+            "giường",
+            "tiên",
+            "yêu",
+            "mưa",
+            "thuở",
+            "ăn",
+            "ấy",
+            "ếch",
+            "thích",
+            "bạn",
+            "tuyết",
+            "hoà",
+            "rượu",
+            "đặt",
+            // Decomposed
+            "Vie\u{0323}\u{0302}t",
+            "ngu\u{031B}o\u{031B}\u{0300}i",
+        ] {
+            let report = explain(w);
+            assert!(report.is_valid(), "'{}': {:?}", w, report.problem);
+        }
+    }
+
+    #[test]
+    fn test_explain_problems() {
+        let cases = [
+            ("", Some(Problem::Empty)),
+            ("ba1", Some(Problem::NotALetter('1'))),
+            ("bcd", Some(Problem::NoVowel)),
+            ("clau", Some(Problem::InvalidInitial)),
+            ("ka", Some(Problem::InvalidSpelling)),
+            ("beach", Some(Problem::InvalidVowelPattern)),
+            ("tiê", Some(Problem::ImpossibleRhyme)),
+            ("tien", Some(Problem::ImpossibleRhyme)),
+            ("ăi", Some(Problem::ImpossibleRhyme)),
+            ("mưan", Some(Problem::ImpossibleRhyme)),
+            ("inh", None),
+            ("ing", Some(Problem::ImpossibleRhyme)),
+            ("ôch", Some(Problem::ImpossibleRhyme)),
+            ("áà", Some(Problem::MultipleMarks)),
+            ("mát", None),
+            ("màt", Some(Problem::StopFinalTone)),
+            ("mach", Some(Problem::StopFinalTone)),
+        ];
+        for (w, problem) in cases {
+            assert_eq!(explain(w).problem, problem, "'{}'", w);
+        }
+    }
+
+    #[test]
+    fn test_explain_parts() {
+        let report = explain("Nghiêng");
+        assert_eq!(report.initial, "Ngh");
+        assert_eq!(report.rhyme, "iêng");
+        assert_eq!(report.mark, mark::NONE);
+
+        let report = explain("quyển");
+        assert_eq!(report.initial, "qu");
+        assert_eq!(report.rhyme, "yển");
+        assert_eq!(report.mark, mark::HOI);
+    }
 }
@@ -0,0 +1,511 @@
+//! UTF-16 FFI for Windows hosts
+//!
+//! C# P/Invoke marshals `string` as UTF-16 (`LPWStr`) and can't free
+//! memory the core allocated, so every call here that takes or returns
+//! text has a `_w` twin of the UTF-8 call in `lib.rs`:
+//!
+//! - Arguments are null-terminated UTF-16 (null passes through as null).
+//!   Unpaired surrogates are rejected like invalid UTF-8.
+//! - Returned strings are null-terminated UTF-16, freed with
+//!   `ime_free_wstring` (never `ime_free_string` or `Marshal.FreeHGlobal`).
+//!
+//! `ime_key_into` fills a caller-owned `Result` so the hot path needs no
+//! free at all; `[StructLayout(Sequential)]` with a 64-element
+//! `ByValArray` maps it directly.
+//!
+//! ```csharp
+//! [DllImport("gonhanh_core.dll")]
+//! static extern IntPtr ime_config_w();
+//! [DllImport("gonhanh_core.dll")]
+//! static extern void ime_free_wstring(IntPtr s);
+//!
+//! IntPtr p = ime_config_w();
+//! string toml = Marshal.PtrToStringUni(p);
+//! ime_free_wstring(p);
+//! ```
+
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+
+use super::*;
+
+/// Hand a string returned by a UTF-8 call to the host as UTF-16
+///
+/// Takes ownership of `s` (frees it with `ime_free_string`).
+unsafe fn into_wide(s: *mut c_char) -> *mut u16 {
+    if s.is_null() {
+        return std::ptr::null_mut();
+    }
+    let wide: Vec<u16> = CStr::from_ptr(s)
+        .to_string_lossy()
+        .encode_utf16()
+        .chain(std::iter::once(0))
+        .collect();
+    ime_free_string(s);
+    Box::into_raw(wide.into_boxed_slice()) as *mut u16
+}
+
+/// Length of a null-terminated UTF-16 string, in units
+unsafe fn wide_len(s: *const u16) -> usize {
+    let mut len = 0;
+    while *s.add(len) != 0 {
+        len += 1;
+    }
+    len
+}
+
+/// UTF-8 copy of a UTF-16 argument: `Ok(None)` for null, `Err` for
+/// unpaired surrogates (reported through `ime_last_error`)
+unsafe fn from_wide(s: *const u16) -> std::result::Result<Option<CString>, ()> {
+    if s.is_null() {
+        return Ok(None);
+    }
+    let units = std::slice::from_raw_parts(s, wide_len(s));
+    match String::from_utf16(units) {
+        // No interior NUL: the scan stopped at the first one
+        Ok(text) => Ok(CString::new(text).ok()),
+        Err(_) => {
+            set_last_error("string is not valid UTF-16".into());
+            Err(())
+        }
+    }
+}
+
+/// Run a UTF-8 call on the converted argument, or return `invalid`
+unsafe fn with_utf8<T>(s: *const u16, invalid: T, f: impl FnOnce(*const c_char) -> T) -> T {
+    match from_wide(s) {
+        Ok(text) => f(text.as_ref().map_or(std::ptr::null(), |t| t.as_ptr())),
+        Err(()) => invalid,
+    }
+}
+
+/// Free a string returned by a `_w` function.
+///
+/// # Safety
+/// `s` must come from a `_w` function (or be null), freed once.
+#[no_mangle]
+pub unsafe extern "C" fn ime_free_wstring(s: *mut u16) {
+    ffi_guard("ime_free_wstring", (), || {
+        if !s.is_null() {
+            let len = wide_len(s) + 1;
+            drop(Box::from_raw(std::ptr::slice_from_raw_parts_mut(s, len)));
+        }
+    })
+}
+
+/// Process a key event into a caller-owned result (see `ime_key_ext`).
+///
+/// # Returns
+/// * `0` on success, `*out` holds the result
+/// * `-1` if `out` is null or engine not initialized
+///
+/// # Safety
+/// `out` must be writable or null.
+#[no_mangle]
+pub unsafe extern "C" fn ime_key_into(
+    key: u16,
+    caps: bool,
+    ctrl: bool,
+    shift: bool,
+    out: *mut Result,
+) -> i32 {
+    ffi_guard("ime_key_into", IME_ERR_PANIC, || {
+        if out.is_null() {
+            set_last_error("out is null".into());
+            return IME_ERR_INVALID_ARG;
+        }
+        match *lock_engine() {
+            Some(ref mut e) => {
+                *out = e.on_key_ext(key, caps, ctrl, shift);
+                0
+            }
+            None => {
+                set_last_error("engine not initialized".into());
+                IME_ERR_INVALID_ARG
+            }
+        }
+    })
+}
+
+// ============================================================
+// Returning text
+// ============================================================
+
+/// `ime_config` as UTF-16.
+#[no_mangle]
+pub extern "C" fn ime_config_w() -> *mut u16 {
+    unsafe { into_wide(ime_config()) }
+}
+
+/// `ime_export_profile` as UTF-16.
+#[no_mangle]
+pub extern "C" fn ime_export_profile_w() -> *mut u16 {
+    unsafe { into_wide(ime_export_profile()) }
+}
+
+/// `ime_last_error` as UTF-16.
+#[no_mangle]
+pub extern "C" fn ime_last_error_w() -> *mut u16 {
+    unsafe { into_wide(ime_last_error()) }
+}
+
+/// `ime_self_test` as UTF-16.
+#[no_mangle]
+pub extern "C" fn ime_self_test_w() -> *mut u16 {
+    unsafe { into_wide(ime_self_test()) }
+}
+
+/// `ime_debug_state` as UTF-16.
+#[no_mangle]
+pub extern "C" fn ime_debug_state_w() -> *mut u16 {
+    unsafe { into_wide(ime_debug_state()) }
+}
+
+/// `ime_word_counts_csv` as UTF-16.
+#[no_mangle]
+pub extern "C" fn ime_word_counts_csv_w() -> *mut u16 {
+    unsafe { into_wide(ime_word_counts_csv()) }
+}
+
+/// `ime_practice_screen` as UTF-16.
+#[no_mangle]
+pub extern "C" fn ime_practice_screen_w() -> *mut u16 {
+    unsafe { into_wide(ime_practice_screen()) }
+}
+
+/// `ime_practice_stats` as UTF-16.
+#[no_mangle]
+pub extern "C" fn ime_practice_stats_w() -> *mut u16 {
+    unsafe { into_wide(ime_practice_stats()) }
+}
+
+/// `ime_long_press` as UTF-16.
+#[no_mangle]
+pub extern "C" fn ime_long_press_w(label: u32) -> *mut u16 {
+    unsafe { into_wide(ime_long_press(label)) }
+}
+
+/// `ime_word_info` as UTF-16.
+///
+/// # Safety
+/// Non-null pointers must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn ime_word_info_w(
+    start: *mut i32,
+    end: *mut i32,
+    committed: *mut bool,
+) -> *mut u16 {
+    into_wide(ime_word_info(start, end, committed))
+}
+
+/// `ime_current_preview` as UTF-16.
+#[no_mangle]
+pub extern "C" fn ime_current_preview_w() -> *mut u16 {
+    unsafe { into_wide(ime_current_preview()) }
+}
+
+/// `ime_last_restore_reason` as UTF-16.
+#[no_mangle]
+pub extern "C" fn ime_last_restore_reason_w() -> *mut u16 {
+    unsafe { into_wide(ime_last_restore_reason()) }
+}
+
+/// `ime_typo` as UTF-16.
+#[no_mangle]
+pub extern "C" fn ime_typo_w() -> *mut u16 {
+    unsafe { into_wide(ime_typo()) }
+}
+
+/// `ime_suggestion` as UTF-16.
+#[no_mangle]
+pub extern "C" fn ime_suggestion_w() -> *mut u16 {
+    unsafe { into_wide(ime_suggestion()) }
+}
+
+/// `ime_suggestion_list` as UTF-16.
+#[no_mangle]
+pub extern "C" fn ime_suggestion_list_w() -> *mut u16 {
+    unsafe { into_wide(ime_suggestion_list()) }
+}
+
+/// `ime_to_keystrokes` with UTF-16 in and out.
+///
+/// # Safety
+/// `word` must be a valid null-terminated UTF-16 string or null.
+#[no_mangle]
+pub unsafe extern "C" fn ime_to_keystrokes_w(word: *const u16, method: u8) -> *mut u16 {
+    with_utf8(word, std::ptr::null_mut(), |w| {
+        into_wide(ime_to_keystrokes(w, method))
+    })
+}
+
+/// `ime_search_key` with UTF-16 in and out.
+///
+/// # Safety
+/// `text` must be a valid null-terminated UTF-16 string or null.
+#[no_mangle]
+pub unsafe extern "C" fn ime_search_key_w(text: *const u16) -> *mut u16 {
+    with_utf8(text, std::ptr::null_mut(), |t| into_wide(ime_search_key(t)))
+}
+
+/// `ime_suggest_similar` with UTF-16 in and out.
+///
+/// # Safety
+/// `word` must be a valid null-terminated UTF-16 string or null.
+#[cfg(feature = "suggestions")]
+#[no_mangle]
+pub unsafe extern "C" fn ime_suggest_similar_w(word: *const u16, max_distance: u8) -> *mut u16 {
+    with_utf8(word, std::ptr::null_mut(), |w| {
+        into_wide(ime_suggest_similar(w, max_distance))
+    })
+}
+
+/// `ime_lint` with UTF-16 in and out.
+///
+/// # Safety
+/// `text` must be a valid null-terminated UTF-16 string or null.
+#[cfg(feature = "suggestions")]
+#[no_mangle]
+pub unsafe extern "C" fn ime_lint_w(text: *const u16) -> *mut u16 {
+    with_utf8(text, std::ptr::null_mut(), |t| into_wide(ime_lint(t)))
+}
+
+/// `ime_recompose_pasted` with UTF-16 in and out.
+///
+/// # Safety
+/// `text` must be a valid null-terminated UTF-16 string or null.
+#[cfg(feature = "conversion")]
+#[no_mangle]
+pub unsafe extern "C" fn ime_recompose_pasted_w(text: *const u16) -> *mut u16 {
+    with_utf8(text, std::ptr::null_mut(), |t| {
+        into_wide(ime_recompose_pasted(t))
+    })
+}
+
+/// `ime_paste_options` with UTF-16 in and out.
+///
+/// # Safety
+/// `text` must be a valid null-terminated UTF-16 string or null.
+#[cfg(feature = "conversion")]
+#[no_mangle]
+pub unsafe extern "C" fn ime_paste_options_w(text: *const u16) -> *mut u16 {
+    with_utf8(text, std::ptr::null_mut(), |t| {
+        into_wide(ime_paste_options(t))
+    })
+}
+
+/// `ime_transform_selection` with UTF-16 in and out.
+///
+/// # Safety
+/// `text` must be a valid null-terminated UTF-16 string or null.
+#[cfg(feature = "conversion")]
+#[no_mangle]
+pub unsafe extern "C" fn ime_transform_selection_w(text: *const u16, op: u8, arg: u8) -> *mut u16 {
+    with_utf8(text, std::ptr::null_mut(), |t| {
+        into_wide(ime_transform_selection(t, op, arg))
+    })
+}
+
+// ============================================================
+// Taking text
+// ============================================================
+
+/// `ime_load_config` with a UTF-16 argument.
+///
+/// # Safety
+/// `toml` must be a valid null-terminated UTF-16 string or null.
+#[no_mangle]
+pub unsafe extern "C" fn ime_load_config_w(toml: *const u16) -> i32 {
+    with_utf8(toml, IME_ERR_INVALID_ARG, |t| ime_load_config(t))
+}
+
+/// `ime_load_rules` with a UTF-16 argument.
+///
+/// # Safety
+/// `src` must be a valid null-terminated UTF-16 string or null.
+#[cfg(feature = "rules")]
+#[no_mangle]
+pub unsafe extern "C" fn ime_load_rules_w(src: *const u16) -> i32 {
+    with_utf8(src, IME_ERR_INVALID_ARG, |s| ime_load_rules(s))
+}
+
+/// `ime_load_plugin` with a UTF-16 argument.
+///
+/// # Safety
+/// `path` must be a valid null-terminated UTF-16 string or null.
+#[cfg(feature = "plugins")]
+#[no_mangle]
+pub unsafe extern "C" fn ime_load_plugin_w(path: *const u16) -> i32 {
+    with_utf8(path, IME_ERR_INVALID_ARG, |p| ime_load_plugin(p))
+}
+
+/// `ime_plugin_suggest` with a UTF-16 argument.
+///
+/// # Safety
+/// `word` must be a valid null-terminated UTF-16 string or null.
+#[cfg(feature = "plugins")]
+#[no_mangle]
+pub unsafe extern "C" fn ime_plugin_suggest_w(word: *const u16) -> *mut c_char {
+    with_utf8(word, std::ptr::null_mut(), |w| ime_plugin_suggest(w))
+}
+
+/// `ime_set_diacritic_model` with a UTF-16 argument.
+///
+/// # Safety
+/// `path` must be a valid null-terminated UTF-16 string or null.
+#[cfg(feature = "ml")]
+#[no_mangle]
+pub unsafe extern "C" fn ime_set_diacritic_model_w(path: *const u16) -> i32 {
+    with_utf8(path, IME_ERR_INVALID_ARG, |p| ime_set_diacritic_model(p))
+}
+
+/// `ime_restore_diacritics` with a UTF-16 argument.
+///
+/// # Safety
+/// `text` must be a valid null-terminated UTF-16 string or null.
+#[cfg(feature = "ml")]
+#[no_mangle]
+pub unsafe extern "C" fn ime_restore_diacritics_w(text: *const u16) -> *mut c_char {
+    with_utf8(text, std::ptr::null_mut(), |t| ime_restore_diacritics(t))
+}
+
+/// `ime_import_profile` with a UTF-16 argument.
+///
+/// # Safety
+/// `json` must be a valid null-terminated UTF-16 string or null.
+#[no_mangle]
+pub unsafe extern "C" fn ime_import_profile_w(json: *const u16) -> i32 {
+    with_utf8(json, IME_ERR_INVALID_ARG, |j| ime_import_profile(j))
+}
+
+/// `ime_text_context` with a UTF-16 argument.
+///
+/// # Safety
+/// `prefix` must be a valid null-terminated UTF-16 string or null.
+#[no_mangle]
+pub unsafe extern "C" fn ime_text_context_w(prefix: *const u16) -> i32 {
+    with_utf8(prefix, IME_ERR_INVALID_ARG, |p| ime_text_context(p))
+}
+
+/// `ime_set_app` with a UTF-16 argument.
+///
+/// # Safety
+/// `app_id` must be a valid null-terminated UTF-16 string or null.
+#[no_mangle]
+pub unsafe extern "C" fn ime_set_app_w(app_id: *const u16) {
+    with_utf8(app_id, (), |a| ime_set_app(a))
+}
+
+/// `ime_add_shortcut` with UTF-16 arguments.
+///
+/// # Safety
+/// Both pointers must be valid null-terminated UTF-16 strings or null.
+#[no_mangle]
+pub unsafe extern "C" fn ime_add_shortcut_w(trigger: *const u16, replacement: *const u16) {
+    with_utf8(trigger, (), |t| {
+        with_utf8(replacement, (), |r| ime_add_shortcut(t, r))
+    })
+}
+
+/// `ime_remove_shortcut` with a UTF-16 argument.
+///
+/// # Safety
+/// `trigger` must be a valid null-terminated UTF-16 string or null.
+#[no_mangle]
+pub unsafe extern "C" fn ime_remove_shortcut_w(trigger: *const u16) {
+    with_utf8(trigger, (), |t| ime_remove_shortcut(t))
+}
+
+/// `ime_shortcut_conflicts` with UTF-16 in and out.
+///
+/// # Safety
+/// `trigger` must be a valid null-terminated UTF-16 string or null.
+#[cfg(feature = "macros")]
+#[no_mangle]
+pub unsafe extern "C" fn ime_shortcut_conflicts_w(trigger: *const u16) -> *mut u16 {
+    with_utf8(trigger, std::ptr::null_mut(), |t| {
+        into_wide(ime_shortcut_conflicts(t))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::keys;
+    use serial_test::serial;
+
+    fn wide(s: &str) -> Vec<u16> {
+        s.encode_utf16().chain(std::iter::once(0)).collect()
+    }
+
+    /// Read and free a returned wide string
+    fn take(p: *mut u16) -> Option<String> {
+        if p.is_null() {
+            return None;
+        }
+        let s = unsafe { String::from_utf16(std::slice::from_raw_parts(p, wide_len(p))) };
+        unsafe { ime_free_wstring(p) };
+        Some(s.unwrap())
+    }
+
+    #[test]
+    fn test_wide_roundtrip() {
+        // Astral characters become surrogate pairs and come back whole
+        let text = "Tiếng Việt 😀 ok";
+        let p = unsafe { into_wide(into_c_string(text.into())) };
+        let units = unsafe { std::slice::from_raw_parts(p, wide_len(p)) };
+        assert_eq!(units, &wide(text)[..text.encode_utf16().count()]);
+        assert_eq!(take(p).as_deref(), Some(text));
+
+        let arg = wide(text);
+        let back = unsafe { from_wide(arg.as_ptr()) }.unwrap().unwrap();
+        assert_eq!(back.to_str().unwrap(), text);
+        assert_eq!(unsafe { from_wide(std::ptr::null()) }, Ok(None));
+        assert!(unsafe { into_wide(std::ptr::null_mut()) }.is_null());
+    }
+
+    #[test]
+    #[serial]
+    fn test_unpaired_surrogate_rejected() {
+        let bad = [0x0061, 0xD83D, 0x0062, 0];
+        assert_eq!(unsafe { from_wide(bad.as_ptr()) }, Err(()));
+        assert!(unsafe { ime_to_keystrokes_w(bad.as_ptr(), 0) }.is_null());
+        assert_eq!(
+            take(ime_last_error_w()).as_deref(),
+            Some("string is not valid UTF-16")
+        );
+    }
+
+    #[test]
+    fn test_text_calls() {
+        let word = wide("được");
+        let keys = unsafe { ime_to_keystrokes_w(word.as_ptr(), 1) };
+        assert_eq!(take(keys).as_deref(), Some("d9u7o7c5"));
+        let key = unsafe { ime_search_key_w(wide("Đường").as_ptr()) };
+        assert_eq!(take(key).as_deref(), Some("duong"));
+        assert_eq!(take(ime_long_press_w('d' as u32)).as_deref(), Some("dđ"));
+    }
+
+    #[test]
+    #[serial]
+    fn test_key_into() {
+        ime_init();
+        let mut out = Result::none();
+        unsafe {
+            ime_add_shortcut_w(wide("vn").as_ptr(), wide("Việt Nam 🇻🇳").as_ptr());
+            assert_eq!(ime_key_into(keys::A, false, false, false, &mut out), 0);
+            assert_eq!(ime_key_into(keys::S, false, false, false, &mut out), 0);
+            assert_eq!(
+                ime_key_into(keys::A, false, false, false, std::ptr::null_mut()),
+                IME_ERR_INVALID_ARG
+            );
+        }
+        assert_eq!(out.action, 1);
+        assert_eq!(out.backspace, 1);
+        assert_eq!(char::from_u32(out.chars[0]), Some('á'));
+        let config = take(ime_config_w()).unwrap();
+        assert!(config.contains("method"));
+        ime_clear_shortcuts();
+        ime_clear();
+    }
+}
@@ -0,0 +1,539 @@
+//! Import from other Vietnamese IMEs
+//!
+//! Users migrating from UniKey, EVKey or OpenKey keep their abbreviations
+//! and basic preferences:
+//!
+//! - `UniKey`: macro file (`.mac`), `;` comments and `trigger:replacement` lines.
+//!   EVKey and OpenKey export macros in the same format.
+//! - `Csv`: `trigger,replacement` rows (RFC 4180 quoting), for snippet
+//!   collections kept in a spreadsheet; a `trigger,replacement` header row
+//!   is skipped
+//! - `EvKey`: `setting.ini` (`[Section]` headers, `Key=Value` lines)
+//! - `OpenKey`: exported settings (`Key=Value` lines, `defaults` style)
+//!
+//! Settings files carry many options we don't have (code tables, hotkeys,
+//! per-app lists); those are reported in `Imported::skipped`, not rejected.
+//! Files may be UTF-8 or UTF-16 with a BOM (UniKey on Windows).
+//!
+//! The two macro formats also go the other way (`export_macros`), so users
+//! can share their shortcuts. Only trigger and replacement are written.
+
+use crate::config::{AutoCorrectMode, ConfigError, EngineConfig, Method, ToneStyle};
+use crate::engine::shortcut::Shortcut;
+
+/// Source application / file type
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImportFormat {
+    /// UniKey macro file (also EVKey/OpenKey macro exports)
+    UniKey,
+    /// `trigger,replacement` spreadsheet rows
+    Csv,
+    /// EVKey `setting.ini`
+    EvKey,
+    /// OpenKey exported settings
+    OpenKey,
+}
+
+impl ImportFormat {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ImportFormat::UniKey => "unikey",
+            ImportFormat::Csv => "csv",
+            ImportFormat::EvKey => "evkey",
+            ImportFormat::OpenKey => "openkey",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "unikey" | "mac" => Some(ImportFormat::UniKey),
+            "csv" => Some(ImportFormat::Csv),
+            "evkey" => Some(ImportFormat::EvKey),
+            "openkey" => Some(ImportFormat::OpenKey),
+            _ => None,
+        }
+    }
+}
+
+/// Error while importing (nothing is applied)
+#[derive(Debug, PartialEq, Eq)]
+pub enum ImportError {
+    /// Not UTF-8 / UTF-16 text
+    Encoding,
+    /// Line doesn't match the format
+    Syntax { line: usize },
+    /// Known setting with a value we can't map
+    InvalidValue { line: usize, key: String },
+    /// Imported settings don't combine into a valid config
+    Config(ConfigError),
+}
+
+impl std::fmt::Display for ImportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImportError::Encoding => write!(f, "file is not UTF-8 or UTF-16 text"),
+            ImportError::Syntax { line } => write!(f, "line {}: unrecognized line", line),
+            ImportError::InvalidValue { line, key } => {
+                write!(f, "line {}: unsupported value for `{}`", line, key)
+            }
+            ImportError::Config(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+/// Parsed import, ready to apply
+#[derive(Debug)]
+pub struct Imported {
+    /// `base` config with imported settings applied
+    pub config: EngineConfig,
+    pub shortcuts: Vec<Shortcut>,
+    /// Settings found in the file that have no equivalent here
+    pub skipped: Vec<String>,
+}
+
+/// What `Engine::import_from` applied
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ImportReport {
+    /// Shortcuts added or replaced
+    pub shortcuts: usize,
+    /// Settings found in the file that have no equivalent here
+    pub skipped: Vec<String>,
+}
+
+/// Parse `bytes` as `format`, starting from `base` settings
+pub fn parse(
+    format: ImportFormat,
+    bytes: &[u8],
+    base: &EngineConfig,
+) -> Result<Imported, ImportError> {
+    let text = decode(bytes)?;
+    let mut out = Imported {
+        config: base.clone(),
+        shortcuts: Vec::new(),
+        skipped: Vec::new(),
+    };
+    match format {
+        ImportFormat::UniKey => parse_macros(&text, &mut out)?,
+        ImportFormat::Csv => parse_csv(&text, &mut out)?,
+        ImportFormat::EvKey | ImportFormat::OpenKey => parse_settings(format, &text, &mut out)?,
+    }
+    out.config.validate().map_err(ImportError::Config)?;
+    Ok(out)
+}
+
+/// UTF-8 (optional BOM) or UTF-16 with BOM
+fn decode(bytes: &[u8]) -> Result<String, ImportError> {
+    let utf16 = |be: bool| {
+        let units: Vec<u16> = bytes[2..]
+            .chunks_exact(2)
+            .map(|c| {
+                if be {
+                    u16::from_be_bytes([c[0], c[1]])
+                } else {
+                    u16::from_le_bytes([c[0], c[1]])
+                }
+            })
+            .collect();
+        String::from_utf16(&units).map_err(|_| ImportError::Encoding)
+    };
+    match bytes {
+        [0xFF, 0xFE, ..] => utf16(false),
+        [0xFE, 0xFF, ..] => utf16(true),
+        [0xEF, 0xBB, 0xBF, rest @ ..] => {
+            String::from_utf8(rest.to_vec()).map_err(|_| ImportError::Encoding)
+        }
+        _ => String::from_utf8(bytes.to_vec()).map_err(|_| ImportError::Encoding),
+    }
+}
+
+/// UniKey `.mac`: `trigger:replacement`, `;` starts a comment line
+fn parse_macros(text: &str, out: &mut Imported) -> Result<(), ImportError> {
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim_end_matches('\r');
+        if line.trim().is_empty() || line.starts_with(';') {
+            continue;
+        }
+        let Some((trigger, replacement)) = line.split_once(':') else {
+            return Err(ImportError::Syntax { line: i + 1 });
+        };
+        let trigger = trigger.trim();
+        if trigger.is_empty() {
+            return Err(ImportError::Syntax { line: i + 1 });
+        }
+        // UniKey macros expand with the typed case (vn → Việt Nam, VN → VIỆT NAM)
+        out.shortcuts.push(Shortcut::new(trigger, replacement));
+    }
+    Ok(())
+}
+
+/// CSV rows: `trigger,replacement`, further columns ignored
+fn parse_csv(text: &str, out: &mut Imported) -> Result<(), ImportError> {
+    for (i, (line, row)) in csv_rows(text)?.into_iter().enumerate() {
+        let [trigger, replacement, ..] = row.as_slice() else {
+            if row.iter().all(|f| f.trim().is_empty()) {
+                continue;
+            }
+            return Err(ImportError::Syntax { line });
+        };
+        let trigger = trigger.trim();
+        if i == 0 && trigger.eq_ignore_ascii_case("trigger") {
+            continue;
+        }
+        if trigger.is_empty() {
+            return Err(ImportError::Syntax { line });
+        }
+        out.shortcuts.push(Shortcut::new(trigger, replacement));
+    }
+    Ok(())
+}
+
+/// RFC 4180 records with the line each starts on; quoted fields may hold
+/// commas, `""` and line breaks
+fn csv_rows(text: &str) -> Result<Vec<(usize, Vec<String>)>, ImportError> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let (mut line, mut start) = (1, 1);
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (quoted, c) {
+            (true, '"') if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            (true, '"') => quoted = false,
+            (true, c) => {
+                if c == '\n' {
+                    line += 1;
+                }
+                field.push(c);
+            }
+            (false, '"') if field.is_empty() => quoted = true,
+            (false, ',') => row.push(std::mem::take(&mut field)),
+            (false, '\r') if chars.peek() == Some(&'\n') => {}
+            (false, '\n') => {
+                row.push(std::mem::take(&mut field));
+                rows.push((start, std::mem::take(&mut row)));
+                line += 1;
+                start = line;
+            }
+            (false, c) => field.push(c),
+        }
+    }
+    if quoted {
+        return Err(ImportError::Syntax { line: start });
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push((start, row));
+    }
+    Ok(rows)
+}
+
+/// Macro file written by `export_macros`
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Exported {
+    pub text: String,
+    /// Triggers of shortcuts the format can't hold (a `:` in a UniKey
+    /// trigger, a line break in a UniKey replacement)
+    pub skipped: Vec<String>,
+}
+
+/// First line of a UniKey macro file; UniKey won't load one without it
+const UNIKEY_HEADER: &str = ";DO NOT DELETE THIS LINE*** version=1 ***";
+
+/// `shortcuts` as a `UniKey` or `Csv` macro file, in the order given;
+/// `None` for the settings formats
+///
+/// CSV starts with a byte order mark and a header row, so spreadsheets
+/// open the Vietnamese text as UTF-8.
+pub fn export_macros<'a>(
+    format: ImportFormat,
+    shortcuts: impl IntoIterator<Item = &'a Shortcut>,
+) -> Option<Exported> {
+    let mut out = Exported::default();
+    match format {
+        ImportFormat::UniKey => {
+            out.text.push_str(UNIKEY_HEADER);
+            out.text.push_str("\r\n");
+            for sc in shortcuts {
+                let trigger = sc.trigger.trim();
+                if trigger.is_empty()
+                    || trigger.contains(':')
+                    || sc.replacement.contains(['\r', '\n'])
+                {
+                    out.skipped.push(sc.trigger.clone());
+                    continue;
+                }
+                out.text.push_str(trigger);
+                out.text.push(':');
+                out.text.push_str(&sc.replacement);
+                out.text.push_str("\r\n");
+            }
+        }
+        ImportFormat::Csv => {
+            out.text.push_str("\u{FEFF}trigger,replacement\r\n");
+            for sc in shortcuts {
+                csv_field(&mut out.text, &sc.trigger);
+                out.text.push(',');
+                csv_field(&mut out.text, &sc.replacement);
+                out.text.push_str("\r\n");
+            }
+        }
+        ImportFormat::EvKey | ImportFormat::OpenKey => return None,
+    }
+    Some(out)
+}
+
+/// `value`, quoted if it needs to be
+fn csv_field(out: &mut String, value: &str) {
+    let plain = !value.contains([',', '"', '\r', '\n']) && value.trim() == value;
+    if plain {
+        out.push_str(value);
+        return;
+    }
+    out.push('"');
+    out.push_str(&value.replace('"', "\"\""));
+    out.push('"');
+}
+
+/// Setting we know how to map
+#[derive(Clone, Copy)]
+enum Field {
+    Method,
+    Enabled,
+    Modern,
+    AutoRestore,
+}
+
+fn field(format: ImportFormat, key: &str) -> Option<Field> {
+    match (format, key) {
+        (ImportFormat::EvKey, "InputType") => Some(Field::Method),
+        (ImportFormat::EvKey, "VietMode") => Some(Field::Enabled),
+        (ImportFormat::EvKey, "ModernOrthography") => Some(Field::Modern),
+        (ImportFormat::EvKey, "AutoRestore") => Some(Field::AutoRestore),
+        (ImportFormat::OpenKey, "InputType") => Some(Field::Method),
+        (ImportFormat::OpenKey, "Language") => Some(Field::Enabled),
+        (ImportFormat::OpenKey, "ModernOrthography") => Some(Field::Modern),
+        (ImportFormat::OpenKey, "RestoreIfInvalidWord") => Some(Field::AutoRestore),
+        _ => None,
+    }
+}
+
+/// `Key=Value` lines (INI sections and `;`/`#` comments ignored)
+fn parse_settings(format: ImportFormat, text: &str, out: &mut Imported) -> Result<(), ImportError> {
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with([';', '#']) || line.starts_with('[') {
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            return Err(ImportError::Syntax { line: i + 1 });
+        };
+        let (key, value) = (key.trim(), value.trim().trim_matches('"'));
+        let Some(f) = field(format, key) else {
+            out.skipped.push(key.to_string());
+            continue;
+        };
+        let invalid = || ImportError::InvalidValue {
+            line: i + 1,
+            key: key.to_string(),
+        };
+        let n: u8 = value.parse().map_err(|_| invalid())?;
+        let flag = match n {
+            0 => false,
+            1 => true,
+            _ => return Err(invalid()), // e.g. InputType=2 (VIQR)
+        };
+        let config = &mut out.config;
+        match f {
+            Field::Method => config.method = Method::from_id(n),
+            Field::Enabled => config.enabled = flag,
+            Field::Modern => {
+                config.tone_style = if flag {
+                    ToneStyle::Modern
+                } else {
+                    ToneStyle::Classic
+                }
+            }
+            Field::AutoRestore => {
+                config.auto_correct = if flag {
+                    AutoCorrectMode::Restore
+                } else {
+                    AutoCorrectMode::Off
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unikey_macros() {
+        let mac = ";DO NOT DELETE THIS LINE*** version=1 ***\r\nvn:Việt Nam\r\nko:không\r\nurl:http://x\r\n";
+        let out = parse(
+            ImportFormat::UniKey,
+            mac.as_bytes(),
+            &EngineConfig::default(),
+        )
+        .unwrap();
+        let pairs: Vec<_> = out
+            .shortcuts
+            .iter()
+            .map(|s| (s.trigger.as_str(), s.replacement.as_str()))
+            .collect();
+        assert_eq!(
+            pairs,
+            [("vn", "Việt Nam"), ("ko", "không"), ("url", "http://x")]
+        );
+    }
+
+    #[test]
+    fn test_unikey_utf16() {
+        let mut bytes = vec![0xFF, 0xFE];
+        for u in "vn:Việt Nam\n".encode_utf16() {
+            bytes.extend_from_slice(&u.to_le_bytes());
+        }
+        let out = parse(ImportFormat::UniKey, &bytes, &EngineConfig::default()).unwrap();
+        assert_eq!(out.shortcuts[0].replacement, "Việt Nam");
+    }
+
+    #[test]
+    fn test_unikey_errors() {
+        let base = EngineConfig::default();
+        assert_eq!(
+            parse(ImportFormat::UniKey, b"vn:x\nbroken\n", &base).err(),
+            Some(ImportError::Syntax { line: 2 })
+        );
+        assert_eq!(
+            parse(ImportFormat::UniKey, &[0xC3, 0x28], &base).err(),
+            Some(ImportError::Encoding)
+        );
+    }
+
+    #[test]
+    fn test_csv_macros() {
+        let csv = "\u{FEFF}trigger,replacement,note\r\n\
+                   vn,Việt Nam,\r\n\
+                   \"ks\",\"Kính gửi, \"\"anh\"\"\nchị\"\r\n\
+                   \r\n\
+                   dc,được";
+        let out = parse(ImportFormat::Csv, csv.as_bytes(), &EngineConfig::default()).unwrap();
+        let pairs: Vec<_> = out
+            .shortcuts
+            .iter()
+            .map(|s| (s.trigger.as_str(), s.replacement.as_str()))
+            .collect();
+        assert_eq!(
+            pairs,
+            [
+                ("vn", "Việt Nam"),
+                ("ks", "Kính gửi, \"anh\"\nchị"),
+                ("dc", "được")
+            ]
+        );
+
+        let base = EngineConfig::default();
+        assert_eq!(
+            parse(ImportFormat::Csv, b"vn,x\nbroken\n", &base).err(),
+            Some(ImportError::Syntax { line: 2 })
+        );
+        assert_eq!(
+            parse(ImportFormat::Csv, b"vn,x\nko,\"open\n\n", &base).err(),
+            Some(ImportError::Syntax { line: 2 })
+        );
+    }
+
+    #[test]
+    fn test_export_round_trip() {
+        let shortcuts = [
+            Shortcut::new("vn", "Việt Nam"),
+            Shortcut::new("tks", "Cảm ơn, chúc \"anh\" một ngày tốt lành"),
+            Shortcut::new("dc", " được "),
+            Shortcut::new("sig", "Trân trọng,\nNguyễn Văn A"),
+            Shortcut::new("a:b", "ạ"),
+        ];
+        let base = EngineConfig::default();
+        let pairs = |list: &[Shortcut]| -> Vec<(String, String)> {
+            list.iter()
+                .map(|s| (s.trigger.clone(), s.replacement.clone()))
+                .collect()
+        };
+
+        let csv = export_macros(ImportFormat::Csv, &shortcuts).unwrap();
+        assert!(csv.skipped.is_empty());
+        let back = parse(ImportFormat::Csv, csv.text.as_bytes(), &base).unwrap();
+        assert_eq!(pairs(&back.shortcuts), pairs(&shortcuts));
+
+        let mac = export_macros(ImportFormat::UniKey, &shortcuts).unwrap();
+        assert!(mac.text.starts_with(UNIKEY_HEADER));
+        assert_eq!(mac.skipped, ["sig", "a:b"]);
+        let back = parse(ImportFormat::UniKey, mac.text.as_bytes(), &base).unwrap();
+        assert_eq!(pairs(&back.shortcuts), pairs(&shortcuts[..3]));
+
+        assert_eq!(export_macros(ImportFormat::EvKey, &shortcuts), None);
+    }
+
+    #[test]
+    fn test_evkey_settings() {
+        let ini = "[Setting]\nInputType=1\nModernOrthography=0\nCodeTable=0\nSwitchKey=1\n";
+        let out = parse(
+            ImportFormat::EvKey,
+            ini.as_bytes(),
+            &EngineConfig::default(),
+        )
+        .unwrap();
+        assert_eq!(out.config.method, Method::Vni);
+        assert_eq!(out.config.tone_style, ToneStyle::Classic);
+        assert_eq!(out.skipped, ["CodeTable", "SwitchKey"]);
+    }
+
+    #[test]
+    fn test_openkey_settings() {
+        let cfg = "InputType = 0\nLanguage = 0\nRestoreIfInvalidWord = \"0\"\n";
+        let out = parse(
+            ImportFormat::OpenKey,
+            cfg.as_bytes(),
+            &EngineConfig::default(),
+        )
+        .unwrap();
+        assert_eq!(out.config.method, Method::Telex);
+        assert!(!out.config.enabled);
+        assert_eq!(out.config.auto_correct, AutoCorrectMode::Off);
+
+        // VIQR input type has no equivalent
+        assert_eq!(
+            parse(
+                ImportFormat::OpenKey,
+                b"InputType=2",
+                &EngineConfig::default()
+            )
+            .err(),
+            Some(ImportError::InvalidValue {
+                line: 1,
+                key: "InputType".into()
+            })
+        );
+    }
+
+    #[test]
+    fn test_format_names() {
+        for f in [
+            ImportFormat::UniKey,
+            ImportFormat::Csv,
+            ImportFormat::EvKey,
+            ImportFormat::OpenKey,
+        ] {
+            assert_eq!(ImportFormat::parse(f.as_str()), Some(f));
+        }
+        assert_eq!(ImportFormat::parse("MAC"), Some(ImportFormat::UniKey));
+        assert_eq!(ImportFormat::parse("gonhanh"), None);
+    }
+}
@@ -3,9 +3,11 @@
 //! Defines key mappings for Vietnamese input methods.
 //! Engine handles all pattern matching based on buffer scan.
 
+pub mod reverse;
 pub mod telex;
 pub mod vni;
 
+pub use reverse::to_keystrokes;
 pub use telex::Telex;
 pub use vni::Vni;
 
@@ -60,10 +62,13 @@ pub trait Method {
     fn remove(&self, key: u16) -> bool;
 }
 
-/// Get method by id
-pub fn get(id: u8) -> Box<dyn Method> {
+static TELEX: Telex = Telex;
+static VNI: Vni = Vni;
+
+/// Get method by id (static instance: nothing is allocated per key)
+pub fn get(id: u8) -> &'static dyn Method {
     match id {
-        1 => Box::new(Vni),
-        _ => Box::new(Telex),
+        1 => &VNI,
+        _ => &TELEX,
     }
 }
@@ -0,0 +1,118 @@
+//! Reverse Transliteration
+//!
+//! Composed Vietnamese back to the keys that type it: "việt" → `vieetj`
+//! (Telex) or `vie6t5` (VNI). Tone keys follow their letter and the mark
+//! key ends the word, the way most people type. Typing the result into the
+//! engine with the same method gives the word back.
+
+use crate::config::Method;
+use crate::data::chars::{self, mark, tone};
+use crate::data::keys;
+use crate::utils;
+
+/// Keystrokes that type `word` with `method`
+///
+/// Case is kept on the letters; modifier keys are uppercase only when the
+/// whole word is. None if the word has a character outside the Vietnamese
+/// alphabet (plus f, j, w, z) or more than one mark. Decomposed input is
+/// composed first.
+pub fn to_keystrokes(word: &str, method: Method) -> Option<String> {
+    let word = chars::compose(word);
+    let all_caps = word.chars().any(char::is_uppercase) && !word.chars().any(char::is_lowercase);
+    let modifier = |c: char| {
+        if all_caps {
+            c.to_ascii_uppercase()
+        } else {
+            c
+        }
+    };
+
+    let mut out = String::with_capacity(word.len() * 2);
+    let mut word_mark = mark::NONE;
+    // Plain vowel that would take a circumflex from the next key (Telex)
+    let mut prev_plain = None;
+    for ch in word.chars() {
+        let p = chars::parse_char(ch)?;
+        let letter = utils::key_to_char(p.key, p.caps)?;
+        if method == Method::Telex
+            && p.tone == tone::NONE
+            && prev_plain == Some(p.key)
+            && matches!(p.key, keys::A | keys::E | keys::O)
+        {
+            // "oo" types ô: a third press undoes it ("xoong" = xooong)
+            out.push(letter);
+        }
+        out.push(letter);
+        prev_plain = (p.tone == tone::NONE && !p.stroke).then_some(p.key);
+
+        if p.stroke {
+            out.push(modifier(match method {
+                Method::Telex => 'd',
+                Method::Vni => '9',
+            }));
+        }
+        if p.tone != tone::NONE {
+            let key = match (method, p.tone) {
+                (Method::Telex, tone::CIRCUMFLEX) => letter.to_ascii_lowercase(),
+                (Method::Telex, _) => 'w',
+                (Method::Vni, tone::CIRCUMFLEX) => '6',
+                (Method::Vni, _) if p.key == keys::A => '8',
+                (Method::Vni, _) => '7',
+            };
+            out.push(modifier(key));
+        }
+        if p.mark != mark::NONE {
+            if word_mark != mark::NONE {
+                return None;
+            }
+            word_mark = p.mark;
+        }
+    }
+
+    if word_mark != mark::NONE {
+        let key = match method {
+            Method::Telex => ['s', 'f', 'r', 'x', 'j'][word_mark as usize - 1],
+            Method::Vni => (b'0' + word_mark) as char,
+        };
+        out.push(modifier(key));
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_telex() {
+        let cases = [
+            ("việt", "vieetj"),
+            ("được", "dduwowcj"),
+            ("Nguyễn", "Nguyeenx"),
+            ("ĂN", "AWN"),
+            ("hoà", "hoaf"),
+            ("xoong", "xooong"),
+            // Decomposed
+            ("vie\u{0323}\u{0302}t", "vieetj"),
+            ("ngu\u{031B}o\u{031B}\u{0300}i", "nguwowif"),
+        ];
+        for (word, keys) in cases {
+            assert_eq!(to_keystrokes(word, Method::Telex).as_deref(), Some(keys));
+        }
+    }
+
+    #[test]
+    fn test_vni() {
+        let cases = [("việt", "vie6t5"), ("được", "d9u7o7c5"), ("ăn", "a8n")];
+        for (word, keys) in cases {
+            assert_eq!(to_keystrokes(word, Method::Vni).as_deref(), Some(keys));
+        }
+    }
+
+    #[test]
+    fn test_not_a_word() {
+        assert_eq!(to_keystrokes("straße", Method::Telex), None);
+        assert_eq!(to_keystrokes("áà", Method::Telex), None);
+        assert_eq!(to_keystrokes("a b", Method::Telex), None);
+    }
+}
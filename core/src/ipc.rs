@@ -0,0 +1,658 @@
+//! Engine over IPC
+//!
+//! Lets helper processes share the host's engine (and its user state)
+//! instead of running their own: on Windows, an elevated companion that
+//! types into admin windows the unelevated hook can't reach.
+//!
+//! The host listens on a Unix socket or a named pipe (`listen`); helpers
+//! connect with `Client`. Each message is a frame: a 4-byte little-endian
+//! length, then that many bytes.
+//!
+//! ```text
+//! request:  op (u8) + arguments
+//!   1 process_key   key (u16 LE), flags (u8: 1=caps, 2=ctrl, 4=shift)
+//!   2 set_config    TOML (UTF-8)
+//!   3 query         name (UTF-8): "config" (TOML), "profile" (JSON),
+//!                   "word" (word being composed, empty if none)
+//!   4 session       session ID (UTF-8), see `serve_sessions`
+//! response: status (u8) + body
+//!   0               ok; process_key body = action, backspace, text (UTF-8)
+//!   1-5             set_config rejected (`ConfigError::code()`), body = message
+//!   40-43           malformed request / unknown query / no engine /
+//!                   no session
+//! ```
+//!
+//! A multi-user host serves `Sessions` instead (`listen_sessions`): each
+//! connection names its session first and only ever reaches that
+//! session's engine.
+
+use crate::config::EngineConfig;
+use crate::engine::{Action, Engine};
+use crate::session::{self, Sessions};
+use std::io::{self, Read, Write};
+use std::sync::Mutex;
+use std::thread::JoinHandle;
+use std::time::Instant;
+
+/// Largest frame either side accepts (a config or profile fits easily)
+pub const MAX_FRAME: usize = 1 << 20;
+
+/// Request opcodes
+pub mod op {
+    pub const PROCESS_KEY: u8 = 1;
+    pub const SET_CONFIG: u8 = 2;
+    pub const QUERY: u8 = 3;
+    pub const SESSION: u8 = 4;
+}
+
+/// Response status codes other than `ConfigError::code()`
+pub mod status {
+    pub const OK: u8 = 0;
+    pub const BAD_REQUEST: u8 = 40;
+    pub const UNKNOWN_QUERY: u8 = 41;
+    pub const NO_ENGINE: u8 = 42;
+    pub const NO_SESSION: u8 = 43;
+}
+
+/// Key flags of `process_key`
+pub mod flags {
+    pub const CAPS: u8 = 1;
+    pub const CTRL: u8 = 2;
+    pub const SHIFT: u8 = 4;
+}
+
+/// A decoded request
+#[derive(Clone, Debug, PartialEq)]
+pub enum Request {
+    ProcessKey {
+        key: u16,
+        caps: bool,
+        ctrl: bool,
+        shift: bool,
+    },
+    SetConfig(String),
+    Query(String),
+    Session(String),
+}
+
+impl Request {
+    pub fn encode(&self) -> Vec<u8> {
+        match self {
+            Request::ProcessKey {
+                key,
+                caps,
+                ctrl,
+                shift,
+            } => {
+                let mut f = 0;
+                for (on, bit) in [
+                    (caps, flags::CAPS),
+                    (ctrl, flags::CTRL),
+                    (shift, flags::SHIFT),
+                ] {
+                    if *on {
+                        f |= bit;
+                    }
+                }
+                let [lo, hi] = key.to_le_bytes();
+                vec![op::PROCESS_KEY, lo, hi, f]
+            }
+            Request::SetConfig(toml) => [&[op::SET_CONFIG], toml.as_bytes()].concat(),
+            Request::Query(name) => [&[op::QUERY], name.as_bytes()].concat(),
+            Request::Session(id) => [&[op::SESSION], id.as_bytes()].concat(),
+        }
+    }
+
+    /// `None` if the payload is malformed
+    pub fn decode(payload: &[u8]) -> Option<Self> {
+        let (&op, args) = payload.split_first()?;
+        let text = || String::from_utf8(args.to_vec()).ok();
+        match op {
+            op::PROCESS_KEY => match *args {
+                [lo, hi, f] => Some(Request::ProcessKey {
+                    key: u16::from_le_bytes([lo, hi]),
+                    caps: f & flags::CAPS != 0,
+                    ctrl: f & flags::CTRL != 0,
+                    shift: f & flags::SHIFT != 0,
+                }),
+                _ => None,
+            },
+            op::SET_CONFIG => text().map(Request::SetConfig),
+            op::QUERY => text().map(Request::Query),
+            op::SESSION => text().map(Request::Session),
+            _ => None,
+        }
+    }
+}
+
+/// A response: status and body
+#[derive(Clone, Debug, PartialEq)]
+pub struct Response {
+    pub status: u8,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    fn ok(body: Vec<u8>) -> Self {
+        Response {
+            status: status::OK,
+            body,
+        }
+    }
+
+    fn error(status: u8, message: impl Into<String>) -> Self {
+        Response {
+            status,
+            body: message.into().into_bytes(),
+        }
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        [&[self.status], &self.body[..]].concat()
+    }
+
+    pub fn decode(payload: &[u8]) -> Option<Self> {
+        let (&status, body) = payload.split_first()?;
+        Some(Response {
+            status,
+            body: body.to_vec(),
+        })
+    }
+
+    /// Body as text (config, profile, word, error message)
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+}
+
+/// Read one frame; `Ok(None)` when the peer closed between frames
+pub fn read_frame(r: &mut impl Read) -> io::Result<Option<Vec<u8>>> {
+    let mut len = [0u8; 4];
+    match r.read_exact(&mut len) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+    let len = u32::from_le_bytes(len) as usize;
+    if len > MAX_FRAME {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("frame of {} bytes", len),
+        ));
+    }
+    let mut payload = vec![0; len];
+    r.read_exact(&mut payload)?;
+    Ok(Some(payload))
+}
+
+/// Write one frame
+pub fn write_frame(w: &mut impl Write, payload: &[u8]) -> io::Result<()> {
+    if payload.len() > MAX_FRAME {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("frame of {} bytes", payload.len()),
+        ));
+    }
+    w.write_all(&(payload.len() as u32).to_le_bytes())?;
+    w.write_all(payload)?;
+    w.flush()
+}
+
+/// Answer one request
+pub fn handle(engine: &mut Engine, request: Request) -> Response {
+    match request {
+        Request::ProcessKey {
+            key,
+            caps,
+            ctrl,
+            shift,
+        } => {
+            let r = engine.on_key_ext(key, caps, ctrl, shift);
+            let text: String = r.chars[..r.count as usize]
+                .iter()
+                .filter_map(|&c| char::from_u32(c))
+                .collect();
+            let mut body = vec![r.action, r.backspace];
+            if r.action != Action::None as u8 {
+                body.extend_from_slice(text.as_bytes());
+            }
+            Response::ok(body)
+        }
+        Request::SetConfig(toml) => {
+            match EngineConfig::from_toml(&toml).and_then(|config| engine.apply_config(&config)) {
+                Ok(()) => Response::ok(Vec::new()),
+                Err(e) => Response::error(e.code() as u8, e.to_string()),
+            }
+        }
+        Request::Query(name) => match name.as_str() {
+            "config" => Response::ok(engine.config().to_toml().into_bytes()),
+            "profile" => Response::ok(engine.export_profile().into_bytes()),
+            "word" => Response::ok(
+                engine
+                    .word_info()
+                    .map(|w| w.text.into_bytes())
+                    .unwrap_or_default(),
+            ),
+            _ => Response::error(status::UNKNOWN_QUERY, format!("unknown query `{}`", name)),
+        },
+        Request::Session(_) => Response::error(status::BAD_REQUEST, "not a session server"),
+    }
+}
+
+/// Answer requests on `stream` until the peer disconnects
+///
+/// The engine is locked per request, so several connections (and the
+/// host's own typing) interleave safely.
+pub fn serve(mut stream: impl Read + Write, engine: &Mutex<Option<Engine>>) -> io::Result<()> {
+    while let Some(payload) = read_frame(&mut stream)? {
+        let response = match Request::decode(&payload) {
+            None => Response::error(status::BAD_REQUEST, "malformed request"),
+            Some(request) => match *engine.lock().unwrap_or_else(|e| e.into_inner()) {
+                Some(ref mut e) => handle(e, request),
+                None => Response::error(status::NO_ENGINE, "engine not initialized"),
+            },
+        };
+        write_frame(&mut stream, &response.encode())?;
+    }
+    Ok(())
+}
+
+/// Answer requests on `stream` from the engine of its session
+///
+/// The first request must be `session`; until then everything else gets
+/// `NO_SESSION`. A connection can't switch to another session.
+pub fn serve_sessions(mut stream: impl Read + Write, sessions: &Mutex<Sessions>) -> io::Result<()> {
+    let mut session: Option<String> = None;
+    while let Some(payload) = read_frame(&mut stream)? {
+        let mut sessions = sessions.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+        let response = match (Request::decode(&payload), &session) {
+            (None, _) => Response::error(status::BAD_REQUEST, "malformed request"),
+            (Some(Request::Session(id)), None) if session::valid_id(&id) => {
+                sessions.with(&id, now, |_| ());
+                session = Some(id);
+                Response::ok(Vec::new())
+            }
+            (Some(Request::Session(_)), _) => {
+                Response::error(status::BAD_REQUEST, "invalid or second session ID")
+            }
+            (Some(_), None) => Response::error(status::NO_SESSION, "no session"),
+            (Some(request), Some(id)) => sessions
+                .with(id, now, |e| handle(e, request))
+                .unwrap_or_else(|| Response::error(status::NO_SESSION, "no session")),
+        };
+        drop(sessions);
+        write_frame(&mut stream, &response.encode())?;
+    }
+    Ok(())
+}
+
+/// Accept connections at `path` on a background thread, one thread per
+/// connection
+///
+/// `path` is a socket file on Unix (replaced if stale, owner-only) and a
+/// pipe name (`\\.\pipe\gonhanh`) on Windows, local clients only.
+pub fn listen(path: &str, engine: &'static Mutex<Option<Engine>>) -> io::Result<JoinHandle<()>> {
+    accept_loop(path, move |stream| {
+        let _ = serve(stream, engine);
+    })
+}
+
+/// `listen` for a multi-user host (see `serve_sessions`)
+pub fn listen_sessions(
+    path: &str,
+    sessions: &'static Mutex<Sessions>,
+) -> io::Result<JoinHandle<()>> {
+    accept_loop(path, move |stream| {
+        let _ = serve_sessions(stream, sessions);
+    })
+}
+
+fn accept_loop(
+    path: &str,
+    serve: impl Fn(transport::Stream) + Copy + Send + 'static,
+) -> io::Result<JoinHandle<()>> {
+    let listener = transport::Listener::bind(path)?;
+    Ok(std::thread::spawn(move || loop {
+        match listener.accept() {
+            Ok(stream) => {
+                std::thread::spawn(move || serve(stream));
+            }
+            Err(_) => std::thread::sleep(std::time::Duration::from_millis(100)),
+        }
+    }))
+}
+
+/// Connection to an engine served by `listen`
+pub struct Client<S> {
+    stream: S,
+}
+
+#[cfg(any(unix, windows))]
+impl Client<transport::Stream> {
+    /// Connect to the engine served at `path`
+    pub fn connect(path: &str) -> io::Result<Self> {
+        Ok(Client::new(transport::connect(path)?))
+    }
+}
+
+impl<S: Read + Write> Client<S> {
+    pub fn new(stream: S) -> Self {
+        Client { stream }
+    }
+
+    pub fn request(&mut self, request: &Request) -> io::Result<Response> {
+        write_frame(&mut self.stream, &request.encode())?;
+        let payload = read_frame(&mut self.stream)?
+            .ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))?;
+        Response::decode(&payload)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "empty response"))
+    }
+
+    /// Process a key: `(action, backspace, text)` as in `ime_key_ext`
+    pub fn process_key(
+        &mut self,
+        key: u16,
+        caps: bool,
+        ctrl: bool,
+        shift: bool,
+    ) -> io::Result<(u8, u8, String)> {
+        let r = self.request(&Request::ProcessKey {
+            key,
+            caps,
+            ctrl,
+            shift,
+        })?;
+        match (r.status, &r.body[..]) {
+            (status::OK, [action, backspace, text @ ..]) => Ok((
+                *action,
+                *backspace,
+                String::from_utf8_lossy(text).into_owned(),
+            )),
+            _ => Err(remote_error(&r)),
+        }
+    }
+
+    /// Apply settings; a rejected config comes back as `InvalidInput`
+    pub fn set_config(&mut self, toml: &str) -> io::Result<()> {
+        let r = self.request(&Request::SetConfig(toml.to_string()))?;
+        match r.status {
+            status::OK => Ok(()),
+            _ => Err(remote_error(&r)),
+        }
+    }
+
+    /// `"config"`, `"profile"` or `"word"`
+    pub fn query(&mut self, name: &str) -> io::Result<String> {
+        let r = self.request(&Request::Query(name.to_string()))?;
+        match r.status {
+            status::OK => Ok(r.text()),
+            _ => Err(remote_error(&r)),
+        }
+    }
+}
+
+fn remote_error(r: &Response) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("engine error {}: {}", r.status, r.text()),
+    )
+}
+
+#[cfg(unix)]
+mod transport {
+    use std::io;
+    use std::os::unix::fs::PermissionsExt;
+    use std::os::unix::net::{UnixListener, UnixStream};
+
+    pub type Stream = UnixStream;
+
+    pub struct Listener(UnixListener);
+
+    impl Listener {
+        pub fn bind(path: &str) -> io::Result<Self> {
+            // A socket left by a crashed host would make bind fail
+            if UnixStream::connect(path).is_err() {
+                let _ = std::fs::remove_file(path);
+            }
+            let listener = UnixListener::bind(path)?;
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+            Ok(Listener(listener))
+        }
+
+        pub fn accept(&self) -> io::Result<Stream> {
+            self.0.accept().map(|(stream, _)| stream)
+        }
+    }
+
+    pub fn connect(path: &str) -> io::Result<Stream> {
+        UnixStream::connect(path)
+    }
+}
+
+#[cfg(windows)]
+mod transport {
+    use std::ffi::c_void;
+    use std::fs::{File, OpenOptions};
+    use std::io;
+    use std::os::windows::io::{FromRawHandle, RawHandle};
+
+    pub type Stream = File;
+
+    const PIPE_ACCESS_DUPLEX: u32 = 0x3;
+    const PIPE_REJECT_REMOTE_CLIENTS: u32 = 0x8;
+    const PIPE_UNLIMITED_INSTANCES: u32 = 255;
+    const ERROR_PIPE_CONNECTED: i32 = 535;
+    const BUFFER: u32 = 64 * 1024;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn CreateNamedPipeW(
+            name: *const u16,
+            open_mode: u32,
+            pipe_mode: u32,
+            max_instances: u32,
+            out_buffer: u32,
+            in_buffer: u32,
+            timeout: u32,
+            security: *mut c_void,
+        ) -> RawHandle;
+        fn ConnectNamedPipe(pipe: RawHandle, overlapped: *mut c_void) -> i32;
+    }
+
+    /// Pipe instances are created per connection
+    pub struct Listener(Vec<u16>);
+
+    impl Listener {
+        pub fn bind(path: &str) -> io::Result<Self> {
+            Ok(Listener(path.encode_utf16().chain(Some(0)).collect()))
+        }
+
+        pub fn accept(&self) -> io::Result<Stream> {
+            // Byte mode, blocking (PIPE_TYPE_BYTE | PIPE_WAIT = 0)
+            let pipe = unsafe {
+                CreateNamedPipeW(
+                    self.0.as_ptr(),
+                    PIPE_ACCESS_DUPLEX,
+                    PIPE_REJECT_REMOTE_CLIENTS,
+                    PIPE_UNLIMITED_INSTANCES,
+                    BUFFER,
+                    BUFFER,
+                    0,
+                    std::ptr::null_mut(),
+                )
+            };
+            if pipe as isize == -1 {
+                return Err(io::Error::last_os_error());
+            }
+            // Take ownership first so the handle is closed on error
+            let file = unsafe { File::from_raw_handle(pipe) };
+            if unsafe { ConnectNamedPipe(pipe, std::ptr::null_mut()) } == 0 {
+                let err = io::Error::last_os_error();
+                // The client connected between create and connect
+                if err.raw_os_error() != Some(ERROR_PIPE_CONNECTED) {
+                    return Err(err);
+                }
+            }
+            Ok(file)
+        }
+    }
+
+    pub fn connect(path: &str) -> io::Result<Stream> {
+        OpenOptions::new().read(true).write(true).open(path)
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+mod transport {
+    use std::io;
+
+    pub type Stream = std::fs::File;
+
+    pub struct Listener;
+
+    impl Listener {
+        pub fn bind(_path: &str) -> io::Result<Self> {
+            Err(io::ErrorKind::Unsupported.into())
+        }
+
+        pub fn accept(&self) -> io::Result<Stream> {
+            Err(io::ErrorKind::Unsupported.into())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::keys;
+
+    fn key(key: u16) -> Request {
+        Request::ProcessKey {
+            key,
+            caps: false,
+            ctrl: false,
+            shift: false,
+        }
+    }
+
+    #[test]
+    fn test_request_roundtrip() {
+        let requests = [
+            Request::ProcessKey {
+                key: keys::A,
+                caps: true,
+                ctrl: false,
+                shift: true,
+            },
+            Request::SetConfig("method = \"vni\"\n".into()),
+            Request::Query("word".into()),
+        ];
+        for r in requests {
+            assert_eq!(Request::decode(&r.encode()), Some(r));
+        }
+        assert_eq!(Request::decode(&[]), None);
+        assert_eq!(Request::decode(&[op::PROCESS_KEY, 0]), None);
+        assert_eq!(Request::decode(&[op::QUERY, 0xFF]), None);
+        assert_eq!(Request::decode(&[9]), None);
+    }
+
+    #[test]
+    fn test_frames() {
+        let mut wire = Vec::new();
+        write_frame(&mut wire, b"abc").unwrap();
+        write_frame(&mut wire, b"").unwrap();
+        let mut r = &wire[..];
+        assert_eq!(read_frame(&mut r).unwrap().as_deref(), Some(&b"abc"[..]));
+        assert_eq!(read_frame(&mut r).unwrap().as_deref(), Some(&b""[..]));
+        assert_eq!(read_frame(&mut r).unwrap(), None);
+
+        let huge = ((MAX_FRAME + 1) as u32).to_le_bytes();
+        assert!(read_frame(&mut &huge[..]).is_err());
+        // Cut off mid-frame
+        assert!(read_frame(&mut &wire[..5]).is_err());
+    }
+
+    #[test]
+    fn test_handle() {
+        let mut e = Engine::new();
+        let r = handle(&mut e, key(keys::A));
+        assert_eq!(r.body[..2], [Action::None as u8, 0]);
+        let r = handle(&mut e, key(keys::S));
+        assert_eq!(r.status, status::OK);
+        assert_eq!(
+            r.body,
+            [&[Action::Send as u8, 1][..], "á".as_bytes()].concat()
+        );
+        assert_eq!(handle(&mut e, Request::Query("word".into())).text(), "á");
+
+        let r = handle(&mut e, Request::SetConfig("method = \"vni\"".into()));
+        assert_eq!(r.status, status::OK);
+        let config = handle(&mut e, Request::Query("config".into())).text();
+        assert!(config.contains("method = \"vni\""));
+
+        let r = handle(&mut e, Request::SetConfig("method = \"dvorak\"".into()));
+        assert_eq!(r.status, 4);
+        let r = handle(&mut e, Request::Query("secrets".into()));
+        assert_eq!(r.status, status::UNKNOWN_QUERY);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_socket_shares_engine() {
+        static ENGINE: Mutex<Option<Engine>> = Mutex::new(None);
+        *ENGINE.lock().unwrap() = Some(Engine::new());
+
+        let path = std::env::temp_dir().join(format!("gonhanh-ipc-{}.sock", std::process::id()));
+        let path = path.to_str().unwrap();
+        listen(path, &ENGINE).unwrap();
+
+        let mut a = Client::connect(path).unwrap();
+        let mut b = Client::connect(path).unwrap();
+        a.process_key(keys::V, false, false, false).unwrap();
+        a.process_key(keys::I, false, false, false).unwrap();
+        a.process_key(keys::E, false, false, false).unwrap();
+        a.process_key(keys::E, false, false, false).unwrap();
+        // The second client continues the same word
+        let (action, backspace, text) = b.process_key(keys::J, false, false, false).unwrap();
+        assert_eq!((action, backspace, text.as_str()), (1, 1, "ệ"));
+        assert_eq!(a.query("word").unwrap(), "việ");
+        assert!(b.set_config("bogus = 1").is_err());
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_sessions_isolated() {
+        static SERVED: std::sync::OnceLock<Mutex<Sessions>> = std::sync::OnceLock::new();
+        let sessions =
+            SERVED.get_or_init(|| Mutex::new(Sessions::new(4, std::time::Duration::from_secs(60))));
+
+        let path = std::env::temp_dir().join(format!("gonhanh-ipcs-{}.sock", std::process::id()));
+        let path = path.to_str().unwrap();
+        listen_sessions(path, sessions).unwrap();
+
+        let mut alice = Client::connect(path).unwrap();
+        let mut bob = Client::connect(path).unwrap();
+        let r = bob.request(&Request::Query("word".into())).unwrap();
+        assert_eq!(r.status, status::NO_SESSION);
+        for (client, id) in [(&mut alice, "alice"), (&mut bob, "bob")] {
+            let r = client.request(&Request::Session(id.into())).unwrap();
+            assert_eq!(r.status, status::OK);
+        }
+        let r = bob.request(&Request::Session("alice".into())).unwrap();
+        assert_eq!(r.status, status::BAD_REQUEST);
+
+        alice.process_key(keys::A, false, false, false).unwrap();
+        alice.process_key(keys::A, false, false, false).unwrap();
+        bob.set_config("method = \"vni\"").unwrap();
+        assert_eq!(alice.query("word").unwrap(), "â");
+        assert_eq!(bob.query("word").unwrap(), "");
+        assert!(alice
+            .query("config")
+            .unwrap()
+            .contains("method = \"telex\""));
+        assert_eq!(sessions.lock().unwrap().len(), 2);
+        let _ = std::fs::remove_file(path);
+    }
+}
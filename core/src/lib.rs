@@ -19,24 +19,137 @@
 //! // Clean up on word boundary
 //! ime_clear();
 //! ```
+//!
+//! # Panics
+//!
+//! No `ime_*` call unwinds into the host. A panic makes the call return its
+//! failure value (null, false, 0 or `IME_ERR_PANIC`) with the message in
+//! `ime_last_error`, and the engine drops the word being typed.
 
-pub mod data;
+#[cfg(feature = "count-allocs")]
+pub mod alloc_count;
+pub mod collate;
+pub mod config;
+pub mod correction_pack;
 pub mod engine;
+pub mod ffi_windows;
+#[cfg(feature = "macros")]
+pub mod importer;
 pub mod input;
+#[cfg(feature = "ipc")]
+pub mod ipc;
+pub mod json;
+#[cfg(feature = "suggestions")]
+pub mod lint;
+#[cfg(feature = "ml")]
+pub mod ml;
+#[cfg(feature = "conversion")]
+pub mod paste;
+#[cfg(feature = "plugins")]
+pub mod plugin;
+pub mod practice;
+pub mod profile;
+#[cfg(feature = "conversion")]
+pub mod recompose;
+pub mod search;
+#[cfg(feature = "conversion")]
+pub mod selection;
+pub mod session;
+pub mod storage;
+#[cfg(feature = "suggestions")]
+pub mod suggest;
+pub mod sync;
+pub mod tokenizer;
 pub mod updater;
 pub mod utils;
 
+/// Linguistic tables, re-exported from the `gonhanh-data` crate
+pub use gonhanh_data as data;
+
+use config::EngineConfig;
+use engine::history::Privacy;
+use engine::observer::Notification;
+use engine::shortcut::Shortcut;
+#[cfg(feature = "macros")]
+use engine::shortcut::ShortcutConflict;
 use engine::{Engine, Result};
+use practice::PracticeSession;
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_void};
 use std::sync::Mutex;
+use std::time::Duration;
+use storage::{
+    CallbackStorage, FileStorage, ReadCallback, RemoveCallback, StorageError, WriteCallback,
+};
+use sync::{CallbackTransport, PullCallback, PushCallback};
 
 // Global engine instance (thread-safe via Mutex)
 static ENGINE: Mutex<Option<Engine>> = Mutex::new(None);
 
-/// Lock the engine mutex, recovering from poisoned state if needed (for tests)
+// Typing practice run (see `ime_practice_start`), separate from the engine
+static PRACTICE: Mutex<Option<PracticeSession>> = Mutex::new(None);
+
+// Message of the last failed FFI call (see `ime_last_error`)
+static LAST_ERROR: Mutex<Option<String>> = Mutex::new(None);
+
+fn set_last_error(msg: String) {
+    *LAST_ERROR.lock().unwrap_or_else(|e| e.into_inner()) = Some(msg);
+}
+
+/// Hand a Rust string to the host (free with `ime_free_string`)
+fn into_c_string(s: String) -> *mut c_char {
+    // Interior NUL cannot appear in our TOML/messages; strip defensively
+    CString::new(s.replace('\0', ""))
+        .map(CString::into_raw)
+        .unwrap_or(std::ptr::null_mut())
+}
+
+/// Lock the engine mutex, recovering from poisoned state if needed
 fn lock_engine() -> std::sync::MutexGuard<'static, Option<Engine>> {
     ENGINE.lock().unwrap_or_else(|e| e.into_inner())
 }
 
+/// Handle a key with the engine, boxed for the host (null if not
+/// initialized); notifications it raised go out once the lock is released
+fn key_result(f: impl FnOnce(&mut Engine) -> Result) -> *mut Result {
+    let r = lock_engine().as_mut().map(f);
+    flush_notifications();
+    r.map_or(std::ptr::null_mut(), |r| Box::into_raw(Box::new(r)))
+}
+
+/// Run the body of an FFI call, turning a panic into `fallback`
+///
+/// These calls run on the host's keyboard thread, where unwinding out of
+/// `extern "C"` aborts the process and takes system-wide input with it.
+/// A caught panic is reported through `ime_last_error` and the engine
+/// starts over (see `recover_after_panic`).
+fn ffi_guard<T>(name: &str, fallback: T, f: impl FnOnce() -> T) -> T {
+    match std::panic::catch_unwind(std::panic::AssertUnwindSafe(f)) {
+        Ok(value) => value,
+        Err(panic) => {
+            set_last_error(format!(
+                "{} panicked: {}",
+                name,
+                utils::panic_message(&*panic)
+            ));
+            recover_after_panic();
+            fallback
+        }
+    }
+}
+
+/// Un-poison the globals and drop the engine's typing state
+///
+/// The word in flight may have been half-updated when the panic hit;
+/// settings, shortcuts and subscriptions are kept.
+fn recover_after_panic() {
+    ENGINE.clear_poison();
+    PRACTICE.clear_poison();
+    if let Some(ref mut e) = *lock_engine() {
+        e.recover();
+    }
+}
+
 // ============================================================
 // FFI Interface
 // ============================================================
@@ -45,13 +158,20 @@ fn lock_engine() -> std::sync::MutexGuard<'static, Option<Engine>> {
 ///
 /// Must be called exactly once before any other `ime_*` functions.
 /// Thread-safe: uses internal mutex.
-///
-/// # Panics
-/// Panics if mutex is poisoned (only if previous call panicked).
 #[no_mangle]
 pub extern "C" fn ime_init() {
-    let mut guard = lock_engine();
-    *guard = Some(Engine::new());
+    ffi_guard("ime_init", (), || {
+        let mut engine = Engine::new();
+        // Queue changes; FFI subscribers run after the engine lock is released.
+        // Suggestions are polled with `ime_suggestion` instead.
+        engine.subscribe(|n| {
+            if let Notification::SettingChanged(_) | Notification::Feedback(_) = n {
+                PENDING.lock().unwrap_or_else(|e| e.into_inner()).push(*n);
+            }
+        });
+        let mut guard = lock_engine();
+        *guard = Some(engine);
+    })
 }
 
 /// Process a key event and return the result.
@@ -68,21 +188,20 @@ pub extern "C" fn ime_init() {
 /// # Result struct
 /// * `action`: 0=None (pass through), 1=Send (replace text), 2=Restore
 /// * `backspace`: number of characters to delete
-/// * `chars`: UTF-32 codepoints to insert
+/// * `chars`: UTF-32 codepoints to insert; a trailing `'\n'` after Enter
+///   means press Return (see `ime_set_enter_after_edit`)
 /// * `count`: number of valid chars
+/// * `caret_back`: characters to move the caret left after inserting (a
+///   shortcut expansion with a `{|}` marker)
 ///
 /// # Note
 /// For VNI mode with Shift+number keys (to type @, #, $ etc.),
 /// use `ime_key_ext` with the shift parameter.
 #[no_mangle]
 pub extern "C" fn ime_key(key: u16, caps: bool, ctrl: bool) -> *mut Result {
-    let mut guard = lock_engine();
-    if let Some(ref mut e) = *guard {
-        let r = e.on_key(key, caps, ctrl);
-        Box::into_raw(Box::new(r))
-    } else {
-        std::ptr::null_mut()
-    }
+    ffi_guard("ime_key", std::ptr::null_mut(), || {
+        key_result(|e| e.on_key(key, caps, ctrl))
+    })
 }
 
 /// Process a key event with extended parameters.
@@ -105,13 +224,146 @@ pub extern "C" fn ime_key(key: u16, caps: bool, ctrl: bool) -> *mut Result {
 /// - etc.
 #[no_mangle]
 pub extern "C" fn ime_key_ext(key: u16, caps: bool, ctrl: bool, shift: bool) -> *mut Result {
-    let mut guard = lock_engine();
-    if let Some(ref mut e) = *guard {
-        let r = e.on_key_ext(key, caps, ctrl, shift);
-        Box::into_raw(Box::new(r))
-    } else {
-        std::ptr::null_mut()
-    }
+    ffi_guard("ime_key_ext", std::ptr::null_mut(), || {
+        key_result(|e| e.on_key_ext(key, caps, ctrl, shift))
+    })
+}
+
+/// Same as `ime_key_ext`, with the host's monotonic clock.
+///
+/// With `idle_commit_secs` set, a word left unfinished that long is
+/// committed as shown before this key is handled. The same key again
+/// within 50 ms is auto-repeat, handled per `repeat_key`.
+///
+/// # Arguments
+/// * `time_ms` - monotonic time in milliseconds (`mach_absolute_time`,
+///   `GetTickCount64`, `CLOCK_MONOTONIC`)
+///
+/// # Returns
+/// * Pointer to `Result` struct (caller must free with `ime_free`)
+/// * `null` if engine not initialized
+#[no_mangle]
+pub extern "C" fn ime_key_at(
+    time_ms: u64,
+    key: u16,
+    caps: bool,
+    ctrl: bool,
+    shift: bool,
+) -> *mut Result {
+    ffi_guard("ime_key_at", std::ptr::null_mut(), || {
+        key_result(|e| e.on_key_at(time_ms, key, caps, ctrl, shift))
+    })
+}
+
+/// Process a full key event (key-down or key-up with modifier flags).
+///
+/// Preferred over `ime_key_ext` for hosts that can observe key-ups: the
+/// engine then tells a held key (auto-repeat) from the same key typed twice.
+///
+/// # Arguments
+/// * `key` - macOS virtual keycode
+/// * `modifiers` - bitmask: 1=Shift, 2=Ctrl, 4=Alt/Option, 8=Cmd/Win, 16=CapsLock
+/// * `down` - true for key-down, false for key-up
+/// * `repeat` - true if the OS reports this key-down as auto-repeat
+///
+/// # Returns
+/// * Pointer to `Result` struct (caller must free with `ime_free`)
+/// * `null` if engine not initialized
+///
+/// Key-up events always return action=0 (pass through).
+#[no_mangle]
+pub extern "C" fn ime_key_event(key: u16, modifiers: u8, down: bool, repeat: bool) -> *mut Result {
+    ffi_guard("ime_key_event", std::ptr::null_mut(), || {
+        key_result(|e| {
+            e.on_event(engine::event::KeyEvent {
+                key,
+                modifiers,
+                down,
+                repeat,
+                time_ms: 0,
+            })
+        })
+    })
+}
+
+/// Process buffered key events in one call.
+///
+/// Same as calling `ime_key_event` for each, under a single lock, except
+/// that consecutive edits are merged into one on the last of them (the
+/// earlier keys get action=1 with nothing to delete or insert: swallow the
+/// key). Redraws once instead of per key during fast typing or replay.
+///
+/// # Arguments
+/// * `events` - array of `len` events (`key: u16, modifiers: u8, down: bool,
+///   repeat: bool, time_ms: u64`, C layout; `time_ms` as in `ime_key_at`,
+///   0 if unknown)
+/// * `out` - array of `len` results, filled in order
+///
+/// # Returns
+/// `0` on success, `-1` if a pointer is null or engine not initialized.
+///
+/// # Safety
+/// `events` and `out` must point to `len` valid elements (may be null if
+/// `len` is 0).
+#[no_mangle]
+pub unsafe extern "C" fn ime_key_events(
+    events: *const engine::event::KeyEvent,
+    len: usize,
+    out: *mut Result,
+) -> i32 {
+    ffi_guard("ime_key_events", IME_ERR_PANIC, || {
+        if len == 0 {
+            return 0;
+        }
+        if events.is_null() || out.is_null() {
+            set_last_error("events or out is null".into());
+            return IME_ERR_INVALID_ARG;
+        }
+        let events = std::slice::from_raw_parts(events, len);
+        let out = std::slice::from_raw_parts_mut(out, len);
+
+        let mut guard = lock_engine();
+        let code = match *guard {
+            Some(ref mut e) => {
+                for (slot, output) in out.iter_mut().zip(e.process_keys(events)) {
+                    *slot = output.into();
+                }
+                0
+            }
+            None => {
+                set_last_error("engine not initialized".into());
+                IME_ERR_INVALID_ARG
+            }
+        };
+        drop(guard);
+        flush_notifications();
+        code
+    })
+}
+
+/// Process a character composed by the OS layout (dead keys, another IME).
+///
+/// # Arguments
+/// * `codepoint` - Unicode scalar value of the inserted character
+///
+/// # Returns
+/// * Pointer to `Result` struct (caller must free with `ime_free`)
+/// * `null` if engine not initialized
+///
+/// Composed letters are recorded so following keystrokes build on them;
+/// the host still inserts the character itself (action=0).
+/// Invalid codepoints end the current word.
+#[no_mangle]
+pub extern "C" fn ime_text(codepoint: u32) -> *mut Result {
+    ffi_guard("ime_text", std::ptr::null_mut(), || {
+        key_result(|e| match char::from_u32(codepoint) {
+            Some(ch) => e.on_text(ch),
+            None => {
+                e.clear();
+                Result::none()
+            }
+        })
+    })
 }
 
 /// Set the input method.
@@ -119,13 +371,21 @@ pub extern "C" fn ime_key_ext(key: u16, caps: bool, ctrl: bool, shift: bool) ->
 /// # Arguments
 /// * `method` - 0 for Telex, 1 for VNI
 ///
-/// No-op if engine not initialized.
+/// Refused if settings conflict with the new method (bracket shortcuts
+/// under VNI); `ime_last_error` then says why. No-op if engine not
+/// initialized.
 #[no_mangle]
 pub extern "C" fn ime_method(method: u8) {
-    let mut guard = lock_engine();
-    if let Some(ref mut e) = *guard {
-        e.set_method(method);
-    }
+    ffi_guard("ime_method", (), || {
+        let mut guard = lock_engine();
+        if let Some(ref mut e) = *guard {
+            if let Err(err) = e.set_method(method) {
+                set_last_error(err.to_string());
+            }
+        }
+        drop(guard);
+        flush_notifications();
+    })
 }
 
 /// Enable or disable the engine.
@@ -134,10 +394,14 @@ pub extern "C" fn ime_method(method: u8) {
 /// No-op if engine not initialized.
 #[no_mangle]
 pub extern "C" fn ime_enabled(enabled: bool) {
-    let mut guard = lock_engine();
-    if let Some(ref mut e) = *guard {
-        e.set_enabled(enabled);
-    }
+    ffi_guard("ime_enabled", (), || {
+        let mut guard = lock_engine();
+        if let Some(ref mut e) = *guard {
+            e.set_enabled(enabled);
+        }
+        drop(guard);
+        flush_notifications();
+    })
 }
 
 /// Set modern tone style.
@@ -147,10 +411,38 @@ pub extern "C" fn ime_enabled(enabled: bool) {
 /// No-op if engine not initialized.
 #[no_mangle]
 pub extern "C" fn ime_modern(modern: bool) {
-    let mut guard = lock_engine();
-    if let Some(ref mut e) = *guard {
-        e.set_modern(modern);
-    }
+    ffi_guard("ime_modern", (), || {
+        let mut guard = lock_engine();
+        if let Some(ref mut e) = *guard {
+            e.set_modern(modern);
+        }
+        drop(guard);
+        flush_notifications();
+    })
+}
+
+/// Turn one auto-restore check on or off.
+///
+/// # Arguments
+/// * `check` - 0=dictionary, 1=invalid, 2=mark-final, 3=w-initial,
+///   4=modifier-consonant, 5=modifier-vowel-pair, 6=modifier-between-vowels,
+///   7=w-final, 8=english-suffix, 9=bigram (other values are ignored)
+/// * `enabled` - false to stop the check from restoring words
+///
+/// No-op if engine not initialized.
+#[no_mangle]
+pub extern "C" fn ime_restore_check(check: u8, enabled: bool) {
+    ffi_guard("ime_restore_check", (), || {
+        let Some(&check) = config::RestoreCheck::ALL.get(check as usize) else {
+            return;
+        };
+        let mut guard = lock_engine();
+        if let Some(ref mut e) = *guard {
+            e.set_restore_check(check, enabled);
+        }
+        drop(guard);
+        flush_notifications();
+    })
 }
 
 /// Clear the input buffer.
@@ -159,164 +451,2187 @@ pub extern "C" fn ime_modern(modern: bool) {
 /// No-op if engine not initialized.
 #[no_mangle]
 pub extern "C" fn ime_clear() {
-    let mut guard = lock_engine();
-    if let Some(ref mut e) = *guard {
-        e.clear();
-    }
+    ffi_guard("ime_clear", (), || {
+        let mut guard = lock_engine();
+        if let Some(ref mut e) = *guard {
+            e.clear();
+        }
+    })
 }
 
-/// Free a result pointer returned by `ime_key`.
+/// The caret moved without typing (click, arrow keys, Home/End).
 ///
-/// # Safety
-/// * `r` must be a pointer returned by `ime_key`, or null
-/// * Must be called exactly once per non-null `ime_key` return
-/// * Do not use `r` after calling this function
+/// Ends the current word so no transform applies across the jump.
+///
+/// # Arguments
+/// * `offset` - new caret offset in UTF-16 units, negative if unknown
 #[no_mangle]
-pub unsafe extern "C" fn ime_free(r: *mut Result) {
-    if !r.is_null() {
-        drop(Box::from_raw(r));
-    }
+pub extern "C" fn ime_caret_moved(offset: i64) {
+    ffi_guard("ime_caret_moved", (), || {
+        if let Some(ref mut e) = *lock_engine() {
+            e.caret_moved(usize::try_from(offset).ok());
+        }
+    })
 }
 
-// ============================================================
-// Shortcut FFI
-// ============================================================
-
-/// Add a shortcut to the engine.
+/// A mouse click or scroll happened.
 ///
-/// # Arguments
-/// * `trigger` - C string for trigger (e.g., "vn")
-/// * `replacement` - C string for replacement (e.g., "Việt Nam")
+/// Ends or keeps the current word per the `mouse_click` / `mouse_scroll`
+/// settings (by default a click commits it, a scroll keeps it). Report
+/// clicks even when the caret offset is unknown: otherwise the engine may
+/// edit the word at the old position.
 ///
-/// # Safety
-/// Both pointers must be valid null-terminated UTF-8 strings.
+/// # Arguments
+/// * `kind` - 0=click, 1=scroll (other values are ignored)
 #[no_mangle]
-pub unsafe extern "C" fn ime_add_shortcut(
-    trigger: *const std::os::raw::c_char,
-    replacement: *const std::os::raw::c_char,
-) {
-    if trigger.is_null() || replacement.is_null() {
-        return;
-    }
+pub extern "C" fn ime_mouse(kind: u8) {
+    use engine::event::MouseEvent;
 
-    let trigger_str = match std::ffi::CStr::from_ptr(trigger).to_str() {
-        Ok(s) => s,
-        Err(_) => return,
-    };
-    let replacement_str = match std::ffi::CStr::from_ptr(replacement).to_str() {
-        Ok(s) => s,
-        Err(_) => return,
-    };
+    ffi_guard("ime_mouse", (), || {
+        let event = match kind {
+            0 => MouseEvent::Click,
+            1 => MouseEvent::Scroll,
+            _ => return,
+        };
+        if let Some(ref mut e) = *lock_engine() {
+            e.on_mouse(event);
+        }
+        flush_notifications();
+    })
+}
 
-    let mut guard = lock_engine();
-    if let Some(ref mut e) = *guard {
-        e.shortcuts_mut().add(engine::shortcut::Shortcut::new(
-            trigger_str,
-            replacement_str,
-        ));
-    }
+/// Focus moved to another field, window or app.
+///
+/// Ends the current word and forgets the caret offset.
+#[no_mangle]
+pub extern "C" fn ime_focus_changed() {
+    ffi_guard("ime_focus_changed", (), || {
+        if let Some(ref mut e) = *lock_engine() {
+            e.focus_changed();
+        }
+    })
 }
 
-/// Remove a shortcut from the engine.
+/// Re-seed the current word from the text before the caret.
 ///
-/// # Arguments
-/// * `trigger` - C string for trigger to remove
+/// Call after `ime_caret_moved` / `ime_focus_changed` when the host can
+/// read the field: the last word of `prefix` becomes the word being typed,
+/// so typing `j` right after "viet" gives "việt".
+///
+/// # Returns
+/// * Number of characters taken from `prefix` (0 = nothing to continue)
+/// * `-1` if `prefix` is null or not UTF-8, or engine not initialized
 ///
 /// # Safety
-/// Pointer must be a valid null-terminated UTF-8 string.
+/// `prefix` must be a valid null-terminated string or null.
 #[no_mangle]
-pub unsafe extern "C" fn ime_remove_shortcut(trigger: *const std::os::raw::c_char) {
-    if trigger.is_null() {
-        return;
-    }
+pub unsafe extern "C" fn ime_text_context(prefix: *const c_char) -> i32 {
+    ffi_guard("ime_text_context", IME_ERR_PANIC, || {
+        if prefix.is_null() {
+            set_last_error("prefix is null".into());
+            return IME_ERR_INVALID_ARG;
+        }
+        let Ok(prefix) = CStr::from_ptr(prefix).to_str() else {
+            set_last_error("prefix is not valid UTF-8".into());
+            return IME_ERR_INVALID_ARG;
+        };
+        match *lock_engine() {
+            Some(ref mut e) => e.text_context(prefix) as i32,
+            None => {
+                set_last_error("engine not initialized".into());
+                IME_ERR_INVALID_ARG
+            }
+        }
+    })
+}
 
-    let trigger_str = match std::ffi::CStr::from_ptr(trigger).to_str() {
-        Ok(s) => s,
-        Err(_) => return,
-    };
+/// Check the field against what the engine sent (lost-edit detection).
+///
+/// Call after delivering an edit, with the field's text before the caret.
+/// On a mismatch the engine drops the word and switches to one edit per
+/// word (the BOUNDARY profile) until `ime_set_output_profile` is called again.
+///
+/// # Returns
+/// * `0` nothing to compare, `1` the field matches, `2` an edit was lost
+/// * `-1` if `prefix` is null or not UTF-8, or engine not initialized
+///
+/// # Safety
+/// `prefix` must be a valid null-terminated string or null.
+#[no_mangle]
+pub unsafe extern "C" fn ime_check_field(prefix: *const c_char) -> i32 {
+    use engine::output::FieldCheck;
 
-    let mut guard = lock_engine();
-    if let Some(ref mut e) = *guard {
-        e.shortcuts_mut().remove(trigger_str);
-    }
+    ffi_guard("ime_check_field", IME_ERR_PANIC, || {
+        if prefix.is_null() {
+            set_last_error("prefix is null".into());
+            return IME_ERR_INVALID_ARG;
+        }
+        let Ok(prefix) = CStr::from_ptr(prefix).to_str() else {
+            set_last_error("prefix is not valid UTF-8".into());
+            return IME_ERR_INVALID_ARG;
+        };
+        match *lock_engine() {
+            Some(ref mut e) => match e.check_field(prefix) {
+                FieldCheck::Unknown => 0,
+                FieldCheck::Match => 1,
+                FieldCheck::Mismatch => 2,
+            },
+            None => {
+                set_last_error("engine not initialized".into());
+                IME_ERR_INVALID_ARG
+            }
+        }
+    })
 }
 
-/// Clear all shortcuts from the engine.
+/// Number of lost edits `ime_check_field` has recorded (at most 16 kept).
 #[no_mangle]
-pub extern "C" fn ime_clear_shortcuts() {
-    let mut guard = lock_engine();
-    if let Some(ref mut e) = *guard {
-        e.shortcuts_mut().clear();
-    }
+pub extern "C" fn ime_ghost_edit_count() -> u32 {
+    ffi_guard("ime_ghost_edit_count", 0, || match *lock_engine() {
+        Some(ref e) => e.ghost_edits().len() as u32,
+        None => 0,
+    })
 }
 
-// ============================================================
-// Tests
-// ============================================================
+/// Number of times edits came faster than the `edit_limit` setting and
+/// the engine stopped editing, taking them for a feedback loop (the host
+/// capturing the keys it injects). Poll it to log the loop or warn the
+/// user; the engine resumes on its own once the edits stop. Edits are
+/// timed by the host clock of `ime_key_at`; keys sent without a time are
+/// never counted.
+#[no_mangle]
+pub extern "C" fn ime_runaway_count() -> u32 {
+    ffi_guard("ime_runaway_count", 0, || match *lock_engine() {
+        Some(ref e) => e.runaways(),
+        None => 0,
+    })
+}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::data::keys;
-    use serial_test::serial;
-    use std::ffi::CString;
+/// Keystrokes that type a composed word ("việt" → "vieetj").
+///
+/// # Arguments
+/// * `word` - one Vietnamese word (UTF-8)
+/// * `method` - 0=Telex, 1=VNI
+///
+/// # Returns
+/// The keystrokes (free with `ime_free_string`), or null if `word` is null
+/// or not a Vietnamese word (details via `ime_last_error`).
+///
+/// # Safety
+/// `word` must be a valid null-terminated string or null.
+#[no_mangle]
+pub unsafe extern "C" fn ime_to_keystrokes(word: *const c_char, method: u8) -> *mut c_char {
+    ffi_guard("ime_to_keystrokes", std::ptr::null_mut(), || {
+        if word.is_null() {
+            set_last_error("word is null".into());
+            return std::ptr::null_mut();
+        }
+        let word = CStr::from_ptr(word).to_string_lossy();
+        match input::to_keystrokes(&word, config::Method::from_id(method)) {
+            Some(keys) => into_c_string(keys),
+            None => {
+                set_last_error(format!("`{}` is not a Vietnamese word", word));
+                std::ptr::null_mut()
+            }
+        }
+    })
+}
 
-    #[test]
-    #[serial]
-    fn test_ffi_flow() {
-        ime_init();
-        ime_method(0); // Telex
+/// Accent popup for an on-screen keyboard key ("a" → "aáàảãạăắằẳẵặâấầẩẫậ").
+///
+/// # Arguments
+/// * `label` - the key's letter as a Unicode scalar (either case)
+///
+/// # Returns
+/// The candidates in popup order, as one UTF-8 string (free with
+/// `ime_free_string`); empty for keys that aren't letters.
+#[no_mangle]
+pub extern "C" fn ime_long_press(label: u32) -> *mut c_char {
+    ffi_guard("ime_long_press", std::ptr::null_mut(), || {
+        let candidates = char::from_u32(label)
+            .map(data::long_press::candidates)
+            .unwrap_or_default();
+        into_c_string(candidates.into_iter().collect())
+    })
+}
 
-        // Type 'a' + 's' -> á
-        let r1 = ime_key(keys::A, false, false);
-        assert!(!r1.is_null());
-        unsafe { ime_free(r1) };
+/// Compare two strings in Vietnamese dictionary order (see `collate`).
+///
+/// # Returns
+/// -1, 0 or 1 as `a` sorts before, with or after `b`. Null sorts as "".
+///
+/// # Safety
+/// `a` and `b` must be valid null-terminated strings or null.
+#[no_mangle]
+pub unsafe extern "C" fn ime_collate(a: *const c_char, b: *const c_char) -> i32 {
+    ffi_guard("ime_collate", 0, || {
+        let text = |p: *const c_char| {
+            if p.is_null() {
+                Default::default()
+            } else {
+                CStr::from_ptr(p).to_string_lossy()
+            }
+        };
+        collate::compare(&text(a), &text(b)) as i32
+    })
+}
 
-        let r2 = ime_key(keys::S, false, false);
-        assert!(!r2.is_null());
-        unsafe {
-            assert_eq!((*r2).chars[0], 'á' as u32);
-            ime_free(r2);
+/// Diacritic-free, lowercase search key of `text` ("Đường" → "duong").
+///
+/// # Returns
+/// The key (free with `ime_free_string`), or null if `text` is null.
+///
+/// # Safety
+/// `text` must be a valid null-terminated string or null.
+#[no_mangle]
+pub unsafe extern "C" fn ime_search_key(text: *const c_char) -> *mut c_char {
+    ffi_guard("ime_search_key", std::ptr::null_mut(), || {
+        if text.is_null() {
+            set_last_error("text is null".into());
+            return std::ptr::null_mut();
         }
+        let text = CStr::from_ptr(text).to_string_lossy();
+        into_c_string(search::search_key(&text).key)
+    })
+}
 
-        ime_clear();
-    }
-
-    #[test]
-    #[serial]
-    fn test_shortcut_ffi_add_and_clear() {
-        ime_init();
-        ime_clear_shortcuts(); // Clear any existing shortcuts
-        ime_method(0); // Telex
-
-        // Add a shortcut via FFI
-        let trigger = CString::new("vn").unwrap();
-        let replacement = CString::new("Việt Nam").unwrap();
+/// Search hash of `text`: equal for strings that differ only in
+/// diacritics, case or the i/y spelling ("Lý" and "li"). Stable across
+/// platforms and releases.
+///
+/// # Safety
+/// `text` must be a valid null-terminated string or null (hashed as "").
+#[no_mangle]
+pub unsafe extern "C" fn ime_search_hash(text: *const c_char) -> u64 {
+    ffi_guard("ime_search_hash", 0, || {
+        let text = if text.is_null() {
+            Default::default()
+        } else {
+            CStr::from_ptr(text).to_string_lossy()
+        };
+        search::search_key(&text).hash
+    })
+}
 
-        unsafe {
-            ime_add_shortcut(trigger.as_ptr(), replacement.as_ptr());
-        }
+/// "Did you mean" suggestions for `word` (see `suggest`).
+///
+/// # Arguments
+/// * `word` - the word to look up (UTF-8)
+/// * `max_distance` - most character edits away a suggestion may be
+///
+/// # Returns
+/// A JSON array of `{"word": ..., "score": ...}`, best first (free with
+/// `ime_free_string`), or null if `word` is null.
+///
+/// # Safety
+/// `word` must be a valid null-terminated string or null.
+#[cfg(feature = "suggestions")]
+#[no_mangle]
+pub unsafe extern "C" fn ime_suggest_similar(word: *const c_char, max_distance: u8) -> *mut c_char {
+    use json::Value;
 
-        // Verify shortcut was added by checking engine state
-        let guard = lock_engine();
-        if let Some(ref e) = *guard {
-            assert_eq!(e.shortcuts().len(), 1);
+    ffi_guard("ime_suggest_similar", std::ptr::null_mut(), || {
+        if word.is_null() {
+            set_last_error("word is null".into());
+            return std::ptr::null_mut();
         }
-        drop(guard);
+        let word = CStr::from_ptr(word).to_string_lossy();
+        let found = suggest::suggest_similar(&word, max_distance as usize)
+            .into_iter()
+            .map(|(w, score)| {
+                Value::Obj(vec![
+                    ("word".into(), Value::Str(w.into())),
+                    ("score".into(), Value::Num(score as f64)),
+                ])
+            })
+            .collect();
+        into_c_string(Value::Arr(found).to_pretty())
+    })
+}
 
-        // Clear all shortcuts
-        ime_clear_shortcuts();
+/// Words in a document to underline (see `lint`), using the engine's
+/// correction packs when it is initialized.
+///
+/// # Returns
+/// A JSON array of `{"start", "end", "kind", "message", "suggestions"}`
+/// in order, offsets in UTF-16 units (free with `ime_free_string`), or
+/// null if `text` is null. `kind` is "misspelled", "correction" or
+/// "iy-spelling".
+///
+/// # Safety
+/// `text` must be a valid null-terminated string or null.
+#[cfg(feature = "suggestions")]
+#[no_mangle]
+pub unsafe extern "C" fn ime_lint(text: *const c_char) -> *mut c_char {
+    use json::Value;
 
-        // Verify shortcuts cleared
-        let guard = lock_engine();
-        if let Some(ref e) = *guard {
-            assert_eq!(e.shortcuts().len(), 0);
+    ffi_guard("ime_lint", std::ptr::null_mut(), || {
+        if text.is_null() {
+            set_last_error("text is null".into());
+            return std::ptr::null_mut();
         }
-        drop(guard);
+        let text = CStr::from_ptr(text).to_string_lossy();
+        let issues = match *lock_engine() {
+            Some(ref e) => e.lint(&text),
+            None => lint::lint(&text),
+        };
+        let utf16 = |byte: usize| text[..byte].encode_utf16().count() as f64;
+        let found = issues
+            .into_iter()
+            .map(|issue| {
+                let suggestions = issue.suggestions.into_iter().map(Value::Str).collect();
+                Value::Obj(vec![
+                    ("start".into(), Value::Num(utf16(issue.span.start))),
+                    ("end".into(), Value::Num(utf16(issue.span.end))),
+                    ("kind".into(), Value::Str(issue.kind.as_str().into())),
+                    ("message".into(), Value::Str(issue.kind.message().into())),
+                    ("suggestions".into(), Value::Arr(suggestions)),
+                ])
+            })
+            .collect();
+        into_c_string(Value::Arr(found).to_pretty())
+    })
+}
+
+/// Converted version of pasted text typed without an IME
+/// ("xin chaof cacs banj" or "xin chao cac ban" → "xin chào các bạn").
+///
+/// For a "Convert pasted text" menu item; see `recompose`.
+///
+/// # Returns
+/// The converted text (free with `ime_free_string`), or null if `text` is
+/// null or there is nothing to convert.
+///
+/// # Safety
+/// `text` must be a valid null-terminated string or null.
+#[cfg(feature = "conversion")]
+#[no_mangle]
+pub unsafe extern "C" fn ime_recompose_pasted(text: *const c_char) -> *mut c_char {
+    ffi_guard("ime_recompose_pasted", std::ptr::null_mut(), || {
+        if text.is_null() {
+            set_last_error("text is null".into());
+            return std::ptr::null_mut();
+        }
+        let text = CStr::from_ptr(text).to_string_lossy();
+        match recompose::recompose_pasted(&text) {
+            Some(r) => into_c_string(r.text),
+            None => std::ptr::null_mut(),
+        }
+    })
+}
+
+/// Ways to paste `text`, for a "Paste as Vietnamese…" submenu; see
+/// `paste`.
+///
+/// # Returns
+/// A JSON array of `{"kind", "label", "preview", "text"}`, likeliest first
+/// and empty if the text is best pasted as it is (free with
+/// `ime_free_string`), or null if `text` is null. `kind` is "recompose",
+/// "tcvn3", "compose", "fold-confusables" or "remove-tones".
+///
+/// # Safety
+/// `text` must be a valid null-terminated string or null.
+#[cfg(feature = "conversion")]
+#[no_mangle]
+pub unsafe extern "C" fn ime_paste_options(text: *const c_char) -> *mut c_char {
+    use json::Value;
+
+    ffi_guard("ime_paste_options", std::ptr::null_mut(), || {
+        if text.is_null() {
+            set_last_error("text is null".into());
+            return std::ptr::null_mut();
+        }
+        let text = CStr::from_ptr(text).to_string_lossy();
+        let options = paste::paste_options(&text)
+            .into_iter()
+            .map(|o| {
+                Value::Obj(vec![
+                    ("kind".into(), Value::Str(o.kind.as_str().into())),
+                    ("label".into(), Value::Str(o.kind.label().into())),
+                    ("preview".into(), Value::Str(o.preview)),
+                    ("text".into(), Value::Str(o.text)),
+                ])
+            })
+            .collect();
+        into_c_string(Value::Arr(options).to_pretty())
+    })
+}
+
+/// Transformed selection, for a "Transform selection" context menu.
+///
+/// # Arguments
+/// * `text` - the selected text (UTF-8)
+/// * `op` - 0=remove tones, 1=VIQR, 2=convert encoding, 3=toggle case,
+///   4=raw keystrokes, 5=tone mark placement, 6=fold fullwidth letters and
+///   lookalikes to ASCII
+/// * `arg` - op 2: source encoding in the high 4 bits, target in the low 4
+///   (0=Unicode, 1=decomposed Unicode, 2=TCVN3); op 4: 0=Telex, 1=VNI;
+///   op 5: 0=modern (hoà), 1=classic (hòa); ignored otherwise
+///
+/// # Returns
+/// The new text (free with `ime_free_string`), or null if `text` is null or
+/// `op`/`arg` is unknown (details via `ime_last_error`).
+///
+/// # Safety
+/// `text` must be a valid null-terminated string or null.
+#[cfg(feature = "conversion")]
+#[no_mangle]
+pub unsafe extern "C" fn ime_transform_selection(
+    text: *const c_char,
+    op: u8,
+    arg: u8,
+) -> *mut c_char {
+    use selection::{Encoding, Transform};
+
+    ffi_guard("ime_transform_selection", std::ptr::null_mut(), || {
+        if text.is_null() {
+            set_last_error("text is null".into());
+            return std::ptr::null_mut();
+        }
+        let transform = match op {
+            0 => Some(Transform::RemoveTones),
+            1 => Some(Transform::Viqr),
+            2 => Encoding::from_id(arg >> 4)
+                .zip(Encoding::from_id(arg & 0x0F))
+                .map(|(from, to)| Transform::Encoding { from, to }),
+            3 => Some(Transform::ToggleCase),
+            4 => Some(Transform::RawKeys(config::Method::from_id(arg))),
+            5 => match arg {
+                0 => Some(Transform::ToneStyle(config::ToneStyle::Modern)),
+                1 => Some(Transform::ToneStyle(config::ToneStyle::Classic)),
+                _ => None,
+            },
+            6 => Some(Transform::FoldConfusables),
+            _ => None,
+        };
+        let Some(transform) = transform else {
+            set_last_error(format!("unknown transform {} ({})", op, arg));
+            return std::ptr::null_mut();
+        };
+        let text = CStr::from_ptr(text).to_string_lossy();
+        into_c_string(selection::transform(&text, transform))
+    })
+}
+
+/// Free a result pointer returned by `ime_key`.
+///
+/// # Safety
+/// * `r` must be a pointer returned by `ime_key`, or null
+/// * Must be called exactly once per non-null `ime_key` return
+/// * Do not use `r` after calling this function
+#[no_mangle]
+pub unsafe extern "C" fn ime_free(r: *mut Result) {
+    ffi_guard("ime_free", (), || {
+        if !r.is_null() {
+            drop(Box::from_raw(r));
+        }
+    })
+}
+
+// ============================================================
+// Output profile FFI
+// ============================================================
+
+/// Select the built-in output profile for the focused app.
+///
+/// Call on every app switch. Excel and remote desktop clients get one
+/// replacement per word, Java apps a cap on backspaces per edit; other
+/// apps keep immediate output. Ends the current word. Word rules match the
+/// id with `app is "..."`.
+///
+/// # Arguments
+/// * `app_id` - macOS bundle id or Windows exe name; null = default profile
+///
+/// # Safety
+/// `app_id` must be a valid null-terminated string or null.
+#[no_mangle]
+pub unsafe extern "C" fn ime_set_app(app_id: *const c_char) {
+    ffi_guard("ime_set_app", (), || {
+        let id = (!app_id.is_null()).then(|| CStr::from_ptr(app_id).to_string_lossy());
+        if let Some(ref mut e) = *lock_engine() {
+            e.set_app(id.as_deref());
+        }
+    })
+}
+
+/// Set output tuning explicitly (host-side per-app settings).
+///
+/// # Arguments
+/// * `max_backspace` - most backspaces in one mid-word edit (0 = no limit);
+///   longer edits are deferred to the end of the word
+/// * `edit_delay_us` - suggested delay between synthetic keystrokes
+/// * `at_boundary` - true = never edit mid-word, replace once per word
+#[no_mangle]
+pub extern "C" fn ime_set_output_profile(max_backspace: u8, edit_delay_us: u32, at_boundary: bool) {
+    ffi_guard("ime_set_output_profile", (), || {
+        use engine::output::{OutputProfile, Strategy};
+        if let Some(ref mut e) = *lock_engine() {
+            let profile = OutputProfile {
+                max_backspace,
+                edit_delay_us,
+                strategy: if at_boundary {
+                    Strategy::AtBoundary
+                } else {
+                    Strategy::Immediate
+                },
+                ..e.output_profile()
+            };
+            e.set_output_profile(profile);
+        }
+    })
+}
+
+/// Deliver edits as select-and-replace instead of backspaces.
+///
+/// For hosts that can replace a text range atomically (accessibility API,
+/// TSF). Edits then come with action 3 (Select): replace the range from
+/// `ime_edit_range` with the result's chars. Needs the caret offset from
+/// `ime_set_caret`; while it's unknown, edits stay backspaces (action 1).
+/// Call after `ime_set_app`, which resets it.
+#[no_mangle]
+pub extern "C" fn ime_set_select_replace(enabled: bool) {
+    ffi_guard("ime_set_select_replace", (), || {
+        use engine::output::{Delivery, OutputProfile};
+        if let Some(ref mut e) = *lock_engine() {
+            let profile = OutputProfile {
+                delivery: if enabled {
+                    Delivery::Select
+                } else {
+                    Delivery::Backspace
+                },
+                ..e.output_profile()
+            };
+            e.set_output_profile(profile);
+        }
+    })
+}
+
+/// Deliver Enter after the correction of the word it ends.
+///
+/// For chat apps, where Enter sends the message: passed through, it can
+/// reach the app before the edit's backspaces. When enabled, an Enter that
+/// ends a word with an edit comes as a trailing `'\n'` in the result's
+/// chars: swallow the key, deliver the edit, then press Return for the
+/// `'\n'`. Shift+Enter and Enter after an unchanged word go through as
+/// usual. Call after `ime_set_app`, which picks it for known chat apps.
+#[no_mangle]
+pub extern "C" fn ime_set_enter_after_edit(enabled: bool) {
+    ffi_guard("ime_set_enter_after_edit", (), || {
+        use engine::output::{BoundaryKey, OutputProfile};
+        if let Some(ref mut e) = *lock_engine() {
+            let profile = OutputProfile {
+                boundary_key: if enabled {
+                    BoundaryKey::AfterEdit
+                } else {
+                    BoundaryKey::PassThrough
+                },
+                ..e.output_profile()
+            };
+            e.set_output_profile(profile);
+        }
+    })
+}
+
+/// Tell the engine where the caret is.
+///
+/// # Arguments
+/// * `offset` - caret offset in the focused field, in UTF-16 code units;
+///   negative = unknown (after a click, arrow key or paste the engine
+///   can't follow)
+#[no_mangle]
+pub extern "C" fn ime_set_caret(offset: i64) {
+    ffi_guard("ime_set_caret", (), || {
+        if let Some(ref mut e) = *lock_engine() {
+            e.set_caret(usize::try_from(offset).ok());
+        }
+    })
+}
+
+/// Text range the last key's edit replaces.
+///
+/// # Returns
+/// true and the range `[start, end)` in UTF-16 code units, or false if the
+/// last key made no edit or the caret is unknown.
+///
+/// # Safety
+/// `start` and `end` must point to writable `u32`s.
+#[no_mangle]
+pub unsafe extern "C" fn ime_edit_range(start: *mut u32, end: *mut u32) -> bool {
+    ffi_guard("ime_edit_range", false, || {
+        if start.is_null() || end.is_null() {
+            return false;
+        }
+        let range = match *lock_engine() {
+            Some(ref e) => e.edit_range(),
+            None => None,
+        };
+        match range {
+            Some(range) => {
+                *start = range.start as u32;
+                *end = range.end as u32;
+                true
+            }
+            None => false,
+        }
+    })
+}
+
+/// Suggested delay between synthetic keystrokes for the focused app.
+///
+/// # Returns
+/// Microseconds (0 = no delay needed, or engine not initialized).
+#[no_mangle]
+pub extern "C" fn ime_edit_delay_hint() -> u32 {
+    ffi_guard("ime_edit_delay_hint", 0, || match *lock_engine() {
+        Some(ref e) => e.output_profile().edit_delay_us,
+        None => 0,
+    })
+}
+
+/// Run the built-in self-test (for "Report a problem").
+///
+/// Types a fixed set of Telex/VNI/restore/auto-correct cases on fresh
+/// engines; the user's engine is not touched.
+///
+/// # Returns
+/// JSON report `{"version", "features", "prewarm", "passed", "cases": [...]}`
+/// (free with `ime_free_string`).
+#[no_mangle]
+pub extern "C" fn ime_self_test() -> *mut c_char {
+    ffi_guard("ime_self_test", std::ptr::null_mut(), || {
+        into_c_string(Engine::self_test().to_json().to_pretty())
+    })
+}
+
+/// The engine's state for the word being typed, for a debug overlay in
+/// dev builds (see `engine::debug`).
+///
+/// # Returns
+/// JSON `{"method", "enabled", "raw_mode", "overflowed", "shown", "raw",
+/// "chars", "held_back", "last_transform", "spell", "typo", "trigger",
+/// "suggestion"}` (free with `ime_free_string`), or null if engine not
+/// initialized.
+#[no_mangle]
+pub extern "C" fn ime_debug_state() -> *mut c_char {
+    ffi_guard("ime_debug_state", std::ptr::null_mut(), || {
+        let state = match *lock_engine() {
+            Some(ref e) => e.debug_state(),
+            None => return std::ptr::null_mut(),
+        };
+        into_c_string(state.to_json().to_pretty())
+    })
+}
+
+/// Word being composed, or the one the last key finished.
+///
+/// For screen readers: call after each key and announce the word once
+/// `committed` is true, instead of echoing the raw keystrokes.
+///
+/// # Arguments
+/// * `start`, `end` - receive the word's offsets from the caret in UTF-16
+///   units (negative = before the caret); may be null
+/// * `committed` - receives true once a boundary key ended the word; may be null
+///
+/// # Returns
+/// The composed word (free with `ime_free_string`), or null if there's none.
+///
+/// # Safety
+/// Non-null pointers must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn ime_word_info(
+    start: *mut i32,
+    end: *mut i32,
+    committed: *mut bool,
+) -> *mut c_char {
+    ffi_guard("ime_word_info", std::ptr::null_mut(), || {
+        let info = match *lock_engine() {
+            Some(ref e) => e.word_info(),
+            None => None,
+        };
+        let Some(info) = info else {
+            return std::ptr::null_mut();
+        };
+        if !start.is_null() {
+            *start = info.start as i32;
+        }
+        if !end.is_null() {
+            *end = info.end as i32;
+        }
+        if !committed.is_null() {
+            *committed = info.committed;
+        }
+        into_c_string(info.text)
+    })
+}
+
+/// Word being composed, for an inline preview or hint window.
+///
+/// Call after each key: "đươc" while "dduwowc" is typed, even when the
+/// app's edits are held back to the end of the word.
+///
+/// # Returns
+/// The composed word, empty between words (free with `ime_free_string`),
+/// or null if engine not initialized.
+#[no_mangle]
+pub extern "C" fn ime_current_preview() -> *mut c_char {
+    ffi_guard(
+        "ime_current_preview",
+        std::ptr::null_mut(),
+        || match *lock_engine() {
+            Some(ref mut e) => into_c_string(e.current_preview().to_string()),
+            None => std::ptr::null_mut(),
+        },
+    )
+}
+
+/// Why auto-restore turned the word the last space or punctuation
+/// finished back into its keystrokes.
+///
+/// # Returns
+/// JSON `{"check", "problem", "matched", "composed", "restored", "message"}`
+/// (free with `ime_free_string`), or null if that word wasn't restored.
+#[no_mangle]
+pub extern "C" fn ime_last_restore_reason() -> *mut c_char {
+    ffi_guard("ime_last_restore_reason", std::ptr::null_mut(), || {
+        let reason = match *lock_engine() {
+            Some(ref e) => e.last_restore_reason(),
+            None => None,
+        };
+        match reason {
+            Some(r) => into_c_string(r.to_json().to_pretty()),
+            None => std::ptr::null_mut(),
+        }
+    })
+}
+
+/// What heuristics on trial would have done differently (see
+/// `engine::shadow`).
+///
+/// # Returns
+/// JSON `{"heuristics": [{"name", "words", "disagreements"}],
+/// "disagreements": [{"heuristic", "word", "keys", "active", "shadow"}]}`
+/// (free with `ime_free_string`), or null if engine not initialized.
+#[no_mangle]
+pub extern "C" fn ime_shadow_report() -> *mut c_char {
+    ffi_guard("ime_shadow_report", std::ptr::null_mut(), || {
+        let report = match *lock_engine() {
+            Some(ref e) => e.shadow_log().to_json(),
+            None => return std::ptr::null_mut(),
+        };
+        into_c_string(report.to_pretty())
+    })
+}
+
+/// How the word being typed reads so far, to tint a preview or the
+/// menu-bar icon.
+///
+/// Call after each key: it changes as letters are added or removed, and
+/// goes back to 0 when the word ends.
+///
+/// # Returns
+/// 0 = no word, 1 = Vietnamese so far, 2 = not Vietnamese, 3 = not
+/// Vietnamese and leaning English (0 if engine not initialized)
+#[no_mangle]
+pub extern "C" fn ime_spell_status() -> u8 {
+    ffi_guard("ime_spell_status", 0, || match *lock_engine() {
+        Some(ref e) => e.spell_status() as u8,
+        None => 0,
+    })
+}
+
+/// Modifier gesture the last `ime_key_event` or `ime_key_events` call
+/// completed (see
+/// `engine::gesture`; mappings are the `gestures` setting).
+///
+/// Toggles are already done when this reports them (and announced to
+/// `ime_subscribe` callbacks as setting changes); suggestions are the
+/// host's to show. `ime_key_event` events are untimed, so only the order
+/// of keys is checked: hosts with timestamps should use `ime_key_events`.
+///
+/// # Returns
+/// 0 = none, 1 = toggle-enabled, 2 = toggle-method, 3 = suggestions
+#[no_mangle]
+pub extern "C" fn ime_gesture() -> u8 {
+    ffi_guard("ime_gesture", 0, || match *lock_engine() {
+        Some(ref e) => e.gesture().map_or(0, |g| g as u8 + 1),
+        None => 0,
+    })
+}
+
+/// Fix a correction pack has for the word being typed, if it's one of
+/// their known misspellings.
+///
+/// Call after each key to underline the word while it's typed ("mún"
+/// with the southern pack on); the boundary key fixes it as usual.
+///
+/// # Returns
+/// The fix (free with `ime_free_string`), or null if the word isn't a
+/// known typo.
+#[no_mangle]
+pub extern "C" fn ime_typo() -> *mut c_char {
+    ffi_guard("ime_typo", std::ptr::null_mut(), || {
+        let fixed = match *lock_engine() {
+            Some(ref e) => e.typo(),
+            None => None,
+        };
+        match fixed {
+            Some(fixed) => into_c_string(fixed.to_string()),
+            None => std::ptr::null_mut(),
+        }
+    })
+}
+
+/// Correction on offer for the word just finished
+/// (`correction_style = "suggest"`).
+///
+/// Call after each key to show or hide the offer; the accept key applies it.
+///
+/// # Returns
+/// The selected candidate (free with `ime_free_string`), or null if
+/// nothing is on offer.
+#[no_mangle]
+pub extern "C" fn ime_suggestion() -> *mut c_char {
+    ffi_guard("ime_suggestion", std::ptr::null_mut(), || {
+        let fixed = match *lock_engine() {
+            Some(ref e) => e.suggestion().map(|s| s.fixed().to_string()),
+            None => None,
+        };
+        match fixed {
+            Some(fixed) => into_c_string(fixed),
+            None => std::ptr::null_mut(),
+        }
+    })
+}
+
+/// The whole candidate list on offer, for hosts that draw it.
+///
+/// Down/Up move the selection (the key result is then action 1 with
+/// nothing to delete or insert: swallow it), the accept key or a number
+/// key after browsing applies a candidate, Esc after browsing closes the
+/// list. Call after each key to redraw.
+///
+/// # Returns
+/// JSON `{"word", "candidates": [...], "selected", "browsing"}` (free with
+/// `ime_free_string`), or null if nothing is on offer.
+#[no_mangle]
+pub extern "C" fn ime_suggestion_list() -> *mut c_char {
+    ffi_guard("ime_suggestion_list", std::ptr::null_mut(), || {
+        let list = match *lock_engine() {
+            Some(ref e) => e.suggestion().map(|s| {
+                let candidates = s.candidates.iter().cloned().map(json::Value::Str).collect();
+                json::Value::Obj(vec![
+                    ("word".into(), json::Value::Str(s.word.clone())),
+                    ("candidates".into(), json::Value::Arr(candidates)),
+                    ("selected".into(), json::Value::Num(s.selected as f64)),
+                    ("browsing".into(), json::Value::Bool(s.browsing)),
+                ])
+            }),
+            None => None,
+        };
+        match list {
+            Some(list) => into_c_string(list.to_pretty()),
+            None => std::ptr::null_mut(),
+        }
+    })
+}
+
+// ============================================================
+// Shortcut FFI
+// ============================================================
+
+/// Add a shortcut to the engine.
+///
+/// # Arguments
+/// * `trigger` - C string for trigger (e.g., "vn")
+/// * `replacement` - C string for replacement (e.g., "Việt Nam")
+///
+/// # Safety
+/// Both pointers must be valid null-terminated UTF-8 strings.
+#[no_mangle]
+pub unsafe extern "C" fn ime_add_shortcut(
+    trigger: *const std::os::raw::c_char,
+    replacement: *const std::os::raw::c_char,
+) {
+    ffi_guard("ime_add_shortcut", (), || {
+        if trigger.is_null() || replacement.is_null() {
+            return;
+        }
+
+        let trigger_str = match std::ffi::CStr::from_ptr(trigger).to_str() {
+            Ok(s) => s,
+            Err(_) => return,
+        };
+        let replacement_str = match std::ffi::CStr::from_ptr(replacement).to_str() {
+            Ok(s) => s,
+            Err(_) => return,
+        };
+
+        let mut guard = lock_engine();
+        if let Some(ref mut e) = *guard {
+            e.shortcuts_mut()
+                .add(Shortcut::new(trigger_str, replacement_str));
+        }
+    })
+}
+
+/// Remove a shortcut from the engine.
+///
+/// # Arguments
+/// * `trigger` - C string for trigger to remove
+///
+/// # Safety
+/// Pointer must be a valid null-terminated UTF-8 string.
+#[no_mangle]
+pub unsafe extern "C" fn ime_remove_shortcut(trigger: *const std::os::raw::c_char) {
+    ffi_guard("ime_remove_shortcut", (), || {
+        if trigger.is_null() {
+            return;
+        }
+
+        let trigger_str = match std::ffi::CStr::from_ptr(trigger).to_str() {
+            Ok(s) => s,
+            Err(_) => return,
+        };
+
+        let mut guard = lock_engine();
+        if let Some(ref mut e) = *guard {
+            e.shortcuts_mut().remove(trigger_str);
+        }
+    })
+}
+
+/// Shortcuts that get in the way of typing, for the settings UI to warn
+/// about before saving.
+///
+/// # Arguments
+/// * `trigger` - trigger being entered, checked against the saved
+///   shortcuts; null checks every saved shortcut
+///
+/// # Returns
+/// JSON array of `{"trigger", "kind"}` with, by kind: "word" (the trigger
+/// spells a Vietnamese word) `"word"`; "correction" (a word the
+/// corrections fix) `"word", "fixed"`; "prefix" (one trigger starts the
+/// other) `"other"`. Free with `ime_free_string`; null if `trigger` is not
+/// UTF-8 or the engine is not initialized.
+///
+/// # Safety
+/// `trigger` must be a valid null-terminated string or null.
+#[cfg(feature = "macros")]
+#[no_mangle]
+pub unsafe extern "C" fn ime_shortcut_conflicts(trigger: *const c_char) -> *mut c_char {
+    ffi_guard("ime_shortcut_conflicts", std::ptr::null_mut(), || {
+        let trigger = if trigger.is_null() {
+            None
+        } else {
+            match CStr::from_ptr(trigger).to_str() {
+                Ok(t) => Some(t),
+                Err(_) => return std::ptr::null_mut(),
+            }
+        };
+        let guard = lock_engine();
+        let Some(ref e) = *guard else {
+            return std::ptr::null_mut();
+        };
+        let conflicts = match trigger {
+            Some(t) => e
+                .check_shortcut(&Shortcut::new(t, ""))
+                .into_iter()
+                .map(|conflict| ShortcutConflict {
+                    trigger: t.to_string(),
+                    conflict,
+                })
+                .collect(),
+            None => e.shortcut_conflicts(),
+        };
+        let list = conflicts.iter().map(ShortcutConflict::to_json).collect();
+        into_c_string(json::Value::Arr(list).to_pretty())
+    })
+}
+
+/// Clear all shortcuts from the engine.
+#[no_mangle]
+pub extern "C" fn ime_clear_shortcuts() {
+    ffi_guard("ime_clear_shortcuts", (), || {
+        let mut guard = lock_engine();
+        if let Some(ref mut e) = *guard {
+            e.shortcuts_mut().clear();
+        }
+    })
+}
+
+/// Set today's date for the "=date" dynamic macro. Call at startup and
+/// when the day changes; an invalid date (such as 0, 0, 0) unsets it.
+///
+/// # Arguments
+/// * `year`, `month` (1-12), `day` (1-31) - local calendar date
+#[cfg(feature = "macros")]
+#[no_mangle]
+pub extern "C" fn ime_set_date(year: u16, month: u8, day: u8) {
+    ffi_guard("ime_set_date", (), || {
+        let mut guard = lock_engine();
+        if let Some(ref mut e) = *guard {
+            e.set_date(engine::macros::Date::new(year, month, day));
+        }
+    })
+}
+
+/// Read the time from the host when a shortcut's `%d` / `%t` or "=date"
+/// expands. Pass null to stop.
+///
+/// `callback` returns local time as seconds since 1970-01-01 00:00 (Unix
+/// time plus the UTC offset), or a negative number if unknown. It runs
+/// while the engine lock is held: it must not call `ime_*`.
+#[cfg(feature = "macros")]
+#[no_mangle]
+pub extern "C" fn ime_set_clock(
+    callback: Option<engine::macros::ClockCallback>,
+    user_data: *mut c_void,
+) {
+    ffi_guard("ime_set_clock", (), || {
+        let mut guard = lock_engine();
+        if let Some(ref mut e) = *guard {
+            match callback {
+                Some(cb) => e.set_clock(engine::macros::CallbackClock::new(cb, user_data)),
+                None => e.clear_clock(),
+            }
+        }
+    })
+}
+
+// ============================================================
+// Config FFI
+// ============================================================
+
+/// Error code: null pointer, invalid UTF-8 or engine not initialized
+pub const IME_ERR_INVALID_ARG: i32 = -1;
+
+/// Error code: the call panicked and the engine was reset (see `ime_last_error`)
+pub const IME_ERR_PANIC: i32 = -2;
+
+/// Load settings from a TOML string.
+///
+/// # Returns
+/// * `0` on success (all settings applied)
+/// * `-1` if `toml` is null / not UTF-8, or engine not initialized
+/// * `ConfigError::code()` (1=syntax, 2=unknown key, 3=wrong type,
+///   4=invalid value, 5=conflict); nothing is applied.
+///   Details via `ime_last_error`.
+///
+/// # Safety
+/// `toml` must be a valid null-terminated string or null.
+#[no_mangle]
+pub unsafe extern "C" fn ime_load_config(toml: *const c_char) -> i32 {
+    ffi_guard("ime_load_config", IME_ERR_PANIC, || {
+        if toml.is_null() {
+            set_last_error("config is null".into());
+            return IME_ERR_INVALID_ARG;
+        }
+        let toml_str = match CStr::from_ptr(toml).to_str() {
+            Ok(s) => s,
+            Err(_) => {
+                set_last_error("config is not valid UTF-8".into());
+                return IME_ERR_INVALID_ARG;
+            }
+        };
+
+        let config = match EngineConfig::from_toml(toml_str) {
+            Ok(c) => c,
+            Err(err) => {
+                set_last_error(err.to_string());
+                return err.code();
+            }
+        };
+
+        let mut guard = lock_engine();
+        let code = match *guard {
+            Some(ref mut e) => match e.apply_config(&config) {
+                Ok(()) => 0,
+                Err(err) => {
+                    set_last_error(err.to_string());
+                    err.code()
+                }
+            },
+            None => {
+                set_last_error("engine not initialized".into());
+                IME_ERR_INVALID_ARG
+            }
+        };
+        drop(guard);
+        flush_notifications();
+        code
+    })
+}
+
+/// Replace the word rules (see `engine::rules`).
+///
+/// # Returns
+/// * `0` on success
+/// * `-1` if `src` is null / not UTF-8, or engine not initialized
+/// * `1` if a line is wrong; the rules in use are kept. Details via
+///   `ime_last_error`.
+///
+/// # Safety
+/// `src` must be a valid null-terminated string or null.
+#[cfg(feature = "rules")]
+#[no_mangle]
+pub unsafe extern "C" fn ime_load_rules(src: *const c_char) -> i32 {
+    ffi_guard("ime_load_rules", IME_ERR_PANIC, || {
+        if src.is_null() {
+            set_last_error("rules are null".into());
+            return IME_ERR_INVALID_ARG;
+        }
+        let Ok(src) = CStr::from_ptr(src).to_str() else {
+            set_last_error("rules are not valid UTF-8".into());
+            return IME_ERR_INVALID_ARG;
+        };
+        let rules = match engine::rules::Rules::parse(src) {
+            Ok(rules) => rules,
+            Err(err) => {
+                set_last_error(err.to_string());
+                return 1;
+            }
+        };
+        match *lock_engine() {
+            Some(ref mut e) => {
+                e.set_rules(rules);
+                0
+            }
+            None => IME_ERR_INVALID_ARG,
+        }
+    })
+}
+
+/// Load a plugin library (see `plugin`) into the engine.
+///
+/// # Arguments
+/// * `path` - path of the `.so`/`.dylib`/`.dll` (UTF-8)
+///
+/// # Returns
+/// * `0` on success
+/// * `-1` if `path` is null / not UTF-8, or engine not initialized
+/// * `1` if the library is not a usable plugin. Details via
+///   `ime_last_error`.
+///
+/// # Safety
+/// `path` must be a valid null-terminated string or null. The library
+/// runs in this process: only load plugins the user installed.
+#[cfg(feature = "plugins")]
+#[no_mangle]
+pub unsafe extern "C" fn ime_load_plugin(path: *const c_char) -> i32 {
+    ffi_guard("ime_load_plugin", IME_ERR_PANIC, || {
+        if path.is_null() {
+            set_last_error("path is null".into());
+            return IME_ERR_INVALID_ARG;
+        }
+        let Ok(path) = CStr::from_ptr(path).to_str() else {
+            set_last_error("path is not valid UTF-8".into());
+            return IME_ERR_INVALID_ARG;
+        };
+        let mut guard = lock_engine();
+        let Some(ref mut e) = *guard else {
+            return IME_ERR_INVALID_ARG;
+        };
+        match plugin::Plugin::load(path) {
+            Ok(p) => {
+                e.add_plugin(p);
+                0
+            }
+            Err(err) => {
+                set_last_error(err.to_string());
+                1
+            }
+        }
+    })
+}
+
+/// Suggestions for `word` from the loaded plugins.
+///
+/// # Returns
+/// A JSON array of words, best first (free with `ime_free_string`), or
+/// null if `word` is null or the engine is not initialized.
+///
+/// # Safety
+/// `word` must be a valid null-terminated string or null.
+#[cfg(feature = "plugins")]
+#[no_mangle]
+pub unsafe extern "C" fn ime_plugin_suggest(word: *const c_char) -> *mut c_char {
+    use json::Value;
+
+    ffi_guard("ime_plugin_suggest", std::ptr::null_mut(), || {
+        if word.is_null() {
+            set_last_error("word is null".into());
+            return std::ptr::null_mut();
+        }
+        let word = CStr::from_ptr(word).to_string_lossy();
+        let words = match *lock_engine() {
+            Some(ref e) => e.plugin_suggestions(&word),
+            None => return std::ptr::null_mut(),
+        };
+        let words = words.into_iter().map(Value::Str).collect();
+        into_c_string(Value::Arr(words).to_pretty())
+    })
+}
+
+/// Use the neural diacritic model at `path` (see `ml`).
+///
+/// The file is read on first use; a file that doesn't load leaves the
+/// rules in charge.
+///
+/// # Returns
+/// * `0` on success
+/// * `-1` if `path` is null / not UTF-8, or engine not initialized
+///
+/// # Safety
+/// `path` must be a valid null-terminated string or null.
+#[cfg(feature = "ml")]
+#[no_mangle]
+pub unsafe extern "C" fn ime_set_diacritic_model(path: *const c_char) -> i32 {
+    ffi_guard("ime_set_diacritic_model", IME_ERR_PANIC, || {
+        if path.is_null() {
+            set_last_error("path is null".into());
+            return IME_ERR_INVALID_ARG;
+        }
+        let Ok(path) = CStr::from_ptr(path).to_str() else {
+            set_last_error("path is not valid UTF-8".into());
+            return IME_ERR_INVALID_ARG;
+        };
+        match *lock_engine() {
+            Some(ref mut e) => {
+                e.set_diacritic_model(ml::DiacriticModel::new(path));
+                0
+            }
+            None => IME_ERR_INVALID_ARG,
+        }
+    })
+}
+
+/// Toneless `text` with diacritics from the neural model.
+///
+/// # Returns
+/// Newly allocated string (free with `ime_free_string`), or null if
+/// `text` is null or no model is set or loads.
+///
+/// # Safety
+/// `text` must be a valid null-terminated string or null.
+#[cfg(feature = "ml")]
+#[no_mangle]
+pub unsafe extern "C" fn ime_restore_diacritics(text: *const c_char) -> *mut c_char {
+    ffi_guard("ime_restore_diacritics", std::ptr::null_mut(), || {
+        if text.is_null() {
+            set_last_error("text is null".into());
+            return std::ptr::null_mut();
+        }
+        let text = CStr::from_ptr(text).to_string_lossy();
+        let model = match *lock_engine() {
+            Some(ref e) => e.diacritic_model().cloned(),
+            None => None,
+        };
+        match model.and_then(|m| m.restore_diacritics(&text)) {
+            Some(restored) => into_c_string(restored),
+            None => std::ptr::null_mut(),
+        }
+    })
+}
+
+/// Current settings as TOML.
+///
+/// # Returns
+/// Newly allocated string (free with `ime_free_string`), or null if
+/// engine not initialized.
+#[no_mangle]
+pub extern "C" fn ime_config() -> *mut c_char {
+    ffi_guard("ime_config", std::ptr::null_mut(), || {
+        let guard = lock_engine();
+        match *guard {
+            Some(ref e) => into_c_string(e.config().to_toml()),
+            None => std::ptr::null_mut(),
+        }
+    })
+}
+
+/// Settings and shortcuts as a portable JSON profile (see `profile`).
+///
+/// # Returns
+/// Newly allocated string (free with `ime_free_string`), or null if
+/// engine not initialized.
+#[no_mangle]
+pub extern "C" fn ime_export_profile() -> *mut c_char {
+    ffi_guard("ime_export_profile", std::ptr::null_mut(), || {
+        let guard = lock_engine();
+        match *guard {
+            Some(ref e) => into_c_string(e.export_profile()),
+            None => std::ptr::null_mut(),
+        }
+    })
+}
+
+/// Replace settings and shortcuts with an exported profile.
+///
+/// # Returns
+/// * `0` on success
+/// * `-1` if `json` is null / not UTF-8, or engine not initialized
+/// * `ProfileError::code()` (1-5=invalid settings as in `ime_load_config`,
+///   20=bad JSON, 21=not a profile, 22=newer version, 23=invalid field);
+///   nothing is applied. Details via `ime_last_error`.
+///
+/// # Safety
+/// `json` must be a valid null-terminated string or null.
+#[no_mangle]
+pub unsafe extern "C" fn ime_import_profile(json: *const c_char) -> i32 {
+    ffi_guard("ime_import_profile", IME_ERR_PANIC, || {
+        if json.is_null() {
+            set_last_error("profile is null".into());
+            return IME_ERR_INVALID_ARG;
+        }
+        let Ok(json) = CStr::from_ptr(json).to_str() else {
+            set_last_error("profile is not valid UTF-8".into());
+            return IME_ERR_INVALID_ARG;
+        };
+
+        let mut guard = lock_engine();
+        let code = match *guard {
+            Some(ref mut e) => match e.import_profile(json) {
+                Ok(()) => 0,
+                Err(err) => {
+                    set_last_error(err.to_string());
+                    err.code()
+                }
+            },
+            None => {
+                set_last_error("engine not initialized".into());
+                IME_ERR_INVALID_ARG
+            }
+        };
+        drop(guard);
+        flush_notifications();
+        code
+    })
+}
+
+/// Message describing the last failed call, or null if none.
+///
+/// Free with `ime_free_string`.
+#[no_mangle]
+pub extern "C" fn ime_last_error() -> *mut c_char {
+    ffi_guard("ime_last_error", std::ptr::null_mut(), || {
+        let guard = LAST_ERROR.lock().unwrap_or_else(|e| e.into_inner());
+        match *guard {
+            Some(ref msg) => into_c_string(msg.clone()),
+            None => std::ptr::null_mut(),
+        }
+    })
+}
+
+/// Free a string returned by `ime_config`, `ime_export_profile`,
+/// `ime_last_error`, `ime_word_info`, `ime_last_restore_reason`,
+/// `ime_to_keystrokes`, `ime_search_key`, `ime_long_press`,
+/// `ime_suggest_similar`, `ime_recompose_pasted`,
+/// `ime_transform_selection`, `ime_self_test`, `ime_debug_state` or
+/// `ime_practice_*`.
+///
+/// # Safety
+/// `s` must come from one of those functions (or be null), freed once.
+#[no_mangle]
+pub unsafe extern "C" fn ime_free_string(s: *mut c_char) {
+    ffi_guard("ime_free_string", (), || {
+        if !s.is_null() {
+            drop(CString::from_raw(s));
+        }
+    })
+}
+
+// ============================================================
+// Observer FFI
+// ============================================================
+
+/// Settings-change callback.
+///
+/// * `kind` - 0=method, 1=enabled, 2=tone_style (1=classic),
+///   3=auto_correct (0=off, 1=restore, 2=all), 4=bracket_shortcuts
+/// * `value` - new value
+/// * `user_data` - pointer passed to `ime_subscribe`
+pub type ImeSettingCallback = extern "C" fn(kind: u8, value: u8, user_data: *mut c_void);
+
+#[derive(Clone, Copy)]
+struct FfiSubscriber {
+    id: u32,
+    callback: ImeSettingCallback,
+    user_data: *mut c_void,
+}
+
+// SAFETY: user_data is opaque to us; the host owns its thread-safety
+unsafe impl Send for FfiSubscriber {}
+
+/// Feedback callback, for sounds or haptics.
+///
+/// * `kind` - 0=word committed, 1=corrected, 2=restored
+/// * `user_data` - pointer passed to `ime_subscribe_feedback`
+pub type ImeFeedbackCallback = extern "C" fn(kind: u8, user_data: *mut c_void);
+
+#[derive(Clone, Copy)]
+struct FfiFeedbackSubscriber {
+    id: u32,
+    callback: ImeFeedbackCallback,
+    user_data: *mut c_void,
+}
+
+// SAFETY: as for FfiSubscriber
+unsafe impl Send for FfiFeedbackSubscriber {}
+
+static SUBSCRIBERS: Mutex<Vec<FfiSubscriber>> = Mutex::new(Vec::new());
+static FEEDBACK_SUBSCRIBERS: Mutex<Vec<FfiFeedbackSubscriber>> = Mutex::new(Vec::new());
+static NEXT_SUBSCRIBER: Mutex<u32> = Mutex::new(0);
+
+// Notifications raised while the engine lock was held
+static PENDING: Mutex<Vec<Notification>> = Mutex::new(Vec::new());
+
+/// Deliver queued notifications to FFI subscribers (no locks held while calling)
+fn flush_notifications() {
+    let pending = std::mem::take(&mut *PENDING.lock().unwrap_or_else(|e| e.into_inner()));
+    if pending.is_empty() {
+        return;
+    }
+    let subscribers = SUBSCRIBERS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone();
+    let feedback = FEEDBACK_SUBSCRIBERS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone();
+    for n in pending {
+        match n {
+            Notification::SettingChanged(setting) => {
+                let (kind, value) = setting.to_ffi();
+                for s in &subscribers {
+                    (s.callback)(kind, value, s.user_data);
+                }
+            }
+            Notification::Feedback(kind) => {
+                for s in &feedback {
+                    (s.callback)(kind as u8, s.user_data);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn next_subscriber_id() -> u32 {
+    let mut next = NEXT_SUBSCRIBER.lock().unwrap_or_else(|e| e.into_inner());
+    *next = next.checked_add(1).unwrap_or(1);
+    *next
+}
+
+/// Subscribe to settings changes.
+///
+/// The callback runs on the thread that changed the setting, after the
+/// engine lock is released (calling `ime_*` from it is allowed).
+///
+/// # Returns
+/// Subscription id (never 0) for `ime_unsubscribe`.
+#[no_mangle]
+pub extern "C" fn ime_subscribe(callback: ImeSettingCallback, user_data: *mut c_void) -> u32 {
+    ffi_guard("ime_subscribe", 0, || {
+        let id = next_subscriber_id();
+        SUBSCRIBERS
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(FfiSubscriber {
+                id,
+                callback,
+                user_data,
+            });
+        id
+    })
+}
+
+/// Subscribe to feedback events: words committed, corrected or
+/// restored, for hosts playing sounds or haptics.
+///
+/// Events only come with the `feedback` setting on, at most one per
+/// `feedback_gap_ms` unless a later one outranks the last (a restore
+/// right after a commit). The callback runs like `ime_subscribe`'s.
+///
+/// # Returns
+/// Subscription id (never 0) for `ime_unsubscribe`.
+#[no_mangle]
+pub extern "C" fn ime_subscribe_feedback(
+    callback: ImeFeedbackCallback,
+    user_data: *mut c_void,
+) -> u32 {
+    ffi_guard("ime_subscribe_feedback", 0, || {
+        let id = next_subscriber_id();
+        FEEDBACK_SUBSCRIBERS
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(FfiFeedbackSubscriber {
+                id,
+                callback,
+                user_data,
+            });
+        id
+    })
+}
+
+/// Remove a subscription (either kind). Returns false if `id` is unknown.
+#[no_mangle]
+pub extern "C" fn ime_unsubscribe(id: u32) -> bool {
+    ffi_guard("ime_unsubscribe", false, || {
+        let mut subs = SUBSCRIBERS.lock().unwrap_or_else(|e| e.into_inner());
+        let mut feedback = FEEDBACK_SUBSCRIBERS
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        let before = subs.len() + feedback.len();
+        subs.retain(|s| s.id != id);
+        feedback.retain(|s| s.id != id);
+        subs.len() + feedback.len() != before
+    })
+}
+
+// ============================================================
+// Storage FFI
+// ============================================================
+
+/// Run `f` on the engine, recording `StorageError` details for `ime_last_error`
+fn with_storage(f: impl FnOnce(&mut Engine) -> std::result::Result<(), StorageError>) -> i32 {
+    let mut guard = lock_engine();
+    let code = match *guard {
+        Some(ref mut e) => match f(e) {
+            Ok(()) => 0,
+            Err(err) => {
+                set_last_error(err.to_string());
+                err.code()
+            }
+        },
+        None => {
+            set_last_error("engine not initialized".into());
+            IME_ERR_INVALID_ARG
+        }
+    };
+    drop(guard);
+    flush_notifications();
+    code
+}
+
+/// Store user data through host callbacks (sandboxed apps).
+///
+/// See `storage::ReadCallback` for the read contract. Callbacks run while
+/// the engine lock is held: they must not call `ime_*`.
+#[no_mangle]
+pub extern "C" fn ime_set_storage(
+    read: ReadCallback,
+    write: WriteCallback,
+    remove: RemoveCallback,
+    user_data: *mut c_void,
+) {
+    ffi_guard("ime_set_storage", (), || {
+        let mut guard = lock_engine();
+        if let Some(ref mut e) = *guard {
+            e.set_storage(CallbackStorage::new(read, write, remove, user_data));
+        }
+    })
+}
+
+/// Store user data as files in `dir` (null = platform default).
+///
+/// # Returns
+/// * `0` on success
+/// * `-1` if `dir` is not UTF-8, no default exists, or engine not initialized
+///
+/// # Safety
+/// `dir` must be a valid null-terminated string or null.
+#[no_mangle]
+pub unsafe extern "C" fn ime_set_storage_dir(dir: *const c_char) -> i32 {
+    ffi_guard("ime_set_storage_dir", IME_ERR_PANIC, || {
+        let dir = if dir.is_null() {
+            match FileStorage::default_dir() {
+                Some(d) => d,
+                None => {
+                    set_last_error("no default storage directory".into());
+                    return IME_ERR_INVALID_ARG;
+                }
+            }
+        } else {
+            match CStr::from_ptr(dir).to_str() {
+                Ok(s) => s.into(),
+                Err(_) => {
+                    set_last_error("storage dir is not valid UTF-8".into());
+                    return IME_ERR_INVALID_ARG;
+                }
+            }
+        };
+        with_storage(|e| {
+            e.set_storage(FileStorage::new(dir));
+            Ok(())
+        })
+    })
+}
+
+/// Serve this engine to helper processes at `path` (see `ipc`).
+///
+/// A socket file on Unix, a pipe name (`\\.\pipe\gonhanh`) on Windows.
+/// Connections are handled on background threads until the process exits.
+///
+/// # Returns
+/// * `0` on success
+/// * `-1` if `path` is null / not UTF-8, or it can't be bound (details
+///   via `ime_last_error`)
+///
+/// # Safety
+/// `path` must be a valid null-terminated string or null.
+#[cfg(feature = "ipc")]
+#[no_mangle]
+pub unsafe extern "C" fn ime_ipc_listen(path: *const c_char) -> i32 {
+    ffi_guard("ime_ipc_listen", IME_ERR_PANIC, || {
+        let path = match (!path.is_null()).then(|| CStr::from_ptr(path).to_str()) {
+            Some(Ok(p)) => p,
+            _ => {
+                set_last_error("path is null or not UTF-8".into());
+                return IME_ERR_INVALID_ARG;
+            }
+        };
+        match ipc::listen(path, &ENGINE) {
+            Ok(_) => 0,
+            Err(e) => {
+                set_last_error(format!("ipc {}: {}", path, e));
+                IME_ERR_INVALID_ARG
+            }
+        }
+    })
+}
+
+/// Serve one engine per user session at `path` (see `ipc::serve_sessions`).
+///
+/// Independent of the host's own engine. Call at most once per process.
+///
+/// # Arguments
+/// * `path` - as in `ime_ipc_listen`
+/// * `max_sessions` - live sessions kept; the least recently used is
+///   evicted beyond that
+/// * `idle_secs` - sessions unused this long are evicted
+/// * `storage_root` - directory keeping each session's data in `<id>/`,
+///   or null to keep nothing
+///
+/// # Returns
+/// * `0` on success
+/// * `-1` on a null / non-UTF-8 argument, a second call, or if `path`
+///   can't be bound (details via `ime_last_error`)
+///
+/// # Safety
+/// `path` and `storage_root` must be valid null-terminated strings or null.
+#[cfg(feature = "ipc")]
+#[no_mangle]
+pub unsafe extern "C" fn ime_ipc_listen_sessions(
+    path: *const c_char,
+    max_sessions: u32,
+    idle_secs: u32,
+    storage_root: *const c_char,
+) -> i32 {
+    use session::Sessions;
+    use std::sync::OnceLock;
+
+    static SESSIONS: OnceLock<Mutex<Sessions>> = OnceLock::new();
+
+    ffi_guard("ime_ipc_listen_sessions", IME_ERR_PANIC, || {
+        let text = |p: *const c_char| (!p.is_null()).then(|| CStr::from_ptr(p).to_str());
+        let (Some(Ok(path)), None | Some(Ok(_))) = (text(path), text(storage_root)) else {
+            set_last_error("path is null or not UTF-8".into());
+            return IME_ERR_INVALID_ARG;
+        };
+        let mut sessions =
+            Sessions::new(max_sessions as usize, Duration::from_secs(idle_secs as u64));
+        if let Some(Ok(root)) = text(storage_root) {
+            sessions = sessions.with_storage_root(root);
+        }
+        if SESSIONS.set(Mutex::new(sessions)).is_err() {
+            set_last_error("session server already running".into());
+            return IME_ERR_INVALID_ARG;
+        }
+        match ipc::listen_sessions(path, SESSIONS.get().unwrap()) {
+            Ok(_) => 0,
+            Err(e) => {
+                set_last_error(format!("ipc {}: {}", path, e));
+                IME_ERR_INVALID_ARG
+            }
+        }
+    })
+}
+
+/// Save settings and shortcuts to storage.
+///
+/// # Returns
+/// `0` on success, otherwise `StorageError::code()` (-1=no storage,
+/// 10=I/O). Details via `ime_last_error`.
+#[no_mangle]
+pub extern "C" fn ime_save() -> i32 {
+    ffi_guard("ime_save", IME_ERR_PANIC, || with_storage(Engine::save))
+}
+
+/// Load settings and shortcuts from storage.
+///
+/// # Returns
+/// `0` on success, otherwise `StorageError::code()` (-1=no storage,
+/// 10=I/O); nothing is applied on error. Details via `ime_last_error`.
+///
+/// Corrupt data is not an error: it's reset to defaults, check
+/// `ime_state_health` afterwards.
+#[no_mangle]
+pub extern "C" fn ime_load() -> i32 {
+    ffi_guard("ime_load", IME_ERR_PANIC, || {
+        with_storage(|e| {
+            e.load()?;
+            if let Some(err) = &e.state_health().config_error {
+                set_last_error(err.to_string());
+            }
+            Ok(())
+        })
+    })
+}
+
+/// Data reset to defaults by the last `ime_load` because it was corrupt.
+///
+/// # Returns
+/// Bitmask: 1=settings, 2=shortcuts, 4=sync history (0 = all fine);
+/// 8 = the settings were kept but had conflicting options, reset to their
+/// defaults (which ones via `ime_last_error`).
+#[no_mangle]
+pub extern "C" fn ime_state_health() -> u32 {
+    ffi_guard("ime_state_health", 0, || {
+        let guard = lock_engine();
+        match *guard {
+            Some(ref e) => e.state_health().to_bits(),
+            None => 0,
+        }
+    })
+}
+
+/// Start warming up the data tables on a background thread.
+///
+/// Call once at app launch, before the user starts typing. Returns
+/// immediately; later calls do nothing. Doesn't need `ime_init`.
+#[no_mangle]
+pub extern "C" fn ime_prewarm() {
+    ffi_guard("ime_prewarm", (), || {
+        let _ = Engine::prewarm();
+    })
+}
+
+/// Progress of `ime_prewarm`.
+///
+/// # Returns
+/// 0=not started, 1=running, 2=ready, 3=a table failed validation
+/// (name via `ime_last_error`).
+#[no_mangle]
+pub extern "C" fn ime_prewarm_status() -> u8 {
+    ffi_guard(
+        "ime_prewarm_status",
+        engine::prewarm::PrewarmStatus::Failed as u8,
+        || {
+            let status = Engine::prewarm_status();
+            if let Some(table) = Engine::prewarm_failure() {
+                set_last_error(format!("data table `{}` is corrupt", table));
+            }
+            status as u8
+        },
+    )
+}
+
+// ============================================================
+// Sync FFI
+// ============================================================
+
+/// Merge settings and shortcuts with the document shared by the host.
+///
+/// `pull`/`push` move the shared document (see `sync::PullCallback`);
+/// they run while the engine lock is held and must not call `ime_*`.
+/// `device` names this machine (e.g. host name). If `pulled` is not null,
+/// it receives the number of entries taken from the shared document.
+///
+/// # Returns
+/// * `0` on success
+/// * `-1` if `device` is null / not UTF-8, or engine not initialized
+/// * `SyncError::code()` (30=transport failed, 31=unreadable document,
+///   others as in `ime_import_profile`); nothing is applied.
+///   Details via `ime_last_error`.
+///
+/// # Safety
+/// `device` must be a valid null-terminated string or null; `pulled` must
+/// be null or point to a writable `u32`.
+#[no_mangle]
+pub unsafe extern "C" fn ime_sync(
+    pull: PullCallback,
+    push: PushCallback,
+    user_data: *mut c_void,
+    device: *const c_char,
+    pulled: *mut u32,
+) -> i32 {
+    ffi_guard("ime_sync", IME_ERR_PANIC, || {
+        if device.is_null() {
+            set_last_error("device is null".into());
+            return IME_ERR_INVALID_ARG;
+        }
+        let Ok(device) = CStr::from_ptr(device).to_str() else {
+            set_last_error("device is not valid UTF-8".into());
+            return IME_ERR_INVALID_ARG;
+        };
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis() as u64);
+        let mut transport = CallbackTransport::new(pull, push, user_data);
+
+        let mut guard = lock_engine();
+        let code = match *guard {
+            Some(ref mut e) => match e.sync(&mut transport, device, now) {
+                Ok(report) => {
+                    if !pulled.is_null() {
+                        *pulled = report.pulled as u32;
+                    }
+                    0
+                }
+                Err(err) => {
+                    set_last_error(err.to_string());
+                    err.code()
+                }
+            },
+            None => {
+                set_last_error("engine not initialized".into());
+                IME_ERR_INVALID_ARG
+            }
+        };
+        drop(guard);
+        flush_notifications();
+        code
+    })
+}
+
+// ============================================================
+// Privacy FFI
+// ============================================================
+
+/// Set what the engine may keep about typed words.
+///
+/// Nothing is kept until this turns retention on; features that look back
+/// (correction-pack context, word counts) stay idle until then.
+///
+/// # Arguments
+/// * `retain` - false = keep nothing past the current word (drops history now)
+/// * `expire_minutes` - forget words older than this (0 = never)
+#[no_mangle]
+pub extern "C" fn ime_set_privacy(retain: bool, expire_minutes: u32) {
+    ffi_guard("ime_set_privacy", (), || {
+        let mut guard = lock_engine();
+        if let Some(ref mut e) = *guard {
+            e.set_privacy(Privacy {
+                retain,
+                expire_after: (expire_minutes > 0)
+                    .then(|| Duration::from_secs(u64::from(expire_minutes) * 60)),
+            });
+        }
+    })
+}
+
+/// Count finished words (opt-in; turning it off forgets the counts).
+///
+/// Counting follows `ime_set_privacy`: nothing is counted with retention
+/// off, and a word's count expires with the rest of the history.
+#[no_mangle]
+pub extern "C" fn ime_set_word_counting(enabled: bool) {
+    ffi_guard("ime_set_word_counting", (), || {
+        if let Some(ref mut e) = *lock_engine() {
+            e.set_word_counting(enabled);
+        }
+    })
+}
+
+/// Word counts as CSV (`word,count` header, most typed first).
+///
+/// # Returns
+/// Newly allocated string (free with `ime_free_string`), or null if
+/// engine not initialized.
+#[no_mangle]
+pub extern "C" fn ime_word_counts_csv() -> *mut c_char {
+    ffi_guard("ime_word_counts_csv", std::ptr::null_mut(), || {
+        let guard = lock_engine();
+        match *guard {
+            Some(ref e) => into_c_string(e.word_counts_csv()),
+            None => std::ptr::null_mut(),
+        }
+    })
+}
+
+/// Forget all typing data: current word, word history and practice stats.
+///
+/// For a "Clear typing data" button. Settings and shortcuts stay.
+#[no_mangle]
+pub extern "C" fn ime_wipe_all() {
+    ffi_guard("ime_wipe_all", (), || {
+        if let Some(ref mut e) = *lock_engine() {
+            e.wipe_all();
+        }
+        *lock_practice() = None;
+    })
+}
+
+// ============================================================
+// Practice FFI
+// ============================================================
+
+fn lock_practice() -> std::sync::MutexGuard<'static, Option<PracticeSession>> {
+    PRACTICE.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Start a typing-practice run over `target` (replaces any running one).
+///
+/// Practice has its own engine; it doesn't touch the one used for typing.
+///
+/// # Arguments
+/// * `target` - text to type (UTF-8)
+/// * `method` - 0=Telex, 1=VNI
+///
+/// # Returns
+/// `0` on success, `-1` if `target` is null or not UTF-8.
+///
+/// # Safety
+/// `target` must be a valid null-terminated string or null.
+#[no_mangle]
+pub unsafe extern "C" fn ime_practice_start(target: *const c_char, method: u8) -> i32 {
+    ffi_guard("ime_practice_start", IME_ERR_PANIC, || {
+        if target.is_null() {
+            set_last_error("target is null".into());
+            return IME_ERR_INVALID_ARG;
+        }
+        let Ok(target) = CStr::from_ptr(target).to_str() else {
+            set_last_error("target is not valid UTF-8".into());
+            return IME_ERR_INVALID_ARG;
+        };
+        let mut session = PracticeSession::new(target);
+        session.set_method(config::Method::from_id(method));
+        *lock_practice() = Some(session);
+        0
+    })
+}
+
+/// Grade one keystroke of the practice run.
+///
+/// # Returns
+/// `Grade::code()`: 0=on track, 1=done, 2..8 = kind of mistake
+/// (letter, circumflex, horn, breve, extra tone, mark, stroke),
+/// `-1` if no run is started.
+#[no_mangle]
+pub extern "C" fn ime_practice_key(key: u16, caps: bool, shift: bool) -> i32 {
+    ffi_guard("ime_practice_key", IME_ERR_PANIC, || {
+        match *lock_practice() {
+            Some(ref mut s) => s.grade_key(key, caps, shift).code(),
+            None => IME_ERR_INVALID_ARG,
+        }
+    })
+}
+
+/// Text typed so far in the practice run.
+///
+/// # Returns
+/// UTF-8 string (free with `ime_free_string`), or null if no run is started.
+#[no_mangle]
+pub extern "C" fn ime_practice_screen() -> *mut c_char {
+    ffi_guard(
+        "ime_practice_screen",
+        std::ptr::null_mut(),
+        || match *lock_practice() {
+            Some(ref s) => into_c_string(s.screen()),
+            None => std::ptr::null_mut(),
+        },
+    )
+}
+
+/// Practice statistics as JSON.
+///
+/// `{"keys", "progress", "wpm", "accuracy", "mistakes": {"<kind>": n}}`,
+/// kinds: letter, circumflex, horn, breve, extra-tone, sac, huyen, hoi,
+/// nga, nang, extra-mark, stroke.
+///
+/// # Returns
+/// UTF-8 string (free with `ime_free_string`), or null if no run is started.
+#[no_mangle]
+pub extern "C" fn ime_practice_stats() -> *mut c_char {
+    ffi_guard(
+        "ime_practice_stats",
+        std::ptr::null_mut(),
+        || match *lock_practice() {
+            Some(ref s) => into_c_string(s.stats().to_json().to_pretty()),
+            None => std::ptr::null_mut(),
+        },
+    )
+}
+
+/// End the practice run.
+#[no_mangle]
+pub extern "C" fn ime_practice_end() {
+    ffi_guard("ime_practice_end", (), || {
+        *lock_practice() = None;
+    })
+}
+
+// ============================================================
+// Tests
+// ============================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::keys;
+    use serial_test::serial;
+
+    #[test]
+    #[serial]
+    fn test_ffi_flow() {
+        ime_init();
+        ime_method(0); // Telex
+
+        // Type 'a' + 's' -> á
+        let r1 = ime_key(keys::A, false, false);
+        assert!(!r1.is_null());
+        unsafe { ime_free(r1) };
+
+        let r2 = ime_key(keys::S, false, false);
+        assert!(!r2.is_null());
+        unsafe {
+            assert_eq!((*r2).chars[0], 'á' as u32);
+            ime_free(r2);
+        }
+
+        ime_clear();
+    }
+
+    #[test]
+    #[serial]
+    fn test_key_event_ffi_flow() {
+        use crate::engine::event::modifier;
+
+        ime_init();
+        ime_method(0); // Telex
+
+        // a down/up, s down → á
+        for (down, key) in [(true, keys::A), (false, keys::A)] {
+            let r = ime_key_event(key, 0, down, false);
+            unsafe { ime_free(r) };
+        }
+        let r = ime_key_event(keys::S, 0, true, false);
+        unsafe {
+            assert_eq!((*r).chars[0], 'á' as u32);
+            ime_free(r);
+        }
+
+        // Key-up never produces output
+        let r = ime_key_event(keys::S, 0, false, false);
+        unsafe {
+            assert_eq!((*r).action, 0);
+            ime_free(r);
+        }
+
+        // Cmd+A clears buffer
+        let r = ime_key_event(keys::A, modifier::CMD, true, false);
+        unsafe { ime_free(r) };
+        let r = ime_key_event(keys::S, 0, true, false);
+        unsafe {
+            assert_eq!((*r).action, 0);
+            ime_free(r);
+        }
+
+        ime_clear();
+    }
+
+    #[test]
+    #[serial]
+    fn test_text_ffi_flow() {
+        ime_init();
+        ime_method(0); // Telex
+
+        // OS dead key produced "ê", then 's' adds sắc
+        let r = ime_text('ê' as u32);
+        unsafe {
+            assert_eq!((*r).action, 0);
+            ime_free(r);
+        }
+        let r = ime_key(keys::S, false, false);
+        unsafe {
+            assert_eq!((*r).backspace, 1);
+            assert_eq!((*r).chars[0], 'ế' as u32);
+            ime_free(r);
+        }
+
+        // Surrogate is not a valid char: ends the word
+        let r = ime_text(0xD800);
+        unsafe { ime_free(r) };
+        let r = ime_key(keys::S, false, false);
+        unsafe {
+            assert_eq!((*r).action, 0);
+            ime_free(r);
+        }
+
+        ime_clear();
+    }
+
+    #[test]
+    #[serial]
+    fn test_shortcut_ffi_add_and_clear() {
+        ime_init();
+        ime_clear_shortcuts(); // Clear any existing shortcuts
+        ime_method(0); // Telex
+
+        // Add a shortcut via FFI
+        let trigger = CString::new("vn").unwrap();
+        let replacement = CString::new("Việt Nam").unwrap();
+
+        unsafe {
+            ime_add_shortcut(trigger.as_ptr(), replacement.as_ptr());
+        }
+
+        // Verify shortcut was added by checking engine state
+        let guard = lock_engine();
+        if let Some(ref e) = *guard {
+            assert_eq!(e.shortcuts().len(), 1);
+        }
+        drop(guard);
+
+        // Clear all shortcuts
+        ime_clear_shortcuts();
+
+        // Verify shortcuts cleared
+        let guard = lock_engine();
+        if let Some(ref e) = *guard {
+            assert_eq!(e.shortcuts().len(), 0);
+        }
+        drop(guard);
 
         ime_clear();
     }
 
+    #[test]
+    #[serial]
+    #[cfg(feature = "macros")]
+    fn test_shortcut_conflicts_ffi() {
+        ime_init();
+        ime_method(0);
+        ime_clear_shortcuts();
+        let (vn, an) = (CString::new("vn").unwrap(), CString::new("an").unwrap());
+        unsafe { ime_add_shortcut(vn.as_ptr(), vn.as_ptr()) };
+
+        let json = |p: *mut c_char| {
+            let s = unsafe { CStr::from_ptr(p) }.to_str().unwrap().to_string();
+            unsafe { ime_free_string(p) };
+            s
+        };
+        assert_eq!(
+            json(unsafe { ime_shortcut_conflicts(std::ptr::null()) }).trim(),
+            "[]"
+        );
+        let found = json(unsafe { ime_shortcut_conflicts(an.as_ptr()) });
+        assert!(found.contains("\"kind\": \"word\""), "{}", found);
+
+        ime_clear_shortcuts();
+    }
+
     #[test]
     #[serial]
     fn test_shortcut_ffi_remove() {
@@ -403,4 +2718,621 @@ mod tests {
         ime_clear_shortcuts();
         ime_clear();
     }
+
+    #[test]
+    #[serial]
+    fn test_config_ffi_roundtrip() {
+        ime_init();
+
+        let toml = CString::new("method = \"vni\"\ntone_style = \"classic\"").unwrap();
+        assert_eq!(unsafe { ime_load_config(toml.as_ptr()) }, 0);
+
+        let out = ime_config();
+        assert!(!out.is_null());
+        let out_str = unsafe { CStr::from_ptr(out) }.to_str().unwrap().to_string();
+        unsafe { ime_free_string(out) };
+        assert!(out_str.contains("method = \"vni\""));
+        assert!(out_str.contains("tone_style = \"classic\""));
+
+        ime_init();
+    }
+
+    #[test]
+    #[serial]
+    fn test_config_ffi_errors() {
+        ime_init();
+
+        let toml = CString::new("method = \"vni\"\ncolour = 1").unwrap();
+        assert_eq!(unsafe { ime_load_config(toml.as_ptr()) }, 2);
+
+        let msg = ime_last_error();
+        let msg_str = unsafe { CStr::from_ptr(msg) }.to_str().unwrap().to_string();
+        unsafe { ime_free_string(msg) };
+        assert_eq!(msg_str, "line 2: unknown key `colour`");
+
+        // Nothing applied on error: still Telex
+        let guard = lock_engine();
+        assert_eq!(
+            guard.as_ref().unwrap().config().method,
+            config::Method::Telex
+        );
+        drop(guard);
+
+        assert_eq!(
+            unsafe { ime_load_config(std::ptr::null()) },
+            IME_ERR_INVALID_ARG
+        );
+
+        // A method the settings conflict with is refused
+        let toml = CString::new("bracket_shortcuts = true").unwrap();
+        assert_eq!(unsafe { ime_load_config(toml.as_ptr()) }, 0);
+        ime_method(1);
+        let msg = ime_last_error();
+        let msg_str = unsafe { CStr::from_ptr(msg) }.to_str().unwrap().to_string();
+        unsafe { ime_free_string(msg) };
+        assert!(msg_str.starts_with("conflicting options"), "{}", msg_str);
+        let guard = lock_engine();
+        assert_eq!(
+            guard.as_ref().unwrap().config().method,
+            config::Method::Telex
+        );
+        drop(guard);
+        ime_init();
+    }
+
+    static SEEN: Mutex<Vec<(u8, u8)>> = Mutex::new(Vec::new());
+
+    extern "C" fn record_setting(kind: u8, value: u8, _user_data: *mut c_void) {
+        SEEN.lock().unwrap().push((kind, value));
+        // Re-entrant call must not deadlock
+        let guard = lock_engine();
+        assert!(guard.is_some());
+    }
+
+    #[test]
+    #[serial]
+    fn test_subscribe_ffi() {
+        ime_init();
+        SEEN.lock().unwrap().clear();
+
+        let id = ime_subscribe(record_setting, std::ptr::null_mut());
+        assert_ne!(id, 0);
+
+        ime_method(1);
+        ime_method(1); // unchanged: no notification
+        ime_modern(false);
+        assert_eq!(*SEEN.lock().unwrap(), vec![(0, 1), (2, 1)]);
+
+        assert!(ime_unsubscribe(id));
+        assert!(!ime_unsubscribe(id));
+        ime_method(0);
+        assert_eq!(SEEN.lock().unwrap().len(), 2);
+
+        ime_init();
+    }
+
+    static FEEDBACK: Mutex<Vec<u8>> = Mutex::new(Vec::new());
+
+    extern "C" fn record_feedback(kind: u8, _user_data: *mut c_void) {
+        FEEDBACK.lock().unwrap().push(kind);
+    }
+
+    #[test]
+    #[serial]
+    fn test_subscribe_feedback_ffi() {
+        ime_init();
+        FEEDBACK.lock().unwrap().clear();
+        let toml = CString::new("feedback = true\nfeedback_gap_ms = 0").unwrap();
+        assert_eq!(unsafe { ime_load_config(toml.as_ptr()) }, 0);
+
+        let id = ime_subscribe_feedback(record_feedback, std::ptr::null_mut());
+        assert_ne!(id, 0);
+        for key in [keys::A, keys::S, keys::SPACE] {
+            unsafe { ime_free(ime_key(key, false, false)) };
+        }
+        assert_eq!(*FEEDBACK.lock().unwrap(), vec![0]);
+
+        assert!(ime_unsubscribe(id));
+        assert!(!ime_unsubscribe(id));
+        ime_init();
+    }
+
+    #[test]
+    #[serial]
+    fn test_key_events_batch() {
+        use engine::event::KeyEvent;
+
+        ime_init();
+        ime_method(0);
+        let events = [
+            KeyEvent::down(keys::A, 0),
+            KeyEvent::up(keys::A, 0),
+            KeyEvent::down(keys::A, 0),
+            KeyEvent::up(keys::A, 0),
+            KeyEvent::down(keys::S, 0),
+        ];
+        let mut out: Vec<Result> = (0..events.len()).map(|_| Result::none()).collect();
+        let code = unsafe { ime_key_events(events.as_ptr(), events.len(), out.as_mut_ptr()) };
+        assert_eq!(code, 0);
+        // a, a (â) and s (ấ): the â edit is merged into the last result
+        assert_eq!(out[0].action, 0);
+        assert_eq!((out[2].action, out[2].count), (1, 0));
+        assert_eq!((out[4].backspace, out[4].count), (1, 1));
+        assert_eq!(out[4].chars[0], 'ấ' as u32);
+
+        assert_eq!(
+            unsafe { ime_key_events(events.as_ptr(), 1, std::ptr::null_mut()) },
+            -1
+        );
+    }
+
+    #[test]
+    #[serial]
+    fn test_output_profile_ffi() {
+        ime_init();
+        let excel = CString::new("com.microsoft.Excel").unwrap();
+        unsafe { ime_set_app(excel.as_ptr()) };
+        assert_eq!(ime_edit_delay_hint(), 8000);
+
+        // Mid-word keys pass through, the word is fixed at the boundary
+        ime_method(0);
+        for key in [keys::A, keys::A] {
+            let r = ime_key(key, false, false);
+            assert_eq!(unsafe { (*r).action }, 0);
+            unsafe { ime_free(r) };
+        }
+        let r = ime_key(keys::SPACE, false, false);
+        assert_eq!(unsafe { ((*r).backspace, (*r).count) }, (2, 2));
+        unsafe { ime_free(r) };
+
+        unsafe { ime_set_app(std::ptr::null()) };
+        assert_eq!(ime_edit_delay_hint(), 0);
+        ime_set_output_profile(3, 500, false);
+        assert_eq!(ime_edit_delay_hint(), 500);
+        ime_set_output_profile(0, 0, false);
+    }
+
+    #[test]
+    #[serial]
+    fn test_select_replace_ffi() {
+        ime_init();
+        ime_method(0);
+        ime_set_select_replace(true);
+        ime_set_caret(3);
+        let (mut start, mut end) = (0u32, 0u32);
+
+        let r = ime_key(keys::A, false, false);
+        unsafe { ime_free(r) };
+        assert!(!unsafe { ime_edit_range(&mut start, &mut end) });
+
+        let r = ime_key(keys::A, false, false);
+        assert_eq!(unsafe { ((*r).action, (*r).backspace) }, (3, 1));
+        unsafe { ime_free(r) };
+        assert!(unsafe { ime_edit_range(&mut start, &mut end) });
+        assert_eq!((start, end), (3, 4));
+
+        ime_clear();
+        ime_set_caret(-1);
+        ime_set_select_replace(false);
+    }
+
+    #[test]
+    #[serial]
+    #[cfg(feature = "autocorrect")]
+    fn test_enter_after_edit_ffi() {
+        ime_init();
+        ime_method(0);
+        ime_set_enter_after_edit(true);
+        for key in [keys::T, keys::E, keys::X, keys::T] {
+            unsafe { ime_free(ime_key(key, false, false)) };
+        }
+        let r = ime_key(keys::RETURN, false, false);
+        let (count, last) = unsafe { ((*r).count, (*r).chars[(*r).count as usize - 1]) };
+        unsafe { ime_free(r) };
+        assert_eq!((count, last), (4, '\n' as u32));
+
+        ime_clear();
+        ime_set_enter_after_edit(false);
+    }
+
+    #[test]
+    #[serial]
+    #[cfg(feature = "macros")]
+    fn test_clock_ffi() {
+        extern "C" fn nine_oh_five(_: *mut c_void) -> i64 {
+            // 2026-10-16 09:05
+            1_792_141_500
+        }
+        ime_init();
+        ime_method(0);
+        ime_clear_shortcuts();
+        let trigger = CString::new("ts").unwrap();
+        let replacement = CString::new("%d %t").unwrap();
+        unsafe { ime_add_shortcut(trigger.as_ptr(), replacement.as_ptr()) };
+        ime_set_clock(Some(nine_oh_five), std::ptr::null_mut());
+
+        for key in [keys::T, keys::S] {
+            unsafe { ime_free(ime_key(key, false, false)) };
+        }
+        let r = ime_key(keys::SPACE, false, false);
+        let (chars, count) = unsafe { ((*r).chars, (*r).count as usize) };
+        let text: String = chars[..count]
+            .iter()
+            .filter_map(|&c| char::from_u32(c))
+            .collect();
+        unsafe { ime_free(r) };
+        assert_eq!(text, "16/10/2026 09:05 ");
+
+        ime_set_clock(None, std::ptr::null_mut());
+        ime_clear_shortcuts();
+        ime_clear();
+    }
+
+    #[test]
+    #[serial]
+    fn test_word_info_ffi() {
+        ime_init();
+        ime_method(0);
+        ime_clear();
+        let (mut start, mut end, mut committed) = (0, 0, true);
+        for key in [keys::A, keys::A, keys::S] {
+            let r = ime_key(key, false, false);
+            unsafe { ime_free(r) };
+        }
+        let word = unsafe { ime_word_info(&mut start, &mut end, &mut committed) };
+        assert_eq!(unsafe { CStr::from_ptr(word) }.to_str().unwrap(), "ấ");
+        assert_eq!((start, end, committed), (-1, 0, false));
+        unsafe { ime_free_string(word) };
+        let preview = ime_current_preview();
+        assert_eq!(unsafe { CStr::from_ptr(preview) }.to_str().unwrap(), "ấ");
+        unsafe { ime_free_string(preview) };
+
+        let r = ime_key(keys::COMMA, false, false);
+        unsafe { ime_free(r) };
+        let word = unsafe { ime_word_info(&mut start, &mut end, &mut committed) };
+        assert_eq!((start, end, committed), (-2, -1, true));
+        unsafe { ime_free_string(word) };
+
+        let r = ime_key(keys::B, false, false);
+        unsafe { ime_free(r) };
+        ime_clear();
+        assert!(unsafe { ime_word_info(&mut start, &mut end, std::ptr::null_mut()) }.is_null());
+    }
+
+    #[test]
+    #[serial]
+    #[cfg(feature = "autocorrect")]
+    fn test_last_restore_reason_ffi() {
+        ime_init();
+        ime_method(0);
+        ime_clear();
+        for key in [keys::T, keys::E, keys::X, keys::T, keys::SPACE] {
+            let r = ime_key(key, false, false);
+            unsafe { ime_free(r) };
+        }
+        let json = ime_last_restore_reason();
+        let reason = unsafe { CStr::from_ptr(json) }
+            .to_str()
+            .unwrap()
+            .to_string();
+        unsafe { ime_free_string(json) };
+        assert!(reason.contains("\"modifier-consonant\""), "{}", reason);
+        assert!(reason.contains("\"xt\""), "{}", reason);
+
+        for key in [keys::V, keys::I, keys::E, keys::E, keys::T, keys::SPACE] {
+            let r = ime_key(key, false, false);
+            unsafe { ime_free(r) };
+        }
+        assert!(ime_last_restore_reason().is_null());
+    }
+
+    #[test]
+    #[serial]
+    fn test_restore_check_ffi() {
+        ime_init();
+        let disabled = || {
+            let toml = ime_config();
+            let s = unsafe { CStr::from_ptr(toml) }
+                .to_str()
+                .unwrap()
+                .to_string();
+            unsafe { ime_free_string(toml) };
+            EngineConfig::from_toml(&s)
+                .unwrap()
+                .restore_disabled
+                .to_string()
+        };
+        ime_restore_check(6, false);
+        ime_restore_check(99, false);
+        assert_eq!(disabled(), "modifier-between-vowels");
+        ime_restore_check(6, true);
+        assert_eq!(disabled(), "");
+    }
+
+    #[test]
+    #[serial]
+    fn test_context_resync_ffi() {
+        ime_init();
+        ime_method(0);
+        ime_clear();
+
+        let prefix = CString::new("Tiếng viet").unwrap();
+        assert_eq!(unsafe { ime_text_context(prefix.as_ptr()) }, 4);
+        let r = ime_key(keys::J, false, false);
+        assert_eq!(unsafe { ((*r).backspace, (*r).count) }, (2, 2));
+        unsafe { ime_free(r) };
+
+        // After a jump the word is gone
+        ime_caret_moved(-1);
+        let r = ime_key(keys::S, false, false);
+        assert_eq!(unsafe { (*r).action }, 0);
+        unsafe { ime_free(r) };
+
+        ime_focus_changed();
+        assert_eq!(
+            unsafe { ime_text_context(std::ptr::null()) },
+            IME_ERR_INVALID_ARG
+        );
+    }
+
+    #[test]
+    fn test_to_keystrokes_ffi() {
+        let word = CString::new("được").unwrap();
+        let keys = unsafe { ime_to_keystrokes(word.as_ptr(), 1) };
+        assert_eq!(
+            unsafe { CStr::from_ptr(keys) }.to_str().unwrap(),
+            "d9u7o7c5"
+        );
+        unsafe { ime_free_string(keys) };
+
+        let word = CString::new("a b").unwrap();
+        assert!(unsafe { ime_to_keystrokes(word.as_ptr(), 0) }.is_null());
+    }
+
+    #[test]
+    fn test_long_press_ffi() {
+        let out = ime_long_press('D' as u32);
+        assert_eq!(unsafe { CStr::from_ptr(out) }.to_str().unwrap(), "DĐ");
+        unsafe { ime_free_string(out) };
+        let out = ime_long_press(0xD800);
+        assert_eq!(unsafe { CStr::from_ptr(out) }.to_str().unwrap(), "");
+        unsafe { ime_free_string(out) };
+    }
+
+    #[test]
+    #[serial]
+    fn test_practice_ffi() {
+        assert_eq!(ime_practice_key(keys::A, false, false), IME_ERR_INVALID_ARG);
+        let target = CString::new("ăn").unwrap();
+        assert_eq!(unsafe { ime_practice_start(target.as_ptr(), 0) }, 0);
+        assert_eq!(ime_practice_key(keys::A, false, false), 0);
+        assert_eq!(ime_practice_key(keys::S, false, false), 7);
+        assert_eq!(ime_practice_key(keys::DELETE, false, false), 0);
+        assert_eq!(ime_practice_key(keys::A, false, false), 0);
+        assert_eq!(ime_practice_key(keys::W, false, false), 0);
+        assert_eq!(ime_practice_key(keys::N, false, false), 1);
+
+        let screen = ime_practice_screen();
+        assert_eq!(unsafe { CStr::from_ptr(screen) }.to_str().unwrap(), "ăn");
+        unsafe { ime_free_string(screen) };
+        let stats = ime_practice_stats();
+        let json = unsafe { CStr::from_ptr(stats) }
+            .to_str()
+            .unwrap()
+            .to_string();
+        unsafe { ime_free_string(stats) };
+        assert!(json.contains("\"extra-mark\": 1"), "{}", json);
+        ime_practice_end();
+        assert!(ime_practice_screen().is_null());
+    }
+
+    #[test]
+    fn test_self_test_ffi() {
+        let report = ime_self_test();
+        let json = unsafe { CStr::from_ptr(report) }
+            .to_str()
+            .unwrap()
+            .to_string();
+        unsafe { ime_free_string(report) };
+        let report = json::parse(&json).unwrap();
+        assert_eq!(report.get("passed").and_then(|v| v.as_bool()), Some(true));
+    }
+
+    #[test]
+    #[serial]
+    fn test_debug_state_ffi() {
+        ime_init();
+        ime_clear();
+        ime_method(0);
+        for key in [keys::V, keys::I, keys::E, keys::E] {
+            let r = ime_key(key, false, false);
+            unsafe { ime_free(r) };
+        }
+        let state = ime_debug_state();
+        let json = unsafe { CStr::from_ptr(state) }
+            .to_str()
+            .unwrap()
+            .to_string();
+        unsafe { ime_free_string(state) };
+        let state = json::parse(&json).unwrap();
+        assert_eq!(state.get("shown").and_then(|v| v.as_str()), Some("viê"));
+        assert_eq!(state.get("raw").and_then(|v| v.as_str()), Some("viee"));
+        ime_clear();
+    }
+
+    #[test]
+    fn test_prewarm_ffi() {
+        ime_prewarm();
+        ime_prewarm(); // no-op
+        while ime_prewarm_status() == 1 {
+            std::thread::yield_now();
+        }
+        assert_eq!(ime_prewarm_status(), 2);
+    }
+
+    #[test]
+    #[serial]
+    fn test_storage_ffi_roundtrip() {
+        let dir = std::env::temp_dir().join(format!("gonhanh-ffi-{}", std::process::id()));
+        let dir_c = CString::new(dir.to_str().unwrap()).unwrap();
+
+        ime_init();
+        assert_eq!(ime_save(), -1); // no storage yet
+        assert_eq!(unsafe { ime_set_storage_dir(dir_c.as_ptr()) }, 0);
+        ime_method(1);
+        assert_eq!(ime_save(), 0);
+
+        ime_init();
+        assert_eq!(unsafe { ime_set_storage_dir(dir_c.as_ptr()) }, 0);
+        std::fs::write(dir.join("shortcuts.tsv"), "vn\tx\tbogus\n").unwrap();
+        assert_eq!(ime_load(), 0);
+        assert_eq!(ime_state_health(), 2); // shortcuts reset, settings fine
+        assert!(dir.join("shortcuts.tsv.corrupt").exists());
+        let cfg = ime_config();
+        assert!(unsafe { CStr::from_ptr(cfg) }
+            .to_str()
+            .unwrap()
+            .contains("vni"));
+        unsafe { ime_free_string(cfg) };
+
+        ime_method(0);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    #[serial]
+    fn test_profile_ffi_roundtrip() {
+        ime_init();
+        ime_method(1);
+        let json = ime_export_profile();
+        assert!(!json.is_null());
+
+        ime_init();
+        assert_eq!(unsafe { ime_import_profile(json) }, 0);
+        unsafe { ime_free_string(json) };
+        let cfg = ime_config();
+        assert!(unsafe { CStr::from_ptr(cfg) }
+            .to_str()
+            .unwrap()
+            .contains("vni"));
+        unsafe { ime_free_string(cfg) };
+
+        let bad = CString::new("{}").unwrap();
+        assert_eq!(unsafe { ime_import_profile(bad.as_ptr()) }, 21);
+        ime_method(0);
+    }
+
+    // Shared document for the sync test (one "cloud" slot)
+    static CLOUD: Mutex<Option<Vec<u8>>> = Mutex::new(None);
+
+    extern "C" fn cloud_pull(buf: *mut u8, cap: usize, _: *mut c_void) -> isize {
+        match &*CLOUD.lock().unwrap() {
+            None => -1,
+            Some(data) => {
+                if data.len() <= cap {
+                    unsafe { std::ptr::copy_nonoverlapping(data.as_ptr(), buf, data.len()) };
+                }
+                data.len() as isize
+            }
+        }
+    }
+
+    extern "C" fn cloud_push(data: *const u8, len: usize, _: *mut c_void) -> i32 {
+        let data = unsafe { std::slice::from_raw_parts(data, len) };
+        *CLOUD.lock().unwrap() = Some(data.to_vec());
+        0
+    }
+
+    #[test]
+    #[serial]
+    fn test_sync_ffi() {
+        let mac = CString::new("mac").unwrap();
+        let win = CString::new("win").unwrap();
+        let null = std::ptr::null_mut();
+        let trigger = CString::new("gn").unwrap();
+        let replacement = CString::new("Gõ Nhanh").unwrap();
+
+        ime_init();
+        unsafe { ime_add_shortcut(trigger.as_ptr(), replacement.as_ptr()) };
+        let code = unsafe { ime_sync(cloud_pull, cloud_push, null, mac.as_ptr(), null as _) };
+        assert_eq!(code, 0);
+
+        ime_init();
+        let mut pulled = 0u32;
+        let code = unsafe { ime_sync(cloud_pull, cloud_push, null, win.as_ptr(), &mut pulled) };
+        assert_eq!(code, 0);
+        assert!(pulled > 0);
+        let profile = ime_export_profile();
+        assert!(unsafe { CStr::from_ptr(profile) }
+            .to_str()
+            .unwrap()
+            .contains("Gõ Nhanh"));
+        unsafe { ime_free_string(profile) };
+
+        *CLOUD.lock().unwrap() = Some(b"garbage".to_vec());
+        let code = unsafe { ime_sync(cloud_pull, cloud_push, null, win.as_ptr(), null as _) };
+        assert_eq!(code, 31);
+        *CLOUD.lock().unwrap() = None;
+    }
+
+    #[test]
+    #[serial]
+    fn test_panic_contained_ffi() {
+        ime_init();
+        ime_method(1);
+        let r = ime_key(keys::A, false, false);
+        unsafe { ime_free(r) };
+
+        // Panic while holding the engine: poisons the lock mid-word
+        let code = ffi_guard("ime_test", IME_ERR_PANIC, || {
+            let _guard = lock_engine();
+            panic!("boom")
+        });
+        assert_eq!(code, IME_ERR_PANIC);
+        let msg = ime_last_error();
+        assert_eq!(
+            unsafe { CStr::from_ptr(msg) }.to_str().unwrap(),
+            "ime_test panicked: boom"
+        );
+        unsafe { ime_free_string(msg) };
+
+        // Lock is usable again; the word is gone but VNI is kept
+        assert!(!ENGINE.is_poisoned());
+        let r = ime_key(keys::N6, false, false);
+        assert_eq!(unsafe { (*r).action }, 0);
+        unsafe { ime_free(r) };
+        let guard = lock_engine();
+        assert_eq!(guard.as_ref().unwrap().config().method, config::Method::Vni);
+    }
+
+    #[test]
+    #[serial]
+    fn test_privacy_ffi() {
+        ime_init();
+        let type_str = |s: &str| {
+            for c in s.chars() {
+                unsafe { ime_free(ime_key(keys::from_char(c).unwrap(), false, false)) };
+            }
+        };
+        let recent = || lock_engine().as_ref().unwrap().recent_words().count();
+
+        type_str("xin chao ");
+        assert_eq!(recent(), 0, "nothing kept until the host opts in");
+        ime_set_privacy(true, 0);
+        type_str("xin chao ");
+        assert_eq!(recent(), 2);
+        ime_set_privacy(false, 0);
+        assert_eq!(recent(), 0);
+        type_str("ban ");
+        assert_eq!(recent(), 0);
+
+        ime_set_privacy(true, 30);
+        type_str("ban ");
+        assert_eq!(recent(), 1);
+        let start = CString::new("ăn").unwrap();
+        assert_eq!(unsafe { ime_practice_start(start.as_ptr(), 0) }, 0);
+        ime_wipe_all();
+        assert_eq!(recent(), 0);
+        assert!(lock_practice().is_none());
+    }
 }
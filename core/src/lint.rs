@@ -0,0 +1,231 @@
+//! Document Linting
+//!
+//! `lint` runs the checks the IME makes while typing over a whole document
+//! and reports what they would change, without changing anything, so an
+//! editor plugin can underline the same words the IME would fix:
+//!
+//! - `Misspelled`: a word with Vietnamese diacritics that is not a
+//!   Vietnamese syllable (`validation::explain`); suggestions come from
+//!   `suggest::suggest_similar`
+//! - `Correction`: a word a correction provider fixes ("nàm" → "làm" with
+//!   the Southern pack); `Engine::lint` asks the engine's packs and
+//!   providers, `lint` has none
+//! - `IySpelling`: "lý" in a document that mostly writes "lí", or the other
+//!   way round. Only the syllables where both spellings are in use (h, k,
+//!   l, m, qu, s, t + i/y with a tone mark) count. With an `IyStyle` set,
+//!   `Engine::lint` reports the other spelling as a `Correction` instead.
+//!
+//! Words are `tokenizer` words, so "nàm-nàm" is two words and links, numbers
+//! and identifiers are skipped. Decomposed words (NFD) are checked as
+//! composed, and fullwidth letters and lookalikes as the ASCII they pass
+//! for (`tokenizer::fold_confusables`). Spans are byte ranges into the
+//! text.
+
+use crate::data::chars::compose;
+use crate::engine::correction::{iy_variant, match_case, swap_iy, Corrections};
+use crate::engine::validation::{explain, Problem};
+use crate::suggest::suggest_similar;
+use crate::tokenizer::{fold_confusables, tokens, TokenKind};
+use std::ops::Range;
+
+/// Most suggestions given for a misspelled word
+const MAX_SUGGESTIONS: usize = 3;
+
+/// What is wrong with a word
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LintKind {
+    Misspelled(Problem),
+    Correction,
+    IySpelling,
+}
+
+impl LintKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LintKind::Misspelled(_) => "misspelled",
+            LintKind::Correction => "correction",
+            LintKind::IySpelling => "iy-spelling",
+        }
+    }
+
+    /// Short English explanation, for a tooltip
+    pub fn message(self) -> &'static str {
+        match self {
+            LintKind::Misspelled(problem) => problem.message(),
+            LintKind::Correction => "common misspelling",
+            LintKind::IySpelling => "i/y spelled unlike the rest of the document",
+        }
+    }
+}
+
+/// A word to underline
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LintIssue {
+    /// Byte range of the word
+    pub span: Range<usize>,
+    pub kind: LintKind,
+    /// Replacements, best first (may be empty)
+    pub suggestions: Vec<String>,
+}
+
+/// Issues in `text`, in order, with the built-in checks only
+pub fn lint(text: &str) -> Vec<LintIssue> {
+    lint_with(text, &Corrections::default())
+}
+
+/// Issues in `text`, in order, asking `corrections` for fixes
+pub fn lint_with(text: &str, corrections: &Corrections) -> Vec<LintIssue> {
+    let words = words(text);
+    // Spelling most of the document uses: true = y
+    let (mut with_i, mut with_y) = (0, 0);
+    for span in &words {
+        match iy_variant(&text[span.clone()]) {
+            Some(true) => with_y += 1,
+            Some(false) => with_i += 1,
+            None => {}
+        }
+    }
+    let minority = match with_i.cmp(&with_y) {
+        std::cmp::Ordering::Less => Some(false),
+        std::cmp::Ordering::Greater => Some(true),
+        std::cmp::Ordering::Equal => None,
+    };
+
+    let mut issues = Vec::new();
+    let mut prev: Option<&str> = None;
+    for span in words {
+        let word = &text[span.clone()];
+        let checked = compose(&fold_confusables(word)).into_owned();
+        if let Some(issue) = check(&checked, prev, corrections, minority) {
+            issues.push(LintIssue {
+                span: span.clone(),
+                ..issue
+            });
+        }
+        prev = Some(word);
+    }
+    issues
+}
+
+/// Byte ranges of the words in `text`
+fn words(text: &str) -> Vec<Range<usize>> {
+    tokens(text)
+        .filter(|t| t.kind == TokenKind::Word)
+        .map(|t| t.span)
+        .collect()
+}
+
+/// First issue found with `word`; the span is filled in by the caller
+fn check(
+    word: &str,
+    prev: Option<&str>,
+    corrections: &Corrections,
+    iy_minority: Option<bool>,
+) -> Option<LintIssue> {
+    let issue = |kind, suggestions| LintIssue {
+        span: 0..0,
+        kind,
+        suggestions,
+    };
+    if let Some(fixed) = corrections.correct(word, prev) {
+        return Some(issue(LintKind::Correction, vec![fixed]));
+    }
+    // Plain ASCII may be English or Vietnamese typed without an IME
+    if !word.is_ascii() {
+        let lower = word.to_lowercase();
+        if let Some(problem) = explain(&lower).problem {
+            if matches!(problem, Problem::NotALetter(_)) {
+                return None;
+            }
+            let suggestions = suggest_similar(&lower, 2)
+                .into_iter()
+                .take(MAX_SUGGESTIONS)
+                .map(|(w, _)| match_case(word, w))
+                .collect();
+            return Some(issue(LintKind::Misspelled(problem), suggestions));
+        }
+    }
+    if iy_minority.is_some() && iy_variant(word) == iy_minority {
+        return Some(issue(LintKind::IySpelling, vec![swap_iy(word)?]));
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{CorrectionPacks, Dialect};
+
+    fn kinds(text: &str) -> Vec<(&str, LintKind)> {
+        lint(text)
+            .into_iter()
+            .map(|i| (&text[i.span], i.kind))
+            .collect()
+    }
+
+    #[test]
+    fn test_words() {
+        let text = "Xin chào, nàm-nàm! Xem www.nàm.vn";
+        let found: Vec<&str> = words(text).into_iter().map(|r| &text[r]).collect();
+        assert_eq!(found, ["Xin", "chào", "nàm", "nàm", "Xem"]);
+    }
+
+    #[test]
+    fn test_clean_text() {
+        assert_eq!(lint("Tiếng Việt rất hay, hello world"), []);
+        assert_eq!(lint(""), []);
+    }
+
+    #[test]
+    fn test_misspelled() {
+        let issues = lint("người Việt tiêg");
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].span, 16..21);
+        assert!(matches!(issues[0].kind, LintKind::Misspelled(_)));
+        // Decomposed: the span covers the combining marks
+        let text = "tie\u{0302}\u{0301}g";
+        assert_eq!(lint(text)[0].span, 0..text.len());
+        assert_eq!(
+            kinds("Hàg"),
+            [("Hàg", LintKind::Misspelled(Problem::InvalidFinal))]
+        );
+    }
+
+    #[test]
+    fn test_corrections() {
+        let mut corrections = Corrections::default();
+        corrections.packs = CorrectionPacks::NONE.with(Dialect::Southern, true);
+        let issues = lint_with("Nàm việc", &corrections);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].kind, LintKind::Correction);
+        assert_eq!(issues[0].suggestions, ["Làm"]);
+        // Nothing to ask without packs
+        assert_eq!(lint("Nàm việc"), []);
+    }
+
+    #[test]
+    fn test_confusables() {
+        let mut corrections = Corrections::default();
+        corrections.packs = CorrectionPacks::NONE.with(Dialect::Southern, true);
+        // Cyrillic а
+        let text = "n\u{0430}\u{0300}m việc";
+        let issues = lint_with(text, &corrections);
+        assert_eq!(issues[0].span, 0..6);
+        assert_eq!(issues[0].suggestions, ["làm"]);
+    }
+
+    #[test]
+    fn test_iy_spelling() {
+        assert_eq!(
+            kinds("lí do, kĩ thuật, mỹ thuật"),
+            [("mỹ", LintKind::IySpelling)]
+        );
+        let issues = lint("Lý do, kỹ thuật, Mĩ");
+        assert_eq!(issues[0].suggestions, ["Mỹ"]);
+        // Even: no spelling to follow
+        assert_eq!(lint("lí do, kỹ thuật"), []);
+        assert_eq!(iy_variant("ly"), None);
+        assert_eq!(iy_variant("quý"), Some(true));
+        assert_eq!(swap_iy("QUÝ").as_deref(), Some("QUÍ"));
+    }
+}
@@ -0,0 +1,346 @@
+//! Neural Diacritic Restorer
+//!
+//! An optional small model for what the rules can't settle from one word
+//! alone: which diacritics toneless text takes in context ("co" →
+//! "có"/"cô"/"cỏ"), and whether keystrokes meant an English word when the
+//! restore checks disagree. The rule-based paths (`data::toneless`,
+//! `engine::restore`) stay the default; the model only runs once the host
+//! hands one over (`Engine::set_diacritic_model`), and it is read from disk
+//! on first use, so an unused model costs nothing.
+//!
+//! The model is a character tagger run with `candle`. It reads lowercase
+//! letters (ids 2-27 for a-z, 1 for anything else, 0 for padding past the
+//! ends), embeds each, looks at a window of neighbours through one hidden
+//! layer and tags every letter with the diacritics it takes. A
+//! `.safetensors` file holds the weights, f16 to keep it small (f32 and
+//! bf16 load too):
+//!
+//! | tensor           | shape            |                                  |
+//! |------------------|------------------|----------------------------------|
+//! | `embedding`      | [28, D]          | letter embeddings                |
+//! | `hidden.weight`  | [W × D, H]       | W letters around each, W odd     |
+//! | `hidden.bias`    | [H]              |                                  |
+//! | `output.weight`  | [H, 19]          | tags                             |
+//! | `output.bias`    | [19]             |                                  |
+//! | `restore.weight` | [H, 1]           | optional: English or not         |
+//! | `restore.bias`   | [1]              |                                  |
+//!
+//! Tag `t` below 18 is the letter with modifier `t / 6` (`chars::tone`)
+//! and tone mark `t % 6` (`chars::mark`); 18 is "đ". A tag a letter can't
+//! take leaves it as it is. The restore head averages the hidden layer over
+//! the keystrokes into the probability they spell an English word, which
+//! competes with the restore checks on confidence (`engine::decision`).
+
+use crate::data::{chars, keys};
+use crate::engine::correction::match_case;
+use crate::engine::restore::RestoreHint;
+use crate::tokenizer::{tokens, TokenKind};
+use candle_core::{DType, Device, Tensor};
+use std::path::PathBuf;
+use std::sync::{Arc, OnceLock};
+
+/// Letter ids: padding, anything else, then a-z
+const VOCAB: usize = 28;
+
+/// Tags: 3 modifiers × 6 tone marks, then "đ"
+const TAGS: usize = 19;
+const TAG_D: u32 = 18;
+
+/// Why a model didn't load
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ModelError(pub String);
+
+impl std::fmt::Display for ModelError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "diacritic model: {}", self.0)
+    }
+}
+
+impl std::error::Error for ModelError {}
+
+impl From<candle_core::Error> for ModelError {
+    fn from(e: candle_core::Error) -> Self {
+        ModelError(e.to_string())
+    }
+}
+
+/// A model file, loaded on first use
+///
+/// Clones share the loaded weights. A file that fails to load is not
+/// retried: every call passes and the rules decide.
+#[derive(Clone)]
+pub struct DiacriticModel {
+    path: PathBuf,
+    model: Arc<OnceLock<Result<Model, ModelError>>>,
+}
+
+struct Model {
+    embedding: Tensor,
+    hidden_w: Tensor,
+    hidden_b: Tensor,
+    output_w: Tensor,
+    output_b: Tensor,
+    restore: Option<(Tensor, Tensor)>,
+    /// Letters on each side of the one tagged
+    radius: usize,
+}
+
+impl DiacriticModel {
+    /// Model at `path`; nothing is read yet
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        DiacriticModel {
+            path: path.into(),
+            model: Arc::default(),
+        }
+    }
+
+    /// Load now rather than on first use, to report a bad file early
+    pub fn preload(&self) -> Result<(), ModelError> {
+        self.model().map(|_| ())
+    }
+
+    fn model(&self) -> Result<&Model, ModelError> {
+        self.model
+            .get_or_init(|| Model::load(&self.path))
+            .as_ref()
+            .map_err(Clone::clone)
+    }
+
+    /// `text` with the diacritics the model puts on its words; `None` if
+    /// the model didn't load. Letters that already have diacritics,
+    /// numbers, links and identifiers are left as they are.
+    pub fn restore_diacritics(&self, text: &str) -> Option<String> {
+        let model = self.model().ok()?;
+        let lower: Vec<char> = text.chars().map(|c| c.to_ascii_lowercase()).collect();
+        let tags = model.tag(&lower).ok()?;
+        let mut out = String::with_capacity(text.len() * 2);
+        let mut chars = 0;
+        for t in tokens(text) {
+            let token = &text[t.span];
+            let len = token.chars().count();
+            if t.kind == TokenKind::Word && token.is_ascii() {
+                let tagged: String = lower[chars..chars + len]
+                    .iter()
+                    .zip(&tags[chars..chars + len])
+                    .map(|(&c, &tag)| apply_tag(c, tag))
+                    .collect();
+                out.push_str(&match_case(token, &tagged));
+            } else {
+                out.push_str(token);
+            }
+            chars += len;
+        }
+        Some(out)
+    }
+
+    /// Probability the keystrokes `raw` spell an English word; `None`
+    /// without a restore head
+    pub fn english_probability(&self, raw: &str) -> Option<f32> {
+        let model = self.model().ok()?;
+        let lower: Vec<char> = raw.chars().map(|c| c.to_ascii_lowercase()).collect();
+        model.english(&lower).ok().flatten()
+    }
+}
+
+impl RestoreHint for DiacriticModel {
+    fn restore(&self, raw: &str, _word: &str) -> Option<f32> {
+        self.english_probability(raw)
+    }
+}
+
+impl Model {
+    fn load(path: &std::path::Path) -> Result<Model, ModelError> {
+        let mut tensors = candle_core::safetensors::load(path, &Device::Cpu)?;
+        let mut take = |name: &str| -> Result<Tensor, ModelError> {
+            let t = tensors
+                .remove(name)
+                .ok_or_else(|| ModelError(format!("no tensor `{}`", name)))?;
+            Ok(t.to_dtype(DType::F32)?)
+        };
+        let embedding = take("embedding")?;
+        let hidden_w = take("hidden.weight")?;
+        let hidden_b = take("hidden.bias")?;
+        let output_w = take("output.weight")?;
+        let output_b = take("output.bias")?;
+        let restore = match (take("restore.weight"), take("restore.bias")) {
+            (Ok(w), Ok(b)) => Some((w, b)),
+            _ => None,
+        };
+
+        let (vocab, dim) = embedding.dims2()?;
+        let (window, hidden) = hidden_w.dims2()?;
+        let shape_ok = vocab == VOCAB
+            && dim > 0
+            && window % dim == 0
+            && (window / dim) % 2 == 1
+            && hidden_b.dims() == [hidden]
+            && output_w.dims() == [hidden, TAGS]
+            && output_b.dims() == [TAGS]
+            && restore
+                .as_ref()
+                .is_none_or(|(w, b)| w.dims() == [hidden, 1] && b.dims() == [1]);
+        if !shape_ok {
+            return Err(ModelError("tensor shapes don't fit together".into()));
+        }
+        Ok(Model {
+            embedding,
+            hidden_w,
+            hidden_b,
+            output_w,
+            output_b,
+            restore,
+            radius: window / dim / 2,
+        })
+    }
+
+    /// Hidden layer for each of `text`'s characters: [len, H]
+    fn hidden(&self, text: &[char]) -> candle_core::Result<Tensor> {
+        let pad = std::iter::repeat_n(0, self.radius);
+        let ids: Vec<u32> = pad
+            .clone()
+            .chain(text.iter().map(|&c| letter_id(c)))
+            .chain(pad)
+            .collect();
+        let ids = Tensor::new(ids.as_slice(), &Device::Cpu)?;
+        let embedded = self.embedding.index_select(&ids, 0)?;
+        let windows: Vec<Tensor> = (0..=2 * self.radius)
+            .map(|offset| embedded.narrow(0, offset, text.len()))
+            .collect::<candle_core::Result<_>>()?;
+        Tensor::cat(&windows, 1)?
+            .matmul(&self.hidden_w)?
+            .broadcast_add(&self.hidden_b)?
+            .relu()
+    }
+
+    /// Best tag for each of `text`'s characters
+    fn tag(&self, text: &[char]) -> candle_core::Result<Vec<u32>> {
+        if text.is_empty() {
+            return Ok(Vec::new());
+        }
+        self.hidden(text)?
+            .matmul(&self.output_w)?
+            .broadcast_add(&self.output_b)?
+            .argmax(1)?
+            .to_vec1()
+    }
+
+    fn english(&self, raw: &[char]) -> candle_core::Result<Option<f32>> {
+        let Some((w, b)) = &self.restore else {
+            return Ok(None);
+        };
+        if raw.is_empty() {
+            return Ok(None);
+        }
+        let logit = self
+            .hidden(raw)?
+            .mean_keepdim(0)?
+            .matmul(w)?
+            .broadcast_add(b)?
+            .flatten_all()?
+            .to_vec1::<f32>()?[0];
+        Ok(Some(1.0 / (1.0 + (-logit).exp())))
+    }
+}
+
+fn letter_id(c: char) -> u32 {
+    match c {
+        'a'..='z' => c as u32 - 'a' as u32 + 2,
+        _ => 1,
+    }
+}
+
+/// `c` with the diacritics of `tag`, or as it is if it can't take them
+fn apply_tag(c: char, tag: u32) -> char {
+    if tag == TAG_D {
+        return if c == 'd' { chars::get_d(false) } else { c };
+    }
+    let Some(key) = keys::from_char(c) else {
+        return c;
+    };
+    if key == keys::D || !keys::is_vowel(key) {
+        return c;
+    }
+    chars::to_char(key, false, (tag / 6) as u8, (tag % 6) as u8).unwrap_or(c)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::chars::{mark, tone};
+    use std::collections::HashMap;
+
+    /// One-hot letters, a window of one: "a" → "â", "o" → "ó", "d" → "đ",
+    /// and "x" or "w" anywhere is English
+    fn save_model(path: &std::path::Path) {
+        let dev = Device::Cpu;
+        let eye = Tensor::eye(VOCAB, DType::F32, &dev).unwrap();
+        let mut out = vec![0f32; VOCAB * TAGS];
+        let mut english = vec![0f32; VOCAB];
+        let mut tag = |c: char, t: u32| out[letter_id(c) as usize * TAGS + t as usize] = 1.0;
+        tag('a', tone::CIRCUMFLEX as u32 * 6);
+        tag('o', mark::SAC as u32);
+        tag('d', TAG_D);
+        english[letter_id('x') as usize] = 40.0;
+        english[letter_id('w') as usize] = 40.0;
+        let tensors: HashMap<&str, Tensor> = [
+            ("embedding", eye.clone()),
+            ("hidden.weight", eye.to_dtype(DType::F16).unwrap()),
+            (
+                "hidden.bias",
+                Tensor::zeros(VOCAB, DType::F32, &dev).unwrap(),
+            ),
+            (
+                "output.weight",
+                Tensor::from_vec(out, (VOCAB, TAGS), &dev).unwrap(),
+            ),
+            (
+                "output.bias",
+                Tensor::zeros(TAGS, DType::F32, &dev).unwrap(),
+            ),
+            (
+                "restore.weight",
+                Tensor::from_vec(english, (VOCAB, 1), &dev).unwrap(),
+            ),
+            ("restore.bias", Tensor::new(&[-5f32], &dev).unwrap()),
+        ]
+        .into();
+        candle_core::safetensors::save(&tensors, path).unwrap();
+    }
+
+    fn temp_model(name: &str) -> DiacriticModel {
+        let path = std::env::temp_dir().join(format!("gonhanh-ml-{}.safetensors", name));
+        save_model(&path);
+        DiacriticModel::new(path)
+    }
+
+    #[test]
+    fn test_restore_diacritics() {
+        let model = temp_model("restore");
+        assert_eq!(
+            model.restore_diacritics("Do an, cho 2 ban").as_deref(),
+            Some("Đó ân, chó 2 bân")
+        );
+        assert_eq!(
+            model
+                .restore_diacritics("xem https://gonhanh.org do_an")
+                .as_deref(),
+            Some("xem https://gonhanh.org do_an")
+        );
+    }
+
+    #[test]
+    fn test_restore_hint() {
+        let model = temp_model("hint");
+        assert!(model.restore("text", "tẽt").unwrap() > 0.99);
+        assert!(model.restore("vieejt", "việt").unwrap() < 0.01);
+        assert_eq!(model.restore("", ""), None);
+    }
+
+    #[test]
+    fn test_missing_model() {
+        let model = DiacriticModel::new("/nonexistent/model.safetensors");
+        assert!(model.preload().is_err());
+        assert_eq!(model.restore_diacritics("do an"), None);
+        assert_eq!(model.restore("text", "tẽt"), None);
+    }
+}
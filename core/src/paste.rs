@@ -0,0 +1,151 @@
+//! Paste As
+//!
+//! Choices for a "Paste as Vietnamese…" submenu: the host passes the
+//! clipboard text, lists each `PasteOption` with its preview and pastes the
+//! `text` of the one picked. Only options that change the text are offered,
+//! the likeliest first:
+//!
+//! - `Recompose`: typed without an IME, tones put back (`recompose`)
+//! - `Tcvn3`: TCVN3 (ABC) bytes read as Latin-1, back to Unicode
+//! - `Compose`: decomposed letters (NFD) to one code point each
+//! - `FoldConfusables`: fullwidth letters and lookalikes to ASCII
+//! - `RemoveTones`: plain ASCII letters, for places that mangle diacritics
+
+use crate::data::chars;
+use crate::recompose::recompose_pasted;
+use crate::selection::{convert_encoding, remove_tones, Encoding};
+use crate::tokenizer::fold_confusables;
+use std::borrow::Cow;
+
+/// Characters of `PasteOption::preview`
+pub const PREVIEW_LEN: usize = 40;
+
+/// How an option changes the text
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PasteKind {
+    Recompose,
+    Tcvn3,
+    Compose,
+    FoldConfusables,
+    RemoveTones,
+}
+
+impl PasteKind {
+    /// Stable id for hosts ("recompose", "tcvn3", ...)
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PasteKind::Recompose => "recompose",
+            PasteKind::Tcvn3 => "tcvn3",
+            PasteKind::Compose => "compose",
+            PasteKind::FoldConfusables => "fold-confusables",
+            PasteKind::RemoveTones => "remove-tones",
+        }
+    }
+
+    /// Short English menu label
+    pub fn label(self) -> &'static str {
+        match self {
+            PasteKind::Recompose => "Add Vietnamese tones",
+            PasteKind::Tcvn3 => "Convert from TCVN3 (ABC)",
+            PasteKind::Compose => "Join combining marks",
+            PasteKind::FoldConfusables => "Replace lookalike characters",
+            PasteKind::RemoveTones => "Remove tones",
+        }
+    }
+}
+
+/// One entry of the submenu
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PasteOption {
+    pub kind: PasteKind,
+    /// What pasting this option inserts
+    pub text: String,
+    /// Start of `text` on one line, at most `PREVIEW_LEN` characters
+    pub preview: String,
+}
+
+/// Options for pasting `text`, most likely first; empty when the text is
+/// best pasted as it is
+pub fn paste_options(text: &str) -> Vec<PasteOption> {
+    let mut options = Vec::new();
+    let mut offer = |kind, converted: Cow<str>| {
+        if converted != text && !options.iter().any(|o: &PasteOption| o.text == converted) {
+            let text = converted.into_owned();
+            options.push(PasteOption {
+                kind,
+                preview: preview(&text),
+                text,
+            });
+        }
+    };
+    if let Some(r) = recompose_pasted(text) {
+        offer(PasteKind::Recompose, r.text.into());
+    }
+    if looks_like_tcvn3(text) {
+        let converted = convert_encoding(text, Encoding::Tcvn3, Encoding::Unicode);
+        offer(PasteKind::Tcvn3, converted.into());
+    }
+    offer(PasteKind::Compose, chars::compose(text));
+    offer(PasteKind::FoldConfusables, fold_confusables(text));
+    offer(PasteKind::RemoveTones, remove_tones(text).into());
+    options
+}
+
+/// Latin-1 letters only, some of them from the range TCVN3 uses for
+/// Vietnamese ("ViÖt Nam")
+fn looks_like_tcvn3(text: &str) -> bool {
+    text.chars().all(|c| (c as u32) < 0x100)
+        && text.chars().any(|c| ('\u{A1}'..='\u{FE}').contains(&c))
+}
+
+/// `text` on one line, cut at `PREVIEW_LEN` characters with "…"
+fn preview(text: &str) -> String {
+    let line = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if line.chars().count() <= PREVIEW_LEN {
+        return line;
+    }
+    let mut head: String = line.chars().take(PREVIEW_LEN - 1).collect();
+    head.push('…');
+    head
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(text: &str) -> Vec<PasteKind> {
+        paste_options(text).into_iter().map(|o| o.kind).collect()
+    }
+
+    #[test]
+    fn test_options() {
+        let options = paste_options("xin chaof cacs banj");
+        assert_eq!(options[0].kind, PasteKind::Recompose);
+        assert_eq!(options[0].text, "xin chào các bạn");
+        assert_eq!(options.len(), 1);
+
+        let options = paste_options("ti\u{D5}ng vi\u{D6}t");
+        assert_eq!(options[0].kind, PasteKind::Tcvn3);
+        assert_eq!(options[0].text, "tiếng việt");
+
+        assert_eq!(
+            kinds("vie\u{0323}\u{0302}t"),
+            [PasteKind::Compose, PasteKind::RemoveTones]
+        );
+        assert_eq!(
+            kinds("ｖｉệｔ"),
+            [PasteKind::FoldConfusables, PasteKind::RemoveTones]
+        );
+        assert_eq!(kinds("Việt Nam"), [PasteKind::RemoveTones]);
+        assert!(kinds("hello world").is_empty());
+    }
+
+    #[test]
+    fn test_preview() {
+        assert_eq!(preview("hai\ndòng"), "hai dòng");
+        let long = "một ".repeat(20);
+        let p = preview(&long);
+        assert_eq!(p.chars().count(), PREVIEW_LEN);
+        assert!(p.ends_with('…'));
+    }
+}
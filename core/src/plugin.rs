@@ -0,0 +1,559 @@
+//! Plugins - corrections, suggestions and restore hints from a shared library
+//!
+//! Heavy providers (a language model, a large dictionary) ship on their own
+//! as a `.so`/`.dylib`/`.dll` the host loads at runtime (`Plugin::load`).
+//! The library exports one C function, the entry point:
+//!
+//! ```c
+//! typedef struct {
+//!     uint32_t abi_version;   // GONHANH_PLUGIN_ABI = 1
+//!     const char *name;       // shown in settings and logs
+//!     // Correct form of `word` (after `prev`, may be null), or null
+//!     char *(*correct)(const char *word, const char *prev);
+//!     // "Did you mean" words for `word`, one per line, or null
+//!     char *(*suggest)(const char *word);
+//!     // 1 = restore `raw` (as typed), 0 = keep `word` (as shown), -1 = no opinion
+//!     int32_t (*restore_hint)(const char *raw, const char *word);
+//!     // Frees the strings returned above
+//!     void (*free_string)(char *s);
+//! } GonhanhPluginV1;
+//!
+//! const GonhanhPluginV1 *gonhanh_plugin_v1(void);
+//! ```
+//!
+//! Strings are UTF-8 and null-terminated; any callback may be null.
+//! Newer ABIs will export a new entry point, so a library can serve several.
+//!
+//! A plugin runs on a thread of its own and the keyboard thread only waits
+//! `budget` for an answer (`DEFAULT_BUDGET`), so a slow plugin costs a
+//! missed correction, not a stuck key. Late answers, panics and malformed
+//! output (not UTF-8, control characters, over `MAX_REPLACEMENT_LEN`
+//! characters) are failures; after `MAX_FAILURES` the plugin is disabled
+//! until loaded again. A plugin that crashes outright (a segfault) still
+//! takes the process with it: isolating that needs a helper process
+//! (`ipc`).
+
+use crate::engine::correction::CorrectionProvider;
+use crate::engine::restore::RestoreHint;
+use crate::engine::shortcut::MAX_REPLACEMENT_LEN;
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Version of `PluginApi` this engine speaks
+pub const ABI_VERSION: u32 = 1;
+
+/// Name of the entry point a plugin library exports
+pub const ENTRY_POINT: &str = "gonhanh_plugin_v1";
+
+/// How long a call waits for the plugin before giving up
+pub const DEFAULT_BUDGET: Duration = Duration::from_millis(50);
+
+/// Failures (late, panicking or malformed answers) before a plugin is
+/// disabled
+pub const MAX_FAILURES: u32 = 3;
+
+/// Most suggestions read from one answer
+pub const MAX_SUGGESTIONS: usize = 16;
+
+pub type CorrectFn = unsafe extern "C" fn(word: *const c_char, prev: *const c_char) -> *mut c_char;
+pub type SuggestFn = unsafe extern "C" fn(word: *const c_char) -> *mut c_char;
+pub type RestoreHintFn = unsafe extern "C" fn(raw: *const c_char, word: *const c_char) -> i32;
+pub type FreeStringFn = unsafe extern "C" fn(s: *mut c_char);
+
+/// What the entry point returns (`GonhanhPluginV1`)
+#[repr(C)]
+pub struct PluginApi {
+    pub abi_version: u32,
+    pub name: *const c_char,
+    pub correct: Option<CorrectFn>,
+    pub suggest: Option<SuggestFn>,
+    pub restore_hint: Option<RestoreHintFn>,
+    pub free_string: Option<FreeStringFn>,
+}
+
+/// Why a plugin didn't load
+#[derive(Debug)]
+pub enum PluginError {
+    /// The library couldn't be opened
+    Open(std::io::Error),
+    /// No `ENTRY_POINT`, or it returned null
+    NoEntryPoint,
+    /// `abi_version` is not `ABI_VERSION`
+    Version(u32),
+    /// Name missing or not UTF-8
+    Name,
+    /// Strings returned without `free_string`
+    NoFree,
+}
+
+impl std::fmt::Display for PluginError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PluginError::Open(e) => write!(f, "can't open plugin: {}", e),
+            PluginError::NoEntryPoint => write!(f, "not a plugin: no {}", ENTRY_POINT),
+            PluginError::Version(v) => {
+                write!(
+                    f,
+                    "plugin ABI {} is not supported (expected {})",
+                    v, ABI_VERSION
+                )
+            }
+            PluginError::Name => write!(f, "plugin has no valid name"),
+            PluginError::NoFree => write!(f, "plugin returns strings but has no free_string"),
+        }
+    }
+}
+
+impl std::error::Error for PluginError {}
+
+/// A loaded plugin
+///
+/// Clones share the plugin (and its failure count), so one can serve as
+/// both a `CorrectionProvider` and a `RestoreHint` (`Engine::add_plugin`).
+#[derive(Clone)]
+pub struct Plugin {
+    inner: Arc<Inner>,
+    budget: Duration,
+}
+
+struct Inner {
+    name: String,
+    api: Api,
+    jobs: Sender<Job>,
+    failures: AtomicU32,
+}
+
+type Job = Box<dyn FnOnce(&Api) + Send>;
+
+/// Malformed or missing answer
+struct Failure;
+
+/// Callbacks copied out of `PluginApi`
+#[derive(Clone, Copy)]
+struct Api {
+    correct: Option<CorrectFn>,
+    suggest: Option<SuggestFn>,
+    restore_hint: Option<RestoreHintFn>,
+    free_string: Option<FreeStringFn>,
+}
+
+impl Plugin {
+    /// Open the library at `path` and start the plugin
+    ///
+    /// # Safety
+    /// Loading a library runs its initializers, and its callbacks run in
+    /// this process: only load plugins the user installed and trusts.
+    pub unsafe fn load(path: &str) -> Result<Plugin, PluginError> {
+        let lib = os::Library::open(path).map_err(PluginError::Open)?;
+        let entry = lib.symbol(ENTRY_POINT);
+        if entry.is_null() {
+            return Err(PluginError::NoEntryPoint);
+        }
+        let entry: unsafe extern "C" fn() -> *const PluginApi = std::mem::transmute(entry);
+        Plugin::start(entry(), Some(lib))
+    }
+
+    /// Start a plugin linked into the host
+    ///
+    /// # Safety
+    /// `api` must be null or point to a valid `PluginApi` whose callbacks
+    /// stay callable for as long as the plugin lives.
+    pub unsafe fn from_api(api: *const PluginApi) -> Result<Plugin, PluginError> {
+        Plugin::start(api, None)
+    }
+
+    unsafe fn start(
+        api: *const PluginApi,
+        lib: Option<os::Library>,
+    ) -> Result<Plugin, PluginError> {
+        let api = api.as_ref().ok_or(PluginError::NoEntryPoint)?;
+        if api.abi_version != ABI_VERSION {
+            return Err(PluginError::Version(api.abi_version));
+        }
+        if api.name.is_null() {
+            return Err(PluginError::Name);
+        }
+        let name = CStr::from_ptr(api.name)
+            .to_str()
+            .map_err(|_| PluginError::Name)?
+            .to_string();
+        if (api.correct.is_some() || api.suggest.is_some()) && api.free_string.is_none() {
+            return Err(PluginError::NoFree);
+        }
+        let calls = Api {
+            correct: api.correct,
+            suggest: api.suggest,
+            restore_hint: api.restore_hint,
+            free_string: api.free_string,
+        };
+        let (jobs, rx) = mpsc::channel::<Job>();
+        std::thread::Builder::new()
+            .name(format!("gonhanh-plugin {}", name))
+            .spawn(move || {
+                // Unloaded once every `Plugin` is gone
+                let _lib = lib;
+                for job in rx {
+                    // A panic drops the reply, which counts as a failure
+                    let _ = catch_unwind(AssertUnwindSafe(|| job(&calls)));
+                }
+            })
+            .map_err(PluginError::Open)?;
+        Ok(Plugin {
+            inner: Arc::new(Inner {
+                name,
+                api: calls,
+                jobs,
+                failures: AtomicU32::new(0),
+            }),
+            budget: DEFAULT_BUDGET,
+        })
+    }
+
+    /// How long each call waits for an answer (`DEFAULT_BUDGET`)
+    pub fn with_budget(mut self, budget: Duration) -> Self {
+        self.budget = budget;
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.inner.name
+    }
+
+    /// Failures so far
+    pub fn failures(&self) -> u32 {
+        self.inner.failures.load(Ordering::Relaxed)
+    }
+
+    /// Failed `MAX_FAILURES` times; every call now passes
+    pub fn is_disabled(&self) -> bool {
+        self.failures() >= MAX_FAILURES
+    }
+
+    /// "Did you mean" words for `word`, empty if the plugin has none
+    pub fn suggest(&self, word: &str) -> Vec<String> {
+        if self.inner.api.suggest.is_none() {
+            return Vec::new();
+        }
+        let Ok(word) = CString::new(word) else {
+            return Vec::new();
+        };
+        self.call(move |api| {
+            let out = unsafe { (api.suggest.unwrap())(word.as_ptr()) };
+            let text = api.take(out)?.unwrap_or_default();
+            let words: Vec<String> = text
+                .lines()
+                .filter(|w| !w.is_empty())
+                .take(MAX_SUGGESTIONS)
+                .map(str::to_string)
+                .collect();
+            match words.iter().all(|w| valid(w)) {
+                true => Ok(words),
+                false => Err(Failure),
+            }
+        })
+        .unwrap_or_default()
+    }
+
+    /// Run `f` on the plugin thread, waiting at most `budget`
+    fn call<T: Send + 'static>(
+        &self,
+        f: impl FnOnce(&Api) -> Result<T, Failure> + Send + 'static,
+    ) -> Option<T> {
+        if self.is_disabled() {
+            return None;
+        }
+        let (tx, rx) = mpsc::sync_channel(1);
+        let job: Job = Box::new(move |api| {
+            if let Ok(answer) = f(api) {
+                let _ = tx.send(answer);
+            }
+        });
+        let answer = match self.inner.jobs.send(job) {
+            Ok(()) => rx.recv_timeout(self.budget).ok(),
+            Err(_) => None,
+        };
+        if answer.is_none() {
+            self.inner.failures.fetch_add(1, Ordering::Relaxed);
+        }
+        answer
+    }
+}
+
+impl CorrectionProvider for Plugin {
+    fn correct(&self, word: &str, prev: Option<&str>) -> Option<String> {
+        self.inner.api.correct?;
+        let word = CString::new(word).ok()?;
+        let prev = prev.map(CString::new).transpose().ok()?;
+        self.call(move |api| {
+            let prev = prev.as_ref().map_or(std::ptr::null(), |p| p.as_ptr());
+            let out = unsafe { (api.correct.unwrap())(word.as_ptr(), prev) };
+            match api.take(out)? {
+                Some(fixed) if !valid(&fixed) => Err(Failure),
+                fixed => Ok(fixed),
+            }
+        })
+        .flatten()
+    }
+}
+
+impl RestoreHint for Plugin {
+    fn restore(&self, raw: &str, word: &str) -> Option<f32> {
+        self.inner.api.restore_hint?;
+        let raw = CString::new(raw).ok()?;
+        let word = CString::new(word).ok()?;
+        self.call(move |api| {
+            match unsafe { (api.restore_hint.unwrap())(raw.as_ptr(), word.as_ptr()) } {
+                -1 => Ok(None),
+                0 => Ok(Some(0.0)),
+                1 => Ok(Some(1.0)),
+                _ => Err(Failure),
+            }
+        })
+        .flatten()
+    }
+}
+
+impl Api {
+    /// Copy a returned string and hand it back to the plugin
+    fn take(&self, s: *mut c_char) -> Result<Option<String>, Failure> {
+        if s.is_null() {
+            return Ok(None);
+        }
+        // SAFETY: the plugin returned a null-terminated string it owns
+        // until `free_string`
+        unsafe {
+            let text = CStr::from_ptr(s).to_str().map(str::to_string);
+            if let Some(free) = self.free_string {
+                free(s);
+            }
+            text.map(Some).map_err(|_| Failure)
+        }
+    }
+}
+
+/// A word the engine may type
+fn valid(word: &str) -> bool {
+    !word.is_empty()
+        && word.chars().count() <= MAX_REPLACEMENT_LEN
+        && !word.chars().any(char::is_control)
+}
+
+#[cfg(unix)]
+mod os {
+    use std::ffi::{c_void, CStr, CString};
+    use std::io;
+    use std::os::raw::{c_char, c_int};
+
+    const RTLD_NOW: c_int = 2;
+
+    #[cfg_attr(target_os = "linux", link(name = "dl"))]
+    extern "C" {
+        fn dlopen(filename: *const c_char, flags: c_int) -> *mut c_void;
+        fn dlsym(handle: *mut c_void, symbol: *const c_char) -> *mut c_void;
+        fn dlclose(handle: *mut c_void) -> c_int;
+        fn dlerror() -> *const c_char;
+    }
+
+    pub struct Library(*mut c_void);
+
+    // SAFETY: a dlopen handle may be used and closed from any thread
+    unsafe impl Send for Library {}
+
+    impl Library {
+        pub unsafe fn open(path: &str) -> io::Result<Self> {
+            let path = CString::new(path).map_err(|_| io::ErrorKind::InvalidInput)?;
+            let handle = dlopen(path.as_ptr(), RTLD_NOW);
+            if handle.is_null() {
+                let err = dlerror();
+                let msg = match err.is_null() {
+                    true => "dlopen failed".into(),
+                    false => CStr::from_ptr(err).to_string_lossy().into_owned(),
+                };
+                return Err(io::Error::other(msg));
+            }
+            Ok(Library(handle))
+        }
+
+        pub unsafe fn symbol(&self, name: &str) -> *mut c_void {
+            match CString::new(name) {
+                Ok(name) => dlsym(self.0, name.as_ptr()),
+                Err(_) => std::ptr::null_mut(),
+            }
+        }
+    }
+
+    impl Drop for Library {
+        fn drop(&mut self) {
+            unsafe { dlclose(self.0) };
+        }
+    }
+}
+
+#[cfg(windows)]
+mod os {
+    use std::ffi::{c_void, CString};
+    use std::io;
+    use std::os::raw::c_char;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn LoadLibraryW(name: *const u16) -> *mut c_void;
+        fn GetProcAddress(module: *mut c_void, name: *const c_char) -> *mut c_void;
+        fn FreeLibrary(module: *mut c_void) -> i32;
+    }
+
+    pub struct Library(*mut c_void);
+
+    // SAFETY: a module handle may be used and freed from any thread
+    unsafe impl Send for Library {}
+
+    impl Library {
+        pub unsafe fn open(path: &str) -> io::Result<Self> {
+            let wide: Vec<u16> = path.encode_utf16().chain(Some(0)).collect();
+            let module = LoadLibraryW(wide.as_ptr());
+            if module.is_null() {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(Library(module))
+        }
+
+        pub unsafe fn symbol(&self, name: &str) -> *mut c_void {
+            match CString::new(name) {
+                Ok(name) => GetProcAddress(self.0, name.as_ptr()),
+                Err(_) => std::ptr::null_mut(),
+            }
+        }
+    }
+
+    impl Drop for Library {
+        fn drop(&mut self) {
+            unsafe { FreeLibrary(self.0) };
+        }
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+mod os {
+    use std::ffi::c_void;
+    use std::io;
+
+    pub struct Library;
+
+    impl Library {
+        pub unsafe fn open(_path: &str) -> io::Result<Self> {
+            Err(io::ErrorKind::Unsupported.into())
+        }
+
+        pub unsafe fn symbol(&self, _name: &str) -> *mut c_void {
+            std::ptr::null_mut()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    unsafe extern "C" fn correct(word: *const c_char, prev: *const c_char) -> *mut c_char {
+        let word = CStr::from_ptr(word).to_str().unwrap();
+        let fixed = match (word, prev.is_null()) {
+            ("nàm", _) => "làm",
+            ("bad", _) => "a\u{7}",
+            ("slow", _) => {
+                std::thread::sleep(Duration::from_millis(200));
+                "slow"
+            }
+            ("after", false) => CStr::from_ptr(prev).to_str().unwrap(),
+            _ => return std::ptr::null_mut(),
+        };
+        CString::new(fixed).unwrap().into_raw()
+    }
+
+    unsafe extern "C" fn suggest(_word: *const c_char) -> *mut c_char {
+        CString::new("việt\n\nviệc\n").unwrap().into_raw()
+    }
+
+    unsafe extern "C" fn restore_hint(raw: *const c_char, _word: *const c_char) -> i32 {
+        match CStr::from_ptr(raw).to_bytes() {
+            b"text" => 1,
+            b"vieejt" => 0,
+            b"weird" => 7,
+            _ => -1,
+        }
+    }
+
+    unsafe extern "C" fn free_string(s: *mut c_char) {
+        drop(CString::from_raw(s));
+    }
+
+    fn api(abi_version: u32) -> PluginApi {
+        PluginApi {
+            abi_version,
+            name: c"test".as_ptr(),
+            correct: Some(correct),
+            suggest: Some(suggest),
+            restore_hint: Some(restore_hint),
+            free_string: Some(free_string),
+        }
+    }
+
+    #[test]
+    fn test_validation() {
+        unsafe {
+            assert!(matches!(
+                Plugin::from_api(std::ptr::null()),
+                Err(PluginError::NoEntryPoint)
+            ));
+            assert!(matches!(
+                Plugin::from_api(&api(2)),
+                Err(PluginError::Version(2))
+            ));
+            let no_free = PluginApi {
+                free_string: None,
+                ..api(ABI_VERSION)
+            };
+            assert!(matches!(
+                Plugin::from_api(&no_free),
+                Err(PluginError::NoFree)
+            ));
+            assert!(matches!(
+                Plugin::load("/nonexistent/plugin.so"),
+                Err(PluginError::Open(_))
+            ));
+            assert_eq!(Plugin::from_api(&api(ABI_VERSION)).unwrap().name(), "test");
+        }
+    }
+
+    #[test]
+    fn test_calls() {
+        let plugin = unsafe { Plugin::from_api(&api(ABI_VERSION)) }.unwrap();
+        assert_eq!(plugin.correct("nàm", None).as_deref(), Some("làm"));
+        assert_eq!(
+            plugin.correct("after", Some("trước")).as_deref(),
+            Some("trước")
+        );
+        assert_eq!(plugin.correct("ok", None), None);
+        assert_eq!(plugin.suggest("viet"), ["việt", "việc"]);
+        assert_eq!(plugin.restore("text", "tẽt"), Some(1.0));
+        assert_eq!(plugin.restore("vieejt", "việt"), Some(0.0));
+        assert_eq!(plugin.restore("abc", "abc"), None);
+        assert_eq!(plugin.failures(), 0);
+    }
+
+    #[test]
+    fn test_failures_disable() {
+        let plugin = unsafe { Plugin::from_api(&api(ABI_VERSION)) }
+            .unwrap()
+            .with_budget(Duration::from_millis(20));
+        assert_eq!(plugin.correct("bad", None), None);
+        assert_eq!(plugin.restore("weird", "weird"), None);
+        assert_eq!(plugin.correct("slow", None), None);
+        assert!(plugin.is_disabled());
+        assert_eq!(plugin.clone().correct("nàm", None), None);
+    }
+}
@@ -0,0 +1,455 @@
+//! Typing Practice
+//!
+//! Grades each keystroke against a target text so hosts can build a
+//! practice screen without re-implementing Telex/VNI. The session runs its
+//! own engine (auto-correct off, so the screen is exactly what was typed)
+//! and keeps the resulting text.
+//!
+//! A key is on track while the text can still become the target: finished
+//! words must match exactly, the word being typed only needs the right
+//! letters so far, and any diacritic it already has must be the one the
+//! target wants (`vie` is on track for "việt", `viê` too, `vió` is not).
+//!
+//! ```ignore
+//! let mut session = PracticeSession::new("tiếng Việt");
+//! for &(key, caps) in &typed {
+//!     match session.grade_key(key, caps, false) {
+//!         Grade::Mistake(m) => highlight(m),
+//!         Grade::Done => break,
+//!         Grade::OnTrack => {}
+//!     }
+//! }
+//! let stats = session.stats();
+//! ```
+
+use crate::config::{AutoCorrectMode, Method};
+use crate::data::chars::{self, mark, tone, Parsed};
+use crate::data::keys;
+use crate::engine::{Action, Engine};
+use crate::json::Value;
+use crate::utils;
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+/// What went wrong at the first differing character
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Mistake {
+    /// Wrong, missing or extra letter (or wrong case)
+    Letter,
+    /// Missing or wrong circumflex (â ê ô)
+    Circumflex,
+    /// Missing or wrong horn (ơ ư)
+    Horn,
+    /// Missing or wrong breve (ă)
+    Breve,
+    /// Diacritic the target doesn't have
+    ExtraTone,
+    /// Missing or wrong tone mark; holds the target's (`mark::NONE` = the
+    /// target has none)
+    Mark(u8),
+    /// d / đ mixed up
+    Stroke,
+}
+
+impl Mistake {
+    /// Stable name (stats keys)
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Mistake::Letter => "letter",
+            Mistake::Circumflex => "circumflex",
+            Mistake::Horn => "horn",
+            Mistake::Breve => "breve",
+            Mistake::ExtraTone => "extra-tone",
+            Mistake::Mark(mark::SAC) => "sac",
+            Mistake::Mark(mark::HUYEN) => "huyen",
+            Mistake::Mark(mark::HOI) => "hoi",
+            Mistake::Mark(mark::NGA) => "nga",
+            Mistake::Mark(mark::NANG) => "nang",
+            Mistake::Mark(_) => "extra-mark",
+            Mistake::Stroke => "stroke",
+        }
+    }
+}
+
+/// Judgment after one keystroke
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Grade {
+    OnTrack,
+    Mistake(Mistake),
+    /// The text matches the target
+    Done,
+}
+
+impl Grade {
+    /// FFI code: 0=on track, 1=done, 2=letter, 3=circumflex, 4=horn,
+    /// 5=breve, 6=extra tone, 7=mark, 8=stroke
+    pub fn code(self) -> i32 {
+        match self {
+            Grade::OnTrack => 0,
+            Grade::Done => 1,
+            Grade::Mistake(m) => match m {
+                Mistake::Letter => 2,
+                Mistake::Circumflex => 3,
+                Mistake::Horn => 4,
+                Mistake::Breve => 5,
+                Mistake::ExtraTone => 6,
+                Mistake::Mark(_) => 7,
+                Mistake::Stroke => 8,
+            },
+        }
+    }
+}
+
+/// Session totals
+#[derive(Clone, Debug, PartialEq)]
+pub struct Stats {
+    /// Keys graded
+    pub keys: u32,
+    /// Keys after which the text was on track
+    pub on_track: u32,
+    /// Target characters typed correctly, from the start
+    pub progress: usize,
+    /// Words per minute (5 characters per word), first key to last
+    pub wpm: f64,
+    /// Mistakes made, counted when a key goes off track
+    pub mistakes: BTreeMap<Mistake, u32>,
+}
+
+impl Stats {
+    /// Share of keys that kept the text on track (1.0 before any key)
+    pub fn accuracy(&self) -> f64 {
+        if self.keys == 0 {
+            1.0
+        } else {
+            self.on_track as f64 / self.keys as f64
+        }
+    }
+
+    pub fn to_json(&self) -> Value {
+        Value::Obj(vec![
+            ("keys".into(), Value::Num(self.keys as f64)),
+            ("progress".into(), Value::Num(self.progress as f64)),
+            ("wpm".into(), Value::Num(self.wpm)),
+            ("accuracy".into(), Value::Num(self.accuracy())),
+            (
+                "mistakes".into(),
+                Value::Obj(
+                    self.mistakes
+                        .iter()
+                        .map(|(m, &n)| (m.as_str().to_string(), Value::Num(n as f64)))
+                        .collect(),
+                ),
+            ),
+        ])
+    }
+}
+
+/// One practice run over a target text
+pub struct PracticeSession {
+    engine: Engine,
+    target: Vec<char>,
+    screen: Vec<char>,
+    started: Instant,
+    first_key: Option<Duration>,
+    last_key: Duration,
+    last: Grade,
+    keys: u32,
+    on_track: u32,
+    mistakes: BTreeMap<Mistake, u32>,
+}
+
+impl PracticeSession {
+    /// Practice `target` with Telex
+    pub fn new(target: &str) -> Self {
+        let mut engine = Engine::new();
+        engine.set_auto_correct(AutoCorrectMode::Off);
+        Self {
+            engine,
+            target: target.chars().collect(),
+            screen: Vec::new(),
+            started: Instant::now(),
+            first_key: None,
+            last_key: Duration::ZERO,
+            last: Grade::OnTrack,
+            keys: 0,
+            on_track: 0,
+            mistakes: BTreeMap::new(),
+        }
+    }
+
+    /// Switch input method (keeps what's typed, ends the current word)
+    pub fn set_method(&mut self, method: Method) {
+        self.engine
+            .set_method(method.id())
+            .expect("practice settings fit either method");
+    }
+
+    /// Grade a keystroke, timed now
+    pub fn grade_key(&mut self, key: u16, caps: bool, shift: bool) -> Grade {
+        let at = self.started.elapsed();
+        self.grade_key_at(key, caps, shift, at)
+    }
+
+    /// Grade a keystroke made `at` after the session started
+    ///
+    /// Keys that type nothing (arrows, modifiers) leave the text alone and
+    /// aren't counted.
+    pub fn grade_key_at(&mut self, key: u16, caps: bool, shift: bool, at: Duration) -> Grade {
+        let typed = typed_char(key, caps, shift);
+        if typed.is_none() && key != keys::DELETE {
+            return self.last;
+        }
+
+        let r = self.engine.on_key_ext(key, caps, false, shift);
+        let consumed =
+            r.action != Action::None as u8 && (key == keys::SPACE || !keys::is_break(key));
+        if r.action != Action::None as u8 {
+            let keep = self.screen.len().saturating_sub(r.backspace as usize);
+            self.screen.truncate(keep);
+            self.screen.extend(
+                r.chars[..r.count as usize]
+                    .iter()
+                    .filter_map(|&c| char::from_u32(c)),
+            );
+        }
+        if !consumed {
+            match typed {
+                Some(c) => self.screen.push(c),
+                None => {
+                    self.screen.pop();
+                }
+            }
+        }
+
+        let grade = self.judge();
+        self.keys += 1;
+        self.first_key.get_or_insert(at);
+        self.last_key = at;
+        match grade {
+            Grade::Mistake(m) => {
+                if !matches!(self.last, Grade::Mistake(_)) {
+                    *self.mistakes.entry(m).or_insert(0) += 1;
+                }
+            }
+            _ => self.on_track += 1,
+        }
+        self.last = grade;
+        grade
+    }
+
+    /// Text typed so far
+    pub fn screen(&self) -> String {
+        self.screen.iter().collect()
+    }
+
+    pub fn stats(&self) -> Stats {
+        let progress = self
+            .screen
+            .iter()
+            .zip(&self.target)
+            .take_while(|(s, t)| s == t)
+            .count();
+        let minutes = self
+            .first_key
+            .map_or(0.0, |first| (self.last_key - first).as_secs_f64() / 60.0);
+        Stats {
+            keys: self.keys,
+            on_track: self.on_track,
+            progress,
+            wpm: if minutes > 0.0 {
+                progress as f64 / 5.0 / minutes
+            } else {
+                0.0
+            },
+            mistakes: self.mistakes.clone(),
+        }
+    }
+
+    /// Compare the screen with the target
+    fn judge(&self) -> Grade {
+        if self.screen == self.target {
+            return Grade::Done;
+        }
+        // Word still being typed: trailing run of letters
+        let word_start = self
+            .screen
+            .iter()
+            .rposition(|c| !c.is_alphabetic())
+            .map_or(0, |i| i + 1);
+
+        for (i, &s) in self.screen[..word_start].iter().enumerate() {
+            if self.target.get(i) != Some(&s) {
+                return Grade::Mistake(classify(s, self.target.get(i).copied()));
+            }
+        }
+
+        let target_end = self.target[word_start.min(self.target.len())..]
+            .iter()
+            .position(|c| !c.is_alphabetic())
+            .map_or(self.target.len(), |n| word_start + n);
+        let mut target_mark = mark::NONE;
+        for &t in &self.target[word_start.min(target_end)..target_end] {
+            if let Some(p) = chars::parse_char(t) {
+                target_mark = target_mark.max(p.mark);
+            }
+        }
+
+        // Marks move while the word grows: compare the word's mark only
+        for (i, &s) in self.screen.iter().enumerate().skip(word_start) {
+            let t = self.target.get(i).copied();
+            let (Some(sp), Some(tp)) = (chars::parse_char(s), t.and_then(chars::parse_char)) else {
+                return Grade::Mistake(Mistake::Letter);
+            };
+            if i >= target_end || sp.key != tp.key || sp.caps != tp.caps {
+                return Grade::Mistake(Mistake::Letter);
+            }
+            if sp.stroke && !tp.stroke {
+                return Grade::Mistake(Mistake::Stroke);
+            }
+            if sp.tone != tone::NONE && sp.tone != tp.tone {
+                return Grade::Mistake(tone_mistake(&tp));
+            }
+            if sp.mark != mark::NONE && sp.mark != target_mark {
+                return Grade::Mistake(Mistake::Mark(target_mark));
+            }
+        }
+        Grade::OnTrack
+    }
+}
+
+/// Mistake for a finished character `s` where the target has `t`
+fn classify(s: char, t: Option<char>) -> Mistake {
+    let (Some(sp), Some(tp)) = (chars::parse_char(s), t.and_then(chars::parse_char)) else {
+        return Mistake::Letter;
+    };
+    if sp.key != tp.key || sp.caps != tp.caps {
+        Mistake::Letter
+    } else if sp.stroke != tp.stroke {
+        Mistake::Stroke
+    } else if sp.tone != tp.tone {
+        tone_mistake(&tp)
+    } else {
+        Mistake::Mark(tp.mark)
+    }
+}
+
+/// Diacritic the target character needs
+fn tone_mistake(target: &Parsed) -> Mistake {
+    match target.tone {
+        tone::CIRCUMFLEX => Mistake::Circumflex,
+        tone::HORN if target.key == keys::A => Mistake::Breve,
+        tone::HORN => Mistake::Horn,
+        _ => Mistake::ExtraTone,
+    }
+}
+
+/// Character a key types on a US layout
+fn typed_char(key: u16, caps: bool, shift: bool) -> Option<char> {
+    if keys::is_letter(key) {
+        return utils::key_to_char(key, caps);
+    }
+    let (plain, shifted) = match key {
+        keys::SPACE => (' ', ' '),
+        keys::N1 => ('1', '!'),
+        keys::N2 => ('2', '@'),
+        keys::N3 => ('3', '#'),
+        keys::N4 => ('4', '$'),
+        keys::N5 => ('5', '%'),
+        keys::N6 => ('6', '^'),
+        keys::N7 => ('7', '&'),
+        keys::N8 => ('8', '*'),
+        keys::N9 => ('9', '('),
+        keys::N0 => ('0', ')'),
+        keys::DOT => ('.', '>'),
+        keys::COMMA => (',', '<'),
+        keys::SEMICOLON => (';', ':'),
+        keys::QUOTE => ('\'', '"'),
+        keys::MINUS => ('-', '_'),
+        keys::EQUAL => ('=', '+'),
+        keys::SLASH => ('/', '?'),
+        keys::LBRACKET => ('[', '{'),
+        keys::RBRACKET => (']', '}'),
+        keys::BACKSLASH => ('\\', '|'),
+        keys::BACKQUOTE => ('`', '~'),
+        keys::RETURN => ('\n', '\n'),
+        _ => return None,
+    };
+    Some(if shift { shifted } else { plain })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn type_str(session: &mut PracticeSession, input: &str) -> Vec<Grade> {
+        input
+            .chars()
+            .map(|c| match c {
+                '<' => session.grade_key(keys::DELETE, false, false),
+                ' ' => session.grade_key(keys::SPACE, false, false),
+                ',' => session.grade_key(keys::COMMA, false, false),
+                _ => session.grade_key(keys::from_char(c).unwrap(), c.is_uppercase(), false),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_on_track_until_done() {
+        let mut s = PracticeSession::new("tiếng Việt");
+        let grades = type_str(&mut s, "tieengs Vieetj");
+        assert!(grades[..grades.len() - 1]
+            .iter()
+            .all(|&g| g == Grade::OnTrack));
+        assert_eq!(grades.last(), Some(&Grade::Done));
+        assert_eq!(s.screen(), "tiếng Việt");
+    }
+
+    #[test]
+    fn test_mark_typed_early() {
+        let mut s = PracticeSession::new("thường");
+        let grades = type_str(&mut s, "thuwfowng");
+        assert!(grades.iter().all(|&g| g != Grade::Mistake(Mistake::Letter)));
+        assert_eq!(grades.last(), Some(&Grade::Done));
+    }
+
+    #[test]
+    fn test_mistakes() {
+        let mut s = PracticeSession::new("việt nam");
+        assert_eq!(
+            type_str(&mut s, "vies"),
+            [
+                Grade::OnTrack,
+                Grade::OnTrack,
+                Grade::OnTrack,
+                Grade::Mistake(Mistake::Mark(mark::NANG))
+            ]
+        );
+        // Fix it, then finish the word without the circumflex
+        type_str(&mut s, "<ejt");
+        assert_eq!(type_str(&mut s, " "), [Grade::Mistake(Mistake::Circumflex)]);
+        assert_eq!(type_str(&mut s, "<<<<<"), [Grade::OnTrack; 5]);
+        assert_eq!(type_str(&mut s, "x"), [Grade::Mistake(Mistake::Letter)]);
+
+        let stats = s.stats();
+        assert_eq!(stats.keys, 15);
+        assert_eq!(stats.mistakes.get(&Mistake::Mark(mark::NANG)), Some(&1));
+        assert_eq!(stats.mistakes.get(&Mistake::Circumflex), Some(&1));
+        assert_eq!(stats.mistakes.get(&Mistake::Letter), Some(&1));
+        assert_eq!(stats.progress, 0);
+    }
+
+    #[test]
+    fn test_wpm() {
+        let mut s = PracticeSession::new("xin chào");
+        let keys = "xin chaof";
+        for (i, c) in keys.chars().enumerate() {
+            let key = keys::from_char(c).unwrap();
+            s.grade_key_at(key, false, false, Duration::from_secs(i as u64));
+        }
+        let stats = s.stats();
+        // 8 characters in 8 seconds
+        assert_eq!(stats.progress, 8);
+        assert!((stats.wpm - 12.0).abs() < 1e-9);
+        assert_eq!(stats.accuracy(), 1.0);
+    }
+}
@@ -0,0 +1,511 @@
+//! Portable user profile
+//!
+//! One JSON document with everything a user would want to carry to another
+//! machine (macOS ↔ Windows): settings and shortcuts.
+//!
+//! ```json
+//! {
+//!   "format": "gonhanh-profile",
+//!   "version": 1,
+//!   "settings": { "method": "telex", "enabled": true, ... },
+//!   "shortcuts": [
+//!     { "trigger": "vn", "replacement": "Việt Nam", "method": "all",
+//!       "immediate": false, "match_case": false, "enabled": true }
+//!   ]
+//! }
+//! ```
+//!
+//! Readers ignore sections and keys they don't know, so older versions can
+//! read profiles that carry data added later (learned words, corrections).
+//! A profile with a higher `version` is rejected.
+
+use crate::config::{
+    AcceptKey, AutoCorrectMode, ConfigError, CorrectionPacks, CorrectionStyle, EngineConfig,
+    Gestures, IyStyle, Method, MouseAction, Nbsp, NbspChord, OverflowPolicy, RemoveKey, RepeatKey,
+    RestoreChecks, RestoreOrder, ToneStyle, MAX_WORD_LEN,
+};
+use crate::engine::shortcut::{CaseMode, InputMethod, Shortcut, TriggerCondition};
+use crate::json::{self, Value};
+
+/// `format` marker
+pub const PROFILE_FORMAT: &str = "gonhanh-profile";
+/// Current profile version
+pub const PROFILE_VERSION: u64 = 1;
+
+/// Error while reading a profile (nothing is applied)
+#[derive(Debug, PartialEq, Eq)]
+pub enum ProfileError {
+    /// Not valid JSON
+    Json(json::ParseError),
+    /// Valid JSON, but not a profile
+    NotAProfile,
+    /// Written by a newer version
+    UnsupportedVersion(u64),
+    /// Field missing or of the wrong type/value
+    Invalid(String),
+    /// Settings don't form a valid config
+    Config(ConfigError),
+}
+
+impl ProfileError {
+    /// Stable numeric code for FFI (ConfigError codes are 1-5)
+    pub fn code(&self) -> i32 {
+        match self {
+            ProfileError::Config(e) => e.code(),
+            ProfileError::Json(_) => 20,
+            ProfileError::NotAProfile => 21,
+            ProfileError::UnsupportedVersion(_) => 22,
+            ProfileError::Invalid(_) => 23,
+        }
+    }
+}
+
+impl std::fmt::Display for ProfileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProfileError::Json(e) => write!(f, "{}", e),
+            ProfileError::NotAProfile => write!(f, "not a {} document", PROFILE_FORMAT),
+            ProfileError::UnsupportedVersion(v) => write!(
+                f,
+                "profile version {} is newer than supported ({})",
+                v, PROFILE_VERSION
+            ),
+            ProfileError::Invalid(field) => write!(f, "invalid `{}`", field),
+            ProfileError::Config(e) => write!(f, "settings: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ProfileError {}
+
+/// Everything exported for a user
+#[derive(Clone, Debug)]
+pub struct Profile {
+    pub config: EngineConfig,
+    pub shortcuts: Vec<Shortcut>,
+}
+
+impl Profile {
+    pub fn to_json(&self) -> String {
+        self.to_value().to_pretty()
+    }
+
+    pub fn from_json(s: &str) -> Result<Profile, ProfileError> {
+        Self::from_value(&json::parse(s).map_err(ProfileError::Json)?)
+    }
+
+    pub fn to_value(&self) -> Value {
+        let c = &self.config;
+        let settings = Value::Obj(vec![
+            ("method".into(), Value::Str(c.method.as_str().into())),
+            ("enabled".into(), Value::Bool(c.enabled)),
+            (
+                "tone_style".into(),
+                Value::Str(c.tone_style.as_str().into()),
+            ),
+            (
+                "auto_correct".into(),
+                Value::Str(c.auto_correct.as_str().into()),
+            ),
+            ("bracket_shortcuts".into(), Value::Bool(c.bracket_shortcuts)),
+            ("money_shorthand".into(), Value::Bool(c.money_shorthand)),
+            ("tab_shortcuts".into(), Value::Bool(c.tab_shortcuts)),
+            ("max_word_len".into(), Value::Num(c.max_word_len as f64)),
+            ("overflow".into(), Value::Str(c.overflow.as_str().into())),
+            ("remove_key".into(), Value::Str(c.remove_key.to_string())),
+            ("uo_compound".into(), Value::Bool(c.uo_compound)),
+            (
+                "correction_packs".into(),
+                Value::Str(c.correction_packs.to_string()),
+            ),
+            ("iy_style".into(), Value::Str(c.iy_style.as_str().into())),
+            (
+                "correction_style".into(),
+                Value::Str(c.correction_style.as_str().into()),
+            ),
+            (
+                "accept_key".into(),
+                Value::Str(c.accept_key.as_str().into()),
+            ),
+            ("nbsp".into(), Value::Str(c.nbsp.as_str().into())),
+            (
+                "nbsp_chord".into(),
+                Value::Str(c.nbsp_chord.as_str().into()),
+            ),
+            (
+                "idle_commit_secs".into(),
+                Value::Num(c.idle_commit_secs as f64),
+            ),
+            (
+                "mouse_click".into(),
+                Value::Str(c.mouse_click.as_str().into()),
+            ),
+            (
+                "mouse_scroll".into(),
+                Value::Str(c.mouse_scroll.as_str().into()),
+            ),
+            (
+                "repeat_key".into(),
+                Value::Str(c.repeat_key.as_str().into()),
+            ),
+            ("gestures".into(), Value::Str(c.gestures.to_string())),
+            ("feedback".into(), Value::Bool(c.feedback)),
+            (
+                "feedback_gap_ms".into(),
+                Value::Num(c.feedback_gap_ms as f64),
+            ),
+            ("edit_limit".into(), Value::Num(c.edit_limit as f64)),
+            (
+                "restore_order".into(),
+                Value::Str(c.restore_order.to_string()),
+            ),
+            (
+                "restore_disabled".into(),
+                Value::Str(c.restore_disabled.to_string()),
+            ),
+            (
+                "bigram_threshold".into(),
+                Value::Num(c.bigram_threshold as f64),
+            ),
+            ("min_confidence".into(), Value::Num(c.min_confidence as f64)),
+        ]);
+        let shortcuts = self.shortcuts.iter().map(shortcut_to_json).collect();
+        Value::Obj(vec![
+            ("format".into(), Value::Str(PROFILE_FORMAT.into())),
+            ("version".into(), Value::Num(PROFILE_VERSION as f64)),
+            ("settings".into(), settings),
+            ("shortcuts".into(), Value::Arr(shortcuts)),
+        ])
+    }
+
+    pub fn from_value(doc: &Value) -> Result<Profile, ProfileError> {
+        if doc.get("format").and_then(Value::as_str) != Some(PROFILE_FORMAT) {
+            return Err(ProfileError::NotAProfile);
+        }
+        let version = doc
+            .get("version")
+            .and_then(Value::as_u64)
+            .ok_or_else(|| invalid("version"))?;
+        if version > PROFILE_VERSION {
+            return Err(ProfileError::UnsupportedVersion(version));
+        }
+
+        let config = match doc.get("settings") {
+            Some(settings) => settings_from_json(settings)?,
+            None => EngineConfig::default(),
+        };
+        config.validate().map_err(ProfileError::Config)?;
+
+        let shortcuts = match doc.get("shortcuts") {
+            Some(list) => list
+                .as_array()
+                .ok_or_else(|| invalid("shortcuts"))?
+                .iter()
+                .map(shortcut_from_json)
+                .collect::<Result<_, _>>()?,
+            None => Vec::new(),
+        };
+        Ok(Profile { config, shortcuts })
+    }
+}
+
+fn invalid(field: &str) -> ProfileError {
+    ProfileError::Invalid(field.into())
+}
+
+fn method_name(m: InputMethod) -> &'static str {
+    match m {
+        InputMethod::All => "all",
+        InputMethod::Telex => "telex",
+        InputMethod::Vni => "vni",
+    }
+}
+
+fn shortcut_to_json(sc: &Shortcut) -> Value {
+    Value::Obj(vec![
+        ("trigger".into(), Value::Str(sc.trigger.clone())),
+        ("replacement".into(), Value::Str(sc.replacement.clone())),
+        (
+            "method".into(),
+            Value::Str(method_name(sc.input_method).into()),
+        ),
+        (
+            "immediate".into(),
+            Value::Bool(sc.condition == TriggerCondition::Immediate),
+        ),
+        (
+            "match_case".into(),
+            Value::Bool(sc.case_mode == CaseMode::MatchCase),
+        ),
+        ("enabled".into(), Value::Bool(sc.enabled)),
+    ])
+}
+
+fn shortcut_from_json(v: &Value) -> Result<Shortcut, ProfileError> {
+    let text = |key: &str| {
+        v.get(key)
+            .and_then(Value::as_str)
+            .ok_or_else(|| invalid(&format!("shortcuts.{}", key)))
+    };
+    let flag = |key: &str, default: bool| match v.get(key) {
+        None => Ok(default),
+        Some(b) => b
+            .as_bool()
+            .ok_or_else(|| invalid(&format!("shortcuts.{}", key))),
+    };
+
+    let trigger = text("trigger")?;
+    if trigger.is_empty() {
+        return Err(invalid("shortcuts.trigger"));
+    }
+    let mut sc = Shortcut::new(trigger, text("replacement")?);
+    sc.input_method = match v.get("method").map(|m| m.as_str()) {
+        None | Some(Some("all")) => InputMethod::All,
+        Some(Some("telex")) => InputMethod::Telex,
+        Some(Some("vni")) => InputMethod::Vni,
+        _ => return Err(invalid("shortcuts.method")),
+    };
+    if flag("immediate", false)? {
+        sc.condition = TriggerCondition::Immediate;
+    }
+    if flag("match_case", false)? {
+        sc.case_mode = CaseMode::MatchCase;
+    }
+    sc.enabled = flag("enabled", true)?;
+    Ok(sc)
+}
+
+fn settings_from_json(v: &Value) -> Result<EngineConfig, ProfileError> {
+    fn field<T>(
+        v: &Value,
+        key: &str,
+        parse: impl Fn(&Value) -> Option<T>,
+        default: T,
+    ) -> Result<T, ProfileError> {
+        match v.get(key) {
+            None => Ok(default),
+            Some(x) => parse(x).ok_or_else(|| invalid(&format!("settings.{}", key))),
+        }
+    }
+
+    if !matches!(v, Value::Obj(_)) {
+        return Err(invalid("settings"));
+    }
+    let d = EngineConfig::default();
+    Ok(EngineConfig {
+        method: field(
+            v,
+            "method",
+            |x| x.as_str().and_then(Method::parse),
+            d.method,
+        )?,
+        enabled: field(v, "enabled", Value::as_bool, d.enabled)?,
+        tone_style: field(
+            v,
+            "tone_style",
+            |x| x.as_str().and_then(ToneStyle::parse),
+            d.tone_style,
+        )?,
+        auto_correct: field(
+            v,
+            "auto_correct",
+            |x| x.as_str().and_then(AutoCorrectMode::parse),
+            d.auto_correct,
+        )?,
+        bracket_shortcuts: field(v, "bracket_shortcuts", Value::as_bool, d.bracket_shortcuts)?,
+        money_shorthand: field(v, "money_shorthand", Value::as_bool, d.money_shorthand)?,
+        tab_shortcuts: field(v, "tab_shortcuts", Value::as_bool, d.tab_shortcuts)?,
+        max_word_len: field(
+            v,
+            "max_word_len",
+            |x| {
+                x.as_u64()
+                    .filter(|n| (1..=MAX_WORD_LEN as u64).contains(n))
+                    .map(|n| n as u8)
+            },
+            d.max_word_len,
+        )?,
+        overflow: field(
+            v,
+            "overflow",
+            |x| x.as_str().and_then(OverflowPolicy::parse),
+            d.overflow,
+        )?,
+        remove_key: field(
+            v,
+            "remove_key",
+            |x| x.as_str().and_then(RemoveKey::parse),
+            d.remove_key,
+        )?,
+        uo_compound: field(v, "uo_compound", Value::as_bool, d.uo_compound)?,
+        correction_packs: field(
+            v,
+            "correction_packs",
+            |x| x.as_str().and_then(CorrectionPacks::parse),
+            d.correction_packs,
+        )?,
+        iy_style: field(
+            v,
+            "iy_style",
+            |x| x.as_str().and_then(IyStyle::parse),
+            d.iy_style,
+        )?,
+        correction_style: field(
+            v,
+            "correction_style",
+            |x| x.as_str().and_then(CorrectionStyle::parse),
+            d.correction_style,
+        )?,
+        accept_key: field(
+            v,
+            "accept_key",
+            |x| x.as_str().and_then(AcceptKey::parse),
+            d.accept_key,
+        )?,
+        nbsp: field(v, "nbsp", |x| x.as_str().and_then(Nbsp::parse), d.nbsp)?,
+        nbsp_chord: field(
+            v,
+            "nbsp_chord",
+            |x| x.as_str().and_then(NbspChord::parse),
+            d.nbsp_chord,
+        )?,
+        idle_commit_secs: field(
+            v,
+            "idle_commit_secs",
+            |x| x.as_u64().and_then(|n| u8::try_from(n).ok()),
+            d.idle_commit_secs,
+        )?,
+        mouse_click: field(
+            v,
+            "mouse_click",
+            |x| x.as_str().and_then(MouseAction::parse),
+            d.mouse_click,
+        )?,
+        mouse_scroll: field(
+            v,
+            "mouse_scroll",
+            |x| x.as_str().and_then(MouseAction::parse),
+            d.mouse_scroll,
+        )?,
+        repeat_key: field(
+            v,
+            "repeat_key",
+            |x| x.as_str().and_then(RepeatKey::parse),
+            d.repeat_key,
+        )?,
+        gestures: field(
+            v,
+            "gestures",
+            |x| x.as_str().and_then(Gestures::parse),
+            d.gestures,
+        )?,
+        feedback: field(v, "feedback", Value::as_bool, d.feedback)?,
+        feedback_gap_ms: field(
+            v,
+            "feedback_gap_ms",
+            |x| x.as_u64().and_then(|n| u8::try_from(n).ok()),
+            d.feedback_gap_ms,
+        )?,
+        edit_limit: field(
+            v,
+            "edit_limit",
+            |x| x.as_u64().and_then(|n| u8::try_from(n).ok()),
+            d.edit_limit,
+        )?,
+        restore_order: field(
+            v,
+            "restore_order",
+            |x| x.as_str().and_then(RestoreOrder::parse),
+            d.restore_order,
+        )?,
+        restore_disabled: field(
+            v,
+            "restore_disabled",
+            |x| x.as_str().and_then(RestoreChecks::parse),
+            d.restore_disabled,
+        )?,
+        bigram_threshold: field(
+            v,
+            "bigram_threshold",
+            |x| x.as_u64().filter(|n| *n <= i8::MAX as u64).map(|n| n as u8),
+            d.bigram_threshold,
+        )?,
+        min_confidence: field(
+            v,
+            "min_confidence",
+            |x| x.as_u64().filter(|n| *n <= 100).map(|n| n as u8),
+            d.min_confidence,
+        )?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Profile {
+        let mut sig = Shortcut::immediate("sig", "Chào \"bạn\"\nthân ái");
+        sig.enabled = false;
+        Profile {
+            config: EngineConfig {
+                method: Method::Vni,
+                tone_style: ToneStyle::Classic,
+                ..EngineConfig::default()
+            },
+            shortcuts: vec![
+                Shortcut::new("vn", "Việt Nam"),
+                Shortcut::telex("ko", "không"),
+                sig,
+            ],
+        }
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let json = sample().to_json();
+        let back = Profile::from_json(&json).unwrap();
+        assert_eq!(back.config, sample().config);
+        assert_eq!(back.to_json(), json);
+    }
+
+    #[test]
+    fn test_forward_compatible() {
+        // Unknown sections/keys from newer minor additions are ignored
+        let json = r#"{
+            "format": "gonhanh-profile", "version": 1,
+            "settings": {"method": "vni", "future_option": 3},
+            "learned_words": ["abc"],
+            "shortcuts": [{"trigger": "vn", "replacement": "Việt Nam", "color": "red"}]
+        }"#;
+        let p = Profile::from_json(json).unwrap();
+        assert_eq!(p.config.method, Method::Vni);
+        assert_eq!(p.shortcuts.len(), 1);
+        assert!(p.shortcuts[0].enabled);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(matches!(
+            Profile::from_json("{"),
+            Err(ProfileError::Json(_))
+        ));
+        assert_eq!(
+            Profile::from_json(r#"{"version": 1}"#).err(),
+            Some(ProfileError::NotAProfile)
+        );
+        assert_eq!(
+            Profile::from_json(r#"{"format": "gonhanh-profile", "version": 9}"#).err(),
+            Some(ProfileError::UnsupportedVersion(9))
+        );
+        assert_eq!(
+            Profile::from_json(
+                r#"{"format": "gonhanh-profile", "version": 1, "settings": {"method": 7}}"#
+            )
+            .err(),
+            Some(ProfileError::Invalid("settings.method".into()))
+        );
+        let conflict = r#"{"format": "gonhanh-profile", "version": 1,
+            "settings": {"method": "vni", "bracket_shortcuts": true}}"#;
+        assert_eq!(Profile::from_json(conflict).unwrap_err().code(), 5);
+    }
+}
@@ -0,0 +1,207 @@
+//! Recompose Pasted Text
+//!
+//! Text pasted from someone who typed without an IME is either toneless
+//! ("xin chao cac ban") or raw keystrokes ("xin chaof cacs banj").
+//! `recompose_pasted` tells which and offers the converted text, for a
+//! "Convert pasted text" menu item.
+//!
+//! Raw keystrokes go through the engine word by word, so each word comes
+//! out exactly as typing it would have given (English words included:
+//! auto-restore keeps them). Toneless text goes through the unigram model
+//! in `data::toneless`; words it doesn't know are left as they are. Links,
+//! numbers and identifiers (`tokenizer`) are never touched. Fullwidth
+//! letters and lookalikes are folded to ASCII first
+//! (`tokenizer::fold_confusables`).
+
+use crate::config::Method;
+use crate::data::{keys, toneless};
+use crate::engine::correction::match_case;
+use crate::engine::{Action, Engine};
+use crate::tokenizer::{fold_confusables, tokens, TokenKind};
+
+/// What the pasted text looked like
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PastedKind {
+    /// Keystrokes of this method
+    Raw(Method),
+    /// Vietnamese without diacritics
+    Toneless,
+}
+
+/// Converted pasted text
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Recomposed {
+    pub kind: PastedKind,
+    pub text: String,
+    /// Words that changed
+    pub changed: usize,
+}
+
+/// Detect raw or toneless Vietnamese in `text` and convert it
+///
+/// `None` when there is nothing to offer: the text already has diacritics,
+/// or too few of its words look like either kind.
+pub fn recompose_pasted(text: &str) -> Option<Recomposed> {
+    let text = &*fold_confusables(text);
+    // Composed already (or another script): leave it alone
+    if !text.is_ascii() {
+        return None;
+    }
+    let words = words(text).count();
+    if words == 0 {
+        return None;
+    }
+
+    let telex = convert_raw(text, Method::Telex);
+    let vni = convert_raw(text, Method::Vni);
+    let raw = if vni.changed > telex.changed {
+        vni
+    } else {
+        telex
+    };
+    // Most Vietnamese words carry a tone; English words ending in s, r, f,
+    // x or j ("this is") convert too, but not as many
+    if raw.changed * 2 > words {
+        return Some(raw);
+    }
+
+    let toneless = convert_toneless(text);
+    (toneless.changed > 0 && words >= 2 && known_words(text) * 2 >= words).then_some(toneless)
+}
+
+/// Words of `text` (`TokenKind::Word`)
+fn words(text: &str) -> impl Iterator<Item = &str> {
+    tokens(text)
+        .filter(|t| t.kind == TokenKind::Word)
+        .map(|t| &text[t.span])
+}
+
+/// Map each word of `text` through `f`, keeping everything between
+fn map_words(text: &str, mut f: impl FnMut(&str) -> String) -> (String, usize) {
+    let mut out = String::with_capacity(text.len() * 2);
+    let mut changed = 0;
+    for t in tokens(text) {
+        let token = &text[t.span];
+        if t.kind != TokenKind::Word {
+            out.push_str(token);
+            continue;
+        }
+        let new = f(token);
+        changed += (new != token) as usize;
+        out.push_str(&new);
+    }
+    (out, changed)
+}
+
+fn convert_raw(text: &str, method: Method) -> Recomposed {
+    let mut engine = Engine::new();
+    engine
+        .set_method(method.id())
+        .expect("default settings fit either method");
+    engine.set_auto_restore(true);
+    let (text, changed) = map_words(text, |word| type_word(&mut engine, word));
+    Recomposed {
+        kind: PastedKind::Raw(method),
+        text,
+        changed,
+    }
+}
+
+/// Screen text after typing `word` and a space
+fn type_word(engine: &mut Engine, word: &str) -> String {
+    let mut screen: Vec<char> = Vec::with_capacity(word.len() + 1);
+    for c in word.chars().chain([' ']) {
+        let Some(key) = keys::from_char(c) else {
+            screen.push(c);
+            continue;
+        };
+        let r = engine.on_key(key, c.is_ascii_uppercase(), false);
+        if r.action == Action::None as u8 {
+            screen.push(c);
+        } else {
+            screen.truncate(screen.len().saturating_sub(r.backspace as usize));
+            screen.extend(
+                r.chars[..r.count as usize]
+                    .iter()
+                    .filter_map(|&c| char::from_u32(c)),
+            );
+            // Space comes with the output when the word is replaced
+            if key == keys::SPACE && screen.last() != Some(&' ') {
+                screen.push(' ');
+            }
+        }
+    }
+    engine.clear();
+    screen.pop();
+    screen.into_iter().collect()
+}
+
+fn convert_toneless(text: &str) -> Recomposed {
+    let (text, changed) = map_words(text, |word| {
+        toneless::likely_spelling(&word.to_ascii_lowercase())
+            .map_or_else(|| word.to_string(), |s| match_case(word, s))
+    });
+    Recomposed {
+        kind: PastedKind::Toneless,
+        text,
+        changed,
+    }
+}
+
+fn known_words(text: &str) -> usize {
+    words(text)
+        .filter(|w| toneless::likely_spelling(&w.to_ascii_lowercase()).is_some())
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_telex() {
+        let r = recompose_pasted("xin chaof cacs banj!").unwrap();
+        assert_eq!(r.kind, PastedKind::Raw(Method::Telex));
+        assert_eq!(r.text, "xin chào các bạn!");
+        assert_eq!(r.changed, 3);
+        // Links and identifiers are kept as typed
+        let r = recompose_pasted("chaof cacs https://banj.vn/cacs user_idd").unwrap();
+        assert_eq!(r.text, "chào các https://banj.vn/cacs user_idd");
+    }
+
+    #[test]
+    fn test_vni() {
+        let r = recompose_pasted("Tie61ng Vie65t hay la81m").unwrap();
+        assert_eq!(r.kind, PastedKind::Raw(Method::Vni));
+        assert_eq!(r.text, "Tiếng Việt hay lắm");
+    }
+
+    #[test]
+    fn test_toneless() {
+        let r = recompose_pasted("Xin chao cac ban, toi la Lan.").unwrap();
+        assert_eq!(r.kind, PastedKind::Toneless);
+        assert_eq!(r.text, "Xin chào các bạn, tôi là Lan.");
+    }
+
+    #[test]
+    fn test_confusables() {
+        let r = recompose_pasted("ｘｉｎ ｃｈａｏｆ ｃａｃｓ ｂａｎｊ").unwrap();
+        assert_eq!(r.text, "xin chào các bạn");
+        // Cyrillic а and с
+        let r = recompose_pasted("xin ch\u{0430}of \u{0441}acs banj").unwrap();
+        assert_eq!(r.text, "xin chào các bạn");
+    }
+
+    #[test]
+    fn test_nothing_to_offer() {
+        for text in [
+            "xin chào các bạn",
+            "hello world, this is a test",
+            "2024",
+            "",
+            "xin",
+        ] {
+            assert_eq!(recompose_pasted(text), None, "{}", text);
+        }
+    }
+}
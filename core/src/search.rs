@@ -0,0 +1,78 @@
+//! Diacritic-insensitive Search
+//!
+//! `search_key` gives two normalizations of a string, so a note or chat app
+//! can match "duong" against "Đường" the same way the IME does:
+//!
+//! - `key`: lowercase, marks and modifiers stripped, đ as d ("Đường" →
+//!   "duong"). Match a query's key against an index of keys.
+//! - `hash`: also merges the spelling variants i/y ("lý" = "lí", "quy" =
+//!   "qui"), for grouping or a hash index. FNV-1a 64 over the UTF-8 of the
+//!   merged form, so it is the same on every platform and release.
+//!
+//! Decomposed text (letters followed by combining marks) gives the same
+//! key as composed text. Fullwidth letters and lookalikes from other
+//! scripts are kept as they are, so keys stay stable; fold pasted text
+//! with `tokenizer::fold_confusables` first to match them.
+
+use crate::data::chars::{self, mark, tone};
+
+/// Normalized forms of a string for search
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SearchKey {
+    pub key: String,
+    pub hash: u64,
+}
+
+/// Search key of `text`
+pub fn search_key(text: &str) -> SearchKey {
+    let mut key = String::with_capacity(text.len());
+    for c in text.chars() {
+        // Combining diacritics (decomposed text)
+        if chars::is_combining(c) {
+            continue;
+        }
+        match chars::parse_char(c) {
+            Some(p) if !c.is_ascii() => {
+                key.extend(chars::to_char(p.key, false, tone::NONE, mark::NONE))
+            }
+            _ => key.extend(c.to_lowercase()),
+        }
+    }
+    let hash = fnv1a(key.chars().map(|c| if c == 'y' { 'i' } else { c }));
+    SearchKey { key, hash }
+}
+
+fn fnv1a(text: impl Iterator<Item = char>) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    let mut buf = [0u8; 4];
+    for c in text {
+        for &b in c.encode_utf8(&mut buf).as_bytes() {
+            hash = (hash ^ b as u64).wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key() {
+        assert_eq!(search_key("Đường Phố").key, "duong pho");
+        assert_eq!(search_key("NGUYỄN Văn A").key, "nguyen van a");
+        assert_eq!(search_key("vie\u{0323}\u{0302}t").key, "viet");
+        assert_eq!(search_key("Straße 1").key, "straße 1");
+    }
+
+    #[test]
+    fn test_hash_merges_variants() {
+        let h = |s| search_key(s).hash;
+        assert_eq!(h("Lý Thái Tổ"), h("li thai to"));
+        assert_eq!(h("quy định"), h("qui dinh"));
+        assert_eq!(h("đi"), h("di"));
+        assert_ne!(h("lý"), h("la"));
+        // FNV-1a of the empty string
+        assert_eq!(h(""), 0xcbf2_9ce4_8422_2325);
+    }
+}
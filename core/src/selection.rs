@@ -0,0 +1,361 @@
+//! Selection Transforms
+//!
+//! Text operations for a "Transform selection" context menu: the host
+//! sends the selected text and replaces it with the result.
+//!
+//! - `RemoveTones`: "Tiếng Việt" → "Tieng Viet" (đ becomes d too)
+//! - `Viqr`: "Việt" → "Vie^.t", the plain-ASCII convention of mailing lists
+//! - `Encoding`: between Unicode (composed or decomposed) and TCVN3 (ABC)
+//! - `ToggleCase`: swaps the case of every letter, Đ ↔ đ included
+//! - `RawKeys`: back to the keystrokes that type each word ("việt" → vieetj)
+//! - `ToneStyle`: moves tone marks to the modern or classic place
+//!   ("hòa" ↔ "hoà"), by the rules the engine types with
+//! - `FoldConfusables`: fullwidth letters and Cyrillic/Greek lookalikes back
+//!   to ASCII ("ｖｉệｔ" → "việt", see `tokenizer::fold_confusables`)
+//!
+//! Decomposed input (e + U+0302 + U+0323, as macOS file names and some web
+//! pages have it) is accepted everywhere.
+
+use crate::config::{Method, ToneStyle};
+use crate::data::chars::{self, compose, decompose, mark, tone};
+use crate::data::keys;
+use crate::engine::transform::place_mark;
+use crate::engine::validation::explain;
+use crate::input::to_keystrokes;
+use crate::tokenizer::{fold_confusables, tokens, TokenKind};
+
+/// Operation on selected text
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Transform {
+    RemoveTones,
+    Viqr,
+    Encoding { from: Encoding, to: Encoding },
+    ToggleCase,
+    RawKeys(Method),
+    ToneStyle(ToneStyle),
+    FoldConfusables,
+}
+
+/// Text encodings of Vietnamese
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Encoding {
+    /// Unicode, one code point per letter (NFC)
+    #[default]
+    Unicode,
+    /// Unicode, letters followed by combining marks (NFD)
+    Decomposed,
+    /// TCVN 5712:1993 (VN3, the ABC fonts), as read with a Latin-1 code page.
+    /// Capital letters with a mark have no code of their own; they come out
+    /// lowercase, as they do with the .VnTime font.
+    Tcvn3,
+}
+
+impl Encoding {
+    /// FFI id (0=Unicode, 1=Decomposed, 2=TCVN3)
+    pub fn from_id(id: u8) -> Option<Encoding> {
+        match id {
+            0 => Some(Encoding::Unicode),
+            1 => Some(Encoding::Decomposed),
+            2 => Some(Encoding::Tcvn3),
+            _ => None,
+        }
+    }
+}
+
+/// Apply `op` to `text`
+pub fn transform(text: &str, op: Transform) -> String {
+    match op {
+        Transform::RemoveTones => remove_tones(text),
+        Transform::Viqr => to_viqr(text),
+        Transform::Encoding { from, to } => convert_encoding(text, from, to),
+        Transform::ToggleCase => toggle_case(text),
+        Transform::RawKeys(method) => to_raw_keys(text, method),
+        Transform::ToneStyle(style) => retone(text, style),
+        Transform::FoldConfusables => fold_confusables(text).into_owned(),
+    }
+}
+
+/// Strip every diacritic: marks, â/ă/ê/ô/ơ/ư and đ
+pub fn remove_tones(text: &str) -> String {
+    compose(text)
+        .chars()
+        .map(|c| match chars::parse_char(c) {
+            Some(p) if !c.is_ascii() => {
+                chars::to_char(p.key, p.caps, tone::NONE, mark::NONE).unwrap_or(c)
+            }
+            _ => c,
+        })
+        .collect()
+}
+
+/// VIQR: ă a( · â a^ · ơ o+ · ư u+ · đ dd, then the mark ' ` ? ~ .
+///
+/// Punctuation that would read as a modifier after a vowel gets a
+/// backslash ("Đi đâu?" → "DDi dda^u\?").
+pub fn to_viqr(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() * 2);
+    let mut after_vowel = false;
+    for c in compose(text).chars() {
+        let parsed = chars::parse_char(c).filter(|_| c.is_alphabetic());
+        let Some(p) = parsed else {
+            if after_vowel && "'`?~.^(+".contains(c) {
+                out.push('\\');
+            }
+            out.push(c);
+            after_vowel = false;
+            continue;
+        };
+        let base = chars::to_char(p.key, p.caps, tone::NONE, mark::NONE).unwrap_or(c);
+        out.push(base);
+        after_vowel = matches!(
+            p.key,
+            keys::A | keys::E | keys::I | keys::O | keys::U | keys::Y
+        );
+        if p.stroke {
+            out.push(base);
+        }
+        match (p.tone, base.to_ascii_lowercase()) {
+            (tone::CIRCUMFLEX, _) => out.push('^'),
+            (tone::HORN, 'a') => out.push('('),
+            (tone::HORN, _) => out.push('+'),
+            _ => {}
+        }
+        if p.mark != mark::NONE {
+            out.push(['\'', '`', '?', '~', '.'][p.mark as usize - 1]);
+        }
+    }
+    out
+}
+
+/// Re-encode `text` read as `from` into `to`
+pub fn convert_encoding(text: &str, from: Encoding, to: Encoding) -> String {
+    let unicode = match from {
+        Encoding::Unicode | Encoding::Decomposed => compose(text).into_owned(),
+        Encoding::Tcvn3 => text.chars().map(tcvn3_decode).collect(),
+    };
+    match to {
+        Encoding::Unicode => unicode,
+        Encoding::Decomposed => decompose(&unicode),
+        Encoding::Tcvn3 => unicode.chars().map(tcvn3_encode).collect(),
+    }
+}
+
+/// Swap lowercase and uppercase letters ("Đà Nẵng" → "đÀ nẴNG")
+pub fn toggle_case(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        if c.is_uppercase() {
+            out.extend(c.to_lowercase());
+        } else {
+            out.extend(c.to_uppercase());
+        }
+    }
+    out
+}
+
+/// Keystrokes that type each word with `method`; words that aren't
+/// Vietnamese are kept as they are
+pub fn to_raw_keys(text: &str, method: Method) -> String {
+    let text = compose(text);
+    let mut out = String::with_capacity(text.len() * 2);
+    let mut rest = text.as_ref();
+    while let Some(c) = rest.chars().next() {
+        let end = rest
+            .find(|c: char| !c.is_alphabetic())
+            .unwrap_or(rest.len())
+            .max(c.len_utf8());
+        let (chunk, tail) = rest.split_at(end);
+        match to_keystrokes(chunk, method) {
+            Some(keys) if c.is_alphabetic() => out.push_str(&keys),
+            _ => out.push_str(chunk),
+        }
+        rest = tail;
+    }
+    out
+}
+
+/// Tone marks of every Vietnamese word moved where `style` puts them
+/// ("Hòa thuận" → "Hoà thuận" for modern); other words are kept
+pub fn retone(text: &str, style: ToneStyle) -> String {
+    let text = compose(text);
+    let mut out = String::with_capacity(text.len());
+    for t in tokens(&text) {
+        let word = &text[t.span];
+        let moved = (t.kind == TokenKind::Word)
+            .then(|| place_mark(word, style == ToneStyle::Modern))
+            .flatten()
+            .filter(|_| explain(&word.to_lowercase()).problem.is_none());
+        out.push_str(moved.as_deref().unwrap_or(word));
+    }
+    out
+}
+
+// ============================================================
+// TCVN3
+// ============================================================
+
+/// TCVN3 codes: each base vowel, then its huyền, hỏi, ngã, sắc, nặng forms
+const TCVN3: [(char, u8, [u8; 5]); 12] = [
+    ('a', b'a', [0xB5, 0xB6, 0xB7, 0xB8, 0xB9]),
+    ('ă', 0xA8, [0xBB, 0xBC, 0xBD, 0xBE, 0xC6]),
+    ('â', 0xA9, [0xC7, 0xC8, 0xC9, 0xCA, 0xCB]),
+    ('e', b'e', [0xCC, 0xCE, 0xCF, 0xD0, 0xD1]),
+    ('ê', 0xAA, [0xD2, 0xD3, 0xD4, 0xD5, 0xD6]),
+    ('i', b'i', [0xD7, 0xD8, 0xDC, 0xDD, 0xDE]),
+    ('o', b'o', [0xDF, 0xE1, 0xE2, 0xE3, 0xE4]),
+    ('ô', 0xAB, [0xE5, 0xE6, 0xE7, 0xE8, 0xE9]),
+    ('ơ', 0xAC, [0xEA, 0xEB, 0xEC, 0xED, 0xEE]),
+    ('u', b'u', [0xEF, 0xF1, 0xF2, 0xF3, 0xF4]),
+    ('ư', 0xAD, [0xF5, 0xF6, 0xF7, 0xF8, 0xF9]),
+    ('y', b'y', [0xFA, 0xFB, 0xFC, 0xFD, 0xFE]),
+];
+
+/// Capitals with their own code (no mark)
+const TCVN3_CAPS: [(char, u8); 7] = [
+    ('Ă', 0xA1),
+    ('Â', 0xA2),
+    ('Ê', 0xA3),
+    ('Ô', 0xA4),
+    ('Ơ', 0xA5),
+    ('Ư', 0xA6),
+    ('Đ', 0xA7),
+];
+
+/// Table order (huyền, hỏi, ngã, sắc, nặng) to `mark` values
+const TCVN3_MARKS: [u8; 5] = [mark::HUYEN, mark::HOI, mark::NGA, mark::SAC, mark::NANG];
+
+fn tcvn3_encode(c: char) -> char {
+    if c == 'đ' {
+        return '\u{AE}';
+    }
+    if let Some(&(_, code)) = TCVN3_CAPS.iter().find(|(u, _)| *u == c) {
+        return code as char;
+    }
+    let Some(p) = chars::parse_char(c).filter(|_| !c.is_ascii()) else {
+        return c;
+    };
+    let Some(base) = chars::to_char(p.key, false, p.tone, mark::NONE) else {
+        return c;
+    };
+    let Some(&(_, plain, marked)) = TCVN3.iter().find(|(b, _, _)| *b == base) else {
+        return c;
+    };
+    let code = match TCVN3_MARKS.iter().position(|&m| m == p.mark) {
+        Some(i) => marked[i],
+        None => plain,
+    };
+    code as char
+}
+
+fn tcvn3_decode(c: char) -> char {
+    if c == '\u{AE}' {
+        return 'đ';
+    }
+    let Ok(code) = u8::try_from(c as u32) else {
+        return c;
+    };
+    if code < 0xA1 {
+        return c;
+    }
+    if let Some(&(u, _)) = TCVN3_CAPS.iter().find(|(_, b)| *b == code) {
+        return u;
+    }
+    for &(base, plain, marked) in &TCVN3 {
+        if plain == code {
+            return base;
+        }
+        if let Some(i) = marked.iter().position(|&b| b == code) {
+            let p = chars::parse_char(base).expect("table holds vowels");
+            return chars::to_char(p.key, false, p.tone, TCVN3_MARKS[i]).unwrap_or(c);
+        }
+    }
+    c
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remove_tones() {
+        assert_eq!(remove_tones("Tiếng Việt, ĐẸP!"), "Tieng Viet, DEP!");
+        assert_eq!(remove_tones("vie\u{0323}\u{0302}t"), "viet");
+        assert_eq!(remove_tones("café 123"), "cafe 123");
+    }
+
+    #[test]
+    fn test_viqr() {
+        assert_eq!(to_viqr("Tiếng Việt"), "Tie^'ng Vie^.t");
+        assert_eq!(to_viqr("Đi đâu?"), "DDi dda^u\\?");
+        assert_eq!(to_viqr("ăn ở"), "a(n o+?");
+        assert_eq!(to_viqr("Hết."), "He^'t.");
+    }
+
+    #[test]
+    fn test_decomposed_roundtrip() {
+        let text = "Người Việt ấm ức, Đà Nẵng";
+        let nfd = convert_encoding(text, Encoding::Unicode, Encoding::Decomposed);
+        assert!(nfd.contains("e\u{0323}\u{0302}"));
+        assert!(nfd.contains("u\u{031B}o\u{031B}\u{0300}"));
+        assert!(nfd.contains("a\u{0302}\u{0301}"));
+        assert!(nfd.contains('Đ'));
+        assert_eq!(
+            convert_encoding(&nfd, Encoding::Decomposed, Encoding::Unicode),
+            text
+        );
+    }
+
+    #[test]
+    fn test_tcvn3() {
+        let tcvn = convert_encoding("tiếng việt đẹp", Encoding::Unicode, Encoding::Tcvn3);
+        assert_eq!(tcvn, "ti\u{D5}ng vi\u{D6}t \u{AE}\u{D1}p");
+        assert_eq!(
+            convert_encoding(&tcvn, Encoding::Tcvn3, Encoding::Unicode),
+            "tiếng việt đẹp"
+        );
+        // Marked capitals fall back to lowercase
+        let tcvn = convert_encoding("Ở ĐÂY", Encoding::Unicode, Encoding::Tcvn3);
+        assert_eq!(
+            convert_encoding(&tcvn, Encoding::Tcvn3, Encoding::Unicode),
+            "ở ĐÂY"
+        );
+    }
+
+    #[test]
+    fn test_toggle_case() {
+        assert_eq!(toggle_case("Đà Nẵng"), "đÀ nẴNG");
+        assert_eq!(toggle_case(&toggle_case("Ưu tiên ĐI")), "Ưu tiên ĐI");
+    }
+
+    #[test]
+    fn test_raw_keys() {
+        assert_eq!(
+            to_raw_keys("Việt Nam, đẹp!", Method::Telex),
+            "Vieetj Nam, ddepj!"
+        );
+        assert_eq!(to_raw_keys("được 100%", Method::Vni), "d9u7o7c5 100%");
+        assert_eq!(to_raw_keys("straße", Method::Telex), "straße");
+    }
+
+    #[test]
+    fn test_retone() {
+        let classic = "Hòa thuận, khỏe mạnh: THÚY hoàn toàn";
+        let modern = "Hoà thuận, khoẻ mạnh: THUÝ hoàn toàn";
+        assert_eq!(retone(classic, ToneStyle::Modern), modern);
+        assert_eq!(retone(modern, ToneStyle::Classic), classic);
+        assert_eq!(retone("ho\u{0300}a", ToneStyle::Modern), "hoà");
+        // Not Vietnamese: left alone
+        assert_eq!(retone("café, naïve", ToneStyle::Modern), "café, naïve");
+    }
+
+    #[test]
+    fn test_transform() {
+        let op = Transform::Encoding {
+            from: Encoding::Unicode,
+            to: Encoding::Unicode,
+        };
+        assert_eq!(transform("vie\u{0302}\u{0323}t", op), "việt");
+        assert_eq!(transform("bạn", Transform::Viqr), "ba.n");
+        assert_eq!(transform("ｂạｎ", Transform::FoldConfusables), "bạn");
+    }
+}
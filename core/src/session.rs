@@ -0,0 +1,248 @@
+//! Per-session engines
+//!
+//! One engine per user session, for hosts serving several users at once
+//! (the IPC server, terminal servers). Everything mutable lives in the
+//! engine (typed word, settings, shortcuts, learned words, history), so
+//! separate engines never see each other's state.
+//!
+//! Sessions are created on first use and evicted when idle longer than
+//! `idle`, or least recently used first once there are more than `max`.
+//! With a storage root, each session keeps its data in its own directory
+//! (`root/<id>`): loaded on creation, saved on eviction.
+
+use crate::engine::Engine;
+use crate::storage::FileStorage;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// Longest session ID
+pub const MAX_ID_LEN: usize = 64;
+
+/// Whether `id` can name a session: 1-64 ASCII letters, digits, `-`, `_`
+/// or `.`, not `.`/`..` (it becomes a directory name)
+pub fn valid_id(id: &str) -> bool {
+    (1..=MAX_ID_LEN).contains(&id.len())
+        && id
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.'))
+        && id != "."
+        && id != ".."
+}
+
+struct Slot {
+    engine: Engine,
+    last_used: Instant,
+}
+
+/// Engines keyed by session ID
+pub struct Sessions {
+    slots: HashMap<String, Slot>,
+    max: usize,
+    idle: Duration,
+    storage_root: Option<PathBuf>,
+}
+
+impl Sessions {
+    /// At most `max` sessions (at least 1), each dropped after `idle`
+    /// without use
+    pub fn new(max: usize, idle: Duration) -> Self {
+        Sessions {
+            slots: HashMap::new(),
+            max: max.max(1),
+            idle,
+            storage_root: None,
+        }
+    }
+
+    /// Persist each session under `root/<id>`
+    pub fn with_storage_root(mut self, root: impl Into<PathBuf>) -> Self {
+        self.storage_root = Some(root.into());
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.slots.contains_key(id)
+    }
+
+    /// Run `f` on the engine of session `id`, creating it if needed
+    ///
+    /// `None` if `id` is not a valid session ID.
+    pub fn with<R>(
+        &mut self,
+        id: &str,
+        now: Instant,
+        f: impl FnOnce(&mut Engine) -> R,
+    ) -> Option<R> {
+        if !valid_id(id) {
+            return None;
+        }
+        self.evict_idle(now);
+        if !self.slots.contains_key(id) {
+            self.make_room();
+            let engine = self.open(id);
+            self.slots.insert(
+                id.to_string(),
+                Slot {
+                    engine,
+                    last_used: now,
+                },
+            );
+        }
+        let slot = self.slots.get_mut(id)?;
+        slot.last_used = now;
+        Some(f(&mut slot.engine))
+    }
+
+    /// End session `id` (saving it); false if there was none
+    pub fn remove(&mut self, id: &str) -> bool {
+        match self.slots.remove(id) {
+            Some(slot) => {
+                self.close(slot);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Drop sessions idle for longer than `idle`; returns how many
+    pub fn evict_idle(&mut self, now: Instant) -> usize {
+        let stale: Vec<String> = self
+            .slots
+            .iter()
+            .filter(|(_, s)| now.saturating_duration_since(s.last_used) > self.idle)
+            .map(|(id, _)| id.clone())
+            .collect();
+        for id in &stale {
+            self.remove(id);
+        }
+        stale.len()
+    }
+
+    /// Evict least recently used sessions until one more fits
+    fn make_room(&mut self) {
+        while self.slots.len() >= self.max {
+            let Some(oldest) = self
+                .slots
+                .iter()
+                .min_by_key(|(_, s)| s.last_used)
+                .map(|(id, _)| id.clone())
+            else {
+                break;
+            };
+            self.remove(&oldest);
+        }
+    }
+
+    fn open(&self, id: &str) -> Engine {
+        let mut engine = Engine::new();
+        if let Some(root) = &self.storage_root {
+            engine.set_storage(FileStorage::new(root.join(id)));
+            // Nothing saved yet for a new session
+            let _ = engine.load();
+        }
+        engine
+    }
+
+    fn close(&self, mut slot: Slot) {
+        if self.storage_root.is_some() {
+            let _ = slot.engine.save();
+        }
+    }
+}
+
+impl Drop for Sessions {
+    fn drop(&mut self) {
+        for (_, slot) in std::mem::take(&mut self.slots) {
+            self.close(slot);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::keys;
+
+    fn type_keys(s: &mut Sessions, id: &str, now: Instant, keys: &[u16]) {
+        for &k in keys {
+            s.with(id, now, |e| e.on_key(k, false, false)).unwrap();
+        }
+    }
+
+    fn word(s: &mut Sessions, id: &str, now: Instant) -> Option<String> {
+        s.with(id, now, |e| e.word_info().map(|w| w.text)).unwrap()
+    }
+
+    #[test]
+    fn test_valid_id() {
+        assert!(valid_id("user-1001"));
+        assert!(valid_id("S-1-5-21.rdp_2"));
+        assert!(!valid_id(""));
+        assert!(!valid_id(".."));
+        assert!(!valid_id("a/b"));
+        assert!(!valid_id(&"x".repeat(MAX_ID_LEN + 1)));
+    }
+
+    #[test]
+    fn test_isolated() {
+        let now = Instant::now();
+        let mut s = Sessions::new(8, Duration::from_secs(60));
+        type_keys(&mut s, "alice", now, &[keys::A, keys::S]);
+        type_keys(&mut s, "bob", now, &[keys::O, keys::O]);
+        assert_eq!(word(&mut s, "alice", now).as_deref(), Some("á"));
+        assert_eq!(word(&mut s, "bob", now).as_deref(), Some("ô"));
+        s.with("bob", now, |e| e.set_method(1).unwrap()).unwrap();
+        assert_eq!(
+            s.with("alice", now, |e| e.config().method),
+            Some(Default::default())
+        );
+        assert_eq!(s.with("../etc", now, |_| ()), None);
+    }
+
+    #[test]
+    fn test_eviction() {
+        let t0 = Instant::now();
+        let mut s = Sessions::new(2, Duration::from_secs(60));
+        type_keys(&mut s, "a", t0, &[keys::A]);
+        type_keys(&mut s, "b", t0 + Duration::from_secs(1), &[keys::A]);
+        // Over the limit: the least recently used goes
+        type_keys(&mut s, "c", t0 + Duration::from_secs(2), &[keys::A]);
+        assert!(!s.contains("a"));
+        assert!(s.contains("b") && s.contains("c"));
+
+        // Idle past the timeout
+        let later = t0 + Duration::from_secs(62);
+        type_keys(&mut s, "c", later, &[keys::S]);
+        assert!(!s.contains("b"));
+        assert_eq!(s.len(), 1);
+        assert_eq!(s.evict_idle(later + Duration::from_secs(61)), 1);
+        assert!(s.is_empty());
+    }
+
+    #[test]
+    fn test_storage_per_session() {
+        let root = std::env::temp_dir().join(format!("gonhanh-sessions-{}", std::process::id()));
+        let now = Instant::now();
+        let mut s = Sessions::new(4, Duration::from_secs(60)).with_storage_root(&root);
+        s.with("alice", now, |e| e.set_method(1).unwrap()).unwrap();
+        s.with("bob", now, |_| ()).unwrap();
+        assert!(s.remove("alice"));
+        assert!(!s.remove("alice"));
+        drop(s);
+
+        let mut s = Sessions::new(4, Duration::from_secs(60)).with_storage_root(&root);
+        let method = |s: &mut Sessions, id| s.with(id, now, |e| e.config().method).unwrap();
+        assert_ne!(method(&mut s, "alice"), method(&mut s, "bob"));
+        drop(s);
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,485 @@
+//! Storage abstraction
+//!
+//! The engine persists user data (settings, shortcuts, later learned data)
+//! as named blobs through the `Storage` trait instead of touching the
+//! filesystem directly. Sandboxed hosts (App Store macOS, UWP) supply their
+//! own backend via FFI callbacks; everyone else uses `FileStorage`.
+//!
+//! Blob names are flat (`config.toml`, `shortcuts.tsv`): no directories.
+//!
+//! Crash safety: `FileStorage` writes through a temp file + rename, so a
+//! blob is either the old or the new version, never half-written. The
+//! engine also `seal`s every blob with a CRC32 trailer and treats a
+//! mismatch on load as corruption (see `StateHealth`).
+
+use crate::config::ConfigError;
+use std::collections::HashMap;
+use std::io;
+use std::os::raw::{c_char, c_void};
+use std::path::PathBuf;
+
+/// Settings blob
+pub const CONFIG_BLOB: &str = "config.toml";
+/// User shortcuts blob
+pub const SHORTCUTS_BLOB: &str = "shortcuts.tsv";
+/// Sync history (see `sync`)
+pub const SYNC_BLOB: &str = "sync.json";
+
+/// Named blob store
+pub trait Storage: Send {
+    /// Read a blob; `Ok(None)` if it doesn't exist
+    fn read(&self, name: &str) -> io::Result<Option<Vec<u8>>>;
+
+    /// Create or replace a blob
+    fn write(&mut self, name: &str, data: &[u8]) -> io::Result<()>;
+
+    /// Delete a blob (no error if missing)
+    fn remove(&mut self, name: &str) -> io::Result<()>;
+}
+
+/// Error while saving or loading
+///
+/// Unreadable blobs are not errors: `Engine::load` resets them to defaults
+/// and reports them in `StateHealth`.
+#[derive(Debug)]
+pub enum StorageError {
+    /// No storage backend configured
+    NoStorage,
+    /// Backend failed
+    Io(io::Error),
+}
+
+impl StorageError {
+    /// Stable numeric code for FFI
+    pub fn code(&self) -> i32 {
+        match self {
+            StorageError::NoStorage => -1,
+            StorageError::Io(_) => 10,
+        }
+    }
+}
+
+impl std::fmt::Display for StorageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StorageError::NoStorage => write!(f, "no storage configured"),
+            StorageError::Io(e) => write!(f, "storage error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for StorageError {}
+
+impl From<io::Error> for StorageError {
+    fn from(e: io::Error) -> Self {
+        StorageError::Io(e)
+    }
+}
+
+/// Blobs reset to defaults by the last `Engine::load`
+///
+/// Hosts should tell the user when this isn't empty ("your shortcuts
+/// could not be read and were reset"). The unreadable data is kept as
+/// `<name>.corrupt` for support.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StateHealth {
+    pub reset: Vec<&'static str>,
+    /// Saved settings read fine but had options that can't go together:
+    /// those were reset to their default, the rest kept
+    pub config_error: Option<ConfigError>,
+}
+
+impl StateHealth {
+    pub fn is_ok(&self) -> bool {
+        self.reset.is_empty() && self.config_error.is_none()
+    }
+
+    /// FFI bitmask: 1=config, 2=shortcuts, 4=sync history, 8=a config
+    /// option reset (`config_error`)
+    pub fn to_bits(&self) -> u32 {
+        let options = if self.config_error.is_some() { 8 } else { 0 };
+        self.reset
+            .iter()
+            .map(|name| match *name {
+                CONFIG_BLOB => 1,
+                SHORTCUTS_BLOB => 2,
+                SYNC_BLOB => 4,
+                _ => 0,
+            })
+            .fold(options, |bits, b| bits | b)
+    }
+}
+
+// ============================================================
+// Checksums
+// ============================================================
+
+/// Trailer line written by `seal`: `#crc32:xxxxxxxx`
+const CRC_TAG: &[u8] = b"#crc32:";
+
+/// CRC-32 (IEEE)
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &b in data {
+        crc ^= b as u32;
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xEDB8_8320 & (crc & 1).wrapping_neg());
+        }
+    }
+    !crc
+}
+
+/// Append a checksum trailer line
+pub fn seal(data: &[u8]) -> Vec<u8> {
+    let mut out = data.to_vec();
+    if !out.is_empty() && !out.ends_with(b"\n") {
+        out.push(b'\n');
+    }
+    let crc = crc32(&out);
+    out.extend_from_slice(CRC_TAG);
+    out.extend_from_slice(format!("{:08x}\n", crc).as_bytes());
+    out
+}
+
+/// Strip and verify the trailer; `None` if the checksum doesn't match.
+///
+/// Blobs without a trailer (hand-written, or from before checksums) are
+/// returned unchanged.
+pub fn unseal(data: &[u8]) -> Option<&[u8]> {
+    let last = data.strip_suffix(b"\n").unwrap_or(data);
+    let start = last.iter().rposition(|&b| b == b'\n').map_or(0, |i| i + 1);
+    let (body, trailer) = (&data[..start], &last[start..]);
+    let Some(hex) = trailer.strip_prefix(CRC_TAG) else {
+        return Some(data);
+    };
+    let expected = std::str::from_utf8(hex)
+        .ok()
+        .and_then(|h| u32::from_str_radix(h, 16).ok())?;
+    (crc32(body) == expected).then_some(body)
+}
+
+/// Reject names that could escape the storage root
+fn check_name(name: &str) -> io::Result<()> {
+    let valid = !name.is_empty()
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'));
+    if valid {
+        Ok(())
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("invalid blob name: {:?}", name),
+        ))
+    }
+}
+
+// ============================================================
+// Filesystem
+// ============================================================
+
+/// One file per blob in a directory
+pub struct FileStorage {
+    dir: PathBuf,
+}
+
+impl FileStorage {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Per-user config directory for the current platform
+    ///
+    /// - macOS: `~/Library/Application Support/GoNhanh`
+    /// - Windows: `%APPDATA%\GoNhanh`
+    /// - Linux: `$XDG_CONFIG_HOME/gonhanh` or `~/.config/gonhanh`
+    pub fn default_dir() -> Option<PathBuf> {
+        let env = |k: &str| {
+            std::env::var_os(k)
+                .filter(|v| !v.is_empty())
+                .map(PathBuf::from)
+        };
+        if cfg!(target_os = "macos") {
+            env("HOME").map(|h| h.join("Library/Application Support/GoNhanh"))
+        } else if cfg!(windows) {
+            env("APPDATA").map(|a| a.join("GoNhanh"))
+        } else {
+            env("XDG_CONFIG_HOME")
+                .or_else(|| env("HOME").map(|h| h.join(".config")))
+                .map(|c| c.join("gonhanh"))
+        }
+    }
+
+    pub fn dir(&self) -> &std::path::Path {
+        &self.dir
+    }
+}
+
+impl Storage for FileStorage {
+    fn read(&self, name: &str) -> io::Result<Option<Vec<u8>>> {
+        check_name(name)?;
+        match std::fs::read(self.dir.join(name)) {
+            Ok(data) => Ok(Some(data)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Atomic: write `.<name>.tmp`, flush to disk, rename over `name`
+    fn write(&mut self, name: &str, data: &[u8]) -> io::Result<()> {
+        use std::io::Write;
+
+        check_name(name)?;
+        std::fs::create_dir_all(&self.dir)?;
+        let tmp = self.dir.join(format!(".{}.tmp", name));
+        let result = std::fs::File::create(&tmp).and_then(|mut f| {
+            f.write_all(data)?;
+            f.sync_all()
+        });
+        match result.and_then(|_| std::fs::rename(&tmp, self.dir.join(name))) {
+            Ok(()) => Ok(()),
+            Err(e) => {
+                let _ = std::fs::remove_file(&tmp);
+                Err(e)
+            }
+        }
+    }
+
+    fn remove(&mut self, name: &str) -> io::Result<()> {
+        check_name(name)?;
+        match std::fs::remove_file(self.dir.join(name)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
+
+// ============================================================
+// In-memory
+// ============================================================
+
+/// Volatile storage (tests, incognito sessions)
+#[derive(Default)]
+pub struct MemoryStorage {
+    blobs: HashMap<String, Vec<u8>>,
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Storage for MemoryStorage {
+    fn read(&self, name: &str) -> io::Result<Option<Vec<u8>>> {
+        check_name(name)?;
+        Ok(self.blobs.get(name).cloned())
+    }
+
+    fn write(&mut self, name: &str, data: &[u8]) -> io::Result<()> {
+        check_name(name)?;
+        self.blobs.insert(name.to_string(), data.to_vec());
+        Ok(())
+    }
+
+    fn remove(&mut self, name: &str) -> io::Result<()> {
+        self.blobs.remove(name);
+        Ok(())
+    }
+}
+
+// ============================================================
+// Host callbacks (FFI)
+// ============================================================
+
+/// Read callback: copy blob `name` into `buf` (capacity `cap`).
+///
+/// Returns the blob length (may exceed `cap`: the engine retries with a
+/// larger buffer), -1 if the blob doesn't exist, or -2 on error.
+pub type ReadCallback =
+    extern "C" fn(name: *const c_char, buf: *mut u8, cap: usize, user_data: *mut c_void) -> isize;
+
+/// Write callback: store `len` bytes as blob `name`. Returns 0 on success.
+pub type WriteCallback =
+    extern "C" fn(name: *const c_char, data: *const u8, len: usize, user_data: *mut c_void) -> i32;
+
+/// Remove callback: delete blob `name`. Returns 0 on success.
+pub type RemoveCallback = extern "C" fn(name: *const c_char, user_data: *mut c_void) -> i32;
+
+/// Storage backed by host callbacks
+pub struct CallbackStorage {
+    read: ReadCallback,
+    write: WriteCallback,
+    remove: RemoveCallback,
+    user_data: *mut c_void,
+}
+
+// SAFETY: user_data is opaque to us; the host owns its thread-safety
+unsafe impl Send for CallbackStorage {}
+
+impl CallbackStorage {
+    pub fn new(
+        read: ReadCallback,
+        write: WriteCallback,
+        remove: RemoveCallback,
+        user_data: *mut c_void,
+    ) -> Self {
+        Self {
+            read,
+            write,
+            remove,
+            user_data,
+        }
+    }
+}
+
+fn c_name(name: &str) -> io::Result<std::ffi::CString> {
+    check_name(name)?;
+    // check_name only allows ASCII without NUL
+    Ok(std::ffi::CString::new(name).expect("validated name"))
+}
+
+fn callback_error(what: &str, name: &str) -> io::Error {
+    io::Error::other(format!("host {} failed for {}", what, name))
+}
+
+impl Storage for CallbackStorage {
+    fn read(&self, name: &str) -> io::Result<Option<Vec<u8>>> {
+        let c = c_name(name)?;
+        let mut buf = vec![0u8; 4096];
+        loop {
+            let n = (self.read)(c.as_ptr(), buf.as_mut_ptr(), buf.len(), self.user_data);
+            match n {
+                -1 => return Ok(None),
+                n if n < 0 => return Err(callback_error("read", name)),
+                n if n as usize > buf.len() => buf.resize(n as usize, 0),
+                n => {
+                    buf.truncate(n as usize);
+                    return Ok(Some(buf));
+                }
+            }
+        }
+    }
+
+    fn write(&mut self, name: &str, data: &[u8]) -> io::Result<()> {
+        let c = c_name(name)?;
+        match (self.write)(c.as_ptr(), data.as_ptr(), data.len(), self.user_data) {
+            0 => Ok(()),
+            _ => Err(callback_error("write", name)),
+        }
+    }
+
+    fn remove(&mut self, name: &str) -> io::Result<()> {
+        let c = c_name(name)?;
+        match (self.remove)(c.as_ptr(), self.user_data) {
+            0 => Ok(()),
+            _ => Err(callback_error("remove", name)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_storage() {
+        let mut s = MemoryStorage::new();
+        assert!(s.read("a.txt").unwrap().is_none());
+        s.write("a.txt", b"hello").unwrap();
+        assert_eq!(s.read("a.txt").unwrap().as_deref(), Some(&b"hello"[..]));
+        s.remove("a.txt").unwrap();
+        assert!(s.read("a.txt").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_invalid_names() {
+        let mut s = MemoryStorage::new();
+        for name in ["", "../x", "a/b", ".hidden", "a\\b"] {
+            assert!(s.write(name, b"x").is_err(), "{:?}", name);
+        }
+    }
+
+    #[test]
+    fn test_file_storage() {
+        let dir = std::env::temp_dir().join(format!("gonhanh-storage-{}", std::process::id()));
+        let mut s = FileStorage::new(&dir);
+        assert!(s.read("config.toml").unwrap().is_none());
+        s.write("config.toml", b"enabled = true").unwrap();
+        assert_eq!(
+            s.read("config.toml").unwrap().as_deref(),
+            Some(&b"enabled = true"[..])
+        );
+        s.write("config.toml", b"enabled = false").unwrap();
+        assert_eq!(
+            s.read("config.toml").unwrap().as_deref(),
+            Some(&b"enabled = false"[..])
+        );
+        // Only the blob itself is left behind, no temp file
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+        s.remove("config.toml").unwrap();
+        s.remove("config.toml").unwrap(); // missing is fine
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    // Host backend: a single fixed blob larger than the first read buffer
+    extern "C" fn big_read(_: *const c_char, buf: *mut u8, cap: usize, _: *mut c_void) -> isize {
+        const LEN: usize = 5000;
+        if cap >= LEN {
+            unsafe { std::ptr::write_bytes(buf, b'x', LEN) };
+        }
+        LEN as isize
+    }
+    extern "C" fn fail_write(_: *const c_char, _: *const u8, _: usize, _: *mut c_void) -> i32 {
+        1
+    }
+    extern "C" fn ok_remove(_: *const c_char, _: *mut c_void) -> i32 {
+        0
+    }
+
+    #[test]
+    fn test_callback_storage() {
+        let mut s = CallbackStorage::new(big_read, fail_write, ok_remove, std::ptr::null_mut());
+        let data = s.read("big.bin").unwrap().unwrap();
+        assert_eq!(data.len(), 5000);
+        assert!(data.iter().all(|&b| b == b'x'));
+        assert!(s.write("big.bin", b"y").is_err());
+        assert!(s.remove("big.bin").is_ok());
+    }
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn test_seal_unseal() {
+        let sealed = seal(b"method = \"vni\"");
+        assert_eq!(unseal(&sealed), Some(&b"method = \"vni\"\n"[..]));
+
+        // Flipped byte
+        let mut bad = sealed.clone();
+        bad[0] = b'M';
+        assert_eq!(unseal(&bad), None);
+
+        // Truncated trailer
+        assert_eq!(unseal(&sealed[..sealed.len() - 3]), None);
+
+        // No trailer: taken as is
+        assert_eq!(unseal(b"a\nb\n"), Some(&b"a\nb\n"[..]));
+        assert_eq!(unseal(&seal(b"")), Some(&b""[..]));
+    }
+
+    #[test]
+    fn test_state_health_bits() {
+        let health = StateHealth {
+            reset: vec![CONFIG_BLOB, SYNC_BLOB],
+            config_error: None,
+        };
+        assert!(!health.is_ok());
+        assert_eq!(health.to_bits(), 5);
+        assert!(StateHealth::default().is_ok());
+    }
+}
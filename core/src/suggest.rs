@@ -0,0 +1,119 @@
+//! "Did you mean" Suggestions
+//!
+//! `suggest_similar` looks up the words of the embedded Vietnamese list
+//! (`data::lexicon::VIETNAMESE`) within an edit distance of the input.
+//! Distance counts characters, so a missing or wrong diacritic is one edit
+//! ("viet" → "việt") like any other typo.
+//!
+//! Scores are in (0, 1], higher is better: closer words always come first,
+//! and among words at the same distance the more frequent ones do.
+
+use crate::data::lexicon::VIETNAMESE;
+use crate::engine::decision::{Decision, DecisionAction, Source};
+
+/// Words within `max_distance` edits of `word`, best first
+pub fn suggest_similar(word: &str, max_distance: usize) -> Vec<(&'static str, f32)> {
+    let word: Vec<char> = word.to_lowercase().chars().collect();
+    if word.is_empty() {
+        return Vec::new();
+    }
+    let total = VIETNAMESE.len().max(1) as f32;
+    let mut found: Vec<(&'static str, f32)> = VIETNAMESE
+        .words()
+        .enumerate()
+        .filter_map(|(rank, candidate)| {
+            let d = distance(&word, candidate, max_distance)?;
+            // Frequency weighs [0.5, 1]; a step in distance always outweighs it
+            let frequency = 1.0 - 0.5 * rank as f32 / total;
+            let closeness = 1.0 / ((1 + d) * (1 + d)) as f32;
+            Some((candidate, frequency * closeness))
+        })
+        .collect();
+    found.sort_by(|a, b| b.1.total_cmp(&a.1));
+    found
+}
+
+/// `suggest_similar` as decisions to replace `word`, scores as
+/// confidence, to weigh against the engine's own (`engine::decision`)
+pub fn similar_decisions(word: &str, max_distance: usize) -> Vec<Decision> {
+    suggest_similar(word, max_distance)
+        .into_iter()
+        .map(|(w, score)| Decision::new(DecisionAction::Replace(w.into()), score, Source::Similar))
+        .collect()
+}
+
+/// Levenshtein distance between `a` and `b`, or `None` once it is sure
+/// to exceed `max`
+fn distance(a: &[char], b: &str, max: usize) -> Option<usize> {
+    let b: Vec<char> = b.chars().collect();
+    if a.len().abs_diff(b.len()) > max {
+        return None;
+    }
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut row = vec![0; b.len() + 1];
+    for (i, &ca) in a.iter().enumerate() {
+        row[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let substitute = prev[j] + usize::from(ca != cb);
+            row[j + 1] = substitute.min(prev[j + 1] + 1).min(row[j] + 1);
+        }
+        if row.iter().min().is_some_and(|&m| m > max) {
+            return None;
+        }
+        std::mem::swap(&mut prev, &mut row);
+    }
+    Some(prev[b.len()]).filter(|&d| d <= max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(word: &str, max: usize) -> Vec<&'static str> {
+        suggest_similar(word, max)
+            .into_iter()
+            .map(|(w, _)| w)
+            .collect()
+    }
+
+    #[test]
+    fn test_distance() {
+        let d = |a: &str, b, max| distance(&a.chars().collect::<Vec<_>>(), b, max);
+        assert_eq!(d("viet", "việt", 2), Some(1));
+        assert_eq!(d("nguoi", "người", 2), Some(2));
+        assert_eq!(d("nguoi", "người", 1), None);
+        assert_eq!(d("", "ăn", 2), Some(2));
+        assert_eq!(d("abc", "abc", 0), Some(0));
+    }
+
+    #[test]
+    fn test_closest_first() {
+        let mut found = words("viet", 1);
+        found.truncate(2);
+        found.sort_unstable();
+        assert_eq!(found, ["viết", "việt"]);
+        assert_eq!(words("Người", 0), ["người"]);
+    }
+
+    #[test]
+    fn test_frequency_breaks_ties() {
+        // "có" and "cô" are one edit from "co"; "có" is more frequent
+        let found = suggest_similar("co", 1);
+        let pos = |w| found.iter().position(|(f, _)| *f == w).unwrap();
+        assert!(pos("có") < pos("cô"));
+        assert!(found.iter().all(|(_, s)| *s > 0.0 && *s <= 1.0));
+    }
+
+    #[test]
+    fn test_similar_decisions() {
+        let decisions = similar_decisions("co", 1);
+        assert_eq!(decisions[0].action, DecisionAction::Replace("có".into()));
+        assert_eq!(decisions[0].confidence, suggest_similar("co", 1)[0].1);
+    }
+
+    #[test]
+    fn test_nothing_close() {
+        assert!(words("xyzzyq", 1).is_empty());
+        assert!(words("", 3).is_empty());
+    }
+}
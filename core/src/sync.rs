@@ -0,0 +1,432 @@
+//! Profile sync
+//!
+//! Keeps a user's profile (see `profile`) in step across machines. The core
+//! owns the data model and the merge; moving bytes (iCloud Drive, OneDrive,
+//! a file share) is left to the host through `SyncTransport`.
+//!
+//! A profile is flattened into entries, one per setting (`settings.method`)
+//! and one per shortcut (`shortcut.vn`). Each entry carries the time and
+//! device of its last change; deleted shortcuts leave a tombstone. Merging
+//! keeps the newer entry (last writer wins), ties go to the greater device
+//! name so every machine converges on the same result.
+//!
+//! `enabled` is per machine and never synced.
+
+use crate::config::EngineConfig;
+use crate::json::{self, Value};
+use crate::profile::{Profile, ProfileError, PROFILE_FORMAT, PROFILE_VERSION};
+use std::collections::BTreeMap;
+use std::io;
+use std::os::raw::c_void;
+
+/// `format` marker of a sync document
+pub const SYNC_FORMAT: &str = "gonhanh-sync";
+/// Current sync document version
+pub const SYNC_VERSION: u64 = 1;
+
+/// Error while syncing (engine state unchanged)
+#[derive(Debug)]
+pub enum SyncError {
+    /// Host transport failed
+    Transport(io::Error),
+    /// Sync document is unreadable
+    Format(String),
+    /// Merged entries don't form a valid profile
+    Profile(ProfileError),
+}
+
+impl SyncError {
+    /// Stable numeric code for FFI (ProfileError codes are 1-5, 20-23)
+    pub fn code(&self) -> i32 {
+        match self {
+            SyncError::Profile(e) => e.code(),
+            SyncError::Transport(_) => 30,
+            SyncError::Format(_) => 31,
+        }
+    }
+}
+
+impl std::fmt::Display for SyncError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SyncError::Transport(e) => write!(f, "sync transport: {}", e),
+            SyncError::Format(msg) => write!(f, "sync document: {}", msg),
+            SyncError::Profile(e) => write!(f, "synced profile: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for SyncError {}
+
+impl From<io::Error> for SyncError {
+    fn from(e: io::Error) -> Self {
+        SyncError::Transport(e)
+    }
+}
+
+/// Moves the shared sync document between machines
+pub trait SyncTransport {
+    /// Latest shared document, or `None` if nothing was pushed yet
+    fn pull(&mut self) -> io::Result<Option<Vec<u8>>>;
+
+    /// Replace the shared document
+    fn push(&mut self, data: &[u8]) -> io::Result<()>;
+}
+
+/// One synced value
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entry {
+    /// `None` = deleted (tombstone)
+    pub value: Option<Value>,
+    /// Milliseconds since the Unix epoch
+    pub modified: u64,
+    pub device: String,
+}
+
+impl Entry {
+    /// Last-writer-wins order
+    fn newer_than(&self, other: &Entry) -> bool {
+        (self.modified, &self.device) > (other.modified, &other.device)
+    }
+}
+
+/// Entries of a synced profile, keyed by `settings.<key>` / `shortcut.<trigger>`
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SyncDoc {
+    entries: BTreeMap<String, Entry>,
+}
+
+/// Profile as `key → value`
+fn flatten(profile: &Profile) -> BTreeMap<String, Value> {
+    let mut out = BTreeMap::new();
+    let doc = profile.to_value();
+    if let Some(Value::Obj(settings)) = doc.get("settings") {
+        for (k, v) in settings.iter().filter(|(k, _)| k != "enabled") {
+            out.insert(format!("settings.{}", k), v.clone());
+        }
+    }
+    for sc in doc
+        .get("shortcuts")
+        .and_then(Value::as_array)
+        .unwrap_or(&[])
+    {
+        if let Some(trigger) = sc.get("trigger").and_then(Value::as_str) {
+            out.insert(format!("shortcut.{}", trigger), sc.clone());
+        }
+    }
+    out
+}
+
+impl SyncDoc {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Entry> {
+        self.entries.get(key)
+    }
+
+    /// Record local state: new or changed values are stamped with
+    /// `now`/`device`, values no longer present become tombstones
+    pub fn update(&mut self, profile: &Profile, now: u64, device: &str) {
+        let current = flatten(profile);
+        let stamp = |value| Entry {
+            value,
+            modified: now,
+            device: device.to_string(),
+        };
+        for (key, entry) in self.entries.iter_mut() {
+            if entry.value.is_some() && !current.contains_key(key) {
+                *entry = stamp(None);
+            }
+        }
+        for (key, value) in current {
+            let changed = self
+                .entries
+                .get(&key)
+                .is_none_or(|e| e.value.as_ref() != Some(&value));
+            if changed {
+                self.entries.insert(key, stamp(Some(value)));
+            }
+        }
+    }
+
+    /// Take every entry of `other` that is newer than ours.
+    /// Returns how many entries changed.
+    pub fn merge(&mut self, other: &SyncDoc) -> usize {
+        let mut taken = 0;
+        for (key, theirs) in &other.entries {
+            let take = self
+                .entries
+                .get(key)
+                .is_none_or(|ours| theirs.newer_than(ours));
+            if take {
+                self.entries.insert(key.clone(), theirs.clone());
+                taken += 1;
+            }
+        }
+        taken
+    }
+
+    /// Rebuild a profile from live entries
+    ///
+    /// If the merged settings conflict (e.g. one machine switched to VNI
+    /// while another enabled Telex bracket shortcuts), `fallback` settings
+    /// are used instead; shortcuts are kept either way.
+    pub fn to_profile(&self, fallback: &EngineConfig) -> Result<Profile, ProfileError> {
+        let mut settings = Vec::new();
+        let mut shortcuts = Vec::new();
+        for (key, entry) in &self.entries {
+            let Some(value) = &entry.value else { continue };
+            if let Some(name) = key.strip_prefix("settings.") {
+                settings.push((name.to_string(), value.clone()));
+            } else if key.starts_with("shortcut.") {
+                shortcuts.push(value.clone());
+            }
+        }
+        let doc = |settings| {
+            Value::Obj(vec![
+                ("format".into(), Value::Str(PROFILE_FORMAT.into())),
+                ("version".into(), Value::Num(PROFILE_VERSION as f64)),
+                ("settings".into(), settings),
+                ("shortcuts".into(), Value::Arr(shortcuts.clone())),
+            ])
+        };
+        match Profile::from_value(&doc(Value::Obj(settings))) {
+            Err(ProfileError::Config(_)) => {
+                let local = Profile {
+                    config: fallback.clone(),
+                    shortcuts: Vec::new(),
+                };
+                let settings = local.to_value().get("settings").cloned();
+                Profile::from_value(&doc(settings.unwrap_or(Value::Obj(Vec::new()))))
+            }
+            result => result,
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        let entries = self
+            .entries
+            .iter()
+            .map(|(key, e)| {
+                let mut fields = vec![
+                    ("key".into(), Value::Str(key.clone())),
+                    ("modified".into(), Value::Num(e.modified as f64)),
+                    ("device".into(), Value::Str(e.device.clone())),
+                ];
+                match &e.value {
+                    Some(v) => fields.push(("value".into(), v.clone())),
+                    None => fields.push(("deleted".into(), Value::Bool(true))),
+                }
+                Value::Obj(fields)
+            })
+            .collect();
+        Value::Obj(vec![
+            ("format".into(), Value::Str(SYNC_FORMAT.into())),
+            ("version".into(), Value::Num(SYNC_VERSION as f64)),
+            ("entries".into(), Value::Arr(entries)),
+        ])
+        .to_pretty()
+    }
+
+    pub fn from_json(s: &str) -> Result<SyncDoc, SyncError> {
+        let bad = |msg: &str| SyncError::Format(msg.to_string());
+        let doc = json::parse(s).map_err(|e| SyncError::Format(e.to_string()))?;
+        if doc.get("format").and_then(Value::as_str) != Some(SYNC_FORMAT) {
+            return Err(bad("not a sync document"));
+        }
+        match doc.get("version").and_then(Value::as_u64) {
+            Some(v) if v <= SYNC_VERSION => {}
+            _ => return Err(bad("unsupported version")),
+        }
+        let mut entries = BTreeMap::new();
+        for e in doc
+            .get("entries")
+            .and_then(Value::as_array)
+            .ok_or_else(|| bad("missing entries"))?
+        {
+            let key = e.get("key").and_then(Value::as_str);
+            let modified = e.get("modified").and_then(Value::as_u64);
+            let device = e.get("device").and_then(Value::as_str);
+            let (Some(key), Some(modified), Some(device)) = (key, modified, device) else {
+                return Err(bad("invalid entry"));
+            };
+            let value = match e.get("deleted").and_then(Value::as_bool) {
+                Some(true) => None,
+                _ => Some(
+                    e.get("value")
+                        .cloned()
+                        .ok_or_else(|| bad("invalid entry"))?,
+                ),
+            };
+            entries.insert(
+                key.to_string(),
+                Entry {
+                    value,
+                    modified,
+                    device: device.to_string(),
+                },
+            );
+        }
+        Ok(SyncDoc { entries })
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<SyncDoc, SyncError> {
+        let s = std::str::from_utf8(bytes).map_err(|_| SyncError::Format("not UTF-8".into()))?;
+        Self::from_json(s)
+    }
+}
+
+/// Outcome of `Engine::sync`
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct SyncReport {
+    /// Entries taken from the shared document
+    pub pulled: usize,
+}
+
+// ============================================================
+// Host callbacks (FFI)
+// ============================================================
+
+/// Pull callback: copy the shared document into `buf` (capacity `cap`).
+///
+/// Returns its length (may exceed `cap`: the engine retries with a larger
+/// buffer), -1 if nothing was pushed yet, or -2 on error.
+pub type PullCallback = extern "C" fn(buf: *mut u8, cap: usize, user_data: *mut c_void) -> isize;
+
+/// Push callback: replace the shared document. Returns 0 on success.
+pub type PushCallback = extern "C" fn(data: *const u8, len: usize, user_data: *mut c_void) -> i32;
+
+/// Transport backed by host callbacks
+pub struct CallbackTransport {
+    pull: PullCallback,
+    push: PushCallback,
+    user_data: *mut c_void,
+}
+
+impl CallbackTransport {
+    pub fn new(pull: PullCallback, push: PushCallback, user_data: *mut c_void) -> Self {
+        Self {
+            pull,
+            push,
+            user_data,
+        }
+    }
+}
+
+impl SyncTransport for CallbackTransport {
+    fn pull(&mut self) -> io::Result<Option<Vec<u8>>> {
+        let mut buf = vec![0u8; 16 * 1024];
+        loop {
+            match (self.pull)(buf.as_mut_ptr(), buf.len(), self.user_data) {
+                -1 => return Ok(None),
+                n if n < 0 => return Err(io::Error::other("host pull failed")),
+                n if n as usize > buf.len() => buf.resize(n as usize, 0),
+                n => {
+                    buf.truncate(n as usize);
+                    return Ok(Some(buf));
+                }
+            }
+        }
+    }
+
+    fn push(&mut self, data: &[u8]) -> io::Result<()> {
+        match (self.push)(data.as_ptr(), data.len(), self.user_data) {
+            0 => Ok(()),
+            _ => Err(io::Error::other("host push failed")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Method;
+    use crate::engine::shortcut::Shortcut;
+
+    fn profile(method: Method, shortcuts: &[(&str, &str)]) -> Profile {
+        Profile {
+            config: EngineConfig {
+                method,
+                ..EngineConfig::default()
+            },
+            shortcuts: shortcuts.iter().map(|(t, r)| Shortcut::new(t, r)).collect(),
+        }
+    }
+
+    #[test]
+    fn test_update_stamps_only_changes() {
+        let mut doc = SyncDoc::new();
+        doc.update(&profile(Method::Telex, &[("vn", "Việt Nam")]), 100, "mac");
+        doc.update(&profile(Method::Vni, &[("vn", "Việt Nam")]), 200, "mac");
+        assert_eq!(doc.get("settings.method").unwrap().modified, 200);
+        assert_eq!(doc.get("shortcut.vn").unwrap().modified, 100);
+        assert!(doc.get("settings.enabled").is_none());
+
+        doc.update(&profile(Method::Vni, &[]), 300, "mac");
+        let vn = doc.get("shortcut.vn").unwrap();
+        assert_eq!((vn.value.is_none(), vn.modified), (true, 300));
+    }
+
+    #[test]
+    fn test_merge_last_writer_wins() {
+        let mut mac = SyncDoc::new();
+        mac.update(&profile(Method::Vni, &[("vn", "Việt Nam")]), 100, "mac");
+        let mut win = SyncDoc::new();
+        win.update(&profile(Method::Telex, &[("ko", "không")]), 200, "win");
+
+        let mut merged = mac.clone();
+        merged.merge(&win);
+        let p = merged.to_profile(&EngineConfig::default()).unwrap();
+        assert_eq!(p.config.method, Method::Telex); // win changed it later
+        assert_eq!(p.shortcuts.len(), 2);
+
+        // Merge is symmetric
+        let mut other = win.clone();
+        other.merge(&mac);
+        assert_eq!(other, merged);
+    }
+
+    #[test]
+    fn test_tombstone_beats_older_value() {
+        let mut a = SyncDoc::new();
+        a.update(&profile(Method::Telex, &[("vn", "Việt Nam")]), 100, "a");
+        let mut b = a.clone();
+        b.update(&profile(Method::Telex, &[]), 200, "b");
+        a.merge(&b);
+        let p = a.to_profile(&EngineConfig::default()).unwrap();
+        assert!(p.shortcuts.is_empty());
+    }
+
+    #[test]
+    fn test_conflicting_settings_fall_back() {
+        let mut a = SyncDoc::new();
+        let mut bracket = profile(Method::Telex, &[]);
+        bracket.config.bracket_shortcuts = true;
+        a.update(&bracket, 100, "a");
+        let mut b = SyncDoc::new();
+        b.update(&profile(Method::Vni, &[("vn", "Việt Nam")]), 50, "b");
+        // Only the method change is newer than a's settings
+        b.entries.get_mut("settings.method").unwrap().modified = 200;
+        a.merge(&b);
+
+        let p = a.to_profile(&bracket.config).unwrap();
+        assert_eq!(p.config, bracket.config);
+        assert_eq!(p.shortcuts.len(), 1);
+    }
+
+    #[test]
+    fn test_json_roundtrip() {
+        let mut doc = SyncDoc::new();
+        doc.update(&profile(Method::Vni, &[("vn", "Việt Nam")]), 100, "mac");
+        doc.update(&profile(Method::Vni, &[]), 200, "mac");
+        assert_eq!(SyncDoc::from_json(&doc.to_json()).unwrap(), doc);
+        assert!(SyncDoc::from_json("{}").is_err());
+        assert!(SyncDoc::from_bytes(&[0xFF]).is_err());
+    }
+}
@@ -0,0 +1,350 @@
+//! Tokenizer
+//!
+//! One word segmentation for everything that looks at text as words:
+//! linting, recomposing pasted text, raw-key conversion and re-seeding the
+//! engine from the text before the caret. `tokens` splits text into
+//! `Token`s without allocating:
+//!
+//! - `Word`: letters, possibly with digits ("việt", "Vie65t" as VNI
+//!   keystrokes), the only tokens worth Vietnamese analysis
+//! - `Number`: digits with `.` `,` `:` between them ("1.000", "10:30")
+//! - `Url`: `http://`, `https://`, `ftp://`, `file://`, `mailto:` and
+//!   `www.` links up to the next space, and e-mail addresses; trailing
+//!   punctuation ("(see www.a.vn).") is left out
+//! - `Identifier`: snake_case, camelCase and tokens starting with a digit
+//!   ("user_id", "isValid", "3rd"), which the engine leaves as typed
+//! - `Whitespace`: a run of whitespace
+//! - `Punctuation`: any other single character (symbols and emoji too)
+//!
+//! Hyphens and apostrophes end words, so "on-line" and "ng'ười" are two
+//! words each. Combining marks (decomposed text) stay with their letter.
+//! Spans are byte ranges into the text.
+//!
+//! Pasted text may spell words with lookalikes ("ｎàｍ", a Cyrillic "а");
+//! `fold_confusables` is the optional pass that turns them back to ASCII
+//! before tokenizing.
+
+use crate::data::chars::{self, is_combining};
+use crate::data::confusables;
+use std::borrow::Cow;
+use std::ops::Range;
+
+/// Longest local part of an e-mail address (RFC 5321)
+const MAX_EMAIL_LOCAL: usize = 64;
+
+const URL_PREFIXES: [&str; 6] = [
+    "http://", "https://", "ftp://", "file://", "mailto:", "www.",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TokenKind {
+    Word,
+    Number,
+    Url,
+    Identifier,
+    Whitespace,
+    Punctuation,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Range<usize>,
+}
+
+/// Tokens of `text`, in order; together they cover all of it
+pub fn tokens(text: &str) -> Tokens<'_> {
+    Tokens { text, pos: 0 }
+}
+
+/// Iterator returned by `tokens`
+#[derive(Clone, Debug)]
+pub struct Tokens<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl Iterator for Tokens<'_> {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Token> {
+        let rest = &self.text[self.pos..];
+        let c = rest.chars().next()?;
+        let (kind, len) = if c.is_whitespace() {
+            (TokenKind::Whitespace, run(rest, char::is_whitespace))
+        } else if let Some(len) = url_len(rest) {
+            (TokenKind::Url, len)
+        } else if c.is_alphanumeric() || c == '_' {
+            word_like(rest)
+        } else {
+            (TokenKind::Punctuation, c.len_utf8())
+        };
+        let span = self.pos..self.pos + len;
+        self.pos += len;
+        Some(Token { kind, span })
+    }
+}
+
+/// Punctuation that can hug a word: ASCII, plus the quotes, brackets and
+/// dashes of Vietnamese typography
+pub fn is_punctuation(c: char) -> bool {
+    c.is_ascii_punctuation()
+        || matches!(
+            c,
+            '“' | '”'
+                | '‘'
+                | '’'
+                | '«'
+                | '»'
+                | '‹'
+                | '›'
+                | '…'
+                | '–'
+                | '—'
+                | '（'
+                | '）'
+                | '【'
+                | '】'
+        )
+}
+
+/// `text` with fullwidth forms as ASCII ("ｘｉｎ，" → "xin,") and
+/// Cyrillic/Greek lookalikes as the Latin letters they pass for, in words
+/// that have Latin letters too ("nàm" with a Cyrillic "а"; "сом" stays)
+///
+/// Borrows `text` when there is nothing to fold.
+pub fn fold_confusables(text: &str) -> Cow<'_, str> {
+    let fold = |c| confusables::fullwidth(c).or_else(|| confusables::lookalike(c));
+    if !text.chars().any(|c| fold(c).is_some()) {
+        return Cow::Borrowed(text);
+    }
+    let wide: String = text
+        .chars()
+        .map(|c| confusables::fullwidth(c).unwrap_or(c))
+        .collect();
+    let mut out = String::with_capacity(wide.len());
+    for t in tokens(&wide) {
+        let token = &wide[t.span];
+        let latin = token
+            .chars()
+            .any(|c| confusables::lookalike(c).is_none() && chars::parse_char(c).is_some());
+        if !latin {
+            out.push_str(token);
+            continue;
+        }
+        out.extend(
+            token
+                .chars()
+                .map(|c| confusables::lookalike(c).unwrap_or(c)),
+        );
+    }
+    Cow::Owned(out)
+}
+
+/// Length of the leading run of chars matching `f`
+fn run(s: &str, f: impl Fn(char) -> bool) -> usize {
+    s.find(|c| !f(c)).unwrap_or(s.len())
+}
+
+/// Word, number or identifier at the start of `s`
+fn word_like(s: &str) -> (TokenKind, usize) {
+    let len = run(s, |c| c.is_alphanumeric() || c == '_' || is_combining(c));
+    let token = &s[..len];
+    if token.starts_with(|c: char| c.is_ascii_digit()) {
+        if token.bytes().all(|b| b.is_ascii_digit()) {
+            return (TokenKind::Number, number_len(s));
+        }
+        return (TokenKind::Identifier, len);
+    }
+    if token.contains('_') || has_hump(token) {
+        return (TokenKind::Identifier, len);
+    }
+    (TokenKind::Word, len)
+}
+
+/// A lowercase letter followed by a capital ("isValid")
+fn has_hump(token: &str) -> bool {
+    let mut prev_lower = false;
+    for c in token.chars() {
+        if prev_lower && c.is_uppercase() {
+            return true;
+        }
+        prev_lower = c.is_lowercase();
+    }
+    false
+}
+
+/// Digits with single separators between them
+fn number_len(s: &str) -> usize {
+    let bytes = s.as_bytes();
+    let mut len = run(s, |c| c.is_ascii_digit());
+    while let [b'.' | b',' | b':', next, ..] = bytes[len..] {
+        if !next.is_ascii_digit() {
+            break;
+        }
+        len += 1 + run(&s[len + 1..], |c| c.is_ascii_digit());
+    }
+    len
+}
+
+/// Length of the link or e-mail address at the start of `s`, if any
+fn url_len(s: &str) -> Option<usize> {
+    let is_link = URL_PREFIXES.iter().any(|p| {
+        s.get(..p.len())
+            .is_some_and(|head| head.eq_ignore_ascii_case(p))
+            && s.len() > p.len()
+    });
+    let len = if is_link {
+        run(s, |c| !c.is_whitespace())
+    } else {
+        email_len(s)?
+    };
+    let trimmed = s[..len].trim_end_matches(is_punctuation).len();
+    // Keep a closing bracket that has its opening one in the link
+    // ("wiki/Foo_(bar)")
+    let len = match &s[trimmed..len] {
+        tail if tail.starts_with(')') && s[..trimmed].contains('(') => trimmed + 1,
+        _ => trimmed,
+    };
+    (len > 0).then_some(len)
+}
+
+/// Length of the e-mail address at the start of `s`, if any
+fn email_len(s: &str) -> Option<usize> {
+    let local = s
+        .char_indices()
+        .take(MAX_EMAIL_LOCAL + 1)
+        .find(|&(_, c)| !(c.is_alphanumeric() || matches!(c, '.' | '_' | '%' | '+' | '-')))
+        .map(|(i, _)| i)?;
+    if local == 0 || !s[local..].starts_with('@') {
+        return None;
+    }
+    let domain = &s[local + 1..];
+    let len = run(domain, |c| c.is_alphanumeric() || matches!(c, '.' | '-'));
+    let host = domain[..len].trim_end_matches('.');
+    let dot = host.find('.')?;
+    (dot > 0).then_some(local + 1 + host.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(text: &str) -> Vec<(&str, TokenKind)> {
+        tokens(text)
+            .filter(|t| t.kind != TokenKind::Whitespace)
+            .map(|t| (&text[t.span], t.kind))
+            .collect()
+    }
+
+    #[test]
+    fn test_covers_text() {
+        let text = "Xin chào, user_id=42 — xem https://gonhanh.org!";
+        let mut end = 0;
+        for t in tokens(text) {
+            assert_eq!(t.span.start, end);
+            end = t.span.end;
+        }
+        assert_eq!(end, text.len());
+        assert_eq!(tokens("").next(), None);
+    }
+
+    #[test]
+    fn test_words() {
+        use TokenKind::*;
+        assert_eq!(
+            kinds("Tiếng Việt, on-line ng'ười Vie65t"),
+            [
+                ("Tiếng", Word),
+                ("Việt", Word),
+                (",", Punctuation),
+                ("on", Word),
+                ("-", Punctuation),
+                ("line", Word),
+                ("ng", Word),
+                ("'", Punctuation),
+                ("ười", Word),
+                ("Vie65t", Word),
+            ]
+        );
+        assert_eq!(
+            kinds("Vie\u{0323}\u{0302}t nam"),
+            [("Vie\u{0323}\u{0302}t", Word), ("nam", Word)]
+        );
+    }
+
+    #[test]
+    fn test_numbers_and_identifiers() {
+        use TokenKind::*;
+        assert_eq!(
+            kinds("1.000,5 lúc 10:30. user_id isValid 3rd _x"),
+            [
+                ("1.000,5", Number),
+                ("lúc", Word),
+                ("10:30", Number),
+                (".", Punctuation),
+                ("user_id", Identifier),
+                ("isValid", Identifier),
+                ("3rd", Identifier),
+                ("_x", Identifier),
+            ]
+        );
+        assert_eq!(kinds("2024."), [("2024", Number), (".", Punctuation)]);
+    }
+
+    #[test]
+    fn test_urls() {
+        use TokenKind::*;
+        assert_eq!(
+            kinds("(xem www.vnexpress.net/tin-tuc)."),
+            [
+                ("(", Punctuation),
+                ("xem", Word),
+                ("www.vnexpress.net/tin-tuc", Url),
+                (")", Punctuation),
+                (".", Punctuation),
+            ]
+        );
+        assert_eq!(
+            kinds("HTTPS://vi.wikipedia.org/wiki/Lý_(họ)"),
+            [("HTTPS://vi.wikipedia.org/wiki/Lý_(họ)", Url)]
+        );
+        assert_eq!(
+            kinds("gửi lan.nguyen@mail.vn."),
+            [
+                ("gửi", Word),
+                ("lan.nguyen@mail.vn", Url),
+                (".", Punctuation)
+            ]
+        );
+        // Not quite
+        assert_eq!(kinds("www."), [("www", Word), (".", Punctuation)]);
+        assert_eq!(
+            kinds("a@b @user"),
+            [
+                ("a", Word),
+                ("@", Punctuation),
+                ("b", Word),
+                ("@", Punctuation),
+                ("user", Word)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fold_confusables() {
+        assert!(matches!(fold_confusables("Việt Nam"), Cow::Borrowed(_)));
+        assert_eq!(fold_confusables("ｘｉｎ　ｃｈàｏ，"), "xin chào,");
+        // Cyrillic а, о and Greek Ο among Latin letters
+        assert_eq!(fold_confusables("nàm v\u{0430}y, ĐΟ"), "nàm vay, ĐO");
+        assert_eq!(fold_confusables("t\u{043E}\u{0301}t"), "to\u{0301}t");
+        // Words of only Cyrillic or Greek are real words
+        assert_eq!(fold_confusables("сом и Οκ"), "сом и Οκ");
+    }
+
+    #[test]
+    fn test_punctuation() {
+        assert!(is_punctuation('“') && is_punctuation(','));
+        assert!(!is_punctuation('a') && !is_punctuation(' '));
+    }
+}
@@ -54,6 +54,15 @@ pub fn key_to_char(key: u16, caps: bool) -> Option<char> {
     Some(if caps { ch.to_ascii_uppercase() } else { ch })
 }
 
+/// Message of a panic caught with `catch_unwind`
+pub fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_default()
+}
+
 /// Collect vowels from buffer with phonological info
 pub fn collect_vowels(buf: &Buffer) -> Vec<Vowel> {
     buf.iter()
@@ -276,7 +285,7 @@ mod test_utils {
     pub fn vni(cases: &[(&str, &str)]) {
         for (input, expected) in cases {
             let mut e = Engine::new();
-            e.set_method(1);
+            e.set_method(1).unwrap();
             let result = type_word(&mut e, input);
             assert_eq!(result, *expected, "[VNI] '{}' → '{}'", input, result);
         }
@@ -288,6 +297,8 @@ mod test_utils {
     /// - "#" triggers Shift+3
     /// - ":" triggers Shift+;
     /// - "/" triggers SLASH (no shift)
+    /// - "_" triggers Shift+-
+    /// - "!", "(", ")" trigger Shift+1, Shift+9, Shift+0
     pub fn type_word_ext(e: &mut Engine, input: &str) -> String {
         let mut screen = String::new();
         for c in input.chars() {
@@ -297,6 +308,10 @@ mod test_utils {
                 '#' => (keys::N3, true),
                 ':' => (keys::SEMICOLON, true),
                 '/' => (keys::SLASH, false), // / doesn't need shift
+                '_' => (keys::MINUS, true),
+                '!' => (keys::N1, true),
+                '(' => (keys::N9, true),
+                ')' => (keys::N0, true),
                 _ => (char_to_key(c), false),
             };
 
@@ -350,8 +365,9 @@ mod test_utils {
                         screen.push(ch);
                     }
                 }
-                // For break keys (punctuation), add the character after auto-restore
-                if keys::is_break(key) {
+                // For break keys (punctuation) and shifted symbols, add the
+                // character after auto-restore
+                if keys::is_break(key) || shift {
                     screen.push(c);
                 }
             } else {
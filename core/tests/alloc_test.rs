@@ -0,0 +1,140 @@
+//! Allocation Tests - keys that must not touch the heap
+//!
+//! Needs the `count-allocs` feature:
+//! `cargo test --features count-allocs --test alloc_test`
+
+use gonhanh_core::alloc_count::{allocs_during, CountingAllocator};
+use gonhanh_core::config::OverflowPolicy;
+use gonhanh_core::data::keys;
+use gonhanh_core::engine::{Engine, Output};
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+fn key(c: char) -> u16 {
+    keys::from_char(c).unwrap()
+}
+
+// ============================================================
+// FAST PATH
+// ============================================================
+
+#[test]
+fn plain_letters_do_not_allocate() {
+    for method in [0, 1] {
+        let mut e = Engine::new();
+        e.set_method(method).unwrap();
+        for word in ["ban", "xin", "chung", "thanh", "nghieng", "string"] {
+            let n = allocs_during(|| {
+                for c in word.chars() {
+                    assert_eq!(
+                        e.process_key(key(c), false, false, false),
+                        Output::PassThrough
+                    );
+                }
+            });
+            assert_eq!(n, 0, "'{}' allocated {} time(s)", word, n);
+            e.clear();
+        }
+    }
+}
+
+#[test]
+fn long_token_does_not_allocate() {
+    for policy in [OverflowPolicy::PassThrough, OverflowPolicy::KeepTail] {
+        let mut e = Engine::new();
+        e.set_overflow_policy(policy);
+        let n = allocs_during(|| {
+            for c in "bcghklmnpqtv".chars().cycle().take(10_000) {
+                e.process_key(key(c), false, false, false);
+            }
+        });
+        assert_eq!(n, 0, "{:?} allocated {} time(s)", policy, n);
+    }
+}
+
+#[test]
+fn replace_reuses_output_buffer() {
+    let mut e = Engine::new();
+    for c in "chuo".chars() {
+        e.process_key(key(c), false, false, false);
+    }
+    assert_eq!(
+        e.process_key(keys::W, false, false, false),
+        Output::Replace {
+            backspace: 2,
+            text: "ươ"
+        }
+    );
+    e.clear();
+
+    // Bracket vowel inserts without deleting anything
+    e.set_bracket_shortcuts(true).unwrap();
+    assert_eq!(
+        e.process_key(keys::LBRACKET, false, false, false),
+        Output::Append('ơ')
+    );
+}
+
+#[test]
+fn preview_reuses_its_buffer() {
+    let mut e = Engine::new();
+    for c in "nghieng".chars() {
+        e.process_key(key(c), false, false, false);
+    }
+    let n = allocs_during(|| {
+        assert_eq!(e.current_preview(), "nghieng");
+    });
+    assert_eq!(n, 0, "preview allocated {} time(s)", n);
+}
+
+// ============================================================
+// BUDGETS
+// ============================================================
+
+/// Transforms (marks, tones) build a few small strings; a word's keys
+/// together stay under one allocation per key
+const TRANSFORM_BUDGET_PER_KEY: usize = 1;
+/// The boundary key records the word (history, counts, shortcuts,
+/// autocorrect lookups)
+const BOUNDARY_BUDGET: usize = 16;
+
+#[test]
+fn marked_words_stay_under_budget() {
+    use gonhanh_core::engine::shortcut::Shortcut;
+
+    let mut e = Engine::new();
+    e.shortcuts_mut().add(Shortcut::new("vn", "Việt Nam"));
+    let words = ["vieetj", "nguwowif", "thuowng", "hoaf", "vn", "string"];
+    // Warm up: first use of each subsystem may size its buffers
+    for word in words {
+        for c in word.chars() {
+            e.process_key(key(c), false, false, false);
+        }
+        e.process_key(keys::SPACE, false, false, false);
+    }
+    for word in words {
+        let n = allocs_during(|| {
+            for c in word.chars() {
+                e.process_key(key(c), false, false, false);
+            }
+        });
+        let budget = TRANSFORM_BUDGET_PER_KEY * word.len();
+        assert!(
+            n <= budget,
+            "'{}' allocated {} time(s), budget {}",
+            word,
+            n,
+            budget
+        );
+        let n = allocs_during(|| {
+            e.process_key(keys::SPACE, false, false, false);
+        });
+        assert!(
+            n <= BOUNDARY_BUDGET,
+            "space after '{}' allocated {} time(s)",
+            word,
+            n
+        );
+    }
+}
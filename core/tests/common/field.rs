@@ -0,0 +1,193 @@
+//! Virtual text field - a simulated host for end-to-end tests
+//!
+//! Plays the platform layer: sends keys to the engine, applies what comes
+//! back (backspaces + text, or select-and-replace) at the caret, and types
+//! pass-through keys itself. Caret moves and focus changes are reported
+//! the way a host should. Tests then check the visible text, which is what
+//! the user actually sees.
+//!
+//! Input strings use the `type_word` conventions: `<` = Backspace,
+//! `\x1b` = Esc, uppercase = Caps.
+
+use gonhanh_core::data::keys;
+use gonhanh_core::engine::{Action, Engine};
+use gonhanh_core::utils::char_to_key;
+
+pub struct VirtualField {
+    engine: Engine,
+    text: Vec<char>,
+    /// Caret as a char index into `text`
+    caret: usize,
+    /// App drops synthetic backspaces (see `swallow_backspaces`)
+    swallow: bool,
+}
+
+impl VirtualField {
+    /// Empty, focused field
+    pub fn new(engine: Engine) -> Self {
+        let mut field = Self {
+            engine,
+            text: Vec::new(),
+            caret: 0,
+            swallow: false,
+        };
+        field.engine.set_caret(Some(0));
+        field
+    }
+
+    pub fn engine(&mut self) -> &mut Engine {
+        &mut self.engine
+    }
+
+    pub fn text(&self) -> String {
+        self.text.iter().collect()
+    }
+
+    pub fn caret(&self) -> usize {
+        self.caret
+    }
+
+    /// Behave like an app that silently drops synthetic backspaces
+    pub fn swallow_backspaces(&mut self) -> &mut Self {
+        self.swallow = true;
+        self
+    }
+
+    /// Text before the caret, as a host would read it for `check_field`
+    pub fn prefix(&self) -> String {
+        self.text[..self.caret].iter().collect()
+    }
+
+    /// Type each character as a key press
+    pub fn type_str(&mut self, input: &str) -> &mut Self {
+        for c in input.chars() {
+            let key = char_to_key(c);
+            assert_ne!(key, 255, "no key for {:?}", c);
+            self.key(key, c.is_uppercase(), Some(c));
+        }
+        self
+    }
+
+    /// Arrow left `n` times
+    pub fn left(&mut self, n: usize) -> &mut Self {
+        for _ in 0..n {
+            self.key(keys::LEFT, false, None);
+        }
+        self
+    }
+
+    /// Arrow right `n` times
+    pub fn right(&mut self, n: usize) -> &mut Self {
+        for _ in 0..n {
+            self.key(keys::RIGHT, false, None);
+        }
+        self
+    }
+
+    /// Click at `pos` (char index)
+    pub fn click(&mut self, pos: usize) -> &mut Self {
+        self.caret = pos.min(self.text.len());
+        let offset = self.utf16(self.caret);
+        self.engine.caret_moved(Some(offset));
+        self
+    }
+
+    /// Click, then hand the engine the text before the caret
+    pub fn click_and_seed(&mut self, pos: usize) -> &mut Self {
+        self.click(pos);
+        let prefix: String = self.text[..self.caret].iter().collect();
+        self.engine.text_context(&prefix);
+        self
+    }
+
+    /// Focus another app
+    pub fn blur(&mut self) -> &mut Self {
+        self.engine.focus_changed();
+        self
+    }
+
+    /// Focus comes back; the host reads the caret again
+    pub fn focus(&mut self) -> &mut Self {
+        self.engine.focus_changed();
+        let offset = self.utf16(self.caret);
+        self.engine.set_caret(Some(offset));
+        self
+    }
+
+    /// Press `key`; `typed` is what the app inserts if the key passes through
+    pub fn key(&mut self, key: u16, caps: bool, typed: Option<char>) -> &mut Self {
+        let r = self.engine.on_key(key, caps, false);
+        let text: Vec<char> = r.chars[..r.count as usize]
+            .iter()
+            .filter_map(|&c| char::from_u32(c))
+            .collect();
+
+        if r.action == Action::Select as u8 {
+            let range = self.engine.edit_range().expect("select without a range");
+            let (start, end) = (self.char_index(range.start), self.char_index(range.end));
+            assert_eq!(end, self.caret, "edit must end at the caret");
+            self.text.splice(start..end, text.iter().copied());
+            self.caret = start + text.len();
+        } else if r.action == Action::Send as u8 {
+            let start = if self.swallow {
+                self.caret
+            } else {
+                self.caret - r.backspace as usize
+            };
+            self.text.splice(start..self.caret, text.iter().copied());
+            self.caret = start + text.len();
+        }
+
+        let consumed =
+            r.action != Action::None as u8 && (key == keys::SPACE || !keys::is_break(key));
+        if !consumed {
+            match key {
+                keys::DELETE if self.caret > 0 => {
+                    self.caret -= 1;
+                    self.text.remove(self.caret);
+                }
+                keys::LEFT => self.caret = self.caret.saturating_sub(1),
+                keys::RIGHT => self.caret = (self.caret + 1).min(self.text.len()),
+                _ => {
+                    if let Some(c) = typed.filter(|_| key != keys::ESC && key != keys::DELETE) {
+                        self.text.insert(self.caret, c);
+                        self.caret += 1;
+                    }
+                }
+            }
+        }
+
+        // Arrows lose the engine's caret; a host reports the new one
+        if self.engine.caret().is_none() {
+            let offset = self.utf16(self.caret);
+            self.engine.caret_moved(Some(offset));
+        }
+        // Lost backspaces are exactly what the engine can't see
+        if !self.swallow {
+            assert_eq!(
+                self.engine.caret(),
+                Some(self.utf16(self.caret)),
+                "engine lost track of the caret in {:?}",
+                self.text()
+            );
+        }
+        self
+    }
+
+    /// UTF-16 offset of char index `pos`
+    fn utf16(&self, pos: usize) -> usize {
+        self.text[..pos].iter().map(|c| c.len_utf16()).sum()
+    }
+
+    /// Char index of UTF-16 offset `offset`
+    fn char_index(&self, offset: usize) -> usize {
+        let mut units = 0;
+        for (i, c) in self.text.iter().enumerate() {
+            if units >= offset {
+                return i;
+            }
+            units += c.len_utf16();
+        }
+        self.text.len()
+    }
+}
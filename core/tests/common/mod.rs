@@ -6,7 +6,10 @@
 #![allow(dead_code)]
 #![allow(unused_imports)]
 
+pub mod field;
+
 // Re-export core test utilities
+pub use field::VirtualField;
 pub use gonhanh_core::utils::{telex, type_word, vni};
 
 use gonhanh_core::engine::{Action, Engine};
@@ -46,7 +49,7 @@ pub fn engine_telex() -> Engine {
 
 pub fn engine_vni() -> Engine {
     let mut e = Engine::new();
-    e.set_method(1);
+    e.set_method(1).unwrap();
     e
 }
 
@@ -343,10 +343,190 @@ fn revert_stroke_double_key() {
 
 #[test]
 fn triple_same_key() {
-    // aaaa → aâ
+    // aaaa → aaa: once undone, the key stays literal
     let mut e = Engine::new();
     let result = common::type_word(&mut e, "aaaa");
-    assert_eq!(result, "aâ");
+    assert_eq!(result, "aaa");
+}
+
+/// Repeated modifier keys, per the state machine in `data::vowel`:
+/// modify, undo, then stay literal
+fn repeated_modifier_cases(
+    modifiers: &[(&str, &str, &str)],
+    prefixes: &[&str],
+) -> Vec<(String, String)> {
+    let mut cases = Vec::new();
+    for &(vowel, key, modified) in modifiers {
+        for prefix in prefixes {
+            cases.push((
+                format!("{prefix}{vowel}{key}"),
+                format!("{prefix}{modified}"),
+            ));
+            for extra in 1..=3 {
+                let keys = key.repeat(extra);
+                cases.push((
+                    format!("{prefix}{vowel}{key}{keys}"),
+                    format!("{prefix}{vowel}{keys}"),
+                ));
+            }
+        }
+    }
+    cases
+}
+
+fn as_refs(cases: &[(String, String)]) -> Vec<(&str, &str)> {
+    cases
+        .iter()
+        .map(|(i, o)| (i.as_str(), o.as_str()))
+        .collect()
+}
+
+#[test]
+fn repeated_modifier_telex() {
+    let modifiers = [
+        ("a", "a", "â"),
+        ("e", "e", "ê"),
+        ("o", "o", "ô"),
+        ("a", "w", "ă"),
+        ("o", "w", "ơ"),
+        ("u", "w", "ư"),
+    ];
+    let cases = repeated_modifier_cases(&modifiers, &["", "t", "m", "th"]);
+    telex(&as_refs(&cases));
+}
+
+#[test]
+fn repeated_modifier_vni() {
+    let modifiers = [
+        ("a", "6", "â"),
+        ("e", "6", "ê"),
+        ("o", "6", "ô"),
+        ("a", "8", "ă"),
+        ("o", "7", "ơ"),
+        ("u", "7", "ư"),
+    ];
+    let cases = repeated_modifier_cases(&modifiers, &["", "t", "m", "th"]);
+    vni(&as_refs(&cases));
+}
+
+#[test]
+fn repeated_modifier_switches() {
+    // Another modifier for the same vowel: last key wins
+    telex(&[
+        ("oow", "ơ"),
+        ("owo", "ô"),
+        ("oowo", "ô"),
+        ("aaw", "ă"),
+        ("awa", "â"),
+        ("awaw", "ă"),
+        ("toow", "tơ"),
+    ]);
+    vni(&[
+        ("o67", "ơ"),
+        ("o76", "ô"),
+        ("a68", "ă"),
+        ("a86", "â"),
+        ("a868", "ă"),
+    ]);
+}
+
+#[test]
+fn repeated_modifier_compound() {
+    // ươ is one nucleus: the key undoes both horns
+    telex(&[
+        ("tuow", "tươ"),
+        ("tuoww", "tuow"),
+        ("tuowww", "tuoww"),
+        ("tuwow", "tươ"),
+        ("tuwoww", "tuow"),
+    ]);
+    vni(&[
+        ("tuo7", "tươ"),
+        ("tuo77", "tuo7"),
+        ("tuo777", "tuo77"),
+        ("tu7o77", "tuo7"),
+    ]);
+}
+
+#[test]
+fn repeated_modifier_not_right_away() {
+    // Only an immediate repeat undoes; marks survive the undo
+    telex(&[
+        ("caaca", "câca"),
+        ("coomo", "cômo"),
+        ("asaa", "áa"),
+        ("asaaa", "áaa"),
+        ("oosw", "ớ"),
+    ]);
+}
+
+// ============================================================
+// UO COMPOUND: One horn key for ươ
+// ============================================================
+
+/// Every ươ rhyme, typed with a single horn key after "uo"
+const TELEX_UO_COMPOUND: &[(&str, &str)] = &[
+    ("nuowcs", "nước"),
+    ("muowif", "mười"),
+    ("buowms", "bướm"),
+    ("luown", "lươn"),
+    ("thuowng", "thương"),
+    ("cuowps", "cướp"),
+    ("muowtj", "mượt"),
+    ("ruowuj", "rượu"),
+    ("dduowngf", "đường"),
+    ("nguowif", "người"),
+    // Horn key typed after the final
+    ("nuocws", "nước"),
+    ("thuongw", "thương"),
+    // w as ư, then o
+    ("nwocs", "nước"),
+    // The u of "qu" is part of the initial
+    ("quow", "quơ"),
+    ("quowr", "quở"),
+];
+
+const VNI_UO_COMPOUND: &[(&str, &str)] = &[
+    ("nuo7c1", "nước"),
+    ("muo7i2", "mười"),
+    ("buo7m1", "bướm"),
+    ("luo7n", "lươn"),
+    ("thuo7ng", "thương"),
+    ("cuo7p1", "cướp"),
+    ("muo7t5", "mượt"),
+    ("ruo7u5", "rượu"),
+    ("d9uo7ng2", "đường"),
+    ("nguo7i2", "người"),
+    ("quo7", "quơ"),
+];
+
+#[test]
+fn uo_compound_telex() {
+    telex(TELEX_UO_COMPOUND);
+}
+
+#[test]
+fn uo_compound_vni() {
+    vni(VNI_UO_COMPOUND);
+}
+
+#[test]
+fn uo_compound_disabled() {
+    let typed = |method: u8, input: &str| {
+        let mut e = Engine::new();
+        e.set_method(method).unwrap();
+        e.set_uo_compound(false);
+        common::type_word(&mut e, input)
+    };
+    // One horn key, one horn
+    assert_eq!(typed(0, "huow"), "huơ");
+    assert_eq!(typed(0, "thuowr"), "thuở");
+    assert_eq!(typed(0, "wo"), "ưo");
+    assert_eq!(typed(1, "huo7"), "huơ");
+    // A horn on each vowel still gives ươ
+    assert_eq!(typed(0, "nuwowcs"), "nước");
+    assert_eq!(typed(0, "dduwowngf"), "đường");
+    assert_eq!(typed(1, "nu7o7c1"), "nước");
 }
 
 // ============================================================
@@ -17,6 +17,10 @@
 //! 4. **Vowel + modifier + vowel (no initial)**: "use" (u+s+e)
 //! 5. **W at start + consonant or later W**: "window", "wow"
 //! 6. **Invalid Vietnamese initial (F)**: "fair", "fix"
+//! 7. **English suffix**: "missing" (a doubled S undid the tone, "mising"
+//!    isn't a syllable, and "-ing" is English)
+//! 8. **Letter bigrams**: "passed" ("pased" is no syllable, and its letter
+//!    pairs are far more common in English than in Telex keystrokes)
 //!
 //! # Limitations
 //!
@@ -25,10 +29,13 @@
 //! - "mix" → "mĩ" (M is valid initial, ĩ is valid)
 //! - "box" → "bõ" (B is valid initial, õ is valid)
 //!
-//! Users should use raw mode (\word) or Esc to restore these manually.
+//! `AutoCorrectMode::All` restores these from the embedded English lexicon;
+//! with the default `Restore`, users should use raw mode (\word) or Esc.
 
 mod common;
-use common::telex;
+use common::{telex, type_word, vni};
+use gonhanh_core::config::AutoCorrectMode;
+use gonhanh_core::engine::Engine;
 
 // =============================================================================
 // PATTERN 1: MODIFIER FOLLOWED BY CONSONANT
@@ -500,6 +507,43 @@ fn vietnamese_common_words_preserved() {
     ]);
 }
 
+// =============================================================================
+// STOP FINALS: p, t, c, ch take only sắc or nặng
+// =============================================================================
+
+#[test]
+fn stop_final_takes_sac_or_nang() {
+    telex(&[
+        ("hopj ", "họp "),
+        ("hocj ", "học "),
+        ("hats ", "hát "),
+        ("sachs ", "sách "),
+        ("thichs ", "thích "),
+        ("ddepj ", "đẹp "),
+        ("toots ", "tốt "),
+        ("vieetj ", "việt "),
+        ("nuwowcs ", "nước "),
+    ]);
+    vni(&[("hop5 ", "họp "), ("ho5p ", "họp "), ("sach1 ", "sách ")]);
+}
+
+#[test]
+fn stop_final_rejects_other_marks() {
+    telex(&[
+        ("hopf ", "hopf "),
+        ("hocr ", "hocr "),
+        ("matx ", "matx "),
+        ("thichf ", "thichf "),
+    ]);
+    vni(&[("hop2 ", "hop2 "), ("hoc3 ", "hoc3 ")]);
+}
+
+#[test]
+fn stop_final_after_other_mark_restores() {
+    telex(&[("hofp ", "hofp "), ("horc ", "horc "), ("saxch ", "saxch ")]);
+    vni(&[("ho2p ", "ho2p ")]);
+}
+
 // =============================================================================
 // EXPANDED ENGLISH RESTORE TESTS - COMMON PROGRAMMING TERMS
 // =============================================================================
@@ -565,3 +609,107 @@ fn same_vowel_pattern_vietnamese() {
         ("mere ", "mể "),     // m + e + r + e → mể (valid structure)
     ]);
 }
+
+// =============================================================================
+// DICTIONARY MODE: AutoCorrectMode::All
+// Lexicon words restore even when they compose valid Vietnamese
+// =============================================================================
+
+fn engine_all() -> Engine {
+    let mut e = Engine::new();
+    e.set_auto_correct(AutoCorrectMode::All);
+    e
+}
+
+#[test]
+fn dictionary_mode_restores_lexicon_words() {
+    for (input, expected) in [
+        ("mix ", "mix "),
+        ("box ", "box "),
+        ("boss ", "boss "),
+        ("data ", "data "),
+        ("Miss ", "Miss "),
+        ("poor, ", "poor, "),
+    ] {
+        let mut e = engine_all();
+        assert_eq!(type_word(&mut e, input), expected, "[All] '{}'", input);
+    }
+}
+
+#[test]
+fn dictionary_mode_keeps_vietnamese() {
+    for (input, expected) in [
+        ("max ", "mã "),
+        ("there ", "thể "),
+        ("six ", "sĩ "),
+        ("vieetj ", "việt "),
+    ] {
+        let mut e = engine_all();
+        assert_eq!(type_word(&mut e, input), expected, "[All] '{}'", input);
+    }
+}
+
+#[test]
+fn dictionary_mode_restores_whole_lexicon() {
+    for word in gonhanh_core::data::lexicon::ENGLISH.words() {
+        let mut e = engine_all();
+        let input = format!("{} ", word);
+        assert_eq!(type_word(&mut e, &input), input, "[All] '{}'", word);
+    }
+}
+
+#[test]
+fn restore_mode_ignores_lexicon() {
+    telex(&[("mix ", "mĩ "), ("box ", "bõ ")]);
+}
+
+// =============================================================================
+// ENGLISH SUFFIX AFTER A DOUBLED TONE KEY
+// "ss" undoes the tone and leaves no transform ("mising"); the suffix tells
+// =============================================================================
+
+#[test]
+fn english_suffix_restores() {
+    telex(&[
+        ("missing ", "missing "),
+        ("kissing ", "kissing "),
+        ("session ", "session "),
+        ("possession ", "possession "),
+        ("assessment ", "assessment "),
+        ("bosses ", "bosses "),
+        ("passable ", "passable "),
+        ("offering, ", "offering, "),
+        ("terrible ", "terrible "),
+        ("massive ", "massive "),
+    ]);
+}
+
+#[test]
+fn english_suffix_keeps_syllables() {
+    // Ends like a suffix but composes a Vietnamese syllable
+    telex(&[("sees ", "sế "), ("chees ", "chế "), ("khees ", "khế ")]);
+}
+
+// =============================================================================
+// LETTER BIGRAMS AS A TIE-BREAKER
+// No syllable, no pattern, no suffix: the letter pairs read as English
+// =============================================================================
+
+#[test]
+fn bigram_restores_english_letters() {
+    telex(&[
+        ("passed ", "passed "),
+        ("issue ", "issue "),
+        ("correct ", "correct "),
+        ("different ", "different "),
+    ]);
+}
+
+#[test]
+fn bigram_threshold_off_keeps_keystroke_result() {
+    for (input, expected) in [("passed ", "pased "), ("correct ", "corect ")] {
+        let mut e = Engine::new();
+        e.set_bigram_threshold(0);
+        assert_eq!(type_word(&mut e, input), expected, "'{}'", input);
+    }
+}
@@ -16,7 +16,7 @@ use gonhanh_core::engine::Engine;
 
 fn assert_no_transform(words: &[&str]) {
     let mut telex = Engine::new();
-    telex.set_method(0);
+    telex.set_method(0).unwrap();
 
     for word in words {
         telex.clear();
@@ -0,0 +1,154 @@
+//! Field Tests - End-to-end scenarios against the visible text
+//!
+//! Each test drives a `VirtualField` the way a user would (typing, Esc,
+//! arrows, clicks, app switches) and checks what ends up on screen.
+
+mod common;
+use common::VirtualField;
+use gonhanh_core::engine::output::{Delivery, FieldCheck, OutputProfile};
+use gonhanh_core::engine::Engine;
+
+fn field() -> VirtualField {
+    VirtualField::new(Engine::new())
+}
+
+/// Same field with each output profile a host may pick (with
+/// `OutputProfile::BOUNDARY` a word only shows composed once it's ended)
+fn fields() -> Vec<VirtualField> {
+    let select = OutputProfile {
+        delivery: Delivery::Select,
+        ..OutputProfile::default()
+    };
+    [OutputProfile::default(), select, OutputProfile::BOUNDARY]
+        .into_iter()
+        .map(|profile| {
+            let mut e = Engine::new();
+            e.set_output_profile(profile);
+            VirtualField::new(e)
+        })
+        .collect()
+}
+
+// ============================================================
+// TYPING: Autocorrect, restore, Esc
+// ============================================================
+
+#[test]
+#[cfg(feature = "autocorrect")]
+fn sentence_with_english_words() {
+    for mut f in fields() {
+        f.type_str("tooi test cais expect nayf, ok.");
+        assert_eq!(f.text(), "tôi test cái expect này, ok.");
+    }
+}
+
+#[test]
+fn esc_restores_then_typing_continues() {
+    for mut f in fields() {
+        f.type_str("vieetj\x1b laf tieengs Vieetj.");
+        assert_eq!(f.text(), "vieetj là tiếng Việt.");
+    }
+}
+
+#[test]
+fn backspace_then_retype() {
+    // Deferred words are given up on Backspace (see `OutputProfile::BOUNDARY`)
+    for mut f in fields().into_iter().take(2) {
+        f.type_str("tieengs<<<eengs vieet ");
+        assert_eq!(f.text(), "tiếng viêt ");
+    }
+}
+
+// ============================================================
+// CARET: Arrows and clicks never compose across a jump
+// ============================================================
+
+#[test]
+fn click_away_starts_new_word() {
+    let mut f = field();
+    f.type_str("vie").click(0).type_str("e");
+    // Without the jump event, "e" would turn "vie" into "viê"
+    assert_eq!(f.text(), "evie");
+    assert_eq!(f.caret(), 1);
+}
+
+#[test]
+fn arrow_keys_end_word() {
+    let mut f = field();
+    f.type_str("ab").left(1).type_str("s");
+    assert_eq!(f.text(), "asb");
+    f.right(1).type_str(" dd");
+    assert_eq!(f.text(), "asb đ");
+}
+
+#[test]
+fn edit_in_middle_of_text() {
+    for mut f in fields() {
+        f.type_str("xin chaof vieetj nam");
+        f.click(4).type_str("banj ");
+        assert_eq!(f.text(), "xin bạn chào việt nam");
+    }
+}
+
+#[test]
+fn click_into_word_and_continue() {
+    let mut f = field();
+    f.type_str("vieet nam");
+    f.click_and_seed(4).type_str("j");
+    assert_eq!(f.text(), "việt nam");
+
+    // Nothing to continue after a space
+    f.click_and_seed(5).type_str("s");
+    assert_eq!(f.text(), "việt snam");
+}
+
+// ============================================================
+// FOCUS: App switches
+// ============================================================
+
+#[test]
+fn focus_change_ends_word() {
+    for mut f in fields() {
+        f.type_str("vie").blur().focus().type_str("e");
+        assert_eq!(f.text(), "viee");
+    }
+}
+
+// ============================================================
+// GHOST EDITS: Apps that drop backspaces
+// ============================================================
+
+#[test]
+fn healthy_field_always_matches() {
+    for mut f in fields() {
+        for c in "vieetj ddaay".chars() {
+            f.type_str(&c.to_string());
+            let prefix = f.prefix();
+            assert_ne!(f.engine().check_field(&prefix), FieldCheck::Mismatch);
+        }
+        assert!(f.engine().ghost_edits().is_empty());
+    }
+}
+
+#[test]
+fn lost_backspaces_fall_back_to_boundary() {
+    let mut f = field();
+    f.swallow_backspaces().type_str("vieetj");
+    assert_eq!(f.text(), "vieêtệt");
+    let prefix = f.prefix();
+    assert_eq!(f.engine().check_field(&prefix), FieldCheck::Mismatch);
+
+    let e = f.engine();
+    assert_eq!(e.output_profile(), OutputProfile::BOUNDARY);
+    let ghost = e.ghost_edits()[0];
+    assert_eq!(
+        (ghost.expected, ghost.profile),
+        (4, OutputProfile::default())
+    );
+    // Dropped with the word: nothing left to compare
+    assert_eq!(e.check_field(&prefix), FieldCheck::Unknown);
+
+    // The host picks a profile again on the next app switch
+    e.set_output_profile(OutputProfile::default());
+    assert_eq!(e.ghost_edits().len(), 1);
+}
@@ -0,0 +1,281 @@
+//! Golden Corpus - auto-restore accuracy on real text
+//!
+//! Each `tests/golden/*.tsv` line is `keystrokes<TAB>expected`: a sentence
+//! of Vietnamese prose, English tech text or a mix of both, as it should
+//! end up on screen. Every line is typed through a fresh engine and scored
+//! word by word:
+//!
+//! - a word is a restore candidate if plain composition (auto-restore
+//!   off) changes it;
+//! - it should be restored if the expected word is the keystrokes;
+//! - it was restored if the engine left the keystrokes.
+//!
+//! From these come restore precision (restored words that should have
+//! been) and recall (words that should have been restored that were), plus
+//! the share of all words that came out exactly as expected. A file sets
+//! its own floors in its header; the run fails if a heuristic change drops
+//! any of them:
+//!
+//! ```text
+//! # method: telex
+//! # min-precision: 0.95
+//! # min-recall: 0.90
+//! # min-accuracy: 0.95
+//! ```
+//!
+//! Runs with `cargo test`; `cargo test --test golden_test` prints the
+//! report on its own, and `GOLDEN_VERBOSE=1` lists every miss.
+
+use gonhanh_core::config::AutoCorrectMode;
+use gonhanh_core::engine::Engine;
+use gonhanh_core::utils::type_word;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+/// Misses listed per file unless `GOLDEN_VERBOSE` is set
+const MISSES_SHOWN: usize = 10;
+
+struct Corpus {
+    name: String,
+    method: u8,
+    min_precision: f64,
+    min_recall: f64,
+    min_accuracy: f64,
+    lines: Vec<(String, String)>,
+}
+
+#[derive(Default)]
+struct Score {
+    words: usize,
+    exact: usize,
+    candidates: usize,
+    /// Restored and should have been
+    hits: usize,
+    /// Restored, but it was Vietnamese
+    false_restores: usize,
+    /// Should have been restored, wasn't
+    missed: usize,
+}
+
+impl Score {
+    fn restored(&self) -> usize {
+        self.hits + self.false_restores
+    }
+
+    fn precision(&self) -> Option<f64> {
+        ratio(self.hits, self.restored())
+    }
+
+    fn recall(&self) -> Option<f64> {
+        ratio(self.hits, self.hits + self.missed)
+    }
+
+    fn accuracy(&self) -> Option<f64> {
+        ratio(self.exact, self.words)
+    }
+
+    fn add(&mut self, other: &Score) {
+        self.words += other.words;
+        self.exact += other.exact;
+        self.candidates += other.candidates;
+        self.hits += other.hits;
+        self.false_restores += other.false_restores;
+        self.missed += other.missed;
+    }
+}
+
+fn ratio(n: usize, of: usize) -> Option<f64> {
+    (of > 0).then(|| n as f64 / of as f64)
+}
+
+struct Percent(Option<f64>);
+
+impl fmt::Display for Percent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(r) => write!(f, "{:6.2}%", r * 100.0),
+            None => write!(f, "{:>7}", "n/a"),
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(msg) => {
+            eprintln!("golden_test: {}", msg);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run() -> Result<bool, String> {
+    let verbose = std::env::var_os("GOLDEN_VERBOSE").is_some();
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden");
+    let mut files: Vec<PathBuf> = std::fs::read_dir(&dir)
+        .map_err(|e| format!("{}: {}", dir.display(), e))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| p.extension().is_some_and(|x| x == "tsv"))
+        .collect();
+    files.sort();
+    if files.is_empty() {
+        return Err(format!("no corpus in {}", dir.display()));
+    }
+
+    println!(
+        "{:<16} {:>6} {:>6} {:>8} {:>9} {:>9} {:>9}",
+        "corpus", "words", "cands", "restored", "precision", "recall", "accuracy"
+    );
+    let mut ok = true;
+    let mut total = Score::default();
+    for path in &files {
+        let corpus = load(path)?;
+        let mut misses = Vec::new();
+        let score = score(&corpus, &mut misses);
+        println!(
+            "{:<16} {:>6} {:>6} {:>8}   {}  {}  {}",
+            corpus.name,
+            score.words,
+            score.candidates,
+            score.restored(),
+            Percent(score.precision()),
+            Percent(score.recall()),
+            Percent(score.accuracy()),
+        );
+        let shown = if verbose { misses.len() } else { MISSES_SHOWN };
+        for miss in misses.iter().take(shown) {
+            println!("    {}", miss);
+        }
+        if misses.len() > shown {
+            println!("    ... {} more (GOLDEN_VERBOSE=1)", misses.len() - shown);
+        }
+        for (what, got, min) in [
+            ("precision", score.precision(), corpus.min_precision),
+            ("recall", score.recall(), corpus.min_recall),
+            ("accuracy", score.accuracy(), corpus.min_accuracy),
+        ] {
+            if let Some(got) = got.filter(|&got| got < min) {
+                println!(
+                    "FAIL {}: {} {:.2}% is below {:.2}%",
+                    corpus.name,
+                    what,
+                    got * 100.0,
+                    min * 100.0
+                );
+                ok = false;
+            }
+        }
+        total.add(&score);
+    }
+    println!(
+        "{:<16} {:>6} {:>6} {:>8}   {}  {}  {}",
+        "total",
+        total.words,
+        total.candidates,
+        total.restored(),
+        Percent(total.precision()),
+        Percent(total.recall()),
+        Percent(total.accuracy()),
+    );
+    Ok(ok)
+}
+
+fn load(path: &Path) -> Result<Corpus, String> {
+    let err = |line: usize, msg: &str| format!("{}:{}: {}", path.display(), line + 1, msg);
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let mut corpus = Corpus {
+        name: path
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        method: 0,
+        min_precision: 0.0,
+        min_recall: 0.0,
+        min_accuracy: 0.0,
+        lines: Vec::new(),
+    };
+    for (n, line) in text.lines().enumerate() {
+        if let Some(comment) = line.strip_prefix('#') {
+            let Some((key, value)) = comment.split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+            let floor = || {
+                value
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|v| (0.0..=1.0).contains(v))
+                    .ok_or_else(|| err(n, "floor must be in [0, 1]"))
+            };
+            match key.trim() {
+                "method" => {
+                    corpus.method = match value {
+                        "telex" => 0,
+                        "vni" => 1,
+                        _ => return Err(err(n, "method must be telex or vni")),
+                    }
+                }
+                "min-precision" => corpus.min_precision = floor()?,
+                "min-recall" => corpus.min_recall = floor()?,
+                "min-accuracy" => corpus.min_accuracy = floor()?,
+                _ => {}
+            }
+            continue;
+        }
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (keys, expected) = line
+            .split_once('\t')
+            .ok_or_else(|| err(n, "expected keystrokes<TAB>output"))?;
+        if keys.split_whitespace().count() != expected.split_whitespace().count() {
+            return Err(err(n, "keystrokes and output differ in word count"));
+        }
+        corpus.lines.push((keys.to_string(), expected.to_string()));
+    }
+    Ok(corpus)
+}
+
+fn typed(method: u8, mode: AutoCorrectMode, keys: &str) -> String {
+    let mut e = Engine::new();
+    e.set_method(method).unwrap();
+    e.set_auto_correct(mode);
+    // The trailing space ends the last word like the others
+    type_word(&mut e, &format!("{} ", keys))
+}
+
+fn score(corpus: &Corpus, misses: &mut Vec<String>) -> Score {
+    let mut score = Score::default();
+    for (keys, expected) in &corpus.lines {
+        let composed = typed(corpus.method, AutoCorrectMode::Off, keys);
+        let output = typed(corpus.method, AutoCorrectMode::default(), keys);
+        let words = keys
+            .split_whitespace()
+            .zip(expected.split_whitespace())
+            .zip(composed.split_whitespace().zip(output.split_whitespace()));
+        for ((keys, expected), (composed, output)) in words {
+            score.words += 1;
+            if output == expected {
+                score.exact += 1;
+            } else {
+                misses.push(format!(
+                    "{:?} → {:?}, expected {:?}",
+                    keys, output, expected
+                ));
+            }
+            if composed == keys {
+                continue;
+            }
+            score.candidates += 1;
+            match (expected == keys, output == keys) {
+                (true, true) => score.hits += 1,
+                (true, false) => score.missed += 1,
+                (false, true) => score.false_restores += 1,
+                (false, false) => {}
+            }
+        }
+    }
+    score
+}
@@ -1,9 +1,30 @@
 //! Integration Tests - Engine state, settings, method switching
 
 mod common;
-use common::{assert_action, assert_passthrough, telex, type_word};
+use common::{assert_action, assert_passthrough, assert_transforms, engine_vni, telex, type_word};
+use gonhanh_core::config::{
+    AcceptKey, AutoCorrectMode, ConfigError, CorrectionPacks, CorrectionStyle, Dialect,
+    EngineConfig, GestureAction, GestureKey, Gestures, IyStyle, Method, MouseAction, Nbsp,
+    NbspChord, OverflowPolicy, RemoveKey, RepeatKey, ToneStyle,
+};
+#[cfg(feature = "autocorrect")]
+use gonhanh_core::config::{RestoreCheck, RestoreChecks, RestoreOrder};
+use gonhanh_core::correction_pack::{Checksum, CorrectionPack};
 use gonhanh_core::data::keys;
-use gonhanh_core::engine::{Action, Engine};
+use gonhanh_core::engine::event::{modifier, KeyEvent, MouseEvent};
+use gonhanh_core::engine::history::Privacy;
+#[cfg(feature = "autocorrect")]
+use gonhanh_core::engine::observer::Feedback;
+use gonhanh_core::engine::observer::{Notification, Setting};
+use gonhanh_core::engine::output::{Delivery, FieldCheck, OutputProfile};
+#[cfg(feature = "autocorrect")]
+use gonhanh_core::engine::validation::Problem;
+use gonhanh_core::engine::{Action, Engine, Output};
+#[cfg(feature = "macros")]
+use gonhanh_core::importer::ImportFormat;
+use gonhanh_core::storage::{self, FileStorage, MemoryStorage, Storage};
+use gonhanh_core::sync::SyncTransport;
+use gonhanh_core::utils::type_word_ext;
 
 // ============================================================
 // ENGINE STATE: Enable/Disable
@@ -52,6 +73,1503 @@ fn ctrl_clears_buffer() {
     assert_passthrough(&mut e, keys::S);
 }
 
+#[test]
+fn modifier_alone_keeps_buffer() {
+    let mut e = Engine::new();
+
+    e.on_key(keys::A, false, false);
+    // Shift / AltGr / Command pressed alone must not end the word
+    assert_passthrough(&mut e, keys::SHIFT);
+    assert_passthrough(&mut e, keys::RIGHT_OPTION);
+    assert_passthrough(&mut e, keys::COMMAND);
+    assert_transforms(&mut e, keys::S);
+}
+
+#[test]
+fn altgr_chord_clears_buffer() {
+    let mut e = Engine::new();
+
+    e.on_key(keys::A, false, false);
+    // AltGr+Q (e.g. '@' on German layout) arrives as Ctrl+Alt
+    e.on_key(keys::Q, false, true);
+    assert_passthrough(&mut e, keys::S);
+}
+
+// ============================================================
+// KEY EVENTS: Modifiers, key-up, auto-repeat
+// ============================================================
+
+fn tap(e: &mut Engine, key: u16) -> u8 {
+    let r = e.on_event(KeyEvent::down(key, 0));
+    e.on_event(KeyEvent::up(key, 0));
+    r.action
+}
+
+#[test]
+fn key_up_never_touches_buffer() {
+    let mut e = Engine::new();
+
+    tap(&mut e, keys::A);
+    let r = e.on_event(KeyEvent::up(keys::S, 0));
+    assert_eq!(r.action, Action::None as u8);
+    assert_eq!(tap(&mut e, keys::S), Action::Send as u8);
+}
+
+#[test]
+fn cmd_shortcut_does_not_pollute_buffer() {
+    let mut e = Engine::new();
+
+    tap(&mut e, keys::A);
+    // Cmd+A (select all) ends the word
+    let r = e.on_event(KeyEvent::down(keys::A, modifier::CMD));
+    assert_eq!(r.action, Action::None as u8);
+    e.on_event(KeyEvent::up(keys::A, modifier::CMD));
+    assert_eq!(tap(&mut e, keys::S), Action::None as u8);
+}
+
+#[test]
+fn shift_modifier_gives_uppercase() {
+    let mut e = Engine::new();
+
+    e.on_event(KeyEvent::down(keys::A, modifier::SHIFT));
+    e.on_event(KeyEvent::up(keys::A, modifier::SHIFT));
+    let r = e.on_event(KeyEvent::down(keys::S, 0));
+    assert_eq!(r.chars[0], 'Á' as u32);
+}
+
+#[test]
+fn same_key_typed_twice_still_composes() {
+    let mut e = Engine::new();
+
+    tap(&mut e, keys::A);
+    // a a → â (distinct key presses, not a repeat)
+    assert_eq!(tap(&mut e, keys::A), Action::Send as u8);
+}
+
+#[test]
+fn held_key_does_not_toggle_marks() {
+    let mut e = Engine::new();
+
+    tap(&mut e, keys::A);
+    // First s applies sắc
+    let r = e.on_event(KeyEvent::down(keys::S, 0));
+    assert_eq!(r.action, Action::Send as u8);
+    // Held s (no key-up in between): typed literally, no revert
+    let r = e.on_event(KeyEvent::down(keys::S, 0));
+    assert_eq!(r.action, Action::None as u8);
+    let r = e.on_event(KeyEvent::down(keys::S, 0).repeated());
+    assert_eq!(r.action, Action::None as u8);
+}
+
+#[test]
+fn held_key_follows_repeat_policy() {
+    let held = |e: &mut Engine, key: u16| {
+        e.on_event(KeyEvent::down(key, 0));
+        for _ in 0..3 {
+            e.on_event(KeyEvent::down(key, 0).repeated());
+        }
+        e.on_event(KeyEvent::up(key, 0));
+    };
+
+    // Single: held o types one o, which s then marks
+    let mut e = Engine::new();
+    e.set_repeat_key(RepeatKey::Single);
+    assert_eq!(e.config().repeat_key, RepeatKey::Single);
+    held(&mut e, keys::O);
+    let r = e.on_event(KeyEvent::down(keys::S, 0));
+    assert_eq!((r.action, r.backspace), (Action::Send as u8, 1));
+    assert_eq!(r.chars[0], 'ó' as u32);
+
+    // Raw: the word is given up, s has no vowel to mark
+    e.clear();
+    e.set_repeat_key(RepeatKey::Raw);
+    held(&mut e, keys::O);
+    assert_eq!(tap(&mut e, keys::S), Action::None as u8);
+
+    // Normal: repeats compose like the key typed again
+    e.set_repeat_key(RepeatKey::Normal);
+    tap(&mut e, keys::A);
+    let r = e.on_event(KeyEvent::down(keys::A, 0).repeated());
+    assert_eq!(r.chars[0], 'â' as u32);
+}
+
+#[test]
+fn fast_same_key_counts_as_repeat() {
+    let mut e = Engine::new();
+    e.set_repeat_key(RepeatKey::Single);
+    let key_at = |e: &mut Engine, time_ms: u64, key: u16| {
+        let r = e.on_key_at(time_ms, key, false, false, false);
+        (r.action, r.count)
+    };
+    key_at(&mut e, 1_000, keys::O);
+    // Held: swallowed
+    assert_eq!(key_at(&mut e, 1_030, keys::O), (Action::Send as u8, 0));
+    assert_eq!(key_at(&mut e, 1_060, keys::O), (Action::Send as u8, 0));
+    // Typed twice: ô
+    assert_eq!(key_at(&mut e, 1_300, keys::O), (Action::Send as u8, 1));
+    // Another key, however fast, isn't a repeat
+    assert_eq!(key_at(&mut e, 1_310, keys::S), (Action::Send as u8, 1));
+    assert_eq!(e.debug_state().shown(), "ố");
+}
+
+#[test]
+fn held_backspace_still_edits_buffer() {
+    let mut e = Engine::new();
+
+    tap(&mut e, keys::A);
+    tap(&mut e, keys::B);
+    e.on_event(KeyEvent::down(keys::DELETE, 0));
+    e.on_event(KeyEvent::down(keys::DELETE, 0).repeated());
+    e.on_event(KeyEvent::up(keys::DELETE, 0));
+    // Buffer is empty: s has no vowel to mark
+    assert_eq!(tap(&mut e, keys::S), Action::None as u8);
+}
+
+#[test]
+fn double_tapped_modifiers_run_gestures() {
+    let tap_at = |e: &mut Engine, key: u16, time_ms: u64| {
+        e.on_event(KeyEvent::down(key, 0).at(time_ms));
+        e.on_event(KeyEvent::up(key, 0).at(time_ms + 60));
+    };
+    let mut e = Engine::new();
+    tap_at(&mut e, keys::SHIFT, 1_000);
+    tap_at(&mut e, keys::SHIFT, 1_200);
+    assert_eq!(e.gesture(), None);
+
+    e.set_gestures(
+        Gestures::NONE
+            .with(GestureKey::Shift, Some(GestureAction::ToggleEnabled))
+            .with(GestureKey::Ctrl, Some(GestureAction::Suggestions)),
+    );
+    let (_, rx) = e.subscribe_channel();
+    tap_at(&mut e, keys::SHIFT, 2_000);
+    assert_eq!(e.gesture(), None);
+    tap_at(&mut e, keys::RIGHT_SHIFT, 2_200);
+    assert_eq!(e.gesture(), Some(GestureAction::ToggleEnabled));
+    assert!(!e.config().enabled);
+    assert_eq!(
+        rx.try_iter().collect::<Vec<_>>(),
+        [
+            Notification::SettingChanged(Setting::Enabled(false)),
+            Notification::Gesture(GestureAction::ToggleEnabled),
+        ]
+    );
+    // Only for the event that completed it
+    tap(&mut e, keys::A);
+    assert_eq!(e.gesture(), None);
+
+    // Left to the host; reported for a batch too
+    let events = [
+        KeyEvent::down(keys::CONTROL, 0).at(3_000),
+        KeyEvent::up(keys::CONTROL, 0).at(3_050),
+        KeyEvent::down(keys::CONTROL, 0).at(3_150),
+        KeyEvent::up(keys::CONTROL, 0).at(3_200),
+        KeyEvent::down(keys::A, 0).at(3_300),
+    ];
+    e.process_keys(&events);
+    assert_eq!(e.gesture(), Some(GestureAction::Suggestions));
+    assert!(!e.config().enabled);
+}
+
+#[test]
+#[cfg(feature = "autocorrect")]
+fn feedback_marks_what_became_of_words() {
+    let feedback = |rx: &std::sync::mpsc::Receiver<Notification>| {
+        rx.try_iter()
+            .filter_map(|n| match n {
+                Notification::Feedback(kind) => Some(kind),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+    };
+    let mut e = Engine::new();
+    let (_, rx) = e.subscribe_channel();
+    type_word(&mut e, "as ");
+    assert!(feedback(&rx).is_empty());
+
+    e.set_feedback(true);
+    e.set_feedback_gap_ms(0);
+    e.set_correction_packs(CorrectionPacks::NONE.with(Dialect::Southern, true));
+    type_word(&mut e, "as text namf ");
+    type_word(&mut e, "as");
+    e.on_key(keys::ESC, false, false);
+    assert_eq!(
+        feedback(&rx),
+        [
+            Feedback::WordCommitted,
+            Feedback::Restored,
+            Feedback::Corrected,
+            Feedback::Restored,
+        ]
+    );
+
+    // Throttled: a commit right after one is dropped, a restore isn't
+    let as_ = [keys::A, keys::S, keys::SPACE];
+    let mut e = Engine::new();
+    let (_, rx) = e.subscribe_channel();
+    e.set_feedback(true);
+    e.set_feedback_gap_ms(200);
+    type_at(&mut e, 1_000, &as_);
+    type_at(&mut e, 1_100, &as_);
+    type_at(
+        &mut e,
+        1_150,
+        &[keys::T, keys::E, keys::X, keys::T, keys::SPACE],
+    );
+    assert_eq!(feedback(&rx), [Feedback::WordCommitted, Feedback::Restored]);
+    // By the keys' time, not how fast they're replayed
+    type_at(&mut e, 1_300, &as_);
+    type_at(&mut e, 1_400, &as_);
+    assert_eq!(feedback(&rx), [Feedback::WordCommitted]);
+
+    // A key without a time is timed now, not by the last timed key
+    let mut e = Engine::new();
+    let (_, rx) = e.subscribe_channel();
+    e.set_feedback(true);
+    e.set_feedback_gap_ms(50);
+    type_at(&mut e, 1_000, &as_);
+    std::thread::sleep(std::time::Duration::from_millis(60));
+    type_word(&mut e, "as ");
+    assert_eq!(
+        feedback(&rx),
+        [Feedback::WordCommitted, Feedback::WordCommitted]
+    );
+}
+
+#[test]
+fn runaway_edits_stop_the_engine() {
+    let aa = [keys::A, keys::A, keys::SPACE];
+    let mut e = Engine::new();
+    let (_, rx) = e.subscribe_channel();
+    e.set_edit_limit(3);
+    rx.try_iter().for_each(drop);
+    for time_ms in [1_000, 1_010, 1_020] {
+        type_at(&mut e, time_ms, &aa);
+    }
+    assert_eq!(e.runaways(), 0);
+
+    // One edit too many: the word is dropped, keys go through as typed
+    assert_eq!(type_at(&mut e, 1_030, &[keys::A, keys::A]), Action::None);
+    assert_eq!(e.runaways(), 1);
+    assert_eq!(rx.try_iter().collect::<Vec<_>>(), [Notification::Runaway]);
+    // Each edit while stopped starts the wait over
+    let dd = [keys::SPACE, keys::D, keys::D];
+    assert_eq!(type_at(&mut e, 1_900, &dd), Action::None);
+    assert_eq!(type_at(&mut e, 2_800, &dd), Action::None);
+
+    // A quiet second later it edits again
+    assert_eq!(type_at(&mut e, 3_800, &dd), Action::Send);
+    assert_eq!(e.runaways(), 1);
+
+    // Keys without a host time aren't counted
+    let mut e = Engine::new();
+    e.set_edit_limit(1);
+    assert_eq!(type_word(&mut e, "aa aa ddi "), "â â đi ");
+    assert_eq!(e.runaways(), 0);
+
+    // ...not even after a timed one
+    let mut e = Engine::new();
+    e.set_edit_limit(3);
+    type_at(&mut e, 1_000, &[keys::SPACE]);
+    assert_eq!(type_word(&mut e, &"aa ".repeat(60)), "â ".repeat(60));
+    assert_eq!(type_at(&mut e, 1_010, &[keys::A, keys::A]), Action::Send);
+    assert_eq!(e.runaways(), 0);
+}
+
+// ============================================================
+// OUTPUT: Only the changed tail is retyped
+// ============================================================
+
+#[test]
+#[cfg(feature = "autocorrect")]
+fn restore_keeps_unchanged_prefix() {
+    let mut e = Engine::new();
+    for c in "text".chars() {
+        e.on_key(keys::from_char(c).unwrap(), false, false);
+    }
+    // Screen shows "tẽt"; only "ẽt" is deleted
+    let r = e.on_key(keys::SPACE, false, false);
+    let text: String = r.chars[..r.count as usize]
+        .iter()
+        .filter_map(|&c| char::from_u32(c))
+        .collect();
+    assert_eq!((r.backspace, text.as_str()), (2, "ext "));
+}
+
+#[test]
+#[cfg(feature = "autocorrect")]
+fn minimal_edits_give_same_screen() {
+    telex(&[
+        ("text ", "text "),
+        ("hoaf ", "hoà "),
+        ("thuowngf ", "thường "),
+        ("dduowcj ", "được "),
+        ("expect ", "expect "),
+    ]);
+}
+
+// ============================================================
+// OUTPUT PROFILES: Slow apps
+// ============================================================
+
+#[cfg(feature = "autocorrect")]
+const PROFILE_CASES: &[(&str, &str)] = &[
+    ("vieetj nam ", "việt nam "),
+    ("tieengs vieetj, ", "tiếng việt, "),
+    ("dduowcj khoong ", "được không "),
+    ("text ", "text "),
+    ("hoaf.", "hoà."),
+];
+
+#[test]
+#[cfg(feature = "autocorrect")]
+fn boundary_strategy_edits_once_per_word() {
+    for (input, expected) in PROFILE_CASES {
+        let mut e = Engine::new();
+        e.set_output_profile(OutputProfile::BOUNDARY);
+        assert_eq!(type_word(&mut e, input), *expected, "{}", input);
+    }
+
+    // Nothing is sent until the word ends
+    let mut e = Engine::new();
+    e.set_output_profile(OutputProfile::BOUNDARY);
+    for c in "vieetj".chars() {
+        let r = e.on_key(keys::from_char(c).unwrap(), false, false);
+        assert_eq!(r.action, Action::None as u8);
+    }
+    let r = e.on_key(keys::SPACE, false, false);
+    let text: String = r.chars[..r.count as usize]
+        .iter()
+        .filter_map(|&c| char::from_u32(c))
+        .collect();
+    // "vieetj" on screen: keep "vi", replace "eetj"
+    assert_eq!((r.backspace, text.as_str()), (4, "ệt "));
+}
+
+#[test]
+#[cfg(feature = "autocorrect")]
+fn max_backspace_defers_long_edits() {
+    let profile = OutputProfile {
+        max_backspace: 1,
+        ..OutputProfile::default()
+    };
+    for (input, expected) in PROFILE_CASES {
+        let mut e = Engine::new();
+        e.set_output_profile(profile);
+        assert_eq!(type_word(&mut e, input), *expected, "{}", input);
+    }
+}
+
+#[test]
+fn backspace_gives_up_deferred_word() {
+    let mut e = Engine::new();
+    e.set_output_profile(OutputProfile::BOUNDARY);
+    // Raw text stays as typed once it's edited by hand
+    assert_eq!(type_word(&mut e, "vieet<j "), "vieej ");
+}
+
+// ============================================================
+// OUTPUT: Enter after the edit
+// ============================================================
+
+/// Text of the result for Enter after typing `word`
+#[cfg(feature = "autocorrect")]
+fn enter_after(profile: OutputProfile, word: &str, shift: bool) -> (u8, String) {
+    let mut e = Engine::new();
+    e.set_output_profile(profile);
+    type_word(&mut e, word);
+    let r = e.on_key_ext(keys::RETURN, false, false, shift);
+    let text = r.chars[..r.count as usize]
+        .iter()
+        .filter_map(|&c| char::from_u32(c))
+        .collect();
+    (r.action, text)
+}
+
+#[test]
+#[cfg(feature = "autocorrect")]
+fn chat_profile_sends_enter_after_the_edit() {
+    let send = Action::Send as u8;
+    assert_eq!(
+        enter_after(OutputProfile::CHAT, "text", false),
+        (send, "ext\n".to_string())
+    );
+    // Elsewhere Enter goes through after the edit, as does Shift+Enter
+    assert_eq!(
+        enter_after(OutputProfile::default(), "text", false),
+        (send, "ext".to_string())
+    );
+    assert_eq!(
+        enter_after(OutputProfile::CHAT, "text", true),
+        (send, "ext".to_string())
+    );
+    // Nothing to fix: nothing to hold Enter back for
+    assert_eq!(
+        enter_after(OutputProfile::CHAT, "xin", false),
+        (Action::None as u8, String::new())
+    );
+}
+
+// ============================================================
+// OUTPUT: Select-and-replace
+// ============================================================
+
+fn select_mode() -> Engine {
+    let mut e = Engine::new();
+    e.set_output_profile(OutputProfile {
+        delivery: Delivery::Select,
+        ..OutputProfile::default()
+    });
+    e
+}
+
+/// Type `input` between `before` and `after` the way a host using
+/// `Delivery::Select` would; returns the whole field
+#[cfg(feature = "autocorrect")]
+fn type_selecting(e: &mut Engine, before: &str, input: &str, after: &str) -> String {
+    let mut field: Vec<char> = before.chars().chain(after.chars()).collect();
+    let mut caret = before.chars().count();
+    e.set_caret(Some(caret));
+    for c in input.chars() {
+        let key = gonhanh_core::utils::char_to_key(c);
+        let r = e.on_key(key, c.is_uppercase(), false);
+        let text: Vec<char> = r.chars[..r.count as usize]
+            .iter()
+            .filter_map(|&c| char::from_u32(c))
+            .collect();
+        match r.action {
+            a if a == Action::Select as u8 => {
+                let range = e.edit_range().unwrap();
+                assert_eq!(range.end, caret, "{}", input);
+                assert_eq!(range.len(), r.backspace as usize);
+                caret = range.start + text.len();
+                field.splice(range, text);
+            }
+            a if a == Action::Send as u8 => {
+                assert_eq!(r.backspace, 0, "{}: backspaces in select mode", input);
+                field.splice(caret..caret, text.iter().copied());
+                caret += text.len();
+            }
+            _ => {}
+        }
+        // The key itself, unless the engine typed it
+        let typed = r.action != Action::None as u8 && (key == keys::SPACE || !keys::is_break(key));
+        if key == keys::DELETE {
+            caret -= 1;
+            field.remove(caret);
+        } else if !typed {
+            field.insert(caret, c);
+            caret += 1;
+        }
+        assert_eq!(e.caret(), Some(caret), "{}: after {:?}", input, c);
+    }
+    field.into_iter().collect()
+}
+
+#[test]
+#[cfg(feature = "autocorrect")]
+fn select_mode_replaces_ranges() {
+    for (input, expected) in PROFILE_CASES {
+        let mut e = select_mode();
+        let field = type_selecting(&mut e, "Xin chào ", input, "!");
+        assert_eq!(field, format!("Xin chào {}!", expected), "{}", input);
+    }
+    let mut e = select_mode();
+    assert_eq!(type_selecting(&mut e, "", "vieet<j ", ""), "việ ");
+
+    // Range is absolute: "ee" at offsets 12..13 becomes "ê"
+    let mut e = select_mode();
+    e.set_caret(Some(10));
+    for key in [keys::V, keys::I, keys::E] {
+        e.on_key(key, false, false);
+    }
+    let r = e.on_key(keys::E, false, false);
+    assert_eq!(r.action, Action::Select as u8);
+    assert_eq!(e.edit_range(), Some(12..13));
+    assert_eq!(e.caret(), Some(13));
+}
+
+#[test]
+fn select_mode_needs_known_caret() {
+    // Never told where the caret is: plain backspaces
+    let mut e = select_mode();
+    e.on_key(keys::A, false, false);
+    let r = e.on_key(keys::A, false, false);
+    assert_eq!((r.action, r.backspace), (Action::Send as u8, 1));
+    assert_eq!(e.edit_range(), None);
+
+    // Arrow keys and paste lose it again
+    for (key, ctrl) in [(keys::LEFT, false), (keys::V, true)] {
+        e.set_caret(Some(4));
+        e.on_key(key, false, ctrl);
+        assert_eq!(e.caret(), None);
+    }
+}
+
+// ============================================================
+// CONTEXT: Caret jumps and re-seeding
+// ============================================================
+
+#[test]
+fn caret_jump_ends_word() {
+    let mut e = Engine::new();
+    e.on_key(keys::A, false, false);
+    e.caret_moved(None);
+    // No "a" to put a circumflex on
+    assert_passthrough(&mut e, keys::A);
+
+    let mut e = Engine::new();
+    e.set_caret(Some(3));
+    e.on_key(keys::A, false, false);
+    e.focus_changed();
+    assert_eq!(e.caret(), None);
+    assert_passthrough(&mut e, keys::A);
+}
+
+#[test]
+fn text_context_continues_word() {
+    let cases = [
+        ("Xin chao", "f", "chào"),
+        ("tiếng viêt", "j", "việt"),
+        ("Được", "s", "Đước"),
+        ("duoc", "w", "dươc"),
+        // Decomposed, as pasted from macOS
+        ("tiếng vie\u{0302}t", "j", "việt"),
+    ];
+    for (prefix, input, expected) in cases {
+        let mut e = Engine::new();
+        let word_len = prefix.split(' ').next_back().unwrap().chars().count();
+        assert_eq!(e.text_context(prefix), word_len, "{}", prefix);
+        let r = e.on_key(
+            keys::from_char(input.chars().next().unwrap()).unwrap(),
+            false,
+            false,
+        );
+        assert_eq!(r.action, Action::Send as u8, "{}", prefix);
+        assert_eq!(
+            e.word_info().unwrap().text,
+            expected,
+            "{} + {}",
+            prefix,
+            input
+        );
+    }
+
+    // Nothing to continue after a space, a symbol, a non-Vietnamese letter,
+    // a link or an identifier
+    let mut e = Engine::new();
+    for prefix in ["viet ", "viet.", "straße", "", "www.viet", "user_viet"] {
+        assert_eq!(e.text_context(prefix), 0, "{}", prefix);
+        assert_passthrough(&mut e, keys::J);
+    }
+}
+
+#[test]
+fn seed_word_continues_composition() {
+    let cases = [
+        ("hoà", "s", "hoá"),
+        ("nguoi", "w", "ngươi"),
+        ("Việt", "z", "Viêt"),
+        ("đươc", "j", "được"),
+        ("tiếng", "f", "tiềng"),
+        ("nguyê", "n", "nguyên"),
+    ];
+    for (word, input, expected) in cases {
+        let mut e = Engine::new();
+        assert!(e.seed_word(word), "{}", word);
+        type_word(&mut e, input);
+        assert_eq!(
+            e.word_info().unwrap().text,
+            expected,
+            "{} + {}",
+            word,
+            input
+        );
+    }
+
+    let mut e = Engine::new();
+    for word in ["", "hai từ", "straße", "abc1", &"a".repeat(65)] {
+        assert!(!e.seed_word(word), "{}", word);
+        assert_eq!(e.word_info(), None);
+    }
+}
+
+// ============================================================
+// REVERSE: Vietnamese → keystrokes
+// ============================================================
+
+const REVERSE_WORDS: &str = "Tôi đã thử rất nhiều bộ gõ tiếng Việt nhưng gặp khó chịu \
+    giữa chừng mượt những giường khuỷu khuyến khích chuyển đổi nguyện vọng huỷ hoại \
+    quỳnh khoẻ khoắn loà nghiêng ngả thoải bằng soạn Đúng hoài ghiền Nếu \
+    ĐƯỢC NGƯỜI xoong hươu rượu uống quốc ưu khuya";
+
+#[test]
+fn keystrokes_type_the_word_back() {
+    for method in [Method::Telex, Method::Vni] {
+        for word in REVERSE_WORDS.split_whitespace() {
+            let typed = gonhanh_core::input::to_keystrokes(word, method).unwrap();
+            let mut e = Engine::new();
+            e.set_method(method.id()).unwrap();
+            assert_eq!(
+                type_word(&mut e, &format!("{} ", typed)),
+                format!("{} ", word),
+                "{:?}: {} typed as {}",
+                method,
+                word,
+                typed
+            );
+        }
+    }
+}
+
+// ============================================================
+// ACCESSIBILITY: Composed word for screen readers
+// ============================================================
+
+#[test]
+fn word_info_tracks_composition() {
+    let mut e = Engine::new();
+    assert_eq!(e.word_info(), None);
+    for c in "vieetj".chars() {
+        e.on_key(keys::from_char(c).unwrap(), false, false);
+    }
+    let info = e.word_info().unwrap();
+    assert_eq!(info.text, "việt");
+    assert_eq!((info.start, info.end, info.committed), (-4, 0, false));
+
+    // Space is part of the word's output; the word ends one before the caret
+    e.on_key(keys::SPACE, false, false);
+    let info = e.word_info().unwrap();
+    assert_eq!(info.text, "việt");
+    assert_eq!((info.start, info.end, info.committed), (-5, -1, true));
+
+    // Gone after the next key
+    e.on_key(keys::N, false, false);
+    assert_eq!(e.word_info().unwrap().text, "n");
+}
+
+#[test]
+#[cfg(feature = "autocorrect")]
+fn word_info_gives_final_word() {
+    // Restored to English at the boundary
+    let mut e = Engine::new();
+    for c in "text".chars() {
+        e.on_key(keys::from_char(c).unwrap(), false, false);
+    }
+    e.on_key(keys::DOT, false, false);
+    let info = e.word_info().unwrap();
+    assert_eq!((info.text.as_str(), info.start, info.end), ("text", -5, -1));
+
+    // Deferred edits: raw text on screen, composed word announced
+    let mut e = Engine::new();
+    e.set_output_profile(OutputProfile::BOUNDARY);
+    for c in "dduowcj".chars() {
+        e.on_key(keys::from_char(c).unwrap(), false, false);
+    }
+    let info = e.word_info().unwrap();
+    assert_eq!((info.text.as_str(), info.start), ("được", -7));
+    e.on_key(keys::SPACE, false, false);
+    let info = e.word_info().unwrap();
+    assert_eq!((info.text.as_str(), info.start, info.end), ("được", -5, -1));
+
+    // Arrow keys move the caret away: nothing to locate
+    let mut e = Engine::new();
+    e.on_key(keys::A, false, false);
+    e.on_key(keys::LEFT, false, false);
+    assert_eq!(e.word_info(), None);
+}
+
+#[test]
+fn preview_follows_each_key() {
+    for profile in [OutputProfile::default(), OutputProfile::BOUNDARY] {
+        let mut e = Engine::new();
+        e.set_output_profile(profile);
+        assert_eq!(e.current_preview(), "");
+        let mut previews = Vec::new();
+        for c in "dduwowcj".chars() {
+            e.on_key(keys::from_char(c).unwrap(), false, false);
+            previews.push(e.current_preview().to_string());
+        }
+        assert_eq!(
+            previews,
+            ["d", "đ", "đu", "đư", "đưo", "đươ", "đươc", "được"],
+            "{:?}",
+            profile
+        );
+        e.on_key(keys::SPACE, false, false);
+        assert_eq!(e.current_preview(), "");
+    }
+}
+
+// ============================================================
+// PRIVACY: Word history
+// ============================================================
+
+#[test]
+fn finished_words_go_to_history() {
+    let mut e = Engine::new();
+    e.set_privacy(Privacy::KEEP);
+    type_word(&mut e, "vieetj nam. dduowcj");
+    // The word being typed isn't history yet
+    assert_eq!(e.recent_words().collect::<Vec<_>>(), ["nam", "việt"]);
+}
+
+#[test]
+fn retention_off_keeps_nothing() {
+    let mut e = Engine::new();
+    type_word(&mut e, "vieetj ");
+    assert_eq!(e.recent_words().count(), 0, "off until the host opts in");
+
+    e.set_privacy(Privacy::KEEP);
+    type_word(&mut e, "vieetj ");
+    assert_eq!(e.recent_words().collect::<Vec<_>>(), ["việt"]);
+    e.set_privacy(Privacy::OFF);
+    assert_eq!(e.recent_words().count(), 0);
+    type_word(&mut e, "nam ");
+    assert_eq!(e.recent_words().count(), 0);
+
+    e.set_privacy(Privacy::KEEP);
+    type_word(&mut e, "nam ");
+    assert_eq!(e.recent_words().collect::<Vec<_>>(), ["nam"]);
+}
+
+#[test]
+fn history_ages_words_by_key_time() {
+    let mut e = Engine::new();
+    e.set_privacy(Privacy {
+        retain: true,
+        expire_after: Some(std::time::Duration::from_secs(1)),
+    });
+    type_at(&mut e, 1_000, &[keys::N, keys::A, keys::M, keys::SPACE]);
+    // Typed a minute later by the host's clock, however soon it arrives
+    type_at(&mut e, 61_000, &[keys::B, keys::A, keys::N, keys::SPACE]);
+    assert_eq!(e.recent_words().collect::<Vec<_>>(), ["ban"]);
+}
+
+#[test]
+fn word_counts_are_opt_in() {
+    let mut e = Engine::new();
+    e.set_privacy(Privacy::KEEP);
+    type_word(&mut e, "vieetj nam ");
+    assert!(e.word_counts().is_empty());
+
+    e.set_word_counting(true);
+    type_word(&mut e, "Vieetj nam vieetj ");
+    assert_eq!(e.word_counts(), [("việt", 2), ("nam", 1)]);
+    assert_eq!(e.word_counts_csv(), "word,count\nviệt,2\nnam,1\n");
+
+    e.wipe_all();
+    assert!(e.word_counts().is_empty());
+    e.set_privacy(Privacy::OFF);
+    type_word(&mut e, "nam ");
+    assert!(e.word_counts().is_empty());
+}
+
+#[test]
+fn wipe_all_forgets_current_word() {
+    let mut e = Engine::new();
+    type_word(&mut e, "xin chaof vieet");
+    e.wipe_all();
+    assert_eq!(e.recent_words().count(), 0);
+    // "viêt" is gone: s starts a new word instead of marking it
+    assert_eq!(type_word(&mut e, "s"), "s");
+}
+
+// ============================================================
+// BATCH: process_keys
+// ============================================================
+
+/// Key-down + key-up for each character
+fn taps(s: &str) -> Vec<KeyEvent> {
+    s.chars()
+        .flat_map(|c| {
+            let key = keys::from_char(c).unwrap();
+            [KeyEvent::down(key, 0), KeyEvent::up(key, 0)]
+        })
+        .collect()
+}
+
+/// Screen text after applying outputs for `events`
+fn screen_after(events: &[KeyEvent], outputs: &[Output]) -> String {
+    let mut screen: Vec<char> = Vec::new();
+    for (ev, out) in events.iter().zip(outputs) {
+        match *out {
+            Output::PassThrough if ev.down => {
+                screen.push(gonhanh_core::utils::key_to_char(ev.key, false).unwrap_or(' '))
+            }
+            Output::PassThrough => {}
+            Output::Append(c) => screen.push(c),
+            Output::Replace { backspace, text } => {
+                screen.truncate(screen.len() - backspace as usize);
+                screen.extend(text.chars());
+            }
+        }
+    }
+    screen.into_iter().collect()
+}
+
+#[test]
+fn batch_merges_consecutive_edits() {
+    let mut e = Engine::new();
+    let events = taps("aas");
+    let outputs = e.process_keys(&events);
+
+    assert_eq!(outputs.len(), events.len());
+    assert_eq!(outputs[0], Output::PassThrough);
+    // aa → â is folded into the sắc edit that follows
+    assert_eq!(
+        outputs[2],
+        Output::Replace {
+            backspace: 0,
+            text: ""
+        }
+    );
+    assert_eq!(
+        outputs[4],
+        Output::Replace {
+            backspace: 1,
+            text: "ấ"
+        }
+    );
+    assert_eq!(screen_after(&events, &outputs), "ấ");
+}
+
+#[test]
+fn batch_matches_key_by_key() {
+    for input in ["vieetj nam", "dduowcj khoong", "tieengs vieetj", "text "] {
+        let events = taps(input);
+
+        let mut one = Engine::new();
+        let mut expected: Vec<char> = Vec::new();
+        for &ev in &events {
+            let r = one.on_event(ev);
+            if r.action == Action::None as u8 {
+                if ev.down {
+                    expected.push(gonhanh_core::utils::key_to_char(ev.key, false).unwrap_or(' '));
+                }
+                continue;
+            }
+            expected.truncate(expected.len() - r.backspace as usize);
+            expected.extend(
+                r.chars[..r.count as usize]
+                    .iter()
+                    .filter_map(|&c| char::from_u32(c)),
+            );
+        }
+
+        let mut batch = Engine::new();
+        let outputs = batch.process_keys(&events);
+        assert_eq!(
+            screen_after(&events, &outputs),
+            expected.into_iter().collect::<String>(),
+            "{}",
+            input
+        );
+    }
+}
+
+// ============================================================
+// TEXT INPUT: Characters composed by the OS layout
+// ============================================================
+
+#[test]
+fn text_input_composes_with_following_keys() {
+    let mut e = Engine::new();
+
+    // "vi" typed, "ê" from a dead key, then "t" + "j" → việt
+    e.on_key(keys::V, false, false);
+    e.on_key(keys::I, false, false);
+    assert_eq!(e.on_text('ê').action, Action::None as u8);
+    e.on_key(keys::T, false, false);
+    let r = e.on_key(keys::J, false, false);
+    assert_eq!(r.action, Action::Send as u8);
+    assert_eq!(r.backspace, 2);
+    assert_eq!(char::from_u32(r.chars[0]), Some('ệ'));
+}
+
+#[test]
+fn text_input_uppercase_and_stroke() {
+    let mut e = Engine::new();
+
+    e.on_text('Đ');
+    e.on_key(keys::O, false, false);
+    let r = e.on_key(keys::F, false, false);
+    assert_eq!(char::from_u32(r.chars[0]), Some('ò'));
+}
+
+#[test]
+fn text_input_precomposed_vowels_take_tones() {
+    // Correcting existing text: the vowel arrives composed, in either case
+    let cases = [
+        (Method::Telex, "Ô", "s", "Ố"),
+        (Method::Telex, "ô", "s", "ố"),
+        (Method::Telex, "Ồ", "s", "Ố"),
+        (Method::Telex, "TÔ", "S", "TỐ"),
+        (Method::Telex, "ĐƯƠ", "j", "ĐƯỢ"),
+        (Method::Telex, "Ó", "o", "Ố"),
+        (Method::Telex, "Ú", "w", "Ứ"),
+        (Method::Telex, "Ă", "x", "Ẵ"),
+        (Method::Vni, "Ô", "1", "Ố"),
+        (Method::Vni, "Ồ", "1", "Ố"),
+        (Method::Vni, "Ó", "6", "Ố"),
+        (Method::Vni, "Ư", "3", "Ử"),
+        (Method::Vni, "ĐƯỜ", "1", "ĐƯỚ"),
+    ];
+    for (method, prefix, input, expected) in cases {
+        // From the OS layout, one char at a time
+        let mut e = Engine::new();
+        e.set_method(method.id()).unwrap();
+        for ch in prefix.chars() {
+            e.on_text(ch);
+        }
+        type_word(&mut e, input);
+        assert_eq!(
+            e.word_info().unwrap().text,
+            expected,
+            "{} + {}",
+            prefix,
+            input
+        );
+
+        // From the text around the caret
+        let mut e = Engine::new();
+        e.set_method(method.id()).unwrap();
+        assert!(e.seed_word(prefix), "{}", prefix);
+        type_word(&mut e, input);
+        assert_eq!(
+            e.word_info().unwrap().text,
+            expected,
+            "{} + {}",
+            prefix,
+            input
+        );
+    }
+}
+
+#[test]
+fn text_input_ascii_uses_key_path() {
+    let mut e = Engine::new();
+
+    e.on_text('a');
+    let r = e.on_text('s');
+    assert_eq!(r.action, Action::Send as u8);
+    assert_eq!(char::from_u32(r.chars[0]), Some('á'));
+}
+
+#[test]
+fn text_input_symbol_breaks_word() {
+    let mut e = Engine::new();
+
+    e.on_key(keys::A, false, false);
+    e.on_text('€');
+    assert_passthrough(&mut e, keys::S);
+}
+
+#[test]
+fn text_input_is_not_restored() {
+    let mut e = Engine::new();
+
+    // OS-composed "é" has no keystrokes to restore to
+    e.on_text('é');
+    let r = e.on_key(keys::ESC, false, false);
+    assert_eq!(r.action, Action::None as u8);
+}
+
+// ============================================================
+// NUMPAD / INTERNATIONAL KEYS
+// ============================================================
+
+#[test]
+fn vni_numpad_digit_is_not_a_mark() {
+    let mut e = engine_vni();
+
+    e.on_key(keys::A, false, false);
+    // Numpad 1 breaks the word instead of adding sắc
+    assert_passthrough(&mut e, keys::KP_1);
+    assert_passthrough(&mut e, keys::N1);
+}
+
+#[test]
+fn numpad_breaks_word_in_telex() {
+    let mut e = Engine::new();
+
+    e.on_key(keys::A, false, false);
+    e.on_key(keys::KP_5, false, false);
+    assert_passthrough(&mut e, keys::S);
+}
+
+#[test]
+fn dead_key_breaks_word() {
+    let mut e = Engine::new();
+
+    e.on_key(keys::A, false, false);
+    e.on_key(keys::DEAD, false, false);
+    assert_passthrough(&mut e, keys::S);
+}
+
+#[test]
+fn windows_vk_types_through_engine() {
+    let mut e = engine_vni();
+
+    // VK_A, VK_1 → á
+    let a = keys::from_windows_vk(0x41).unwrap();
+    let one = keys::from_windows_vk(0x31).unwrap();
+    e.on_key(a, false, false);
+    assert_transforms(&mut e, one);
+
+    // VK_NUMPAD1 never adds a mark
+    e.clear();
+    e.on_key(a, false, false);
+    assert_passthrough(&mut e, keys::from_windows_vk(0x61).unwrap());
+}
+
+// ============================================================
+// CONFIG: EngineConfig round-trip
+// ============================================================
+
+#[test]
+fn config_applies_to_engine() {
+    let mut e = Engine::new();
+    let config = EngineConfig::from_toml("method = \"vni\"\nenabled = true\n").unwrap();
+    e.apply_config(&config).unwrap();
+    assert_eq!(e.config(), config);
+
+    let result = type_word(&mut e, "a1");
+    assert_eq!(result, "á");
+}
+
+#[test]
+fn config_auto_correct_off_keeps_composed_word() {
+    let mut e = Engine::new();
+    let mut config = e.config();
+    config.auto_correct = AutoCorrectMode::Off;
+    e.apply_config(&config).unwrap();
+
+    let result = type_word(&mut e, "text ");
+    assert_eq!(result, "tẽt ");
+}
+
+#[test]
+#[cfg(feature = "autocorrect")]
+fn config_restore_order_changes_speed_not_outcome() {
+    let words = [
+        "text ", "raw ", "core ", "their ", "window ", "hofp ", "vieejt ", "cura ",
+    ];
+    let mut reversed = RestoreCheck::ALL;
+    reversed.reverse();
+    let orders = [RestoreOrder::DEFAULT, RestoreOrder::new(&reversed).unwrap()];
+    let typed: Vec<Vec<String>> = orders
+        .iter()
+        .map(|&order| {
+            let mut e = Engine::new();
+            e.set_restore_order(order);
+            assert_eq!(e.config().restore_order, order);
+            words.iter().map(|w| type_word(&mut e, w)).collect()
+        })
+        .collect();
+    assert_eq!(typed[0], typed[1]);
+    assert_eq!(typed[0][..2], ["text ", "raw "]);
+
+    // "tẽt" is a valid syllable: the tone key before a consonant decides,
+    // and nothing after it runs
+    let mut e = Engine::new();
+    type_word(&mut e, "text ");
+    let stats = e.restore_stats();
+    assert_eq!(stats.evaluated(RestoreCheck::Invalid), 1);
+    assert_eq!(stats.hits(RestoreCheck::Invalid), 0);
+    assert_eq!(stats.hits(RestoreCheck::ModifierConsonant), 1);
+    assert_eq!(stats.evaluated(RestoreCheck::WFinal), 0);
+    let mut e = Engine::new();
+    e.set_restore_order(RestoreOrder::new(&[RestoreCheck::WFinal]).unwrap());
+    type_word(&mut e, "text ");
+    assert_eq!(e.restore_stats().evaluated(RestoreCheck::WFinal), 1);
+}
+
+#[test]
+#[cfg(feature = "autocorrect")]
+fn config_restore_disabled_check_keeps_word() {
+    let mut e = Engine::new();
+    assert_eq!(type_word(&mut e, "core "), "core ");
+
+    let config = EngineConfig::from_toml("restore_disabled = \"modifier-between-vowels\"").unwrap();
+    e.apply_config(&config).unwrap();
+    assert_eq!(type_word(&mut e, "core "), "cỏe ");
+    // The other checks still restore
+    assert_eq!(type_word(&mut e, "text "), "text ");
+
+    e.set_restore_check(RestoreCheck::ModifierBetweenVowels, true);
+    assert_eq!(e.config().restore_disabled, RestoreChecks::NONE);
+    assert_eq!(type_word(&mut e, "core "), "core ");
+}
+
+#[test]
+#[cfg(feature = "autocorrect")]
+fn restore_reason_names_check_and_characters() {
+    let mut e = Engine::new();
+    let reason = |e: &mut Engine, word: &str| {
+        type_word(e, word);
+        e.last_restore_reason()
+    };
+
+    let r = reason(&mut e, "text ").unwrap();
+    assert_eq!(r.check, RestoreCheck::ModifierConsonant);
+    assert_eq!(
+        (r.matched.as_str(), r.composed.as_str(), r.restored.as_str()),
+        ("xt", "tẽt", "text")
+    );
+    assert_eq!(r.message(), "\"xt\": tone key before a consonant");
+
+    let r = reason(&mut e, "expect ").unwrap();
+    assert_eq!(
+        (r.check, r.composed.as_str()),
+        (RestoreCheck::Invalid, "ễpct")
+    );
+    assert_eq!(r.problem, Some(Problem::InvalidFinal));
+    assert_eq!(r.message(), "\"p\": not a Vietnamese final consonant");
+
+    // "hòp" also has a tone key before a consonant, which runs first by default
+    let r = reason(&mut e, "hofp ").unwrap();
+    assert_eq!(
+        (r.check, r.matched.as_str()),
+        (RestoreCheck::ModifierConsonant, "fp")
+    );
+    e.set_restore_order(RestoreOrder::new(&[RestoreCheck::MarkFinal]).unwrap());
+    let r = reason(&mut e, "hofp ").unwrap();
+    assert_eq!(
+        (r.check, r.matched.as_str()),
+        (RestoreCheck::MarkFinal, "p")
+    );
+    assert_eq!(r.problem, Some(Problem::StopFinalTone));
+
+    let r = reason(&mut e, "raw,").unwrap();
+    assert_eq!((r.check, r.matched.as_str()), (RestoreCheck::WFinal, "aw"));
+
+    // A word that stays Vietnamese clears it
+    assert_eq!(reason(&mut e, "vieetj "), None);
+}
+
+#[test]
+fn config_bracket_shortcuts() {
+    let mut e = Engine::new();
+    e.apply_config(&EngineConfig::from_toml("bracket_shortcuts = true").unwrap())
+        .unwrap();
+
+    // [ → ơ
+    e.on_key(keys::M, false, false);
+    let r = e.on_key(keys::LBRACKET, false, false);
+    assert_eq!(r.action, Action::Send as u8);
+    assert_eq!(r.backspace, 0);
+    assert_eq!(char::from_u32(r.chars[0]), Some('ơ'));
+
+    // Marks apply on top: mơ + f → mờ
+    let r = e.on_key(keys::F, false, false);
+    assert_eq!(r.backspace, 1);
+    assert_eq!(char::from_u32(r.chars[0]), Some('ờ'));
+
+    // ESC restores the bracket itself: mờ → m[f (m stays on screen)
+    let r = e.on_key(keys::ESC, false, false);
+    let restored: String = r.chars[..r.count as usize]
+        .iter()
+        .filter_map(|&c| char::from_u32(c))
+        .collect();
+    assert_eq!((r.backspace, restored.as_str()), (1, "[f"));
+
+    // ] → Ư with CapsLock; Shift+] is '}' and passes through
+    let r = e.on_key(keys::RBRACKET, true, false);
+    assert_eq!(char::from_u32(r.chars[0]), Some('Ư'));
+    e.clear();
+    let r = e.on_key_ext(keys::RBRACKET, true, false, true);
+    assert_eq!(r.action, Action::None as u8);
+}
+
+#[test]
+fn config_bracket_shortcuts_off_by_default() {
+    let mut e = Engine::new();
+    let result = type_word(&mut e, "m[");
+    assert_eq!(result, "m[");
+}
+
+#[test]
+fn observers_see_setting_changes() {
+    let mut e = Engine::new();
+    let (_, rx) = e.subscribe_channel();
+
+    let mut config = e.config();
+    config.method = Method::Vni;
+    config.tone_style = ToneStyle::Classic;
+    e.apply_config(&config).unwrap();
+    e.set_enabled(true); // already enabled: no event
+
+    let events: Vec<Notification> = rx.try_iter().collect();
+    assert_eq!(
+        events,
+        vec![
+            Notification::SettingChanged(Setting::Method(Method::Vni)),
+            Notification::SettingChanged(Setting::ToneStyle(ToneStyle::Classic)),
+        ]
+    );
+}
+
+#[test]
+#[cfg(feature = "macros")]
+fn storage_save_and_load_restores_state() {
+    let mut e = Engine::new();
+    assert!(e.save().is_err()); // no storage yet
+    e.set_storage(MemoryStorage::new());
+
+    e.set_method(1).unwrap();
+    e.shortcuts_mut().add(Shortcut::new("vn", "Việt Nam"));
+    e.save().unwrap();
+
+    e.set_method(0).unwrap();
+    e.shortcuts_mut().clear();
+    e.load().unwrap();
+
+    assert_eq!(e.config().method, Method::Vni);
+    assert_eq!(type_word(&mut e, "vn "), "Việt Nam ");
+}
+
+#[test]
+fn conflicting_saved_settings_lose_only_the_conflict() {
+    let mut saved = MemoryStorage::new();
+    let toml = "method = \"telex\"\nremove_key = \"s\"\nmax_word_len = 20\n";
+    saved
+        .write("config.toml", &storage::seal(toml.as_bytes()))
+        .unwrap();
+    let mut e = Engine::new();
+    e.set_storage(saved);
+    e.load().unwrap();
+
+    let health = e.state_health();
+    assert!(health.reset.is_empty());
+    assert!(matches!(
+        health.config_error,
+        Some(ConfigError::Conflict(_))
+    ));
+    assert_eq!(health.to_bits(), 8);
+    assert_eq!(e.config().max_word_len, 20);
+    assert_eq!(e.config().remove_key, RemoveKey::Default);
+
+    // VNI loses the bracket shortcuts
+    let mut saved = MemoryStorage::new();
+    let toml = "method = \"vni\"\nbracket_shortcuts = true\n";
+    saved
+        .write("config.toml", &storage::seal(toml.as_bytes()))
+        .unwrap();
+    e.set_storage(saved);
+    e.load().unwrap();
+    assert!(e.state_health().config_error.is_some());
+    assert_eq!(e.config().method, Method::Vni);
+    assert!(!e.config().bracket_shortcuts);
+}
+
+#[test]
+fn conflicting_settings_are_refused() {
+    let conflict = |r: Result<(), ConfigError>| matches!(r, Err(ConfigError::Conflict(_)));
+    let mut e = Engine::new();
+    e.set_bracket_shortcuts(true).unwrap();
+    assert!(conflict(e.set_method(1)));
+    assert_eq!(e.config().method, Method::Telex);
+    e.set_bracket_shortcuts(false).unwrap();
+    e.set_method(1).unwrap();
+    assert!(conflict(e.set_bracket_shortcuts(true)));
+    assert!(!e.config().bracket_shortcuts);
+
+    // A config is applied whole or not at all
+    let mut config = e.config();
+    config.max_word_len = 20;
+    config.bracket_shortcuts = true;
+    assert!(conflict(e.apply_config(&config)));
+    assert_ne!(e.config().max_word_len, 20);
+    // Settings changing together don't trip over each other
+    let config = EngineConfig::from_toml("method = \"telex\"\nbracket_shortcuts = true").unwrap();
+    e.apply_config(&config).unwrap();
+    assert!(e.config().bracket_shortcuts);
+}
+
+#[test]
+fn saved_settings_always_load_back() {
+    // A setter either applies or is refused with nothing changed
+    fn set(e: &mut Engine, setter: impl FnOnce(&mut Engine) -> Result<(), ConfigError>) {
+        let before = e.config();
+        if let Err(err) = setter(e) {
+            assert!(matches!(err, ConfigError::Conflict(_)));
+            assert_eq!(e.config(), before);
+        }
+    }
+    let remove_keys = [
+        RemoveKey::Default,
+        RemoveKey::Off,
+        RemoveKey::Key('q'),
+        RemoveKey::Key('s'),
+        RemoveKey::Key('1'),
+    ];
+    for method in [0, 1] {
+        for brackets in [false, true] {
+            for remove_key in remove_keys {
+                // The method last too: it can clash with what came before
+                for method_last in [false, true] {
+                    let mut e = Engine::new();
+                    e.set_storage(MemoryStorage::new());
+                    if !method_last {
+                        set(&mut e, |e| e.set_method(method));
+                    }
+                    set(&mut e, |e| e.set_bracket_shortcuts(brackets));
+                    e.set_max_word_len(20);
+                    set(&mut e, |e| e.set_remove_key(remove_key));
+                    if method_last {
+                        set(&mut e, |e| e.set_method(method));
+                    }
+                    let saved = e.config();
+                    assert_eq!(saved.validate(), Ok(()));
+                    e.save().unwrap();
+
+                    e.apply_config(&EngineConfig::default()).unwrap();
+                    e.load().unwrap();
+                    let case = (method, brackets, remove_key, method_last);
+                    assert!(e.state_health().is_ok(), "{:?}", case);
+                    assert_eq!(e.config(), saved, "{:?}", case);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+#[cfg(feature = "macros")]
+fn import_unikey_macros_and_evkey_settings() {
+    let mut e = Engine::new();
+    let report = e
+        .import_from(ImportFormat::UniKey, b";comment\nvn:Vi\xe1\xbb\x87t Nam\n")
+        .unwrap();
+    assert_eq!(report.shortcuts, 1);
+    assert_eq!(type_word(&mut e, "vn "), "Việt Nam ");
+
+    let report = e
+        .import_from(
+            ImportFormat::EvKey,
+            b"[Setting]\nInputType=1\nCodeTable=0\n",
+        )
+        .unwrap();
+    assert_eq!(report.skipped, ["CodeTable"]);
+    assert_eq!(e.config().method, Method::Vni);
+
+    // Invalid file: nothing applied
+    assert!(e
+        .import_from(ImportFormat::EvKey, b"InputType=0\nbroken")
+        .is_err());
+    assert_eq!(e.config().method, Method::Vni);
+}
+
+#[test]
+#[cfg(feature = "macros")]
+fn macros_shared_as_csv_and_unikey_files() {
+    let mut alice = Engine::new();
+    alice
+        .shortcuts_mut()
+        .add(Shortcut::new("ks", "Kính gửi quý khách,"));
+    alice
+        .shortcuts_mut()
+        .add(Shortcut::new("tt", "Trân trọng,\nPhòng CSKH"));
+
+    for (format, shared) in [(ImportFormat::Csv, 2), (ImportFormat::UniKey, 1)] {
+        let file = alice.export_macros(format).unwrap();
+        let mut bob = Engine::new();
+        let report = bob.import_from(format, file.text.as_bytes()).unwrap();
+        assert_eq!(report.shortcuts, shared, "{:?}", format);
+        assert_eq!(type_word(&mut bob, "ks "), "Kính gửi quý khách, ");
+    }
+}
+
+#[test]
+#[cfg(feature = "macros")]
+fn profile_moves_settings_and_shortcuts_between_engines() {
+    let mut mac = Engine::new();
+    mac.set_modern(false);
+    mac.shortcuts_mut().add(Shortcut::new("gn", "Gõ Nhanh"));
+
+    let mut win = Engine::new();
+    win.import_profile(&mac.export_profile()).unwrap();
+    assert_eq!(win.config().tone_style, ToneStyle::Classic);
+    assert_eq!(type_word(&mut win, "gn "), "Gõ Nhanh ");
+
+    // Bad profile leaves state untouched
+    assert!(win.import_profile("{\"format\": \"other\"}").is_err());
+    assert_eq!(win.config().tone_style, ToneStyle::Classic);
+}
+
+#[test]
+fn storage_recovers_from_corrupt_blob() {
+    let dir = std::env::temp_dir().join(format!("gonhanh-health-{}", std::process::id()));
+    let mut e = Engine::new();
+    e.set_storage(FileStorage::new(&dir));
+    e.set_method(1).unwrap();
+    e.shortcuts_mut().add(Shortcut::new("gn", "Gõ Nhanh"));
+    e.save().unwrap();
+
+    // Flip a byte in the settings file: checksum no longer matches
+    let path = dir.join("config.toml");
+    let mut data = std::fs::read(&path).unwrap();
+    data[0] ^= 0x20;
+    std::fs::write(&path, data).unwrap();
+
+    let mut e = Engine::new();
+    e.set_storage(FileStorage::new(&dir));
+    e.set_modern(false);
+    e.load().unwrap();
+    assert_eq!(e.state_health().reset, ["config.toml"]);
+    assert_eq!(e.config(), EngineConfig::default()); // settings reset
+    assert!(e.shortcuts().lookup("gn").is_some()); // shortcuts intact
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+/// Shared document held in memory (stands in for iCloud/OneDrive)
+#[derive(Default)]
+struct MemoryTransport(Option<Vec<u8>>);
+
+impl SyncTransport for MemoryTransport {
+    fn pull(&mut self) -> std::io::Result<Option<Vec<u8>>> {
+        Ok(self.0.clone())
+    }
+
+    fn push(&mut self, data: &[u8]) -> std::io::Result<()> {
+        self.0 = Some(data.to_vec());
+        Ok(())
+    }
+}
+
+#[test]
+fn sync_merges_changes_from_two_machines() {
+    let mut cloud = MemoryTransport::default();
+    let mut mac = Engine::new();
+    let mut win = Engine::new();
+    mac.sync(&mut cloud, "mac", 1).unwrap();
+    win.sync(&mut cloud, "win", 2).unwrap();
+
+    // Each machine changes something different
+    mac.shortcuts_mut().add(Shortcut::new("gn", "Gõ Nhanh"));
+    win.set_method(1).unwrap();
+    win.set_enabled(false);
+    mac.sync(&mut cloud, "mac", 10).unwrap();
+    win.sync(&mut cloud, "win", 20).unwrap();
+    mac.sync(&mut cloud, "mac", 30).unwrap();
+
+    for e in [&mac, &win] {
+        assert_eq!(e.config().method, Method::Vni);
+        assert!(e.shortcuts().lookup("gn").is_some());
+    }
+    // `enabled` is per machine
+    assert!(mac.config().enabled);
+    assert!(!win.config().enabled);
+
+    // Later deletion wins over the older definition
+    win.shortcuts_mut().remove("gn");
+    win.sync(&mut cloud, "win", 40).unwrap();
+    mac.sync(&mut cloud, "mac", 50).unwrap();
+    assert!(mac.shortcuts().lookup("gn").is_none());
+}
+
 // ============================================================
 // METHOD SWITCHING: Telex <-> VNI
 // ============================================================
@@ -61,7 +1579,7 @@ fn method_switch_preserves_buffer() {
     let mut e = Engine::new();
 
     e.on_key(keys::A, false, false);
-    e.set_method(1); // Switch to VNI
+    e.set_method(1).unwrap(); // Switch to VNI
 
     // VNI tone '1' works on previous 'a'
     let r = e.on_key(keys::N1, false, false);
@@ -71,7 +1589,7 @@ fn method_switch_preserves_buffer() {
 #[test]
 fn invalid_method_defaults_to_telex() {
     let mut e = Engine::new();
-    e.set_method(99); // Invalid
+    e.set_method(99).unwrap(); // Invalid
 
     let result = type_word(&mut e, "as");
     assert_eq!(result, "á");
@@ -86,13 +1604,13 @@ fn switch_telex_to_vni_and_back() {
     assert_eq!(result, "á");
 
     // Switch to VNI
-    e.set_method(1);
+    e.set_method(1).unwrap();
     e.clear();
     let result = type_word(&mut e, "a1");
     assert_eq!(result, "á");
 
     // Back to Telex
-    e.set_method(0);
+    e.set_method(0).unwrap();
     e.clear();
     let result = type_word(&mut e, "af");
     assert_eq!(result, "à");
@@ -161,168 +1679,698 @@ fn telex_w_as_vowel_after_valid_consonant() {
 }
 
 #[test]
-fn telex_w_passthrough_after_invalid_consonant() {
-    // "kw" → "kw" (invalid: k cannot precede ư)
+fn telex_w_as_vowel_reverted_by_foreign_pattern() {
+    // "woy": ươ + y isn't Vietnamese → "woy", erasing only the "ươ" on screen
+    let mut e = Engine::new();
+    e.on_key(keys::W, false, false);
+    e.on_key(keys::O, false, false);
+    let result = e.on_key(keys::Y, false, false);
+    assert_eq!(result.action, 1);
+    assert_eq!(result.backspace, 2);
+    assert_eq!(&result.chars[..3], &['w' as u32, 'o' as u32, 'y' as u32]);
+}
+
+#[test]
+fn telex_w_passthrough_after_invalid_consonant() {
+    // "kw" → "kw" (invalid: k cannot precede ư)
+    let mut e = Engine::new();
+    e.on_key(keys::K, false, false);
+    let result = e.on_key(keys::W, false, false);
+    assert_eq!(result.action, 0); // passthrough
+}
+
+#[test]
+fn telex_ww_reverts() {
+    // "ww" → revert to "w" (shortcut skipped)
+    // User typing pattern: w→ư, ww→w, www→ww
+    let mut e = Engine::new();
+
+    // First w → ư
+    let result = e.on_key(keys::W, false, false);
+    assert_eq!(result.action, 1);
+    assert_eq!(result.chars[0], 'ư' as u32);
+
+    // Second w → revert to "w" (single w, shortcut skipped)
+    let result = e.on_key(keys::W, false, false);
+    assert_eq!(result.action, 1);
+    assert_eq!(result.backspace, 1); // delete "ư"
+    assert_eq!(result.count, 1); // output "w"
+    assert_eq!(result.chars[0], 'w' as u32);
+
+    // Third w → just adds w (shortcut was skipped, not retried)
+    let result = e.on_key(keys::W, false, false);
+    assert_eq!(result.action, 0); // Pass through (normal letter)
+}
+
+#[test]
+fn vni_w_passes_through() {
+    // In VNI mode, "w" should pass through
+    let mut e = Engine::new();
+    e.set_method(1).unwrap(); // VNI
+    assert_passthrough(&mut e, keys::W);
+}
+
+#[test]
+fn word_boundary_shortcut_vn() {
+    // Default shortcuts are currently disabled
+    // This test verifies that "vn" + space just passes through
+    let mut e = Engine::new();
+
+    // Type "vn"
+    e.on_key(keys::V, false, false);
+    e.on_key(keys::N, false, false);
+
+    // Space - no shortcut expansion (shortcuts disabled)
+    let result = e.on_key(keys::SPACE, false, false);
+    assert_eq!(result.action, 0); // Action::None (passthrough)
+}
+
+#[test]
+fn vni_tone_without_vowel_passes_through() {
+    let mut e = Engine::new();
+    e.set_method(1).unwrap();
+    assert_passthrough(&mut e, keys::N1);
+}
+
+// ============================================================
+// BACKSPACE HANDLING
+// ============================================================
+
+#[test]
+fn backspace_on_empty_buffer() {
+    let mut e = Engine::new();
+    assert_passthrough(&mut e, keys::DELETE);
+}
+
+#[test]
+fn multiple_backspace_clears_all() {
+    let mut e = Engine::new();
+
+    e.on_key(keys::A, false, false);
+    e.on_key(keys::B, false, false);
+    e.on_key(keys::DELETE, false, false);
+    e.on_key(keys::DELETE, false, false);
+
+    assert_passthrough(&mut e, keys::S);
+}
+
+// ============================================================
+// CONSONANT-ONLY WORDS
+// ============================================================
+
+#[test]
+fn consonant_only_no_conversion() {
+    let mut e = Engine::new();
+    let result = type_word(&mut e, "bcd");
+    assert_eq!(result, "bcd");
+}
+
+#[test]
+fn tone_after_consonant_only() {
+    let mut e = Engine::new();
+    let result = type_word(&mut e, "bcs");
+    assert_eq!(result, "bcs");
+}
+
+// ============================================================
+// CLEAR BUFFER
+// ============================================================
+
+#[test]
+fn clear_resets_state() {
+    let mut e = Engine::new();
+
+    e.on_key(keys::A, false, false);
+    e.clear();
+    assert_passthrough(&mut e, keys::S);
+}
+
+// ============================================================
+// ORTHOGRAPHY: Modern style (hoà, not hòa)
+// ============================================================
+
+#[test]
+fn modern_orthography_hoa() {
+    let mut e = Engine::new();
+    let result = type_word(&mut e, "hoaf");
+    assert_eq!(result, "hoà"); // Modern: tone on last vowel
+}
+
+const MODERN_ORTHO_CASES: &[(&str, &str)] = &[
+    ("hoaf", "hoà"),
+    ("hoas", "hoá"),
+    ("hoar", "hoả"),
+    ("hoax", "hoã"),
+    ("hoaj", "hoạ"),
+];
+
+#[test]
+fn modern_orthography_full() {
+    for (input, expected) in MODERN_ORTHO_CASES {
+        let mut e = Engine::new();
+        let result = type_word(&mut e, input);
+        assert_eq!(result, *expected, "Modern: {} → {}", input, result);
+    }
+}
+
+#[test]
+fn classic_orthography() {
+    let cases = [
+        ("hoaf", "hòa"),
+        ("thuys", "thúy"),
+        ("khoer", "khỏe"),
+        // A final consonant or the qu initial decide in either style
+        ("hoafn", "hoàn"),
+        ("quas", "quá"),
+        ("muaf", "mùa"),
+    ];
+    for (input, expected) in cases {
+        let mut e = Engine::new();
+        e.set_modern(false);
+        assert_eq!(type_word(&mut e, input), expected, "Classic: {}", input);
+    }
+}
+
+// ============================================================
+// REVERT BEHAVIOR
+// ============================================================
+
+#[test]
+fn double_tone_reverts() {
+    telex(&[("ass", "as")]);
+}
+
+#[test]
+fn double_mark_reverts() {
+    telex(&[("aaa", "aa")]);
+}
+
+#[test]
+fn triple_same_key_behavior() {
+    let mut e = Engine::new();
+    // a → a, aa → â, aaa → aa, aaaa → aaa
+    let result = type_word(&mut e, "aaaa");
+    assert_eq!(result, "aaa");
+}
+
+// ============================================================
+// CORRECTIONS: Dialect packs
+// ============================================================
+
+#[test]
+fn correction_packs_fix_words_on_space() {
+    let mut e = Engine::new();
+    // Off by default
+    assert_eq!(type_word(&mut e, "namf "), "nàm ");
+
+    e.set_correction_packs(CorrectionPacks::NONE.with(Dialect::Southern, true));
+    e.clear();
+    assert_eq!(type_word(&mut e, "namf "), "làm ");
+    e.clear();
+    assert_eq!(type_word(&mut e, "Namf "), "Làm ");
+    // Real words and other packs' entries are left alone
+    e.clear();
+    assert_eq!(type_word(&mut e, "lamf "), "làm ");
+    e.clear();
+    assert_eq!(type_word(&mut e, "duwowuj "), "dượu ");
+}
+
+#[test]
+fn iy_style_respells_words_on_space() {
+    let mut e = Engine::new();
+    // Off by default: both spellings stay
+    assert_eq!(type_word(&mut e, "lis "), "lí ");
+    e.clear();
+    assert_eq!(type_word(&mut e, "kyx "), "kỹ ");
+
+    e.set_iy_style(IyStyle::PreferY);
+    for (input, expected) in [("lis ", "lý "), ("Quis ", "Quý "), ("vif ", "vì ")] {
+        e.clear();
+        assert_eq!(type_word(&mut e, input), expected, "'{}'", input);
+    }
+    e.set_iy_style(IyStyle::PreferI);
+    for (input, expected) in [("kyx ", "kĩ "), ("mys ", "mí "), ("my ", "my ")] {
+        e.clear();
+        assert_eq!(type_word(&mut e, input), expected, "'{}'", input);
+    }
+    assert_eq!(e.config().iy_style, IyStyle::PreferI);
+}
+
+#[test]
+fn correction_packs_fix_words_before_punctuation() {
+    let mut e = Engine::new();
+    e.set_correction_packs(CorrectionPacks::NONE.with(Dialect::Southern, true));
+    for (input, expected) in [
+        ("namf,", "làm,"),
+        ("namf.", "làm."),
+        ("namf!", "làm!"),
+        ("(namf)", "(làm)"),
+        ("\"Namf\"", "\"Làm\""),
+    ] {
+        e.clear();
+        assert_eq!(type_word_ext(&mut e, input), expected, "'{}'", input);
+    }
+
+    // A closing quote that arrives as text (another layout, an IME below)
+    e.clear();
+    type_word(&mut e, "namf");
+    let r = e.on_text('”');
+    let text: String = r.chars[..r.count as usize]
+        .iter()
+        .filter_map(|&c| char::from_u32(c))
+        .collect();
+    assert_eq!((r.backspace, text.as_str()), (3, "làm"));
+}
+
+#[test]
+fn hyphen_and_apostrophe_split_words() {
+    let mut e = Engine::new();
+    e.set_auto_restore(true);
+    e.set_correction_packs(CorrectionPacks::NONE.with(Dialect::Southern, true));
+    for (input, expected) in [
+        ("on-line ", "on-line "),
+        ("e-mail ", "e-mail "),
+        ("d'accord ", "d'accord "),
+        ("rock'n'roll ", "rock'n'roll "),
+        // Each half is its own word
+        ("ng'uowif ", "ng'ười "),
+        ("namf-namf ", "làm-làm "),
+        ("Namf-lois ", "Làm-nói "),
+    ] {
+        e.clear();
+        assert_eq!(type_word_ext(&mut e, input), expected, "'{}'", input);
+    }
+}
+
+#[test]
+fn correction_packs_toggle_independently() {
+    let mut e = Engine::new();
+    let (_, rx) = e.subscribe_channel();
+    let packs = CorrectionPacks::NONE
+        .with(Dialect::Northern, true)
+        .with(Dialect::Central, true);
+    e.set_correction_packs(packs);
+    assert_eq!(
+        rx.try_recv(),
+        Ok(Notification::SettingChanged(Setting::CorrectionPacks(
+            packs
+        )))
+    );
+    assert_eq!(e.config().correction_packs, packs);
+
+    assert_eq!(type_word(&mut e, "duwowuj "), "rượu ");
+    e.clear();
+    assert_eq!(type_word(&mut e, "nhuwngr "), "những ");
+    e.clear();
+    assert_eq!(type_word(&mut e, "namf "), "nàm ");
+}
+
+#[test]
+fn suggested_corrections_wait_for_accept_key() {
     let mut e = Engine::new();
-    e.on_key(keys::K, false, false);
-    let result = e.on_key(keys::W, false, false);
-    assert_eq!(result.action, 0); // passthrough
+    e.set_correction_packs(CorrectionPacks::NONE.with(Dialect::Southern, true));
+    e.set_correction_style(CorrectionStyle::Suggest);
+    let (_, rx) = e.subscribe_channel();
+
+    // The word stays as typed, the fix is on offer
+    assert_eq!(type_word(&mut e, "namf "), "nàm ");
+    assert_eq!(rx.try_recv(), Ok(Notification::SuggestionOffered));
+    let offer = e.suggestion().unwrap();
+    assert_eq!((offer.word.as_str(), offer.fixed()), ("nàm", "làm"));
+
+    let r = e.on_key(keys::TAB, false, false);
+    assert_eq!(r.action, Action::Send as u8);
+    let text: String = r.chars[..r.count as usize]
+        .iter()
+        .filter_map(|&c| char::from_u32(c))
+        .collect();
+    assert_eq!((r.backspace, text.as_str()), (4, "làm "));
+    assert_eq!(rx.try_recv(), Ok(Notification::SuggestionCleared));
+    assert_eq!(e.suggestion(), None);
+
+    // Any other key drops the offer and does its usual job
+    type_word(&mut e, "namf ");
+    assert_eq!(type_word(&mut e, "a"), "a");
+    assert_eq!(e.suggestion(), None);
+    let r = e.on_key(keys::TAB, false, false);
+    assert_eq!(r.action, Action::None as u8);
+
+    // A configurable key; closing quotes typed as text are known too
+    e.set_accept_key(AcceptKey::Backquote);
+    e.clear();
+    type_word(&mut e, "namf");
+    e.on_text('”');
+    assert_eq!(e.suggestion().map(|s| s.after), Some('”'));
+    let r = e.on_key(keys::BACKQUOTE, false, false);
+    assert_eq!(r.backspace, 4);
+
+    // A punctuation key's character depends on the layout: nothing to offer
+    e.clear();
+    assert_eq!(type_word_ext(&mut e, "namf,"), "nàm,");
+    assert_eq!(e.suggestion(), None);
+
+    e.set_correction_style(CorrectionStyle::Replace);
+    e.clear();
+    assert_eq!(type_word(&mut e, "namf "), "làm ");
+}
+
+/// Backspaces and text of the result for `key`
+#[cfg(feature = "suggestions")]
+fn edit_for(e: &mut Engine, key: u16) -> (u8, u8, String) {
+    let r = e.on_key(key, false, false);
+    let text = r.chars[..r.count as usize]
+        .iter()
+        .filter_map(|&c| char::from_u32(c))
+        .collect();
+    (r.action, r.backspace, text)
 }
 
 #[test]
-fn telex_ww_reverts() {
-    // "ww" → revert to "w" (shortcut skipped)
-    // User typing pattern: w→ư, ww→w, www→ww
+#[cfg(feature = "suggestions")]
+fn candidate_keys_work_the_same_everywhere() {
+    let send = Action::Send as u8;
+    let swallowed = (send, 0, String::new());
     let mut e = Engine::new();
+    e.set_correction_packs(CorrectionPacks::NONE.with(Dialect::Southern, true));
+    e.set_correction_style(CorrectionStyle::Suggest);
+    let (_, rx) = e.subscribe_channel();
 
-    // First w → ư
-    let result = e.on_key(keys::W, false, false);
-    assert_eq!(result.action, 1);
-    assert_eq!(result.chars[0], 'ư' as u32);
+    type_word(&mut e, "namf ");
+    assert_eq!(rx.try_recv(), Ok(Notification::SuggestionOffered));
+    let candidates = e.suggestion().unwrap().candidates.clone();
+    assert_eq!(candidates[0], "làm");
+    assert!(candidates.len() > 1, "{:?}", candidates);
 
-    // Second w → revert to "w" (single w, shortcut skipped)
-    let result = e.on_key(keys::W, false, false);
-    assert_eq!(result.action, 1);
-    assert_eq!(result.backspace, 1); // delete "ư"
-    assert_eq!(result.count, 1); // output "w"
-    assert_eq!(result.chars[0], 'w' as u32);
+    // Down/Up move the selection and wrap; the screen stays
+    assert_eq!(edit_for(&mut e, keys::DOWN), swallowed);
+    assert_eq!(rx.try_recv(), Ok(Notification::SuggestionSelected(1)));
+    assert_eq!(edit_for(&mut e, keys::UP), swallowed);
+    assert_eq!(edit_for(&mut e, keys::UP), swallowed);
+    let last = candidates.len() - 1;
+    assert_eq!(e.suggestion().unwrap().selected, last);
+    assert_eq!(e.suggestion().unwrap().fixed(), candidates[last]);
 
-    // Third w → just adds w (shortcut was skipped, not retried)
-    let result = e.on_key(keys::W, false, false);
-    assert_eq!(result.action, 0); // Pass through (normal letter)
+    // A number picks straight from the list
+    assert_eq!(
+        edit_for(&mut e, keys::N2),
+        (send, 4, format!("{} ", candidates[1]))
+    );
+    assert_eq!(e.suggestion(), None);
+
+    // Esc closes the list and leaves the word
+    e.clear();
+    type_word(&mut e, "namf ");
+    edit_for(&mut e, keys::DOWN);
+    while rx.try_recv().is_ok() {}
+    assert_eq!(edit_for(&mut e, keys::ESC), swallowed);
+    assert_eq!(rx.try_recv(), Ok(Notification::SuggestionCleared));
+    assert_eq!(e.suggestion(), None);
+
+    // Until the list is browsed, numbers are just typed
+    e.clear();
+    assert_eq!(type_word(&mut e, "namf 2"), "nàm 2");
+    assert_eq!(e.suggestion(), None);
 }
 
 #[test]
-fn vni_w_passes_through() {
-    // In VNI mode, "w" should pass through
+#[cfg(feature = "suggestions")]
+fn lint_uses_engine_correction_packs() {
     let mut e = Engine::new();
-    e.set_method(1); // VNI
-    assert_passthrough(&mut e, keys::W);
+    let text = "Nàm việc, lí do";
+    assert_eq!(e.lint(text), []);
+    e.set_correction_packs(CorrectionPacks::NONE.with(Dialect::Southern, true));
+    let issues = e.lint(text);
+    assert_eq!(issues.len(), 1);
+    assert_eq!(issues[0].span, 0..4);
+    assert_eq!(issues[0].suggestions, ["Làm"]);
 }
 
 #[test]
-fn word_boundary_shortcut_vn() {
-    // Default shortcuts are currently disabled
-    // This test verifies that "vn" + space just passes through
+fn community_pack_plugs_into_engine() {
+    let body = r#"{
+        "format": "gonhanh-correction-pack", "format_version": 1,
+        "name": "medical", "version": "1.0.0", "language": "vi",
+        "entries": [
+            { "typed": "thuốt", "correct": "thuốc", "after": ["uống"] },
+            { "typed": "nàm", "correct": "nằm" }
+        ]
+    }"#;
+    let pack =
+        CorrectionPack::load(body.as_bytes(), &Checksum::sign(body.as_bytes()), &Checksum).unwrap();
     let mut e = Engine::new();
+    e.set_privacy(Privacy::KEEP);
+    e.set_correction_packs(CorrectionPacks::NONE.with(Dialect::Southern, true));
+    e.add_correction_provider(pack);
 
-    // Type "vn"
-    e.on_key(keys::V, false, false);
-    e.on_key(keys::N, false, false);
+    // Asked before the dialect packs
+    assert_eq!(type_word(&mut e, "namf "), "nằm ");
+    // Context rule: previous word from history
+    assert_eq!(type_word(&mut e, "uoongs "), "uống ");
+    assert_eq!(type_word(&mut e, "thuoots "), "thuốc ");
+    assert_eq!(type_word(&mut e, "thuoots "), "thuốt ");
 
-    // Space - no shortcut expansion (shortcuts disabled)
-    let result = e.on_key(keys::SPACE, false, false);
-    assert_eq!(result.action, 0); // Action::None (passthrough)
+    // No history, no context
+    e.set_privacy(Privacy::OFF);
+    assert_eq!(type_word(&mut e, "uoongs "), "uống ");
+    assert_eq!(type_word(&mut e, "thuoots "), "thuốt ");
 }
 
+// ============================================================
+// NBSP: Shift+Space
+// ============================================================
+
 #[test]
-fn vni_tone_without_vowel_passes_through() {
+fn shift_space_types_nbsp_without_finishing_word() {
     let mut e = Engine::new();
-    e.set_method(1);
-    assert_passthrough(&mut e, keys::N1);
-}
+    e.set_correction_packs(CorrectionPacks::NONE.with(Dialect::Southern, true));
 
-// ============================================================
-// BACKSPACE HANDLING
-// ============================================================
+    // Off: Shift+Space is a space like any other
+    assert_eq!(type_word(&mut e, "namf"), "nàm");
+    let r = e.on_key_ext(keys::SPACE, false, false, true);
+    assert_eq!(r.action, Action::Send as u8);
+    assert_eq!(r.chars[r.count as usize - 1], ' ' as u32);
+
+    for (nbsp, c) in [(Nbsp::Regular, '\u{00A0}'), (Nbsp::Narrow, '\u{202F}')] {
+        e.set_nbsp(nbsp);
+        assert_eq!(e.config().nbsp, nbsp);
+        // No correction, no restore: the word stays as shown
+        for word in ["namf", "tesst"] {
+            type_word(&mut e, word);
+            let r = e.on_key_ext(keys::SPACE, false, false, true);
+            assert_eq!((r.backspace, r.count), (0, 1), "{}", word);
+            assert_eq!(r.chars[0], c as u32);
+        }
+        // Plain Space still finishes words
+        assert_eq!(type_word(&mut e, "namf "), "làm ");
+    }
+}
 
 #[test]
-fn backspace_on_empty_buffer() {
+fn nbsp_keeps_the_word_being_typed() {
+    let mut e = Engine::new();
+    e.set_privacy(Privacy::KEEP);
+    e.set_nbsp(Nbsp::Regular);
+
+    // Backspace takes the space back and the word goes on
+    type_word(&mut e, "vieet");
+    e.on_key_ext(keys::SPACE, false, false, true);
+    let info = e.word_info().unwrap();
+    assert_eq!((info.start, info.end, info.committed), (-5, -1, false));
+    assert_eq!(e.check_field("viêt\u{00A0}"), FieldCheck::Match);
+    assert_passthrough(&mut e, keys::DELETE);
+    assert_transforms(&mut e, keys::J);
+    assert_eq!(e.word_info().unwrap().text, "việt");
+
+    // Any other key commits the word as shown, then starts a new one
+    e.on_key_ext(keys::SPACE, false, false, true);
+    assert_eq!(type_word(&mut e, "nam"), "nam");
+    assert_eq!(e.recent_words().next(), Some("việt"));
+
+    // Held-back edits reach the screen before the space
     let mut e = Engine::new();
+    e.set_nbsp(Nbsp::Regular);
+    e.set_output_profile(OutputProfile::BOUNDARY);
+    type_word(&mut e, "vieetj");
+    let r = e.on_key_ext(keys::SPACE, false, false, true);
+    let text: String = r.chars[..r.count as usize]
+        .iter()
+        .filter_map(|&c| char::from_u32(c))
+        .collect();
+    assert_eq!((r.backspace, text.as_str()), (4, "ệt\u{00A0}"));
     assert_passthrough(&mut e, keys::DELETE);
+    // "việt" on screen: keep "vi", replace "ệt"
+    assert_eq!(type_word(&mut e, "s "), "ết ");
 }
 
 #[test]
-fn multiple_backspace_clears_all() {
+fn nbsp_chord_is_configurable() {
     let mut e = Engine::new();
+    e.set_nbsp(Nbsp::Narrow);
+    e.set_nbsp_chord(NbspChord::AltSpace);
+    assert_eq!(e.config().nbsp_chord, NbspChord::AltSpace);
 
-    e.on_key(keys::A, false, false);
-    e.on_key(keys::B, false, false);
-    e.on_key(keys::DELETE, false, false);
-    e.on_key(keys::DELETE, false, false);
+    // Shift+Space is a plain space again
+    type_word(&mut e, "vieet");
+    e.on_key_ext(keys::SPACE, false, false, true);
+    assert!(e.word_info().unwrap().committed);
 
-    assert_passthrough(&mut e, keys::S);
+    type_word(&mut e, "vieet");
+    let r = e.on_event(KeyEvent::down(keys::SPACE, modifier::ALT));
+    assert_eq!((r.backspace, r.count), (0, 1));
+    assert_eq!(r.chars[0], '\u{202F}' as u32);
+    assert!(!e.word_info().unwrap().committed);
+    // Other modifiers with it are another chord
+    e.clear();
+    let r = e.on_event(KeyEvent::down(keys::SPACE, modifier::ALT | modifier::CMD));
+    assert_eq!(r.action, Action::None as u8);
 }
 
 // ============================================================
-// CONSONANT-ONLY WORDS
+// IDLE COMMIT: Stale words are left alone
 // ============================================================
 
+fn type_at(e: &mut Engine, time_ms: u64, keys: &[u16]) -> Action {
+    let mut last = Action::None;
+    for &k in keys {
+        let r = e.on_key_at(time_ms, k, false, false, false);
+        last = if r.action == Action::Send as u8 {
+            Action::Send
+        } else {
+            Action::None
+        };
+    }
+    last
+}
+
 #[test]
-fn consonant_only_no_conversion() {
+fn idle_word_is_committed_before_next_key() {
+    let vieet = [keys::V, keys::I, keys::E, keys::E, keys::T];
     let mut e = Engine::new();
-    let result = type_word(&mut e, "bcd");
-    assert_eq!(result, "bcd");
+    e.set_idle_commit_secs(5);
+    assert_eq!(e.config().idle_commit_secs, 5);
+
+    // Within the limit: j still marks the word
+    type_at(&mut e, 1_000, &vieet);
+    assert_eq!(type_at(&mut e, 5_999, &[keys::J]), Action::Send);
+    e.clear();
+
+    // Past it: the word was committed, j starts a new one
+    type_at(&mut e, 10_000, &vieet);
+    assert_eq!(type_at(&mut e, 15_000, &[keys::J]), Action::None);
+    e.clear();
+
+    // Untimed keys and the setting off never commit
+    type_at(&mut e, 20_000, &vieet);
+    assert_eq!(
+        e.on_key_ext(keys::J, false, false, false).action,
+        Action::Send as u8
+    );
+    e.clear();
+    e.set_idle_commit_secs(0);
+    type_at(&mut e, 30_000, &vieet);
+    assert_eq!(type_at(&mut e, 90_000, &[keys::J]), Action::Send);
 }
 
 #[test]
-fn tone_after_consonant_only() {
+fn idle_commit_with_key_events() {
     let mut e = Engine::new();
-    let result = type_word(&mut e, "bcs");
-    assert_eq!(result, "bcs");
+    e.set_idle_commit_secs(2);
+    for (i, k) in [keys::A, keys::A].into_iter().enumerate() {
+        e.on_event(KeyEvent::down(k, 0).at(100 + i as u64));
+        e.on_event(KeyEvent::up(k, 0).at(100 + i as u64));
+    }
+    let r = e.on_event(KeyEvent::down(keys::S, 0).at(2_200));
+    assert_eq!(r.action, Action::None as u8);
 }
 
 // ============================================================
-// CLEAR BUFFER
+// MOUSE: Clicks and scrolls
 // ============================================================
 
 #[test]
-fn clear_resets_state() {
+fn click_ends_word_scroll_keeps_it() {
     let mut e = Engine::new();
+    e.set_privacy(Privacy::KEEP);
+    type_word(&mut e, "vieet");
+    e.on_mouse(MouseEvent::Scroll);
+    assert_transforms(&mut e, keys::J);
 
-    e.on_key(keys::A, false, false);
     e.clear();
-    assert_passthrough(&mut e, keys::S);
+    type_word(&mut e, "vieet");
+    e.on_mouse(MouseEvent::Click);
+    assert_passthrough(&mut e, keys::J);
+    assert_eq!(e.recent_words().next(), Some("viêt"));
 }
 
-// ============================================================
-// ORTHOGRAPHY: Modern style (hoà, not hòa)
-// ============================================================
-
 #[test]
-fn modern_orthography_hoa() {
+fn mouse_actions_are_configurable() {
     let mut e = Engine::new();
-    let result = type_word(&mut e, "hoaf");
-    assert_eq!(result, "hoà"); // Modern: tone on last vowel
-}
+    e.set_privacy(Privacy::KEEP);
+    e.set_mouse_actions(MouseAction::Clear, MouseAction::Commit);
+    assert_eq!(e.config().mouse_click, MouseAction::Clear);
+    assert_eq!(e.config().mouse_scroll, MouseAction::Commit);
 
-const MODERN_ORTHO_CASES: &[(&str, &str)] = &[
-    ("hoaf", "hoà"),
-    ("hoas", "hoá"),
-    ("hoar", "hoả"),
-    ("hoax", "hoã"),
-    ("hoaj", "hoạ"),
-];
+    type_word(&mut e, "vieet");
+    e.on_mouse(MouseEvent::Scroll);
+    assert_passthrough(&mut e, keys::J);
 
-#[test]
-fn modern_orthography_full() {
-    for (input, expected) in MODERN_ORTHO_CASES {
-        let mut e = Engine::new();
-        let result = type_word(&mut e, input);
-        assert_eq!(result, *expected, "Modern: {} → {}", input, result);
-    }
+    // Clear forgets the word instead of recording it
+    e.clear();
+    type_word(&mut e, "tieen");
+    e.on_mouse(MouseEvent::Click);
+    assert_eq!(e.recent_words().next(), Some("viêt"));
 }
 
 // ============================================================
-// REVERT BEHAVIOR
+// REMOVE KEY: Replacing and stripping marks
 // ============================================================
 
 #[test]
-fn double_tone_reverts() {
-    telex(&[("ass", "as")]);
-}
+fn mark_replacement_retypes_changed_tail_only() {
+    let mut e = Engine::new();
+    type_word(&mut e, "mays");
+    // máy → mày: "m" stays
+    let r = e.on_key(keys::F, false, false);
+    assert_eq!((r.action, r.backspace, r.count), (Action::Send as u8, 2, 2));
+    assert_eq!(char::from_u32(r.chars[0]), Some('à'));
 
-#[test]
-fn double_mark_reverts() {
-    telex(&[("aaa", "aa")]);
+    // mày → may
+    let r = e.on_key(keys::Z, false, false);
+    assert_eq!((r.backspace, r.count), (2, 2));
+    assert_eq!(char::from_u32(r.chars[0]), Some('a'));
+
+    // Nothing to strip: z is a plain letter
+    assert_passthrough(&mut e, keys::Z);
 }
 
 #[test]
-fn triple_same_key_behavior() {
+fn remove_key_is_configurable() {
+    let typed = |method: u8, key: RemoveKey, input: &str| {
+        let mut e = Engine::new();
+        e.set_method(method).unwrap();
+        e.set_remove_key(key).unwrap();
+        type_word(&mut e, input)
+    };
+
+    assert_eq!(typed(0, RemoveKey::Off, "maysz"), "máyz");
+    assert_eq!(typed(0, RemoveKey::Key('q'), "maysq"), "may");
+    assert_eq!(typed(0, RemoveKey::Key('q'), "maysz"), "máyz");
+    assert_eq!(typed(1, RemoveKey::Off, "may10"), "máy0");
+    assert_eq!(typed(1, RemoveKey::Key('q'), "may1q"), "may");
+    assert_eq!(typed(1, RemoveKey::Key('q'), "may10"), "máy0");
+    // A key that already means something keeps its meaning: refused
     let mut e = Engine::new();
-    // a → a, aa → â, aaa → aa, aaaa → aâ
-    let result = type_word(&mut e, "aaaa");
-    assert_eq!(result, "aâ");
+    e.set_method(1).unwrap();
+    let err = e.set_remove_key(RemoveKey::Key('1')).unwrap_err();
+    assert!(matches!(err, ConfigError::Conflict(_)));
+    assert_eq!(e.config().remove_key, RemoveKey::Default);
+    assert_eq!(type_word(&mut e, "may1"), "máy");
+
+    let config = EngineConfig::from_toml("method = \"vni\"\nremove_key = \"off\"").unwrap();
+    let mut e = Engine::new();
+    e.apply_config(&config).unwrap();
+    assert_eq!(e.config().remove_key, RemoveKey::Off);
+    assert_eq!(type_word(&mut e, "may10"), "máy0");
 }
 
 // ============================================================
@@ -354,6 +2402,54 @@ fn repeated_typing_sessions() {
     assert_eq!(result2, "chào");
 }
 
+// ============================================================
+// LONG TOKENS: max_word_len and overflow policy
+// ============================================================
+
+/// Base64-looking token, `len` characters
+fn long_token(len: usize) -> String {
+    "dGhpcyBpcyBhIGxvbmcgdG9rZW4gZm9yIHRoZSBlbmdpbmU"
+        .chars()
+        .cycle()
+        .take(len)
+        .collect()
+}
+
+#[test]
+fn long_token_passes_through() {
+    let token = long_token(10_000);
+    let limit = EngineConfig::default().max_word_len as usize;
+    let mut e = Engine::new();
+    let head = type_word(&mut e, &token[..limit]);
+
+    let mut e = Engine::new();
+    let screen = type_word(&mut e, &format!("{} vieetj ", token));
+    assert_eq!(screen, format!("{}{} việt ", head, &token[limit..]));
+}
+
+#[test]
+fn long_token_keeps_tail() {
+    let mut e = Engine::new();
+    e.set_max_word_len(3);
+    e.set_overflow_policy(OverflowPolicy::KeepTail);
+    // Only "hoa" is left to take the mark
+    assert_eq!(type_word(&mut e, "xyzhoaf "), "xyzhoà ");
+
+    e.set_overflow_policy(OverflowPolicy::PassThrough);
+    assert_eq!(type_word(&mut e, "xyzhoaf "), "xyzhoaf ");
+    assert_eq!(type_word(&mut e, "hoaf "), "hoà ");
+}
+
+#[test]
+fn long_token_keeps_tail_bounded() {
+    let token = long_token(10_000);
+    let mut e = Engine::new();
+    e.set_overflow_policy(OverflowPolicy::KeepTail);
+    let screen = type_word(&mut e, &token);
+    assert_eq!(screen.chars().count(), token.len());
+    assert!(e.word_info().unwrap().text.chars().count() <= 32);
+}
+
 // ============================================================
 // EDGE: Special sequences
 // ============================================================
@@ -639,7 +2735,7 @@ fn vni_shift_2_passes_through_for_at_symbol() {
     // VNI: "hi" + Shift+2 should NOT apply huyền mark
     // User wants to type "hi@", not "hì"
     let mut e = Engine::new();
-    e.set_method(1); // VNI
+    e.set_method(1).unwrap(); // VNI
 
     // Type "hi"
     e.on_key(keys::H, false, false);
@@ -658,7 +2754,7 @@ fn vni_shift_2_passes_through_for_at_symbol() {
 fn vni_shift_numbers_all_pass_through() {
     // All Shift+number combinations should pass through in VNI
     let mut e = Engine::new();
-    e.set_method(1); // VNI
+    e.set_method(1).unwrap(); // VNI
 
     // Type a vowel first
     e.on_key(keys::A, false, false);
@@ -692,7 +2788,7 @@ fn vni_shift_numbers_all_pass_through() {
 fn vni_without_shift_still_applies_marks() {
     // VNI: Without shift, number keys should still apply marks
     let mut e = Engine::new();
-    e.set_method(1); // VNI
+    e.set_method(1).unwrap(); // VNI
 
     // Type "a" + "2" (no shift) = à
     e.on_key(keys::A, false, false);
@@ -711,7 +2807,7 @@ fn telex_shift_not_affected() {
     // Telex mode should not be affected by the shift parameter
     // (Telex doesn't use number keys for marks)
     let mut e = Engine::new();
-    e.set_method(0); // Telex
+    e.set_method(0).unwrap(); // Telex
 
     // Type "a" + Shift+2 - should just pass through (2 is not a Telex modifier)
     e.on_key(keys::A, false, false);
@@ -730,6 +2826,7 @@ fn telex_shift_not_affected() {
 use gonhanh_core::engine::shortcut::Shortcut;
 
 #[test]
+#[cfg(feature = "macros")]
 fn shortcut_triggers_on_space() {
     let mut e = Engine::new();
 
@@ -745,6 +2842,331 @@ fn shortcut_triggers_on_space() {
 }
 
 #[test]
+#[cfg(feature = "macros")]
+fn shortcut_caret_marker_moves_the_caret_back() {
+    let mut e = Engine::new();
+    e.shortcuts_mut().add(Shortcut::new("ks", "Kính gửi {|},"));
+    e.set_caret(Some(0));
+
+    assert_eq!(type_word(&mut e, "ks "), "Kính gửi , ");
+    assert_eq!(e.caret_back(), 2);
+    // "Kính gửi |, "
+    assert_eq!(e.caret(), Some(9));
+
+    // Only for the key that expanded
+    type_word(&mut e, "a");
+    assert_eq!(e.caret_back(), 0);
+    assert_eq!(e.caret(), Some(10));
+}
+
+#[test]
+#[cfg(feature = "macros")]
+fn shortcut_expands_on_tab_when_turned_on() {
+    let mut e = Engine::new();
+    e.shortcuts_mut().add(Shortcut::new("vn", "Việt Nam"));
+    e.shortcuts_mut().add(Shortcut::new("toi", "Tôi là An"));
+
+    // Off by default: Tab only ends the word
+    type_word(&mut e, "vn");
+    assert_passthrough(&mut e, keys::TAB);
+
+    e.set_tab_shortcuts(true);
+    assert!(e.config().tab_shortcuts);
+    type_word(&mut e, "vn");
+    let r = e.on_key(keys::TAB, false, false);
+    let text: String = r.chars[..r.count as usize]
+        .iter()
+        .filter_map(|&c| char::from_u32(c))
+        .collect();
+    assert_eq!((r.backspace, text.as_str()), (2, "Việt Nam"));
+
+    // Matched on the letters, like space does
+    type_word(&mut e, "tooi");
+    assert_transforms(&mut e, keys::TAB);
+
+    // A word that only starts a trigger, or runs past one, keeps its Tab
+    type_word(&mut e, "v");
+    assert_passthrough(&mut e, keys::TAB);
+    type_word(&mut e, "vnx");
+    assert_passthrough(&mut e, keys::TAB);
+
+    // Space still expands with a space after it
+    assert_eq!(type_word(&mut e, "vn "), "Việt Nam ");
+}
+
+#[test]
+fn known_typo_shows_while_typed() {
+    let mut e = Engine::new();
+    type_word(&mut e, "muns");
+    assert_eq!(e.typo(), None);
+    e.clear();
+
+    // Turning a pack on mid-word catches the word as it stands
+    type_word(&mut e, "muns");
+    e.set_correction_packs(CorrectionPacks::NONE.with(Dialect::Southern, true));
+    assert_eq!(e.typo(), Some("muốn"));
+    e.on_key(keys::DELETE, false, false);
+    assert_eq!(e.typo(), None);
+    e.clear();
+
+    // Known as soon as the tone is on, in any case; gone with a letter more
+    type_word(&mut e, "mun");
+    assert_eq!(e.typo(), None);
+    type_word(&mut e, "s");
+    assert_eq!(e.typo(), Some("muốn"));
+    type_word(&mut e, "g");
+    assert_eq!(e.typo(), None);
+    e.clear();
+    type_word(&mut e, "MUNS");
+    assert_eq!(e.typo(), Some("muốn"));
+    e.clear();
+
+    // The boundary fixes what was flagged, and only that
+    assert_eq!(type_word(&mut e, "muns mun "), "muốn mun ");
+    assert_eq!(e.typo(), None);
+}
+
+#[test]
+fn spell_status_follows_the_word() {
+    use gonhanh_core::engine::spell::SpellStatus;
+    let mut e = Engine::new();
+    let (_, rx) = e.subscribe_channel();
+    type_word(&mut e, "bl");
+    assert_eq!(e.spell_status(), SpellStatus::Invalid);
+    assert!(rx.try_recv().is_err());
+    e.clear();
+
+    e.watch_spell_status(true);
+    let events = |rx: &std::sync::mpsc::Receiver<Notification>| {
+        rx.try_iter()
+            .map(|n| match n {
+                Notification::SpellStatus(status) => status,
+                other => panic!("{:?}", other),
+            })
+            .collect::<Vec<_>>()
+    };
+    // A tone changes no letter, so no status
+    type_word(&mut e, "bas");
+    assert_eq!(events(&rx), [SpellStatus::Vietnamese]);
+    type_word(&mut e, "l");
+    e.on_key(keys::DELETE, false, false);
+    assert_eq!(events(&rx), [SpellStatus::Invalid, SpellStatus::Vietnamese]);
+    type_word(&mut e, " ");
+    assert_eq!(events(&rx), [SpellStatus::Empty]);
+    assert_eq!(e.spell_status(), SpellStatus::Empty);
+}
+
+#[test]
+#[cfg(feature = "macros")]
+fn shortcut_added_mid_word_expands() {
+    let mut e = Engine::new();
+    e.shortcuts_mut().add(Shortcut::new("hn", "Hà Nội"));
+    type_word(&mut e, "vn");
+    e.shortcuts_mut().add(Shortcut::new("vn", "Việt Nam"));
+    let r = e.on_key(keys::SPACE, false, false);
+    assert_eq!((r.action, r.backspace), (Action::Send as u8, 2));
+}
+
+#[test]
+#[cfg(feature = "macros")]
+fn shortcut_placeholders_fill_in_on_expansion() {
+    use gonhanh_core::engine::macros::{Date, Time};
+    let mut e = Engine::new();
+    e.set_storage(MemoryStorage::new());
+    e.shortcuts_mut().add(Shortcut::new("bug", "BUG-%n"));
+    e.shortcuts_mut().add(Shortcut::new("log", "[%d %t]"));
+
+    assert_eq!(type_word(&mut e, "bug bug "), "BUG-1 BUG-2 ");
+    // No clock yet: the time is left as typed
+    assert_eq!(type_word(&mut e, "log "), "[%d %t] ");
+    e.set_date(Date::new(2026, 10, 16));
+    assert_eq!(type_word(&mut e, "log "), "[16/10/2026 %t] ");
+    e.set_clock(|| Some((Date::new(2026, 10, 17)?, Time::new(8, 30)?)));
+    assert_eq!(type_word(&mut e, "log "), "[17/10/2026 08:30] ");
+
+    // The counter is saved with the shortcuts
+    e.save().unwrap();
+    e.shortcuts_mut().clear();
+    e.load().unwrap();
+    assert_eq!(type_word(&mut e, "bug "), "BUG-3 ");
+}
+
+#[test]
+#[cfg(feature = "macros")]
+fn shortcut_conflicts_are_reported_before_saving() {
+    use gonhanh_core::engine::shortcut::Conflict;
+    let mut e = Engine::new();
+    e.set_correction_packs(CorrectionPacks::NONE.with(Dialect::Southern, true));
+    e.shortcuts_mut().add(Shortcut::new("vn", "Việt Nam"));
+
+    assert!(e
+        .check_shortcut(&Shortcut::new("ks", "Kính gửi"))
+        .is_empty());
+    // "toi " also expands where "tôi" or "tới" was meant
+    e.shortcuts_mut().add(Shortcut::new("toi", "Tôi là An"));
+    assert_eq!(type_word(&mut e, "tooi "), "Tôi là An ");
+    e.shortcuts_mut().remove("toi");
+    assert_eq!(
+        e.check_shortcut(&Shortcut::new("Toi", "Tôi là An")),
+        [Conflict::Word("toi".into())]
+    );
+    assert_eq!(
+        e.check_shortcut(&Shortcut::new("mun", "mùng")),
+        [
+            Conflict::Word("mun".into()),
+            Conflict::Correction {
+                word: "mún".into(),
+                fixed: "muốn".into()
+            }
+        ]
+    );
+    assert_eq!(
+        e.check_shortcut(&Shortcut::new("vnd", "Việt Nam đồng")),
+        [Conflict::Prefix("vn".into())]
+    );
+    // Replacing "vn" isn't a conflict with itself
+    assert!(e.check_shortcut(&Shortcut::new("vn", "VN")).is_empty());
+
+    e.shortcuts_mut().add(Shortcut::new("vnd", "Việt Nam đồng"));
+    e.shortcuts_mut().add(Shortcut::new("an", "anh"));
+    let found: Vec<_> = e
+        .shortcut_conflicts()
+        .into_iter()
+        .map(|c| (c.trigger, c.conflict))
+        .collect();
+    assert_eq!(
+        found,
+        [
+            ("an".to_string(), Conflict::Word("an".into())),
+            ("vn".to_string(), Conflict::Prefix("vnd".into())),
+            ("vnd".to_string(), Conflict::Prefix("vn".into())),
+        ]
+    );
+}
+
+#[test]
+#[cfg(feature = "macros")]
+fn dynamic_macros_expand_on_space() {
+    use gonhanh_core::engine::macros::Date;
+    let mut e = Engine::new();
+    assert_eq!(
+        type_word(&mut e, "=1234d "),
+        "một nghìn hai trăm ba mươi tư đồng "
+    );
+    assert_eq!(type_word(&mut e, "=date "), "=date ");
+    e.set_date(Date::new(2026, 10, 16));
+    assert_eq!(type_word(&mut e, "=date "), "ngày 16 tháng 10 năm 2026 ");
+    // Typed raw otherwise, like a spreadsheet formula
+    assert_eq!(type_word(&mut e, "=sum "), "=sum ");
+    assert_eq!(type_word(&mut e, "=vieetj "), "=vieetj ");
+    assert_eq!(type_word(&mut e, "=12<d "), "một đồng ");
+}
+
+#[test]
+#[cfg(feature = "macros")]
+fn money_shorthand_is_opt_in() {
+    let mut e = Engine::new();
+    assert_eq!(type_word(&mut e, "25k "), "25k ");
+    e.apply_config(&EngineConfig::from_toml("money_shorthand = true").unwrap())
+        .unwrap();
+    assert_eq!(
+        type_word(&mut e, "gias 25k, ship 1m2 "),
+        "giá 25k, ship 1,2 triệu "
+    );
+    assert_eq!(type_word(&mut e, "25k "), "25.000₫ ");
+    // Not after other characters, not other units
+    assert_eq!(type_word(&mut e, "x*25k "), "x*25k ");
+    assert_eq!(type_word(&mut e, "x 2mm "), "x 2mm ");
+    assert_eq!(type_word(&mut e, "x 25kg "), "x 25kg ");
+}
+
+#[test]
+#[cfg(feature = "rules")]
+fn word_rules_run_at_word_boundary() {
+    use gonhanh_core::engine::rules::Rules;
+    let mut e = Engine::new();
+    e.set_rules(
+        Rules::parse(
+            "when keys ends \"wr\" and app is \"com.microsoft.VSCode\" then restore\n\
+             when word is \"ko\" then replace \"không\"",
+        )
+        .unwrap(),
+    );
+    assert_eq!(type_word(&mut e, "ko "), "không ");
+    assert_eq!(type_word(&mut e, "ko,"), "không,");
+    assert_eq!(type_word(&mut e, "uwr "), "ử ");
+    e.set_app(Some("com.microsoft.VSCode"));
+    assert_eq!(type_word(&mut e, "uwr "), "uwr ");
+}
+
+#[test]
+#[cfg(feature = "autocorrect")]
+fn restore_hints_decide_before_the_checks() {
+    use gonhanh_core::engine::restore::RestoreHint;
+    struct Hint;
+    impl RestoreHint for Hint {
+        fn restore(&self, raw: &str, _word: &str) -> Option<f32> {
+            match raw {
+                "text" => Some(0.0),
+                "vieejt" => Some(1.0),
+                _ => None,
+            }
+        }
+    }
+    let mut e = Engine::new();
+    assert_eq!(type_word(&mut e, "text "), "text ");
+    e.add_restore_hint(Hint);
+    assert_eq!(type_word(&mut e, "text "), "tẽt ");
+    assert_eq!(type_word(&mut e, "vieejt "), "vieejt ");
+    assert_eq!(type_word(&mut e, "vieetj "), "việt ");
+}
+
+#[test]
+#[cfg(feature = "autocorrect")]
+fn min_confidence_holds_back_unsure_changes() {
+    let mut e = Engine::new();
+    e.set_correction_packs(CorrectionPacks::NONE.with(Dialect::Southern, true));
+    assert_eq!(type_word(&mut e, "namf "), "làm ");
+    assert_eq!(type_word(&mut e, "text "), "text ");
+    // Corrections are less sure than restores
+    e.set_min_confidence(60);
+    assert_eq!(type_word(&mut e, "namf "), "nàm ");
+    assert_eq!(type_word(&mut e, "text "), "text ");
+    e.set_min_confidence(100);
+    assert_eq!(type_word(&mut e, "text "), "tẽt ");
+}
+
+#[test]
+#[cfg(feature = "autocorrect")]
+fn shadow_heuristics_never_change_output() {
+    use gonhanh_core::engine::decision::{Decision, DecisionAction, Source};
+    use gonhanh_core::engine::history::Privacy;
+    use gonhanh_core::engine::shadow::ShadowWord;
+    let mut e = Engine::new();
+    e.set_privacy(Privacy::KEEP);
+    // Would keep every word as shown
+    e.add_shadow(("keep-all", |_: &ShadowWord| {
+        Some(Decision::new(DecisionAction::Keep, 1.0, Source::Hint))
+    }));
+    assert_eq!(type_word(&mut e, "text "), "text ");
+    assert_eq!(type_word(&mut e, "vieetj "), "việt ");
+    let stats = e.shadow_log().stats("keep-all").unwrap();
+    assert_eq!((stats.words, stats.disagreements), (2, 1));
+    let d = e.shadow_log().disagreements().next().unwrap();
+    assert_eq!(
+        (d.keys.as_str(), &d.active),
+        ("text", &DecisionAction::Restore)
+    );
+
+    e.set_privacy(Privacy::OFF);
+    assert_eq!(e.shadow_log().disagreements().count(), 0);
+    assert_eq!(type_word(&mut e, "text "), "text ");
+    assert_eq!(e.shadow_log().disagreements().count(), 0);
+    assert_eq!(e.shadow_log().stats("keep-all").unwrap().disagreements, 2);
+}
+
+#[test]
+#[cfg(feature = "macros")]
 fn shortcut_tphcm_expands() {
     let mut e = Engine::new();
 
@@ -761,6 +3183,7 @@ fn shortcut_tphcm_expands() {
 }
 
 #[test]
+#[cfg(feature = "macros")]
 fn shortcut_tphcm_raw_result() {
     let mut e = Engine::new();
 
@@ -802,6 +3225,7 @@ fn shortcut_does_not_trigger_without_space() {
 }
 
 #[test]
+#[cfg(feature = "macros")]
 fn shortcut_multiple_shortcuts() {
     let mut e = Engine::new();
 
@@ -833,6 +3257,7 @@ fn shortcut_case_sensitive_no_match() {
 }
 
 #[test]
+#[cfg(feature = "macros")]
 fn shortcut_case_sensitive_exact_match() {
     let mut e = Engine::new();
 
@@ -897,6 +3322,7 @@ fn shortcut_clear_all() {
 }
 
 #[test]
+#[cfg(feature = "macros")]
 fn shortcut_with_vietnamese_output() {
     let mut e = Engine::new();
 
@@ -908,10 +3334,11 @@ fn shortcut_with_vietnamese_output() {
 }
 
 #[test]
+#[cfg(feature = "macros")]
 fn shortcut_vni_mode() {
     // Shortcuts should work in VNI mode too
     let mut e = Engine::new();
-    e.set_method(1); // VNI
+    e.set_method(1).unwrap(); // VNI
 
     e.shortcuts_mut().add(Shortcut::new("vn", "Việt Nam"));
 
@@ -1,7 +1,10 @@
 mod common;
-use common::{telex, vni};
+#[cfg(feature = "autocorrect")]
+use common::telex;
+use common::vni;
 
 #[test]
+#[cfg(feature = "autocorrect")]
 fn paragraph_telex() {
     // Telex patterns from typing_test.rs:
     // - â = aa, ê = ee, ô = oo
@@ -0,0 +1,227 @@
+//! Soak Tests - memory must not grow with the amount typed
+//!
+//! Types pseudo-random Vietnamese and English words (with edits, restores,
+//! caret jumps and re-seeding) and tracks live heap bytes on this thread.
+//! After a warm-up, typing ten times as much again must not grow the heap:
+//! anything the engine remembers across words has to be capped.
+//!
+//! The default run is short. The long one is ignored; run it in release:
+//! `SOAK_WORDS=5000000 cargo test --release --test soak_test -- --ignored`
+
+use gonhanh_core::config::Method;
+use gonhanh_core::data::keys;
+use gonhanh_core::engine::output::{Delivery, OutputProfile};
+use gonhanh_core::engine::Engine;
+use gonhanh_core::input::to_keystrokes;
+use gonhanh_core::utils::char_to_key;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+struct Counting;
+
+thread_local! {
+    static LIVE: Cell<isize> = const { Cell::new(0) };
+}
+
+fn track(delta: isize) {
+    let _ = LIVE.try_with(|n| n.set(n.get() + delta));
+}
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        track(layout.size() as isize);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        track(-(layout.size() as isize));
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        track(new_size as isize - layout.size() as isize);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+fn live_bytes() -> isize {
+    LIVE.with(Cell::get)
+}
+
+/// Heap growth allowed between the two measurements
+const SLACK: isize = 16 * 1024;
+
+const VIETNAMESE: &[&str] = &[
+    "việt",
+    "được",
+    "người",
+    "khuyến",
+    "hoà",
+    "giường",
+    "nghiêng",
+    "tiếng",
+    "Nam",
+    "quốc",
+    "ăn",
+    "đường",
+    "thuỷ",
+    "những",
+    "trưởng",
+    "Nguyễn",
+    "ĐỒNG",
+    "xoong",
+    "chuyện",
+    "ươ",
+];
+
+const ENGLISH: &[&str] = &[
+    "text", "expect", "Google", "window", "mix", "world", "string", "fix", "router", "class",
+];
+
+/// xorshift64: deterministic, no dependencies
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn pick<'a>(&mut self, items: &[&'a str]) -> &'a str {
+        items[self.below(items.len())]
+    }
+}
+
+struct Typist {
+    engine: Engine,
+    rng: Rng,
+    method: Method,
+    caret: usize,
+}
+
+impl Typist {
+    fn new(seed: u64) -> Self {
+        let mut engine = Engine::new();
+        engine.set_caret(Some(0));
+        Self {
+            engine,
+            rng: Rng(seed),
+            method: Method::Telex,
+            caret: 0,
+        }
+    }
+
+    fn press(&mut self, key: u16, caps: bool) {
+        self.engine.on_key(key, caps, false);
+        let _ = self.engine.word_info();
+    }
+
+    fn type_keys(&mut self, keystrokes: &str) {
+        for c in keystrokes.chars() {
+            self.press(char_to_key(c), c.is_uppercase());
+        }
+    }
+
+    /// One word and whatever follows it
+    fn word(&mut self) {
+        let word = match self.rng.below(10) {
+            0..=5 => {
+                let word = self.rng.pick(VIETNAMESE);
+                to_keystrokes(word, self.method).unwrap()
+            }
+            6..=8 => self.rng.pick(ENGLISH).to_string(),
+            _ => (0..1 + self.rng.below(12))
+                .map(|_| (b'a' + self.rng.below(26) as u8) as char)
+                .collect(),
+        };
+        self.type_keys(&word);
+
+        match self.rng.below(20) {
+            0 => self.type_keys("<<"),
+            1 => self.press(keys::ESC, false),
+            2 => {
+                self.caret += self.rng.below(50);
+                self.engine.caret_moved(Some(self.caret));
+            }
+            3 => {
+                self.engine.text_context("xin chào việt");
+                self.type_keys("j");
+            }
+            4 => self.engine.focus_changed(),
+            5 => {
+                let profile = match self.rng.below(3) {
+                    0 => OutputProfile::SLOW,
+                    1 => OutputProfile::BOUNDARY,
+                    _ => OutputProfile {
+                        delivery: Delivery::Select,
+                        ..OutputProfile::default()
+                    },
+                };
+                self.engine.set_output_profile(profile);
+            }
+            6 => {
+                self.method = match self.method {
+                    Method::Telex => Method::Vni,
+                    Method::Vni => Method::Telex,
+                };
+                self.engine.set_method(self.method.id()).unwrap();
+            }
+            _ => {}
+        }
+        if self.engine.caret().is_none() {
+            self.engine.set_caret(Some(self.caret));
+        }
+        let boundary = [
+            keys::SPACE,
+            keys::SPACE,
+            keys::SPACE,
+            keys::DOT,
+            keys::RETURN,
+        ];
+        let key = boundary[self.rng.below(boundary.len())];
+        self.press(key, false);
+    }
+}
+
+fn soak(words: usize) {
+    let mut typist = Typist::new(0x5eed_1e55);
+    let warm_up = words / 10;
+    for _ in 0..warm_up {
+        typist.word();
+    }
+    let before = live_bytes();
+    for _ in warm_up..words {
+        typist.word();
+    }
+    let grown = live_bytes() - before;
+    assert!(
+        grown <= SLACK,
+        "heap grew {} bytes over {} words",
+        grown,
+        words - warm_up
+    );
+}
+
+#[test]
+fn typing_keeps_memory_bounded() {
+    soak(50_000);
+}
+
+#[test]
+#[ignore = "long: run in release with --ignored"]
+fn typing_millions_of_words_keeps_memory_bounded() {
+    let words = std::env::var("SOAK_WORDS")
+        .ok()
+        .and_then(|n| n.parse().ok())
+        .unwrap_or(2_000_000);
+    soak(words);
+}
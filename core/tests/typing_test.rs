@@ -617,6 +617,43 @@ const TELEX_SWITCH_DIACRITICS: &[(&str, &str)] = &[
     ("uoow", "ươ"), // uô + w → ươ (switch to horn)
 ];
 
+// ============================================================
+// REPLACING AND REMOVING MARKS - z (Telex) / 0 (VNI)
+// ============================================================
+
+const TELEX_REPLACE_REMOVE: &[(&str, &str)] = &[
+    // Another mark key replaces the mark, wherever the word is
+    ("mays", "máy"),
+    ("maysf", "mày"),
+    ("maysfr", "mảy"),
+    ("vieetsj", "việt"),
+    ("nguwowisf", "người"),
+    // z strips one layer per press: mark, then tone
+    ("maysz", "may"),
+    ("maysfz", "may"),
+    ("maaysz", "mây"),
+    ("maayszz", "may"),
+    ("vieetjzz", "viet"),
+    ("nguwowifz", "ngươi"),
+    // Nothing left to strip: z types itself
+    ("maz", "maz"),
+    ("vieetjzzz", "vietz"),
+    ("VIEETJZ", "VIÊT"),
+];
+
+const VNI_REPLACE_REMOVE: &[(&str, &str)] = &[
+    ("may1", "máy"),
+    ("may12", "mày"),
+    ("may123", "mảy"),
+    ("vie6t15", "việt"),
+    ("may10", "may"),
+    ("may120", "may"),
+    ("ma6y10", "mây"),
+    ("ma6y100", "may"),
+    ("ngu7o7i20", "ngươi"),
+    ("ma0", "ma0"),
+];
+
 // ============================================================
 // TEST FUNCTIONS
 // ============================================================
@@ -775,3 +812,13 @@ fn telex_switch_diacritics() {
 fn vni_switch_diacritics() {
     vni(VNI_SWITCH_DIACRITICS);
 }
+
+#[test]
+fn telex_replace_and_remove() {
+    telex(TELEX_REPLACE_REMOVE);
+}
+
+#[test]
+fn vni_replace_and_remove() {
+    vni(VNI_REPLACE_REMOVE);
+}
@@ -12,7 +12,7 @@
 //     pub action: u8,
 //     pub backspace: u8,
 //     pub count: u8,
-//     pub _pad: u8,
+//     pub caret_back: u8,
 // }
 //
 // Note: Rust #[repr(C)] uses C ABI layout, which matches C++ struct layout
@@ -23,7 +23,7 @@ struct ImeResult {
     uint8_t action;      // 1 byte
     uint8_t backspace;   // 1 byte
     uint8_t count;       // 1 byte
-    uint8_t _pad;        // 1 byte (explicit padding to 4-byte boundary)
+    uint8_t caret_back;  // 1 byte: move the caret left after inserting
 };
 
 // Verify struct size matches Rust at compile time
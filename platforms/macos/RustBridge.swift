@@ -200,7 +200,7 @@ private struct ImeResult {
     var action: UInt8
     var backspace: UInt8
     var count: UInt8
-    var _pad: UInt8
+    var caret_back: UInt8
 }
 
 @_silgen_name("ime_init") private func ime_init()
@@ -209,6 +209,8 @@ private struct ImeResult {
 @_silgen_name("ime_enabled") private func ime_enabled(_ enabled: Bool)
 @_silgen_name("ime_clear") private func ime_clear()
 @_silgen_name("ime_free") private func ime_free(_ result: UnsafeMutablePointer<ImeResult>?)
+@_silgen_name("ime_focus_changed") private func ime_focus_changed()
+@_silgen_name("ime_set_app") private func ime_set_app(_ appId: UnsafePointer<CChar>?)
 
 // Shortcut FFI
 @_silgen_name("ime_add_shortcut") private func ime_add_shortcut(_ trigger: UnsafePointer<CChar>?, _ replacement: UnsafePointer<CChar>?)
@@ -254,6 +256,14 @@ class RustBridge {
 
     static func clearBuffer() { ime_clear() }
 
+    /// Focus moved to another app or field: end the word, forget the caret
+    static func focusChanged() { ime_focus_changed() }
+
+    /// Pick the output profile for the focused app (slow apps get fewer edits)
+    static func setApp(_ bundleId: String) {
+        bundleId.withCString { ime_set_app($0) }
+    }
+
     // MARK: - Shortcuts
 
     /// Add a shortcut to the engine
@@ -601,7 +611,8 @@ class PerAppModeManager {
         guard bundleId != currentBundleId else { return }
         currentBundleId = bundleId
 
-        RustBridge.clearBuffer()
+        RustBridge.focusChanged()
+        RustBridge.setApp(bundleId)
 
         guard AppState.shared.isSmartModeEnabled else { return }
 
@@ -0,0 +1,29 @@
+// Build the native libraries first: platforms/mobile/scripts/build-android.sh
+plugins {
+    id("com.android.library") version "8.2.2"
+    kotlin("android") version "1.9.22"
+}
+
+android {
+    namespace = "org.gonhanh"
+    compileSdk = 34
+
+    defaultConfig {
+        minSdk = 24
+    }
+
+    sourceSets["main"].jniLibs.srcDirs("src/main/jniLibs")
+
+    compileOptions {
+        sourceCompatibility = JavaVersion.VERSION_17
+        targetCompatibility = JavaVersion.VERSION_17
+    }
+
+    kotlinOptions {
+        jvmTarget = "17"
+    }
+}
+
+dependencies {
+    implementation("net.java.dev.jna:jna:5.14.0@aar")
+}
@@ -0,0 +1,145 @@
+package org.gonhanh
+
+import com.sun.jna.Library
+import com.sun.jna.Native
+import com.sun.jna.Pointer
+import com.sun.jna.Structure
+import org.json.JSONArray
+
+enum class InputMethod(val id: Byte) { TELEX(0), VNI(1) }
+
+/** Text edit to apply before the cursor: delete [backspace] characters, then insert [text] */
+data class Edit(val backspace: Int, val text: String)
+
+class EngineException(val code: Int, message: String) : Exception(message)
+
+/**
+ * Kotlin face of the Rust core for an InputMethodService.
+ *
+ * Feed every character the user taps through [type] and apply the returned
+ * edit with `InputConnection` (`deleteSurroundingText(backspace, 0)`, then
+ * `commitText`). When it returns null, commit the character yourself.
+ */
+object GoNhanh {
+    /** Must match core/src/engine/mod.rs `Result` (64 = buffer::MAX) */
+    @Structure.FieldOrder("chars", "action", "backspace", "count", "caretBack")
+    class ImeResult(p: Pointer) : Structure(p) {
+        @JvmField var chars = IntArray(64)
+        @JvmField var action: Byte = 0
+        @JvmField var backspace: Byte = 0
+        @JvmField var count: Byte = 0
+        @JvmField var caretBack: Byte = 0
+
+        init { read() }
+    }
+
+    @Suppress("FunctionName")
+    private interface Core : Library {
+        fun ime_init()
+        fun ime_method(method: Byte)
+        fun ime_enabled(enabled: Boolean)
+        fun ime_clear()
+        fun ime_focus_changed()
+        fun ime_key_ext(key: Short, caps: Boolean, ctrl: Boolean, shift: Boolean): Pointer?
+        fun ime_text(codepoint: Int): Pointer?
+        fun ime_free(r: Pointer?)
+        fun ime_text_context(prefix: String): Int
+        fun ime_load_config(toml: String): Int
+        fun ime_config(): Pointer?
+        fun ime_set_storage_dir(dir: String): Int
+        fun ime_save(): Int
+        fun ime_load(): Int
+        fun ime_long_press(label: Int): Pointer?
+        fun ime_suggest_similar(word: String, maxDistance: Byte): Pointer?
+        fun ime_last_error(): Pointer?
+        fun ime_free_string(s: Pointer?)
+    }
+
+    private const val KEY_DELETE: Short = 51
+
+    private val core: Core by lazy { Native.load("gonhanh_core", Core::class.java) }
+
+    @Volatile private var initialized = false
+
+    @Synchronized
+    fun initialize() {
+        if (initialized) return
+        core.ime_init()
+        initialized = true
+    }
+
+    fun setMethod(method: InputMethod) = core.ime_method(method.id)
+
+    fun setEnabled(enabled: Boolean) = core.ime_enabled(enabled)
+
+    /** A character tapped on the keyboard (letters, digits, space, symbols) */
+    fun type(codepoint: Int): Edit? = edit(core.ime_text(codepoint))
+
+    /** The backspace key: the engine may undo a transform instead */
+    fun backspace(): Edit? = edit(core.ime_key_ext(KEY_DELETE, false, false, false))
+
+    /** End the current word (enter key, field switch) */
+    fun clear() = core.ime_clear()
+
+    /** The user moved the cursor or the editor changed */
+    fun focusChanged() = core.ime_focus_changed()
+
+    /** Continue the word before the cursor (`getTextBeforeCursor`) */
+    fun resume(prefix: String): Int = core.ime_text_context(prefix)
+
+    // Settings
+
+    fun loadConfig(toml: String) = check(core.ime_load_config(toml))
+
+    fun config(): String? = take(core.ime_config())
+
+    /** Keep settings and shortcuts in [dir] (`Context.filesDir`) */
+    fun setStorage(dir: String) = check(core.ime_set_storage_dir(dir))
+
+    fun save() = check(core.ime_save())
+
+    fun load() = check(core.ime_load())
+
+    // Keyboard UI
+
+    /** Accent popup for a key: 'a' → ["a", "á", "à", ...] */
+    fun longPress(label: Char): List<String> {
+        val popup = take(core.ime_long_press(label.code)) ?: return emptyList()
+        return popup.codePoints().toArray().map { String(Character.toChars(it)) }
+    }
+
+    /** Words close to [word], best first */
+    fun suggestions(word: String, maxDistance: Int = 2): List<String> {
+        val json = take(core.ime_suggest_similar(word, maxDistance.toByte())) ?: return emptyList()
+        val items = JSONArray(json)
+        return List(items.length()) { items.getJSONObject(it).getString("word") }
+    }
+
+    // Helpers
+
+    private fun edit(ptr: Pointer?): Edit? {
+        ptr ?: return null
+        try {
+            val r = ImeResult(ptr)
+            if (r.action.toInt() == 0) return null
+            val count = r.count.toInt() and 0xFF
+            val text = String(r.chars, 0, count)
+            return Edit(r.backspace.toInt() and 0xFF, text)
+        } finally {
+            core.ime_free(ptr)
+        }
+    }
+
+    private fun take(ptr: Pointer?): String? {
+        ptr ?: return null
+        try {
+            return ptr.getString(0, "UTF-8")
+        } finally {
+            core.ime_free_string(ptr)
+        }
+    }
+
+    private fun check(code: Int) {
+        if (code != 0) throw EngineException(code, take(core.ime_last_error()) ?: "")
+    }
+}
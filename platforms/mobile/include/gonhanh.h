@@ -0,0 +1,56 @@
+#ifndef GONHANH_H
+#define GONHANH_H
+
+// C interface of the Rust core used by the iOS and Android packages
+// (from core/src/lib.rs). Strings are UTF-8; free returned strings with
+// ime_free_string and results with ime_free.
+
+#include <stdbool.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+// Must match core/src/engine/mod.rs `Result` (64 = buffer::MAX)
+typedef struct {
+    uint32_t chars[64];
+    uint8_t action;     // 0=None (pass through), 1=Send (replace), 2=Restore
+    uint8_t backspace;  // characters to delete before inserting chars
+    uint8_t count;      // valid entries in chars
+    uint8_t caret_back; // move the caret left this many characters after
+} ImeResult;
+
+// Error codes
+#define IME_ERR_INVALID_ARG (-1)
+#define IME_ERR_PANIC (-2)
+
+void ime_init(void);
+void ime_method(uint8_t method);
+void ime_enabled(bool enabled);
+void ime_clear(void);
+void ime_focus_changed(void);
+void ime_caret_moved(int64_t offset);
+
+ImeResult *ime_key_ext(uint16_t key, bool caps, bool ctrl, bool shift);
+ImeResult *ime_text(uint32_t codepoint);
+void ime_free(ImeResult *r);
+int32_t ime_text_context(const char *prefix);
+
+int32_t ime_load_config(const char *toml);
+char *ime_config(void);
+int32_t ime_set_storage_dir(const char *dir);
+int32_t ime_save(void);
+int32_t ime_load(void);
+
+char *ime_long_press(uint32_t label);
+char *ime_suggest_similar(const char *word, uint8_t max_distance);
+
+char *ime_last_error(void);
+void ime_free_string(char *s);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif // GONHANH_H
@@ -0,0 +1,15 @@
+// swift-tools-version:5.7
+import PackageDescription
+
+// Build the XCFramework first: platforms/mobile/scripts/build-ios.sh
+let package = Package(
+    name: "GoNhanh",
+    platforms: [.iOS(.v13)],
+    products: [
+        .library(name: "GoNhanh", targets: ["GoNhanh"]),
+    ],
+    targets: [
+        .binaryTarget(name: "GoNhanhCore", path: "GoNhanhCore.xcframework"),
+        .target(name: "GoNhanh", dependencies: ["GoNhanhCore"]),
+    ]
+)
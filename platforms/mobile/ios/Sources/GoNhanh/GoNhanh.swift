@@ -0,0 +1,139 @@
+import Foundation
+import GoNhanhCore
+
+// MARK: - Types
+
+public enum InputMethod: UInt8 {
+    case telex = 0
+    case vni = 1
+}
+
+/// Text edit to apply before the caret: delete `backspace` characters, then
+/// insert `text`
+public struct Edit: Equatable {
+    public let backspace: Int
+    public let text: String
+}
+
+public struct EngineError: Error {
+    public let code: Int32
+    public let message: String
+}
+
+// MARK: - GoNhanh (Public API)
+
+/// Swift face of the Rust core for keyboard extensions
+///
+/// Feed every character the user taps through `type(_:)` and apply the
+/// returned edit with `textDocumentProxy` (`deleteBackward` × backspace,
+/// then `insertText`). When it returns nil, insert the character yourself.
+public enum GoNhanh {
+    private static var isInitialized = false
+
+    public static func initialize() {
+        guard !isInitialized else { return }
+        ime_init()
+        isInitialized = true
+    }
+
+    public static func setMethod(_ method: InputMethod) { ime_method(method.rawValue) }
+
+    public static func setEnabled(_ enabled: Bool) { ime_enabled(enabled) }
+
+    /// A character tapped on the keyboard (letters, digits, space, symbols)
+    public static func type(_ character: Character) -> Edit? {
+        guard let scalar = character.unicodeScalars.first, character.unicodeScalars.count == 1 else {
+            ime_clear()
+            return nil
+        }
+        return edit(ime_text(scalar.value))
+    }
+
+    /// The backspace key: the engine may undo a transform instead
+    public static func backspace() -> Edit? {
+        edit(ime_key_ext(KeyCode.delete, false, false, false))
+    }
+
+    /// End the current word (return key, field switch)
+    public static func clear() { ime_clear() }
+
+    /// The user moved the caret or the host switched fields
+    public static func focusChanged() { ime_focus_changed() }
+
+    /// Continue the word before the caret (`documentContextBeforeInput`)
+    @discardableResult
+    public static func resume(before prefix: String) -> Int {
+        Int(prefix.withCString { ime_text_context($0) })
+    }
+
+    // MARK: - Settings
+
+    public static func loadConfig(_ toml: String) throws {
+        let code = toml.withCString { ime_load_config($0) }
+        if code != 0 { throw lastError(code) }
+    }
+
+    public static func config() -> String? { take(ime_config()) }
+
+    /// Keep settings and shortcuts in `directory` (an app group container)
+    public static func setStorage(directory: URL) throws {
+        let code = directory.path.withCString { ime_set_storage_dir($0) }
+        if code != 0 { throw lastError(code) }
+    }
+
+    public static func save() throws {
+        let code = ime_save()
+        if code != 0 { throw lastError(code) }
+    }
+
+    public static func load() throws {
+        let code = ime_load()
+        if code != 0 { throw lastError(code) }
+    }
+
+    // MARK: - Keyboard UI
+
+    /// Accent popup for a key: "a" → ["a", "á", "à", ...]
+    public static func longPress(_ label: Character) -> [Character] {
+        guard let scalar = label.unicodeScalars.first else { return [] }
+        return Array(take(ime_long_press(scalar.value)) ?? "")
+    }
+
+    /// Words close to `word`, best first
+    public static func suggestions(for word: String, maxDistance: UInt8 = 2) -> [String] {
+        guard let json = word.withCString({ take(ime_suggest_similar($0, maxDistance)) }),
+              let data = json.data(using: .utf8),
+              let items = try? JSONSerialization.jsonObject(with: data) as? [[String: Any]]
+        else { return [] }
+        return items.compactMap { $0["word"] as? String }
+    }
+
+    // MARK: - Helpers
+
+    private enum KeyCode {
+        static let delete: UInt16 = 51
+    }
+
+    private static func edit(_ ptr: UnsafeMutablePointer<ImeResult>?) -> Edit? {
+        guard let ptr = ptr else { return nil }
+        defer { ime_free(ptr) }
+        let r = ptr.pointee
+        guard r.action != 0 else { return nil }
+        let text = withUnsafeBytes(of: r.chars) { raw in
+            raw.bindMemory(to: UInt32.self).prefix(Int(r.count))
+                .compactMap(Unicode.Scalar.init)
+                .map(Character.init)
+        }
+        return Edit(backspace: Int(r.backspace), text: String(text))
+    }
+
+    private static func take(_ ptr: UnsafeMutablePointer<CChar>?) -> String? {
+        guard let ptr = ptr else { return nil }
+        defer { ime_free_string(ptr) }
+        return String(cString: ptr)
+    }
+
+    private static func lastError(_ code: Int32) -> EngineError {
+        EngineError(code: code, message: take(ime_last_error()) ?? "")
+    }
+}
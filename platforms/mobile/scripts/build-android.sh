@@ -0,0 +1,37 @@
+#!/bin/bash
+# Build the Android library (AAR)
+# Usage: ./scripts/build-android.sh
+# Requires: Android NDK (ANDROID_NDK_HOME), cargo-ndk, Gradle, rustup targets
+#   aarch64-linux-android armv7-linux-androideabi x86_64-linux-android
+
+set -e
+
+if [ -f "$HOME/.cargo/env" ]; then
+    source "$HOME/.cargo/env"
+fi
+
+SCRIPT_DIR="$(cd "$(dirname "${BASH_SOURCE[0]}")" && pwd)"
+MOBILE_DIR="$(dirname "$SCRIPT_DIR")"
+ROOT_DIR="$(dirname "$(dirname "$MOBILE_DIR")")"
+CORE_DIR="$ROOT_DIR/core"
+ANDROID_DIR="$MOBILE_DIR/android"
+
+if ! command -v cargo-ndk &> /dev/null; then
+    echo "cargo-ndk not found: cargo install cargo-ndk"
+    exit 1
+fi
+
+echo "🦀 Building Rust core for Android..."
+cd "$CORE_DIR"
+cargo ndk \
+    -t arm64-v8a -t armeabi-v7a -t x86_64 \
+    --platform 24 \
+    -o "$ANDROID_DIR/src/main/jniLibs" \
+    build --release --lib
+
+echo "Building AAR..."
+cd "$ANDROID_DIR"
+gradle assembleRelease
+
+echo "✅ Android library built successfully!"
+echo "📦 Output: platforms/mobile/android/build/outputs/aar/"
@@ -0,0 +1,42 @@
+#!/bin/bash
+# Build GoNhanhCore.xcframework for the Swift package
+# Usage: ./scripts/build-ios.sh
+# Requires: Xcode, rustup targets aarch64-apple-ios aarch64-apple-ios-sim x86_64-apple-ios
+
+set -e
+
+if [ -f "$HOME/.cargo/env" ]; then
+    source "$HOME/.cargo/env"
+fi
+
+SCRIPT_DIR="$(cd "$(dirname "${BASH_SOURCE[0]}")" && pwd)"
+MOBILE_DIR="$(dirname "$SCRIPT_DIR")"
+ROOT_DIR="$(dirname "$(dirname "$MOBILE_DIR")")"
+CORE_DIR="$ROOT_DIR/core"
+OUT="$MOBILE_DIR/ios/GoNhanhCore.xcframework"
+
+echo "🦀 Building Rust core for iOS..."
+cd "$CORE_DIR"
+
+for target in aarch64-apple-ios aarch64-apple-ios-sim x86_64-apple-ios; do
+    echo "Building for $target..."
+    cargo build --release --lib --target "$target"
+done
+
+# Simulator slices share one library
+echo "Creating simulator universal library..."
+mkdir -p target/ios-sim/release
+lipo -create \
+    target/aarch64-apple-ios-sim/release/libgonhanh_core.a \
+    target/x86_64-apple-ios/release/libgonhanh_core.a \
+    -output target/ios-sim/release/libgonhanh_core.a
+
+echo "Creating XCFramework..."
+rm -rf "$OUT"
+xcodebuild -create-xcframework \
+    -library target/aarch64-apple-ios/release/libgonhanh_core.a -headers "$MOBILE_DIR/include" \
+    -library target/ios-sim/release/libgonhanh_core.a -headers "$MOBILE_DIR/include" \
+    -output "$OUT"
+
+echo "✅ iOS core built successfully!"
+echo "📦 Output: platforms/mobile/ios/GoNhanhCore.xcframework"
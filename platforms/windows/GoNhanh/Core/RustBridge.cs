@@ -128,12 +128,12 @@ public enum ImeAction : byte
 [StructLayout(LayoutKind.Sequential)]
 internal struct NativeResult
 {
-    [MarshalAs(UnmanagedType.ByValArray, SizeConst = 32)]
+    [MarshalAs(UnmanagedType.ByValArray, SizeConst = 64)]
     public uint[] chars;
     public byte action;
     public byte backspace;
     public byte count;
-    public byte _pad;
+    public byte caret_back;
 }
 
 /// <summary>
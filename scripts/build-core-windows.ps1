@@ -4,7 +4,9 @@
 param(
     [switch]$Release = $true,
     [switch]$x86 = $false,
-    [switch]$ARM64 = $false
+    [switch]$ARM64 = $false,
+    # Keyboard-hook DLL: no autocorrect/macros/conversion/suggestions
+    [switch]$Slim = $false
 )
 
 $ErrorActionPreference = "Stop"
@@ -25,12 +27,13 @@ if (-not (Test-Path $outputDir)) {
 # Build configuration
 $buildType = if ($Release) { "release" } else { "debug" }
 $buildFlag = if ($Release) { "--release" } else { "" }
+$featureFlags = if ($Slim) { @("--no-default-features") } else { @() }
 
 # Primary target: x64
 Write-Host "`nBuilding for x86_64-pc-windows-msvc..." -ForegroundColor Yellow
 Push-Location $coreDir
 try {
-    cargo build $buildFlag --target x86_64-pc-windows-msvc
+    cargo build $buildFlag @featureFlags --target x86_64-pc-windows-msvc
 
     $srcDll = Join-Path $coreDir "target/x86_64-pc-windows-msvc/$buildType/gonhanh_core.dll"
     $dstDll = Join-Path $outputDir "gonhanh_core.dll"
@@ -54,7 +57,7 @@ if ($x86) {
     Write-Host "`nBuilding for i686-pc-windows-msvc..." -ForegroundColor Yellow
     Push-Location $coreDir
     try {
-        cargo build $buildFlag --target i686-pc-windows-msvc
+        cargo build $buildFlag @featureFlags --target i686-pc-windows-msvc
 
         $srcDll = Join-Path $coreDir "target/i686-pc-windows-msvc/$buildType/gonhanh_core.dll"
         $dstDll = Join-Path $outputDir "gonhanh_core_x86.dll"
@@ -73,7 +76,7 @@ if ($ARM64) {
     Write-Host "`nBuilding for aarch64-pc-windows-msvc..." -ForegroundColor Yellow
     Push-Location $coreDir
     try {
-        cargo build $buildFlag --target aarch64-pc-windows-msvc
+        cargo build $buildFlag @featureFlags --target aarch64-pc-windows-msvc
 
         $srcDll = Join-Path $coreDir "target/aarch64-pc-windows-msvc/$buildType/gonhanh_core.dll"
         $dstDll = Join-Path $outputDir "gonhanh_core_arm64.dll"
@@ -0,0 +1,162 @@
+#!/usr/bin/env python3
+"""Generate core/data/src/bigram.rs, the English-vs-Vietnamese letter
+bigram model used by auto-restore.
+
+Training data:
+  English     core/data/model/english.txt (common words)
+  Vietnamese  core/data/lexicon/vietnamese.txt, as Telex keystrokes (tone
+              key after the vowels and at the end of the word), and the
+              Telex corpora in core/bench/corpus/{prose,chat}.txt
+
+Usage: python3 scripts/gen-bigram-model.py
+"""
+
+import math
+import os
+import unicodedata
+
+ROOT = os.path.join(os.path.dirname(os.path.abspath(__file__)), "..", "core")
+OUT = os.path.join(ROOT, "data", "src", "bigram.rs")
+
+EDGE = 26  # word boundary
+SMOOTHING = 0.5
+TONE_KEYS = {"́": "s", "̀": "f", "̉": "r", "̃": "x", "̣": "j"}
+VOWEL_KEYS = {"̂": None, "̆": "w", "̛": "w"}  # None: double the vowel
+
+
+def telex(word):
+    """Telex keystrokes for a Vietnamese word: (tone after vowels, tone last)"""
+    letters, tone = [], ""
+    for ch in word:
+        base, *marks = unicodedata.normalize("NFD", ch)
+        if base == "đ":
+            letters.append("dd")
+            continue
+        keys = base
+        for m in marks:
+            if m in TONE_KEYS:
+                tone = TONE_KEYS[m]
+            elif m in VOWEL_KEYS:
+                keys += VOWEL_KEYS[m] or base
+        letters.append(keys)
+    plain = "".join(letters)
+    last_vowel = max((i for i, l in enumerate(letters) if l[0] in "aeiouy"), default=len(letters) - 1)
+    after_vowels = "".join(letters[: last_vowel + 1]) + tone + "".join(letters[last_vowel + 1 :])
+    return [after_vowels, plain + tone]
+
+
+def typed_words(path):
+    """Words of a Telex corpus, with ⌫ applied"""
+    for line in open(path, encoding="utf-8"):
+        if line.startswith("#"):
+            continue
+        text = []
+        for ch in line:
+            if ch == "⌫":
+                if text:
+                    text.pop()
+            else:
+                text.append(ch)
+        word = ""
+        for ch in "".join(text).lower() + " ":
+            if "a" <= ch <= "z":
+                word += ch
+            elif word:
+                yield word
+                word = ""
+
+
+def counts(words):
+    table = [[0.0] * 27 for _ in range(27)]
+    for w in words:
+        seq = [EDGE] + [ord(c) - ord("a") for c in w] + [EDGE]
+        for a, b in zip(seq, seq[1:]):
+            table[a][b] += 1
+    return table
+
+
+def conditional(table):
+    probs = []
+    for row in table:
+        total = sum(row) + SMOOTHING * 27
+        probs.append([(n + SMOOTHING) / total for n in row])
+    return probs
+
+
+def main():
+    english = [w for w in open(os.path.join(ROOT, "data", "model", "english.txt")).read().split()]
+    vietnamese = []
+    for w in open(os.path.join(ROOT, "data", "lexicon", "vietnamese.txt"), encoding="utf-8").read().split():
+        vietnamese += telex(w)
+    for name in ("prose.txt", "chat.txt"):
+        vietnamese += typed_words(os.path.join(ROOT, "bench", "corpus", name))
+
+    en, vi = conditional(counts(english)), conditional(counts(vietnamese))
+    rows = []
+    for a in range(27):
+        scores = [max(-127, min(127, round(10 * math.log2(en[a][b] / vi[a][b])))) for b in range(27)]
+        rows.append("    [" + ", ".join(str(s) for s in scores) + "],")
+
+    with open(OUT, "w", encoding="utf-8") as f:
+        f.write(HEADER.format(en=len(english), vi=len(vietnamese)))
+        f.write("\n".join(rows))
+        f.write(FOOTER)
+
+
+HEADER = """//! Letter Bigram Model
+//!
+//! How much more English than Vietnamese (Telex keystrokes) each pair of
+//! letters is: `log2(P_english(b | a) / P_vietnamese(b | a))` in tenths of a
+//! bit, with `a`/`b` being `a`..`z` or the word boundary. 729 bytes.
+//!
+//! Generated by `scripts/gen-bigram-model.py` from {en} English words and
+//! {vi} Vietnamese ones; do not edit by hand.
+
+/// Row and column of the word boundary
+pub const EDGE: usize = 26;
+
+/// `SCORES[a][b]`, letters `a`..`z` as 0..=25
+#[rustfmt::skip]
+pub const SCORES: [[i8; 27]; 27] = [
+"""
+
+FOOTER = """
+];
+
+/// Average score per letter pair of a lowercase ASCII word, boundaries
+/// included (tenths of a bit; > 0 leans English). `None` for other input.
+pub fn score(word: &[u8]) -> Option<i32> {
+    let mut prev = EDGE;
+    let mut sum = 0i32;
+    for &c in word {
+        if !c.is_ascii_lowercase() {
+            return None;
+        }
+        let cur = (c - b'a') as usize;
+        sum += SCORES[prev][cur] as i32;
+        prev = cur;
+    }
+    sum += SCORES[prev][EDGE] as i32;
+    Some(sum / (word.len() as i32 + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_score() {
+        for word in ["through", "window", "question", "strength"] {
+            assert!(score(word.as_bytes()).unwrap() > 0, "{}", word);
+        }
+        for word in ["nguwowif", "truwowngf", "vieetj", "nhuwngx"] {
+            assert!(score(word.as_bytes()).unwrap() < 0, "{}", word);
+        }
+        assert_eq!(score(b"Ok"), None);
+        assert_eq!(score(b""), Some(SCORES[EDGE][EDGE] as i32));
+    }
+}
+"""
+
+if __name__ == "__main__":
+    main()